@@ -0,0 +1,491 @@
+use crate::cli::OutputFormat;
+use crate::history_engine::HistoryEntryJson;
+use crate::output::Output;
+use chrono::TimeZone;
+use log::debug;
+use sam_core::entities::identifiers::Identifier;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use thiserror::Error;
+
+// events are single json lines, longer lines end the connection.
+const MAX_EVENT_SIZE: usize = 1024 * 1024;
+// connections past this number are refused until others end.
+const MAX_CONNECTIONS: usize = 64;
+// a tcp connection that sends nothing for this long is closed and frees its slot.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// TOKEN_ENV_VAR holds the token the senders have to present before their events,
+/// it is required to listen on other addresses than the loopback.
+pub const TOKEN_ENV_VAR: &str = "SAM_AUDIT_TOKEN";
+
+/// AuditCommand runs the audit receiver, which appends the events sent by other sam
+/// instances to a local store and never runs anything, or queries the store.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuditCommand {
+    Serve {
+        listen: Listen,
+        store: Option<PathBuf>,
+    },
+    Query {
+        alias: Option<Identifier>,
+        source: Option<String>,
+        store: Option<PathBuf>,
+    },
+}
+
+/// Listen is where the audit receiver waits for events, a unix socket,
+/// the default one when it's not provided, or a tcp address, on the loopback
+/// when it's only a port.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Listen {
+    Socket(Option<PathBuf>),
+    Tcp(String),
+}
+
+pub struct AuditEngine {
+    pub store: PathBuf,
+    pub socket: PathBuf,
+    pub output: OutputFormat,
+    pub pager: bool,
+    pub token: Option<String>,
+}
+
+/// AuditEvent is an entry of the history, in the format of `sam history export`,
+/// along with when and from where it was received.
+#[derive(Debug, Serialize, Deserialize)]
+struct AuditEvent {
+    received_at: i64,
+    source: String,
+    #[serde(flatten)]
+    entry: HistoryEntryJson,
+}
+
+impl AuditEngine {
+    pub fn run(self, cmd: AuditCommand) -> Result<i32> {
+        match cmd {
+            AuditCommand::Serve { listen, store } => {
+                let store = store.unwrap_or_else(|| self.store.clone());
+                self.serve(listen, &store)
+            }
+            AuditCommand::Query {
+                alias,
+                source,
+                store,
+            } => {
+                let store = store.unwrap_or_else(|| self.store.clone());
+                self.query(&store, alias.as_ref(), source.as_deref())
+            }
+        }
+    }
+
+    fn serve(&self, listen: Listen, store: &Path) -> Result<i32> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(store)
+            .map_err(|err| ErrorAuditEngine::Store(store.to_path_buf(), err))?;
+        let receiver = Receiver {
+            store: Arc::new(Mutex::new(file)),
+            token: self.token.clone().map(Arc::new),
+            connections: Arc::new(AtomicUsize::new(0)),
+        };
+        match listen {
+            Listen::Tcp(address) => {
+                let address = tcp_address(&address);
+                if receiver.token.is_none() && !is_loopback(&address)? {
+                    return Err(ErrorAuditEngine::NoToken(address));
+                }
+                let listener = TcpListener::bind(&address)
+                    .map_err(|err| ErrorAuditEngine::Listen(address.clone(), err))?;
+                eprintln!("receiving audit events on {}", address);
+                for stream in listener.incoming().flatten() {
+                    let source = stream
+                        .peer_addr()
+                        .map(|a| a.to_string())
+                        .unwrap_or_default();
+                    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+                    receiver.spawn(stream, source);
+                }
+            }
+            Listen::Socket(path) => {
+                let path = path.unwrap_or_else(|| self.socket.clone());
+                serve_socket(&path, &receiver)?;
+            }
+        }
+        Ok(0)
+    }
+
+    fn query(&self, store: &Path, alias: Option<&Identifier>, source: Option<&str>) -> Result<i32> {
+        let file = match File::open(store) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(ErrorAuditEngine::Store(store.to_path_buf(), err)),
+        };
+        let mut output = Output::new(self.pager);
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|err| ErrorAuditEngine::Store(store.to_path_buf(), err))?;
+            let event: AuditEvent = match serde_json::from_str(&line) {
+                Ok(event) => event,
+                Err(_) => continue,
+            };
+            if !matches(&event, alias, source) {
+                continue;
+            }
+            match self.output {
+                OutputFormat::Json => writeln!(output, "{}", line)?,
+                OutputFormat::Text => write!(output, "{}", describe(&event, &chrono::Local))?,
+            }
+        }
+        output.finish()?;
+        Ok(0)
+    }
+}
+
+// tcp_address listens on the loopback when the address is only a port.
+fn tcp_address(address: &str) -> String {
+    if address.parse::<u16>().is_ok() {
+        format!("127.0.0.1:{}", address)
+    } else {
+        address.to_string()
+    }
+}
+
+// is_loopback tells whether every address the tcp address resolves to is a loopback one.
+fn is_loopback(address: &str) -> Result<bool> {
+    let mut addresses = address
+        .to_socket_addrs()
+        .map_err(|err| ErrorAuditEngine::Listen(address.to_string(), err))?;
+    Ok(addresses.all(|a| a.ip().is_loopback()))
+}
+
+#[cfg(unix)]
+fn serve_socket(path: &Path, receiver: &Receiver) -> Result<()> {
+    let listener = bind_socket(path)?;
+    eprintln!("receiving audit events on {}", path.display());
+    for stream in listener.incoming().flatten() {
+        receiver.spawn(stream, String::from("unix"));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn serve_socket(_: &Path, _: &Receiver) -> Result<()> {
+    Err(ErrorAuditEngine::NoSocket)
+}
+
+// bind_socket removes the socket left behind by a receiver that stopped,
+// but refuses to replace the one of a receiver that is still running.
+#[cfg(unix)]
+fn bind_socket(path: &Path) -> Result<UnixListener> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(ErrorAuditEngine::AlreadyServing(path.to_path_buf()));
+        }
+        std::fs::remove_file(path)?;
+    }
+    UnixListener::bind(path)
+        .map_err(|err| ErrorAuditEngine::Listen(path.display().to_string(), err))
+}
+
+// Receiver is what the connections share, the store, the token senders present and
+// the number of connections being received.
+struct Receiver {
+    store: Arc<Mutex<File>>,
+    token: Option<Arc<String>>,
+    connections: Arc<AtomicUsize>,
+}
+
+impl Receiver {
+    // spawn receives the events of a connection on its own thread, the connections past
+    // MAX_CONNECTIONS are refused.
+    fn spawn<S>(&self, stream: S, source: String)
+    where
+        S: Read + Write + Send + 'static,
+        for<'a> &'a S: Read + Write,
+    {
+        let slot = match Slot::take(&self.connections) {
+            Some(slot) => slot,
+            None => {
+                let _ = writeln!(&stream, "error: too many connections, try again later");
+                debug!("[SAM][ audit ] source={} refused", source);
+                return;
+            }
+        };
+        let store = self.store.clone();
+        let token = self.token.clone();
+        thread::spawn(move || {
+            let _slot = slot;
+            let mut output = &stream;
+            let token = token.as_deref().map(String::as_str);
+            match receive(BufReader::new(&stream), &mut output, &source, token, &store) {
+                Ok(count) => debug!("[SAM][ audit ] source={} events={}", source, count),
+                Err(err) => debug!("[SAM][ audit ] source={} error={}", source, err),
+            }
+        });
+    }
+}
+
+// Slot is a connection being received, it's given back when dropped.
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    fn take(connections: &Arc<AtomicUsize>) -> Option<Slot> {
+        connections
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                (count < MAX_CONNECTIONS).then_some(count + 1)
+            })
+            .ok()
+            .map(|_| Slot(connections.clone()))
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// receive reads the events sent on a connection, one json line each, and appends
+/// the valid ones to the store. It answers each line with `ok` or the reason it was
+/// rejected, and returns the number of events stored. When the receiver has a token,
+/// the first line has to be `token <token>`, the connection is closed otherwise.
+fn receive(
+    mut input: impl BufRead,
+    output: &mut impl Write,
+    source: &str,
+    token: Option<&str>,
+    store: &Mutex<File>,
+) -> Result<usize> {
+    let mut stored = 0;
+    if let Some(token) = token {
+        let mut line = vec![];
+        (&mut input)
+            .take(MAX_EVENT_SIZE as u64)
+            .read_until(b'\n', &mut line)?;
+        let expected = format!("token {}", token);
+        if !same_token(line.trim_ascii_end(), expected.as_bytes()) {
+            writeln!(output, "error: invalid token")?;
+            return Ok(stored);
+        }
+        writeln!(output, "ok")?;
+    }
+    loop {
+        let mut line = vec![];
+        let read = (&mut input)
+            .take(MAX_EVENT_SIZE as u64)
+            .read_until(b'\n', &mut line)?;
+        if read == 0 {
+            return Ok(stored);
+        }
+        if read == MAX_EVENT_SIZE && line.last() != Some(&b'\n') {
+            writeln!(
+                output,
+                "error: events are limited to {} bytes",
+                MAX_EVENT_SIZE
+            )?;
+            return Ok(stored);
+        }
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        let event = serde_json::from_slice::<HistoryEntryJson>(&line).map(|entry| AuditEvent {
+            received_at: chrono::Utc::now().timestamp(),
+            source: source.to_string(),
+            entry,
+        });
+        match event {
+            Ok(event) => {
+                let mut json = serde_json::to_vec(&event)?;
+                json.push(b'\n');
+                // a poisoned lock only means another connection panicked while writing.
+                let mut store = store.lock().unwrap_or_else(|e| e.into_inner());
+                store.write_all(&json)?;
+                stored += 1;
+                writeln!(output, "ok")?;
+            }
+            Err(err) => writeln!(output, "error: {}", err)?,
+        }
+    }
+}
+
+// same_token compares the tokens in a time that doesn't depend on where they differ.
+fn same_token(given: &[u8], expected: &[u8]) -> bool {
+    given.len() == expected.len()
+        && given
+            .iter()
+            .zip(expected)
+            .fold(0, |diff, (g, e)| diff | (g ^ e))
+            == 0
+}
+
+fn matches(event: &AuditEvent, alias: Option<&Identifier>, source: Option<&str>) -> bool {
+    alias.is_none_or(|a| Identifier::from_str(event.entry.alias()) == *a)
+        && source.is_none_or(|s| event.source.starts_with(s))
+}
+
+fn describe<Tz: TimeZone>(event: &AuditEvent, tz: &Tz) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let received_at = tz
+        .timestamp_opt(event.received_at, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default();
+    let mut text = format!(
+        "{} {}{}{} from {} in {}\n",
+        received_at,
+        crossterm::style::Attribute::Bold,
+        event.entry.alias(),
+        crossterm::style::Attribute::Reset,
+        event.source,
+        event.entry.pwd(),
+    );
+    for command in event.entry.commands() {
+        text.push_str(&format!("  {}\n", command));
+    }
+    text
+}
+
+pub type Result<T> = std::result::Result<T, ErrorAuditEngine>;
+
+#[derive(Debug, Error)]
+pub enum ErrorAuditEngine {
+    #[error("can't use the audit store {0}\n-> {1}")]
+    Store(PathBuf, io::Error),
+    #[error("can't listen on {0}\n-> {1}")]
+    Listen(String, io::Error),
+    #[error("an audit receiver is already listening on {0}")]
+    AlreadyServing(PathBuf),
+    #[error("{0} isn't a loopback address, set a token in ${TOKEN_ENV_VAR} to listen on it")]
+    NoToken(String),
+    #[error(
+        "unix sockets aren't available on this platform, listen on a tcp address with --listen"
+    )]
+    NoSocket,
+    #[error("could not serialize the audit event\n-> {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{0}")]
+    Io(#[from] io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        describe, is_loopback, matches, receive, tcp_address, AuditEvent, Slot, MAX_CONNECTIONS,
+    };
+    use sam_core::entities::identifiers::Identifier;
+    use std::fs::{self, File};
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::{Arc, Mutex};
+
+    const ENTRY: &str = r#"{"alias":"k8s::pods","desc":"lists pods","original_alias":"kubectl get pods","commands":["kubectl get pods"],"choices":{},"pwd":"/srv","timestamp":1700000000}"#;
+
+    #[test]
+    fn test_receive() {
+        let path = std::env::temp_dir().join(format!("sam-audit-{}", std::process::id()));
+        let store = Mutex::new(File::create(&path).unwrap());
+        let input = format!("{}\n\nnot json\n{}", ENTRY, ENTRY);
+        let mut output = vec![];
+        let stored = receive(input.as_bytes(), &mut output, "10.0.0.2:5000", None, &store).unwrap();
+        assert_eq!(stored, 2);
+        let output = String::from_utf8(output).unwrap();
+        let answers: Vec<&str> = output.lines().collect();
+        assert_eq!(answers[0], "ok");
+        assert!(answers[1].starts_with("error: "));
+        assert_eq!(answers[2], "ok");
+
+        let content = fs::read_to_string(&path).unwrap();
+        let events: Vec<AuditEvent> = content
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].source, "10.0.0.2:5000");
+        assert_eq!(events[0].entry.alias(), "k8s::pods");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_receive_with_token() {
+        let path = std::env::temp_dir().join(format!("sam-audit-token-{}", std::process::id()));
+        let store = Mutex::new(File::create(&path).unwrap());
+        let input = format!("token s3cret\n{}\n", ENTRY);
+        let mut output = vec![];
+        let stored = receive(
+            input.as_bytes(),
+            &mut output,
+            "unix",
+            Some("s3cret"),
+            &store,
+        );
+        assert_eq!(stored.unwrap(), 1);
+        assert_eq!(String::from_utf8(output).unwrap(), "ok\nok\n");
+
+        for input in [format!("token s3cre\n{}\n", ENTRY), format!("{}\n", ENTRY)] {
+            let mut output = vec![];
+            let stored = receive(
+                input.as_bytes(),
+                &mut output,
+                "unix",
+                Some("s3cret"),
+                &store,
+            );
+            assert_eq!(stored.unwrap(), 0);
+            assert_eq!(String::from_utf8(output).unwrap(), "error: invalid token\n");
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_tcp_address() {
+        assert_eq!(tcp_address("7878"), "127.0.0.1:7878");
+        assert_eq!(tcp_address("0.0.0.0:7878"), "0.0.0.0:7878");
+        assert!(is_loopback("127.0.0.1:7878").unwrap());
+        assert!(is_loopback("[::1]:7878").unwrap());
+        assert!(!is_loopback("0.0.0.0:7878").unwrap());
+        assert!(!is_loopback("10.0.0.2:7878").unwrap());
+        assert!(is_loopback("7878").is_err());
+    }
+
+    #[test]
+    fn test_slots() {
+        let connections = Arc::new(AtomicUsize::new(0));
+        let slots: Vec<Slot> = (0..MAX_CONNECTIONS)
+            .map(|_| Slot::take(&connections).unwrap())
+            .collect();
+        assert!(Slot::take(&connections).is_none());
+        drop(slots);
+        assert!(Slot::take(&connections).is_some());
+    }
+
+    #[test]
+    fn test_matches_and_describe() {
+        let event = AuditEvent {
+            received_at: 1700000000,
+            source: String::from("10.0.0.2:5000"),
+            entry: serde_json::from_str(ENTRY).unwrap(),
+        };
+        let pods = Identifier::with_namespace("pods", Some("k8s"));
+        let logs = Identifier::with_namespace("logs", Some("k8s"));
+        assert!(matches(&event, None, None));
+        assert!(matches(&event, Some(&pods), Some("10.0.0.2")));
+        assert!(!matches(&event, Some(&logs), None));
+        assert!(!matches(&event, None, Some("unix")));
+
+        let text = describe(&event, &chrono::Utc);
+        assert!(text.starts_with("2023-11-14 22:13:20 "));
+        assert!(text.contains("k8s::pods"));
+        assert!(text.ends_with("from 10.0.0.2:5000 in /srv\n  kubectl get pods\n"));
+    }
+}
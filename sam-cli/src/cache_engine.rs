@@ -1,12 +1,20 @@
-use sam_persistence::{CacheEntry, CacheError, RustBreakCache};
+use log::debug;
+use sam_core::entities::commands::Command;
+use sam_persistence::repositories::VarsRepository;
+use sam_persistence::{CacheEntry, CacheError, CacheKey, RustBreakCache, VarsCache};
+use sam_terminals::processes::ShellCommand;
 use sam_tui::modal_view::{ModalView, Value};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use std::time::Duration;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 pub struct CacheEngine {
     pub cache_dir: PathBuf,
     pub ttl: Duration,
+    pub vars: VarsRepository,
+    pub env_variables: HashMap<String, String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -14,6 +22,15 @@ pub enum CacheCommand {
     PrintKeys,
     DeleteEntries,
     Clear,
+    Refresh,
+}
+
+/// RefreshTarget is a `from_command` var's command to run again to refresh the cache.
+#[derive(Debug, Clone, PartialEq)]
+struct RefreshTarget {
+    name: String,
+    key: CacheKey,
+    ttl: Option<Duration>,
 }
 
 impl CacheEngine {
@@ -22,25 +39,134 @@ impl CacheEngine {
             CacheCommand::PrintKeys => self.print_keys(),
             CacheCommand::Clear => self.cache_clear(),
             CacheCommand::DeleteEntries => self.delete_entries(),
+            CacheCommand::Refresh => self.cache_refresh(),
+        }
+    }
+
+    /// cache_refresh runs the commands of the `from_command` vars again, all at once,
+    /// and stores their output in the cache. The vars without dependencies are always
+    /// refreshed, the other ones only for the choices of their dependencies that are
+    /// already in the cache.
+    fn cache_refresh(self) -> Result<i32> {
+        let cache = RustBreakCache::with_ttl(&self.cache_dir, &self.ttl)?;
+        let targets = self.refresh_targets(cache.entries()?)?;
+        let mut running = Vec::with_capacity(targets.len());
+        let mut failed = vec![];
+        for target in targets {
+            let mut cmd = ShellCommand::make_command(target.key.command.clone());
+            cmd.envs(&self.env_variables)
+                .envs(target.key.env.iter().cloned())
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped());
+            match cmd.spawn() {
+                Ok(child) => running.push((target, child, Instant::now())),
+                Err(_) => failed.push(target),
+            }
+        }
+
+        let mut refreshed = 0;
+        for (target, child, started) in running {
+            let output = child.wait_with_output()?;
+            debug!(
+                "[SAM][ var = '{}' ] command='{}' duration={}ms exit_code={} from_cache=false",
+                target.name,
+                target.key.command,
+                started.elapsed().as_millis(),
+                output
+                    .status
+                    .code()
+                    .map_or_else(|| String::from("none"), |code| code.to_string()),
+            );
+            if output.status.success() && output.stderr.is_empty() {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                cache.put(&target.name, &target.key, &stdout, target.ttl)?;
+                refreshed += 1;
+            } else {
+                failed.push(target);
+            }
+        }
+
+        println!(
+            "{}{}{} cache entries refreshed{}",
+            crossterm::style::Attribute::Bold,
+            crossterm::style::SetForegroundColor(crossterm::style::Color::DarkGreen),
+            refreshed,
+            crossterm::style::Attribute::Reset,
+        );
+        for target in &failed {
+            println!(
+                "- {}{}{} failed: {}",
+                crossterm::style::SetForegroundColor(crossterm::style::Color::DarkRed),
+                target.name,
+                crossterm::style::Attribute::Reset,
+                target.key,
+            );
         }
+        Ok(if failed.is_empty() { 0 } else { 1 })
+    }
+
+    fn refresh_targets(
+        &self,
+        entries: impl Iterator<Item = CacheEntry>,
+    ) -> Result<Vec<RefreshTarget>> {
+        let mut entries_by_var: HashMap<String, Vec<CacheKey>> = HashMap::new();
+        for entry in entries {
+            entries_by_var
+                .entry(entry.name.clone())
+                .or_default()
+                .push(entry.key());
+        }
+
+        let mut vars: Vec<_> = self
+            .vars
+            .vars_iter()
+            .filter(|v| v.is_command() && v.is_cached())
+            .collect();
+        vars.sort_by_key(|v| v.name());
+
+        let mut seen = HashSet::new();
+        let mut targets = vec![];
+        for var in vars {
+            let name = var.name().to_string();
+            let mut keys = entries_by_var.remove(&name).unwrap_or_default();
+            if var.dependencies().is_empty() {
+                let sh_cmd: ShellCommand<String> = var.command().into();
+                let command = sh_cmd.replace_env_vars_in_command(&self.env_variables)?;
+                keys.insert(0, CacheKey::new(command.value(), &HashMap::new()));
+            }
+            for key in keys {
+                if seen.insert(key.to_string()) {
+                    targets.push(RefreshTarget {
+                        name: name.clone(),
+                        key,
+                        ttl: var.cache_ttl(),
+                    });
+                }
+            }
+        }
+        Ok(targets)
     }
 
     fn print_keys(self) -> Result<i32> {
         let cache = RustBreakCache::with_ttl(self.cache_dir, &self.ttl)?;
         println!(
             "{}{}Keys present in cache{}\n",
-            termion::style::Bold,
-            termion::color::Fg(termion::color::Green),
-            termion::style::Reset,
+            crossterm::style::Attribute::Bold,
+            crossterm::style::SetForegroundColor(crossterm::style::Color::DarkGreen),
+            crossterm::style::Attribute::Reset,
         );
-        for key in cache.entries()? {
+        for entry in cache.entries()? {
             println!(
                 "- {}{}{}{}",
-                termion::style::Bold,
-                termion::color::Fg(termion::color::Green),
-                key.command,
-                termion::style::Reset,
+                crossterm::style::Attribute::Bold,
+                crossterm::style::SetForegroundColor(crossterm::style::Color::DarkGreen),
+                entry.command,
+                crossterm::style::Attribute::Reset,
             );
+            for (key, value) in &entry.env {
+                println!("    with {}={}", key, value);
+            }
         }
         Ok(0)
     }
@@ -53,7 +179,7 @@ impl CacheEngine {
             let response = controller.run();
             if let Some(output) = response {
                 for entry in output.marked_values {
-                    cache.delete(&entry.0.command)?;
+                    cache.delete(entry.0.key().to_string())?;
                 }
             }
         } else {
@@ -75,6 +201,8 @@ type Result<T> = std::result::Result<T, ErrorCacheEngine>;
 pub enum ErrorCacheEngine {
     #[error("an error happened while trying to clear the cache\n -> {0}")]
     CacheClear(#[from] CacheError),
+    #[error("could not run a command to refresh the cache\n -> {0}")]
+    Refresh(#[from] std::io::Error),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -86,10 +214,87 @@ impl Value for CacheEntryWrapper {
     }
 
     fn preview(&self) -> String {
+        let env: Vec<String> = self
+            .0
+            .env
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect();
         format!(
-            "Command: {}\n\nOUTPUT\n======\n{}",
+            "Command: {}\nEnvironment: {}\n\nOUTPUT\n======\n{}",
             self.0.command.as_str(),
+            env.join(" "),
             self.0.output.as_str(),
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheEngine, RefreshTarget};
+    use sam_core::entities::choices::Choice;
+    use sam_core::entities::namespaces::NamespaceUpdater;
+    use sam_core::entities::vars::Var;
+    use sam_persistence::repositories::VarsRepository;
+    use sam_persistence::{CacheEntry, CacheKey};
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    #[test]
+    fn test_refresh_targets() {
+        let mut regions =
+            Var::from_command("region", "a region", "echo eu-west-1").with_cache(None, Some(3600));
+        NamespaceUpdater::update(&mut regions, "aws");
+        let mut instances =
+            Var::from_command("instance", "an instance", "list-instances {{ region }}");
+        NamespaceUpdater::update(&mut instances, "aws");
+        let mut token =
+            Var::from_command("token", "a token", "vault read").with_cache(Some(false), None);
+        NamespaceUpdater::update(&mut token, "aws");
+        let mut static_var = Var::new("size", "a size", vec![Choice::new("small", None)]);
+        NamespaceUpdater::update(&mut static_var, "aws");
+
+        let engine = CacheEngine {
+            cache_dir: Default::default(),
+            ttl: Duration::from_secs(60),
+            vars: VarsRepository::new(vec![regions, instances, token, static_var].into_iter()),
+            env_variables: HashMap::new(),
+        };
+        let entries = vec![
+            CacheEntry {
+                name: String::from("aws::instance"),
+                command: String::from("list-instances eu-west-1"),
+                env: vec![],
+                output: String::from("i-1234"),
+            },
+            CacheEntry {
+                name: String::from("aws::region"),
+                command: String::from("echo eu-west-1"),
+                env: vec![],
+                output: String::from("eu-west-1"),
+            },
+            CacheEntry {
+                name: String::from("aws::removed"),
+                command: String::from("echo removed"),
+                env: vec![],
+                output: String::from("removed"),
+            },
+        ];
+        let targets = engine.refresh_targets(entries.into_iter()).unwrap();
+        assert_eq!(
+            targets,
+            vec![
+                RefreshTarget {
+                    name: String::from("aws::instance"),
+                    key: CacheKey::new("list-instances eu-west-1", &HashMap::new()),
+                    ttl: None,
+                },
+                RefreshTarget {
+                    name: String::from("aws::region"),
+                    key: CacheKey::new("echo eu-west-1", &HashMap::new()),
+                    ttl: Some(Duration::from_secs(3600)),
+                },
+            ]
+        );
+    }
+}
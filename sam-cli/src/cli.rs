@@ -1,15 +1,27 @@
+use crate::audit_engine::{AuditCommand, Listen};
 use crate::cache_engine::CacheCommand;
+use crate::complete_engine::CompleteCommand;
 use crate::config_engine::ConfigCommand;
+use crate::export_engine::{ErrorExportEngine, ExportCommand, Shell, SHELLS};
 use crate::history_engine::HistoryCommand;
+use crate::list_engine::{ListCommand, ListFilter};
+use crate::resolve_engine::{ResolveOutput, ResolveVarCommand};
+use crate::run_engine::RunCommand;
+use crate::scaffold_engine::ScaffoldCommand;
+use crate::stats_engine::StatsCommand;
+use crate::suggest_engine::SuggestCommand;
 use crate::HashMap;
-use clap::{App, Arg, ArgMatches, Values};
+use clap::{App, AppSettings, Arg, ArgMatches, Values};
 use sam_core::engines::SamCommand;
+use sam_core::entities::aliases::Impact;
 use sam_core::entities::choices::Choice;
 use sam_core::entities::identifiers;
 use sam_core::entities::identifiers::Identifier;
 use std::convert::TryFrom;
 use std::env;
 use std::ffi::OsString;
+use std::path::PathBuf;
+use std::str::FromStr;
 use thiserror::Error;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -18,21 +30,81 @@ const AUTHORS: &str = env!("CARGO_PKG_AUTHORS");
 const ABOUT: &str = "sam lets you difine custom aliases and search them using fuzzy search.";
 const ABOUT_SUB_RUN: &str = "let's you select and alias then run it";
 const ABOUT_SUB_SHOW_HISTORY: &str = "displays the last commands that you ran";
+const ABOUT_SUB_HISTORY_RUN: &str =
+    "searches the history by alias, choices and commands and runs the selected entry again";
 const ABOUT_SUB_RUN_LAST: &str = "runs the last command that was run again. shortcut is `sam %`";
+const ABOUT_SUB_HISTORY_EXPORT: &str = "prints the history as json lines, one entry per line";
+const ABOUT_SUB_HISTORY_IMPORT: &str =
+    "imports entries exported with `sam history export` into the history";
+const ABOUT_SUB_HISTORY_MIGRATE: &str =
+    "upgrades a history file written by an older version of sam";
+const ABOUT_SUB_HISTORY_SHOW: &str =
+    "shows the last execution of an alias and the output it recorded";
+const ABOUT_SUB_MODIFY_LAST: &str =
+    "runs the last alias again, choosing the vars to resolve again. shortcut is `sam m`";
 const ABOUT_SUB_SHOW_LAST: &str = "runs the last command that was run again. shortcut is `sam s`";
 const ABOUT_SUB_CHECK_CONFIG: &str = "checks your configuration files";
 const ABOUT_SUB_CACHE_CLEAR: &str = "clears the cache for vars 'from_command' outputs";
 const ABOUT_SUB_CACHE_KEYS: &str = "lists all the cache keys";
+const ABOUT_SUB_CACHE_REFRESH: &str =
+    "runs the commands of 'from_command' vars again to refresh their cached output";
 const ABOUT_SUB_CACHE_DELETE: &str =
     "explore the content of the command cache in order to delete entries";
 const ABOUT_SUB_ALIAS: &str = "run's a provided alias";
+const ABOUT_SUB_STATS: &str = "displays statistics about the aliases you ran";
+const ABOUT_SUB_STATS_HEATMAP: &str =
+    "displays when aliases are executed by day of the week and hour";
+const ABOUT_SUB_STATS_SUMMARY: &str =
+    "displays the most used aliases, the runs per namespace and the aliases never used";
+const ABOUT_SUB_AUDIT: &str = "receives and queries the audit events of other sam instances";
+const ABOUT_SUB_AUDIT_SERVE: &str =
+    "listens for the history entries sent by other sam instances and appends them to the audit store";
+const ABOUT_SUB_AUDIT_QUERY: &str = "prints the events of the audit store";
+const ABOUT_SUB_SUGGEST: &str =
+    "suggests new aliases from the commands you repeat the most in your shell history";
+const ABOUT_SUB_EXPORT: &str =
+    "prints shell functions and completions for your aliases, to source in your shell's startup file";
+const ABOUT_SUB_COMPLETIONS: &str =
+    "prints a completion script for sam's subcommands, aliases and choices";
+const ABOUT_SUB_COMPLETE: &str = "lists aliases or choices for completion scripts";
+const ABOUT_SUB_LIST: &str = "lists the available aliases, or vars";
+const ABOUT_SUB_WATCH: &str =
+    "reloads your aliases and vars whenever their files change and reports what changed";
+const ABOUT_SUB_NEW: &str = "creates an alias or a var by answering a few questions";
+const ABOUT_SUB_EDIT: &str = "opens the file defining an alias or a var in $EDITOR";
+const ABOUT_SUB_RESOLVE_VAR: &str = "asks for the choices of a var and prints them";
+const ABOUT_SUB_RESOLVE: &str =
+    "asks for the choices of an alias and prints its commands instead of running them";
+const ABOUT_SUB_RECORD: &str =
+    "asks for the choices of an alias and saves them to a run that can be replayed and shared";
+const ABOUT_SUB_REPLAY: &str = "runs an alias with the choices of a recorded run";
+const ABOUT_SUB_LAST_LOG: &str =
+    "prints the trace of the last run, the vars it resolved, from the cache or not, and the commands it ran";
+const ABOUT_SUB_TUTORIAL: &str = "walks you through the basics of sam using sample recipes";
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum SubCommand {
     SamCommand(SamCommand),
     HistoryCommand(HistoryCommand),
+    /// HistoryMigrate upgrades the history file written by older versions of sam.
+    /// It runs before the environment is loaded since loading it reads the history.
+    HistoryMigrate,
     CacheCommand(CacheCommand),
+    StatsCommand(StatsCommand),
+    AuditCommand(AuditCommand),
+    Suggest(SuggestCommand),
+    Export(ExportCommand),
+    Completions(Shell),
+    Complete(CompleteCommand),
+    ListCommand(ListCommand),
     ConfigCheck(ConfigCommand),
+    Tutorial,
+    Watch,
+    Scaffold(ScaffoldCommand),
+    Edit(String),
+    ResolveVar(ResolveVarCommand),
+    Run(RunCommand),
+    LastLog,
 }
 #[derive(Clone, Debug, PartialEq)]
 pub struct CLIRequest {
@@ -43,8 +115,20 @@ pub struct CLIRequest {
 #[derive(Clone, Debug, PartialEq)]
 pub struct CLISettings {
     pub dry: bool,
+    pub diff: bool,
+    pub record: bool,
+    pub quote: bool,
+    pub all: bool,
     pub silent: bool,
     pub no_cache: bool,
+    pub no_pager: bool,
+    pub non_interactive: bool,
+    pub use_defaults: bool,
+    pub answers_file: Option<PathBuf>,
+    pub jobs: Option<usize>,
+    pub impacts: Vec<Impact>,
+    pub tags: Vec<String>,
+    pub output: OutputFormat,
     pub default_choices: DefaultChoices,
 }
 
@@ -52,8 +136,27 @@ impl TryFrom<ArgMatches<'_>> for CLISettings {
     type Error = CLIError;
     fn try_from(matches: ArgMatches) -> Result<Self, Self::Error> {
         let dry = matches.is_present("dry");
+        let diff = matches.is_present("diff");
+        let record = matches.is_present("record");
+        let quote = matches.is_present("quote");
+        let all = matches.is_present("all");
         let silent = matches.is_present("silent");
         let no_cache = matches.is_present("no-cache");
+        let no_pager = matches.is_present("no-pager");
+        let non_interactive = matches.is_present("non-interactive");
+        let use_defaults = matches.is_present("use-defaults");
+        let answers_file = matches.value_of("answers-file").map(PathBuf::from);
+        let jobs = parse_jobs(matches.value_of("jobs"))?;
+        let impacts = matches
+            .values_of("impact")
+            .map(|values| values.map(parse_impact).collect())
+            .transpose()?
+            .unwrap_or_default();
+        let tags = matches
+            .values_of("tag")
+            .map(|values| values.map(String::from).collect())
+            .unwrap_or_default();
+        let output = OutputFormat::from_str(matches.value_of("output").unwrap_or_default())?;
 
         let defaults_extractor = |subcommand: &str| {
             matches
@@ -64,14 +167,42 @@ impl TryFrom<ArgMatches<'_>> for CLISettings {
         let defaults_values = matches
             .values_of("choices")
             .or_else(|| defaults_extractor("alias"))
-            .or_else(|| defaults_extractor("run"));
+            .or_else(|| defaults_extractor("run"))
+            .or_else(|| defaults_extractor("resolve-var"))
+            .or_else(|| defaults_extractor("resolve"))
+            .or_else(|| defaults_extractor("record"));
 
-        let default_choices = DefaultChoices::try_from(defaults_values)?;
+        let mut default_choices = DefaultChoices::try_from(defaults_values)?;
+        // the arguments given after `--` fill the `{{ arg:N }}` parameters of the alias.
+        let arguments = ["alias", "resolve"]
+            .iter()
+            .find_map(|subcommand| matches.subcommand_matches(subcommand))
+            .and_then(|e| e.values_of("arguments"))
+            .into_iter()
+            .flatten();
+        for (i, argument) in arguments.enumerate() {
+            default_choices.0.insert(
+                Identifier::argument(i + 1),
+                vec![Choice::from_value(argument)],
+            );
+        }
 
         Ok(CLISettings {
             dry,
+            diff,
+            record,
+            quote,
+            all,
             silent,
             no_cache,
+            no_pager,
+            non_interactive,
+            use_defaults,
+            answers_file,
+            jobs,
+            impacts,
+            tags,
+            output,
             default_choices,
         })
     }
@@ -88,7 +219,25 @@ fn app_init() -> App<'static, 'static> {
     let arg_dry = Arg::with_name("dry")
         .long("dry")
         .short("d")
-        .help("dry run, don't execute the final command.");
+        .help("dry run, print the final commands instead of executing them.");
+
+    let arg_quote = Arg::with_name("quote")
+        .long("quote")
+        .requires("dry")
+        .help("with --dry, shell-quote each printed command.");
+
+    let arg_diff = Arg::with_name("diff")
+        .long("diff")
+        .help("show what changed since the previous execution of the alias before running it.");
+
+    let arg_record = Arg::with_name("record")
+        .long("record")
+        .help("record what the alias prints in the history, see `sam history show`.");
+
+    let arg_all = Arg::with_name("all")
+        .long("all")
+        .short("a")
+        .help("show hidden aliases in the alias picker and in list.");
 
     let arg_silent = Arg::with_name("silent")
         .long("silent")
@@ -100,13 +249,99 @@ fn app_init() -> App<'static, 'static> {
         .short("-n")
         .help("avoid relying of the vars cache.");
 
+    let arg_no_pager = Arg::with_name("no-pager")
+        .long("no-pager")
+        .help("print long outputs, the list of the aliases, the history or the audit events, without a pager.");
+
+    let arg_non_interactive = Arg::with_name("non-interactive")
+        .long("non-interactive")
+        .help("fail instead of prompting when a var has no choice given with -c or in [defaults], for scripts and CI.");
+
+    let arg_use_defaults = Arg::with_name("use-defaults")
+        .long("use-defaults")
+        .help("use the default of the vars that declare one instead of asking for a choice.");
+
+    let arg_answers_file = Arg::with_name("answers-file")
+        .long("answers-file")
+        .takes_value(true)
+        .help("read the choices of the vars from a yaml file mapping each var to its choices, `-` reads stdin.");
+
+    let arg_jobs = Arg::with_name("jobs")
+        .long("jobs")
+        .short("j")
+        .takes_value(true)
+        .help("run the commands of an alias in parallel, at most <jobs> at a time.");
+
+    let arg_impact = Arg::with_name("impact")
+        .long("impact")
+        .takes_value(true)
+        .use_delimiter(true)
+        .possible_values(&IMPACTS)
+        .help("only offer and list the aliases with these impacts. example '--impact low,medium'");
+
+    let arg_tag = Arg::with_name("tag")
+        .long("tag")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .use_delimiter(true)
+        .help("only offer and list the aliases with all of these tags. example '--tag k8s,prod'");
+
+    let arg_output = Arg::with_name("output")
+        .long("output")
+        .short("o")
+        .takes_value(true)
+        .possible_values(&OUTPUT_FORMATS)
+        .default_value("text")
+        .help("the output format of history, check-config and list.");
+
     let subc_run = App::new("run")
         .arg(arg_choices.clone())
         .about(ABOUT_SUB_RUN);
 
-    let subc_interract_history = App::new("history").about(ABOUT_SUB_SHOW_HISTORY);
+    let subc_interract_history = App::new("history")
+        .about(ABOUT_SUB_SHOW_HISTORY)
+        .subcommand(App::new("run").about(ABOUT_SUB_HISTORY_RUN))
+        .subcommand(
+            App::new("export")
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["jsonl"])
+                        .default_value("jsonl")
+                        .help("the format of the exported history."),
+                )
+                .about(ABOUT_SUB_HISTORY_EXPORT),
+        )
+        .subcommand(
+            App::new("import")
+                .arg(
+                    Arg::with_name("file")
+                        .help("the file to import, `-` reads from stdin.")
+                        .required(true)
+                        .index(1),
+                )
+                .about(ABOUT_SUB_HISTORY_IMPORT),
+        )
+        .subcommand(App::new("migrate").about(ABOUT_SUB_HISTORY_MIGRATE))
+        .subcommand(
+            App::new("show")
+                .arg(
+                    Arg::with_name("alias")
+                        .help("the alias whose last execution is shown, the last alias otherwise.")
+                        .index(1),
+                )
+                .about(ABOUT_SUB_HISTORY_SHOW),
+        );
     let subc_rerun_last = App::new("run-last").alias("%").about(ABOUT_SUB_RUN_LAST);
+    let subc_modify_last = App::new("modify-last")
+        .alias("m")
+        .about(ABOUT_SUB_MODIFY_LAST);
     let subc_show_last = App::new("show-last").alias("s").about(ABOUT_SUB_SHOW_LAST);
+    let arg_arguments = Arg::with_name("arguments").multiple(true).last(true).help(
+        "the arguments of the alias, given after `--`, they fill its {{ arg:1 }}, {{ arg:2 }}...",
+    );
     let subc_alias = App::new("alias")
         .arg(
             Arg::with_name("alias")
@@ -114,26 +349,251 @@ fn app_init() -> App<'static, 'static> {
                 .required(true)
                 .index(1),
         )
+        .arg(arg_arguments.clone())
         .arg(arg_choices.clone())
         .about(ABOUT_SUB_ALIAS);
 
+    let subc_stats = App::new("stats")
+        .about(ABOUT_SUB_STATS)
+        .subcommand(
+            App::new("heatmap")
+                .arg(
+                    Arg::with_name("alias")
+                        .help("only count the executions of this alias.")
+                        .index(1),
+                )
+                .about(ABOUT_SUB_STATS_HEATMAP),
+        )
+        .subcommand(App::new("summary").about(ABOUT_SUB_STATS_SUMMARY));
+
+    let arg_store = Arg::with_name("store")
+        .long("store")
+        .takes_value(true)
+        .help("the file audit events are stored in, defaults to audit.jsonl next to the history.");
+
+    let subc_audit = App::new("audit")
+        .about(ABOUT_SUB_AUDIT)
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            App::new("serve")
+                .arg(
+                    Arg::with_name("socket")
+                        .long("socket")
+                        .takes_value(true)
+                        .help("the unix socket to listen on, defaults to audit.sock next to the history."),
+                )
+                .arg(
+                    Arg::with_name("listen")
+                        .long("listen")
+                        .takes_value(true)
+                        .conflicts_with("socket")
+                        .help("the tcp address to listen on instead of a unix socket, on the loopback when it's only a port. example '7878'. Other addresses than the loopback need a token in $SAM_AUDIT_TOKEN."),
+                )
+                .arg(arg_store.clone())
+                .about(ABOUT_SUB_AUDIT_SERVE),
+        )
+        .subcommand(
+            App::new("query")
+                .arg(
+                    Arg::with_name("alias")
+                        .long("alias")
+                        .takes_value(true)
+                        .help("only print the events of this alias."),
+                )
+                .arg(
+                    Arg::with_name("source")
+                        .long("source")
+                        .takes_value(true)
+                        .help("only print the events received from addresses starting with this prefix."),
+                )
+                .arg(arg_store)
+                .about(ABOUT_SUB_AUDIT_QUERY),
+        );
+
+    let subc_suggest = App::new("suggest")
+        .arg(
+            Arg::with_name("history-file")
+                .long("history-file")
+                .takes_value(true)
+                .help("the shell history file to read, defaults to the one of your current shell."),
+        )
+        .arg(
+            Arg::with_name("min-count")
+                .long("min-count")
+                .takes_value(true)
+                .help("the minimum number of times a command must be repeated. defaults to 3."),
+        )
+        .about(ABOUT_SUB_SUGGEST);
+
+    let subc_export = App::new("export")
+        .arg(
+            Arg::with_name("shell")
+                .long("shell")
+                .takes_value(true)
+                .possible_values(&SHELLS)
+                .default_value("bash")
+                .help("the shell to export the aliases for."),
+        )
+        .about(ABOUT_SUB_EXPORT);
+
+    let subc_completions = App::new("completions")
+        .arg(
+            Arg::with_name("shell")
+                .possible_values(&SHELLS)
+                .required(true)
+                .index(1)
+                .help("the shell to generate the completion script for."),
+        )
+        .about(ABOUT_SUB_COMPLETIONS);
+
+    let arg_identifier = Arg::with_name("identifier")
+        .index(1)
+        .help("the name, or namespace::name, of the entry to create.");
+    let subc_new = App::new("new")
+        .about(ABOUT_SUB_NEW)
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(App::new("alias").arg(arg_identifier.clone()))
+        .subcommand(App::new("var").arg(arg_identifier));
+
+    let subc_edit = App::new("edit")
+        .arg(
+            Arg::with_name("identifier")
+                .required(true)
+                .index(1)
+                .help("the name, or namespace::name, of the alias or the var to edit."),
+        )
+        .about(ABOUT_SUB_EDIT);
+
+    let subc_resolve_var = App::new("resolve-var")
+        .arg(
+            Arg::with_name("var")
+                .required(true)
+                .index(1)
+                .help("the name, or namespace::name, of the var."),
+        )
+        .arg(
+            Arg::with_name("print")
+                .long("print")
+                .help("print the values of the choices only, one per line."),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .conflicts_with("print")
+                .help("print the var and its choices as json, like `--output json`."),
+        )
+        .arg(arg_choices.clone())
+        .about(ABOUT_SUB_RESOLVE_VAR);
+
+    let subc_resolve = App::new("resolve")
+        .arg(
+            Arg::with_name("alias")
+                .help("the alias to resolve.")
+                .required(true)
+                .index(1),
+        )
+        .arg(arg_arguments)
+        .arg(arg_choices.clone())
+        .about(ABOUT_SUB_RESOLVE);
+
+    let arg_run = Arg::with_name("run")
+        .required(true)
+        .index(1)
+        .help("the name of the run, the file runs/<name>.yaml of a root directory.");
+    let subc_record = App::new("record")
+        .arg(arg_run.clone())
+        .arg(
+            Arg::with_name("alias")
+                .index(2)
+                .help("the alias to record, chosen with the picker otherwise."),
+        )
+        .arg(arg_choices.clone())
+        .about(ABOUT_SUB_RECORD);
+    let subc_replay = App::new("replay").arg(arg_run).about(ABOUT_SUB_REPLAY);
+
+    let subc_complete = App::new("complete")
+        .setting(AppSettings::Hidden)
+        .arg(
+            Arg::with_name("kind")
+                .possible_values(&["aliases", "choices"])
+                .required(true)
+                .index(1),
+        )
+        .about(ABOUT_SUB_COMPLETE);
+
+    let subc_list = App::new("list")
+        .about(ABOUT_SUB_LIST)
+        .arg(
+            Arg::with_name("kind")
+                .possible_values(&["aliases", "vars"])
+                .default_value("aliases")
+                .index(1)
+                .help("what to list."),
+        )
+        .arg(
+            Arg::with_name("namespace")
+                .long("namespace")
+                .short("n")
+                .takes_value(true)
+                .help("only list the entries of this namespace."),
+        )
+        .arg(
+            Arg::with_name("tag")
+                .long("tag")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .use_delimiter(true)
+                .help("only list the aliases with all of these tags, or the vars they use."),
+        );
+
     App::new("sam")
         .version(VERSION)
         .author(AUTHORS)
         .about(ABOUT)
         .arg(arg_dry)
+        .arg(arg_diff)
+        .arg(arg_record)
+        .arg(arg_quote)
+        .arg(arg_all)
         .arg(arg_silent)
         .arg(arg_no_cache)
+        .arg(arg_no_pager)
+        .arg(arg_non_interactive)
+        .arg(arg_use_defaults)
+        .arg(arg_answers_file)
+        .arg(arg_jobs)
+        .arg(arg_impact)
+        .arg(arg_tag)
+        .arg(arg_output)
         .arg(arg_choices.clone())
         .subcommand(subc_run)
         .subcommand(subc_alias)
         .subcommand(subc_rerun_last)
+        .subcommand(subc_modify_last)
         .subcommand(subc_show_last)
         .subcommand(subc_interract_history)
         .subcommand(App::new("check-config").about(ABOUT_SUB_CHECK_CONFIG))
         .subcommand(App::new("cache-clear").about(ABOUT_SUB_CACHE_CLEAR))
         .subcommand(App::new("cache-keys").about(ABOUT_SUB_CACHE_KEYS))
+        .subcommand(App::new("cache-refresh").about(ABOUT_SUB_CACHE_REFRESH))
         .subcommand(App::new("cache-keys-delete").about(ABOUT_SUB_CACHE_DELETE))
+        .subcommand(subc_stats)
+        .subcommand(subc_audit)
+        .subcommand(subc_suggest)
+        .subcommand(subc_export)
+        .subcommand(subc_completions)
+        .subcommand(subc_list)
+        .subcommand(subc_complete)
+        .subcommand(App::new("tutorial").about(ABOUT_SUB_TUTORIAL))
+        .subcommand(App::new("watch").about(ABOUT_SUB_WATCH))
+        .subcommand(subc_new)
+        .subcommand(subc_edit)
+        .subcommand(subc_resolve_var)
+        .subcommand(subc_resolve)
+        .subcommand(subc_record)
+        .subcommand(subc_replay)
+        .subcommand(App::new("last-log").about(ABOUT_SUB_LAST_LOG))
 }
 
 fn make_cli_request<'a, T, I>(app: App<'a, 'a>, args: I) -> Result<CLIRequest, CLIError>
@@ -153,23 +613,218 @@ where
         ("run-last", Some(_)) => {
             SubCommand::HistoryCommand(HistoryCommand::ExecuteLastExecutedAlias)
         }
+        ("modify-last", Some(_)) => {
+            SubCommand::HistoryCommand(HistoryCommand::ModifyThenExecuteLastAlias)
+        }
         ("show-last", Some(_)) => {
             SubCommand::HistoryCommand(HistoryCommand::DisplayLastExecutedAlias)
         }
-        ("history", Some(_)) => SubCommand::HistoryCommand(HistoryCommand::InterractWithHistory),
+        ("history", Some(e)) => match e.subcommand() {
+            ("run", Some(_)) => SubCommand::HistoryCommand(HistoryCommand::SearchAndExecute),
+            ("export", Some(_)) => SubCommand::HistoryCommand(HistoryCommand::Export),
+            ("migrate", Some(_)) => SubCommand::HistoryMigrate,
+            ("show", Some(s)) => SubCommand::HistoryCommand(HistoryCommand::Show(
+                s.value_of("alias").map(Identifier::from_str),
+            )),
+            ("import", Some(i)) => SubCommand::HistoryCommand(HistoryCommand::Import(
+                PathBuf::from(i.value_of("file").unwrap_or_default()),
+            )),
+            _ => SubCommand::HistoryCommand(HistoryCommand::InterractWithHistory),
+        },
         ("check-config", Some(_)) => SubCommand::ConfigCheck(ConfigCommand::All),
         ("cache-clear", Some(_)) => SubCommand::CacheCommand(CacheCommand::Clear),
         ("cache-keys", Some(_)) => SubCommand::CacheCommand(CacheCommand::PrintKeys),
+        ("cache-refresh", Some(_)) => SubCommand::CacheCommand(CacheCommand::Refresh),
         ("cache-keys-delete", Some(_)) => SubCommand::CacheCommand(CacheCommand::DeleteEntries),
+        ("stats", Some(e)) if e.subcommand_matches("summary").is_some() => {
+            SubCommand::StatsCommand(StatsCommand::Summary)
+        }
+        ("stats", Some(e)) => {
+            let alias = e
+                .subcommand_matches("heatmap")
+                .and_then(|h| h.value_of("alias"))
+                .map(Identifier::from_str);
+            SubCommand::StatsCommand(StatsCommand::Heatmap { alias })
+        }
+        ("audit", Some(e)) => match e.subcommand() {
+            ("serve", Some(s)) => SubCommand::AuditCommand(AuditCommand::Serve {
+                listen: match s.value_of("listen") {
+                    Some(address) => Listen::Tcp(address.to_string()),
+                    None => Listen::Socket(s.value_of("socket").map(PathBuf::from)),
+                },
+                store: s.value_of("store").map(PathBuf::from),
+            }),
+            (_, q) => SubCommand::AuditCommand(AuditCommand::Query {
+                alias: q
+                    .and_then(|q| q.value_of("alias"))
+                    .map(Identifier::from_str),
+                source: q.and_then(|q| q.value_of("source")).map(String::from),
+                store: q.and_then(|q| q.value_of("store")).map(PathBuf::from),
+            }),
+        },
+        ("suggest", Some(e)) => {
+            let min_count = e
+                .value_of("min-count")
+                .map(|c| {
+                    c.parse::<usize>()
+                        .map_err(|_| CLIError::MalformedMinCount(c.to_string()))
+                })
+                .transpose()?;
+            SubCommand::Suggest(SuggestCommand {
+                history_file: e.value_of("history-file").map(PathBuf::from),
+                min_count,
+            })
+        }
+        ("export", Some(e)) => {
+            let shell = Shell::from_str(e.value_of("shell").unwrap_or_default())?;
+            SubCommand::Export(ExportCommand { shell })
+        }
+        ("completions", Some(e)) => {
+            SubCommand::Completions(Shell::from_str(e.value_of("shell").unwrap_or_default())?)
+        }
+        ("complete", Some(e)) => match e.value_of("kind") {
+            Some("choices") => SubCommand::Complete(CompleteCommand::Choices),
+            _ => SubCommand::Complete(CompleteCommand::Aliases),
+        },
+        ("list", Some(e)) => {
+            let filter = ListFilter {
+                namespace: e.value_of("namespace").map(String::from),
+                tags: e
+                    .values_of("tag")
+                    .map(|tags| tags.map(String::from).collect())
+                    .unwrap_or_default(),
+            };
+            match e.value_of("kind") {
+                Some("vars") => SubCommand::ListCommand(ListCommand::Vars(filter)),
+                _ => SubCommand::ListCommand(ListCommand::Aliases(filter)),
+            }
+        }
+        ("tutorial", Some(_)) => SubCommand::Tutorial,
+        ("watch", Some(_)) => SubCommand::Watch,
+        ("new", Some(e)) => match e.subcommand() {
+            ("var", v) => SubCommand::Scaffold(ScaffoldCommand::NewVar(
+                v.and_then(|v| v.value_of("identifier")).map(String::from),
+            )),
+            (_, a) => SubCommand::Scaffold(ScaffoldCommand::NewAlias(
+                a.and_then(|a| a.value_of("identifier")).map(String::from),
+            )),
+        },
+        ("edit", Some(e)) => {
+            SubCommand::Edit(e.value_of("identifier").unwrap_or_default().to_string())
+        }
+        ("resolve", Some(e)) => {
+            let alias = parse_alias(e.value_of("alias"))?;
+            SubCommand::SamCommand(SamCommand::ResolveAlias { alias })
+        }
+        ("record", Some(e)) => SubCommand::Run(RunCommand::Record {
+            name: e.value_of("run").unwrap_or_default().to_string(),
+            alias: e.value_of("alias").map(Identifier::from_str),
+        }),
+        ("replay", Some(e)) => SubCommand::Run(RunCommand::Replay(
+            e.value_of("run").unwrap_or_default().to_string(),
+        )),
+        ("last-log", Some(_)) => SubCommand::LastLog,
+        ("resolve-var", Some(e)) => SubCommand::ResolveVar(ResolveVarCommand {
+            var: e.value_of("var").unwrap_or_default().to_string(),
+            output: if e.is_present("json") || settings.output == OutputFormat::Json {
+                ResolveOutput::Json
+            } else if e.is_present("print") {
+                ResolveOutput::Values
+            } else {
+                ResolveOutput::Choices
+            },
+        }),
 
         (&_, _) => SubCommand::SamCommand(SamCommand::ChooseAndExecuteAlias),
     };
     Ok(CLIRequest { command, settings })
 }
 
+/// completion_script generates the completion script of the command line with clap
+/// and extends it to complete aliases and choices using `sam complete`.
+pub fn completion_script(shell: Shell) -> String {
+    let clap_shell = match shell {
+        Shell::Bash => clap::Shell::Bash,
+        Shell::Zsh => clap::Shell::Zsh,
+        Shell::Fish => clap::Shell::Fish,
+    };
+    let mut buffer: Vec<u8> = vec![];
+    app_init().gen_completions_to("sam", clap_shell, &mut buffer);
+    let script = String::from_utf8_lossy(&buffer).to_string();
+    match shell {
+        Shell::Bash => format!("{}\n{}", script, BASH_DYNAMIC_COMPLETIONS),
+        Shell::Zsh => format!(
+            "{}\n{}",
+            script.trim_end().trim_end_matches("_sam \"$@\""),
+            ZSH_DYNAMIC_COMPLETIONS
+        ),
+        Shell::Fish => format!("{}\n{}", script, FISH_DYNAMIC_COMPLETIONS),
+    }
+}
+
+const BASH_DYNAMIC_COMPLETIONS: &str = r#"_sam_dynamic() {
+    local cur prev
+    cur="${COMP_WORDS[COMP_CWORD]}"
+    prev="${COMP_WORDS[COMP_CWORD-1]}"
+    if declare -F _get_comp_words_by_ref >/dev/null; then
+        _get_comp_words_by_ref -n : cur prev
+    fi
+    case "$prev" in
+        alias) COMPREPLY=($(compgen -W "$(sam complete aliases 2>/dev/null | cut -f1)" -- "$cur")) ;;
+        -c|--choices) COMPREPLY=($(compgen -W "$(sam complete choices 2>/dev/null | cut -f1)" -- "$cur")) ;;
+        *) _sam "$@"; return ;;
+    esac
+    if declare -F __ltrim_colon_completions >/dev/null; then
+        __ltrim_colon_completions "$cur"
+    fi
+}
+complete -F _sam_dynamic -o bashdefault -o default sam
+"#;
+
+const ZSH_DYNAMIC_COMPLETIONS: &str = r#"_sam_dynamic() {
+    local -a entries
+    case "${words[CURRENT-1]}" in
+        alias) entries=("${(@f)$(sam complete aliases 2>/dev/null)}") ;;
+        -c|--choices) entries=("${(@f)$(sam complete choices 2>/dev/null)}") ;;
+        *) _sam "$@"; return ;;
+    esac
+    entries=("${(@)entries//:/\:}")
+    entries=("${(@)entries//$'\t'/:}")
+    _describe 'values' entries
+}
+
+_sam_dynamic "$@"
+"#;
+
+const FISH_DYNAMIC_COMPLETIONS: &str = r#"complete -c sam -n "__fish_seen_subcommand_from alias" -f -a "(sam complete aliases 2>/dev/null)"
+complete -c sam -s c -l choices -x -a "(sam complete choices 2>/dev/null)"
+"#;
+
 pub fn read_cli_request() -> Result<CLIRequest, CLIError> {
     let app = app_init();
-    make_cli_request(app, &mut env::args_os())
+    make_cli_request(app, env::args_os())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+const OUTPUT_FORMATS: [&str; 2] = ["text", "json"];
+const IMPACTS: [&str; 3] = ["low", "medium", "high"];
+
+impl FromStr for OutputFormat {
+    type Err = CLIError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            other => Err(CLIError::UnsupportedOutputFormat(other.to_string())),
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -197,6 +852,25 @@ fn parse_alias(alias: Option<&str>) -> Result<Identifier, CLIError> {
     }
 }
 
+fn parse_jobs(jobs: Option<&str>) -> Result<Option<usize>, CLIError> {
+    match jobs.map(|j| j.parse::<usize>()) {
+        Some(Ok(0)) | Some(Err(_)) => Err(CLIError::MalformedJobs(
+            jobs.unwrap_or_default().to_string(),
+        )),
+        Some(Ok(n)) => Ok(Some(n)),
+        None => Ok(None),
+    }
+}
+
+fn parse_impact(impact: &str) -> Result<Impact, CLIError> {
+    match impact {
+        "low" => Ok(Impact::Low),
+        "medium" => Ok(Impact::Medium),
+        "high" => Ok(Impact::High),
+        other => Err(CLIError::UnsupportedImpact(other.to_string())),
+    }
+}
+
 fn parse_choice(default: &str) -> Result<(Identifier, Choice), CLIError> {
     let parts: Vec<&str> = default.split('=').collect();
     if parts.len() == 2 {
@@ -220,6 +894,16 @@ pub enum CLIError {
     MissingNamespaceForChoice(Identifier, String),
     #[error("malformed choice {0}, it should be -c namespace::var_name=choice")]
     MalformedChoice(String),
+    #[error("malformed jobs count {0}, it should be a positive number")]
+    MalformedJobs(String),
+    #[error("malformed minimum count {0}, it should be a positive number")]
+    MalformedMinCount(String),
+    #[error("{0}")]
+    Export(#[from] ErrorExportEngine),
+    #[error("output format {0} is not supported, it should be text or json")]
+    UnsupportedOutputFormat(String),
+    #[error("impact {0} is not supported, it should be low, medium or high")]
+    UnsupportedImpact(String),
 }
 
 #[cfg(test)]
@@ -227,11 +911,26 @@ mod tests {
 
     use crate::cli::DefaultChoices;
     use maplit::hashmap;
+    use sam_core::entities::aliases::Impact;
     use sam_core::entities::{choices::Choice, identifiers::Identifier};
 
-    use super::{app_init, make_cli_request, CLIRequest, SubCommand};
-    use crate::cli::CLISettings;
+    use super::{
+        app_init, completion_script, make_cli_request, parse_jobs, CLIRequest, SubCommand,
+    };
+    use crate::audit_engine::{AuditCommand, Listen};
+    use crate::cache_engine::CacheCommand;
+    use crate::cli::{CLISettings, OutputFormat};
+    use crate::complete_engine::CompleteCommand;
+    use crate::export_engine::{ExportCommand, Shell};
+    use crate::history_engine::HistoryCommand;
+    use crate::list_engine::{ListCommand, ListFilter};
+    use crate::resolve_engine::{ResolveOutput, ResolveVarCommand};
+    use crate::run_engine::RunCommand;
+    use crate::scaffold_engine::ScaffoldCommand;
+    use crate::stats_engine::StatsCommand;
+    use crate::suggest_engine::SuggestCommand;
     use sam_core::engines::SamCommand;
+    use std::path::PathBuf;
 
     #[test]
     fn alias_subcommand() {
@@ -250,8 +949,20 @@ mod tests {
             }),
             settings: CLISettings {
                 dry: false,
+                diff: false,
+                record: false,
+                quote: false,
+                all: false,
                 silent: false,
                 no_cache: false,
+                no_pager: false,
+                non_interactive: false,
+                use_defaults: false,
+                answers_file: None,
+                jobs: None,
+                impacts: vec![],
+                tags: vec![],
+                output: OutputFormat::Text,
                 default_choices: DefaultChoices(hashmap! {
                 Identifier::with_namespace("some_choice", Some("some_ns")) => vec![Choice::from_value("value")],
                 Identifier::with_namespace("some_other_choice", Some("some_ns")) => vec![Choice::from_value("value2")],
@@ -262,6 +973,405 @@ mod tests {
         assert_eq!(request.unwrap(), expected_cli_request);
     }
 
+    #[test]
+    fn jobs_argument() {
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "-j", "4", "run"]).unwrap();
+        assert_eq!(request.settings.jobs, Some(4));
+        assert!(parse_jobs(Some("0")).is_err());
+        assert!(parse_jobs(Some("many")).is_err());
+        assert_eq!(parse_jobs(None).unwrap(), None);
+    }
+
+    #[test]
+    fn non_interactive_argument() {
+        let app = app_init();
+        let request =
+            make_cli_request(app, ["sam", "--non-interactive", "alias", "ns::alias"]).unwrap();
+        assert!(request.settings.non_interactive);
+        let app = app_init();
+        let request =
+            make_cli_request(app, ["sam", "--answers-file", "-", "alias", "ns::alias"]).unwrap();
+        assert_eq!(request.settings.answers_file, Some(PathBuf::from("-")));
+        assert!(!request.settings.use_defaults);
+        let app = app_init();
+        let request =
+            make_cli_request(app, ["sam", "--use-defaults", "alias", "ns::alias"]).unwrap();
+        assert!(request.settings.use_defaults);
+    }
+
+    #[test]
+    fn cache_refresh_subcommand() {
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "cache-refresh"]).unwrap();
+        assert_eq!(
+            request.command,
+            SubCommand::CacheCommand(CacheCommand::Refresh)
+        );
+    }
+
+    #[test]
+    fn modify_last_subcommand() {
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "m"]).unwrap();
+        assert_eq!(
+            request.command,
+            SubCommand::HistoryCommand(HistoryCommand::ModifyThenExecuteLastAlias)
+        );
+    }
+
+    #[test]
+    fn history_export_import_subcommands() {
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "history", "export", "--format", "jsonl"]);
+        assert_eq!(
+            request.unwrap().command,
+            SubCommand::HistoryCommand(HistoryCommand::Export)
+        );
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "history", "import", "backup.jsonl"]);
+        assert_eq!(
+            request.unwrap().command,
+            SubCommand::HistoryCommand(HistoryCommand::Import(PathBuf::from("backup.jsonl")))
+        );
+
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "history", "migrate"]);
+        assert_eq!(request.unwrap().command, SubCommand::HistoryMigrate);
+    }
+
+    #[test]
+    fn history_show_subcommand() {
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "history", "show"]).unwrap();
+        assert_eq!(
+            request.command,
+            SubCommand::HistoryCommand(HistoryCommand::Show(None))
+        );
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "--record", "history", "show", "k8s::pods"]);
+        let request = request.unwrap();
+        assert!(request.settings.record);
+        assert_eq!(
+            request.command,
+            SubCommand::HistoryCommand(HistoryCommand::Show(Some(Identifier::from_str(
+                "k8s::pods"
+            ))))
+        );
+    }
+
+    #[test]
+    fn history_run_subcommand() {
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "history", "run"]).unwrap();
+        assert_eq!(
+            request.command,
+            SubCommand::HistoryCommand(HistoryCommand::SearchAndExecute)
+        );
+    }
+
+    #[test]
+    fn stats_subcommand() {
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "stats", "heatmap", "ns::alias"]).unwrap();
+        let expected = SubCommand::StatsCommand(StatsCommand::Heatmap {
+            alias: Some(Identifier::with_namespace("alias", Some("ns"))),
+        });
+        assert_eq!(request.command, expected);
+
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "stats", "summary"]).unwrap();
+        let expected = SubCommand::StatsCommand(StatsCommand::Summary);
+        assert_eq!(request.command, expected);
+    }
+
+    #[test]
+    fn suggest_subcommand() {
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "suggest", "--min-count", "5"]).unwrap();
+        let expected = SubCommand::Suggest(SuggestCommand {
+            history_file: None,
+            min_count: Some(5),
+        });
+        assert_eq!(request.command, expected);
+    }
+
+    #[test]
+    fn export_subcommand() {
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "export", "--shell", "fish"]).unwrap();
+        let expected = SubCommand::Export(ExportCommand { shell: Shell::Fish });
+        assert_eq!(request.command, expected);
+    }
+
+    #[test]
+    fn completions_subcommand() {
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "completions", "zsh"]).unwrap();
+        assert_eq!(request.command, SubCommand::Completions(Shell::Zsh));
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "complete", "choices"]).unwrap();
+        assert_eq!(
+            request.command,
+            SubCommand::Complete(CompleteCommand::Choices)
+        );
+        let script = completion_script(Shell::Bash);
+        assert!(script.contains("complete -F _sam_dynamic"));
+        assert!(script.contains("cache-keys-delete"));
+    }
+
+    #[test]
+    fn list_subcommand_with_json_output() {
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "--output", "json", "list"]).unwrap();
+        assert_eq!(
+            request.command,
+            SubCommand::ListCommand(ListCommand::Aliases(ListFilter::default()))
+        );
+        assert_eq!(request.settings.output, OutputFormat::Json);
+    }
+
+    #[test]
+    fn list_subcommand_with_filters() {
+        let app = app_init();
+        let request = make_cli_request(
+            app,
+            [
+                "sam",
+                "list",
+                "vars",
+                "--namespace",
+                "k8s",
+                "--tag",
+                "prod,eu",
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            request.command,
+            SubCommand::ListCommand(ListCommand::Vars(ListFilter {
+                namespace: Some(String::from("k8s")),
+                tags: vec![String::from("prod"), String::from("eu")],
+            }))
+        );
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "list", "-n", "k8s"]).unwrap();
+        assert_eq!(
+            request.command,
+            SubCommand::ListCommand(ListCommand::Aliases(ListFilter {
+                namespace: Some(String::from("k8s")),
+                tags: vec![],
+            }))
+        );
+    }
+
+    #[test]
+    fn list_subcommand_with_hidden_aliases() {
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "--all", "list"]).unwrap();
+        assert_eq!(
+            request.command,
+            SubCommand::ListCommand(ListCommand::Aliases(ListFilter::default()))
+        );
+        assert!(request.settings.all);
+    }
+
+    #[test]
+    fn list_subcommand_with_impacts() {
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "--impact", "low,medium", "list"]).unwrap();
+        assert_eq!(request.settings.impacts, vec![Impact::Low, Impact::Medium]);
+    }
+
+    #[test]
+    fn run_subcommand_with_tags() {
+        let app = app_init();
+        let request =
+            make_cli_request(app, ["sam", "--tag", "k8s,prod", "--tag", "eu", "run"]).unwrap();
+        assert_eq!(request.settings.tags, vec!["k8s", "prod", "eu"]);
+    }
+
+    #[test]
+    fn audit_subcommand() {
+        let app = app_init();
+        let request =
+            make_cli_request(app, ["sam", "audit", "serve", "--listen", "0.0.0.0:7878"]).unwrap();
+        assert_eq!(
+            request.command,
+            SubCommand::AuditCommand(AuditCommand::Serve {
+                listen: Listen::Tcp(String::from("0.0.0.0:7878")),
+                store: None,
+            })
+        );
+        let app = app_init();
+        let request =
+            make_cli_request(app, ["sam", "audit", "query", "--alias", "k8s::pods"]).unwrap();
+        assert_eq!(
+            request.command,
+            SubCommand::AuditCommand(AuditCommand::Query {
+                alias: Some(Identifier::with_namespace("pods", Some("k8s"))),
+                source: None,
+                store: None,
+            })
+        );
+    }
+
+    #[test]
+    fn new_subcommand() {
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "new", "alias", "k8s::pods"]).unwrap();
+        assert_eq!(
+            request.command,
+            SubCommand::Scaffold(ScaffoldCommand::NewAlias(Some(String::from("k8s::pods"))))
+        );
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "new", "var"]).unwrap();
+        assert_eq!(
+            request.command,
+            SubCommand::Scaffold(ScaffoldCommand::NewVar(None))
+        );
+    }
+
+    #[test]
+    fn last_log_subcommand() {
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "--output", "json", "last-log"]).unwrap();
+        assert_eq!(request.command, SubCommand::LastLog);
+        assert_eq!(request.settings.output, OutputFormat::Json);
+    }
+
+    #[test]
+    fn edit_subcommand() {
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "edit", "k8s::pods"]).unwrap();
+        assert_eq!(request.command, SubCommand::Edit(String::from("k8s::pods")));
+    }
+
+    #[test]
+    fn alias_subcommand_with_arguments() {
+        let app = app_init();
+        let request = make_cli_request(
+            app,
+            [
+                "sam",
+                "alias",
+                "k8s::deploy",
+                "-c",
+                "k8s::env=prod",
+                "--",
+                "api",
+                "v1.2",
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            request.settings.default_choices,
+            DefaultChoices(hashmap! {
+                Identifier::with_namespace("env", Some("k8s")) => vec![Choice::from_value("prod")],
+                Identifier::argument(1) => vec![Choice::from_value("api")],
+                Identifier::argument(2) => vec![Choice::from_value("v1.2")],
+            })
+        );
+
+        // the functions of `sam export` always pass `--`, with or without arguments.
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "alias", "k8s::deploy", "--"]).unwrap();
+        assert_eq!(
+            request.command,
+            SubCommand::SamCommand(SamCommand::ExecuteAlias {
+                alias: Identifier::with_namespace("deploy", Some("k8s"))
+            })
+        );
+        assert_eq!(
+            request.settings.default_choices,
+            DefaultChoices(hashmap! {})
+        );
+    }
+
+    #[test]
+    fn record_and_replay_subcommands() {
+        let app = app_init();
+        let request = make_cli_request(
+            app,
+            [
+                "sam",
+                "record",
+                "deploy-prod",
+                "k8s::rollout",
+                "-c",
+                "k8s::env=prod",
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            request.command,
+            SubCommand::Run(RunCommand::Record {
+                name: String::from("deploy-prod"),
+                alias: Some(Identifier::with_namespace("rollout", Some("k8s"))),
+            })
+        );
+        assert_eq!(
+            request.settings.default_choices,
+            DefaultChoices(hashmap! {
+                Identifier::with_namespace("env", Some("k8s")) => vec![Choice::from_value("prod")],
+            })
+        );
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "replay", "deploy-prod"]).unwrap();
+        assert_eq!(
+            request.command,
+            SubCommand::Run(RunCommand::Replay(String::from("deploy-prod")))
+        );
+    }
+
+    #[test]
+    fn resolve_subcommand() {
+        let app = app_init();
+        let request =
+            make_cli_request(app, ["sam", "resolve", "k8s::logs", "-c", "k8s::env=prod"]).unwrap();
+        assert_eq!(
+            request.command,
+            SubCommand::SamCommand(SamCommand::ResolveAlias {
+                alias: Identifier::with_namespace("logs", Some("k8s")),
+            })
+        );
+        assert_eq!(
+            request.settings.default_choices,
+            DefaultChoices(hashmap! {
+                Identifier::with_namespace("env", Some("k8s")) => vec![Choice::from_value("prod")],
+            })
+        );
+    }
+
+    #[test]
+    fn resolve_var_subcommand() {
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "resolve-var", "k8s::env", "--print"]).unwrap();
+        assert_eq!(
+            request.command,
+            SubCommand::ResolveVar(ResolveVarCommand {
+                var: String::from("k8s::env"),
+                output: ResolveOutput::Values,
+            })
+        );
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "-o", "json", "resolve-var", "env"]).unwrap();
+        assert_eq!(
+            request.command,
+            SubCommand::ResolveVar(ResolveVarCommand {
+                var: String::from("env"),
+                output: ResolveOutput::Json,
+            })
+        );
+    }
+
+    #[test]
+    fn tutorial_subcommand() {
+        let app = app_init();
+        let request = make_cli_request(app, ["sam", "tutorial"]);
+        assert_eq!(request.unwrap().command, SubCommand::Tutorial);
+    }
+
     #[test]
     fn no_subcommand() {
         let app = app_init();
@@ -275,8 +1385,20 @@ mod tests {
             command: SubCommand::SamCommand(SamCommand::ChooseAndExecuteAlias {}),
             settings: CLISettings {
                 dry: false,
+                diff: false,
+                record: false,
+                quote: false,
+                all: false,
                 silent: false,
                 no_cache: false,
+                no_pager: false,
+                non_interactive: false,
+                use_defaults: false,
+                answers_file: None,
+                jobs: None,
+                impacts: vec![],
+                tags: vec![],
+                output: OutputFormat::Text,
                 default_choices: DefaultChoices(hashmap! {
                 Identifier::with_namespace("some_choice", Some("some_ns")) => vec![Choice::from_value("value")],
                 Identifier::with_namespace("some_other_choice", Some("some_ns")) => vec![Choice::from_value("value2")],
@@ -300,8 +1422,20 @@ mod tests {
             command: SubCommand::SamCommand(SamCommand::ChooseAndExecuteAlias {}),
             settings: CLISettings {
                 dry: false,
+                diff: false,
+                record: false,
+                quote: false,
+                all: false,
                 silent: false,
                 no_cache: false,
+                no_pager: false,
+                non_interactive: false,
+                use_defaults: false,
+                answers_file: None,
+                jobs: None,
+                impacts: vec![],
+                tags: vec![],
+                output: OutputFormat::Text,
                 default_choices: DefaultChoices(hashmap! {
                 Identifier::with_namespace("some_choice", Some("some_ns")) => vec![Choice::from_value("value")],
                 Identifier::with_namespace("some_other_choice", Some("some_ns")) => vec![Choice::from_value("value2")],
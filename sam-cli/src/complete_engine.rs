@@ -0,0 +1,117 @@
+use sam_core::entities::aliases::Alias;
+use sam_core::entities::vars::Var;
+use sam_persistence::repositories::{AliasesRepository, VarsRepository};
+
+/// CompleteCommand lists the aliases or the choices of the vars for completion scripts,
+/// in a machine readable format: one entry per line, the value and its description
+/// separated by a tab.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompleteCommand {
+    Aliases,
+    Choices,
+}
+
+pub struct CompleteEngine {
+    pub aliases: AliasesRepository,
+    pub vars: VarsRepository,
+}
+
+impl CompleteEngine {
+    pub fn run(&self, cmd: CompleteCommand) -> i32 {
+        let lines = match cmd {
+            CompleteCommand::Aliases => {
+                let mut aliases: Vec<Alias> = self
+                    .aliases
+                    .visible_aliases()
+                    .into_iter()
+                    .cloned()
+                    .collect();
+                aliases.sort_by_key(|a| a.full_name().to_string());
+                alias_lines(&aliases)
+            }
+            CompleteCommand::Choices => {
+                let mut vars: Vec<&Var> = self.vars.vars_iter().collect();
+                vars.sort_by_key(|v| v.name());
+                choice_lines(vars.into_iter())
+            }
+        };
+        for line in lines {
+            println!("{}", line);
+        }
+        0
+    }
+}
+
+fn alias_lines(aliases: &[Alias]) -> Vec<String> {
+    aliases
+        .iter()
+        .map(|a| format!("{}\t{}", a.full_name(), single_line(a.desc())))
+        .collect()
+}
+
+// vars with static choices are listed once per choice, the others
+// are listed without a value so that only the identifier gets completed.
+fn choice_lines<'a>(vars: impl Iterator<Item = &'a Var>) -> Vec<String> {
+    let mut lines = vec![];
+    for var in vars {
+        let choices = var.choices();
+        if choices.is_empty() {
+            lines.push(format!("{}=\t{}", var.name(), single_line(var.desc())));
+        }
+        for choice in choices {
+            lines.push(format!(
+                "{}={}\t{}",
+                var.name(),
+                choice.value(),
+                single_line(choice.desc().unwrap_or_else(|| var.desc()))
+            ));
+        }
+    }
+    lines
+}
+
+fn single_line(s: &str) -> String {
+    s.replace(['\n', '\t'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{alias_lines, choice_lines};
+    use sam_core::entities::aliases::Alias;
+    use sam_core::entities::choices::Choice;
+    use sam_core::entities::namespaces::NamespaceUpdater;
+    use sam_core::entities::vars::Var;
+
+    #[test]
+    fn test_alias_lines() {
+        let mut alias = Alias::new("list", "list's a\ndirectory", "ls");
+        NamespaceUpdater::update(&mut alias, "dirs");
+        assert_eq!(
+            alias_lines(&[alias]),
+            vec!["dirs::list\tlist's a directory"]
+        );
+    }
+
+    #[test]
+    fn test_choice_lines() {
+        let mut directory = Var::new(
+            "directory",
+            "a directory",
+            vec![
+                Choice::new("/tmp", Some("temporary files")),
+                Choice::new("/etc", None),
+            ],
+        );
+        NamespaceUpdater::update(&mut directory, "dirs");
+        let mut file = Var::from_command("file", "a file", "ls -1");
+        NamespaceUpdater::update(&mut file, "dirs");
+        assert_eq!(
+            choice_lines(vec![&directory, &file].into_iter()),
+            vec![
+                "dirs::directory=/tmp\ttemporary files",
+                "dirs::directory=/etc\ta directory",
+                "dirs::file=\ta file",
+            ]
+        );
+    }
+}
@@ -1,14 +1,26 @@
-use crate::cli::CLISettings;
+use crate::cli::{CLISettings, OutputFormat};
+use crate::shell_history::ShellHistory;
+use sam_core::entities::aliases::Impact;
 use sam_core::entities::choices::Choice;
+use sam_core::entities::conditions::matches_os;
 use sam_core::entities::identifiers::Identifier;
+use sam_core::entities::namespaces::NamespaceAliases;
 use sam_persistence::CacheError;
+use sam_readers::{NamespaceStrategy, Namespaces, Warning};
+use sam_terminals::processes::{ErrorsShell, Shell};
+use sam_terminals::streams::detach_stdin;
+use sam_tui::modal_view::ThemeSettings;
+use sam_tui::Picker;
 use sam_utils::fsutils;
 use sam_utils::fsutils::walk_dir;
 use sam_utils::fsutils::ErrorsFS;
+use sam_utils::interpolation::interpolate;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::env::consts::OS;
 use std::fs;
-use std::io;
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 use thiserror::Error;
@@ -16,6 +28,12 @@ use thiserror::Error;
 const CONFIG_FILE_NAME: &str = ".sam_rc.toml";
 const HISTORY_DIR: &str = ".local/share/sam/";
 const CACHE_DIR: &str = ".cache/";
+// written once the tutorial was shown, so that it's only offered once.
+const TUTORIAL_MARKER: &str = "tutorial_seen";
+const TRACES_DIR: &str = "traces";
+const PROFILE_ENV_VAR: &str = "SAM_PROFILE";
+const CHOICE_ENV_PREFIX: &str = "SAM_CHOICE_";
+const DEFAULT_TTL: u64 = 1800;
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct AppSettings {
@@ -27,61 +45,372 @@ pub struct AppSettings {
     cache_dir: PathBuf,
     #[serde(skip)]
     history_file: PathBuf,
+    /// show what changed since the previous execution of an alias before running it.
+    #[serde(default)]
+    pub diff: bool,
+    /// the program used to select aliases and choices, builtin, fzf or skim.
+    #[serde(default)]
+    pub picker: Picker,
+    /// the colors of sam's chooser, `[theme]` with a name, dark, light or solarized, and the
+    /// colors of the highlighted line, the marked items, the preview header and the prompt.
+    #[serde(default)]
+    pub theme: ThemeSettings,
+    /// the shell, bash, zsh or fish, whose history the commands run by sam are appended to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell_history: Option<ShellHistory>,
+    /// the history file of the shell, the default one of the shell otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell_history_file: Option<PathBuf>,
+    /// the namespaces whose aliases and vars are left out.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_namespaces: Vec<String>,
+    /// how the namespaces of the aliases and vars are found, directory, path, header or none.
+    #[serde(default)]
+    pub namespace_strategy: NamespaceStrategy,
+    /// other names of the namespaces, `[namespace_aliases]` with `k8s = "kubernetes"`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    namespace_aliases: HashMap<String, String>,
+    /// the shell the commands run with, `zsh -c`, the one of the user otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+    /// run the commands in a login shell, which reads the profile of the user first.
+    #[serde(default)]
+    pub login_shell: bool,
+    /// the profile, a client or an environment, whose cache and history are used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    profile: Option<String>,
     #[serde(skip)]
     pub dry: bool,
+    /// record what the aliases print in the history, `--record`.
+    #[serde(skip)]
+    pub record: bool,
+    #[serde(skip)]
+    pub quote: bool,
+    #[serde(skip)]
+    pub all: bool,
     #[serde(skip)]
     pub silent: bool,
     #[serde(skip)]
     pub no_cache: bool,
     #[serde(skip)]
+    pub no_pager: bool,
+    #[serde(skip)]
+    pub non_interactive: bool,
+    /// use the default of the vars that declare one, `--use-defaults`.
+    #[serde(skip)]
+    pub use_defaults: bool,
+    #[serde(skip)]
+    pub jobs: Option<usize>,
+    #[serde(skip)]
+    pub impacts: Vec<Impact>,
+    #[serde(skip)]
+    pub tags: Vec<String>,
+    #[serde(skip)]
+    pub output: OutputFormat,
+    #[serde(skip)]
     pub defaults: HashMap<Identifier, Vec<Choice>>,
+    /// the deprecated settings and the shadowed defaults found in the configuration files.
+    #[serde(skip)]
+    warnings: Vec<Warning>,
+}
+
+/// SettingsFile is a configuration file, the settings it leaves out are inherited
+/// from the files it's layered on top of.
+#[derive(Debug, Deserialize, Default, Clone)]
+struct SettingsFile {
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(default)]
+    root_dir: Vec<PathBuf>,
+    cache_ttl: Option<u64>,
+    /// deprecated, cache_ttl is read instead when both are set.
+    ttl: Option<u64>,
+    diff: Option<bool>,
+    picker: Option<Picker>,
+    theme: Option<ThemeSettings>,
+    shell_history: Option<ShellHistory>,
+    shell_history_file: Option<PathBuf>,
+    #[serde(default)]
+    disabled_namespaces: Vec<String>,
+    namespace_strategy: Option<NamespaceStrategy>,
+    /// other names of the namespaces, `k8s = "kubernetes"`.
+    #[serde(default)]
+    namespace_aliases: HashMap<String, String>,
+    shell: Option<String>,
+    login_shell: Option<bool>,
+    profile: Option<String>,
+    /// the choices used for vars instead of prompting for them, `"k8s::env" = "prod"`.
+    #[serde(default)]
+    defaults: HashMap<String, String>,
+    /// settings that only apply on some operating systems, `[target.'cfg(macos)']`.
+    #[serde(default)]
+    target: HashMap<String, TargetSettings>,
+    #[serde(flatten)]
+    env_variables: HashMap<String, String>,
+}
+
+/// TargetSettings are added to, or override, the settings on the operating systems they target.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+struct TargetSettings {
+    #[serde(default)]
+    root_dir: Vec<PathBuf>,
+    cache_ttl: Option<u64>,
+    ttl: Option<u64>,
+    diff: Option<bool>,
+    picker: Option<Picker>,
+    shell: Option<String>,
+    login_shell: Option<bool>,
+    #[serde(flatten)]
+    env_variables: HashMap<String, String>,
 }
 
 type Result<T> = std::result::Result<T, ErrorsSettings>;
 
-impl AppSettings {
-    fn read_config(path: PathBuf) -> Result<AppSettings> {
+impl SettingsFile {
+    fn read(path: PathBuf) -> Result<SettingsFile> {
         let path = fsutils::ensure_exists(path)
             .and_then(fsutils::ensure_is_file)
             .and_then(fsutils::ensure_sufficient_permisions)?;
         let content = fs::read_to_string(&path)?;
-        let conf: AppSettings = toml::from_str(content.as_str())?;
-        Ok(conf)
+        let file: SettingsFile = toml::from_str(&interpolate(&content))?;
+        Ok(SettingsFile { path, ..file }.for_os(OS))
+    }
+
+    /// for_os applies the target sections matching the operating system, `cfg(linux)`
+    /// or `cfg(linux | macos)`, in the alphabetical order of their names.
+    fn for_os(mut self, os: &str) -> Self {
+        let mut targets: Vec<(String, TargetSettings)> = self.target.drain().collect();
+        targets.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (cfg, target) in targets {
+            let matches = cfg
+                .trim()
+                .strip_prefix("cfg(")
+                .and_then(|c| c.strip_suffix(')'))
+                .is_some_and(|c| matches_os(c, os));
+            if !matches {
+                continue;
+            }
+            self.root_dir.extend(target.root_dir);
+            self.cache_ttl = target.cache_ttl.or(target.ttl).or(self.cache_ttl);
+            self.ttl = target.ttl.or(self.ttl);
+            self.diff = target.diff.or(self.diff);
+            self.picker = target.picker.or(self.picker);
+            self.shell = target.shell.or(self.shell);
+            self.login_shell = target.login_shell.or(self.login_shell);
+            self.env_variables.extend(target.env_variables);
+        }
+        self
+    }
+
+    /// relative_to makes the relative root directories relative to the directory of the file.
+    fn relative_to(mut self, dir: &Path) -> Self {
+        for root_dir in self.root_dir.iter_mut() {
+            if root_dir.is_relative() {
+                *root_dir = dir.join(&root_dir);
+            }
+        }
+        self
+    }
+}
+
+impl AppSettings {
+    /// from_files layers the configuration files, from the lowest precedence to the highest.
+    /// Root directories and disabled namespaces are added to the ones of the previous files, key value pairs,
+    /// namespace aliases and defaults override the ones with the same name and the other settings replace the
+    /// previous ones. A default overriding the one of a previous file with another value is reported as shadowed.
+    fn from_files(files: impl IntoIterator<Item = SettingsFile>) -> AppSettings {
+        let mut settings = AppSettings {
+            ttl: DEFAULT_TTL,
+            ..AppSettings::default()
+        };
+        for file in files {
+            settings.root_dir.extend(file.root_dir);
+            if file.ttl.is_some() {
+                settings.warnings.push(Warning::DeprecatedField {
+                    field: String::from("ttl"),
+                    replacement: String::from("cache_ttl"),
+                    source_file: file.path.clone(),
+                });
+            }
+            settings.ttl = file.cache_ttl.or(file.ttl).unwrap_or(settings.ttl);
+            settings.diff = file.diff.unwrap_or(settings.diff);
+            settings.picker = file.picker.unwrap_or(settings.picker);
+            settings.theme = file.theme.unwrap_or(settings.theme);
+            settings.shell_history = file.shell_history.or(settings.shell_history);
+            settings.shell_history_file = file.shell_history_file.or(settings.shell_history_file);
+            settings
+                .disabled_namespaces
+                .extend(file.disabled_namespaces);
+            settings.namespace_strategy = file
+                .namespace_strategy
+                .unwrap_or(settings.namespace_strategy);
+            settings.namespace_aliases.extend(file.namespace_aliases);
+            settings.shell = file.shell.or(settings.shell);
+            settings.login_shell = file.login_shell.unwrap_or(settings.login_shell);
+            settings.profile = file.profile.or(settings.profile);
+            settings.env_variables.extend(file.env_variables);
+            let mut defaults: Vec<(String, String)> = file.defaults.into_iter().collect();
+            defaults.sort();
+            for (id, value) in defaults {
+                let id = Identifier::from_str(&id);
+                let choices = vec![Choice::from_value(value)];
+                let previous = settings.defaults.insert(id.clone(), choices.clone());
+                if previous.is_some_and(|previous| previous != choices) {
+                    settings.warnings.push(Warning::ShadowedDefault {
+                        identifier: id,
+                        source_file: file.path.clone(),
+                    });
+                }
+            }
+        }
+        settings
+    }
+
+    /// config_files returns the configuration files sam reads, from the lowest precedence to
+    /// the highest: the one in the home directory, the one at the root of the git repository
+    /// the current directory belongs to, and the one in the current directory.
+    fn config_files() -> Result<Vec<PathBuf>> {
+        let current_dir =
+            std::env::current_dir().map_err(|_| ErrorsSettings::CantFindCurrentDirectory)?;
+        let mut files = vec![Self::home_dir_config_path()?];
+        if let Some(git_root) = git_root(&current_dir) {
+            files.push(git_root.join(CONFIG_FILE_NAME));
+        }
+        files.push(current_dir.join(CONFIG_FILE_NAME));
+        let mut seen = HashSet::new();
+        files.retain(|f| seen.insert(f.clone()));
+        Ok(files)
     }
 
     pub fn load(cli_settings: Option<CLISettings>) -> Result<Self> {
-        let home_dir_o = Self::home_dir_config_path()?;
-        let current_dir_o = Self::current_dir_config_path();
-
-        let config_home_dir = Self::read_config(home_dir_o);
-        let config_current_dir = current_dir_o.and_then(Self::read_config);
-
-        let cache_dir =
-            Self::file_path_with_suffix(CACHE_DIR, "sam", ErrorsSettings::CantFindCurrentDirectory)?;
-        let history_file =
-            Self::file_path_with_suffix(HISTORY_DIR, "history", ErrorsSettings::CantFindHistoryDirectory(HISTORY_DIR.to_string()))?;
-
-        let mut settings = config_current_dir
-            .or(config_home_dir)
-            .and_then(AppSettings::validate)
-            .map(|mut e| {
-                e.cache_dir = cache_dir;
-                e.history_file = history_file;
-                e
-            })?;
+        let home_config = Self::home_dir_config_path()?;
+        let mut files = vec![];
+        for path in Self::config_files()? {
+            if path == home_config {
+                // its relative root directories stay relative to the current directory.
+                files.push(SettingsFile::read(path));
+            } else if path.exists() {
+                let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+                files.push(SettingsFile::read(path).map(|f| f.relative_to(&dir)));
+            }
+        }
+        // the home configuration is optional as long as a project provides one.
+        if files.len() > 1 {
+            files.retain(|f| {
+                !matches!(
+                    f,
+                    Err(ErrorsSettings::FileSystem(ErrorsFS::PathDoesNotExist(_)))
+                )
+            });
+        }
+        let files = files.into_iter().collect::<Result<Vec<SettingsFile>>>()?;
+
+        let cache_dir = Self::file_path_with_suffix(
+            CACHE_DIR,
+            "sam",
+            ErrorsSettings::CantFindCurrentDirectory,
+        )?;
+        let history_file = Self::file_path_with_suffix(
+            HISTORY_DIR,
+            "history",
+            ErrorsSettings::CantFindHistoryDirectory(HISTORY_DIR.to_string()),
+        )?;
+
+        let mut settings = AppSettings::from_files(files);
+        if let Some(profile) = env::var(PROFILE_ENV_VAR).ok().filter(|p| !p.is_empty()) {
+            settings.profile = Some(profile);
+        }
+        settings
+            .defaults
+            .extend(env_defaults(env::vars_os().filter_map(|(k, v)| {
+                Some((k.into_string().ok()?, v.into_string().ok()?))
+            })));
+        let profile = settings.profile.clone();
+        let mut settings = AppSettings::validate(settings).map(|mut e| {
+            e.cache_dir = profile_path(cache_dir, profile.as_deref());
+            e.history_file = profile_path(history_file, profile.as_deref());
+            e
+        })?;
 
         if let Some(m) = cli_settings {
+            if let Some(path) = &m.answers_file {
+                settings.defaults.extend(read_answers(path)?);
+            }
             settings.merge_command_line_args(m);
         }
 
         Ok(settings)
     }
 
+    /// is_first_run returns true when no configuration file can be found
+    /// in the home directory, the git repository or the current directory,
+    /// and the tutorial wasn't shown yet.
+    pub fn is_first_run() -> bool {
+        let no_config = Self::config_files()
+            .map(|files| !files.iter().any(|f| f.exists()))
+            .unwrap_or(true);
+        let tutorial_seen = Self::tutorial_marker().is_some_and(|marker| marker.exists());
+        no_config && !tutorial_seen
+    }
+
+    /// mark_tutorial_seen records that the tutorial was shown, it isn't launched on its own anymore.
+    pub fn mark_tutorial_seen() -> io::Result<()> {
+        match Self::tutorial_marker() {
+            Some(marker) => {
+                if let Some(dir) = marker.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                fs::write(marker, "")
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn tutorial_marker() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(HISTORY_DIR).join(TUTORIAL_MARKER))
+    }
+
+    /// bootstrap writes a minimal configuration file in the home directory that
+    /// points to the provided root directory and creates the directories used
+    /// for the cache and the history. It returns the path of the configuration file.
+    pub fn bootstrap(root_dir: &Path) -> Result<PathBuf> {
+        let home_dir = dirs::home_dir().ok_or(ErrorsSettings::CantFindHomeDirectory)?;
+        fs::create_dir_all(home_dir.join(HISTORY_DIR))?;
+        fs::create_dir_all(home_dir.join(CACHE_DIR))?;
+        let config_path = Self::home_dir_config_path()?;
+        let content = format!(
+            "# the locations of your aliases and vars\n\
+             root_dir = [{:?}]\n\
+             # the time in seconds for which sam keeps the output of `from_command` vars in its cache\n\
+             cache_ttl = {}\n",
+            root_dir.display().to_string(),
+            DEFAULT_TTL
+        );
+        fs::write(&config_path, content)?;
+        Ok(config_path)
+    }
+
     fn merge_command_line_args(&mut self, cmd_args: CLISettings) {
         self.dry = cmd_args.dry;
+        self.diff = self.diff || cmd_args.diff;
+        self.record = cmd_args.record;
+        self.quote = cmd_args.quote;
+        self.all = cmd_args.all;
         self.silent = cmd_args.silent;
         self.no_cache = cmd_args.no_cache;
-        self.defaults = cmd_args.default_choices.0;
+        self.no_pager = cmd_args.no_pager;
+        self.non_interactive = cmd_args.non_interactive;
+        self.use_defaults = cmd_args.use_defaults;
+        self.jobs = cmd_args.jobs;
+        self.impacts = cmd_args.impacts;
+        self.tags = cmd_args.tags;
+        self.output = cmd_args.output;
+        self.defaults.extend(cmd_args.default_choices.0);
+    }
+
+    /// warnings returns the deprecated settings and the shadowed defaults of the configuration files.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
     }
 
     pub const fn ttl(&self) -> Duration {
@@ -96,7 +425,22 @@ impl AppSettings {
         self.history_file.as_ref()
     }
 
+    /// traces_dir is where the traces of the runs are written, next to the history. It's
+    /// known before the settings are loaded, so the traces of all the profiles are kept there.
+    pub fn traces_dir() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(HISTORY_DIR).join(TRACES_DIR))
+    }
+
     fn validate(orig: AppSettings) -> Result<AppSettings> {
+        orig.shell()?;
+        if let Some(profile) = &orig.profile {
+            let valid = profile
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid {
+                return Err(ErrorsSettings::InvalidProfile(profile.clone()));
+            }
+        }
         for path in &orig.root_dir {
             if let Ok(files) = fsutils::walk_dir(path) {
                 for f in files {
@@ -120,16 +464,39 @@ impl AppSettings {
             .ok_or(err)
     }
 
-    fn current_dir_config_path() -> Result<PathBuf> {
-        std::env::current_dir()
-            .map_err(|_| ErrorsSettings::CantFindCurrentDirectory)
-            .map(|e| e.join(CONFIG_FILE_NAME))
+    pub fn root_dirs(&self) -> &[PathBuf] {
+        &self.root_dir
+    }
+
+    /// is_disabled tells whether the aliases and vars of a namespace are left out.
+    pub fn is_disabled(&self, namespace: Option<&str>) -> bool {
+        namespace.is_some_and(|ns| self.disabled_namespaces.iter().any(|d| d == ns))
     }
+
+    /// namespaces finds the namespaces of the files of the root directories.
+    pub fn namespaces(&self) -> Namespaces {
+        Namespaces::new(self.namespace_strategy, self.root_dir.iter().cloned())
+    }
+
+    pub fn namespace_aliases(&self) -> NamespaceAliases {
+        NamespaceAliases::new(self.namespace_aliases.clone())
+    }
+
+    /// shell returns the shell the commands run with, the one of the user unless the
+    /// configuration sets one.
+    pub fn shell(&self) -> Result<Shell> {
+        let shell = match &self.shell {
+            Some(shell) => Shell::parse(shell)?,
+            None => Shell::user(),
+        };
+        Ok(shell.with_login(self.login_shell))
+    }
+
     pub fn variables(&self) -> HashMap<String, String> {
         self.env_variables.clone()
     }
 
-    fn sam_files(&self) -> impl Iterator<Item=PathBuf> + '_ {
+    fn sam_files(&self) -> impl Iterator<Item = PathBuf> + '_ {
         self.root_dir
             .iter()
             .map(AsRef::as_ref)
@@ -137,27 +504,40 @@ impl AppSettings {
             .flatten()
     }
 
-    pub fn aliases_files(&self) -> impl Iterator<Item=PathBuf> + '_ {
-        self.sam_files().filter(|f| {
-            if let Some(file_name) = f.file_name() {
-                file_name == "aliases.yaml" || file_name == "aliases.yml"
-            } else {
-                false
-            }
-        })
+    pub fn aliases_files(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.sam_files().filter(|f| is_aliases_file(f))
     }
 
-    pub fn vars_files(&self) -> impl Iterator<Item=PathBuf> + '_ {
-        self.sam_files().filter(|f| {
-            if let Some(file_name) = f.file_name() {
-                file_name == "vars.yaml" || file_name == "vars.yml"
-            } else {
-                false
-            }
-        })
+    pub fn vars_files(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.sam_files().filter(|f| is_vars_file(f))
+    }
+
+    /// empty_root_dirs returns the root directories without any aliases or vars file,
+    /// the ones that don't exist included.
+    pub fn empty_root_dirs(&self) -> Vec<PathBuf> {
+        self.root_dir
+            .iter()
+            .filter(|dir| {
+                !walk_dir(dir)
+                    .unwrap_or_default()
+                    .iter()
+                    .any(|f| is_aliases_file(f) || is_vars_file(f))
+            })
+            .cloned()
+            .collect()
     }
 }
 
+fn is_aliases_file(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name == "aliases.yaml" || name == "aliases.yml")
+}
+
+fn is_vars_file(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name == "vars.yaml" || name == "vars.yml")
+}
+
 #[derive(Debug, Error)]
 pub enum ErrorsSettings {
     #[error("got deserialize the configuration file because\n-> {0}")]
@@ -166,6 +546,12 @@ pub enum ErrorsSettings {
     CantReadConfigFile(#[from] io::Error),
     #[error("got the following file-system related error\n-> {0}")]
     FileSystem(#[from] ErrorsFS),
+    #[error("{0}")]
+    Shell(#[from] ErrorsShell),
+    #[error("the answers file {} is invalid\n-> {1}", .0.display())]
+    InvalidAnswers(PathBuf, String),
+    #[error("invalid profile '{0}', allowed characters are [a-zA-Z0-9_-]")]
+    InvalidProfile(String),
     #[error("could not initialize the cache\n-> {0}")]
     VarsCache(#[from] CacheError),
     #[error("we were unable to locate the home directory for the current user")]
@@ -174,6 +560,362 @@ pub enum ErrorsSettings {
     CantFindCacheDirectory,
     #[error("we were unable to locate the current directory for the current user")]
     CantFindCurrentDirectory,
-    #[error("we were unable to locate the history directory for the current user, make sure {0} exists")]
+    #[error(
+        "we were unable to locate the history directory for the current user, make sure {0} exists"
+    )]
     CantFindHistoryDirectory(String),
 }
+
+/// env_defaults reads the choices given as `SAM_CHOICE_<ns>__<var>` environment variables,
+/// `SAM_CHOICE_k8s__env=prod` for `k8s::env`. The single underscore of the variables sam
+/// exports to the commands of the aliases keeps them from being read back.
+fn env_defaults(
+    variables: impl Iterator<Item = (String, String)>,
+) -> HashMap<Identifier, Vec<Choice>> {
+    variables
+        .filter_map(|(key, value)| {
+            let (namespace, name) = key.strip_prefix(CHOICE_ENV_PREFIX)?.split_once("__")?;
+            if namespace.is_empty() || name.is_empty() {
+                return None;
+            }
+            let id = Identifier::with_namespace(name, Some(namespace));
+            Some((id, vec![Choice::from_value(value)]))
+        })
+        .collect()
+}
+
+/// read_answers reads the choices of the vars from an answers file, `-` reads stdin. It maps
+/// each var to a value, or to a list of them for the vars taking several choices. The
+/// choices of the entries of `sam history export` can be used as they are.
+fn read_answers(path: &Path) -> Result<HashMap<Identifier, Vec<Choice>>> {
+    let invalid = |err: String| ErrorsSettings::InvalidAnswers(path.to_path_buf(), err);
+    let content = if path == Path::new("-") {
+        let mut content = String::new();
+        io::stdin().read_to_string(&mut content)?;
+        // what was piped is used up, the aliases shouldn't be offered it.
+        detach_stdin()?;
+        content
+    } else {
+        fs::read_to_string(path)?
+    };
+    let answers: HashMap<String, serde_yaml::Value> =
+        serde_yaml::from_str(&content).map_err(|err| invalid(err.to_string()))?;
+    answers
+        .into_iter()
+        .map(|(id, answer)| {
+            let choices = answer_choices(answer)
+                .ok_or_else(|| invalid(format!("the answer for {} is invalid", id)))?;
+            Ok((Identifier::from_str(&id), choices))
+        })
+        .collect()
+}
+
+/// answer_choices reads the choices of a var in an answers file, a value or a list of them.
+pub fn answer_choices(answer: serde_yaml::Value) -> Option<Vec<Choice>> {
+    match answer {
+        serde_yaml::Value::Sequence(values) => values.into_iter().map(answer_choice).collect(),
+        value => answer_choice(value).map(|c| vec![c]),
+    }
+}
+
+// answer_choice reads a value of an answers file, a string, a number, a boolean or a
+// choice as it's exported with the history.
+fn answer_choice(value: serde_yaml::Value) -> Option<Choice> {
+    match value {
+        serde_yaml::Value::String(s) => Some(Choice::from_value(s)),
+        serde_yaml::Value::Number(n) => Some(Choice::from_value(n.to_string())),
+        serde_yaml::Value::Bool(b) => Some(Choice::from_value(b.to_string())),
+        value @ serde_yaml::Value::Mapping(_) => serde_yaml::from_value(value).ok(),
+        _ => None,
+    }
+}
+
+// profile_path gives each profile its own file, `history-client` for `history`.
+fn profile_path(path: PathBuf, profile: Option<&str>) -> PathBuf {
+    match (profile, path.file_name().and_then(|n| n.to_str())) {
+        (Some(profile), Some(name)) => {
+            let name = format!("{}-{}", name, profile);
+            path.with_file_name(name)
+        }
+        _ => path,
+    }
+}
+
+// git_root returns the root of the git repository the directory belongs to, if any.
+fn git_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|d| d.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{env_defaults, git_root, profile_path, read_answers, AppSettings, SettingsFile};
+    use sam_core::entities::choices::Choice;
+    use sam_core::entities::identifiers::Identifier;
+    use sam_readers::{NamespaceStrategy, Warning};
+    use sam_terminals::processes::Shell;
+    use sam_tui::modal_view::ThemeName;
+    use sam_tui::Picker;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_profile_path() {
+        let history = PathBuf::from("/home/me/.local/share/sam/history");
+        assert_eq!(profile_path(history.clone(), None), history);
+        assert_eq!(
+            profile_path(history, Some("client-a")),
+            PathBuf::from("/home/me/.local/share/sam/history-client-a")
+        );
+
+        let settings = AppSettings {
+            profile: Some(String::from("../other")),
+            ..AppSettings::default()
+        };
+        assert!(AppSettings::validate(settings).is_err());
+    }
+
+    #[test]
+    fn test_env_defaults() {
+        let variables = [
+            ("SAM_CHOICE_k8s__env", "prod"),
+            ("SAM_CHOICE_K8S_NAMESPACE", "default"),
+            ("SAM_CHOICE___env", "dev"),
+            ("EDITOR", "vim"),
+        ];
+        let defaults = env_defaults(
+            variables
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string())),
+        );
+        assert_eq!(
+            defaults.into_iter().collect::<Vec<_>>(),
+            vec![(
+                Identifier::with_namespace("env", Some("k8s")),
+                vec![Choice::from_value("prod")]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_read_answers() {
+        let path = std::env::temp_dir().join(format!("sam-answers-{}.yaml", std::process::id()));
+        fs::write(
+            &path,
+            r#"
+k8s::env: prod
+k8s::pods: [api, web]
+k8s::replicas: 3
+k8s::region:
+  - value: eu-west-1
+    desc: ireland
+"#,
+        )
+        .unwrap();
+        let answers = read_answers(&path).unwrap();
+        let answer = |name: &str| {
+            answers
+                .get(&Identifier::with_namespace(name, Some("k8s")))
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(answer("env"), vec![Choice::from_value("prod")]);
+        assert_eq!(
+            answer("pods"),
+            vec![Choice::from_value("api"), Choice::from_value("web")]
+        );
+        assert_eq!(answer("replicas"), vec![Choice::from_value("3")]);
+        assert_eq!(
+            answer("region"),
+            vec![Choice::new("eu-west-1", Some("ireland"))]
+        );
+
+        fs::write(&path, "k8s::env: ~\n").unwrap();
+        assert!(read_answers(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_for_os() {
+        let settings: SettingsFile = toml::from_str(
+            r#"
+            root_dir = ["/recipes"]
+            ttl = 1800
+            EDITOR = "vim"
+
+            [target.'cfg(macos)']
+            root_dir = ["/recipes/macos"]
+            picker = "fzf"
+            EDITOR = "open -e"
+
+            [target.'cfg(linux | windows)']
+            ttl = 60
+
+            [target.'cfg(windows)']
+            shell = "pwsh"
+            "#,
+        )
+        .expect("can't parse the settings");
+
+        let macos = settings.clone().for_os("macos");
+        assert_eq!(
+            macos.root_dir,
+            vec![PathBuf::from("/recipes"), PathBuf::from("/recipes/macos")]
+        );
+        assert_eq!(macos.ttl, Some(1800));
+        assert_eq!(macos.picker, Some(Picker::Fzf));
+        assert_eq!(macos.env_variables.get("EDITOR").unwrap(), "open -e");
+
+        let linux = settings.clone().for_os("linux");
+        assert_eq!(linux.root_dir, vec![PathBuf::from("/recipes")]);
+        assert_eq!(linux.ttl, Some(60));
+        assert_eq!(linux.cache_ttl, Some(60));
+        assert_eq!(linux.picker, None);
+        assert_eq!(linux.env_variables.get("EDITOR").unwrap(), "vim");
+        assert_eq!(linux.shell, None);
+        assert!(linux.target.is_empty());
+
+        let windows = settings.for_os("windows");
+        assert_eq!(windows.shell.as_deref(), Some("pwsh"));
+    }
+
+    #[test]
+    fn test_from_files() {
+        let mut home: SettingsFile = toml::from_str(
+            r#"
+            root_dir = ["/recipes"]
+            ttl = 1800
+            picker = "fzf"
+            disabled_namespaces = ["legacy"]
+            EDITOR = "vim"
+            PAGER = "less"
+
+            [defaults]
+            "k8s::env" = "staging"
+            "k8s::namespace" = "default"
+
+            [namespace_aliases]
+            k8s = "kubernetes"
+            "#,
+        )
+        .expect("can't parse the settings");
+        let mut project: SettingsFile = toml::from_str(
+            r#"
+            root_dir = ["recipes"]
+            cache_ttl = 60
+            ttl = 120
+            namespace_strategy = "path"
+            shell = "zsh -c"
+            login_shell = true
+            profile = "client-a"
+            disabled_namespaces = ["k8s_old"]
+            EDITOR = "code"
+
+            [defaults]
+            "k8s::env" = "prod"
+
+            [theme]
+            name = "solarized"
+            marked = "178"
+            "#,
+        )
+        .expect("can't parse the settings");
+
+        home.path = PathBuf::from("/home/.sam_rc.toml");
+        project.path = PathBuf::from("/project/.sam_rc.toml");
+        let settings =
+            AppSettings::from_files(vec![home, project.relative_to(Path::new("/project"))]);
+        assert_eq!(
+            settings.root_dir,
+            vec![PathBuf::from("/recipes"), PathBuf::from("/project/recipes")]
+        );
+        assert_eq!(settings.ttl, 60);
+        assert_eq!(settings.picker, Picker::Fzf);
+        assert_eq!(settings.theme.name, ThemeName::Solarized);
+        assert_eq!(
+            settings.theme.marked.map(|c| c.to_string()).as_deref(),
+            Some("178")
+        );
+        assert!(!settings.variables().contains_key("theme"));
+        assert_eq!(settings.disabled_namespaces, vec!["legacy", "k8s_old"]);
+        assert_eq!(settings.namespace_strategy, NamespaceStrategy::Path);
+        assert_eq!(settings.shell().unwrap().to_string(), "zsh -l -c");
+        assert_eq!(settings.profile.as_deref(), Some("client-a"));
+        assert!(!settings.variables().contains_key("disabled_namespaces"));
+        assert!(!settings.variables().contains_key("namespace_aliases"));
+        assert_eq!(
+            settings
+                .namespace_aliases()
+                .resolve(&Identifier::with_namespace("env", Some("k8s"))),
+            Identifier::with_namespace("env", Some("kubernetes"))
+        );
+        assert_eq!(settings.variables().get("EDITOR").unwrap(), "code");
+        assert_eq!(settings.variables().get("PAGER").unwrap(), "less");
+        assert_eq!(
+            settings
+                .defaults
+                .get(&Identifier::with_namespace("env", Some("k8s"))),
+            Some(&vec![Choice::from_value("prod")])
+        );
+        assert_eq!(
+            settings
+                .defaults
+                .get(&Identifier::with_namespace("namespace", Some("k8s"))),
+            Some(&vec![Choice::from_value("default")])
+        );
+
+        assert_eq!(
+            settings.warnings(),
+            &[
+                Warning::DeprecatedField {
+                    field: String::from("ttl"),
+                    replacement: String::from("cache_ttl"),
+                    source_file: PathBuf::from("/home/.sam_rc.toml"),
+                },
+                Warning::DeprecatedField {
+                    field: String::from("ttl"),
+                    replacement: String::from("cache_ttl"),
+                    source_file: PathBuf::from("/project/.sam_rc.toml"),
+                },
+                Warning::ShadowedDefault {
+                    identifier: Identifier::with_namespace("env", Some("k8s")),
+                    source_file: PathBuf::from("/project/.sam_rc.toml"),
+                },
+            ]
+        );
+
+        let settings = AppSettings::from_files(vec![]);
+        assert_eq!(settings.ttl, super::DEFAULT_TTL);
+        assert!(settings.warnings().is_empty());
+        assert_eq!(settings.shell().unwrap(), Shell::user());
+    }
+
+    #[test]
+    fn test_git_root() {
+        let repo = std::env::temp_dir().join(format!("sam-git-root-{}", std::process::id()));
+        let nested = repo.join("services/api");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        assert_eq!(git_root(&nested), Some(repo.clone()));
+        assert_eq!(git_root(&repo), Some(repo.clone()));
+        fs::remove_dir_all(&repo).unwrap();
+    }
+
+    #[test]
+    fn test_empty_root_dirs() {
+        let dir = std::env::temp_dir().join(format!("sam-empty-roots-{}", std::process::id()));
+        let recipes = dir.join("recipes");
+        let empty = dir.join("empty");
+        fs::create_dir_all(recipes.join("k8s")).unwrap();
+        fs::create_dir_all(empty.join("k8s")).unwrap();
+        fs::write(recipes.join("k8s/vars.yml"), "").unwrap();
+        fs::write(empty.join("k8s/notes.md"), "").unwrap();
+        let settings = AppSettings {
+            root_dir: vec![recipes, empty.clone(), dir.join("missing")],
+            ..AppSettings::default()
+        };
+        assert_eq!(settings.empty_root_dirs(), vec![empty, dir.join("missing")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
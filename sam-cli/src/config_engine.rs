@@ -1,8 +1,17 @@
+use crate::cli::OutputFormat;
+use crate::environment::ErrorEnvironment;
+use sam_core::algorithms::{ErrorDependencyResolution, VarsCollection};
 use sam_core::entities::commands::programs_used;
 use sam_core::entities::commands::unset_env_vars;
+use sam_core::entities::commands::Command;
+use sam_core::entities::identifiers::Identifier;
 use sam_persistence::repositories::AliasesRepository;
 use sam_persistence::repositories::VarsRepository;
+use sam_readers::{Location, Sources, Warning, Warnings};
+use serde::Serialize;
 use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -18,28 +27,263 @@ pub struct ConfigEngine {
     pub aliases: AliasesRepository,
     pub vars: VarsRepository,
     pub env_variables: HashMap<String, String>,
+    pub warnings: Warnings,
+    pub sources: Sources,
+    pub output: OutputFormat,
 }
 
 impl ConfigEngine {
     pub fn run(&self, cmd: ConfigCommand) -> Result<i32> {
+        if self.output == OutputFormat::Json {
+            return self.run_json(cmd);
+        }
         match cmd {
             ConfigCommand::CheckUnsetEnvVars => self.check_unset_env_vars(),
             ConfigCommand::CheckUnavailablePrograms => self.check_unavailable_programs(),
             ConfigCommand::All => {
                 self.check_unavailable_programs()?;
-                self.check_unset_env_vars()
+                self.check_warnings()?;
+                let definitions = self.check_definitions()?;
+                Ok(definitions.max(self.check_unset_env_vars()?))
             }
         }
     }
-    fn check_unset_env_vars(&self) -> Result<i32> {
+
+    fn run_json(&self, cmd: ConfigCommand) -> Result<i32> {
+        let all = cmd == ConfigCommand::All;
+        let mut report = ConfigReport {
+            missing_programs: (all || cmd == ConfigCommand::CheckUnavailablePrograms)
+                .then(|| self.unavailable_programs()),
+            warnings: all.then(|| self.warnings.iter().map(|w| w.to_string()).collect()),
+            unset_env_vars: (all || cmd == ConfigCommand::CheckUnsetEnvVars)
+                .then(|| self.unset_env_vars()),
+            diagnostics: vec![],
+        };
+        if report.warnings.is_some() {
+            report
+                .diagnostics
+                .extend(self.warnings.iter().map(Diagnostic::from));
+        }
+        for program in report.missing_programs.iter().flatten() {
+            report.diagnostics.push(Diagnostic::new(
+                Severity::Warning,
+                "missing-program",
+                format!("program {} can't be found in the PATH", program),
+                None,
+            ));
+        }
+        if all {
+            report.diagnostics.extend(self.definition_issues());
+        }
+        for var in report.unset_env_vars.iter().flatten() {
+            report.diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                "unset-env-var",
+                format!("environment variable {} is not set", var),
+                None,
+            ));
+        }
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        let has_errors = report
+            .diagnostics
+            .iter()
+            .any(|d| d.severity == Severity::Error);
+        Ok(if has_errors { 1 } else { 0 })
+    }
+
+    /// definition_issues lists the aliases using vars that aren't defined, the vars
+    /// that depend on each other, the ones whose pattern is invalid and the vars that
+    /// no alias uses.
+    fn definition_issues(&self) -> Vec<Diagnostic> {
+        let mut issues = vec![];
+        let mut aliases = self.aliases.aliases();
+        aliases.sort_by_key(|a| a.identifier().to_string());
+        let mut used = HashSet::new();
+        for alias in &aliases {
+            for dependency in alias.dependencies() {
+                if self.vars.get(&dependency).is_none() {
+                    issues.push(Diagnostic::new(
+                        Severity::Error,
+                        "undefined-var",
+                        format!(
+                            "alias {} uses the undefined var {}",
+                            alias.identifier(),
+                            dependency
+                        ),
+                        self.sources.alias(&alias.identifier()),
+                    ));
+                }
+                used.insert(dependency);
+            }
+        }
+        let mut history_vars: Vec<(Identifier, Identifier)> = self
+            .vars
+            .vars_iter()
+            .filter_map(|var| Some((var.name(), var.history_source()?)))
+            .collect();
+        history_vars.sort_by_key(|(var, _)| var.to_string());
+        for (var, source) in history_vars {
+            let alias = aliases.iter().find(|a| a.identifier() == source);
+            let (severity, code, message) = match alias {
+                None => (
+                    Severity::Error,
+                    "undefined-alias",
+                    format!(
+                        "var {} offers the outputs of the undefined alias {}",
+                        var, source
+                    ),
+                ),
+                Some(alias) if !alias.captures_output() => (
+                    Severity::Warning,
+                    "uncaptured-output",
+                    format!(
+                        "var {} offers the outputs of alias {}, which doesn't set `capture: true`",
+                        var, source
+                    ),
+                ),
+                Some(_) => continue,
+            };
+            issues.push(Diagnostic::new(
+                severity,
+                code,
+                message,
+                self.sources.var(&var),
+            ));
+        }
+        // the patterns validating the inputs and filtering the choices.
+        let mut invalid_patterns: Vec<(Identifier, String)> = self
+            .vars
+            .vars_iter()
+            .flat_map(|var| {
+                let input = var.input_rules().check().err().map(|e| e.to_string());
+                let filter = var
+                    .choice_filter()
+                    .and_then(|f| f.check().err())
+                    .map(|e| e.to_string());
+                input
+                    .into_iter()
+                    .chain(filter)
+                    .map(move |e| (var.name(), e))
+            })
+            .collect();
+        invalid_patterns.sort_by_key(|(var, _)| var.to_string());
+        for (var, err) in invalid_patterns {
+            issues.push(Diagnostic::new(
+                Severity::Error,
+                "invalid-pattern",
+                format!("var {}: {}", var, err),
+                self.sources.var(&var),
+            ));
+        }
+        for cycle in self.vars.cycles() {
+            let location = self.sources.var(&cycle[0]);
+            issues.push(Diagnostic::new(
+                Severity::Error,
+                "cyclic-dependency",
+                ErrorDependencyResolution::CyclicDependency(cycle).to_string(),
+                location,
+            ));
+        }
+        // the vars the vars of the aliases depend on are used as well.
+        let mut pending: Vec<Identifier> = used.iter().cloned().collect();
+        while let Some(name) = pending.pop() {
+            for dependency in self
+                .vars
+                .get(&name)
+                .map(|v| v.dependencies())
+                .unwrap_or_default()
+            {
+                if used.insert(dependency.clone()) {
+                    pending.push(dependency);
+                }
+            }
+        }
+        let mut unused: Vec<Identifier> = self
+            .vars
+            .identifiers()
+            .into_iter()
+            .filter(|id| !used.contains(id))
+            .collect();
+        unused.sort_by_key(Identifier::to_string);
+        for name in unused {
+            issues.push(Diagnostic::new(
+                Severity::Warning,
+                "unused-var",
+                format!("var {} isn't used by any alias", name),
+                self.sources.var(&name),
+            ));
+        }
+        issues
+    }
+
+    fn check_definitions(&self) -> Result<i32> {
+        let issues = self.definition_issues();
+        if issues.is_empty() {
+            return Ok(0);
+        }
+        println!("Definitions:");
+        for issue in &issues {
+            let color: &dyn Display = match issue.severity {
+                Severity::Error => {
+                    &crossterm::style::SetForegroundColor(crossterm::style::Color::DarkRed)
+                }
+                Severity::Warning => {
+                    &crossterm::style::SetForegroundColor(crossterm::style::Color::DarkYellow)
+                }
+            };
+            println!(
+                "- {}{}{}{}",
+                crossterm::style::Attribute::Bold,
+                color,
+                issue,
+                crossterm::style::Attribute::Reset,
+            );
+        }
+        let has_errors = issues.iter().any(|i| i.severity == Severity::Error);
+        Ok(if has_errors { 1 } else { 0 })
+    }
+
+    fn check_warnings(&self) -> Result<i32> {
+        if self.warnings.is_empty() {
+            return Ok(0);
+        }
+        println!("Warnings:");
+        for warning in self.warnings.iter() {
+            println!(
+                "- {}{}{}{}",
+                crossterm::style::Attribute::Bold,
+                crossterm::style::SetForegroundColor(crossterm::style::Color::DarkYellow),
+                warning,
+                crossterm::style::Attribute::Reset,
+            );
+        }
+        Ok(0)
+    }
+
+    fn unset_env_vars(&self) -> Vec<String> {
         let missing_envvars_in_aliases = unset_env_vars(self.aliases.aliases().iter());
         let missing_envvars_in_vars = unset_env_vars(self.vars.vars_iter());
-        let envvars_in_config: HashSet<&String> = self.env_variables.keys().collect();
+        let aliases = self.aliases.aliases();
+        // variables declared in the env of an alias are set when it runs.
+        let envvars_in_config: HashSet<&String> = self
+            .env_variables
+            .keys()
+            .chain(aliases.iter().flat_map(|a| a.env().keys()))
+            .collect();
         let all_envvars: HashSet<&String> = missing_envvars_in_vars
             .union(&missing_envvars_in_aliases)
             .collect();
 
-        let missing_envvars: Vec<_> = all_envvars.difference(&envvars_in_config).collect();
+        let mut missing_envvars: Vec<String> = all_envvars
+            .difference(&envvars_in_config)
+            .map(|e| e.to_string())
+            .collect();
+        missing_envvars.sort();
+        missing_envvars
+    }
+
+    fn check_unset_env_vars(&self) -> Result<i32> {
+        let missing_envvars = self.unset_env_vars();
         if missing_envvars.is_empty() {
             return Ok(0);
         }
@@ -47,33 +291,38 @@ impl ConfigEngine {
         for var in &missing_envvars {
             println!(
                 "- {}{}{}{}",
-                termion::style::Bold,
-                termion::color::Fg(termion::color::Red),
+                crossterm::style::Attribute::Bold,
+                crossterm::style::SetForegroundColor(crossterm::style::Color::DarkRed),
                 var,
-                termion::style::Reset,
+                crossterm::style::Attribute::Reset,
             );
         }
         Ok(1)
     }
 
-    fn check_unavailable_programs(&self) -> Result<i32> {
+    fn unavailable_programs(&self) -> Vec<String> {
         let programs_in_aliases = programs_used(self.aliases.aliases().iter());
         let programs_in_vars = programs_used(self.vars.vars_iter());
-        let mut missing_programs = vec![];
-        for prg in programs_in_aliases.union(&programs_in_vars) {
-            if !Self::is_program_available(prg) {
-                missing_programs.push(prg)
-            }
-        }
+        let mut missing_programs: Vec<String> = programs_in_aliases
+            .union(&programs_in_vars)
+            .filter(|prg| !Self::is_program_available(prg))
+            .cloned()
+            .collect();
+        missing_programs.sort();
+        missing_programs
+    }
+
+    fn check_unavailable_programs(&self) -> Result<i32> {
+        let missing_programs = self.unavailable_programs();
         if !missing_programs.is_empty() {
             println!("Missing programs:");
             for prg in missing_programs {
                 println!(
                     "- {}{}{}{}",
-                    termion::style::Bold,
-                    termion::color::Fg(termion::color::Red),
+                    crossterm::style::Attribute::Bold,
+                    crossterm::style::SetForegroundColor(crossterm::style::Color::DarkRed),
                     prg,
-                    termion::style::Reset,
+                    crossterm::style::Attribute::Reset,
                 );
             }
         }
@@ -89,7 +338,215 @@ impl ConfigEngine {
     }
 }
 
+/// report_load_failure prints the reason aliases and vars couldn't be loaded
+/// the way `check-config --output json` reports the issues it found.
+pub fn report_load_failure(err: &ErrorEnvironment) -> Result<i32> {
+    let (code, location) = match err {
+        ErrorEnvironment::AliasRead(e) => ("invalid-aliases-file", e.location()),
+        ErrorEnvironment::VarRead(e) => ("invalid-vars-file", e.location()),
+        _ => ("load-failure", None),
+    };
+    let report = ConfigReport {
+        diagnostics: vec![Diagnostic::new(
+            Severity::Error,
+            code,
+            err.to_string(),
+            location,
+        )],
+        ..ConfigReport::default()
+    };
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(1)
+}
+
+#[derive(Debug, Default, Serialize)]
+struct ConfigReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    missing_programs: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warnings: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unset_env_vars: Option<Vec<String>>,
+    // every issue found, with the file, line and column it comes from when known,
+    // for editors and CI to annotate recipes with.
+    diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct Diagnostic {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    column: Option<usize>,
+    severity: Severity,
+    code: &'static str,
+    message: String,
+}
+
+impl Diagnostic {
+    fn new(
+        severity: Severity,
+        code: &'static str,
+        message: String,
+        location: Option<Location>,
+    ) -> Self {
+        Diagnostic {
+            file: location.as_ref().map(|l| l.file.clone()),
+            line: location.as_ref().and_then(|l| l.line),
+            column: location.as_ref().and_then(|l| l.column),
+            severity,
+            code,
+            message,
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", Location::new(file, self.line, self.column))?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<&Warning> for Diagnostic {
+    fn from(warning: &Warning) -> Self {
+        Diagnostic::new(
+            Severity::Warning,
+            warning.code(),
+            warning.to_string(),
+            warning.location(),
+        )
+    }
+}
+
 type Result<T> = std::result::Result<T, ErrorsConfigEngine>;
 
 #[derive(Debug, Error)]
-pub enum ErrorsConfigEngine {}
+pub enum ErrorsConfigEngine {
+    #[error("could not serialize the configuration report\n-> {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConfigEngine, Severity};
+    use crate::cli::OutputFormat;
+    use sam_core::entities::aliases::Alias;
+    use sam_core::entities::choices::ChoiceFilter;
+    use sam_core::entities::identifiers::Identifier;
+    use sam_core::entities::vars::{InputRules, Var};
+    use sam_persistence::repositories::{AliasesRepository, VarsRepository};
+    use sam_readers::{Sources, Warnings};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_definition_issues() {
+        let aliases = vec![
+            Alias::new("list", "lists", "ls {{ dir }} {{ missing }} {{ created }}"),
+            Alias::new("create", "creates", "touch {{ root }} {{ port }}"),
+        ];
+        let vars = vec![
+            Var::from_command("dir", "dirs", "ls {{ root }}"),
+            Var::new("root", "roots", vec![]),
+            Var::from_input("port", "port", "port").with_input_rules(InputRules {
+                pattern: Some(String::from("[0-9")),
+                ..InputRules::default()
+            }),
+            Var::new("pattern", "patterns", vec![]).with_choice_filter(Some(ChoiceFilter {
+                pattern: Some(String::from("(prod")),
+                ..ChoiceFilter::default()
+            })),
+            Var::from_command("a", "a", "echo {{ b }}"),
+            Var::from_command("b", "b", "echo {{ a }}"),
+            Var::from_history("created", "created files", "create"),
+            Var::from_history("deleted", "deleted files", "delete"),
+        ];
+        let mut sources = Sources::default();
+        sources.add_var(Identifier::new("pattern"), "/tmp/sam-missing/vars.yaml");
+        let engine = ConfigEngine {
+            aliases: AliasesRepository::new(aliases.into_iter()).unwrap(),
+            vars: VarsRepository::new(vars.into_iter()),
+            env_variables: HashMap::new(),
+            warnings: Warnings::default(),
+            sources,
+            output: OutputFormat::Text,
+        };
+        let issues: Vec<(Severity, &str, String)> = engine
+            .definition_issues()
+            .iter()
+            .map(|d| (d.severity, d.code, d.to_string()))
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                (
+                    Severity::Error,
+                    "undefined-var",
+                    String::from("alias list uses the undefined var missing")
+                ),
+                (
+                    Severity::Warning,
+                    "uncaptured-output",
+                    String::from(
+                        "var created offers the outputs of alias create, which doesn't set `capture: true`"
+                    )
+                ),
+                (
+                    Severity::Error,
+                    "undefined-alias",
+                    String::from("var deleted offers the outputs of the undefined alias delete")
+                ),
+                (
+                    Severity::Error,
+                    "invalid-pattern",
+                    String::from(
+                        "/tmp/sam-missing/vars.yaml: var pattern: the pattern (prod of the filter isn't a valid regular expression"
+                    )
+                ),
+                (
+                    Severity::Error,
+                    "invalid-pattern",
+                    String::from(
+                        "var port: the pattern [0-9 of the var isn't a valid regular expression"
+                    )
+                ),
+                (
+                    Severity::Error,
+                    "cyclic-dependency",
+                    String::from("vars depend on each other: a -> b -> a")
+                ),
+                (
+                    Severity::Warning,
+                    "unused-var",
+                    String::from("var a isn't used by any alias")
+                ),
+                (
+                    Severity::Warning,
+                    "unused-var",
+                    String::from("var b isn't used by any alias")
+                ),
+                (
+                    Severity::Warning,
+                    "unused-var",
+                    String::from("var deleted isn't used by any alias")
+                ),
+                (
+                    Severity::Warning,
+                    "unused-var",
+                    String::from("/tmp/sam-missing/vars.yaml: var pattern isn't used by any alias")
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,116 @@
+/// Word represents a word of a command in a word level diff.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Word<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// word_diff computes a word level diff between two commands using the
+/// longest common subsequence of their words.
+pub fn word_diff<'a>(old: &'a str, new: &'a str) -> Vec<Word<'a>> {
+    let old: Vec<&str> = old.split_whitespace().collect();
+    let new: Vec<&str> = new.split_whitespace().collect();
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut words = Vec::with_capacity(old.len().max(new.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            words.push(Word::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            words.push(Word::Removed(old[i]));
+            i += 1;
+        } else {
+            words.push(Word::Added(new[j]));
+            j += 1;
+        }
+    }
+    words.extend(old[i..].iter().map(|w| Word::Removed(w)));
+    words.extend(new[j..].iter().map(|w| Word::Added(w)));
+    words
+}
+
+pub fn has_changes(words: &[Word]) -> bool {
+    words.iter().any(|w| !matches!(w, Word::Same(_)))
+}
+
+/// render_old prints the previous command, highlighting the removed words.
+pub fn render_old(words: &[Word]) -> String {
+    render(words, |w| match w {
+        Word::Same(s) => Some(s.to_string()),
+        Word::Removed(s) => Some(highlight(
+            s,
+            crossterm::style::SetForegroundColor(crossterm::style::Color::DarkRed),
+        )),
+        Word::Added(_) => None,
+    })
+}
+
+/// render_new prints the upcoming command, highlighting the added words.
+pub fn render_new(words: &[Word]) -> String {
+    render(words, |w| match w {
+        Word::Same(s) => Some(s.to_string()),
+        Word::Added(s) => Some(highlight(
+            s,
+            crossterm::style::SetForegroundColor(crossterm::style::Color::DarkGreen),
+        )),
+        Word::Removed(_) => None,
+    })
+}
+
+fn render<'a>(words: &[Word<'a>], f: impl Fn(&Word<'a>) -> Option<String>) -> String {
+    words
+        .iter()
+        .filter_map(f)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn highlight(word: &str, color: impl std::fmt::Display) -> String {
+    format!(
+        "{}{}{}{}",
+        crossterm::style::Attribute::Bold,
+        color,
+        word,
+        crossterm::style::Attribute::Reset
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{has_changes, word_diff, Word};
+
+    #[test]
+    fn test_word_diff() {
+        let words = word_diff(
+            "kubectl --context prod get pods",
+            "kubectl --context staging get pods -w",
+        );
+        assert_eq!(
+            words,
+            vec![
+                Word::Same("kubectl"),
+                Word::Same("--context"),
+                Word::Removed("prod"),
+                Word::Added("staging"),
+                Word::Same("get"),
+                Word::Same("pods"),
+                Word::Added("-w"),
+            ]
+        );
+        assert!(has_changes(&words));
+        assert!(!has_changes(&word_diff("ls -la", "ls  -la")));
+    }
+}
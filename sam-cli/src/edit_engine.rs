@@ -0,0 +1,172 @@
+use sam_core::entities::identifiers::Identifier;
+use sam_readers::{Location, Sources};
+use sam_terminals::processes::{editor, editor_command};
+use std::io;
+use std::path::Path;
+use thiserror::Error;
+
+// editors that take `file:line:column` rather than `+line file`.
+const COLON_EDITORS: [&str; 5] = ["code", "codium", "subl", "hx", "zed"];
+
+/// EditEngine opens the file an alias or a var is defined in with $VISUAL or $EDITOR,
+/// vi otherwise, at the line of its definition.
+pub struct EditEngine {
+    pub sources: Sources,
+}
+
+impl EditEngine {
+    pub fn run(&self, target: &str) -> Result<i32> {
+        let location = self.locate(target)?;
+        let editor = editor();
+        let status = editor_command(&editor, &editor_args(&editor, &location))
+            .status()
+            .map_err(|err| ErrorEditEngine::Editor(editor.clone(), err))?;
+        Ok(status.code().unwrap_or(1))
+    }
+
+    // locate finds the alias, then the var, with the identifier. A name without
+    // a namespace has to belong to a single alias or var.
+    fn locate(&self, target: &str) -> Result<Location> {
+        let id = Identifier::from_str(target);
+        if id.namespace.is_some() {
+            return self
+                .sources
+                .alias(&id)
+                .or_else(|| self.sources.var(&id))
+                .ok_or_else(|| ErrorEditEngine::NotFound(target.to_string()));
+        }
+        let mut found: Vec<(&str, &Identifier)> = self
+            .sources
+            .aliases()
+            .filter(|a| a.name() == id.name())
+            .map(|a| ("alias", a))
+            .chain(
+                self.sources
+                    .vars()
+                    .filter(|v| v.name() == id.name())
+                    .map(|v| ("var", v)),
+            )
+            .collect();
+        found.sort();
+        match found.as_slice() {
+            [] => Err(ErrorEditEngine::NotFound(target.to_string())),
+            [("alias", alias)] => Ok(self.sources.alias(alias).expect("the alias has a source")),
+            [(_, var)] => Ok(self.sources.var(var).expect("the var has a source")),
+            _ => {
+                let candidates: Vec<String> = found
+                    .iter()
+                    .map(|(kind, id)| format!("{} {}", kind, id))
+                    .collect();
+                Err(ErrorEditEngine::Ambiguous(
+                    target.to_string(),
+                    candidates.join(", "),
+                ))
+            }
+        }
+    }
+}
+
+// editor_args are the arguments opening the file at the location,
+// most editors follow vi and take the line first.
+fn editor_args(editor: &str, location: &Location) -> Vec<String> {
+    let file = location.file.display().to_string();
+    let line = match location.line {
+        Some(line) => line,
+        None => return vec![file],
+    };
+    let program = editor
+        .split_whitespace()
+        .next()
+        .and_then(|p| Path::new(p).file_name())
+        .and_then(|p| p.to_str())
+        .unwrap_or_default();
+    if COLON_EDITORS.contains(&program) {
+        let position = format!("{}:{}:{}", file, line, location.column.unwrap_or(1));
+        if program == "code" || program == "codium" {
+            return vec![String::from("--goto"), position];
+        }
+        return vec![position];
+    }
+    vec![format!("+{}", line), file]
+}
+
+pub type Result<T> = std::result::Result<T, ErrorEditEngine>;
+
+#[derive(Debug, Error)]
+pub enum ErrorEditEngine {
+    #[error("there is no alias nor var named {0}")]
+    NotFound(String),
+    #[error("several aliases or vars are named {0}, add the namespace to pick one: {1}")]
+    Ambiguous(String, String),
+    #[error("can't run the editor {0}\n-> {1}")]
+    Editor(String, io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{editor_args, EditEngine};
+    use sam_core::entities::identifiers::Identifier;
+    use sam_readers::{Location, Sources};
+    use std::fs;
+
+    #[test]
+    fn test_locate() {
+        let dir = std::env::temp_dir().join(format!("sam-edit-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let aliases = dir.join("aliases.yaml");
+        fs::write(
+            &aliases,
+            "- name: pods\n  desc: pods\n  alias: ls\n\n- name: logs\n",
+        )
+        .unwrap();
+        let mut sources = Sources::default();
+        sources.add_alias(Identifier::with_namespace("logs", Some("k8s")), &aliases);
+        sources.add_alias(Identifier::with_namespace("pods", Some("k8s")), &aliases);
+        sources.add_var(
+            Identifier::with_namespace("pods", Some("docker")),
+            "/recipes/vars.yaml",
+        );
+        let engine = EditEngine { sources };
+
+        assert_eq!(
+            engine.locate("k8s::logs").unwrap(),
+            Location::new(&aliases, Some(5), Some(3))
+        );
+        assert_eq!(engine.locate("logs").unwrap().line, Some(5));
+        assert_eq!(
+            engine.locate("docker::pods").unwrap(),
+            Location::new("/recipes/vars.yaml", None, None)
+        );
+        assert_eq!(
+            engine.locate("pods").unwrap_err().to_string(),
+            "several aliases or vars are named pods, add the namespace to pick one: alias k8s::pods, var docker::pods"
+        );
+        assert_eq!(
+            engine.locate("k8s::nodes").unwrap_err().to_string(),
+            "there is no alias nor var named k8s::nodes"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_editor_args() {
+        let location = Location::new("/recipes/k8s/aliases.yaml", Some(12), Some(3));
+        assert_eq!(
+            editor_args("nvim", &location),
+            vec!["+12", "/recipes/k8s/aliases.yaml"]
+        );
+        assert_eq!(
+            editor_args("/usr/bin/code --wait", &location),
+            vec!["--goto", "/recipes/k8s/aliases.yaml:12:3"]
+        );
+        assert_eq!(
+            editor_args("hx", &location),
+            vec!["/recipes/k8s/aliases.yaml:12:3"]
+        );
+        let location = Location::new("/recipes/k8s/aliases.yaml", None, None);
+        assert_eq!(
+            editor_args("nano", &location),
+            vec!["/recipes/k8s/aliases.yaml"]
+        );
+    }
+}
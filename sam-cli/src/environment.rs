@@ -1,24 +1,43 @@
+use crate::audit_engine::{self, AuditEngine};
 use crate::cache_engine::CacheEngine;
+use crate::complete_engine::CompleteEngine;
 use crate::config::AppSettings;
 use crate::config_engine::ConfigEngine;
-use crate::executors::make_executor;
+use crate::edit_engine::EditEngine;
+use crate::executors::{
+    make_confirmation, make_executor, DiffExecutor, PrintExecutor, ShellHistoryExecutor,
+};
+use crate::export_engine::ExportEngine;
 use crate::history_engine::HistoryEngine;
+use crate::list_engine::ListEngine;
 use crate::logger::{ErrorLogger, FileLogger, SilentLogger};
+use crate::resolve_engine::{self, ResolveEngine};
+use crate::run_engine::RunEngine;
+use crate::scaffold_engine::ScaffoldEngine;
+use crate::stats_engine::StatsEngine;
+use crate::watch_engine::WatchEngine;
+use sam_core::algorithms::VarsCollection;
 use sam_core::engines::{SamEngine, SamExecutor, SamLogger, VarsDefaultValuesSetter};
+use sam_core::entities::namespaces::Namespace;
 use sam_persistence::repositories::{
     AliasesRepository, ErrorsAliasesRepository, ErrorsVarsRepository, VarsRepository,
 };
 use sam_persistence::{
     AliasHistory, CacheError, ErrorAliasHistory, NoopVarsCache, RustBreakCache, VarsCache,
 };
-use sam_readers::read_aliases_from_path;
-use sam_readers::read_vars_repository;
+use sam_readers::read_aliases_with_namespaces;
+use sam_readers::read_vars_with_namespaces;
 use sam_readers::ErrorsAliasRead;
 use sam_readers::ErrorsVarRead;
+use sam_readers::{Sources, Warning, Warnings};
+use sam_secrets::Secrets;
 use sam_tui::{ErrorsUIV2, UserInterfaceV2};
 use sam_utils::fsutils;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::Path;
 use std::rc::Rc;
 use thiserror::Error;
 
@@ -30,17 +49,47 @@ pub struct Environment {
     pub config: AppSettings,
     pub history: AliasHistory,
     pub cache: Box<dyn VarsCache>,
+    pub warnings: Warnings,
+    pub sources: Sources,
 }
 
 impl Environment {
     pub fn sam_engine(
         self,
-    ) -> SamEngine<UserInterfaceV2, AliasesRepository, VarsRepository, VarsRepository> {
-        let executor: Rc<dyn SamExecutor> = make_executor(self.config.dry)
-            .expect("Could not initialize executors, please open a ticket");
-        let resolver = UserInterfaceV2::new(self.env_variables.clone(), self.cache);
+    ) -> Result<SamEngine<UserInterfaceV2, AliasesRepository, VarsRepository, VarsRepository>> {
+        let mut executor: Rc<dyn SamExecutor> = make_executor(
+            self.config.dry,
+            self.config.quote,
+            self.config.jobs,
+            self.config.record,
+            self.history.clone(),
+        )
+        .map_err(ErrorEnvironment::Executor)?;
+        if self.config.diff {
+            // the history is read before the alias is run and recorded.
+            let previous = self.history.entries()?.map(|e| e.r);
+            executor = Rc::new(DiffExecutor::new(executor, previous));
+        }
+        if let Some(shell) = self.config.shell_history.filter(|_| !self.config.dry) {
+            let file = self
+                .config
+                .shell_history_file
+                .clone()
+                .or_else(|| shell.default_file());
+            if let Some(file) = file {
+                executor = Rc::new(ShellHistoryExecutor::new(executor, shell, file));
+            }
+        }
+        let last_choices = self.history.last_choices()?;
+        let history_outputs = self.history.outputs()?;
+        let resolver = UserInterfaceV2::new(self.env_variables.clone(), self.cache)
+            .with_picker(self.config.picker)
+            .with_non_interactive(self.config.non_interactive)
+            .with_last_choices(last_choices)
+            .with_history_outputs(history_outputs)
+            .with_secrets(Box::new(Secrets::default()));
 
-        SamEngine {
+        Ok(SamEngine {
             resolver,
             aliases: self.aliases,
             vars: self.vars.clone(),
@@ -48,26 +97,138 @@ impl Environment {
             logger: self.logger,
             env_variables: self.env_variables,
             history: RefCell::new(Box::new(self.history)),
+            confirmation: make_confirmation(self.config.dry),
             executor,
-        }
+        })
+    }
+
+    /// resolve_alias_engine prints the commands of the aliases it resolves instead of running
+    /// them. When stdout is captured, the pickers are drawn on the terminal.
+    pub fn resolve_alias_engine(
+        self,
+    ) -> Result<SamEngine<UserInterfaceV2, AliasesRepository, VarsRepository, VarsRepository>> {
+        let output = resolve_engine::output().map_err(ErrorEnvironment::Output)?;
+        let mut engine = self.sam_engine()?;
+        engine.executor = Rc::new(PrintExecutor {
+            output: RefCell::new(output),
+        });
+        Ok(engine)
     }
 
     pub fn cache_engine(self) -> CacheEngine {
         CacheEngine {
             cache_dir: self.config.cache_dir().to_owned(),
             ttl: self.config.ttl(),
+            vars: self.vars,
+            env_variables: self.env_variables,
+        }
+    }
+
+    pub fn export_engine(self) -> ExportEngine {
+        ExportEngine {
+            aliases: self.aliases,
+        }
+    }
+
+    pub fn list_engine(self) -> ListEngine {
+        ListEngine {
+            aliases: self.aliases,
+            vars: self.vars,
+            output: self.config.output,
+            pager: !self.config.no_pager,
+        }
+    }
+
+    pub fn complete_engine(self) -> CompleteEngine {
+        CompleteEngine {
+            aliases: self.aliases,
+            vars: self.vars,
+        }
+    }
+
+    pub fn audit_engine(self) -> AuditEngine {
+        AuditEngine {
+            store: self.config.history_file().with_file_name("audit.jsonl"),
+            socket: self.config.history_file().with_file_name("audit.sock"),
+            output: self.config.output,
+            pager: !self.config.no_pager,
+            token: std::env::var(audit_engine::TOKEN_ENV_VAR)
+                .ok()
+                .filter(|t| !t.is_empty()),
+        }
+    }
+
+    pub fn scaffold_engine(self) -> ScaffoldEngine {
+        ScaffoldEngine {
+            root_dirs: self.config.root_dirs().to_vec(),
+            aliases_files: self.config.aliases_files().collect(),
+            vars_files: self.config.vars_files().collect(),
+            sources: self.sources,
+        }
+    }
+
+    pub fn edit_engine(self) -> EditEngine {
+        EditEngine {
+            sources: self.sources,
+        }
+    }
+
+    pub fn watch_engine(self) -> WatchEngine {
+        WatchEngine {
+            aliases: self.aliases.aliases(),
+            vars: self.vars.vars_iter().count(),
+            warnings: self.warnings.len(),
+            config: self.config,
         }
     }
 
+    pub fn stats_engine(self) -> StatsEngine {
+        StatsEngine {
+            history: self.history,
+            aliases: self
+                .aliases
+                .aliases()
+                .iter()
+                .map(|alias| alias.identifier())
+                .collect(),
+            output: self.config.output,
+        }
+    }
+
+    pub fn resolve_engine(
+        self,
+    ) -> Result<ResolveEngine<UserInterfaceV2, AliasesRepository, VarsRepository, VarsRepository>>
+    {
+        let vars = self.vars.identifiers();
+        let sam_engine = self.sam_engine()?;
+        Ok(ResolveEngine { sam_engine, vars })
+    }
+
+    pub fn run_engine(
+        self,
+    ) -> Result<RunEngine<UserInterfaceV2, AliasesRepository, VarsRepository, VarsRepository>> {
+        let root_dirs = self.config.root_dirs().to_vec();
+        let sam_engine = self.sam_engine()?;
+        Ok(RunEngine {
+            sam_engine,
+            root_dirs,
+        })
+    }
+
     pub fn history_engine(
         self,
-    ) -> HistoryEngine<UserInterfaceV2, AliasesRepository, VarsRepository, VarsRepository> {
+    ) -> Result<HistoryEngine<UserInterfaceV2, AliasesRepository, VarsRepository, VarsRepository>>
+    {
         let history = self.history.clone();
-        let sam_engine = self.sam_engine();
-        HistoryEngine {
+        let output = self.config.output;
+        let pager = !self.config.no_pager;
+        let sam_engine = self.sam_engine()?;
+        Ok(HistoryEngine {
             sam_engine,
             history,
-        }
+            output,
+            pager,
+        })
     }
     // Clippy is making a false positive on this one
     #[allow(clippy::missing_const_for_fn)]
@@ -76,33 +237,123 @@ impl Environment {
             aliases: self.aliases,
             vars: self.vars,
             env_variables: self.env_variables,
+            warnings: self.warnings,
+            sources: self.sources,
+            output: self.config.output,
         }
     }
 }
 
 pub fn from_settings(config: AppSettings) -> Result<Environment> {
-    let cache: Box<dyn VarsCache> = if !config.no_cache {
+    let mut read_only = vec![];
+    let history_writable = is_writable(config.history_file());
+    // the cache keeps its locks next to its state, in the same directory.
+    let cache_writable = config.no_cache
+        || (config.cache_dir().parent().is_none_or(is_dir_writable)
+            && is_writable(config.cache_dir()));
+    if !history_writable {
+        read_only.push(config.history_file().to_path_buf());
+    }
+    if !cache_writable {
+        read_only.push(config.cache_dir().to_path_buf());
+    }
+    let cache: Box<dyn VarsCache> = if !config.no_cache && cache_writable {
         Box::new(RustBreakCache::with_ttl(config.cache_dir(), &config.ttl())?)
     } else {
         Box::new(NoopVarsCache {})
     };
-    let history = AliasHistory::new(config.history_file(), Some(1000))?;
+    let history = if history_writable {
+        AliasHistory::new(config.history_file(), Some(1000))?
+    } else {
+        AliasHistory::in_memory(config.history_file(), Some(1000))
+    };
 
     let logger = logger_instance(config.silent)?;
 
-    let mut aliases_vec = vec![];
-    for f in config.aliases_files() {
-        aliases_vec.extend(read_aliases_from_path(&f)?);
+    let mut warnings = Warnings::default();
+    warnings.extend(config.warnings().iter().cloned());
+    if !read_only.is_empty() {
+        warnings.push(Warning::ReadOnlyState(read_only));
     }
-    let aliases = AliasesRepository::new(aliases_vec.into_iter())?;
-
+    for dir in config.empty_root_dirs() {
+        warnings.push(Warning::NoRecipes(dir));
+    }
+    let mut sources = Sources::default();
     let mut vars = VarsRepository::default();
+    let namespaces = config.namespaces();
+    let namespace_aliases = config.namespace_aliases();
     for f in config.vars_files() {
-        vars.merge(read_vars_repository(&f)?);
+        let vars_in_file = read_vars_with_namespaces(&f, &namespaces)?;
+        let vars_in_file = VarsRepository::new(
+            vars_in_file
+                .vars_iter()
+                .filter(|v| !config.is_disabled(v.namespace()))
+                .cloned(),
+        );
+        for var in vars_in_file.vars_iter() {
+            if vars.get(&var.name()).is_some() {
+                warnings.push(Warning::DuplicateVar {
+                    identifier: var.name(),
+                    source_file: f.clone(),
+                });
+            }
+            sources.add_var(var.name(), &f);
+        }
+        vars.merge(vars_in_file);
     }
-    vars.set_defaults(&config.defaults);
+    for id in config.defaults.keys() {
+        // the arguments of the aliases aren't vars.
+        if id.argument_position().is_none() && vars.get(&namespace_aliases.resolve(id)).is_none() {
+            warnings.push(Warning::UnknownDefault(id.clone()));
+        }
+    }
+    // the choices are given to the vars in their own namespace, for the ones given with -c
+    // to win over the defaults of the recipes even when they use a namespace alias.
+    let mut defaults: HashMap<_, _> = config
+        .defaults
+        .iter()
+        .map(|(id, choices)| (namespace_aliases.resolve(id), choices.clone()))
+        .collect();
+    if config.use_defaults {
+        // the choices given with -c or in the configuration win over the ones of the recipes.
+        for var in vars.vars_iter() {
+            if let Some(default) = var.default_choice() {
+                defaults.entry(var.name()).or_insert_with(|| vec![default]);
+            }
+        }
+    }
+    let mut vars = vars.with_namespace_aliases(namespace_aliases.clone());
+    vars.set_defaults(&defaults);
+    vars.expand_wildcards();
     vars.ensure_no_missing_dependency()?;
 
+    let mut aliases_vec = vec![];
+    let mut aliases_sources = HashSet::new();
+    for f in config.aliases_files() {
+        for alias in read_aliases_with_namespaces(&f, &namespaces)? {
+            if config.is_disabled(alias.namespace()) {
+                continue;
+            }
+            if !aliases_sources.insert(alias.identifier()) {
+                warnings.push(Warning::DuplicateAlias {
+                    identifier: alias.identifier(),
+                    source_file: f.clone(),
+                });
+            }
+            sources.add_alias(alias.identifier(), &f);
+            aliases_vec.push(alias);
+        }
+    }
+    let known_vars = vars.identifiers();
+    let aliases_vec = aliases_vec
+        .into_iter()
+        .map(|alias| alias.with_wildcards_expanded(&known_vars));
+    let aliases = AliasesRepository::with_namespace_aliases(aliases_vec, namespace_aliases)?
+        .with_hidden(config.all)
+        .with_impacts(config.impacts.clone())
+        .with_tags(config.tags.clone())
+        .with_usage(history.frecency()?);
+
     Ok(Environment {
         aliases,
         vars,
@@ -111,9 +362,40 @@ pub fn from_settings(config: AppSettings) -> Result<Environment> {
         config,
         history,
         cache,
+        warnings,
+        sources,
     })
 }
 
+// is_writable tells whether the state file at path can be written, home directories of
+// corporate images or live systems can be read-only. Other errors are left to the state.
+fn is_writable(path: &Path) -> bool {
+    match OpenOptions::new().append(true).create(true).open(path) {
+        Ok(_) => true,
+        Err(err) => !is_read_only(&err),
+    }
+}
+
+// is_dir_writable tells whether files can be created in the directory at path. A directory
+// can't be opened for writing, a file is created in it and removed instead.
+fn is_dir_writable(path: &Path) -> bool {
+    let probe = path.join(format!(".sam-probe-{}", std::process::id()));
+    match OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            true
+        }
+        Err(err) => !is_read_only(&err),
+    }
+}
+
+fn is_read_only(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem
+    )
+}
+
 fn logger_instance(silent: bool) -> Result<Rc<dyn SamLogger>> {
     if !silent {
         Ok(Rc::new(FileLogger::new()))
@@ -139,8 +421,36 @@ pub enum ErrorEnvironment {
     AliasRepository(#[from] ErrorsAliasesRepository),
     #[error("could not open the history file because\n-> {0}")]
     ErrAliasHistory(#[from] ErrorAliasHistory),
+    #[error("could not initialize the executors\n-> {0}")]
+    Executor(Box<dyn std::error::Error>),
+    #[error("could not open the output\n-> {0}")]
+    Output(io::Error),
     #[error("could not open the vars cache because\n-> {0}")]
     CacheError(#[from] CacheError),
     #[error("could not initialize logger -> {0}")]
     LoggerError(#[from] ErrorLogger),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{is_dir_writable, is_writable};
+    use sam_utils::fsutils::{TempDirectory, TempFile};
+
+    #[test]
+    fn test_is_writable() {
+        let f = TempFile::new().expect("failed to created a temporary file");
+        assert!(is_writable(&f.path));
+        // sysfs refuses new files, even to root.
+        #[cfg(target_os = "linux")]
+        assert!(!is_writable(std::path::Path::new("/sys/sam-history")));
+    }
+
+    #[test]
+    fn test_is_dir_writable() {
+        let dir = TempDirectory::new().expect("failed to create a temporary directory");
+        assert!(is_dir_writable(&dir.path));
+        assert_eq!(std::fs::read_dir(&dir.path).unwrap().count(), 0);
+        #[cfg(target_os = "linux")]
+        assert!(!is_dir_writable(std::path::Path::new("/sys")));
+    }
+}
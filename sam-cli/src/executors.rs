@@ -1,27 +1,669 @@
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
 use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, Stdio};
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
+use crate::diff;
+use crate::export_engine::posix_quote;
+use crate::shell_history::ShellHistory;
 use log::debug;
-use sam_core::engines::{ErrorSamEngine, SamExecutor};
-use sam_core::entities::aliases::ResolvedAlias;
+use sam_core::engines::{
+    CommandReport, ErrorSamEngine, ExecutionReport, SamConfirmation, SamExecutor,
+};
+use sam_core::entities::aliases::{ResolvedAlias, StdinMode};
+use sam_core::entities::identifiers::Identifier;
+use sam_persistence::AliasHistory;
 
-use sam_terminals::processes::ShellCommand;
+use sam_terminals::input::{stdin_is_piped, terminal, user_input};
+use sam_terminals::processes::{Shell, ShellCommand};
+use sam_terminals::streams::detach_stdin;
 use sam_terminals::tmux::{Tmux, TmuxError};
 
-pub fn make_executor(dry: bool) -> Result<Rc<dyn SamExecutor>, Box<dyn std::error::Error>> {
+pub fn make_executor(
+    dry: bool,
+    quote: bool,
+    jobs: Option<usize>,
+    record: bool,
+    history: AliasHistory,
+) -> Result<Rc<dyn SamExecutor>, Box<dyn std::error::Error>> {
     if dry {
-        Ok(Rc::new(DryExecutor {}))
-    } else if env::var("TMUX").is_ok() {
+        return Ok(Rc::new(DryRunExecutor { quote }));
+    }
+    let inner: Rc<dyn SamExecutor> = if let Some(max_concurrency) = jobs {
+        debug!(
+            "running up to {} commands at once, using ParallelExecutor",
+            max_concurrency
+        );
+        Rc::new(ParallelExecutor::new(max_concurrency))
+    } else if env::var("TMUX").is_ok() && !stdin_is_piped() {
+        // what's piped to sam can't reach the panes tmux creates.
         debug!("running inside tmux, using TmuxExecutor");
-        let executor = TmuxExecutor::with_current_session()?;
-        Ok(Rc::new(executor))
+        Rc::new(TmuxExecutor::with_current_session()?)
     } else {
         debug!("no tmux detected, using ShellExecutor");
-        Ok(Rc::new(ShellExecutor {}))
+        Rc::new(ShellExecutor {})
+    };
+    let inner = Rc::new(InteractiveExecutor {
+        inner,
+        piped: stdin_is_piped(),
+    });
+    let inner = Rc::new(CaptureExecutor {
+        inner,
+        history,
+        record,
+    });
+    let inner = Rc::new(OutcomeExecutor { inner });
+    Ok(Rc::new(StdinExecutor {
+        inner,
+        piped: stdin_is_piped(),
+    }))
+}
+
+/// DiffExecutor shows what changed since the previous execution of an alias
+/// before handing it over to the inner executor.
+pub struct DiffExecutor {
+    inner: Rc<dyn SamExecutor>,
+    previous: HashMap<Identifier, ResolvedAlias>,
+}
+
+impl DiffExecutor {
+    /// new expects the executions from the history, oldest first.
+    pub fn new(inner: Rc<dyn SamExecutor>, history: impl Iterator<Item = ResolvedAlias>) -> Self {
+        let previous = history.map(|r| (r.name().clone(), r)).collect();
+        DiffExecutor { inner, previous }
+    }
+}
+
+impl SamExecutor for DiffExecutor {
+    fn execute_resolved_alias(
+        &self,
+        alias: &ResolvedAlias,
+        env_variables: &HashMap<String, String>,
+    ) -> Result<ExecutionReport, ErrorSamEngine> {
+        if let Some(previous) = self.previous.get(alias.name()) {
+            show_diff(previous, alias, &mut io::stderr())?;
+        }
+        self.inner.execute_resolved_alias(alias, env_variables)
+    }
+}
+
+fn show_diff(
+    previous: &ResolvedAlias,
+    alias: &ResolvedAlias,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let changed_choices = changed_choices(previous, alias);
+    // sensitive choices are compared masked, as they were recorded in the history.
+    let commands: Vec<String> = alias.commands().iter().map(|c| alias.mask(c)).collect();
+    let commands_changed = previous.commands() != commands.as_slice();
+    if changed_choices.is_empty() && !commands_changed {
+        return writeln!(output, "{} is unchanged since its last run.", alias.name());
+    }
+    writeln!(
+        output,
+        "\n{}{}changes since the last run of {}:{}",
+        crossterm::style::Attribute::Bold,
+        crossterm::style::SetForegroundColor(crossterm::style::Color::DarkYellow),
+        alias.name(),
+        crossterm::style::Attribute::Reset,
+    )?;
+    for (var, before, after) in changed_choices {
+        writeln!(output, "  {}: {} -> {}", var, before, after)?;
+    }
+    let nb_commands = previous.commands().len().max(commands.len());
+    for i in 0..nb_commands {
+        let old = previous.commands().get(i).map(String::as_str).unwrap_or("");
+        let new = commands.get(i).map(String::as_str).unwrap_or("");
+        let words = diff::word_diff(old, new);
+        if diff::has_changes(&words) {
+            writeln!(output, "- {}", diff::render_old(&words))?;
+            writeln!(output, "+ {}", diff::render_new(&words))?;
+        }
+    }
+    Ok(())
+}
+
+// changed_choices returns the vars whose choices differ from the previous
+// execution along with their previous and current values, sensitive vars are left out.
+fn changed_choices(
+    previous: &ResolvedAlias,
+    alias: &ResolvedAlias,
+) -> Vec<(String, String, String)> {
+    let values = |r: &ResolvedAlias, id: &Identifier| {
+        r.choice(id)
+            .map(|c| {
+                c.iter()
+                    .map(|c| c.value())
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            })
+            .unwrap_or_default()
+    };
+    let mut changed: Vec<(String, String, String)> = alias
+        .choices()
+        .keys()
+        .filter(|id| !alias.sensitive().contains(id))
+        .map(|id| (id.to_string(), values(previous, id), values(alias, id)))
+        .filter(|(_, before, after)| before != after)
+        .collect();
+    changed.sort();
+    changed
+}
+
+/// ShellHistoryExecutor appends the commands of an alias to the history file of the shell
+/// once they ran, so that Ctrl-R finds them. Commands holding sensitive choices are left out.
+pub struct ShellHistoryExecutor {
+    inner: Rc<dyn SamExecutor>,
+    shell: ShellHistory,
+    file: PathBuf,
+}
+
+impl ShellHistoryExecutor {
+    pub fn new(inner: Rc<dyn SamExecutor>, shell: ShellHistory, file: PathBuf) -> Self {
+        ShellHistoryExecutor { inner, shell, file }
+    }
+}
+
+impl SamExecutor for ShellHistoryExecutor {
+    fn execute_resolved_alias(
+        &self,
+        alias: &ResolvedAlias,
+        env_variables: &HashMap<String, String>,
+    ) -> Result<ExecutionReport, ErrorSamEngine> {
+        let started = SystemTime::now();
+        let result = self.inner.execute_resolved_alias(alias, env_variables);
+        // the commands didn't run when the alias wasn't confirmed or couldn't be spawned.
+        let report = result?;
+        let commands: Vec<String> = alias
+            .commands()
+            .iter()
+            .filter(|cmd| alias.mask(cmd) == **cmd)
+            .cloned()
+            .collect();
+        if commands.is_empty() {
+            return Ok(report);
+        }
+        let duration = started.elapsed().unwrap_or_default();
+        if let Err(err) = self.shell.append(&self.file, &commands, started, duration) {
+            eprintln!(
+                "{}could not add the commands to {}: {}{}",
+                crossterm::style::SetForegroundColor(crossterm::style::Color::DarkYellow),
+                self.file.display(),
+                err,
+                crossterm::style::Attribute::Reset
+            );
+        }
+        Ok(report)
+    }
+}
+
+/// MAX_CAPTURED_LINES is the number of lines, the last ones, kept from the output of an alias.
+const MAX_CAPTURED_LINES: usize = 100;
+
+/// MAX_RECORDED_BYTES is the size of the transcript, the last lines, kept from what an alias
+/// prints when its output is recorded.
+const MAX_RECORDED_BYTES: usize = 64 * 1024;
+
+/// CaptureExecutor runs the aliases that capture or record their output itself, one command
+/// after the other, and stores the lines they print in the history. The other aliases are
+/// handed over to the inner executor.
+pub struct CaptureExecutor {
+    inner: Rc<dyn SamExecutor>,
+    history: AliasHistory,
+    // the output of all the aliases is recorded, `--record`.
+    record: bool,
+}
+
+impl SamExecutor for CaptureExecutor {
+    fn execute_resolved_alias(
+        &self,
+        alias: &ResolvedAlias,
+        env_variables: &HashMap<String, String>,
+    ) -> Result<ExecutionReport, ErrorSamEngine> {
+        let captures = alias.captures_output();
+        let records = (self.record || alias.records_output()) && alias.interactive() != Some(true);
+        if !captures && !records {
+            return self.inner.execute_resolved_alias(alias, env_variables);
+        }
+        println!();
+        eprintln!();
+        let env_variables = alias_env_variables(alias, env_variables);
+        let mut lines = VecDeque::new();
+        let transcript = Arc::new(Mutex::new(Transcript::default()));
+        let mut reports = Vec::with_capacity(alias.commands().len());
+        // batch aliases don't wait for input, unless they take what's piped to sam.
+        let detached = alias.interactive() == Some(false)
+            && !matches!(
+                alias.stdin(),
+                Some(StdinMode::Passthrough | StdinMode::Require)
+            );
+        for cmd in alias.commands() {
+            let mut command = alias_command(alias, cmd)?;
+            command.envs(&env_variables).stdout(Stdio::piped());
+            if detached {
+                command.stdin(Stdio::null());
+            }
+            if records {
+                command.stderr(Stdio::piped());
+            }
+            let started = Instant::now();
+            let mut child = command.spawn()?;
+            // stderr is read on its own thread so that neither pipe fills up while the other is read.
+            let stderr = child.stderr.take().map(|stderr| {
+                let transcript = Arc::clone(&transcript);
+                thread::spawn(move || {
+                    capture(stderr, &mut io::stderr(), |line| {
+                        if let Ok(mut transcript) = transcript.lock() {
+                            transcript.push(line);
+                        }
+                    })
+                })
+            });
+            if let Some(stdout) = child.stdout.take() {
+                capture(stdout, &mut io::stdout(), |line| {
+                    if records {
+                        if let Ok(mut transcript) = transcript.lock() {
+                            transcript.push(line.clone());
+                        }
+                    }
+                    keep_last(&mut lines, line);
+                })?;
+            }
+            if let Some(stderr) = stderr {
+                stderr.join().map_err(|_| ErrorSamEngine::ExitCode)??;
+            }
+            let exit_status = child.wait()?;
+            reports.push(command_report(alias, cmd, started, exit_status.code())?);
+        }
+        let mut outputs: Vec<String> = lines.into();
+        if captures {
+            self.history
+                .record_outputs(alias.name(), outputs.clone())
+                .map_err(|err| ErrorSamEngine::HistoryNotAvailable(Box::new(err)))?;
+        }
+        if records {
+            let transcript = std::mem::take(&mut *transcript.lock().expect("poisoned transcript"));
+            // the choices of sensitive vars never reach the history.
+            outputs = transcript.lines().map(|l| alias.mask(&l)).collect();
+            self.history
+                .record_transcript(alias.name(), outputs.clone())
+                .map_err(|err| ErrorSamEngine::HistoryNotAvailable(Box::new(err)))?;
+        }
+        Ok(ExecutionReport::new(reports).with_outputs(outputs))
     }
 }
 
+/// InteractiveExecutor runs the interactive aliases, ssh or psql sessions, itself: one
+/// command after the other in the foreground, with the terminal as their stdin even when
+/// something is piped to sam. The other aliases are handed over to the inner executor,
+/// which may run them in parallel or in tmux panes.
+pub struct InteractiveExecutor {
+    inner: Rc<dyn SamExecutor>,
+    piped: bool,
+}
+
+impl SamExecutor for InteractiveExecutor {
+    fn execute_resolved_alias(
+        &self,
+        alias: &ResolvedAlias,
+        env_variables: &HashMap<String, String>,
+    ) -> Result<ExecutionReport, ErrorSamEngine> {
+        if alias.interactive() != Some(true) {
+            return self.inner.execute_resolved_alias(alias, env_variables);
+        }
+        println!();
+        eprintln!();
+        let env_variables = alias_env_variables(alias, env_variables);
+        let mut reports = Vec::with_capacity(alias.commands().len());
+        for cmd in alias.commands() {
+            let mut command = alias_command(alias, cmd)?;
+            command.envs(&env_variables);
+            if let Some(tty) = terminal().filter(|_| self.piped) {
+                command.stdin(tty);
+            }
+            let started = Instant::now();
+            let exit_status = command.status()?;
+            reports.push(command_report(alias, cmd, started, exit_status.code())?);
+        }
+        Ok(ExecutionReport::new(reports))
+    }
+}
+
+// capture copies what a command prints to the output as it comes and hands over its lines.
+fn capture(
+    input: impl Read,
+    output: &mut impl Write,
+    mut on_line: impl FnMut(String),
+) -> io::Result<()> {
+    let mut input = BufReader::new(input);
+    let mut line = vec![];
+    while input.read_until(b'\n', &mut line)? > 0 {
+        output.write_all(&line)?;
+        output.flush()?;
+        on_line(String::from_utf8_lossy(&line).trim_end().to_string());
+        line.clear();
+    }
+    Ok(())
+}
+
+// keep_last keeps the last MAX_CAPTURED_LINES lines captured.
+fn keep_last(lines: &mut VecDeque<String>, line: String) {
+    lines.push_back(line);
+    if lines.len() > MAX_CAPTURED_LINES {
+        lines.pop_front();
+    }
+}
+
+/// Transcript keeps the last lines printed by an alias, up to MAX_RECORDED_BYTES.
+#[derive(Debug, Default)]
+struct Transcript {
+    lines: VecDeque<String>,
+    size: usize,
+    dropped: usize,
+}
+
+impl Transcript {
+    fn push(&mut self, line: String) {
+        self.size += line.len();
+        self.lines.push_back(line);
+        while self.size > MAX_RECORDED_BYTES && self.lines.len() > 1 {
+            if let Some(dropped) = self.lines.pop_front() {
+                self.size -= dropped.len();
+                self.dropped += 1;
+            }
+        }
+    }
+
+    // lines returns the kept lines, preceded by the number of the dropped ones.
+    fn lines(self) -> impl Iterator<Item = String> {
+        let dropped = (self.dropped > 0).then(|| format!("[{} lines dropped]", self.dropped));
+        dropped.into_iter().chain(self.lines)
+    }
+}
+
+/// OutcomeExecutor displays the success or the failure message of an alias
+/// and runs its on_success or on_failure hook once the inner executor ran it.
+pub struct OutcomeExecutor {
+    inner: Rc<dyn SamExecutor>,
+}
+
+impl SamExecutor for OutcomeExecutor {
+    fn execute_resolved_alias(
+        &self,
+        alias: &ResolvedAlias,
+        env_variables: &HashMap<String, String>,
+    ) -> Result<ExecutionReport, ErrorSamEngine> {
+        let result = self.inner.execute_resolved_alias(alias, env_variables);
+        let succeeded = result.as_ref().is_ok_and(ExecutionReport::succeeded);
+        if let Some(message) = outcome_message(alias, succeeded) {
+            eprintln!("{}", message);
+        }
+        if let Some(hook) = outcome_hook(alias, succeeded) {
+            let mut env_variables = alias_env_variables(alias, env_variables);
+            if let Ok(report) = &result {
+                env_variables.insert(
+                    String::from("SAM_EXIT_CODE"),
+                    report.exit_code().to_string(),
+                );
+            }
+            let mut command = alias_command(alias, hook)?;
+            command.envs(&env_variables);
+            let started = Instant::now();
+            let status = command.status();
+            log_process(
+                alias,
+                hook,
+                started,
+                status.as_ref().ok().and_then(|s| s.code()),
+            );
+            if !status.is_ok_and(|status| status.success()) {
+                eprintln!(
+                    "{}the {} hook of {} failed{}",
+                    crossterm::style::SetForegroundColor(crossterm::style::Color::DarkYellow),
+                    if succeeded {
+                        "on_success"
+                    } else {
+                        "on_failure"
+                    },
+                    alias.name(),
+                    crossterm::style::Attribute::Reset
+                );
+            }
+        }
+        result
+    }
+}
+
+fn outcome_hook(alias: &ResolvedAlias, succeeded: bool) -> Option<&str> {
+    if succeeded {
+        alias.on_success()
+    } else {
+        alias.on_failure()
+    }
+}
+
+fn outcome_message(alias: &ResolvedAlias, succeeded: bool) -> Option<String> {
+    if succeeded {
+        alias.success_message().map(|m| {
+            format!(
+                "{}{}{}{}",
+                crossterm::style::Attribute::Bold,
+                crossterm::style::SetForegroundColor(crossterm::style::Color::DarkGreen),
+                m,
+                crossterm::style::Attribute::Reset
+            )
+        })
+    } else {
+        alias.failure_message().map(|m| {
+            format!(
+                "{}{}{}{}",
+                crossterm::style::Attribute::Bold,
+                crossterm::style::SetForegroundColor(crossterm::style::Color::DarkRed),
+                m,
+                crossterm::style::Attribute::Reset
+            )
+        })
+    }
+}
+
+/// make_confirmation returns what's asked of the user before an alias is recorded
+/// in the history and run. Nothing is asked in dry runs, nothing runs.
+pub fn make_confirmation(dry: bool) -> Rc<dyn SamConfirmation> {
+    if dry {
+        Rc::new(NoConfirmation {})
+    } else {
+        Rc::new(UserConfirmation {})
+    }
+}
+
+/// NoConfirmation lets all the aliases run.
+pub struct NoConfirmation {}
+
+impl SamConfirmation for NoConfirmation {
+    fn confirm(&self, _: &ResolvedAlias) -> Result<(), ErrorSamEngine> {
+        Ok(())
+    }
+}
+
+/// UserConfirmation prints the hint of deprecated aliases and asks the user to
+/// type the confirmation phrase of critical aliases, or to answer the question of
+/// aliases that require a confirmation.
+pub struct UserConfirmation {}
+
+impl SamConfirmation for UserConfirmation {
+    fn confirm(&self, alias: &ResolvedAlias) -> Result<(), ErrorSamEngine> {
+        write_deprecation(alias, &mut io::stderr())?;
+        if let Some(phrase) = alias.confirmation() {
+            let mut input = BufReader::new(user_input());
+            let confirmed = confirm(alias, phrase, &mut input, &mut io::stderr())?;
+            if !confirmed {
+                return Err(ErrorSamEngine::ConfirmationMismatch(alias.name().clone()));
+            }
+        } else if let Some(question) = alias.question() {
+            let mut input = BufReader::new(user_input());
+            let confirmed = ask(alias, question, &mut input, &mut io::stderr())?;
+            if !confirmed {
+                return Err(ErrorSamEngine::ConfirmationDeclined(alias.name().clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// StdinExecutor checks the `stdin` field of an alias against what's piped to sam before
+/// handing it over to the inner executor. When something is piped to an alias that doesn't
+/// set it, the user is asked on the terminal whether the commands get it, they read
+/// /dev/null otherwise. Without a terminal to ask on, they get it as they always did.
+pub struct StdinExecutor {
+    inner: Rc<dyn SamExecutor>,
+    piped: bool,
+}
+
+impl SamExecutor for StdinExecutor {
+    fn execute_resolved_alias(
+        &self,
+        alias: &ResolvedAlias,
+        env_variables: &HashMap<String, String>,
+    ) -> Result<ExecutionReport, ErrorSamEngine> {
+        match (alias.stdin(), self.piped) {
+            (Some(StdinMode::Require), false) => {
+                return Err(ErrorSamEngine::StdinRequired(alias.name().clone()))
+            }
+            (Some(StdinMode::Forbid), true) => {
+                return Err(ErrorSamEngine::StdinForbidden(alias.name().clone()))
+            }
+            // interactive aliases read the terminal, what's piped is left to no one.
+            (None, true) if alias.interactive() != Some(true) => {
+                if let Some(tty) = terminal() {
+                    if !pass_stdin(alias, &mut BufReader::new(tty), &mut io::stderr())? {
+                        detach_stdin()?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        self.inner.execute_resolved_alias(alias, env_variables)
+    }
+}
+
+fn pass_stdin(
+    alias: &ResolvedAlias,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<bool> {
+    write!(
+        output,
+        "{}something is piped to sam, pass it to {}?{} [y/N] ",
+        crossterm::style::Attribute::Bold,
+        alias.name(),
+        crossterm::style::Attribute::Reset
+    )?;
+    output.flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn confirm(
+    alias: &ResolvedAlias,
+    phrase: &str,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<bool> {
+    writeln!(
+        output,
+        "\n{}{}{} is a critical alias, it will run:{}",
+        crossterm::style::Attribute::Bold,
+        crossterm::style::SetForegroundColor(crossterm::style::Color::DarkRed),
+        alias.name(),
+        crossterm::style::Attribute::Reset,
+    )?;
+    write_annotation(alias, output)?;
+    for cmd in alias.commands() {
+        writeln!(output, "  {}", alias.mask(cmd))?;
+    }
+    write!(output, "type '{}' to confirm: ", phrase)?;
+    output.flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    Ok(answer.trim() == phrase)
+}
+
+// write_deprecation tells that the alias is deprecated and what to use instead.
+fn write_deprecation(alias: &ResolvedAlias, output: &mut impl Write) -> io::Result<()> {
+    match alias.deprecation() {
+        Some(hint) => writeln!(
+            output,
+            "{}{}{} is deprecated: {}{}",
+            crossterm::style::Attribute::Bold,
+            crossterm::style::SetForegroundColor(crossterm::style::Color::DarkYellow),
+            alias.name(),
+            hint,
+            crossterm::style::Attribute::Reset
+        ),
+        None => Ok(()),
+    }
+}
+
+// write_annotation shows the impact and the estimated duration of the alias before it's confirmed.
+fn write_annotation(alias: &ResolvedAlias, output: &mut impl Write) -> io::Result<()> {
+    match alias.annotation() {
+        Some(annotation) => writeln!(
+            output,
+            "  {}{}{}{}",
+            crossterm::style::Attribute::Bold,
+            crossterm::style::SetForegroundColor(crossterm::style::Color::DarkYellow),
+            annotation,
+            crossterm::style::Attribute::Reset
+        ),
+        None => Ok(()),
+    }
+}
+
+fn ask(
+    alias: &ResolvedAlias,
+    question: &str,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<bool> {
+    writeln!(output, "\n{} will run:", alias.name())?;
+    write_annotation(alias, output)?;
+    for cmd in alias.commands() {
+        writeln!(output, "  {}", alias.mask(cmd))?;
+    }
+    write!(
+        output,
+        "{}{}{} [y/N] ",
+        crossterm::style::Attribute::Bold,
+        question,
+        crossterm::style::Attribute::Reset
+    )?;
+    output.flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// alias_env_variables merges the environment declared by an alias on top of
+/// the environment variables coming from the configuration, and the choices
+/// exported as environment variables.
+fn alias_env_variables(
+    alias: &ResolvedAlias,
+    env_variables: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    let mut merged = alias.choices_env();
+    merged.extend(env_variables.clone());
+    merged.extend(alias.env().clone());
+    merged
+}
+
 pub struct TmuxExecutor {
     current_session: String,
     windows: Vec<String>,
@@ -54,7 +696,7 @@ impl SamExecutor for TmuxExecutor {
         &self,
         alias: &ResolvedAlias,
         env_variables: &HashMap<String, String>,
-    ) -> Result<i32, ErrorSamEngine> {
+    ) -> Result<ExecutionReport, ErrorSamEngine> {
         println!();
         eprintln!();
         let window_name = self.window_name_for_alias(alias);
@@ -64,11 +706,15 @@ impl SamExecutor for TmuxExecutor {
         if commands.len() == 1 {
             ShellExecutor {}.execute_resolved_alias(alias, env_variables)
         } else {
+            let env_variables = alias_env_variables(alias, env_variables);
             for cmd in alias.commands() {
                 let shcmd =
-                    ShellCommand::new(cmd.clone()).replace_env_vars_in_command(env_variables)?;
+                    ShellCommand::new(cmd.clone()).replace_env_vars_in_command(&env_variables)?;
                 let command = shcmd.value();
-                debug!("execute_resolved_alias: running command {:?}", cmd);
+                debug!(
+                    "execute_resolved_alias: running command {:?}",
+                    alias.mask(cmd)
+                );
                 t.run_command_in_new_pane(&window_name, command, directory.to_str().unwrap_or("."))
                     .map_err(|err| ErrorSamEngine::ExecutorFailure(Box::new(err)))?;
                 t.set_layout(sam_terminals::tmux::WindowLayout::Tiled, &window_name)
@@ -76,7 +722,12 @@ impl SamExecutor for TmuxExecutor {
             }
             t.set_layout(sam_terminals::tmux::WindowLayout::Tiled, &window_name)
                 .map_err(|err| ErrorSamEngine::ExecutorFailure(Box::new(err)))?;
-            Ok(0)
+            // the commands keep running in their panes, sam doesn't wait for them.
+            let commands = commands
+                .iter()
+                .map(|cmd| CommandReport::detached(alias.mask(cmd)))
+                .collect();
+            Ok(ExecutionReport::new(commands))
         }
     }
 }
@@ -88,26 +739,608 @@ impl SamExecutor for ShellExecutor {
         &self,
         alias: &ResolvedAlias,
         env_variables: &HashMap<String, String>,
-    ) -> Result<i32, ErrorSamEngine> {
+    ) -> Result<ExecutionReport, ErrorSamEngine> {
         println!();
         eprintln!();
+        let env_variables = alias_env_variables(alias, env_variables);
+        let mut reports = Vec::with_capacity(alias.commands().len());
         for cmd in alias.commands() {
-            let mut command: std::process::Command = ShellCommand::new(cmd).into();
-            command.envs(env_variables);
+            let mut command = alias_command(alias, cmd)?;
+            command.envs(&env_variables);
+            let started = Instant::now();
             let exit_status = command.status()?;
-            exit_status.code().ok_or(ErrorSamEngine::ExitCode)?;
+            reports.push(command_report(alias, cmd, started, exit_status.code())?);
+        }
+        Ok(ExecutionReport::new(reports))
+    }
+}
+
+/// ParallelExecutor runs the commands of a resolved alias concurrently, with at most
+/// `max_concurrency` commands running at the same time. It returns the exit code
+/// of the first command, in the order of the alias commands, that did not succeed.
+pub struct ParallelExecutor {
+    max_concurrency: usize,
+}
+
+impl ParallelExecutor {
+    pub fn new(max_concurrency: usize) -> Self {
+        ParallelExecutor {
+            max_concurrency: max_concurrency.max(1),
+        }
+    }
+
+    fn run<'a>(
+        &self,
+        alias: &'a ResolvedAlias,
+        env_variables: &HashMap<String, String>,
+        running: &mut Vec<Process<'a>>,
+        reports: &mut Vec<(usize, CommandReport)>,
+    ) -> Result<(), ErrorSamEngine> {
+        for (index, cmd) in alias.commands().iter().enumerate() {
+            while running.len() >= self.max_concurrency {
+                Self::wait_any(alias, running, reports)?;
+            }
+            debug!(
+                "execute_resolved_alias: spawning command {:?}",
+                alias.mask(cmd)
+            );
+            let mut command = alias_command(alias, cmd)?;
+            command.envs(env_variables);
+            running.push((index, command.spawn()?, cmd, Instant::now()));
+        }
+        while !running.is_empty() {
+            Self::wait_any(alias, running, reports)?;
+        }
+        Ok(())
+    }
+
+    // wait_any waits for whichever of the running commands exits first.
+    fn wait_any(
+        alias: &ResolvedAlias,
+        running: &mut Vec<Process>,
+        reports: &mut Vec<(usize, CommandReport)>,
+    ) -> Result<(), ErrorSamEngine> {
+        loop {
+            for i in 0..running.len() {
+                if let Some(exit_status) = running[i].1.try_wait()? {
+                    let (index, _, cmd, started) = running.swap_remove(i);
+                    reports.push((
+                        index,
+                        command_report(alias, cmd, started, exit_status.code())?,
+                    ));
+                    return Ok(());
+                }
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+// the command index in the alias, its process, its command line and when it started.
+type Process<'a> = (usize, Child, &'a str, Instant);
+
+// how often ParallelExecutor checks whether one of the commands exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+impl SamExecutor for ParallelExecutor {
+    fn execute_resolved_alias(
+        &self,
+        alias: &ResolvedAlias,
+        env_variables: &HashMap<String, String>,
+    ) -> Result<ExecutionReport, ErrorSamEngine> {
+        println!();
+        eprintln!();
+        let env_variables = alias_env_variables(alias, env_variables);
+        let mut running = Vec::with_capacity(self.max_concurrency);
+        let mut reports = Vec::with_capacity(alias.commands().len());
+        let result = self.run(alias, &env_variables, &mut running, &mut reports);
+        // the commands still running are stopped rather than left behind.
+        for (_, mut child, _, _) in running {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        result?;
+        // the commands are reported in the order of the alias, not the one they exited in.
+        reports.sort_by_key(|(index, _)| *index);
+        Ok(ExecutionReport::new(
+            reports.into_iter().map(|(_, report)| report).collect(),
+        ))
+    }
+}
+
+// alias_command makes the command running a command line of an alias, with the shell
+// of the alias and its login_shell when they override the default ones.
+fn alias_command(
+    alias: &ResolvedAlias,
+    cmd: &str,
+) -> Result<std::process::Command, ErrorSamEngine> {
+    let default = Shell::default_shell();
+    let shell = match alias.shell() {
+        Some(shell) => Shell::parse(shell)
+            .map_err(|err| ErrorSamEngine::ExecutorFailure(Box::new(err)))?
+            .with_login(default.is_login()),
+        None => default,
+    };
+    let shell = match alias.login_shell() {
+        Some(login) => shell.with_login(login),
+        None => shell,
+    };
+    Ok(ShellCommand::new(cmd).with_shell(Some(shell)).into())
+}
+
+// log_process logs a command of an alias once its process exited, the choices of
+// sensitive vars are masked.
+fn log_process(alias: &ResolvedAlias, cmd: &str, started: Instant, code: Option<i32>) {
+    debug!(
+        "[SAM][ alias = '{}' ] command='{}' duration={}ms exit_code={}",
+        alias.name(),
+        alias.mask(cmd),
+        started.elapsed().as_millis(),
+        code.map_or_else(|| String::from("none"), |code| code.to_string()),
+    );
+}
+
+// command_report logs a command of an alias once its process exited and reports how it ran,
+// a command killed by a signal has no exit code to report.
+fn command_report(
+    alias: &ResolvedAlias,
+    cmd: &str,
+    started: Instant,
+    code: Option<i32>,
+) -> Result<CommandReport, ErrorSamEngine> {
+    log_process(alias, cmd, started, code);
+    let code = code.ok_or(ErrorSamEngine::ExitCode)?;
+    Ok(CommandReport::new(alias.mask(cmd), code, started.elapsed()))
+}
+
+/// DryRunExecutor prints the resolved commands of an alias to stdout, one per
+/// line, instead of running them so that they can be copied or piped elsewhere.
+/// With quote, each command is shell-quoted, ready to be passed to `sh -c`.
+pub struct DryRunExecutor {
+    quote: bool,
+}
+
+impl SamExecutor for DryRunExecutor {
+    fn execute_resolved_alias(
+        &self,
+        alias: &ResolvedAlias,
+        _env_variables: &HashMap<String, String>,
+    ) -> Result<ExecutionReport, ErrorSamEngine> {
+        for line in dry_run_lines(alias, self.quote) {
+            println!("{}", line);
         }
-        Ok(0)
+        Ok(ExecutionReport::default())
     }
 }
 
-pub struct DryExecutor {}
-impl SamExecutor for DryExecutor {
+/// PrintExecutor writes the resolved commands of an alias, one per line, to the
+/// output it's given, the stdout sam was started with for `sam resolve`, so that
+/// they can be captured while the pickers are drawn on the terminal.
+pub struct PrintExecutor {
+    pub output: RefCell<File>,
+}
+
+impl SamExecutor for PrintExecutor {
     fn execute_resolved_alias(
         &self,
-        _alias: &ResolvedAlias,
+        alias: &ResolvedAlias,
         _env_variables: &HashMap<String, String>,
-    ) -> Result<i32, ErrorSamEngine> {
-        Ok(0)
+    ) -> Result<ExecutionReport, ErrorSamEngine> {
+        let mut output = self.output.borrow_mut();
+        for line in dry_run_lines(alias, false) {
+            writeln!(output, "{}", line)?;
+        }
+        output.flush()?;
+        Ok(ExecutionReport::default())
+    }
+}
+
+fn dry_run_lines(alias: &ResolvedAlias, quote: bool) -> Vec<String> {
+    alias
+        .commands()
+        .iter()
+        .map(|cmd| if quote { posix_quote(cmd) } else { cmd.clone() })
+        .collect()
+}
+
+/// SandboxExecutor prints the commands of a resolved alias instead of running them.
+pub struct SandboxExecutor {}
+impl SamExecutor for SandboxExecutor {
+    fn execute_resolved_alias(
+        &self,
+        alias: &ResolvedAlias,
+        _env_variables: &HashMap<String, String>,
+    ) -> Result<ExecutionReport, ErrorSamEngine> {
+        println!();
+        println!("In a real run, sam would now execute:");
+        for cmd in alias.commands() {
+            println!(
+                "  {}{}{}",
+                crossterm::style::Attribute::Bold,
+                cmd,
+                crossterm::style::Attribute::Reset
+            );
+        }
+        Ok(ExecutionReport::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        alias_command, alias_env_variables, ask, capture, changed_choices, confirm, dry_run_lines,
+        keep_last, outcome_message, pass_stdin, show_diff, write_deprecation, DryRunExecutor,
+        InteractiveExecutor, OutcomeExecutor, ParallelExecutor, StdinExecutor, Transcript,
+        MAX_CAPTURED_LINES, MAX_RECORDED_BYTES,
+    };
+    use sam_core::engines::{ErrorSamEngine, SamExecutor};
+    use sam_core::entities::aliases::{Impact, ResolvedAlias, StdinMode};
+    use sam_core::entities::choices::Choice;
+    use sam_core::entities::identifiers::Identifier;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::rc::Rc;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sam-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn resolved_alias(commands: &[&str]) -> ResolvedAlias {
+        ResolvedAlias::new(
+            Identifier::with_namespace("parallel", Some("test")),
+            String::from("runs commands in parallel"),
+            String::from("{{ command }}"),
+            commands.iter().map(|c| c.to_string()).collect(),
+            HashMap::new(),
+        )
+    }
+
+    #[test]
+    fn test_alias_env_variables() {
+        let alias = resolved_alias(&["true"]).with_env(maplit::hashmap! {
+            String::from("CLUSTER") => String::from("prod"),
+        });
+        let config_env = maplit::hashmap! {
+            String::from("CLUSTER") => String::from("dev"),
+            String::from("REGION") => String::from("eu"),
+        };
+        let merged = alias_env_variables(&alias, &config_env);
+        assert_eq!(merged.get("CLUSTER"), Some(&String::from("prod")));
+        assert_eq!(merged.get("REGION"), Some(&String::from("eu")));
+
+        let alias = ResolvedAlias::new(
+            Identifier::with_namespace("pods", Some("k8s")),
+            String::from("list pods"),
+            String::from("kubectl get pods -n {{ ns }}"),
+            vec![String::from("kubectl get pods -n default")],
+            maplit::hashmap! {
+                Identifier::with_namespace("ns", Some("k8s")) => vec![Choice::new("default", None)],
+            },
+        );
+        let merged = alias_env_variables(&alias, &config_env);
+        assert_eq!(
+            merged.get("SAM_CHOICE_K8S_NS"),
+            Some(&String::from("default"))
+        );
+    }
+
+    #[test]
+    fn test_capture() {
+        let input = "i-1\r\ni-2\n\nno newline";
+        let mut output = vec![];
+        let mut lines = std::collections::VecDeque::new();
+        capture(input.as_bytes(), &mut output, |l| keep_last(&mut lines, l)).unwrap();
+        assert_eq!(output, input.as_bytes());
+        assert_eq!(lines, vec!["i-1", "i-2", "", "no newline"]);
+
+        let input: String = (0..MAX_CAPTURED_LINES + 2)
+            .map(|i| format!("{}\n", i))
+            .collect();
+        capture(input.as_bytes(), &mut vec![], |l| keep_last(&mut lines, l)).unwrap();
+        assert_eq!(lines.len(), MAX_CAPTURED_LINES);
+        assert_eq!(lines.front().map(String::as_str), Some("2"));
+    }
+
+    #[test]
+    fn test_transcript() {
+        let mut transcript = Transcript::default();
+        transcript.push(String::from("first"));
+        transcript.push(String::from("second"));
+        assert_eq!(
+            transcript.lines().collect::<Vec<_>>(),
+            vec!["first", "second"]
+        );
+
+        let mut transcript = Transcript::default();
+        transcript.push(String::from("first"));
+        transcript.push("x".repeat(MAX_RECORDED_BYTES));
+        assert_eq!(
+            transcript.lines().collect::<Vec<_>>(),
+            vec![
+                String::from("[1 lines dropped]"),
+                "x".repeat(MAX_RECORDED_BYTES)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interactive_executor() {
+        // the inner executor only prints the commands.
+        let executor = InteractiveExecutor {
+            inner: Rc::new(DryRunExecutor { quote: false }),
+            piped: false,
+        };
+        let alias = resolved_alias(&["exit 3"]).with_interactive(Some(true));
+        let code = executor
+            .execute_resolved_alias(&alias, &HashMap::new())
+            .unwrap()
+            .exit_code();
+        assert_eq!(code, 3);
+
+        for interactive in [None, Some(false)] {
+            let alias = resolved_alias(&["exit 3"]).with_interactive(interactive);
+            let code = executor
+                .execute_resolved_alias(&alias, &HashMap::new())
+                .unwrap()
+                .exit_code();
+            assert_eq!(code, 0);
+        }
+    }
+
+    #[test]
+    fn test_dry_run_lines() {
+        let alias = resolved_alias(&["echo it's done", "ls /tmp"]);
+        assert_eq!(
+            dry_run_lines(&alias, false),
+            vec!["echo it's done", "ls /tmp"]
+        );
+        assert_eq!(
+            dry_run_lines(&alias, true),
+            vec!["'echo it'\\''s done'", "'ls /tmp'"]
+        );
+    }
+
+    #[test]
+    fn test_confirm() {
+        let alias = resolved_alias(&["dropdb production"]);
+        let mut output = vec![];
+        let mut input = "test::parallel\n".as_bytes();
+        assert!(confirm(&alias, "test::parallel", &mut input, &mut output).unwrap());
+        let mut input = "y\n".as_bytes();
+        assert!(!confirm(&alias, "test::parallel", &mut input, &mut output).unwrap());
+        assert!(String::from_utf8_lossy(&output).contains("dropdb production"));
+
+        let mut input = "y\n".as_bytes();
+        assert!(ask(&alias, "drop it?", &mut input, &mut output).unwrap());
+        let mut input = "\n".as_bytes();
+        assert!(!ask(&alias, "drop it?", &mut input, &mut output).unwrap());
+
+        let alias = alias.with_impact(Some(Impact::High), Some(String::from("5m")));
+        let mut output = vec![];
+        let mut input = "y\n".as_bytes();
+        assert!(ask(&alias, "drop it?", &mut input, &mut output).unwrap());
+        assert!(String::from_utf8_lossy(&output).contains("high impact, estimated duration: 5m"));
+
+        let mut output = vec![];
+        write_deprecation(&alias, &mut output).unwrap();
+        assert!(output.is_empty());
+        let alias = alias.with_deprecated(Some(String::from("use test::serial instead")));
+        write_deprecation(&alias, &mut output).unwrap();
+        assert!(String::from_utf8_lossy(&output)
+            .contains("test::parallel is deprecated: use test::serial instead"));
+    }
+
+    #[test]
+    fn test_stdin_executor() {
+        let executor = |piped| StdinExecutor {
+            inner: Rc::new(DryRunExecutor { quote: false }),
+            piped,
+        };
+        let env = HashMap::new();
+        let alias = resolved_alias(&["jq ."]).with_stdin(Some(StdinMode::Require));
+        assert!(matches!(
+            executor(false).execute_resolved_alias(&alias, &env),
+            Err(ErrorSamEngine::StdinRequired(_))
+        ));
+        assert_eq!(
+            executor(true)
+                .execute_resolved_alias(&alias, &env)
+                .unwrap()
+                .exit_code(),
+            0
+        );
+
+        let alias = resolved_alias(&["vim"]).with_stdin(Some(StdinMode::Forbid));
+        assert!(matches!(
+            executor(true).execute_resolved_alias(&alias, &env),
+            Err(ErrorSamEngine::StdinForbidden(_))
+        ));
+        assert_eq!(
+            executor(false)
+                .execute_resolved_alias(&alias, &env)
+                .unwrap()
+                .exit_code(),
+            0
+        );
+
+        let alias = resolved_alias(&["cat"]).with_stdin(Some(StdinMode::Passthrough));
+        assert_eq!(
+            executor(true)
+                .execute_resolved_alias(&alias, &env)
+                .unwrap()
+                .exit_code(),
+            0
+        );
+        let alias = resolved_alias(&["cat"]);
+        assert_eq!(
+            executor(false)
+                .execute_resolved_alias(&alias, &env)
+                .unwrap()
+                .exit_code(),
+            0
+        );
+
+        let mut output = vec![];
+        assert!(pass_stdin(&alias, &mut "yes\n".as_bytes(), &mut output).unwrap());
+        assert!(!pass_stdin(&alias, &mut "\n".as_bytes(), &mut output).unwrap());
+        assert!(String::from_utf8_lossy(&output).contains("pass it to test::parallel?"));
+    }
+
+    #[test]
+    fn test_outcome_message() {
+        let alias = resolved_alias(&["./deploy.sh"]);
+        assert_eq!(outcome_message(&alias, true), None);
+        let alias = alias.with_messages(Some(String::from("deployed")), None);
+        assert!(outcome_message(&alias, true).unwrap().contains("deployed"));
+        assert_eq!(outcome_message(&alias, false), None);
+    }
+
+    #[test]
+    fn test_outcome_hooks() {
+        let dir = temp_dir("outcome-hooks");
+        let path = dir.join("exit_code");
+        let executor = OutcomeExecutor {
+            inner: Rc::new(ParallelExecutor::new(1)),
+        };
+        let hook = format!("echo $SAM_EXIT_CODE > {}", path.display());
+        let alias = resolved_alias(&["exit 3"]).with_hooks(None, Some(hook.clone()));
+        let code = executor
+            .execute_resolved_alias(&alias, &HashMap::new())
+            .unwrap()
+            .exit_code();
+        assert_eq!(code, 3);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "3\n");
+        std::fs::remove_file(&path).unwrap();
+
+        let alias = resolved_alias(&["true"]).with_hooks(None, Some(hook));
+        executor
+            .execute_resolved_alias(&alias, &HashMap::new())
+            .unwrap()
+            .exit_code();
+        assert!(!path.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parallel_executor() {
+        let executor = ParallelExecutor::new(2);
+        let alias = resolved_alias(&["exit 0", "exit 3", "exit 0", "exit 4"]);
+        let report = executor
+            .execute_resolved_alias(&alias, &HashMap::new())
+            .unwrap();
+        assert_eq!(report.exit_code(), 3);
+        let codes: Vec<Option<i32>> = report.commands().iter().map(|c| c.exit_code()).collect();
+        assert_eq!(codes, vec![Some(0), Some(3), Some(0), Some(4)]);
+        assert_eq!(report.commands()[1].command(), "exit 3");
+
+        let alias = resolved_alias(&["true", "true", "true"]);
+        let code = executor
+            .execute_resolved_alias(&alias, &HashMap::new())
+            .unwrap()
+            .exit_code();
+        assert_eq!(code, 0);
+    }
+
+    #[test]
+    fn test_parallel_executor_slots() {
+        let dir = temp_dir("parallel-slots");
+        let done = dir.join("done");
+        let seen = dir.join("seen");
+        // the first command waits for the quick ones, they can only finish if they take the
+        // free slot in turn while it runs.
+        let first = format!(
+            "for i in $(seq 100); do [ \"$(cat {0} 2>/dev/null | wc -l)\" -ge 3 ] && break; sleep 0.1; done; wc -l < {0} > {1}",
+            done.display(),
+            seen.display()
+        );
+        let quick = format!("echo done >> {}", done.display());
+        let alias = resolved_alias(&[&first, &quick, &quick, &quick]);
+        let report = ParallelExecutor::new(2)
+            .execute_resolved_alias(&alias, &HashMap::new())
+            .unwrap();
+        assert_eq!(report.commands()[0].command(), first);
+        assert_eq!(std::fs::read_to_string(&seen).unwrap().trim(), "3");
+
+        // the commands still running are killed when one of them can't be reported.
+        let touched = dir.join("touched");
+        let slow = format!(
+            "for i in $(seq 100); do sleep 0.1; done; touch {}",
+            touched.display()
+        );
+        let alias = resolved_alias(&[&slow, "kill -9 $$"]);
+        assert!(ParallelExecutor::new(2)
+            .execute_resolved_alias(&alias, &HashMap::new())
+            .is_err());
+        // the killed command is waited for, it can't touch the file anymore.
+        assert!(!touched.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_alias_shell() {
+        let executor = ParallelExecutor::new(1);
+        // $0 is the shell the command runs with.
+        let alias =
+            resolved_alias(&["test \"$0\" = sh && exit 3"]).with_shell(Some(String::from("sh -c")));
+        let code = executor
+            .execute_resolved_alias(&alias, &HashMap::new())
+            .unwrap()
+            .exit_code();
+        assert_eq!(code, 3);
+
+        // a login shell reads the profile of the user, it is checked without running it.
+        let alias = resolved_alias(&["true"])
+            .with_shell(Some(String::from("bash")))
+            .with_login_shell(Some(true));
+        let command = alias_command(&alias, "true").unwrap();
+        assert_eq!(command.get_program(), "bash");
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            vec!["-l", "-c", "true"]
+        );
+
+        let alias = resolved_alias(&["true"]).with_shell(Some(String::from("'sh")));
+        assert!(matches!(
+            executor.execute_resolved_alias(&alias, &HashMap::new()),
+            Err(ErrorSamEngine::ExecutorFailure(_))
+        ));
+    }
+
+    #[test]
+    fn test_changed_choices() {
+        let with_cluster = |cluster: &str| {
+            ResolvedAlias::new(
+                Identifier::with_namespace("pods", Some("k8s")),
+                String::from("list pods"),
+                String::from("kubectl --context {{ cluster }} get pods -n {{ ns }}"),
+                vec![format!("kubectl --context {} get pods -n default", cluster)],
+                maplit::hashmap! {
+                    Identifier::with_namespace("cluster", Some("k8s")) => vec![Choice::new(cluster, None)],
+                    Identifier::with_namespace("ns", Some("k8s")) => vec![Choice::new("default", None)],
+                },
+            )
+        };
+        assert_eq!(
+            changed_choices(&with_cluster("prod"), &with_cluster("staging")),
+            vec![(
+                String::from("k8s::cluster"),
+                String::from("prod"),
+                String::from("staging")
+            )]
+        );
+        assert!(changed_choices(&with_cluster("prod"), &with_cluster("prod")).is_empty());
+
+        let sensitive = with_cluster("staging")
+            .with_sensitive(vec![Identifier::with_namespace("cluster", Some("k8s"))]);
+        assert!(changed_choices(&with_cluster("prod"), &sensitive).is_empty());
+        let mut output = vec![];
+        show_diff(&sensitive.clone().redact(), &sensitive, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "k8s::pods is unchanged since its last run.\n"
+        );
     }
 }
@@ -0,0 +1,236 @@
+use sam_core::entities::aliases::Alias;
+use sam_persistence::repositories::AliasesRepository;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+pub const SHELLS: [&str; 3] = ["bash", "zsh", "fish"];
+
+impl FromStr for Shell {
+    type Err = ErrorExportEngine;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            other => Err(ErrorExportEngine::UnsupportedShell(other.to_string())),
+        }
+    }
+}
+
+// ZSH_WIDGET replaces the first `{{ var }}` of the command line with the choices made
+// for the var in sam, the picker is drawn on the terminal while the value is captured.
+const ZSH_WIDGET: &str = r#"
+sam-resolve-var() {
+    local value choice
+    if [[ $BUFFER =~ '\{\{ *([A-Za-z0-9_:]+) *\}\}' ]]; then
+        value=$(sam resolve-var "${match[1]}" --print </dev/tty)
+        if [[ $? -eq 0 && -n $value ]]; then
+            choice=${(j: :)${(f)value}}
+            BUFFER="${BUFFER[1,MBEGIN-1]}${choice}${BUFFER[MEND+1,-1]}"
+            CURSOR=$(( MBEGIN - 1 + ${#choice} ))
+        fi
+    fi
+    zle reset-prompt
+}
+zle -N sam-resolve-var
+bindkey '^Xv' sam-resolve-var
+"#;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportCommand {
+    pub shell: Shell,
+}
+
+pub struct ExportEngine {
+    pub aliases: AliasesRepository,
+}
+
+impl ExportEngine {
+    pub fn run(&self, cmd: ExportCommand) -> Result<i32> {
+        let mut aliases = self.aliases.aliases();
+        aliases.sort_by_key(|a| a.full_name().to_string());
+        print!("{}", export(&aliases, cmd.shell));
+        Ok(0)
+    }
+}
+
+/// export renders a shell function for each alias that runs it through sam
+/// and completions for the aliases of `sam alias`, along with the widget resolving
+/// vars in the command line for zsh.
+fn export(aliases: &[Alias], shell: Shell) -> String {
+    let mut out = format!(
+        "# sam aliases, generated by `sam export --shell {}`\n",
+        SHELLS[shell as usize]
+    );
+    for alias in aliases {
+        out.push_str(&function(alias, shell));
+    }
+    out.push('\n');
+    out.push_str(&completions(aliases, shell));
+    if shell == Shell::Zsh {
+        out.push_str(ZSH_WIDGET);
+    }
+    out
+}
+
+fn function_name(alias: &Alias) -> String {
+    alias
+        .full_name()
+        .replace("::", "_")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn function(alias: &Alias, shell: Shell) -> String {
+    match shell {
+        Shell::Bash | Shell::Zsh => format!(
+            // the arguments of the function are the ones of the alias, given after `--`.
+            "# {}\n{}() {{ sam alias {} -- \"$@\"; }}\n",
+            alias.desc().replace('\n', " "),
+            function_name(alias),
+            alias.full_name(),
+        ),
+        Shell::Fish => format!(
+            "function {} --description {}\n    sam alias {} -- $argv\nend\n",
+            function_name(alias),
+            fish_quote(alias.desc()),
+            alias.full_name(),
+        ),
+    }
+}
+
+fn completions(aliases: &[Alias], shell: Shell) -> String {
+    match shell {
+        Shell::Bash => {
+            let words: Vec<String> = aliases.iter().map(|a| a.full_name().to_string()).collect();
+            format!(
+                "_sam_export_complete() {{
+    local cur prev
+    cur=\"${{COMP_WORDS[COMP_CWORD]}}\"
+    prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"
+    if declare -F _get_comp_words_by_ref >/dev/null; then
+        _get_comp_words_by_ref -n : cur prev
+    fi
+    if [ \"$prev\" = \"alias\" ]; then
+        COMPREPLY=($(compgen -W {} -- \"$cur\"))
+        if declare -F __ltrim_colon_completions >/dev/null; then
+            __ltrim_colon_completions \"$cur\"
+        fi
+    fi
+}}
+complete -F _sam_export_complete sam
+",
+                posix_quote(&words.join(" "))
+            )
+        }
+        Shell::Zsh => {
+            let mut out =
+                String::from("_sam_export_complete() {\n    local -a aliases\n    aliases=(\n");
+            for alias in aliases {
+                let entry = format!(
+                    "{}:{}",
+                    alias.full_name().replace(':', "\\:"),
+                    alias.desc().replace('\n', " ")
+                );
+                out.push_str(&format!("        {}\n", posix_quote(&entry)));
+            }
+            out.push_str(
+                "    )
+    if (( CURRENT == 3 )) && [[ ${words[2]} == alias ]]; then
+        _describe 'alias' aliases
+    fi
+}
+compdef _sam_export_complete sam
+",
+            );
+            out
+        }
+        Shell::Fish => aliases
+            .iter()
+            .map(|alias| {
+                format!(
+                    "complete -c sam -n '__fish_seen_subcommand_from alias' -f -a {} -d {}\n",
+                    fish_quote(&alias.full_name()),
+                    fish_quote(alias.desc()),
+                )
+            })
+            .collect(),
+    }
+}
+
+pub fn posix_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn fish_quote(value: &str) -> String {
+    format!(
+        "'{}'",
+        value
+            .replace('\\', "\\\\")
+            .replace('\'', "\\'")
+            .replace('\n', " ")
+    )
+}
+
+pub type Result<T> = std::result::Result<T, ErrorExportEngine>;
+
+#[derive(Debug, Error)]
+pub enum ErrorExportEngine {
+    #[error("shell {0} is not supported, supported shells are bash, zsh and fish")]
+    UnsupportedShell(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{completions, export, function, Shell};
+    use sam_core::entities::aliases::Alias;
+    use sam_core::entities::namespaces::NamespaceUpdater;
+
+    fn alias() -> Alias {
+        let mut alias = Alias::new("list", "list's a directory", "ls {{ directory }}");
+        NamespaceUpdater::update(&mut alias, "dirs");
+        alias
+    }
+
+    #[test]
+    fn test_functions() {
+        let alias = alias();
+        assert_eq!(
+            function(&alias, Shell::Bash),
+            "# list's a directory\ndirs_list() { sam alias dirs::list -- \"$@\"; }\n"
+        );
+        assert_eq!(
+            function(&alias, Shell::Fish),
+            "function dirs_list --description 'list\\'s a directory'\n    sam alias dirs::list -- $argv\nend\n"
+        );
+    }
+
+    #[test]
+    fn test_completions() {
+        let aliases = vec![alias()];
+        assert!(completions(&aliases, Shell::Bash).contains("compgen -W 'dirs::list'"));
+        assert!(
+            completions(&aliases, Shell::Zsh).contains("'dirs\\:\\:list:list'\\''s a directory'")
+        );
+        assert_eq!(
+            completions(&aliases, Shell::Fish),
+            "complete -c sam -n '__fish_seen_subcommand_from alias' -f -a 'dirs::list' -d 'list\\'s a directory'\n"
+        );
+    }
+
+    #[test]
+    fn test_zsh_widget() {
+        let aliases = vec![alias()];
+        assert!(export(&aliases, Shell::Zsh).ends_with("bindkey '^Xv' sam-resolve-var\n"));
+        assert!(!export(&aliases, Shell::Bash).contains("sam resolve-var"));
+    }
+}
@@ -1,3 +1,9 @@
+use crate::cli::OutputFormat;
+use crate::output::Output;
+use chrono::TimeZone;
+use sam_core::entities::aliases::ResolvedAlias;
+use sam_core::entities::choices::Choice;
+use sam_core::entities::commands::Command;
 use sam_core::{
     algorithms::{resolver::Resolver, VarsCollection, VarsDefaultValues},
     engines::{
@@ -6,15 +12,29 @@ use sam_core::{
     },
     entities::identifiers::Identifier,
 };
-use sam_persistence::{AliasHistory, ErrorAliasHistory, HistoryEntry};
+use sam_persistence::{AliasHistory, ErrorAliasHistory, HistoryEntry, HISTORY_VERSION};
 use sam_tui::modal_view::{ModalView, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum HistoryCommand {
     InterractWithHistory,
+    SearchAndExecute,
     ExecuteLastExecutedAlias,
+    ModifyThenExecuteLastAlias,
     DisplayLastExecutedAlias,
+    /// Export prints the history as json lines, one entry per line.
+    Export,
+    /// Import reads entries exported with Export from a file, `-` reads stdin.
+    Import(PathBuf),
+    /// Show prints the last entry of the history, or the last one of an alias,
+    /// with the output it recorded.
+    Show(Option<Identifier>),
 }
 
 pub struct HistoryEngine<
@@ -25,6 +45,8 @@ pub struct HistoryEngine<
 > {
     pub sam_engine: SamEngine<R, AR, VR, DV>,
     pub history: AliasHistory,
+    pub output: OutputFormat,
+    pub pager: bool,
 }
 
 impl<
@@ -36,10 +58,98 @@ impl<
 {
     pub fn run(&mut self, command: HistoryCommand) -> Result<i32> {
         match command {
+            HistoryCommand::InterractWithHistory if self.output == OutputFormat::Json => {
+                self.print_history_json()
+            }
             HistoryCommand::InterractWithHistory => self.interract_with_history(),
+            HistoryCommand::SearchAndExecute => self.search_and_execute(),
             HistoryCommand::ExecuteLastExecutedAlias => self.execute_last_executed_alias(),
+            HistoryCommand::ModifyThenExecuteLastAlias => self.modify_then_execute_last_alias(),
             HistoryCommand::DisplayLastExecutedAlias => self.display_last_executed_alias(),
+            HistoryCommand::Export => self.export_history(),
+            HistoryCommand::Import(path) => self.import_history(&path),
+            HistoryCommand::Show(alias) => self.show_entry(alias.as_ref()),
+        }
+    }
+
+    fn print_history_json(&self) -> Result<i32> {
+        let entries: Vec<HistoryEntryJson> = self
+            .history
+            .entries()?
+            .map(HistoryEntryJson::from)
+            .collect();
+        let mut output = Output::new(self.pager);
+        writeln!(output, "{}", serde_json::to_string_pretty(&entries)?)
+            .and_then(|_| output.finish())
+            .map_err(|err| ErrorHistoryEngine::Export(PathBuf::from("-"), err))?;
+        Ok(0)
+    }
+
+    fn show_entry(&self, alias: Option<&Identifier>) -> Result<i32> {
+        let entry = self
+            .history
+            .entries()?
+            .filter(|e| alias.is_none_or(|a| e.r.name() == a))
+            .last();
+        let entry = match (entry, alias) {
+            (Some(entry), _) => entry,
+            (None, Some(alias)) => {
+                println!("{} is not in the history", alias);
+                return Ok(0);
+            }
+            (None, None) => {
+                println!("history empty");
+                return Ok(0);
+            }
+        };
+        let text = if self.output == OutputFormat::Json {
+            serde_json::to_string_pretty(&HistoryEntryJson::from(entry))? + "\n"
+        } else {
+            describe_entry(&entry, &chrono::Local)
+        };
+        let mut output = Output::new(self.pager);
+        write!(output, "{}", text)
+            .and_then(|_| output.finish())
+            .map_err(|err| ErrorHistoryEngine::Export(PathBuf::from("-"), err))?;
+        Ok(0)
+    }
+
+    fn export_history(&self) -> Result<i32> {
+        let mut stdout = io::stdout().lock();
+        for entry in self.history.entries()? {
+            let line = serde_json::to_string(&HistoryEntryJson::from(entry))?;
+            writeln!(stdout, "{}", line)
+                .map_err(|err| ErrorHistoryEngine::Export(PathBuf::from("-"), err))?;
+        }
+        Ok(0)
+    }
+
+    fn import_history(&self, path: &PathBuf) -> Result<i32> {
+        let reader: Box<dyn BufRead> = if path.as_os_str() == "-" {
+            Box::new(BufReader::new(io::stdin()))
+        } else {
+            let file =
+                File::open(path).map_err(|err| ErrorHistoryEngine::Import(path.clone(), err))?;
+            Box::new(BufReader::new(file))
+        };
+        let mut entries = vec![];
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line.map_err(|err| ErrorHistoryEngine::Import(path.clone(), err))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: HistoryEntryJson = serde_json::from_str(&line)
+                .map_err(|err| ErrorHistoryEngine::MalformedEntry(idx + 1, err))?;
+            entries.push(HistoryEntry::from(entry));
         }
+        let read = entries.len();
+        let imported = self.history.import(entries.into_iter())?;
+        println!(
+            "imported {} entries, {} were already in the history",
+            imported,
+            read - imported
+        );
+        Ok(0)
     }
 
     fn interract_with_history(&mut self) -> Result<i32> {
@@ -68,19 +178,105 @@ impl<
         Ok(0)
     }
 
-    fn execute_last_executed_alias(&self) -> Result<i32> {
+    /// search_and_execute fuzzy searches the history by alias, choices and commands
+    /// and runs the selected entry again, exactly as it ran before.
+    fn search_and_execute(&mut self) -> Result<i32> {
+        let entries = search_entries(self.history.entries()?);
+        if entries.is_empty() {
+            println!("history empty");
+            return Ok(0);
+        }
+        let controller = ModalView::new(entries, vec![], false);
+        let selection_o = controller
+            .run()
+            .and_then(|v| v.values().take(1).next())
+            .map(|e| e.entry.r);
+        match selection_o {
+            Some(alias) if !alias.redacted().is_empty() => self.resolve_redacted_again(&alias),
+            Some(alias) => self.execute_again(alias),
+            None => Ok(0),
+        }
+    }
+
+    fn execute_last_executed_alias(&mut self) -> Result<i32> {
         let resolved_alias_o = self.history.get_last()?;
-        if let Some(alias) = resolved_alias_o {
-            Ok(self
-                .sam_engine
-                .executor
-                .execute_resolved_alias(&alias, &self.sam_engine.env_variables)?)
+        if let Some(alias) = resolved_alias_o
+            .as_ref()
+            .filter(|a| !a.redacted().is_empty())
+        {
+            self.resolve_redacted_again(alias)
+        } else if let Some(alias) = resolved_alias_o {
+            self.execute_again(alias)
         } else {
             println!("history empty");
             Ok(0)
         }
     }
 
+    /// modify_then_execute_last_alias lets the user pick the vars of the last
+    /// executed alias to resolve again, the choices of the other vars are kept.
+    fn modify_then_execute_last_alias(&mut self) -> Result<i32> {
+        let last = match self.history.entries()?.last() {
+            Some(entry) => entry,
+            None => {
+                println!("history empty");
+                return Ok(0);
+            }
+        };
+        let alias_id = last.r.name().clone();
+        self.sam_engine
+            .aliases
+            .get(&alias_id)
+            .ok_or_else(|| ErrorHistoryEngine::AliasNotAvailable(alias_id.clone(), last.pwd))?;
+
+        let mut choices: Vec<ChoicesWrapper> = last
+            .r
+            .choices()
+            .iter()
+            .map(|(id, c)| ChoicesWrapper::new(id, c))
+            .collect();
+        choices.sort_by(|a, b| a.text.cmp(&b.text));
+        let selection = if choices.is_empty() {
+            HashSet::new()
+        } else {
+            match ModalView::new(choices, vec![], true).run() {
+                Some(response) => response.values().map(|c| c.var).collect(),
+                None => return Ok(0),
+            }
+        };
+
+        let kept = kept_choices(last.r.choices(), &selection, &self.sam_engine.vars);
+        self.sam_engine.defaults.set_defaults(&kept);
+        Ok(self
+            .sam_engine
+            .run(ExecuteAlias { alias: alias_id })?
+            .exit_code())
+    }
+
+    /// resolve_redacted_again runs an entry whose sensitive choices were left out of the
+    /// history, they are resolved again while the other vars keep their recorded choices.
+    // execute_again runs an entry of the history as it was, once confirmed. It is recorded
+    // again so that the history keeps the order the aliases ran in.
+    fn execute_again(&self, alias: ResolvedAlias) -> Result<i32> {
+        self.sam_engine.confirmation.confirm(&alias)?;
+        self.sam_engine.history.borrow_mut().put(alias.clone())?;
+        Ok(self
+            .sam_engine
+            .executor
+            .execute_resolved_alias(&alias, &self.sam_engine.env_variables)?
+            .exit_code())
+    }
+
+    fn resolve_redacted_again(&mut self, alias: &ResolvedAlias) -> Result<i32> {
+        self.sam_engine.defaults.set_defaults(alias.choices());
+        Ok(self
+            .sam_engine
+            .run(ExecuteAlias {
+                alias: alias.name().clone(),
+            })?
+            .exit_code())
+    }
+
     fn display_last_executed_alias(&self) -> Result<i32> {
         let resolved_alias_o = self.history.get_last()?;
         if let Some(alias) = resolved_alias_o {
@@ -95,6 +291,230 @@ impl<
     }
 }
 
+// describe_entry shows when and where an entry ran, its commands and what they printed.
+fn describe_entry<Tz: TimeZone>(entry: &HistoryEntry, tz: &Tz) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let ran_at = entry
+        .timestamp
+        .and_then(|ts| tz.timestamp_opt(ts, 0).single())
+        .map(|t| format!("{} ", t.format("%Y-%m-%d %H:%M:%S")))
+        .unwrap_or_default();
+    let mut text = format!(
+        "{}{}{}{} in {}\n",
+        ran_at,
+        crossterm::style::Attribute::Bold,
+        entry.r.name(),
+        crossterm::style::Attribute::Reset,
+        entry.pwd,
+    );
+    for command in entry.r.commands() {
+        text.push_str(&format!("$ {}\n", command));
+    }
+    text.push('\n');
+    if entry.r.transcript().is_empty() {
+        text.push_str("the output wasn't recorded, run the alias with --record to record it.\n");
+    }
+    for line in entry.r.transcript() {
+        text.push_str(line);
+        text.push('\n');
+    }
+    text
+}
+
+// kept_choices returns the choices that don't need to be resolved again: the
+// vars that weren't selected and don't depend on a selected var.
+fn kept_choices(
+    choices: &HashMap<Identifier, Vec<Choice>>,
+    selection: &HashSet<Identifier>,
+    vars: &dyn VarsCollection,
+) -> HashMap<Identifier, Vec<Choice>> {
+    let mut modified = selection.clone();
+    loop {
+        let dependents: Vec<Identifier> = choices
+            .keys()
+            .filter(|id| !modified.contains(*id))
+            .filter(|id| {
+                vars.get(id)
+                    .is_some_and(|v| v.dependencies().iter().any(|dep| modified.contains(dep)))
+            })
+            .cloned()
+            .collect();
+        if dependents.is_empty() {
+            break;
+        }
+        modified.extend(dependents);
+    }
+    choices
+        .iter()
+        .filter(|(id, _)| !modified.contains(*id))
+        .map(|(id, c)| (id.clone(), c.clone()))
+        .collect()
+}
+
+/// ChoicesWrapper displays the choices of a var from the history.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ChoicesWrapper {
+    var: Identifier,
+    text: String,
+    choices: String,
+}
+
+impl ChoicesWrapper {
+    fn new(var: &Identifier, choices: &[Choice]) -> Self {
+        let values: Vec<&str> = choices.iter().map(|c| c.value()).collect();
+        ChoicesWrapper {
+            var: var.clone(),
+            text: var.to_string(),
+            choices: values.join(", "),
+        }
+    }
+}
+
+impl Value for ChoicesWrapper {
+    fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn desc(&self) -> Option<&str> {
+        Some(&self.choices)
+    }
+
+    fn preview(&self) -> String {
+        format!("{} was {}\n", self.text, self.choices)
+    }
+}
+
+// identifiers can't be used as json object keys, choices are keyed by their string representation.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct HistoryEntryJson {
+    alias: String,
+    desc: String,
+    original_alias: String,
+    commands: Vec<String>,
+    choices: BTreeMap<String, Vec<Choice>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    confirmation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    question: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    success_message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    failure_message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    on_success: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    on_failure: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    export_choices: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    redacted: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    capture: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    outputs: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    record_output: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    transcript: Vec<String>,
+    pwd: String,
+    timestamp: Option<i64>,
+}
+
+impl HistoryEntryJson {
+    pub(crate) fn alias(&self) -> &str {
+        &self.alias
+    }
+
+    pub(crate) fn commands(&self) -> &[String] {
+        &self.commands
+    }
+
+    pub(crate) fn pwd(&self) -> &str {
+        &self.pwd
+    }
+}
+
+impl From<HistoryEntry> for HistoryEntryJson {
+    fn from(entry: HistoryEntry) -> Self {
+        HistoryEntryJson {
+            alias: entry.r.name().to_string(),
+            desc: entry.r.desc().to_string(),
+            original_alias: entry.r.original_alias().to_string(),
+            commands: entry.r.commands().to_vec(),
+            choices: entry
+                .r
+                .choices()
+                .iter()
+                .map(|(id, c)| (id.to_string(), c.clone()))
+                .collect(),
+            env: entry
+                .r
+                .env()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            confirmation: entry.r.confirmation().map(String::from),
+            question: entry.r.question().map(String::from),
+            success_message: entry.r.success_message().map(String::from),
+            failure_message: entry.r.failure_message().map(String::from),
+            on_success: entry.r.on_success().map(String::from),
+            on_failure: entry.r.on_failure().map(String::from),
+            export_choices: entry.r.export_choices(),
+            redacted: entry.r.redacted().iter().map(|id| id.to_string()).collect(),
+            capture: entry.r.captures_output(),
+            outputs: entry.r.outputs().to_vec(),
+            record_output: entry.r.records_output(),
+            transcript: entry.r.transcript().to_vec(),
+            pwd: entry.pwd,
+            timestamp: entry.timestamp,
+        }
+    }
+}
+
+impl From<HistoryEntryJson> for HistoryEntry {
+    fn from(entry: HistoryEntryJson) -> Self {
+        let choices = entry
+            .choices
+            .into_iter()
+            .map(|(id, c)| (Identifier::from_str(&id), c))
+            .collect();
+        let r = ResolvedAlias::new(
+            Identifier::from_str(&entry.alias),
+            entry.desc,
+            entry.original_alias,
+            entry.commands,
+            choices,
+        )
+        .with_env(entry.env.into_iter().collect())
+        .with_confirmation(entry.confirmation)
+        .with_question(entry.question)
+        .with_messages(entry.success_message, entry.failure_message)
+        .with_hooks(entry.on_success, entry.on_failure)
+        .with_export_choices(entry.export_choices)
+        .with_redacted(
+            entry
+                .redacted
+                .iter()
+                .map(|id| Identifier::from_str(id))
+                .collect(),
+        )
+        .with_capture(entry.capture)
+        .with_outputs(entry.outputs)
+        .with_record_output(entry.record_output)
+        .with_transcript(entry.transcript);
+        HistoryEntry {
+            r,
+            pwd: entry.pwd,
+            timestamp: entry.timestamp,
+            version: HISTORY_VERSION,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct HistoryEntryWrapper(HistoryEntry);
 
@@ -122,7 +542,93 @@ impl Value for HistoryEntryWrapper {
     }
 }
 
+/// HistorySearchEntry is a history entry that can be searched by its
+/// alias and commands, the text, as well as by its choices, the description.
+#[derive(Debug, Clone)]
+struct HistorySearchEntry {
+    entry: HistoryEntry,
+    text: String,
+    choices: String,
+}
+
+impl From<HistoryEntry> for HistorySearchEntry {
+    fn from(entry: HistoryEntry) -> Self {
+        let text = format!("{}  {}", entry.r.name(), entry.r.commands().join(" ; "));
+        let mut choices: Vec<String> = entry
+            .r
+            .choices()
+            .iter()
+            .map(|(id, c)| {
+                let values: Vec<&str> = c.iter().map(|c| c.value()).collect();
+                format!("{}={}", id, values.join(","))
+            })
+            .collect();
+        choices.sort();
+        HistorySearchEntry {
+            entry,
+            text,
+            choices: choices.join(" "),
+        }
+    }
+}
+
+impl Eq for HistorySearchEntry {}
+
+impl PartialEq for HistorySearchEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.entry == other.entry
+    }
+}
+
+impl std::hash::Hash for HistorySearchEntry {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.text.hash(state);
+    }
+}
+
+impl Value for HistorySearchEntry {
+    fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn desc(&self) -> Option<&str> {
+        if self.choices.is_empty() {
+            None
+        } else {
+            Some(&self.choices)
+        }
+    }
+
+    fn preview(&self) -> String {
+        format!("{}\nRan from: {}\n", self.entry.r, self.entry.pwd)
+    }
+}
+
+// search_entries lists the most recent entries first, an alias that ran
+// the same commands several times is listed once.
+fn search_entries(entries: impl Iterator<Item = HistoryEntry>) -> Vec<HistorySearchEntry> {
+    let mut entries: Vec<HistoryEntry> = entries.collect();
+    entries.reverse();
+    let mut seen = std::collections::HashSet::new();
+    entries
+        .into_iter()
+        .map(HistorySearchEntry::from)
+        .filter(|e| seen.insert(e.text.clone()))
+        .collect()
+}
+
 pub type Result<T> = std::result::Result<T, ErrorHistoryEngine>;
+/// migrate_history upgrades the entries of the history file to the current format.
+pub fn migrate_history(history_file: &Path) -> Result<i32> {
+    let migrated = AliasHistory::migrate(history_file)?;
+    if migrated == 0 {
+        println!("the history is up to date");
+    } else {
+        println!("migrated {} history entries", migrated);
+    }
+    Ok(0)
+}
+
 #[derive(Debug, Error)]
 pub enum ErrorHistoryEngine {
     #[error("could not run a command\n-> {0}")]
@@ -131,4 +637,129 @@ pub enum ErrorHistoryEngine {
     AliasNotAvailable(Identifier, String),
     #[error("could not read from history\n-> {0}")]
     History(#[from] ErrorAliasHistory),
+    #[error("could not serialize the history\n-> {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("could not export the history to {0}\n-> {1}")]
+    Export(PathBuf, io::Error),
+    #[error("could not import the history from {0}\n-> {1}")]
+    Import(PathBuf, io::Error),
+    #[error("line {0} is not a valid history entry\n-> {1}")]
+    MalformedEntry(usize, serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{describe_entry, kept_choices, search_entries, HistoryEntryJson};
+    use sam_core::entities::aliases::ResolvedAlias;
+    use sam_core::entities::choices::Choice;
+    use sam_core::entities::identifiers::Identifier;
+    use sam_core::entities::vars::Var;
+    use sam_persistence::repositories::VarsRepository;
+    use sam_persistence::{HistoryEntry, HISTORY_VERSION};
+    use sam_tui::modal_view::Value;
+
+    fn entry(cluster: &str, timestamp: i64) -> HistoryEntry {
+        HistoryEntry {
+            r: ResolvedAlias::new(
+                Identifier::with_namespace("pods", Some("k8s")),
+                String::from("list pods"),
+                String::from("kubectl --context {{ cluster }} get pods"),
+                vec![format!("kubectl --context {} get pods", cluster)],
+                maplit::hashmap! {
+                    Identifier::with_namespace("cluster", Some("k8s")) => vec![Choice::new(cluster, None)],
+                },
+            ),
+            pwd: String::from("/tmp"),
+            timestamp: Some(timestamp),
+            version: HISTORY_VERSION,
+        }
+    }
+
+    #[test]
+    fn test_search_entries() {
+        let entries = vec![entry("prod", 1), entry("staging", 2), entry("prod", 3)];
+        let entries = search_entries(entries.into_iter());
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].text(),
+            "k8s::pods  kubectl --context prod get pods"
+        );
+        assert_eq!(entries[0].entry.timestamp, Some(3));
+        assert_eq!(entries[0].desc(), Some("k8s::cluster=prod"));
+        assert_eq!(entries[1].desc(), Some("k8s::cluster=staging"));
+    }
+
+    #[test]
+    fn test_kept_choices() {
+        let cluster = Identifier::new("cluster");
+        let namespace = Identifier::new("namespace");
+        let pod = Identifier::new("pod");
+        let output = Identifier::new("output");
+        let vars = VarsRepository::new(
+            vec![
+                Var::new("cluster", "a cluster", vec![]),
+                Var::from_command(
+                    "namespace",
+                    "a namespace",
+                    "kubectl --context {{ cluster }} get ns",
+                ),
+                Var::from_command("pod", "a pod", "kubectl -n {{ namespace }} get pods"),
+                Var::new("output", "the output format", vec![]),
+            ]
+            .into_iter(),
+        );
+        let choices = maplit::hashmap! {
+            cluster.clone() => vec![Choice::new("prod", None)],
+            namespace.clone() => vec![Choice::new("default", None)],
+            pod.clone() => vec![Choice::new("api", None)],
+            output.clone() => vec![Choice::new("yaml", None)],
+        };
+
+        let kept = kept_choices(&choices, &maplit::hashset! {namespace}, &vars);
+        assert_eq!(
+            kept,
+            maplit::hashmap! {
+                cluster.clone() => vec![Choice::new("prod", None)],
+                output => vec![Choice::new("yaml", None)],
+            }
+        );
+        let kept = kept_choices(&choices, &maplit::hashset! {cluster}, &vars);
+        assert_eq!(kept.len(), 1);
+    }
+
+    #[test]
+    fn test_history_entry_json_round_trip() {
+        let entry = entry("prod", 1);
+        let line = serde_json::to_string(&HistoryEntryJson::from(entry.clone())).unwrap();
+        assert!(!line.contains('\n'));
+        let parsed: HistoryEntryJson = serde_json::from_str(&line).unwrap();
+        assert_eq!(HistoryEntry::from(parsed), entry);
+
+        let mut entry = entry;
+        entry.r = entry
+            .r
+            .with_record_output(true)
+            .with_transcript(vec![String::from("api-1 Running")]);
+        let line = serde_json::to_string(&HistoryEntryJson::from(entry.clone())).unwrap();
+        let parsed: HistoryEntryJson = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            HistoryEntry::from(parsed).r.transcript(),
+            entry.r.transcript()
+        );
+    }
+
+    #[test]
+    fn test_describe_entry() {
+        let entry = entry("prod", 1700000000);
+        let text = describe_entry(&entry, &chrono::Utc);
+        assert!(text.starts_with("2023-11-14 22:13:20 "));
+        assert!(text.contains("k8s::pods"));
+        assert!(text.contains("$ kubectl --context prod get pods\n"));
+        assert!(text.contains("--record"));
+
+        let mut entry = entry;
+        entry.r = entry.r.with_transcript(vec![String::from("api-1 Running")]);
+        let text = describe_entry(&entry, &chrono::Utc);
+        assert!(text.ends_with("\n\napi-1 Running\n"));
+    }
 }
@@ -0,0 +1,339 @@
+use crate::cli::OutputFormat;
+use crate::output::Output;
+use sam_core::algorithms::VarsCollection;
+use sam_core::entities::aliases::{Alias, Impact};
+use sam_core::entities::commands::Command;
+use sam_core::entities::identifiers::Identifier;
+use sam_core::entities::namespaces::Namespace;
+use sam_core::entities::vars::Var;
+use sam_persistence::repositories::{AliasesRepository, VarsRepository};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::{self, Write};
+use thiserror::Error;
+
+/// ListCommand lists the aliases or the vars of the repositories without running anything.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListCommand {
+    Aliases(ListFilter),
+    Vars(ListFilter),
+}
+
+/// ListFilter limits a listing to a namespace and to the aliases carrying all of the tags.
+/// Vars have no tags, with tags only the vars the matching aliases use are listed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListFilter {
+    pub namespace: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl ListFilter {
+    fn keeps_alias(&self, alias: &Alias) -> bool {
+        self.keeps_namespace(alias.namespace()) && alias.has_tags(&self.tags)
+    }
+
+    fn keeps_namespace(&self, namespace: Option<&str>) -> bool {
+        self.namespace.is_none() || self.namespace.as_deref() == namespace
+    }
+}
+
+pub struct ListEngine {
+    pub aliases: AliasesRepository,
+    pub vars: VarsRepository,
+    pub output: OutputFormat,
+    pub pager: bool,
+}
+
+impl ListEngine {
+    pub fn run(&self, cmd: ListCommand) -> Result<i32> {
+        match cmd {
+            ListCommand::Aliases(filter) => self.list_aliases(&filter),
+            ListCommand::Vars(filter) => self.list_vars(&filter),
+        }
+    }
+
+    fn list_aliases(&self, filter: &ListFilter) -> Result<i32> {
+        let mut aliases: Vec<Alias> = self
+            .aliases
+            .visible_aliases()
+            .into_iter()
+            .filter(|alias| filter.keeps_alias(alias))
+            .cloned()
+            .collect();
+        aliases.sort_by_key(|a| a.full_name().to_string());
+        let listed: Vec<ListedAlias> = aliases.iter().map(ListedAlias::from).collect();
+        let mut output = Output::new(self.pager);
+        match self.output {
+            OutputFormat::Json => writeln!(output, "{}", serde_json::to_string_pretty(&listed)?)?,
+            OutputFormat::Text => {
+                for alias in listed {
+                    writeln!(
+                        output,
+                        "{}{}{}{}\n  {}\n  {}",
+                        crossterm::style::Attribute::Bold,
+                        crossterm::style::SetForegroundColor(crossterm::style::Color::DarkGreen),
+                        alias.identifier,
+                        crossterm::style::Attribute::Reset,
+                        alias.desc,
+                        alias.alias,
+                    )?;
+                    if let Some(annotation) = &alias.annotation {
+                        writeln!(
+                            output,
+                            "  {}{}{}",
+                            crossterm::style::SetForegroundColor(
+                                crossterm::style::Color::DarkYellow
+                            ),
+                            annotation,
+                            crossterm::style::Attribute::Reset
+                        )?;
+                    }
+                    if let Some(hint) = &alias.deprecated {
+                        writeln!(
+                            output,
+                            "  {}deprecated: {}{}",
+                            crossterm::style::SetForegroundColor(
+                                crossterm::style::Color::DarkYellow
+                            ),
+                            hint,
+                            crossterm::style::Attribute::Reset
+                        )?;
+                    }
+                    if !alias.dependencies.is_empty() {
+                        writeln!(output, "  vars: {}", alias.dependencies.join(", "))?;
+                    }
+                    if !alias.tags.is_empty() {
+                        writeln!(output, "  tags: {}", alias.tags.join(", "))?;
+                    }
+                }
+            }
+        }
+        output.finish()?;
+        Ok(0)
+    }
+
+    fn list_vars(&self, filter: &ListFilter) -> Result<i32> {
+        let used = (!filter.tags.is_empty()).then(|| {
+            let aliases = self.aliases.visible_aliases();
+            let tagged = aliases
+                .into_iter()
+                .filter(|alias| alias.has_tags(&filter.tags));
+            used_vars(tagged, &self.vars)
+        });
+        let mut vars: Vec<&Var> = self
+            .vars
+            .vars_iter()
+            .filter(|var| filter.keeps_namespace(var.namespace()))
+            .filter(|var| used.as_ref().is_none_or(|used| used.contains(&var.name())))
+            .collect();
+        vars.sort_by_key(|v| v.name().to_string());
+        let listed: Vec<ListedVar> = vars.into_iter().map(ListedVar::from).collect();
+        let mut output = Output::new(self.pager);
+        match self.output {
+            OutputFormat::Json => writeln!(output, "{}", serde_json::to_string_pretty(&listed)?)?,
+            OutputFormat::Text => {
+                for var in listed {
+                    writeln!(
+                        output,
+                        "{}{}{}{}\n  {}",
+                        crossterm::style::Attribute::Bold,
+                        crossterm::style::SetForegroundColor(crossterm::style::Color::DarkGreen),
+                        var.identifier,
+                        crossterm::style::Attribute::Reset,
+                        var.desc,
+                    )?;
+                    if let Some(command) = &var.from_command {
+                        writeln!(output, "  from_command: {}", command)?;
+                    }
+                    if !var.choices.is_empty() {
+                        writeln!(output, "  choices: {}", var.choices.join(", "))?;
+                    }
+                    if !var.dependencies.is_empty() {
+                        writeln!(output, "  vars: {}", var.dependencies.join(", "))?;
+                    }
+                }
+            }
+        }
+        output.finish()?;
+        Ok(0)
+    }
+}
+
+// used_vars returns the vars the aliases depend on, directly or through other vars.
+fn used_vars<'a>(
+    aliases: impl Iterator<Item = &'a Alias>,
+    vars: &impl VarsCollection,
+) -> HashSet<Identifier> {
+    let mut pending: Vec<Identifier> = aliases.flat_map(|a| a.dependencies()).collect();
+    let mut used = HashSet::new();
+    while let Some(id) = pending.pop() {
+        if let Some(var) = vars.get(&id) {
+            pending.extend(var.dependencies());
+        }
+        used.insert(id);
+    }
+    used
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct ListedAlias {
+    identifier: String,
+    desc: String,
+    alias: String,
+    dependencies: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    impact: Option<Impact>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    estimated_duration: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deprecated: Option<String>,
+    #[serde(skip)]
+    annotation: Option<String>,
+}
+
+impl From<&Alias> for ListedAlias {
+    fn from(alias: &Alias) -> Self {
+        ListedAlias {
+            identifier: alias.full_name().to_string(),
+            desc: alias.desc().to_string(),
+            alias: alias.alias().to_string(),
+            dependencies: alias.dependencies().iter().map(|d| d.to_string()).collect(),
+            impact: alias.impact(),
+            estimated_duration: alias.estimated_duration().map(String::from),
+            tags: alias.tags().to_vec(),
+            deprecated: alias.deprecation().map(String::from),
+            annotation: alias.annotation(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct ListedVar {
+    identifier: String,
+    desc: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from_command: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    choices: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    dependencies: Vec<String>,
+}
+
+impl From<&Var> for ListedVar {
+    fn from(var: &Var) -> Self {
+        ListedVar {
+            identifier: var.name().to_string(),
+            desc: var.desc().to_string(),
+            from_command: (!var.command().is_empty()).then(|| var.command().to_string()),
+            choices: var
+                .choices()
+                .iter()
+                .map(|c| c.value().to_string())
+                .collect(),
+            dependencies: var.dependencies().iter().map(|d| d.to_string()).collect(),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ErrorListEngine>;
+
+#[derive(Debug, Error)]
+pub enum ErrorListEngine {
+    #[error("could not serialize the listing\n-> {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("could not print the listing\n-> {0}")]
+    Io(#[from] io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{used_vars, ListFilter, ListedAlias, ListedVar};
+    use sam_core::entities::aliases::{Alias, Impact};
+    use sam_core::entities::choices::Choice;
+    use sam_core::entities::identifiers::Identifier;
+    use sam_core::entities::namespaces::NamespaceUpdater;
+    use sam_core::entities::vars::Var;
+    use sam_persistence::repositories::VarsRepository;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_listed_alias() {
+        let mut alias = Alias::new("list", "list's a directory", "ls {{ directory }}");
+        NamespaceUpdater::update(&mut alias, "dirs");
+        let listed = ListedAlias::from(&alias);
+        assert_eq!(
+            serde_json::to_string(&listed).unwrap(),
+            r#"{"identifier":"dirs::list","desc":"list's a directory","alias":"ls {{ directory }}","dependencies":["dirs::directory"]}"#
+        );
+        let alias = alias.with_impact(Some(Impact::High), Some(String::from("5m")));
+        assert_eq!(
+            serde_json::to_string(&ListedAlias::from(&alias)).unwrap(),
+            r#"{"identifier":"dirs::list","desc":"list's a directory","alias":"ls {{ directory }}","dependencies":["dirs::directory"],"impact":"high","estimated_duration":"5m"}"#
+        );
+        let alias = alias.with_tags(vec![String::from("fs")]);
+        assert!(serde_json::to_string(&ListedAlias::from(&alias))
+            .unwrap()
+            .ends_with(r#""estimated_duration":"5m","tags":["fs"]}"#));
+    }
+
+    #[test]
+    fn test_list_filter() {
+        let mut pods = Alias::new("pods", "lists the pods", "kubectl get pods")
+            .with_tags(vec![String::from("k8s"), String::from("prod")]);
+        NamespaceUpdater::update(&mut pods, "k8s");
+        let mut list = Alias::new("list", "lists a directory", "ls");
+        NamespaceUpdater::update(&mut list, "dirs");
+
+        let all = ListFilter::default();
+        assert!(all.keeps_alias(&pods) && all.keeps_alias(&list));
+        let k8s = ListFilter {
+            namespace: Some(String::from("k8s")),
+            tags: vec![],
+        };
+        assert!(k8s.keeps_alias(&pods) && !k8s.keeps_alias(&list));
+        let prod = ListFilter {
+            namespace: None,
+            tags: vec![String::from("prod")],
+        };
+        assert!(prod.keeps_alias(&pods) && !prod.keeps_alias(&list));
+    }
+
+    #[test]
+    fn test_used_vars() {
+        let mut env = Var::new("env", "the environment", vec![Choice::from_value("prod")]);
+        NamespaceUpdater::update(&mut env, "k8s");
+        let mut pod = Var::from_command("pod", "a pod", "kubectl get pods -n {{ env }}");
+        NamespaceUpdater::update(&mut pod, "k8s");
+        let mut other = Var::from_command("other", "unused", "ls");
+        NamespaceUpdater::update(&mut other, "k8s");
+        let vars = VarsRepository::new(vec![env, pod, other].into_iter());
+        let mut logs = Alias::new("logs", "tails the logs", "kubectl logs {{ pod }}");
+        NamespaceUpdater::update(&mut logs, "k8s");
+
+        assert_eq!(
+            used_vars(vec![&logs].into_iter(), &vars),
+            HashSet::from([
+                Identifier::with_namespace("pod", Some("k8s")),
+                Identifier::with_namespace("env", Some("k8s")),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_listed_var() {
+        let mut env = Var::new("env", "the environment", vec![Choice::from_value("prod")]);
+        NamespaceUpdater::update(&mut env, "k8s");
+        let mut pod = Var::from_command("pod", "a pod", "kubectl get pods -n {{ env }}");
+        NamespaceUpdater::update(&mut pod, "k8s");
+        assert_eq!(
+            serde_json::to_string(&ListedVar::from(&pod)).unwrap(),
+            r#"{"identifier":"k8s::pod","desc":"a pod","from_command":"kubectl get pods -n {{ env }}","dependencies":["k8s::env"]}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&ListedVar::from(&env)).unwrap(),
+            r#"{"identifier":"k8s::env","desc":"the environment","choices":["prod"]}"#
+        );
+    }
+}
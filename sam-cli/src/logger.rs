@@ -1,5 +1,11 @@
-use log::info;
+use chrono::Utc;
+use log::{info, Level, LevelFilter, Log, Metadata, Record};
 use sam_core::entities::aliases::Alias;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs::{self, File};
+use std::io::Write;
+use std::sync::{Mutex, OnceLock};
 use std::{fmt::Display, path::PathBuf};
 
 use thiserror::Error;
@@ -39,11 +45,11 @@ impl SamLogger for FileLogger {
     }
 
     fn choice(&self, var: &dyn Display, choice: &dyn Display) {
-        info!("[SAM][ var = '{}' ] Choice was: '{}'", var, choice)
+        info!("[SAM][ var = '{}' ] choice='{}'", var, choice)
     }
     fn alias(&self, alias: &Alias) {
         info!(
-            "[SAM][ alias = '{}::{}' ]",
+            "[SAM][ alias = '{}::{}' ] resolving its vars",
             alias.namespace().unwrap_or_default(),
             alias.name(),
         )
@@ -57,3 +63,261 @@ impl SamLogger for SilentLogger {
     fn choice(&self, _: &dyn Display, _: &dyn Display) {}
     fn alias(&self, _: &Alias) {}
 }
+
+// the number of traces kept, the oldest ones are removed when a run starts a new one.
+const MAX_TRACES: usize = 20;
+
+/// TraceEvent is a step of a run, read from the logs following the
+/// `[SAM][ var = 'name' ] key='value' key=value` convention.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TraceEvent {
+    pub time: String,
+    pub level: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub alias: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub var: Option<String>,
+    #[serde(skip_serializing_if = "Map::is_empty", default)]
+    pub fields: Map<String, Value>,
+    pub message: String,
+}
+
+impl TraceEvent {
+    /// parse reads the alias or the var a log is about and the fields of its message,
+    /// `duration=12ms` becomes `"duration_ms": 12`. Other logs aren't traced.
+    pub fn parse(time: String, level: Level, text: &str) -> Option<TraceEvent> {
+        let rest = text.strip_prefix("[SAM][")?;
+        let (subject, message) = rest.split_once(']')?;
+        let (kind, name) = subject.split_once('=').unwrap_or((subject, ""));
+        let name = Some(name.trim().trim_matches('\'').to_string()).filter(|n| !n.is_empty());
+        let message = message.trim().to_string();
+        let (alias, var) = match kind.trim() {
+            "alias" => (name, None),
+            "var" => (None, name),
+            _ => (None, None),
+        };
+        Some(TraceEvent {
+            time,
+            level: level.to_string(),
+            alias,
+            var,
+            fields: fields(&message),
+            message,
+        })
+    }
+}
+
+// fields reads the `key=value` and `key='value'` pairs of a message, a quoted value ends
+// at the quote followed by the next pair or the end of the message.
+fn fields(message: &str) -> Map<String, Value> {
+    let mut fields = Map::new();
+    let mut rest = message;
+    while let Some((key, after)) = next_key(rest) {
+        let (value, remaining) = match after.strip_prefix('\'') {
+            Some(quoted) => {
+                let quotes: Vec<usize> = quoted
+                    .char_indices()
+                    .filter(|(_, c)| *c == '\'')
+                    .map(|(i, _)| i)
+                    .collect();
+                let ends = |after: fn(&str) -> bool| {
+                    quotes.iter().copied().find(|i| {
+                        let rest = &quoted[i + 1..];
+                        rest.is_empty() || after(rest)
+                    })
+                };
+                // a value followed by words that aren't pairs ends at the first quote before them.
+                let end = ends(next_key_at_start)
+                    .or_else(|| ends(|rest| rest.starts_with(' ')))
+                    .unwrap_or(quoted.len());
+                let value = Value::String(quoted[..end].to_string());
+                (value, quoted.get(end + 1..).unwrap_or_default())
+            }
+            None => {
+                let (value, remaining) = after.split_once(' ').unwrap_or((after, ""));
+                (field_value(value), remaining)
+            }
+        };
+        match value
+            .as_str()
+            .and_then(|v| v.strip_suffix("ms")?.parse::<u64>().ok())
+        {
+            Some(ms) if !after.starts_with('\'') => {
+                fields.insert(format!("{}_ms", key), Value::from(ms))
+            }
+            _ => fields.insert(key.to_string(), value),
+        };
+        rest = remaining;
+    }
+    fields
+}
+
+// next_key finds the first word of the text that starts a pair, it returns its key and
+// what follows the `=`.
+fn next_key(text: &str) -> Option<(&str, &str)> {
+    let mut offset = 0;
+    for word in text.split(' ') {
+        if let Some((key, _)) = word.split_once('=').filter(|(key, _)| is_key(key)) {
+            return Some((key, &text[offset + key.len() + 1..]));
+        }
+        offset += word.len() + 1;
+    }
+    None
+}
+
+fn next_key_at_start(text: &str) -> bool {
+    text.strip_prefix(' ')
+        .and_then(|t| t.split_once('='))
+        .is_some_and(|(key, _)| is_key(key))
+}
+
+fn is_key(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_lowercase() || c == '_')
+}
+
+fn field_value(value: &str) -> Value {
+    match value {
+        "none" => Value::Null,
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => value
+            .parse::<i64>()
+            .map(Value::from)
+            .unwrap_or_else(|_| Value::String(value.to_string())),
+    }
+}
+
+/// TraceLogger writes the debug logs of sam as json lines to a trace file per run, in the
+/// traces directory, whatever RUST_LOG is. The logs are handed to the logger it wraps as well.
+/// The file is created with the first event, runs that don't resolve anything leave none.
+pub struct TraceLogger {
+    inner: Box<dyn Log>,
+    dir: Option<PathBuf>,
+    file: OnceLock<Option<Mutex<File>>>,
+}
+
+impl TraceLogger {
+    pub fn new(inner: Box<dyn Log>, dir: Option<PathBuf>) -> Self {
+        TraceLogger {
+            inner,
+            dir,
+            file: OnceLock::new(),
+        }
+    }
+
+    /// install makes the trace logger the logger of sam, debug logs are enabled for it.
+    pub fn install(self) -> Result<(), log::SetLoggerError> {
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(log::max_level().max(LevelFilter::Debug));
+        Ok(())
+    }
+
+    fn traced(metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Debug && metadata.target().starts_with("sam")
+    }
+
+    // open creates the trace of this run and removes the oldest ones.
+    fn open(&self) -> Option<Mutex<File>> {
+        let dir = self.dir.as_ref()?;
+        fs::create_dir_all(dir).ok()?;
+        for old in traces(dir).iter().rev().skip(MAX_TRACES - 1) {
+            let _ = fs::remove_file(old);
+        }
+        let name = format!(
+            "{}-{}.jsonl",
+            Utc::now().format("%Y%m%dT%H%M%S%.3fZ"),
+            std::process::id()
+        );
+        File::create(dir.join(name)).ok().map(Mutex::new)
+    }
+}
+
+impl Log for TraceLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        Self::traced(metadata) || self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if Self::traced(record.metadata()) {
+            let time = Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+            let text = record.args().to_string();
+            let event = TraceEvent::parse(time, record.level(), &text);
+            let file = event
+                .as_ref()
+                .and_then(|_| self.file.get_or_init(|| self.open()).as_ref());
+            if let (Some(event), Some(file)) = (event, file) {
+                if let (Ok(line), Ok(mut file)) = (serde_json::to_string(&event), file.lock()) {
+                    // a trace that can't be written doesn't stop the run.
+                    let _ = writeln!(file, "{}", line);
+                }
+            }
+        }
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// traces returns the trace files of the directory, from the oldest to the latest.
+pub fn traces(dir: &std::path::Path) -> Vec<PathBuf> {
+    let mut traces: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "jsonl"))
+                .collect()
+        })
+        .unwrap_or_default();
+    traces.sort();
+    traces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TraceEvent;
+    use log::Level;
+    use serde_json::json;
+
+    #[test]
+    fn test_trace_event() {
+        let event = TraceEvent::parse(
+            String::from("2026-10-15T08:00:00.000Z"),
+            Level::Debug,
+            "[SAM][ var = 'k8s::pod' ] command='kubectl get pods -l 'app=api'' duration=120ms exit_code=0 from_cache=false",
+        )
+        .unwrap();
+        assert_eq!(event.var.as_deref(), Some("k8s::pod"));
+        assert_eq!(event.alias, None);
+        assert_eq!(
+            serde_json::to_value(&event.fields).unwrap(),
+            json!({
+                "command": "kubectl get pods -l 'app=api'",
+                "duration_ms": 120,
+                "exit_code": 0,
+                "from_cache": false,
+            })
+        );
+
+        let event = TraceEvent::parse(
+            String::new(),
+            Level::Debug,
+            "[SAM][ alias = 'k8s::logs' ] command='kubectl logs api' duration=3ms exit_code=none",
+        )
+        .unwrap();
+        assert_eq!(event.alias.as_deref(), Some("k8s::logs"));
+        assert_eq!(event.fields["exit_code"], json!(null));
+
+        let event = TraceEvent::parse(
+            String::new(),
+            Level::Debug,
+            "[SAM][ var = 'pod' ] retrying command='false' in 200ms",
+        )
+        .unwrap();
+        assert_eq!(event.fields["command"], json!("false"));
+        assert_eq!(event.message, "retrying command='false' in 200ms");
+        assert!(TraceEvent::parse(String::new(), Level::Debug, "no tmux detected").is_none());
+    }
+}
@@ -1,60 +1,173 @@
 use crate::config::{AppSettings, ErrorsSettings};
 use crate::config_engine::ErrorsConfigEngine;
 use crate::environment::ErrorEnvironment;
+use audit_engine::ErrorAuditEngine;
 use cache_engine::ErrorCacheEngine;
-use cli::SubCommand;
+use cli::{OutputFormat, SubCommand};
+use edit_engine::ErrorEditEngine;
+use export_engine::ErrorExportEngine;
 use flexi_logger::{FileSpec, Logger, LoggerHandle, WriteMode};
 use history_engine::ErrorHistoryEngine;
-use log::error;
-use sam_core::engines::ErrorSamEngine;
+use list_engine::ErrorListEngine;
+use logger::TraceLogger;
+use resolve_engine::ErrorResolveEngine;
+use run_engine::ErrorRunEngine;
+use sam_core::engines::{ErrorSamEngine, SamCommand};
+use sam_readers::Warnings;
+use sam_terminals::processes::Shell;
+use sam_tui::modal_view::{ColorSupport, UITheme};
+use scaffold_engine::ErrorScaffoldEngine;
+use stats_engine::ErrorStatsEngine;
 use std::collections::HashMap;
+use suggest_engine::{ErrorSuggestEngine, SuggestEngine};
 use thiserror::Error;
+use trace_engine::{ErrorTraceEngine, TraceEngine};
+use tutorial::{ErrorTutorial, TutorialEngine};
+use watch_engine::ErrorWatchEngine;
 
+mod audit_engine;
 mod cache_engine;
 mod cli;
+mod complete_engine;
 mod config;
 mod config_engine;
+mod diff;
+mod edit_engine;
 mod environment;
 mod executors;
+mod export_engine;
 mod history_engine;
+mod list_engine;
 mod logger;
+mod output;
+mod resolve_engine;
+mod run_engine;
+mod scaffold_engine;
+mod shell_history;
+mod stats_engine;
+mod suggest_engine;
+mod trace_engine;
+mod tutorial;
+mod watch_engine;
 
 fn main() {
-    let _logger = init_logger().expect("can't initialize logs");
-    match run() {
-        Ok(i) => {
-            std::process::exit(i);
-        }
+    let logger = init_logger().expect("can't initialize logs");
+    let exit_code = match run() {
+        Ok(i) => i,
         Err(err) => {
             eprintln!("An error happened while running the program {}", err);
-            std::process::exit(1);
+            err.exit_code()
         }
-    }
+    };
+    // exit doesn't run destructors, the buffered logs are written first.
+    logger.flush();
+    std::process::exit(exit_code);
 }
 
 fn run() -> Result<i32> {
     let cli_request = cli::read_cli_request()?;
+    // only the bare interactive run is replaced by the tutorial, subcommands run as asked.
+    let first_run = cli_request.command
+        == SubCommand::SamCommand(SamCommand::ChooseAndExecuteAlias)
+        && AppSettings::is_first_run()
+        && TutorialEngine::can_run();
+    if let SubCommand::Completions(shell) = cli_request.command {
+        print!("{}", cli::completion_script(shell));
+        return Ok(0);
+    }
+    if cli_request.command == SubCommand::Tutorial || first_run {
+        return Ok(TutorialEngine::in_home_directory()?.run()?);
+    }
     let app_config = AppSettings::load(Some(cli_request.settings))?;
-    let environment = environment::from_settings(app_config)?;
+    Shell::set_default(app_config.shell()?);
+    UITheme::set_default(app_config.theme.theme(ColorSupport::detect()));
+    // the trace of the last run is printed even when the recipes can't be loaded.
+    if cli_request.command == SubCommand::LastLog {
+        let engine = TraceEngine {
+            dir: AppSettings::traces_dir(),
+            output: app_config.output,
+        };
+        return Ok(engine.run()?);
+    }
+    if cli_request.command == SubCommand::HistoryMigrate {
+        return Ok(history_engine::migrate_history(app_config.history_file())?);
+    }
+    let json_config_check = matches!(cli_request.command, SubCommand::ConfigCheck(_))
+        && app_config.output == OutputFormat::Json;
+    let environment = match environment::from_settings(app_config) {
+        Err(err) if json_config_check => return Ok(config_engine::report_load_failure(&err)?),
+        environment => environment?,
+    };
+    let warnings = environment.warnings.clone();
+    let print_warnings = !matches!(cli_request.command, SubCommand::ConfigCheck(_));
 
-    run_command(cli_request.command, environment)
+    let exit_code = run_command(cli_request.command, environment);
+    if print_warnings {
+        print_warnings_summary(&warnings);
+    }
+    exit_code
+}
+
+fn print_warnings_summary(warnings: &Warnings) {
+    if warnings.is_empty() {
+        return;
+    }
+    eprintln!(
+        "{}{} warning(s) while loading aliases and vars, run `sam check-config` for details{}",
+        crossterm::style::SetForegroundColor(crossterm::style::Color::DarkYellow),
+        warnings.len(),
+        crossterm::style::Attribute::Reset,
+    );
+    eprint!("{}", warnings);
 }
 
 fn run_command(sub_command: SubCommand, env: environment::Environment) -> Result<i32> {
     match sub_command {
-        SubCommand::SamCommand(s) => Ok(env.sam_engine().run(s)?),
+        SubCommand::SamCommand(s @ SamCommand::ResolveAlias { .. }) => {
+            Ok(env.resolve_alias_engine()?.run(s)?.exit_code())
+        }
+        SubCommand::SamCommand(s) => Ok(env.sam_engine()?.run(s)?.exit_code()),
         SubCommand::CacheCommand(s) => Ok(env.cache_engine().run(s)?),
         SubCommand::ConfigCheck(s) => Ok(env.config_engine().run(s)?),
-        SubCommand::HistoryCommand(s) => Ok(env.history_engine().run(s)?),
+        SubCommand::HistoryCommand(s) => Ok(env.history_engine()?.run(s)?),
+        // migrated in run before the recipes are loaded, an old history can't break it.
+        SubCommand::HistoryMigrate => {
+            unreachable!("the history is migrated before the environment is loaded")
+        }
+        SubCommand::StatsCommand(s) => Ok(env.stats_engine().run(s)?),
+        SubCommand::AuditCommand(s) => Ok(env.audit_engine().run(s)?),
+        SubCommand::Suggest(s) => Ok(SuggestEngine {}.run(s)?),
+        SubCommand::Export(s) => Ok(env.export_engine().run(s)?),
+        SubCommand::Complete(s) => Ok(env.complete_engine().run(s)),
+        SubCommand::ListCommand(s) => Ok(env.list_engine().run(s)?),
+        SubCommand::Completions(shell) => {
+            print!("{}", cli::completion_script(shell));
+            Ok(0)
+        }
+        SubCommand::Tutorial => Ok(TutorialEngine::in_home_directory()?.run()?),
+        SubCommand::Watch => Ok(env.watch_engine().run()?),
+        SubCommand::Scaffold(s) => Ok(env.scaffold_engine().run(s)?),
+        SubCommand::Edit(target) => Ok(env.edit_engine().run(&target)?),
+        SubCommand::ResolveVar(s) => Ok(env.resolve_engine()?.run(s)?),
+        SubCommand::Run(s) => Ok(env.run_engine()?.run(s)?),
+        SubCommand::LastLog => Ok(TraceEngine {
+            dir: AppSettings::traces_dir(),
+            output: env.config.output,
+        }
+        .run()?),
     }
 }
 
 fn init_logger() -> Result<LoggerHandle> {
-    Ok(Logger::try_with_env()?
+    let (logger, handle) = Logger::try_with_env()?
         .log_to_file(FileSpec::default())
         .write_mode(WriteMode::BufferAndFlush)
         .use_utc()
-        .start()?)
+        .build()?;
+    TraceLogger::new(logger, AppSettings::traces_dir())
+        .install()
+        .map_err(flexi_logger::FlexiLoggerError::from)?;
+    Ok(handle)
 }
 
 type Result<T> = std::result::Result<T, ErrorMain>;
@@ -75,6 +188,42 @@ pub enum ErrorMain {
     ConfigError(#[from] ErrorsConfigEngine),
     #[error("{0}")]
     HistoryError(#[from] ErrorHistoryEngine),
+    #[error("{0}")]
+    StatsError(#[from] ErrorStatsEngine),
+    #[error("{0}")]
+    AuditError(#[from] ErrorAuditEngine),
+    #[error("{0}")]
+    SuggestError(#[from] ErrorSuggestEngine),
+    #[error("{0}")]
+    ExportError(#[from] ErrorExportEngine),
+    #[error("{0}")]
+    ListError(#[from] ErrorListEngine),
+    #[error("{0}")]
+    TutorialError(#[from] ErrorTutorial),
+    #[error("{0}")]
+    WatchError(#[from] ErrorWatchEngine),
+    #[error("{0}")]
+    ScaffoldError(#[from] ErrorScaffoldEngine),
+    #[error("{0}")]
+    EditError(#[from] ErrorEditEngine),
+    #[error("{0}")]
+    ResolveError(#[from] ErrorResolveEngine),
+    #[error("{0}")]
+    RunError(#[from] ErrorRunEngine),
+    #[error("{0}")]
+    TraceError(#[from] ErrorTraceEngine),
     #[error("Can't initialise logging because {0}")]
     LoggingError(#[from] flexi_logger::FlexiLoggerError),
 }
+
+impl ErrorMain {
+    /// exit_code is the exit code of the alias that failed, 1 for the other errors.
+    fn exit_code(&self) -> i32 {
+        match self {
+            ErrorMain::SamEngine(err)
+            | ErrorMain::HistoryError(ErrorHistoryEngine::SamEngine(err))
+            | ErrorMain::RunError(ErrorRunEngine::SamEngine(err)) => err.exit_code(),
+            _ => 1,
+        }
+    }
+}
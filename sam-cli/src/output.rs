@@ -0,0 +1,100 @@
+use sam_terminals::processes::shell_command;
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::process::Stdio;
+
+/// Output is where the commands displaying long texts, the list of the aliases, the history
+/// or the audit events, write. When paging is enabled and stdout is a terminal, the text is
+/// kept until finish shows it in $PAGER, or in the pager of sam-tui when it's unset, unless
+/// it fits in the terminal.
+pub struct Output {
+    sink: Sink,
+}
+
+enum Sink {
+    Stdout(io::Stdout),
+    Paged(Vec<u8>),
+}
+
+impl Output {
+    pub fn new(pager: bool) -> Output {
+        let sink = if pager && io::stdout().is_terminal() {
+            Sink::Paged(vec![])
+        } else {
+            Sink::Stdout(io::stdout())
+        };
+        Output { sink }
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        match self.sink {
+            Sink::Stdout(mut stdout) => stdout.flush(),
+            Sink::Paged(buffer) => page(&String::from_utf8_lossy(&buffer)),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.sink {
+            Sink::Stdout(stdout) => stdout.write(buf),
+            Sink::Paged(buffer) => buffer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.sink {
+            Sink::Stdout(stdout) => stdout.flush(),
+            Sink::Paged(_) => Ok(()),
+        }
+    }
+}
+
+fn page(text: &str) -> io::Result<()> {
+    let height = crossterm::terminal::size().map_or(usize::MAX, |(_, h)| h as usize);
+    if fits(text, height) {
+        return io::stdout().write_all(text.as_bytes());
+    }
+    match env::var("PAGER") {
+        Ok(pager) if !pager.trim().is_empty() => external_pager(&pager, text),
+        _ => sam_tui::page(text),
+    }
+}
+
+// fits tells whether a text can be printed without scrolling, the prompt
+// shown afterwards takes the last line of the terminal.
+fn fits(text: &str, height: usize) -> bool {
+    text.lines().count() < height
+}
+
+// external_pager runs the pager through the shell since $PAGER can hold arguments,
+// like git it makes less keep the colors when LESS isn't set.
+fn external_pager(pager: &str, text: &str) -> io::Result<()> {
+    let mut command = shell_command(pager);
+    command.stdin(Stdio::piped());
+    if env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+    let mut child = command.spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        match stdin.write_all(text.as_bytes()) {
+            // the pager was quit before reading the whole text.
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {}
+            result => result?,
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fits;
+
+    #[test]
+    fn test_fits() {
+        assert!(fits("a\nb\n", 3));
+        assert!(!fits("a\nb\nc", 3));
+        assert!(fits("", 1));
+    }
+}
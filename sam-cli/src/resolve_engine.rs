@@ -0,0 +1,164 @@
+use sam_core::algorithms::resolver::Resolver;
+use sam_core::algorithms::{VarsCollection, VarsDefaultValues};
+use sam_core::engines::{AliasCollection, ErrorSamEngine, SamEngine, VarsDefaultValuesSetter};
+use sam_core::entities::choices::Choice;
+use sam_core::entities::identifiers::Identifier;
+use sam_terminals::streams::stdout_to_terminal;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, Write};
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolveVarCommand {
+    pub var: String,
+    pub output: ResolveOutput,
+}
+
+/// ResolveOutput is how the choices are printed: `var=value` lines, the format of `--choices`,
+/// the values only, one per line, or a json object holding the var and its choices.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResolveOutput {
+    Choices,
+    Values,
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+struct ResolvedVar<'a> {
+    var: String,
+    choices: &'a [Choice],
+}
+
+/// ResolveEngine asks for the choices of a single var, with the same pickers and prompts as
+/// when running an alias, and prints them so that scripts and shell widgets can reuse them.
+pub struct ResolveEngine<
+    R: Resolver,
+    AR: AliasCollection,
+    VR: VarsCollection,
+    DV: VarsDefaultValuesSetter + VarsDefaultValues,
+> {
+    pub sam_engine: SamEngine<R, AR, VR, DV>,
+    pub vars: Vec<Identifier>,
+}
+
+impl<
+        R: Resolver,
+        AR: AliasCollection,
+        VR: VarsCollection,
+        DV: VarsDefaultValues + VarsDefaultValuesSetter,
+    > ResolveEngine<R, AR, VR, DV>
+{
+    pub fn run(&self, cmd: ResolveVarCommand) -> Result<i32> {
+        let var = find_var(&self.vars, &cmd.var)?;
+        let mut output = output()?;
+        let choices = self.sam_engine.resolve_var(&var)?;
+        match cmd.output {
+            ResolveOutput::Json => {
+                let resolved = ResolvedVar {
+                    var: var.to_string(),
+                    choices: &choices,
+                };
+                writeln!(output, "{}", serde_json::to_string_pretty(&resolved)?)?;
+            }
+            ResolveOutput::Values => {
+                for choice in &choices {
+                    writeln!(output, "{}", choice.value())?;
+                }
+            }
+            ResolveOutput::Choices => {
+                for choice in &choices {
+                    writeln!(output, "{}={}", var, choice.value())?;
+                }
+            }
+        }
+        Ok(0)
+    }
+}
+
+// find_var finds the var with the identifier, a name without a namespace has to belong
+// to a single var, `{{ env }}` is typed more often than `{{ ns::env }}`.
+fn find_var(vars: &[Identifier], target: &str) -> Result<Identifier> {
+    let id = Identifier::from_str(target);
+    if vars.contains(&id) {
+        return Ok(id);
+    }
+    let mut found: Vec<&Identifier> = vars
+        .iter()
+        .filter(|v| id.namespace.is_none() && v.name() == id.name())
+        .collect();
+    found.sort();
+    match found.as_slice() {
+        [] => Err(ErrorResolveEngine::NotFound(target.to_string())),
+        [var] => Ok((*var).clone()),
+        _ => {
+            let candidates: Vec<String> = found.iter().map(|v| v.to_string()).collect();
+            Err(ErrorResolveEngine::Ambiguous(
+                target.to_string(),
+                candidates.join(", "),
+            ))
+        }
+    }
+}
+
+// output returns where the choices are written. When stdout is captured, by a shell
+// widget for instance, the pickers are drawn on the terminal instead.
+pub fn output() -> io::Result<File> {
+    stdout_to_terminal()
+}
+
+pub type Result<T> = std::result::Result<T, ErrorResolveEngine>;
+
+#[derive(Debug, Error)]
+pub enum ErrorResolveEngine {
+    #[error("there is no var named {0}")]
+    NotFound(String),
+    #[error("several vars are named {0}, add the namespace to pick one: {1}")]
+    Ambiguous(String, String),
+    #[error("{0}")]
+    SamEngine(#[from] ErrorSamEngine),
+    #[error("can't print the choices\n-> {0}")]
+    Io(#[from] io::Error),
+    #[error("can't serialize the choices\n-> {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_var, ResolvedVar};
+    use sam_core::entities::choices::Choice;
+    use sam_core::entities::identifiers::Identifier;
+
+    #[test]
+    fn test_find_var() {
+        let vars = vec![
+            Identifier::with_namespace("env", Some("k8s")),
+            Identifier::with_namespace("env", Some("aws")),
+            Identifier::with_namespace("region", Some("aws")),
+        ];
+        assert_eq!(find_var(&vars, "k8s::env").unwrap(), vars[0]);
+        assert_eq!(find_var(&vars, "region").unwrap(), vars[2]);
+        assert_eq!(
+            find_var(&vars, "env").unwrap_err().to_string(),
+            "several vars are named env, add the namespace to pick one: aws::env, k8s::env"
+        );
+        assert_eq!(
+            find_var(&vars, "k8s::region").unwrap_err().to_string(),
+            "there is no var named k8s::region"
+        );
+    }
+
+    #[test]
+    fn test_resolved_var() {
+        let var = Identifier::with_namespace("env", Some("k8s"));
+        let choices = vec![Choice::new("prod", Some("production"))];
+        let resolved = ResolvedVar {
+            var: var.to_string(),
+            choices: &choices,
+        };
+        assert_eq!(
+            serde_json::to_string(&resolved).unwrap(),
+            r#"{"var":"k8s::env","choices":[{"value":"prod","desc":"production"}]}"#
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use crate::config::answer_choices;
+use sam_core::algorithms::resolver::Resolver;
+use sam_core::algorithms::{VarsCollection, VarsDefaultValues};
+use sam_core::engines::{
+    AliasCollection, ErrorSamEngine, ExecutionReport, SamCommand, SamEngine, SamExecutor,
+    VarsDefaultValuesSetter,
+};
+use sam_core::entities::aliases::ResolvedAlias;
+use sam_core::entities::choices::Choice;
+use sam_core::entities::identifiers::Identifier;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use thiserror::Error;
+
+const PROMPT: &str = "Choose an alias to record > ";
+// runs are kept next to the recipes, in the runs directory of a root directory.
+const RUNS_DIR: &str = "runs";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RunCommand {
+    /// Record asks for the choices of an alias and writes them to a run instead of running it.
+    Record {
+        name: String,
+        alias: Option<Identifier>,
+    },
+    /// Replay runs the alias of a run with its choices, the placeholders are asked for.
+    Replay(String),
+}
+
+/// Run is an alias and the choices of its vars, written as yaml so that it can be edited
+/// and shared with the recipes. Vars set to `~` are placeholders, they are asked for
+/// when the run is replayed.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Run {
+    alias: String,
+    #[serde(default)]
+    choices: BTreeMap<String, serde_yaml::Value>,
+}
+
+impl Run {
+    // from_alias keeps the choices of a resolved alias, the ones of the sensitive
+    // vars are left as placeholders so that they are never written to a run.
+    fn from_alias(alias: &ResolvedAlias) -> Self {
+        let choices = alias
+            .choices()
+            .iter()
+            .map(|(id, choices)| {
+                let value = match choices.as_slice() {
+                    _ if alias.sensitive().contains(id) => serde_yaml::Value::Null,
+                    [choice] => serde_yaml::Value::String(choice.value().to_string()),
+                    choices => serde_yaml::Value::Sequence(
+                        choices
+                            .iter()
+                            .map(|c| serde_yaml::Value::String(c.value().to_string()))
+                            .collect(),
+                    ),
+                };
+                (id.to_string(), value)
+            })
+            .collect();
+        Run {
+            alias: alias.name().to_string(),
+            choices,
+        }
+    }
+
+    // choices are the choices of the vars that aren't placeholders.
+    fn choices(&self) -> std::result::Result<HashMap<Identifier, Vec<Choice>>, String> {
+        self.choices
+            .iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(id, value)| {
+                let choices = answer_choices(value.clone())
+                    .ok_or_else(|| format!("the choices of {} are invalid", id))?;
+                Ok((Identifier::from_str(id), choices))
+            })
+            .collect()
+    }
+}
+
+// RunRecorder keeps the alias it's given instead of running it.
+#[derive(Default)]
+struct RunRecorder {
+    alias: RefCell<Option<ResolvedAlias>>,
+}
+
+impl SamExecutor for RunRecorder {
+    fn execute_resolved_alias(
+        &self,
+        alias: &ResolvedAlias,
+        _env_variables: &HashMap<String, String>,
+    ) -> std::result::Result<ExecutionReport, ErrorSamEngine> {
+        self.alias.replace(Some(alias.clone()));
+        Ok(ExecutionReport::default())
+    }
+}
+
+/// RunEngine records the choices of an alias to a named run file and replays them later,
+/// so that teams can keep their runbooks next to their recipes.
+pub struct RunEngine<
+    R: Resolver,
+    AR: AliasCollection,
+    VR: VarsCollection,
+    DV: VarsDefaultValuesSetter + VarsDefaultValues,
+> {
+    pub sam_engine: SamEngine<R, AR, VR, DV>,
+    pub root_dirs: Vec<PathBuf>,
+}
+
+impl<
+        R: Resolver,
+        AR: AliasCollection,
+        VR: VarsCollection,
+        DV: VarsDefaultValues + VarsDefaultValuesSetter,
+    > RunEngine<R, AR, VR, DV>
+{
+    pub fn run(&mut self, cmd: RunCommand) -> Result<i32> {
+        match cmd {
+            RunCommand::Record { name, alias } => self.record(&name, alias),
+            RunCommand::Replay(name) => self.replay(&name),
+        }
+    }
+
+    fn record(&mut self, name: &str, alias: Option<Identifier>) -> Result<i32> {
+        validate_name(name)?;
+        let root_dir = self.root_dirs.first().ok_or(ErrorRunEngine::NoRootDir)?;
+        let path = run_path(root_dir, name);
+        let alias = match alias {
+            Some(alias) => alias,
+            None => self
+                .sam_engine
+                .aliases
+                .select_alias(&self.sam_engine.resolver, &self.sam_engine.vars, PROMPT)
+                .map_err(ErrorSamEngine::from)?
+                .identifier(),
+        };
+        let recorder = Rc::new(RunRecorder::default());
+        self.sam_engine.executor = recorder.clone();
+        self.sam_engine.run(SamCommand::ResolveAlias { alias })?;
+        let resolved = recorder
+            .alias
+            .take()
+            .expect("the alias is resolved before it's executed");
+        let content = serde_yaml::to_string(&Run::from_alias(&resolved))
+            .map_err(|err| ErrorRunEngine::Invalid(path.clone(), err.to_string()))?;
+        fs::create_dir_all(root_dir.join(RUNS_DIR))?;
+        fs::write(&path, content)?;
+        println!(
+            "run {} recorded in {}, replay it with `sam replay {}`",
+            name,
+            path.display(),
+            name
+        );
+        Ok(0)
+    }
+
+    fn replay(&mut self, name: &str) -> Result<i32> {
+        validate_name(name)?;
+        let path = self
+            .root_dirs
+            .iter()
+            .map(|dir| run_path(dir, name))
+            .find(|path| path.exists())
+            .ok_or_else(|| ErrorRunEngine::NotFound(name.to_string()))?;
+        let invalid = |err: String| ErrorRunEngine::Invalid(path.clone(), err);
+        let run: Run = serde_yaml::from_str(&fs::read_to_string(&path)?)
+            .map_err(|e| invalid(e.to_string()))?;
+        let alias = Identifier::from_str(&run.alias);
+        if self.sam_engine.aliases.get(&alias).is_none() {
+            return Err(ErrorRunEngine::UnknownAlias(alias, path));
+        }
+        let choices = run.choices().map_err(invalid)?;
+        // the placeholders are asked for even when a default is configured.
+        self.sam_engine.defaults.set_defaults(&choices);
+        Ok(self
+            .sam_engine
+            .run(SamCommand::ExecuteAlias { alias })?
+            .exit_code())
+    }
+}
+
+fn run_path(root_dir: &Path, name: &str) -> PathBuf {
+    root_dir.join(RUNS_DIR).join(format!("{}.yaml", name))
+}
+
+// validate_name keeps the runs in the runs directory.
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(ErrorRunEngine::InvalidName(name.to_string()))
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ErrorRunEngine>;
+
+#[derive(Debug, Error)]
+pub enum ErrorRunEngine {
+    #[error("invalid run name '{0}', allowed characters are [a-zA-Z0-9_-]")]
+    InvalidName(String),
+    #[error("there is no run named {0}, record it with `sam record {0}`")]
+    NotFound(String),
+    #[error("the run {} is invalid\n-> {1}", .0.display())]
+    Invalid(PathBuf, String),
+    #[error("the alias {0} of the run {} doesn't exist anymore", .1.display())]
+    UnknownAlias(Identifier, PathBuf),
+    #[error("no root directory is configured to record the run in")]
+    NoRootDir,
+    #[error("{0}")]
+    SamEngine(#[from] ErrorSamEngine),
+    #[error("can't read or write the run\n-> {0}")]
+    Io(#[from] io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_name, Run};
+    use maplit::hashmap;
+    use sam_core::entities::aliases::Alias;
+    use sam_core::entities::choices::Choice;
+    use sam_core::entities::identifiers::Identifier;
+    use sam_core::entities::namespaces::NamespaceUpdater;
+
+    #[test]
+    fn test_run() {
+        let env = Identifier::with_namespace("env", Some("k8s"));
+        let pods = Identifier::with_namespace("pods", Some("k8s"));
+        let token = Identifier::with_namespace("token", Some("k8s"));
+        let mut alias = Alias::new("rollout", "", "deploy {{ env }} {{ pods }} {{ token }}");
+        NamespaceUpdater::update(&mut alias, "k8s");
+        let resolved = alias
+            .with_choices(&hashmap! {
+                env.clone() => vec![Choice::from_value("prod")],
+                pods.clone() => vec![Choice::from_value("api"), Choice::from_value("web")],
+                token.clone() => vec![Choice::from_value("s3cr3t")],
+            })
+            .unwrap()
+            .with_sensitive(vec![token]);
+        let run = Run::from_alias(&resolved);
+        let content = serde_yaml::to_string(&run).unwrap();
+        assert!(!content.contains("s3cr3t"));
+
+        let run: Run = serde_yaml::from_str(&content).unwrap();
+        assert_eq!(run.alias, "k8s::rollout");
+        assert_eq!(
+            run.choices().unwrap(),
+            hashmap! {
+                env => vec![Choice::from_value("prod")],
+                pods => vec![Choice::from_value("api"), Choice::from_value("web")],
+            }
+        );
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("deploy-prod_2").is_ok());
+        assert!(validate_name("../deploy").is_err());
+        assert!(validate_name("").is_err());
+    }
+}
@@ -0,0 +1,567 @@
+use sam_core::entities::identifiers::Identifier;
+use sam_core::entities::namespaces::Namespace;
+use sam_readers::{read_aliases_from_path, read_vars_repository, Location, Sources};
+use sam_terminals::input::{terminal, user_input};
+use sam_tui::modal_view::{ModalView, Value};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// ScaffoldCommand creates an alias or a var through a few questions,
+/// the identifier, `name` or `namespace::name`, is asked for when it's missing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScaffoldCommand {
+    NewAlias(Option<String>),
+    NewVar(Option<String>),
+}
+
+/// ScaffoldEngine appends the aliases and the vars it creates to the aliases or the vars
+/// file of their namespace, and creates the file, or the namespace in the first root
+/// directory, when it doesn't exist yet.
+pub struct ScaffoldEngine {
+    pub root_dirs: Vec<PathBuf>,
+    pub aliases_files: Vec<PathBuf>,
+    pub vars_files: Vec<PathBuf>,
+    pub sources: Sources,
+}
+
+/// Kind is what the engine creates, an alias or a var.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Alias,
+    Var,
+}
+
+impl Kind {
+    const fn file_name(self) -> &'static str {
+        match self {
+            Kind::Alias => "aliases.yaml",
+            Kind::Var => "vars.yaml",
+        }
+    }
+
+    fn defined(self, sources: &Sources, id: &Identifier) -> Option<Location> {
+        match self {
+            Kind::Alias => sources.alias(id),
+            Kind::Var => sources.var(id),
+        }
+    }
+
+    // vars are referenced as `{{ namespace::name }}` in commands, their names and
+    // the namespaces are limited to what these references accept.
+    fn is_valid_name(self, name: &str) -> bool {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || (self == Kind::Alias && c == '-'))
+    }
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Kind::Alias => write!(f, "alias"),
+            Kind::Var => write!(f, "var"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, PartialEq)]
+struct AliasEntry {
+    name: String,
+    desc: String,
+    alias: String,
+}
+
+#[derive(Debug, Default, Serialize, PartialEq)]
+struct VarEntry {
+    name: String,
+    desc: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    choices: Vec<ChoiceEntry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from_command: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from_input: Option<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct ChoiceEntry {
+    value: String,
+}
+
+impl ScaffoldEngine {
+    pub fn run(&self, cmd: ScaffoldCommand) -> Result<i32> {
+        // stdin isn't locked for good, the modal view reads it as well.
+        let mut input = io::BufReader::new(user_input());
+        match cmd {
+            ScaffoldCommand::NewAlias(id) => self.new_alias(id, &mut input),
+            ScaffoldCommand::NewVar(id) => self.new_var(id, &mut input),
+        }
+    }
+
+    fn new_alias(&self, id: Option<String>, input: &mut impl BufRead) -> Result<i32> {
+        let id = match self.identifier(Kind::Alias, id, input)? {
+            Some(id) => id,
+            None => return Ok(0),
+        };
+        let entry = AliasEntry {
+            name: id.name().to_string(),
+            desc: ask(input, "description")?,
+            alias: ask_until_answered(input, "command, vars are written {{ name }}")?,
+        };
+        let file = self.target_file(Kind::Alias, &id)?;
+        append_entry(Kind::Alias, &file, &entry)?;
+        println!("alias {} added to {}", id, file.display());
+        for var in Identifier::parse(&entry.alias, id.namespace()) {
+            if self.sources.var(&var).is_none() {
+                println!(
+                    "  var {} isn't defined yet, `sam new var {}` creates it",
+                    var, var
+                );
+            }
+        }
+        Ok(0)
+    }
+
+    fn new_var(&self, id: Option<String>, input: &mut impl BufRead) -> Result<i32> {
+        let id = match self.identifier(Kind::Var, id, input)? {
+            Some(id) => id,
+            None => return Ok(0),
+        };
+        let mut entry = VarEntry {
+            name: id.name().to_string(),
+            desc: ask(input, "description")?,
+            ..VarEntry::default()
+        };
+        let source = match choose("where the choices come from", VarSource::ALL.to_vec())? {
+            Some(source) => source,
+            None => return Ok(0),
+        };
+        match source {
+            VarSource::Choices => loop {
+                let value = ask(input, "choice, leave it empty when you are done")?;
+                if value.is_empty() && !entry.choices.is_empty() {
+                    break;
+                }
+                if !value.is_empty() {
+                    entry.choices.push(ChoiceEntry { value });
+                }
+            },
+            VarSource::FromCommand => {
+                entry.from_command = Some(ask_until_answered(
+                    input,
+                    "command printing one choice per line",
+                )?)
+            }
+            VarSource::FromInput => {
+                entry.from_input = Some(ask(input, "prompt shown when the value is asked for")?)
+            }
+        }
+        let file = self.target_file(Kind::Var, &id)?;
+        append_entry(Kind::Var, &file, &entry)?;
+        println!("var {} added to {}", id, file.display());
+        Ok(0)
+    }
+
+    // identifier validates the identifier, asks for the namespace when it's missing and
+    // refuses the identifiers that are already in use. None means the user gave up.
+    fn identifier(
+        &self,
+        kind: Kind,
+        given: Option<String>,
+        input: &mut impl BufRead,
+    ) -> Result<Option<Identifier>> {
+        let given = match given {
+            Some(given) => given,
+            None => ask_until_answered(
+                input,
+                &format!(
+                    "name of the {}, or namespace::name to pick its namespace",
+                    kind
+                ),
+            )?,
+        };
+        let (name, namespace) = match given.split_once("::") {
+            Some((namespace, name)) => (name, Some(namespace)),
+            None => (given.as_str(), None),
+        };
+        if !kind.is_valid_name(name) {
+            return Err(ErrorScaffoldEngine::InvalidName(name.to_string(), kind));
+        }
+        if let Some(namespace) = namespace.filter(|ns| !Kind::Var.is_valid_name(ns)) {
+            return Err(ErrorScaffoldEngine::InvalidNamespace(namespace.to_string()));
+        }
+        let namespace = match namespace {
+            Some(namespace) => namespace.to_string(),
+            None => match self.choose_namespace(kind)? {
+                Some(namespace) => namespace,
+                None => return Ok(None),
+            },
+        };
+        let id = Identifier::with_namespace(name, Some(namespace));
+        match kind.defined(&self.sources, &id) {
+            Some(location) => Err(ErrorScaffoldEngine::AlreadyDefined(kind, id, location)),
+            None => Ok(Some(id)),
+        }
+    }
+
+    fn choose_namespace(&self, kind: Kind) -> Result<Option<String>> {
+        let namespaces: Vec<NamespaceChoice> = self
+            .namespaces()
+            .into_iter()
+            .map(|(name, dir)| NamespaceChoice {
+                name,
+                dir: dir.display().to_string(),
+            })
+            .collect();
+        if namespaces.is_empty() {
+            return Err(ErrorScaffoldEngine::NoNamespace(kind));
+        }
+        Ok(choose("the namespace", namespaces)?.map(|ns| ns.name))
+    }
+
+    // namespaces are the directories of the aliases and the vars files,
+    // the first directory found is kept when several have the same name.
+    fn namespaces(&self) -> BTreeMap<String, PathBuf> {
+        let mut namespaces = BTreeMap::new();
+        for file in self.aliases_files.iter().chain(self.vars_files.iter()) {
+            if let (Some(namespace), Some(dir)) = (namespace_of(file), file.parent()) {
+                namespaces
+                    .entry(namespace.to_string())
+                    .or_insert_with(|| dir.to_path_buf());
+            }
+        }
+        namespaces
+    }
+
+    fn target_file(&self, kind: Kind, id: &Identifier) -> Result<PathBuf> {
+        let namespace = id.namespace().unwrap_or_default();
+        let files = match kind {
+            Kind::Alias => &self.aliases_files,
+            Kind::Var => &self.vars_files,
+        };
+        if let Some(file) = files.iter().find(|f| namespace_of(f) == Some(namespace)) {
+            return Ok(file.clone());
+        }
+        let dir = self
+            .namespaces()
+            .remove(namespace)
+            .or_else(|| self.root_dirs.first().map(|root| root.join(namespace)))
+            .ok_or(ErrorScaffoldEngine::NoRootDir)?;
+        Ok(dir.join(kind.file_name()))
+    }
+}
+
+fn namespace_of(file: &Path) -> Option<&str> {
+    file.parent()
+        .and_then(|dir| dir.file_name())
+        .and_then(|name| name.to_str())
+}
+
+// choose shows the values in the modal view, which reads the keys from the terminal.
+fn choose<V: Value>(what: &str, values: Vec<V>) -> Result<Option<V>> {
+    if !io::stdin().is_terminal() && terminal().is_none() {
+        return Err(ErrorScaffoldEngine::NotATerminal(what.to_string()));
+    }
+    Ok(ModalView::new(values, vec![], false)
+        .run()
+        .and_then(|response| response.values().next()))
+}
+
+fn ask(input: &mut impl BufRead, question: &str) -> Result<String> {
+    print!("{}: ", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    if input.read_line(&mut answer)? == 0 {
+        return Err(ErrorScaffoldEngine::NoAnswer(question.to_string()));
+    }
+    Ok(answer.trim().to_string())
+}
+
+fn ask_until_answered(input: &mut impl BufRead, question: &str) -> Result<String> {
+    loop {
+        let answer = ask(input, question)?;
+        if !answer.is_empty() {
+            return Ok(answer);
+        }
+    }
+}
+
+// append_entry writes the entry at the end of the file, separated from the previous
+// ones by an empty line. The file is read back and restored when sam can't read it
+// anymore, which happens when it isn't a list of entries.
+fn append_entry(kind: Kind, file: &Path, entry: &impl Serialize) -> Result<()> {
+    let yaml = serde_yaml::to_string(std::slice::from_ref(entry))?;
+    let yaml = yaml.trim_start_matches("---\n");
+    let previous = match fs::read_to_string(file) {
+        Ok(content) => Some(content),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err.into()),
+    };
+    let mut content = previous.clone().unwrap_or_default();
+    if !content.trim().is_empty() {
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push('\n');
+    }
+    content.push_str(yaml);
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(file, content)?;
+
+    let read_back = match kind {
+        Kind::Alias => read_aliases_from_path(file)
+            .map_err(|e| e.to_string())
+            .err(),
+        Kind::Var => read_vars_repository(file).map_err(|e| e.to_string()).err(),
+    };
+    if let Some(err) = read_back {
+        match previous {
+            Some(previous) => fs::write(file, previous)?,
+            None => fs::remove_file(file)?,
+        }
+        return Err(ErrorScaffoldEngine::Unreadable(file.to_path_buf(), err));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct NamespaceChoice {
+    name: String,
+    dir: String,
+}
+
+impl Value for NamespaceChoice {
+    fn text(&self) -> &str {
+        &self.name
+    }
+
+    fn desc(&self) -> Option<&str> {
+        Some(&self.dir)
+    }
+
+    fn preview(&self) -> String {
+        self.dir.clone()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum VarSource {
+    Choices,
+    FromCommand,
+    FromInput,
+}
+
+impl VarSource {
+    const ALL: [VarSource; 3] = [
+        VarSource::Choices,
+        VarSource::FromCommand,
+        VarSource::FromInput,
+    ];
+}
+
+impl Value for VarSource {
+    fn text(&self) -> &str {
+        match self {
+            VarSource::Choices => "choices",
+            VarSource::FromCommand => "from_command",
+            VarSource::FromInput => "from_input",
+        }
+    }
+
+    fn desc(&self) -> Option<&str> {
+        Some(match self {
+            VarSource::Choices => "a fixed list of values",
+            VarSource::FromCommand => "the lines printed by a command",
+            VarSource::FromInput => "a value typed when the alias runs",
+        })
+    }
+
+    fn preview(&self) -> String {
+        self.desc().unwrap_or_default().to_string()
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ErrorScaffoldEngine>;
+
+#[derive(Debug, Error)]
+pub enum ErrorScaffoldEngine {
+    #[error(
+        "{0} isn't a valid {1} name, use letters, digits and `_`, aliases can use `-` as well"
+    )]
+    InvalidName(String, Kind),
+    #[error("{0} isn't a valid namespace, use letters, digits and `_`")]
+    InvalidNamespace(String),
+    #[error("{0} {1} is already defined in {2}")]
+    AlreadyDefined(Kind, Identifier, Location),
+    #[error("there is no namespace yet, name the {0} namespace::name to create one")]
+    NoNamespace(Kind),
+    #[error("there is no root directory to create the namespace in, add one to root_dir")]
+    NoRootDir,
+    #[error("{0} can only be picked in a terminal")]
+    NotATerminal(String),
+    #[error("no answer was provided for: {0}")]
+    NoAnswer(String),
+    #[error("sam can't read {0} once the entry is added, it was left unchanged\n-> {1}")]
+    Unreadable(PathBuf, String),
+    #[error("could not format the entry\n-> {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("{0}")]
+    Io(#[from] io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{append_entry, AliasEntry, ChoiceEntry, Kind, ScaffoldEngine, VarEntry};
+    use sam_core::algorithms::VarsCollection;
+    use sam_core::entities::identifiers::Identifier;
+    use sam_readers::{read_aliases_from_path, read_vars_repository, Sources};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("sam-scaffold-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_is_valid_name() {
+        assert!(Kind::Alias.is_valid_name("list-pods_2"));
+        assert!(Kind::Var.is_valid_name("pod_2"));
+        assert!(!Kind::Var.is_valid_name("pod-2"));
+        assert!(!Kind::Alias.is_valid_name("list pods"));
+        assert!(!Kind::Alias.is_valid_name(""));
+    }
+
+    #[test]
+    fn test_identifier() {
+        let mut sources = Sources::default();
+        sources.add_alias(
+            Identifier::with_namespace("pods", Some("k8s")),
+            "/recipes/k8s/aliases.yaml",
+        );
+        let engine = ScaffoldEngine {
+            root_dirs: vec![],
+            aliases_files: vec![],
+            vars_files: vec![],
+            sources,
+        };
+        let mut input = "k8s::logs\n".as_bytes();
+        assert_eq!(
+            engine.identifier(Kind::Alias, None, &mut input).unwrap(),
+            Some(Identifier::with_namespace("logs", Some("k8s")))
+        );
+        let err = engine
+            .identifier(Kind::Alias, Some(String::from("k8s::pods")), &mut input)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "alias k8s::pods is already defined in /recipes/k8s/aliases.yaml"
+        );
+        let err = engine
+            .identifier(Kind::Var, Some(String::from("k8s::my-pod")), &mut input)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "my-pod isn't a valid var name, use letters, digits and `_`, aliases can use `-` as well"
+        );
+        let err = engine
+            .identifier(Kind::Var, Some(String::from("pod")), &mut input)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "there is no namespace yet, name the var namespace::name to create one"
+        );
+    }
+
+    #[test]
+    fn test_target_file() {
+        let engine = ScaffoldEngine {
+            root_dirs: vec![PathBuf::from("/recipes"), PathBuf::from("/team")],
+            aliases_files: vec![PathBuf::from("/team/k8s/aliases.yml")],
+            vars_files: vec![PathBuf::from("/team/docker/vars.yaml")],
+            sources: Sources::default(),
+        };
+        let target = |kind, ns| {
+            engine
+                .target_file(kind, &Identifier::with_namespace("x", Some(ns)))
+                .unwrap()
+        };
+        assert_eq!(
+            target(Kind::Alias, "k8s"),
+            PathBuf::from("/team/k8s/aliases.yml")
+        );
+        assert_eq!(
+            target(Kind::Var, "k8s"),
+            PathBuf::from("/team/k8s/vars.yaml")
+        );
+        assert_eq!(
+            target(Kind::Alias, "docker"),
+            PathBuf::from("/team/docker/aliases.yaml")
+        );
+        assert_eq!(
+            target(Kind::Var, "git"),
+            PathBuf::from("/recipes/git/vars.yaml")
+        );
+    }
+
+    #[test]
+    fn test_append_entry() {
+        let dir = temp_dir("append");
+        let aliases = dir.join("k8s").join("aliases.yaml");
+        let pods = AliasEntry {
+            name: String::from("pods"),
+            desc: String::from("lists the pods"),
+            alias: String::from("kubectl get pods -n {{ namespace }}"),
+        };
+        append_entry(Kind::Alias, &aliases, &pods).unwrap();
+        let logs = AliasEntry {
+            name: String::from("logs"),
+            desc: String::from("tails: the logs"),
+            alias: String::from("kubectl logs -f {{ pod }}"),
+        };
+        append_entry(Kind::Alias, &aliases, &logs).unwrap();
+        assert_eq!(
+            fs::read_to_string(&aliases).unwrap(),
+            "- name: pods\n  desc: lists the pods\n  alias: \"kubectl get pods -n {{ namespace }}\"\n\n\
+             - name: logs\n  desc: \"tails: the logs\"\n  alias: \"kubectl logs -f {{ pod }}\"\n"
+        );
+        let read = read_aliases_from_path(&aliases).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(
+            read[1].identifier(),
+            Identifier::with_namespace("logs", Some("k8s"))
+        );
+
+        let vars = dir.join("k8s").join("vars.yaml");
+        let namespace = VarEntry {
+            name: String::from("namespace"),
+            desc: String::from("a namespace"),
+            choices: vec![ChoiceEntry {
+                value: String::from("default"),
+            }],
+            ..VarEntry::default()
+        };
+        append_entry(Kind::Var, &vars, &namespace).unwrap();
+        let repository = read_vars_repository(&vars).unwrap();
+        let var = repository
+            .get(&Identifier::with_namespace("namespace", Some("k8s")))
+            .unwrap();
+        assert_eq!(var.choices()[0].value(), "default");
+
+        // a file sam can't read once the entry is added is left as it was.
+        fs::write(&vars, "name: not a list\n").unwrap();
+        assert!(append_entry(Kind::Var, &vars, &namespace).is_err());
+        assert_eq!(fs::read_to_string(&vars).unwrap(), "name: not a list\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
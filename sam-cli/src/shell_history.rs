@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+// how long to wait for zsh to release its history lock, and the age past which
+// a lock is considered stale, zsh uses the same 10 seconds.
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+// the bytes zsh writes as Meta followed by the byte xor 32 in its history file.
+const ZSH_META: u8 = 0x83;
+const ZSH_MARKER: u8 = 0xa2;
+
+/// ShellHistory is the shell whose history file the commands run by sam are appended to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ShellHistory {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl ShellHistory {
+    /// default_file returns $HISTFILE for bash and zsh when it's exported, or
+    /// the file each shell uses by default otherwise.
+    pub fn default_file(self) -> Option<PathBuf> {
+        let home = dirs::home_dir()?;
+        let histfile = env::var_os("HISTFILE").map(PathBuf::from);
+        Some(match self {
+            ShellHistory::Bash => histfile.unwrap_or_else(|| home.join(".bash_history")),
+            ShellHistory::Zsh => histfile.unwrap_or_else(|| {
+                env::var_os("ZDOTDIR")
+                    .map(PathBuf::from)
+                    .unwrap_or(home)
+                    .join(".zsh_history")
+            }),
+            ShellHistory::Fish => env::var_os("XDG_DATA_HOME")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join(".local/share"))
+                .join("fish/fish_history"),
+        })
+    }
+
+    // entry formats a command the way the shell writes it in its history, bash
+    // entries only have a timestamp when the history file already has some.
+    fn entry(self, command: &str, timestamp: u64, duration: u64, timestamps: bool) -> Vec<u8> {
+        match self {
+            ShellHistory::Bash if timestamps => format!("#{}\n{}\n", timestamp, command).into(),
+            ShellHistory::Bash => format!("{}\n", command).into(),
+            ShellHistory::Zsh => {
+                let command = command.replace('\n', "\\\n");
+                let mut entry = format!(": {}:{};", timestamp, duration).into_bytes();
+                for byte in command.bytes() {
+                    if byte == 0 || (ZSH_META..=ZSH_MARKER).contains(&byte) {
+                        entry.extend([ZSH_META, byte ^ 32]);
+                    } else {
+                        entry.push(byte);
+                    }
+                }
+                entry.push(b'\n');
+                entry
+            }
+            ShellHistory::Fish => {
+                let command = command.replace('\\', "\\\\").replace('\n', "\\n");
+                format!("- cmd: {}\n  when: {}\n", command, timestamp).into()
+            }
+        }
+    }
+
+    /// append adds the commands to the history file while holding the lock the shell
+    /// uses for it: a `.LOCK` file next to it for zsh and flock for fish, bash doesn't lock.
+    pub fn append(
+        self,
+        path: &Path,
+        commands: &[String],
+        timestamp: SystemTime,
+        duration: Duration,
+    ) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let _lock = match self {
+            ShellHistory::Zsh => Some(LockFile::acquire(path)?),
+            _ => None,
+        };
+        let timestamps = self == ShellHistory::Bash && has_timestamps(path);
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if self == ShellHistory::Fish {
+            file.lock()?;
+        }
+        let timestamp = timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let mut entries = vec![];
+        for command in commands {
+            entries.extend(self.entry(command, timestamp, duration.as_secs(), timestamps));
+        }
+        // a single write so that the entries aren't interleaved with the ones of a shell.
+        file.write_all(&entries)
+    }
+}
+
+// has_timestamps tells whether a bash history file records timestamps, as
+// `#1700000000` lines before the commands.
+fn has_timestamps(path: &Path) -> bool {
+    fs::read(path).is_ok_and(|content| {
+        content.split(|b| *b == b'\n').any(|line| {
+            line.len() > 1 && line[0] == b'#' && line[1..].iter().all(u8::is_ascii_digit)
+        })
+    })
+}
+
+/// LockFile is the `<histfile>.LOCK` file zsh creates while writing its history,
+/// it's removed when the lock is dropped.
+struct LockFile {
+    path: PathBuf,
+}
+
+impl LockFile {
+    fn acquire(histfile: &Path) -> io::Result<LockFile> {
+        let mut path = histfile.as_os_str().to_owned();
+        path.push(".LOCK");
+        let path = PathBuf::from(path);
+        let mut waited = Duration::ZERO;
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(LockFile { path }),
+                Err(err) if err.kind() != io::ErrorKind::AlreadyExists => return Err(err),
+                Err(err) if waited >= LOCK_TIMEOUT => {
+                    let stale = fs::metadata(&path)
+                        .and_then(|m| m.modified())
+                        .map(|modified| modified.elapsed().unwrap_or_default() >= LOCK_TIMEOUT)
+                        .unwrap_or(true);
+                    if !stale {
+                        return Err(err);
+                    }
+                    let _ = fs::remove_file(&path);
+                    waited = Duration::ZERO;
+                }
+                Err(_) => {
+                    thread::sleep(LOCK_POLL_INTERVAL);
+                    waited += LOCK_POLL_INTERVAL;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShellHistory;
+    use std::fs;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_entry() {
+        assert_eq!(
+            ShellHistory::Bash.entry("ls -l", 1700000000, 2, false),
+            b"ls -l\n"
+        );
+        assert_eq!(
+            ShellHistory::Bash.entry("ls -l", 1700000000, 2, true),
+            b"#1700000000\nls -l\n"
+        );
+        assert_eq!(
+            ShellHistory::Zsh.entry("ls\nwc -l", 1700000000, 2, false),
+            b": 1700000000:2;ls\\\nwc -l\n"
+        );
+        // Ġ is 0xc4 0xa0 in utf-8, zsh metafies the second byte.
+        assert_eq!(
+            ShellHistory::Zsh.entry("echo Ġ", 1, 0, false),
+            [&b": 1:0;echo \xc4"[..], &[0x83, 0xa0 ^ 32], b"\n"].concat()
+        );
+        assert_eq!(
+            ShellHistory::Fish.entry("printf 'a\\n'\necho b", 1700000000, 2, false),
+            b"- cmd: printf 'a\\\\n'\\necho b\n  when: 1700000000\n"
+        );
+    }
+
+    #[test]
+    fn test_append() {
+        let dir = std::env::temp_dir().join(format!("sam-shell-history-{}", std::process::id()));
+        let histfile = dir.join(".bash_history");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&histfile, "#1600000000\ncd /tmp\n").unwrap();
+        let at = SystemTime::UNIX_EPOCH + Duration::from_secs(1700000000);
+        ShellHistory::Bash
+            .append(&histfile, &[String::from("ls")], at, Duration::ZERO)
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(&histfile).unwrap(),
+            "#1600000000\ncd /tmp\n#1700000000\nls\n"
+        );
+
+        let histfile = dir.join(".zsh_history");
+        ShellHistory::Zsh
+            .append(&histfile, &[String::from("ls")], at, Duration::from_secs(3))
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(&histfile).unwrap(),
+            ": 1700000000:3;ls\n"
+        );
+        assert!(!dir.join(".zsh_history.LOCK").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,367 @@
+use crate::cli::OutputFormat;
+use chrono::{Datelike, TimeZone, Timelike};
+use sam_core::entities::identifiers::Identifier;
+use sam_persistence::{AliasHistory, ErrorAliasHistory, HistoryEntry};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use thiserror::Error;
+
+const DAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+// the number of aliases listed as the most used ones.
+const MOST_USED: usize = 10;
+
+pub struct StatsEngine {
+    pub history: AliasHistory,
+    pub aliases: Vec<Identifier>,
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatsCommand {
+    Heatmap { alias: Option<Identifier> },
+    Summary,
+}
+
+impl StatsEngine {
+    pub fn run(self, cmd: StatsCommand) -> Result<i32> {
+        match cmd {
+            StatsCommand::Heatmap { alias } => self.heatmap(alias),
+            StatsCommand::Summary => self.summary(),
+        }
+    }
+
+    fn summary(self) -> Result<i32> {
+        let summary = Summary::from_entries(self.history.entries()?, &self.aliases);
+        match self.output {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&summary)?),
+            OutputFormat::Text => print!("{}", summary),
+        }
+        Ok(0)
+    }
+
+    fn heatmap(self, alias: Option<Identifier>) -> Result<i32> {
+        let timestamps = self
+            .history
+            .entries()?
+            .filter(|e| alias.as_ref().map(|a| e.r.name() == a).unwrap_or(true))
+            .filter_map(|e: HistoryEntry| e.timestamp);
+        let heatmap = Heatmap::from_timestamps(timestamps, &chrono::Local);
+        if heatmap.total() == 0 {
+            println!("No timestamped executions in history yet.");
+            return Ok(0);
+        }
+        println!(
+            "{}{}Executions by day and hour{}",
+            crossterm::style::Attribute::Bold,
+            crossterm::style::SetForegroundColor(crossterm::style::Color::DarkGreen),
+            crossterm::style::Attribute::Reset,
+        );
+        if let Some(a) = alias {
+            println!("for alias {}", a);
+        }
+        println!();
+        print!("{}", heatmap);
+        Ok(0)
+    }
+}
+
+/// Heatmap counts executions per day of the week (rows, starting on monday)
+/// and per hour of the day (columns).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Heatmap {
+    cells: [[usize; 24]; 7],
+}
+
+impl Heatmap {
+    pub fn from_timestamps<Tz: TimeZone>(timestamps: impl Iterator<Item = i64>, tz: &Tz) -> Self {
+        let mut heatmap = Heatmap::default();
+        for ts in timestamps {
+            if let Some(time) = tz.timestamp_opt(ts, 0).single() {
+                let day = time.weekday().num_days_from_monday() as usize;
+                let hour = time.hour() as usize;
+                heatmap.cells[day][hour] += 1;
+            }
+        }
+        heatmap
+    }
+
+    pub fn total(&self) -> usize {
+        self.cells.iter().flatten().sum()
+    }
+
+    fn max(&self) -> usize {
+        self.cells.iter().flatten().copied().max().unwrap_or(0)
+    }
+
+    fn shade(&self, count: usize) -> char {
+        let max = self.max();
+        if count == 0 || max == 0 {
+            SHADES[0]
+        } else {
+            let levels = SHADES.len() - 1;
+            SHADES[(count * levels).div_ceil(max).min(levels)]
+        }
+    }
+}
+
+impl std::fmt::Display for Heatmap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "     ")?;
+        for hour in (0..24).step_by(3) {
+            write!(f, "{:<6}", format!("{:02}h", hour))?;
+        }
+        writeln!(f)?;
+        for (day, row) in DAYS.iter().zip(self.cells.iter()) {
+            write!(f, "{}  ", day)?;
+            for count in row {
+                let shade = self.shade(*count);
+                write!(f, "{}{}", shade, shade)?;
+            }
+            writeln!(f, "  {}", row.iter().sum::<usize>())?;
+        }
+        writeln!(
+            f,
+            "\n{} executions, darker cells are busier (max {} in an hour)",
+            self.total(),
+            self.max()
+        )
+    }
+}
+
+/// Summary aggregates the history: the most used aliases, the runs per namespace, how long
+/// resolving the vars takes on average and the aliases that never ran, which may be removed.
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct Summary {
+    runs: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    average_resolution_ms: Option<u64>,
+    most_used: Vec<AliasUsage>,
+    namespaces: Vec<NamespaceUsage>,
+    never_used: Vec<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct AliasUsage {
+    alias: String,
+    runs: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    average_resolution_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct NamespaceUsage {
+    namespace: String,
+    runs: usize,
+}
+
+impl Summary {
+    pub fn from_entries(
+        entries: impl Iterator<Item = HistoryEntry>,
+        aliases: &[Identifier],
+    ) -> Self {
+        let mut runs: HashMap<Identifier, (usize, Vec<Duration>)> = HashMap::new();
+        for entry in entries {
+            let (count, durations) = runs.entry(entry.r.name().clone()).or_default();
+            *count += 1;
+            durations.extend(entry.r.resolution_time());
+        }
+        let mut namespaces: HashMap<String, usize> = HashMap::new();
+        for (alias, (count, _)) in &runs {
+            let namespace = alias.namespace.clone().unwrap_or_default();
+            *namespaces.entry(namespace).or_default() += count;
+        }
+        let all: Vec<Duration> = runs.values().flat_map(|(_, d)| d).copied().collect();
+        let mut most_used: Vec<AliasUsage> = runs
+            .iter()
+            .map(|(alias, (count, durations))| AliasUsage {
+                alias: alias.to_string(),
+                runs: *count,
+                average_resolution_ms: average_ms(durations),
+            })
+            .collect();
+        most_used.sort_by(|a, b| b.runs.cmp(&a.runs).then_with(|| a.alias.cmp(&b.alias)));
+        most_used.truncate(MOST_USED);
+        let mut namespaces: Vec<NamespaceUsage> = namespaces
+            .into_iter()
+            .map(|(namespace, runs)| NamespaceUsage { namespace, runs })
+            .collect();
+        namespaces.sort_by(|a, b| {
+            b.runs
+                .cmp(&a.runs)
+                .then_with(|| a.namespace.cmp(&b.namespace))
+        });
+        let mut never_used: Vec<String> = aliases
+            .iter()
+            .filter(|alias| !runs.contains_key(alias))
+            .map(Identifier::to_string)
+            .collect();
+        never_used.sort();
+        Summary {
+            runs: runs.values().map(|(count, _)| count).sum(),
+            average_resolution_ms: average_ms(&all),
+            most_used,
+            namespaces,
+            never_used,
+        }
+    }
+}
+
+fn average_ms(durations: &[Duration]) -> Option<u64> {
+    let total: Duration = durations.iter().sum();
+    (!durations.is_empty()).then(|| total.as_millis() as u64 / durations.len() as u64)
+}
+
+fn seconds(ms: u64) -> String {
+    match ms {
+        0..=999 => format!("{}ms", ms),
+        _ => format!("{:.1}s", ms as f64 / 1000.0),
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let title = |f: &mut std::fmt::Formatter<'_>, title: &str| {
+            writeln!(
+                f,
+                "\n{}{}{}{}",
+                crossterm::style::Attribute::Bold,
+                crossterm::style::SetForegroundColor(crossterm::style::Color::DarkGreen),
+                title,
+                crossterm::style::Attribute::Reset,
+            )
+        };
+        write!(f, "{} runs", self.runs)?;
+        match self.average_resolution_ms {
+            Some(ms) => writeln!(f, ", the vars are resolved in {} on average", seconds(ms))?,
+            None => writeln!(f)?,
+        }
+        if !self.most_used.is_empty() {
+            title(f, "Most used aliases")?;
+            let width = self
+                .most_used
+                .iter()
+                .map(|u| u.alias.len())
+                .max()
+                .unwrap_or(0);
+            for usage in &self.most_used {
+                write!(
+                    f,
+                    "  {:<width$}  {:>5} runs",
+                    usage.alias,
+                    usage.runs,
+                    width = width
+                )?;
+                match usage.average_resolution_ms {
+                    Some(ms) => writeln!(f, "  resolved in {}", seconds(ms))?,
+                    None => writeln!(f)?,
+                }
+            }
+        }
+        if !self.namespaces.is_empty() {
+            title(f, "Runs per namespace")?;
+            for usage in &self.namespaces {
+                let namespace = match usage.namespace.as_str() {
+                    "" => "(none)",
+                    namespace => namespace,
+                };
+                writeln!(f, "  {:<20}  {:>5}", namespace, usage.runs)?;
+            }
+        }
+        if !self.never_used.is_empty() {
+            title(f, "Never used, candidates for cleanup")?;
+            for alias in &self.never_used {
+                writeln!(f, "  {}", alias)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ErrorStatsEngine>;
+#[derive(Debug, Error)]
+pub enum ErrorStatsEngine {
+    #[error("could not read from history\n-> {0}")]
+    History(#[from] ErrorAliasHistory),
+    #[error("could not write the statistics as json\n-> {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Heatmap, Summary};
+    use chrono::Utc;
+    use sam_core::entities::aliases::Alias;
+    use sam_core::entities::identifiers::Identifier;
+    use sam_core::entities::namespaces::NamespaceUpdater;
+    use sam_persistence::HistoryEntry;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    fn entry(namespace: &str, name: &str, resolution_ms: Option<u64>) -> HistoryEntry {
+        let mut alias = Alias::new(name, "", "echo");
+        NamespaceUpdater::update(&mut alias, namespace);
+        let r = alias.with_choices(&HashMap::new()).unwrap();
+        HistoryEntry {
+            r: match resolution_ms {
+                Some(ms) => r.with_resolution_time(Duration::from_millis(ms)),
+                None => r,
+            },
+            pwd: String::from("/"),
+            timestamp: None,
+            version: 0,
+        }
+    }
+
+    #[test]
+    fn test_summary() {
+        let entries = vec![
+            entry("k8s", "logs", Some(1_000)),
+            entry("k8s", "logs", Some(3_000)),
+            entry("k8s", "pods", None),
+            entry("git", "push", Some(500)),
+        ];
+        let aliases = vec![
+            Identifier::with_namespace("logs", Some("k8s")),
+            Identifier::with_namespace("cleanup", Some("k8s")),
+        ];
+        let summary = Summary::from_entries(entries.into_iter(), &aliases);
+        assert_eq!(
+            serde_json::to_value(&summary).unwrap(),
+            json!({
+                "runs": 4,
+                "average_resolution_ms": 1500,
+                "most_used": [
+                    {"alias": "k8s::logs", "runs": 2, "average_resolution_ms": 2000},
+                    {"alias": "git::push", "runs": 1, "average_resolution_ms": 500},
+                    {"alias": "k8s::pods", "runs": 1},
+                ],
+                "namespaces": [
+                    {"namespace": "k8s", "runs": 3},
+                    {"namespace": "git", "runs": 1},
+                ],
+                "never_used": ["k8s::cleanup"],
+            })
+        );
+        assert!(summary
+            .to_string()
+            .contains("k8s::logs      2 runs  resolved in 2.0s"));
+    }
+
+    #[test]
+    fn test_heatmap_from_timestamps() {
+        // 2022-04-04 was a monday.
+        let monday_9am = 1_649_062_800;
+        let sunday_11pm = monday_9am + 6 * 86_400 + 14 * 3_600;
+        let timestamps = vec![monday_9am, monday_9am + 60, sunday_11pm];
+        let heatmap = Heatmap::from_timestamps(timestamps.into_iter(), &Utc);
+        assert_eq!(heatmap.total(), 3);
+        assert_eq!(heatmap.cells[0][9], 2);
+        assert_eq!(heatmap.cells[6][23], 1);
+        assert_eq!(heatmap.shade(0), ' ');
+        assert_eq!(heatmap.shade(1), '▒');
+        assert_eq!(heatmap.shade(2), '█');
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use thiserror::Error;
+
+const DEFAULT_MIN_COUNT: usize = 3;
+const MAX_CHOICES: usize = 20;
+const MAX_NAME_TOKENS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SuggestCommand {
+    pub history_file: Option<PathBuf>,
+    pub min_count: Option<usize>,
+}
+
+pub struct SuggestEngine {}
+
+impl SuggestEngine {
+    pub fn run(&self, cmd: SuggestCommand) -> Result<i32> {
+        let history_file = match cmd.history_file {
+            Some(path) => path,
+            None => shell_history_file().ok_or(ErrorSuggestEngine::HistoryFileNotFound)?,
+        };
+        if !Self::confirm(&format!(
+            "sam is about to read your shell history from {} to suggest new aliases, continue?",
+            history_file.display()
+        ))? {
+            return Ok(0);
+        }
+        let content = fs::read(&history_file)?;
+        let commands = parse_history(&String::from_utf8_lossy(&content));
+        let suggestions = suggest(&commands, cmd.min_count.unwrap_or(DEFAULT_MIN_COUNT));
+        if suggestions.is_empty() {
+            println!("No repeated commands found, there is nothing to suggest.");
+            return Ok(0);
+        }
+        println!(
+            "{}{}Draft aliases, review them before adding them to your recipes{}\n",
+            crossterm::style::Attribute::Bold,
+            crossterm::style::SetForegroundColor(crossterm::style::Color::DarkGreen),
+            crossterm::style::Attribute::Reset,
+        );
+        println!("# aliases.yaml\n{}", aliases_yaml(&suggestions));
+        let vars = vars_yaml(&suggestions);
+        if !vars.is_empty() {
+            println!("# vars.yaml\n{}", vars);
+        }
+        Ok(0)
+    }
+
+    fn confirm(question: &str) -> Result<bool> {
+        print!("{} [y/N] ", question);
+        io::stdout().flush()?;
+        let mut buffer = String::new();
+        io::stdin().read_line(&mut buffer)?;
+        Ok(matches!(buffer.trim(), "y" | "Y" | "yes"))
+    }
+}
+
+/// shell_history_file returns the history file of the current shell,
+/// $HISTFILE takes precedence over the default locations.
+fn shell_history_file() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("HISTFILE").map(PathBuf::from) {
+        return Some(path);
+    }
+    let home_dir = dirs::home_dir()?;
+    let shell = std::env::var("SHELL").unwrap_or_default();
+    let candidates = if shell.ends_with("zsh") {
+        vec![".zsh_history", ".zhistory", ".bash_history"]
+    } else if shell.ends_with("fish") {
+        vec![".local/share/fish/fish_history", ".bash_history"]
+    } else {
+        vec![".bash_history", ".zsh_history"]
+    };
+    candidates
+        .into_iter()
+        .map(|c| home_dir.join(c))
+        .find(|p| p.exists())
+}
+
+/// parse_history extracts commands from bash, zsh (including the extended
+/// history format) and fish history files.
+fn parse_history(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.starts_with("- cmd: ") {
+                Some(line.trim_start_matches("- cmd: "))
+            } else if line.starts_with(": ") {
+                line.split_once(';').map(|(_, cmd)| cmd)
+            } else if line.starts_with('#')
+                || line.starts_with("- ")
+                || line.starts_with("when: ")
+                || line.starts_with("paths:")
+            {
+                None
+            } else {
+                Some(line)
+            }
+        })
+        .map(str::trim)
+        .filter(|cmd| !cmd.is_empty() && !cmd.starts_with("sam ") && *cmd != "sam")
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct SuggestedVar {
+    name: String,
+    // choices along with the number of times they were used.
+    choices: Vec<(String, usize)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Suggestion {
+    name: String,
+    alias: String,
+    count: usize,
+    example: String,
+    vars: Vec<SuggestedVar>,
+}
+
+/// suggest clusters commands that share the same program, subcommand and
+/// number of arguments. Inside a cluster, the tokens that differ from one
+/// command to the other become vars.
+fn suggest(commands: &[String], min_count: usize) -> Vec<Suggestion> {
+    let mut clusters: HashMap<(usize, String, String), Vec<Vec<&str>>> = HashMap::new();
+    for cmd in commands {
+        let tokens: Vec<&str> = cmd.split_whitespace().collect();
+        let key = (
+            tokens.len(),
+            tokens.first().unwrap_or(&"").to_string(),
+            tokens.get(1).unwrap_or(&"").to_string(),
+        );
+        clusters.entry(key).or_default().push(tokens);
+    }
+
+    let mut suggestions: Vec<Suggestion> = clusters
+        .into_values()
+        .filter(|c| c.len() >= min_count)
+        .filter_map(suggestion_for_cluster)
+        .collect();
+    suggestions.sort_by(|a, b| b.count.cmp(&a.count).then(a.name.cmp(&b.name)));
+
+    let mut alias_names = HashSet::new();
+    let mut var_names = HashSet::new();
+    for suggestion in suggestions.iter_mut() {
+        suggestion.name = unique_name(&suggestion.name, &mut alias_names);
+        for var in suggestion.vars.iter_mut() {
+            let name = unique_name(&var.name, &mut var_names);
+            if name != var.name {
+                suggestion.alias = suggestion.alias.replace(
+                    &format!("{{{{ {} }}}}", var.name),
+                    &format!("{{{{ {} }}}}", name),
+                );
+                var.name = name;
+            }
+        }
+    }
+    suggestions
+}
+
+fn suggestion_for_cluster(cluster: Vec<Vec<&str>>) -> Option<Suggestion> {
+    let width = cluster.first()?.len();
+    let varying: Vec<bool> = (0..width)
+        .map(|i| cluster.iter().any(|tokens| tokens[i] != cluster[0][i]))
+        .collect();
+    let nb_varying = varying.iter().filter(|v| **v).count();
+    // the program is never a var, commands that have too little in common
+    // don't make good aliases.
+    if varying[0] || nb_varying * 2 > width {
+        return None;
+    }
+
+    let mut alias_tokens = Vec::with_capacity(width);
+    let mut name_tokens = Vec::with_capacity(MAX_NAME_TOKENS);
+    let mut vars = Vec::with_capacity(nb_varying);
+    for i in 0..width {
+        if varying[i] {
+            let name = var_name(i, if i > 0 { cluster[0][i - 1] } else { "" });
+            alias_tokens.push(format!("{{{{ {} }}}}", name));
+            vars.push(SuggestedVar {
+                name,
+                choices: choices_by_frequency(cluster.iter().map(|tokens| tokens[i])),
+            });
+        } else {
+            alias_tokens.push(cluster[0][i].to_string());
+            if name_tokens.len() < MAX_NAME_TOKENS && !cluster[0][i].starts_with('-') {
+                name_tokens.push(sanitize(cluster[0][i]));
+            }
+        }
+    }
+
+    Some(Suggestion {
+        name: name_tokens.join("_"),
+        alias: alias_tokens.join(" "),
+        count: cluster.len(),
+        example: cluster[0].join(" "),
+        vars,
+    })
+}
+
+fn var_name(position: usize, previous_token: &str) -> String {
+    let flag = previous_token.trim_start_matches('-');
+    if previous_token.starts_with('-') && !flag.is_empty() {
+        sanitize(flag)
+    } else {
+        format!("arg{}", position)
+    }
+}
+
+fn sanitize(token: &str) -> String {
+    let name: String = token
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let name = name.trim_matches('_').to_lowercase();
+    if name.is_empty() {
+        String::from("cmd")
+    } else {
+        name
+    }
+}
+
+fn unique_name(name: &str, taken: &mut HashSet<String>) -> String {
+    let mut candidate = name.to_string();
+    let mut idx = 1;
+    while taken.contains(&candidate) {
+        idx += 1;
+        candidate = format!("{}_{}", name, idx);
+    }
+    taken.insert(candidate.clone());
+    candidate
+}
+
+fn choices_by_frequency<'a>(values: impl Iterator<Item = &'a str>) -> Vec<(String, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for value in values {
+        *counts.entry(value).or_default() += 1;
+    }
+    let mut choices: Vec<(String, usize)> = counts
+        .into_iter()
+        .map(|(v, c)| (v.to_string(), c))
+        .collect();
+    choices.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    choices.truncate(MAX_CHOICES);
+    choices
+}
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn aliases_yaml(suggestions: &[Suggestion]) -> String {
+    let mut out = String::new();
+    for s in suggestions {
+        out.push_str(&format!(
+            "- name: {}\n  desc: {}\n  alias: {}\n\n",
+            s.name,
+            quote(&format!(
+                "ran {} times, for example: {}",
+                s.count, s.example
+            )),
+            quote(&s.alias),
+        ));
+    }
+    out
+}
+
+fn vars_yaml(suggestions: &[Suggestion]) -> String {
+    let mut out = String::new();
+    for var in suggestions.iter().flat_map(|s| s.vars.iter()) {
+        out.push_str(&format!(
+            "- name: {}\n  desc: {}\n  choices:\n",
+            var.name,
+            quote(&format!(
+                "suggested from your shell history for {}",
+                var.name
+            )),
+        ));
+        for (value, count) in &var.choices {
+            out.push_str(&format!(
+                "    - value: {}\n      desc: {}\n",
+                quote(value),
+                quote(&format!("used {} times", count)),
+            ));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+pub type Result<T> = std::result::Result<T, ErrorSuggestEngine>;
+
+#[derive(Debug, Error)]
+pub enum ErrorSuggestEngine {
+    #[error("could not find your shell history file, provide it with --history-file")]
+    HistoryFileNotFound,
+    #[error("could not read the shell history\n-> {0}")]
+    IO(#[from] io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_history, suggest, SuggestedVar};
+
+    #[test]
+    fn test_parse_history() {
+        let content = "\
+: 1649062800:0;kubectl get pods
+#1649062800
+ls -la
+- cmd: git status
+  when: 1649062800
+  paths:
+    - /tmp
+sam run
+";
+        let commands = parse_history(content);
+        assert_eq!(commands, vec!["kubectl get pods", "ls -la", "git status"]);
+    }
+
+    #[test]
+    fn test_suggest() {
+        let commands: Vec<String> = vec![
+            "kubectl logs -n prod api",
+            "kubectl logs -n prod api",
+            "kubectl logs -n staging worker",
+            "cargo build",
+            "cargo build",
+            "cargo build",
+            "ls /tmp",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+        let suggestions = suggest(&commands, 3);
+        assert_eq!(suggestions.len(), 2);
+
+        assert_eq!(suggestions[0].name, "cargo_build");
+        assert_eq!(suggestions[0].alias, "cargo build");
+        assert!(suggestions[0].vars.is_empty());
+
+        assert_eq!(suggestions[1].name, "kubectl_logs");
+        assert_eq!(suggestions[1].alias, "kubectl logs -n {{ n }} {{ arg4 }}");
+        assert_eq!(suggestions[1].count, 3);
+        assert_eq!(
+            suggestions[1].vars[0],
+            SuggestedVar {
+                name: String::from("n"),
+                choices: vec![(String::from("prod"), 2), (String::from("staging"), 1)],
+            }
+        );
+    }
+}
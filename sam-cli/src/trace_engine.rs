@@ -0,0 +1,109 @@
+use crate::cli::OutputFormat;
+use crate::logger::{traces, TraceEvent};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// TraceEngine prints the trace of the latest run, the vars it resolved, whether their
+/// choices came from the cache, how long their commands took and how they exited.
+pub struct TraceEngine {
+    pub dir: Option<PathBuf>,
+    pub output: OutputFormat,
+}
+
+impl TraceEngine {
+    pub fn run(&self) -> Result<i32> {
+        let path = self
+            .dir
+            .as_ref()
+            .and_then(|dir| traces(dir).pop())
+            .ok_or(ErrorTraceEngine::NoTrace)?;
+        let content = fs::read_to_string(&path)?;
+        if self.output == OutputFormat::Json {
+            print!("{}", content);
+            return Ok(0);
+        }
+        println!(
+            "{}trace of the last run, {}{}",
+            crossterm::style::Attribute::Bold,
+            path.display(),
+            crossterm::style::Attribute::Reset
+        );
+        for line in content.lines().filter(|l| !l.trim().is_empty()) {
+            let event: TraceEvent = serde_json::from_str(line)
+                .map_err(|err| ErrorTraceEngine::Invalid(path.clone(), err.to_string()))?;
+            println!("{}", render(&event));
+        }
+        Ok(0)
+    }
+}
+
+// render prints an event on a line, the time of the day, what it's about and its fields.
+fn render(event: &TraceEvent) -> String {
+    let time = event
+        .time
+        .split_once('T')
+        .map_or(event.time.as_str(), |(_, time)| time.trim_end_matches('Z'));
+    let subject = match (&event.alias, &event.var) {
+        (Some(alias), _) => format!("alias {}", alias),
+        (_, Some(var)) => format!("var {}", var),
+        _ => String::new(),
+    };
+    let details = if event.fields.is_empty() {
+        event.message.clone()
+    } else {
+        event
+            .fields
+            .iter()
+            .map(|(key, value)| match value.as_str() {
+                Some(value) => format!("{}: {}", key, value),
+                None => format!("{}: {}", key, value),
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    format!(
+        "{}{}{} {}{:<30}{} {}",
+        crossterm::style::Attribute::Dim,
+        time,
+        crossterm::style::Attribute::Reset,
+        crossterm::style::SetForegroundColor(crossterm::style::Color::DarkGreen),
+        subject,
+        crossterm::style::Attribute::Reset,
+        details
+    )
+}
+
+type Result<T> = std::result::Result<T, ErrorTraceEngine>;
+
+#[derive(Debug, Error)]
+pub enum ErrorTraceEngine {
+    #[error("no run was traced yet")]
+    NoTrace,
+    #[error("the trace {} is invalid\n-> {1}", .0.display())]
+    Invalid(PathBuf, String),
+    #[error("can't read the trace\n-> {0}")]
+    Io(#[from] io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use crate::logger::TraceEvent;
+    use log::Level;
+
+    #[test]
+    fn test_render() {
+        let event = TraceEvent::parse(
+            String::from("2026-10-15T08:00:00.042Z"),
+            Level::Debug,
+            "[SAM][ var = 'k8s::pod' ] command='kubectl get pods' from_cache=true",
+        )
+        .unwrap();
+        let line = render(&event);
+        assert!(line.contains("08:00:00.042"));
+        assert!(line.contains("var k8s::pod"));
+        assert!(line.contains("command: kubectl get pods, from_cache: true"));
+    }
+}
@@ -0,0 +1,262 @@
+use crate::config::{AppSettings, ErrorsSettings};
+use crate::executors::{NoConfirmation, SandboxExecutor};
+use crate::logger::SilentLogger;
+use sam_core::engines::{ErrorSamEngine, SamCommand, SamEngine, SamHistory};
+use sam_core::entities::aliases::ResolvedAlias;
+use sam_persistence::repositories::{AliasesRepository, ErrorsAliasesRepository, VarsRepository};
+use sam_persistence::NoopVarsCache;
+use sam_readers::{read_aliases_from_path, read_vars_repository, ErrorsAliasRead, ErrorsVarRead};
+use sam_tui::UserInterfaceV2;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use thiserror::Error;
+
+const TUTORIAL_DIR: &str = ".sam/tutorial";
+const TUTORIAL_NAMESPACE: &str = "tutorial";
+
+const TUTORIAL_ALIASES: &str = "- name: greet
+  desc: prints a greeting, you get to choose which one.
+  alias: echo {{ greeting }} world
+
+- name: count_lines
+  desc: counts the lines of a file
+  alias: wc -l {{ file }}
+";
+
+const TUTORIAL_VARS: &str = "- name: greeting
+  desc: a greeting to use
+  choices:
+    - value: hello
+      desc: a classic
+    - value: bonjour
+      desc: a french one
+    - value: hola
+      desc: a spanish one
+
+- name: file
+  desc: a file to count the lines of
+  choices:
+    - value: /etc/hosts
+      desc: the static table of host names
+    - value: /etc/shells
+      desc: the login shells of the system
+";
+
+const STEP_WELCOME: &str = "Welcome to sam!
+
+It looks like this is the first time you are running sam: no configuration
+file was found in your home directory nor in the current directory.
+
+sam lets you write templates of complex commands (aliases) and fills in their
+variables (vars) for you through a small terminal user interface.
+This short tutorial will walk you through the basics.";
+
+const STEP_RECIPES: &str = "Aliases and vars live in `aliases.yaml` and `vars.yaml` files.
+The name of the directory holding them is used as their namespace.
+The following sample recipes were written for you:";
+
+const STEP_SELECTION: &str =
+    "Let's try them! sam will now ask you to pick an alias, then a choice for
+each of its vars. Type to filter, use the arrows to move and press Enter to
+select. Pick `tutorial::greet` to see how a var with static choices works.
+Nothing will actually be executed during this tutorial.";
+
+const STEP_CACHE_AND_HISTORY: &str = "Caching:
+  the output of `from_command` vars is kept in a cache for `ttl` seconds so that
+  running the same alias again is instant. Use `sam cache-keys` to list the
+  cached commands, `sam cache-clear` to empty the cache and `--no-cache` to
+  bypass it for a single run.
+
+History:
+  every alias you run is recorded with the choices you made. Use `sam history`
+  to browse it, `sam run-last` (or `sam %`) to run the last alias again and
+  `sam show-last` to print its commands.";
+
+pub struct TutorialEngine {
+    pub recipes_dir: PathBuf,
+}
+
+impl TutorialEngine {
+    pub fn in_home_directory() -> Result<Self> {
+        let home_dir = dirs::home_dir().ok_or(ErrorTutorial::CantFindHomeDirectory)?;
+        Ok(TutorialEngine {
+            recipes_dir: home_dir.join(TUTORIAL_DIR),
+        })
+    }
+
+    /// can_run returns true if the tutorial can interact with the user.
+    pub fn can_run() -> bool {
+        io::stdin().is_terminal() && io::stdout().is_terminal()
+    }
+
+    pub fn run(&self) -> Result<i32> {
+        AppSettings::mark_tutorial_seen()?;
+        Self::step(STEP_WELCOME)?;
+
+        let (aliases_file, vars_file) = self.write_recipes()?;
+        println!("{}\n", STEP_RECIPES);
+        println!("# {}\n{}", aliases_file.display(), TUTORIAL_ALIASES);
+        println!("# {}\n{}", vars_file.display(), TUTORIAL_VARS);
+        Self::step(STEP_SELECTION)?;
+
+        let history = self.run_sandboxed_engine(&aliases_file, &vars_file)?;
+        if let Some(last) = history {
+            println!("\nThis is what sam recorded in its history:\n\n{}", last);
+        }
+        Self::step(STEP_CACHE_AND_HISTORY)?;
+
+        if Self::confirm("Do you want sam to create a configuration file using these recipes?")? {
+            let config_path = AppSettings::bootstrap(&self.recipes_dir)?;
+            println!(
+                "Configuration written to {}, you can now run `sam`.",
+                config_path.display()
+            );
+        } else {
+            println!(
+                "No configuration written, take a look at the README to write your own. \
+                 `sam tutorial` shows this tutorial again."
+            );
+        }
+        Ok(0)
+    }
+
+    fn write_recipes(&self) -> Result<(PathBuf, PathBuf)> {
+        let namespace_dir = self.recipes_dir.join(TUTORIAL_NAMESPACE);
+        fs::create_dir_all(&namespace_dir)?;
+        let aliases_file = namespace_dir.join("aliases.yaml");
+        let vars_file = namespace_dir.join("vars.yaml");
+        Self::write_recipe(&aliases_file, TUTORIAL_ALIASES)?;
+        Self::write_recipe(&vars_file, TUTORIAL_VARS)?;
+        Ok((aliases_file, vars_file))
+    }
+
+    // write_recipe writes a sample recipe, a file that was changed since the last
+    // tutorial is only replaced once the user agrees.
+    fn write_recipe(file: &Path, content: &str) -> Result<()> {
+        match fs::read_to_string(file) {
+            Ok(existing) if existing == content => return Ok(()),
+            Ok(_) => {
+                let question = format!("{} was changed, overwrite it?", file.display());
+                if !Self::confirm(&question)? {
+                    return Err(ErrorTutorial::RecipeChanged(file.to_path_buf()));
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+        fs::write(file, content)?;
+        Ok(())
+    }
+
+    fn run_sandboxed_engine(
+        &self,
+        aliases_file: &Path,
+        vars_file: &Path,
+    ) -> Result<Option<ResolvedAlias>> {
+        let aliases = AliasesRepository::new(read_aliases_from_path(aliases_file)?.into_iter())?;
+        let vars: VarsRepository = read_vars_repository(vars_file)?;
+        let resolver = UserInterfaceV2::new(HashMap::new(), Box::new(NoopVarsCache {}));
+        let mut engine = SamEngine {
+            resolver,
+            aliases,
+            vars: vars.clone(),
+            defaults: vars,
+            logger: Rc::new(SilentLogger),
+            history: RefCell::new(Box::new(TutorialHistory::default())),
+            env_variables: HashMap::new(),
+            confirmation: Rc::new(NoConfirmation {}),
+            executor: Rc::new(SandboxExecutor {}),
+        };
+        engine.run(SamCommand::ChooseAndExecuteAlias)?;
+        let last = engine.history.borrow().get_last()?;
+        Ok(last)
+    }
+
+    fn step(text: &str) -> Result<()> {
+        println!("\n{}\n", text);
+        print!(
+            "{}press Enter to continue{}",
+            crossterm::style::Attribute::Italic,
+            crossterm::style::Attribute::Reset
+        );
+        io::stdout().flush()?;
+        let mut buffer = String::new();
+        io::stdin().read_line(&mut buffer)?;
+        Ok(())
+    }
+
+    fn confirm(question: &str) -> Result<bool> {
+        print!("\n{} [y/N] ", question);
+        io::stdout().flush()?;
+        let mut buffer = String::new();
+        io::stdin().read_line(&mut buffer)?;
+        Ok(matches!(buffer.trim(), "y" | "Y" | "yes"))
+    }
+}
+
+/// TutorialHistory keeps the aliases run during the tutorial in memory, the history
+/// of the user is left untouched.
+#[derive(Default)]
+struct TutorialHistory {
+    aliases: Vec<ResolvedAlias>,
+}
+
+impl SamHistory for TutorialHistory {
+    fn put(&mut self, alias: ResolvedAlias) -> std::result::Result<(), ErrorSamEngine> {
+        self.aliases.push(alias);
+        Ok(())
+    }
+
+    fn get_last_n(&self, n: usize) -> std::result::Result<Vec<ResolvedAlias>, ErrorSamEngine> {
+        let skip = self.aliases.len().saturating_sub(n);
+        Ok(self.aliases[skip..].to_vec())
+    }
+}
+
+type Result<T> = std::result::Result<T, ErrorTutorial>;
+
+#[derive(Debug, Error)]
+pub enum ErrorTutorial {
+    #[error("we were unable to locate the home directory for the current user")]
+    CantFindHomeDirectory,
+    #[error("{0} was left untouched, move it away to run the tutorial")]
+    RecipeChanged(PathBuf),
+    #[error("could not interact with the terminal\n-> {0}")]
+    IO(#[from] io::Error),
+    #[error("could not read the tutorial aliases\n-> {0}")]
+    AliasRead(#[from] ErrorsAliasRead),
+    #[error("could not read the tutorial vars\n-> {0}")]
+    VarRead(#[from] ErrorsVarRead),
+    #[error("could not figure out alias substitution\n-> {0}")]
+    AliasRepository(#[from] ErrorsAliasesRepository),
+    #[error("could not run the tutorial alias\n-> {0}")]
+    SamEngine(#[from] ErrorSamEngine),
+    #[error("could not write the configuration\n-> {0}")]
+    Settings(#[from] ErrorsSettings),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TutorialEngine, TUTORIAL_ALIASES, TUTORIAL_VARS};
+    use std::fs;
+
+    #[test]
+    fn test_write_recipes() {
+        let dir = std::env::temp_dir().join(format!("sam-tutorial-{}", std::process::id()));
+        let tutorial = TutorialEngine {
+            recipes_dir: dir.clone(),
+        };
+        let (aliases_file, vars_file) = tutorial.write_recipes().expect("can't write the recipes");
+        assert_eq!(fs::read_to_string(&aliases_file).unwrap(), TUTORIAL_ALIASES);
+        assert_eq!(fs::read_to_string(&vars_file).unwrap(), TUTORIAL_VARS);
+        // the recipes of a previous tutorial are kept as they are.
+        tutorial
+            .write_recipes()
+            .expect("can't write the recipes again");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
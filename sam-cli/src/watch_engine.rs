@@ -0,0 +1,189 @@
+use crate::config::AppSettings;
+use crate::environment;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use sam_core::entities::aliases::Alias;
+use sam_core::entities::identifiers::Identifier;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+use thiserror::Error;
+
+// editors write a file in several steps, the events that follow
+// the first one within this delay trigger a single reload.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// WatchEngine reloads the aliases and the vars through sam-readers whenever
+/// an aliases or a vars file of the root directories changes, and reports what changed.
+pub struct WatchEngine {
+    pub config: AppSettings,
+    pub aliases: Vec<Alias>,
+    pub vars: usize,
+    pub warnings: usize,
+}
+
+impl WatchEngine {
+    pub fn run(mut self) -> Result<i32> {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        for dir in self.config.root_dirs() {
+            watcher.watch(dir, RecursiveMode::Recursive)?;
+        }
+        println!(
+            "watching {} aliases and {} vars, {} warning(s)",
+            self.aliases.len(),
+            self.vars,
+            self.warnings
+        );
+        while let Ok(event) = rx.recv() {
+            if !event.is_ok_and(|e| is_recipe_event(&e)) {
+                continue;
+            }
+            while rx.recv_timeout(DEBOUNCE).is_ok() {}
+            self.reload();
+        }
+        Ok(0)
+    }
+
+    fn reload(&mut self) {
+        let env = match environment::from_settings(self.config.clone()) {
+            Ok(env) => env,
+            Err(err) => {
+                eprintln!(
+                    "{}reload failed{}, the previous aliases and vars are kept\n-> {}",
+                    crossterm::style::SetForegroundColor(crossterm::style::Color::DarkRed),
+                    crossterm::style::Attribute::Reset,
+                    err
+                );
+                return;
+            }
+        };
+        let aliases = env.aliases.aliases();
+        let vars = env.vars.vars_iter().count();
+        let changes = Changes::between(&self.aliases, &aliases);
+        println!(
+            "{}reloaded{} {} aliases and {} vars, {} warning(s){}",
+            crossterm::style::SetForegroundColor(crossterm::style::Color::DarkGreen),
+            crossterm::style::Attribute::Reset,
+            aliases.len(),
+            vars,
+            env.warnings.len(),
+            changes
+        );
+        if !env.warnings.is_empty() {
+            eprint!("{}", env.warnings);
+        }
+        self.aliases = aliases;
+        self.vars = vars;
+        self.warnings = env.warnings.len();
+    }
+}
+
+// is_recipe_event tells whether an event created, modified or removed an aliases or a vars file.
+fn is_recipe_event(event: &Event) -> bool {
+    let kind = matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    );
+    kind && event.paths.iter().any(|p| is_recipe_file(p))
+}
+
+fn is_recipe_file(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| {
+        ["aliases.yaml", "aliases.yml", "vars.yaml", "vars.yml"]
+            .iter()
+            .any(|f| name == *f)
+    })
+}
+
+/// Changes lists the aliases added, removed or modified by a reload.
+#[derive(Debug, Default, PartialEq)]
+struct Changes {
+    added: Vec<Identifier>,
+    removed: Vec<Identifier>,
+    modified: Vec<Identifier>,
+}
+
+impl Changes {
+    fn between(before: &[Alias], after: &[Alias]) -> Changes {
+        let before: BTreeMap<String, &Alias> = before
+            .iter()
+            .map(|a| (a.full_name().to_string(), a))
+            .collect();
+        let after: BTreeMap<String, &Alias> = after
+            .iter()
+            .map(|a| (a.full_name().to_string(), a))
+            .collect();
+        let mut changes = Changes::default();
+        for (name, alias) in after.iter() {
+            match before.get(name) {
+                None => changes.added.push(alias.identifier()),
+                Some(previous) if previous != alias => changes.modified.push(alias.identifier()),
+                Some(_) => {}
+            }
+        }
+        for (name, alias) in before.iter() {
+            if !after.contains_key(name) {
+                changes.removed.push(alias.identifier());
+            }
+        }
+        changes
+    }
+}
+
+impl std::fmt::Display for Changes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (sign, ids) in [
+            ('+', &self.added),
+            ('-', &self.removed),
+            ('~', &self.modified),
+        ] {
+            for id in ids {
+                write!(f, "\n  {} {}", sign, id)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ErrorWatchEngine>;
+
+#[derive(Debug, Error)]
+pub enum ErrorWatchEngine {
+    #[error("can't watch the root directories\n-> {0}")]
+    Notify(#[from] notify::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_recipe_file, Changes};
+    use sam_core::entities::aliases::Alias;
+    use sam_core::entities::namespaces::NamespaceUpdater;
+    use std::path::Path;
+
+    fn alias(name: &str, command: &str) -> Alias {
+        let mut alias = Alias::new(name, "desc", command);
+        NamespaceUpdater::update(&mut alias, "ns");
+        alias
+    }
+
+    #[test]
+    fn test_is_recipe_file() {
+        assert!(is_recipe_file(Path::new("/recipes/k8s/aliases.yaml")));
+        assert!(is_recipe_file(Path::new("/recipes/k8s/vars.yml")));
+        assert!(!is_recipe_file(Path::new("/recipes/k8s/.aliases.yaml.swp")));
+        assert!(!is_recipe_file(Path::new("/recipes/k8s")));
+    }
+
+    #[test]
+    fn test_changes() {
+        let before = vec![alias("ls", "ls"), alias("pwd", "pwd"), alias("cat", "cat")];
+        let after = vec![alias("ls", "ls -l"), alias("pwd", "pwd"), alias("wc", "wc")];
+        let changes = Changes::between(&before, &after);
+        assert_eq!(changes.added, vec![alias("wc", "").identifier()]);
+        assert_eq!(changes.removed, vec![alias("cat", "").identifier()]);
+        assert_eq!(changes.modified, vec![alias("ls", "").identifier()]);
+        assert_eq!(changes.to_string(), "\n  + ns::wc\n  - ns::cat\n  ~ ns::ls");
+        assert_eq!(Changes::between(&before, &before), Changes::default());
+    }
+}
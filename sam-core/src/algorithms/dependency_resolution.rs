@@ -7,8 +7,12 @@ use crate::entities::{
     aliases::Alias,
     choices::Choice,
     commands::Command,
-    dependencies::{Dependencies, ExecutionSequence},
+    conditions::{evaluate, ErrorCondition},
+    dependencies::{
+        substitute_choices, substitute_choices_partial, Dependencies, ExecutionSequence,
+    },
     identifiers::{Identifier, Identifiers},
+    namespaces::Namespace,
     vars::Var,
 };
 
@@ -22,13 +26,18 @@ pub trait VarsCollection {
 }
 
 pub trait VarsDefaultValues {
-    fn default_value(&self, id: &Identifier) -> Option<&Choice>;
+    /// default_values returns the choices given for a var, there can be several of them
+    /// for the vars the user could pick several choices for. None when there are none.
+    fn default_values(&self, id: &Identifier) -> Option<&[Choice]>;
 }
 
 pub fn execution_sequence_for_dependencies<Deps: Dependencies>(
     vars: &dyn VarsCollection,
     dep: Deps,
 ) -> std::result::Result<ExecutionSequence, ErrorDependencyResolution> {
+    if let Some(cycle) = find_cycle(vars, dep.dependencies()) {
+        return Err(ErrorDependencyResolution::CyclicDependency(cycle));
+    }
     let mut already_seen = HashSet::new();
     let mut already_inserted = HashSet::new();
     let mut candidates = dep.dependencies();
@@ -70,19 +79,104 @@ pub fn execution_sequence_for_dependencies<Deps: Dependencies>(
     }
 }
 
+/// find_cycle returns the first chain of vars reachable from roots that leads back to
+/// one of its vars, it starts and ends with that var.
+fn find_cycle(vars: &dyn VarsCollection, roots: Vec<Identifier>) -> Option<Vec<Identifier>> {
+    let mut done = HashSet::new();
+    roots
+        .into_iter()
+        .find_map(|root| cycle_from(vars, root, &mut vec![], &mut done))
+}
+
+fn cycle_from(
+    vars: &dyn VarsCollection,
+    name: Identifier,
+    path: &mut Vec<Identifier>,
+    done: &mut HashSet<Identifier>,
+) -> Option<Vec<Identifier>> {
+    if let Some(start) = path.iter().position(|id| *id == name) {
+        let mut cycle = path[start..].to_vec();
+        cycle.push(name);
+        return Some(cycle);
+    }
+    if done.contains(&name) {
+        return None;
+    }
+    let dependencies = vars.get(&name).map(Var::dependencies).unwrap_or_default();
+    path.push(name);
+    for dependency in dependencies {
+        if let Some(cycle) = cycle_from(vars, dependency, path, done) {
+            return Some(cycle);
+        }
+    }
+    if let Some(name) = path.pop() {
+        done.insert(name);
+    }
+    None
+}
+
+/// with_prompt_order reorders an execution sequence so that the preferred vars are prompted
+/// for as early as their dependencies allow, in the order they are listed. The other vars
+/// keep their relative order.
+pub fn with_prompt_order(
+    vars: &dyn VarsCollection,
+    seq: ExecutionSequence,
+    preferred: &[Identifier],
+) -> ExecutionSequence {
+    let mut remaining: Vec<&Identifier> = seq.as_slice().iter().collect();
+    let mut ordered: Vec<&Identifier> = Vec::with_capacity(remaining.len());
+    let rank = |id: &Identifier| {
+        preferred
+            .iter()
+            .position(|p| p == id)
+            .unwrap_or(preferred.len())
+    };
+    while !remaining.is_empty() {
+        let is_ready = |id: &Identifier| {
+            vars.get(id).is_none_or(|var| {
+                var.dependencies()
+                    .iter()
+                    .all(|dep| !remaining.contains(&dep))
+            })
+        };
+        let next = remaining
+            .iter()
+            .enumerate()
+            .filter(|(_, id)| is_ready(id))
+            .min_by_key(|(idx, id)| (rank(id), *idx))
+            .map_or(0, |(idx, _)| idx);
+        ordered.push(remaining.remove(next));
+    }
+    ExecutionSequence::new(ordered)
+}
+
 #[derive(Debug, Error)]
 pub enum ErrorDependencyResolution {
     #[error("missing the following dependencies:\n{0}")]
     MissingDependencies(Identifiers),
     #[error("the provided variables are unknown:\n{0}")]
     UnknowVarsDefaults(Identifiers),
+    #[error("vars depend on each other: {}", cycle_path(.0))]
+    CyclicDependency(Vec<Identifier>),
+    #[error("the condition of var {var_name} is invalid\n-> {error}")]
+    InvalidCondition {
+        var_name: Identifier,
+        error: ErrorCondition,
+    },
+    #[error("the following vars need a choice, give it with -c or in the [defaults] of the configuration:\n{0}")]
+    MissingChoices(Identifiers),
     #[error("no choices available for var {var_name}\n-> {error}")]
     NoChoiceForVar {
         var_name: Identifier,
-        error: ErrorsResolver,
+        error: Box<ErrorsResolver>,
     },
 }
 
+fn cycle_path(cycle: &[Identifier]) -> String {
+    let names: Vec<String> = cycle.iter().map(Identifier::to_string).collect();
+    names.join(" -> ")
+}
+
 pub fn choices_for_execution_sequence<R: Resolver>(
     alias: &Alias,
     vars_col: &dyn VarsCollection,
@@ -95,24 +189,123 @@ pub fn choices_for_execution_sequence<R: Resolver>(
         full_name: alias.full_name().to_string(),
         choices: HashMap::new(),
         execution_sequence: vars.identifiers(),
+        sensitive: vars
+            .identifiers()
+            .into_iter()
+            .filter(|id| vars_col.get(id).is_some_and(Var::is_sensitive))
+            .collect(),
     };
-    for var_name in vars.as_slice() {
+    let sequence = vars.as_slice();
+    // the positions of the vars the resolver was asked for so far.
+    let mut asked: Vec<usize> = vec![];
+    // the vars the user would have to pick a choice for in non interactive mode.
+    let mut missing: Vec<Identifier> = vec![];
+    let mut idx = 0;
+    while let Some(var_name) = sequence.get(idx) {
+        // the commands whose dependencies are known run while the user picks the other vars,
+        // the one of the current var runs right away so that its choices can be streamed.
+        let ready = ready_commands(&sequence[idx + 1..], vars_col, vars_defaults, &ctx.choices);
+        if !ready.is_empty() {
+            resolver.prefetch_dynamic(&ready, &ctx);
+        }
         if let Some(var) = vars_col.get(var_name) {
-            let choice = if let Some(default) = vars_defaults.default_value(&var.name()) {
-                vec![default.to_owned()]
+            let choice = if let Some(defaults) = vars_defaults.default_values(&var.name()) {
+                defaults.to_vec()
+            } else if var.dependencies().iter().any(|dep| missing.contains(dep)) {
+                // the var is left out until the choices it depends on are given.
+                idx += 1;
+                continue;
+            } else if !condition_holds(var, &ctx.choices)? {
+                vec![var.otherwise()]
             } else {
-                choice_for_var(resolver, var, &ctx.choices, &ctx)?
+                match choice_for_var(resolver, var, &ctx.choices, &ctx) {
+                    Ok(choice) => {
+                        asked.push(idx);
+                        choice
+                    }
+                    // the user is asked for the previous var again, and for the ones after it.
+                    Err(ErrorDependencyResolution::NoChoiceForVar { error, .. })
+                        if matches!(*error, ErrorsResolver::Cancelled(_)) && !asked.is_empty() =>
+                    {
+                        idx = asked.pop().unwrap_or_default();
+                        for id in &sequence[idx..] {
+                            ctx.choices.remove(id);
+                        }
+                        continue;
+                    }
+                    Err(ErrorDependencyResolution::NoChoiceForVar { error, .. })
+                        if matches!(*error, ErrorsResolver::NonInteractive(_)) =>
+                    {
+                        missing.push(var.name());
+                        idx += 1;
+                        continue;
+                    }
+                    Err(err) => return Err(err),
+                }
             };
             ctx.choices.insert(var.name(), choice);
+            idx += 1;
         } else {
             return Err(ErrorDependencyResolution::MissingDependencies(Identifiers(
                 vec![(*var_name).clone()],
             )));
         }
     }
+    if !missing.is_empty() {
+        return Err(ErrorDependencyResolution::MissingChoices(Identifiers(
+            missing,
+        )));
+    }
     Ok(ctx.choices.into_iter().collect())
 }
 
+// ready_commands returns the commands of the `from_command` vars that are left to resolve,
+// have no default value and whose dependencies all have choices.
+fn ready_commands<'a>(
+    remaining: &[Identifier],
+    vars_col: &'a dyn VarsCollection,
+    vars_defaults: &dyn VarsDefaultValues,
+    choices: &HashMap<Identifier, Vec<Choice>>,
+) -> Vec<(&'a Var, String)> {
+    remaining
+        .iter()
+        .filter_map(|id| vars_col.get(id))
+        .filter(|var| var.is_command() && vars_defaults.default_values(&var.name()).is_none())
+        .filter(|var| {
+            var.dependencies()
+                .iter()
+                .all(|dep| choices.contains_key(dep))
+        })
+        .filter(|var| condition_holds(var, choices).unwrap_or(false))
+        .filter_map(|var| var.substitute_for_choices(choices).ok().map(|c| (var, c)))
+        .flat_map(|(var, commands)| commands.into_iter().map(move |c| (var, c)))
+        .collect()
+}
+
+// condition_holds evaluates the condition of a var, if any, with the first choice of
+// each of its dependencies.
+fn condition_holds(
+    var: &Var,
+    choices: &HashMap<Identifier, Vec<Choice>>,
+) -> std::result::Result<bool, ErrorDependencyResolution> {
+    let when = match var.condition() {
+        Some(when) => when,
+        None => return Ok(true),
+    };
+    let condition = substitute_choices_partial(when, var.namespace(), &first_choices(choices));
+    evaluate(&condition).map_err(|error| ErrorDependencyResolution::InvalidCondition {
+        var_name: var.name(),
+        error,
+    })
+}
+
+fn first_choices(choices: &HashMap<Identifier, Vec<Choice>>) -> HashMap<Identifier, Choice> {
+    choices
+        .iter()
+        .filter_map(|(id, c)| c.first().map(|c| (id.clone(), c.clone())))
+        .collect()
+}
+
 /// will return a valid choice for the current Var using the provided VarResolver and the
 /// HashMap of choices provided.
 /// First, this function will look into the `choices` HashMap to fill values for all the dependencies of the current
@@ -126,12 +319,18 @@ pub fn choice_for_var<'repository, R>(
 where
     R: Resolver,
 {
-    resolve_choice_for_var(resolver, var, choices, ctx).map_err(|err| {
-        ErrorDependencyResolution::NoChoiceForVar {
+    resolve_choice_for_var(resolver, var, choices, ctx)
+        .and_then(|selected| match var.map_command() {
+            Some(command) => selected
+                .into_iter()
+                .map(|choice| resolver.map_choice(var, command, choice, ctx))
+                .collect(),
+            None => Ok(selected),
+        })
+        .map_err(|err| ErrorDependencyResolution::NoChoiceForVar {
             var_name: var.name(),
-            error: err,
-        }
-    })
+            error: Box::new(err),
+        })
 }
 
 fn resolve_choice_for_var<'repository, R>(
@@ -143,19 +342,33 @@ fn resolve_choice_for_var<'repository, R>(
 where
     R: Resolver,
 {
-    if var.is_command() {
+    if var.is_command() || var.is_url() {
         let mut choices_out: Vec<Choice> = vec![];
         let mut has_one_rep = true;
-        let commands: Vec<String> = var
-            .substitute_for_choices(choices)?
-            .iter()
-            .map(Clone::clone)
-            .collect();
-        for command in commands {
-            let mut choices = resolver.resolve_dynamic(var, command, ctx)?;
+        let requests = match var.url() {
+            Some(url) => substitute_choices(url, var.namespace(), choices)?,
+            None => var.substitute_for_choices(choices)?,
+        };
+        // the choices are streamed before they can all be filtered.
+        if let ([request], None) = (requests.as_slice(), var.choice_filter()) {
+            if let Some(selected) = var
+                .is_command()
+                .then(|| resolver.stream_dynamic(var, request, ctx))
+                .flatten()
+            {
+                return selected;
+            }
+        }
+        for request in requests {
+            let mut choices = if var.is_url() {
+                resolver.resolve_url(var, request, ctx)?
+            } else {
+                resolver.resolve_dynamic(var, request, ctx)?
+            };
             has_one_rep &= choices.len() == 1;
             choices_out.append(&mut choices);
         }
+        let choices_out = filtered(var, choices_out)?;
         if choices_out.is_empty() {
             Err(ErrorsResolver::DynamicResolveEmpty(
                 var.name(),
@@ -167,14 +380,47 @@ where
         } else {
             resolver.resolve_static(var, choices_out.into_iter(), ctx)
         }
+    } else if let Some(reference) = var.secret() {
+        let reference =
+            substitute_choices_partial(reference, var.namespace(), &first_choices(choices));
+        resolver
+            .resolve_secret(var, reference, ctx)
+            .map(|c| vec![c])
+    } else if let Some(plugin) = var.plugin() {
+        let plugin = substitute_choices_partial(plugin, var.namespace(), &first_choices(choices));
+        let choices = filtered(var, resolver.resolve_plugin(var, plugin, ctx)?)?;
+        resolver.resolve_static(var, choices.into_iter(), ctx)
+    } else if let Some(template) = var.template() {
+        // computed vars aren't asked for, each combination of the choices they use gives a value.
+        let values = substitute_choices(template, var.namespace(), choices)?;
+        filtered(var, values.into_iter().map(Choice::from_value).collect())
+    } else if let Some(directory) = var.path() {
+        let directory =
+            substitute_choices_partial(directory, var.namespace(), &first_choices(choices));
+        let choices = filtered(var, resolver.resolve_path(var, directory, ctx)?)?;
+        resolver.resolve_static(var, choices.into_iter(), ctx)
+    } else if let Some(alias) = var.history_source() {
+        resolver.resolve_history(var, &alias, ctx)
     } else if var.is_input() {
         let prompt = var.prompt().unwrap_or("no provided prompt");
         resolver.resolve_input(var, prompt, ctx).map(|c| vec![c])
     } else {
-        resolver.resolve_static(var, var.choices().into_iter(), ctx)
+        let choices = filtered(var, var.choices())?;
+        resolver.resolve_static(var, choices.into_iter(), ctx)
+    }
+}
+
+// filtered post-processes the choices with the filter of the var, when it has one.
+fn filtered(var: &Var, choices: Vec<Choice>) -> std::result::Result<Vec<Choice>, ErrorsResolver> {
+    match var.choice_filter() {
+        Some(filter) => filter
+            .apply(choices)
+            .map_err(|err| ErrorsResolver::InvalidFilter(var.name(), err)),
+        None => Ok(choices),
     }
 }
 
+#[cfg(test)]
 pub mod mocks {
     use std::collections::HashMap;
 
@@ -193,8 +439,8 @@ pub mod mocks {
         }
     }
     impl VarsDefaultValues for VarsDefaultValuesMock {
-        fn default_value(&self, id: &Identifier) -> Option<&Choice> {
-            self.0.get(id).and_then(|v| v.first())
+        fn default_values(&self, id: &Identifier) -> Option<&[Choice]> {
+            self.0.get(id).map(Vec::as_slice).filter(|c| !c.is_empty())
         }
     }
 }
@@ -207,10 +453,15 @@ mod tests {
     use crate::algorithms::dependency_resolution::resolve_choice_for_var;
     use crate::algorithms::mocks::StaticResolver;
     use crate::algorithms::resolver::ResolverContext;
-    use crate::algorithms::{choices_for_execution_sequence, execution_sequence_for_dependencies};
+    use crate::algorithms::{
+        choices_for_execution_sequence, execution_sequence_for_dependencies, with_prompt_order,
+        ErrorDependencyResolution,
+    };
+    use crate::entities::aliases::Alias;
     use crate::entities::choices::Choice;
     use crate::entities::identifiers::fixtures::*;
     use crate::entities::vars::fixtures::*;
+    use crate::entities::vars::Var;
     use maplit::hashmap;
 
     #[test]
@@ -240,6 +491,7 @@ mod tests {
             full_name: alias.full_name().to_string(),
             choices: choices.clone(),
             execution_sequence: vec![],
+            sensitive: vec![],
         };
         let ret_var1 = resolve_choice_for_var(&resolver, &var1, &choices, &ctx);
         assert!(ret_var1.is_ok());
@@ -265,13 +517,53 @@ mod tests {
         assert!(seq.is_ok());
         let seq = execution_sequence_for_dependencies(&repo, VAR_USE_LISTING.clone());
         assert!(seq.is_ok());
-        let expected = vec![
+        let expected = [
             VAR_DIRECTORY_NAME.clone(),
             VAR_PATTERN_NAME.clone(),
             VAR_LISTING_NAME.clone(),
         ];
         assert_eq!(expected.iter().as_slice(), seq.unwrap().as_ref());
     }
+
+    #[test]
+    fn test_execution_sequence_with_cycle() {
+        let vars = vec![
+            Var::from_command("a", "a", "echo {{ b }}"),
+            Var::from_command("b", "b", "echo {{ c }}"),
+            Var::from_command("c", "c", "echo {{ b }}"),
+        ];
+        let repo = VarsCollectionMock(vars.into_iter().map(|v| (v.name(), v)).collect());
+        let alias = Alias::new("run", "runs", "run {{ a }}");
+        match execution_sequence_for_dependencies(&repo, alias) {
+            Err(err @ ErrorDependencyResolution::CyclicDependency(_)) => {
+                assert_eq!(err.to_string(), "vars depend on each other: b -> c -> b")
+            }
+            other => panic!("expected a cyclic dependency, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_with_prompt_order() {
+        let full = vec![
+            VAR_DIRECTORY.clone(),
+            VAR_LISTING.clone(),
+            VAR_PATTERN.clone(),
+        ];
+        let repo = VarsCollectionMock(full.into_iter().map(|c| (c.name(), c)).collect());
+        let seq = execution_sequence_for_dependencies(&repo, VAR_USE_LISTING.clone()).unwrap();
+        let preferred = [VAR_LISTING_NAME.clone(), VAR_PATTERN_NAME.clone()];
+        let seq = with_prompt_order(&repo, seq, &preferred);
+        // listing depends on directory and pattern, pattern is the only one that can move up.
+        let expected = [
+            VAR_PATTERN_NAME.clone(),
+            VAR_DIRECTORY_NAME.clone(),
+            VAR_LISTING_NAME.clone(),
+        ];
+        assert_eq!(expected.iter().as_slice(), seq.as_ref());
+        let seq = with_prompt_order(&repo, seq, &[]);
+        assert_eq!(expected.iter().as_slice(), seq.as_ref());
+    }
+
     #[test]
     fn test_var_repository_choices() {
         let choice_final = Choice::from_value("final_value");
@@ -313,4 +605,446 @@ mod tests {
         expected.sort();
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn test_choices_for_execution_sequence_with_condition() {
+        use crate::entities::aliases::Alias;
+        use crate::entities::identifiers::Identifier;
+        use crate::entities::vars::Var;
+
+        let env = Var::new(
+            "env",
+            "the environment",
+            vec![Choice::new("dev", None), Choice::new("prod", None)],
+        );
+        let approver = Var::new("approver", "who approved", vec![Choice::new("alice", None)])
+            .with_condition(
+                Some(String::from("{{ env }} == 'prod'")),
+                Some(String::from("nobody")),
+            );
+        let alias = Alias::new("deploy", "deploys", "deploy {{ env }} {{ approver }}");
+        let repo = VarsCollectionMock(
+            vec![env, approver]
+                .into_iter()
+                .map(|v| (v.name(), v))
+                .collect(),
+        );
+        let defaults = VarsDefaultValuesMock::default();
+        let resolve = |env: &str| {
+            let static_res = hashmap![
+                Identifier::new("env") => vec![Choice::new(env, None)],
+                Identifier::new("approver") => vec![Choice::new("alice", None)],
+            ];
+            let resolver = StaticResolver::new(None, hashmap! {}, static_res);
+            let seq = execution_sequence_for_dependencies(&repo, &alias).unwrap();
+            let choices: std::collections::HashMap<Identifier, Vec<Choice>> =
+                choices_for_execution_sequence(&alias, &repo, &defaults, &resolver, seq)
+                    .unwrap()
+                    .into_iter()
+                    .collect();
+            choices.get(&Identifier::new("approver")).cloned()
+        };
+        assert_eq!(resolve("prod"), Some(vec![Choice::new("alice", None)]));
+        assert_eq!(resolve("dev"), Some(vec![Choice::new("nobody", None)]));
+    }
+
+    #[test]
+    fn test_choices_for_execution_sequence_cancelled() {
+        use crate::entities::aliases::Alias;
+        use crate::entities::identifiers::Identifier;
+        use crate::entities::vars::Var;
+
+        let env = Var::new(
+            "env",
+            "the environment",
+            vec![Choice::new("dev", None), Choice::new("prod", None)],
+        );
+        let pod = Var::from_command("pod", "a pod", "kubectl -n {{ env }} get pods");
+        let alias = Alias::new("logs", "shows logs", "kubectl logs {{ pod }}");
+        let repo = VarsCollectionMock(vec![env, pod].into_iter().map(|v| (v.name(), v)).collect());
+        let defaults = VarsDefaultValuesMock::default();
+        let resolver = |cancelled: &[&str]| {
+            StaticResolver::new(
+                None,
+                hashmap! { String::from("kubectl -n prod get pods") => vec![Choice::from_value("api")] },
+                hashmap! { Identifier::new("env") => vec![Choice::new("prod", None)] },
+            )
+            .with_cancel_once(cancelled)
+        };
+
+        let resolver_env_asked_twice = resolver(&["kubectl -n prod get pods"]);
+        let seq = execution_sequence_for_dependencies(&repo, &alias).unwrap();
+        let choices: std::collections::HashMap<Identifier, Vec<Choice>> =
+            choices_for_execution_sequence(
+                &alias,
+                &repo,
+                &defaults,
+                &resolver_env_asked_twice,
+                seq,
+            )
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(
+            choices.get(&Identifier::new("pod")),
+            Some(&vec![Choice::from_value("api")])
+        );
+        assert_eq!(
+            *resolver_env_asked_twice.asked.borrow(),
+            vec![Identifier::new("env"), Identifier::new("env")]
+        );
+
+        // there is no previous var to go back to.
+        let alias = Alias::new("pods", "lists pods", "echo {{ pod }}");
+        let pod = Var::from_command("pod", "a pod", "kubectl -n prod get pods");
+        let repo = VarsCollectionMock(hashmap! { pod.name() => pod });
+        let seq = execution_sequence_for_dependencies(&repo, &alias).unwrap();
+        let res = choices_for_execution_sequence(
+            &alias,
+            &repo,
+            &defaults,
+            &resolver(&["kubectl -n prod get pods"]),
+            seq,
+        );
+        assert!(matches!(
+            res,
+            Err(ErrorDependencyResolution::NoChoiceForVar { error, .. })
+                if matches!(*error, crate::algorithms::resolver::ErrorsResolver::Cancelled(_))
+        ));
+    }
+
+    #[test]
+    fn test_choices_for_execution_sequence_non_interactive() {
+        use crate::entities::aliases::Alias;
+        use crate::entities::identifiers::{Identifier, Identifiers};
+        use crate::entities::vars::Var;
+
+        let choices = vec![Choice::new("dev", None), Choice::new("prod", None)];
+        let env = Var::new("env", "the environment", choices.clone());
+        let region = Var::new("region", "the region", choices);
+        let pod = Var::from_command("pod", "a pod", "kubectl -n {{ env }} get pods");
+        let alias = Alias::new(
+            "logs",
+            "shows logs",
+            "kubectl logs --region {{ region }} {{ pod }}",
+        );
+        let repo = VarsCollectionMock(
+            vec![env, region, pod]
+                .into_iter()
+                .map(|v| (v.name(), v))
+                .collect(),
+        );
+        let resolver = StaticResolver::new(
+            None,
+            hashmap! { String::from("kubectl -n prod get pods") => vec![Choice::from_value("api")] },
+            hashmap! {},
+        )
+        .non_interactive();
+
+        // pod is left out until env is given.
+        let seq = execution_sequence_for_dependencies(&repo, &alias).unwrap();
+        let res = choices_for_execution_sequence(
+            &alias,
+            &repo,
+            &VarsDefaultValuesMock::default(),
+            &resolver,
+            seq,
+        );
+        match res {
+            Err(ErrorDependencyResolution::MissingChoices(Identifiers(mut missing))) => {
+                missing.sort();
+                assert_eq!(
+                    missing,
+                    vec![Identifier::new("env"), Identifier::new("region")]
+                );
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let defaults = VarsDefaultValuesMock(hashmap! {
+            Identifier::new("env") => vec![Choice::from_value("prod")],
+            Identifier::new("region") => vec![Choice::from_value("dev")],
+        });
+        let seq = execution_sequence_for_dependencies(&repo, &alias).unwrap();
+        let choices: std::collections::HashMap<Identifier, Vec<Choice>> =
+            choices_for_execution_sequence(&alias, &repo, &defaults, &resolver, seq)
+                .unwrap()
+                .into_iter()
+                .collect();
+        assert_eq!(
+            choices.get(&Identifier::new("pod")),
+            Some(&vec![Choice::from_value("api")])
+        );
+    }
+
+    #[test]
+    fn test_choices_for_execution_sequence_with_several_defaults() {
+        use crate::entities::aliases::Alias;
+        use crate::entities::identifiers::Identifier;
+        use crate::entities::vars::Var;
+
+        let pod = Var::new(
+            "pod",
+            "a pod",
+            vec![Choice::from_value("api"), Choice::from_value("web")],
+        );
+        let alias = Alias::new("logs", "shows logs", "kubectl logs {{ pod }}");
+        let repo = VarsCollectionMock(hashmap! { pod.name() => pod });
+        let defaults = VarsDefaultValuesMock(hashmap! {
+            Identifier::new("pod") => vec![Choice::from_value("api"), Choice::from_value("web")],
+        });
+        let resolver = StaticResolver::new(None, hashmap! {}, hashmap! {}).non_interactive();
+        let seq = execution_sequence_for_dependencies(&repo, &alias).unwrap();
+        let choices = choices_for_execution_sequence(&alias, &repo, &defaults, &resolver, seq);
+        assert_eq!(
+            choices.unwrap(),
+            vec![(
+                Identifier::new("pod"),
+                vec![Choice::from_value("api"), Choice::from_value("web")]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_choices_for_execution_sequence_with_secret() {
+        use crate::entities::aliases::Alias;
+        use crate::entities::identifiers::Identifier;
+        use crate::entities::vars::Var;
+
+        let env = Var::new("env", "the environment", vec![Choice::new("prod", None)]);
+        let token = Var::from_secret("token", "the api token", "pass:{{ env }}/api");
+        let alias = Alias::new("call", "calls the api", "curl -H 'token: {{ token }}'");
+        let repo = VarsCollectionMock(
+            vec![env, token]
+                .into_iter()
+                .map(|v| (v.name(), v))
+                .collect(),
+        );
+        let defaults = VarsDefaultValuesMock::default();
+        let resolver = StaticResolver::new(
+            None,
+            hashmap! { String::from("pass:prod/api") => vec![Choice::from_value("s3cr3t")] },
+            hashmap! { Identifier::new("env") => vec![Choice::new("prod", None)] },
+        );
+        let seq = execution_sequence_for_dependencies(&repo, &alias).unwrap();
+        assert_eq!(
+            seq.identifiers(),
+            vec![Identifier::new("env"), Identifier::new("token")]
+        );
+        let choices: std::collections::HashMap<Identifier, Vec<Choice>> =
+            choices_for_execution_sequence(&alias, &repo, &defaults, &resolver, seq)
+                .unwrap()
+                .into_iter()
+                .collect();
+        assert_eq!(
+            choices.get(&Identifier::new("token")),
+            Some(&vec![Choice::from_value("s3cr3t")])
+        );
+    }
+
+    #[test]
+    fn test_choices_for_execution_sequence_with_url() {
+        use crate::entities::aliases::Alias;
+        use crate::entities::identifiers::Identifier;
+        use crate::entities::vars::Var;
+
+        let env = Var::new("env", "the environment", vec![Choice::new("prod", None)]);
+        let service = Var::from_url(
+            "service",
+            "a service",
+            "https://registry/{{ env }}/services",
+        );
+        let alias = Alias::new("logs", "tails the logs", "logs {{ service }}");
+        let repo = VarsCollectionMock(
+            vec![env, service]
+                .into_iter()
+                .map(|v| (v.name(), v))
+                .collect(),
+        );
+        let defaults = VarsDefaultValuesMock::default();
+        let resolver = StaticResolver::new(
+            None,
+            hashmap! { String::from("https://registry/prod/services") => vec![Choice::from_value("billing")] },
+            hashmap! { Identifier::new("env") => vec![Choice::new("prod", None)] },
+        );
+        let seq = execution_sequence_for_dependencies(&repo, &alias).unwrap();
+        assert_eq!(
+            seq.identifiers(),
+            vec![Identifier::new("env"), Identifier::new("service")]
+        );
+        let choices: std::collections::HashMap<Identifier, Vec<Choice>> =
+            choices_for_execution_sequence(&alias, &repo, &defaults, &resolver, seq)
+                .unwrap()
+                .into_iter()
+                .collect();
+        assert_eq!(
+            choices.get(&Identifier::new("service")),
+            Some(&vec![Choice::from_value("billing")])
+        );
+    }
+
+    #[test]
+    fn test_choices_for_execution_sequence_with_template() {
+        use crate::entities::aliases::Alias;
+        use crate::entities::identifiers::Identifier;
+        use crate::entities::vars::Var;
+
+        let env = Var::new("env", "the environment", vec![]);
+        let cluster = Var::from_template("cluster", "the cluster", "eks-{{ env | upper }}");
+        let alias = Alias::new(
+            "nodes",
+            "lists the nodes",
+            "kubectl --context {{ cluster }}",
+        );
+        let repo = VarsCollectionMock(
+            vec![env, cluster]
+                .into_iter()
+                .map(|v| (v.name(), v))
+                .collect(),
+        );
+        let defaults = VarsDefaultValuesMock::default();
+        // the template isn't handed to the resolver.
+        let resolver = StaticResolver::new(
+            None,
+            hashmap! {},
+            hashmap! {
+                Identifier::new("env") => vec![Choice::from_value("prod"), Choice::from_value("dev")],
+            },
+        );
+        let seq = execution_sequence_for_dependencies(&repo, &alias).unwrap();
+        let choices: std::collections::HashMap<Identifier, Vec<Choice>> =
+            choices_for_execution_sequence(&alias, &repo, &defaults, &resolver, seq)
+                .unwrap()
+                .into_iter()
+                .collect();
+        assert_eq!(
+            choices.get(&Identifier::new("cluster")),
+            Some(&vec![
+                Choice::from_value("eks-PROD"),
+                Choice::from_value("eks-DEV")
+            ])
+        );
+    }
+
+    #[test]
+    fn test_choices_for_execution_sequence_with_path() {
+        use crate::entities::aliases::Alias;
+        use crate::entities::identifiers::Identifier;
+        use crate::entities::vars::Var;
+
+        let env = Var::new("env", "the environment", vec![Choice::new("prod", None)]);
+        let manifest = Var::from_path("manifest", "a manifest", "k8s/{{ env }}");
+        let alias = Alias::new(
+            "apply",
+            "applies a manifest",
+            "kubectl apply -f {{ manifest }}",
+        );
+        let repo = VarsCollectionMock(
+            vec![env, manifest]
+                .into_iter()
+                .map(|v| (v.name(), v))
+                .collect(),
+        );
+        let defaults = VarsDefaultValuesMock::default();
+        let resolver = StaticResolver::new(
+            None,
+            hashmap! { String::from("k8s/prod") => vec![Choice::from_value("k8s/prod/api.yaml")] },
+            hashmap! {
+                Identifier::new("env") => vec![Choice::new("prod", None)],
+                Identifier::new("manifest") => vec![Choice::from_value("k8s/prod/api.yaml")],
+            },
+        );
+        let seq = execution_sequence_for_dependencies(&repo, &alias).unwrap();
+        assert_eq!(
+            seq.identifiers(),
+            vec![Identifier::new("env"), Identifier::new("manifest")]
+        );
+        let choices: std::collections::HashMap<Identifier, Vec<Choice>> =
+            choices_for_execution_sequence(&alias, &repo, &defaults, &resolver, seq)
+                .unwrap()
+                .into_iter()
+                .collect();
+        assert_eq!(
+            choices.get(&Identifier::new("manifest")),
+            Some(&vec![Choice::from_value("k8s/prod/api.yaml")])
+        );
+    }
+
+    #[test]
+    fn test_choices_for_execution_sequence_with_map_command() {
+        use crate::entities::aliases::Alias;
+        use crate::entities::identifiers::Identifier;
+        use crate::entities::vars::Var;
+
+        let env = Var::new("env", "the environment", vec![Choice::new("prod", None)])
+            .with_map_command(Some(String::from("upper")));
+        let region = Var::new("region", "a region", vec![Choice::new("eu", None)])
+            .with_map_command(Some(String::from("upper")));
+        let alias = Alias::new("deploy", "deploys", "deploy {{ env }} {{ region }}");
+        let repo = VarsCollectionMock(
+            vec![env, region]
+                .into_iter()
+                .map(|v| (v.name(), v))
+                .collect(),
+        );
+        // choices given as defaults are used as they are.
+        let defaults = VarsDefaultValuesMock(
+            hashmap! { Identifier::new("region") => vec![Choice::from_value("us")] },
+        );
+        let resolver = StaticResolver::new(
+            None,
+            hashmap! {},
+            hashmap! { Identifier::new("env") => vec![Choice::new("prod", None)] },
+        );
+        let seq = execution_sequence_for_dependencies(&repo, &alias).unwrap();
+        let choices: std::collections::HashMap<Identifier, Vec<Choice>> =
+            choices_for_execution_sequence(&alias, &repo, &defaults, &resolver, seq)
+                .unwrap()
+                .into_iter()
+                .collect();
+        let env = &choices[&Identifier::new("env")][0];
+        assert_eq!((env.value(), env.display()), ("PROD", "prod"));
+        assert_eq!(
+            choices.get(&Identifier::new("region")),
+            Some(&vec![Choice::from_value("us")])
+        );
+    }
+
+    #[test]
+    fn test_ready_commands() {
+        use super::ready_commands;
+        use crate::entities::identifiers::Identifier;
+
+        let cluster = Var::new("cluster", "a cluster", vec![Choice::new("prod", None)]);
+        let pods = Var::from_command("pods", "a pod", "kubectl --context {{ cluster }} get pods");
+        let nodes = Var::from_command("nodes", "a node", "kubectl get nodes");
+        let regions = Var::from_command("regions", "a region", "aws ec2 describe-regions");
+        let repo = VarsCollectionMock(
+            vec![cluster, pods, nodes, regions]
+                .into_iter()
+                .map(|v| (v.name(), v))
+                .collect(),
+        );
+        let defaults = VarsDefaultValuesMock(
+            hashmap! { Identifier::new("regions") => vec![Choice::from_value("eu-west-1")] },
+        );
+        let remaining = vec![
+            Identifier::new("cluster"),
+            Identifier::new("pods"),
+            Identifier::new("nodes"),
+            Identifier::new("regions"),
+        ];
+        let commands = |choices| -> Vec<String> {
+            ready_commands(&remaining, &repo, &defaults, &choices)
+                .into_iter()
+                .map(|(_, c)| c)
+                .collect()
+        };
+        assert_eq!(commands(hashmap! {}), vec!["kubectl get nodes"]);
+        let choices = hashmap! { Identifier::new("cluster") => vec![Choice::from_value("prod")] };
+        assert_eq!(
+            commands(choices),
+            vec!["kubectl --context prod get pods", "kubectl get nodes"]
+        );
+    }
 }
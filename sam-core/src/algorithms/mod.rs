@@ -4,6 +4,7 @@ pub mod resolver;
 pub use dependency_resolution::choice_for_var;
 pub use dependency_resolution::choices_for_execution_sequence;
 pub use dependency_resolution::execution_sequence_for_dependencies;
+pub use dependency_resolution::with_prompt_order;
 pub use dependency_resolution::ErrorDependencyResolution;
 pub use dependency_resolution::VarsCollection;
 pub use dependency_resolution::VarsDefaultValues;
@@ -15,7 +16,8 @@ pub mod mocks {
     use crate::entities::vars::Var;
     use crate::entities::{aliases::AliasAndDependencies, choices::Choice};
     pub use dependency_resolution::mocks::*;
-    use std::collections::HashMap;
+    use std::cell::RefCell;
+    use std::collections::{HashMap, HashSet};
 
     use super::resolver::{ErrorsResolver, Resolver};
 
@@ -24,9 +26,15 @@ pub mod mocks {
         identifier_to_select: Option<Identifier>,
         dynamic_res: HashMap<String, Vec<Choice>>,
         static_res: HashMap<Identifier, Vec<Choice>>,
+        // the commands whose resolution is cancelled the first time they run.
+        cancel_once: RefCell<HashSet<String>>,
+        // the vars resolve_static was called for, in order.
+        pub asked: RefCell<Vec<Identifier>>,
+        // whether the vars left out of static_res need the user to pick a choice.
+        non_interactive: bool,
     }
     impl StaticResolver {
-        pub const fn new(
+        pub fn new(
             identifier_to_select: Option<Identifier>,
             dynamic_res: HashMap<String, Vec<Choice>>,
             static_res: HashMap<Identifier, Vec<Choice>>,
@@ -35,8 +43,23 @@ pub mod mocks {
                 identifier_to_select,
                 dynamic_res,
                 static_res,
+                cancel_once: RefCell::new(HashSet::new()),
+                asked: RefCell::new(vec![]),
+                non_interactive: false,
             }
         }
+
+        pub fn non_interactive(mut self) -> Self {
+            self.non_interactive = true;
+            self
+        }
+
+        pub fn with_cancel_once(self, commands: &[&str]) -> Self {
+            self.cancel_once
+                .borrow_mut()
+                .extend(commands.iter().map(|c| c.to_string()));
+            self
+        }
     }
     impl Resolver for StaticResolver {
         fn resolve_input(
@@ -58,6 +81,9 @@ pub mod mocks {
             cmd: String,
             _ctx: &ResolverContext,
         ) -> Result<Vec<Choice>, ErrorsResolver> {
+            if self.cancel_once.borrow_mut().remove(&cmd) {
+                return Err(ErrorsResolver::Cancelled(var.name()));
+            }
             let choices = self
                 .dynamic_res
                 .iter()
@@ -72,17 +98,96 @@ pub mod mocks {
             }
         }
 
+        fn resolve_url(
+            &self,
+            var: &Var,
+            url: String,
+            _ctx: &ResolverContext,
+        ) -> Result<Vec<Choice>, ErrorsResolver> {
+            self.dynamic_res
+                .get(&url)
+                .cloned()
+                .ok_or_else(|| ErrorsResolver::NoChoiceWasAvailable(var.name()))
+        }
+
+        fn resolve_plugin(
+            &self,
+            var: &Var,
+            plugin: String,
+            _ctx: &ResolverContext,
+        ) -> Result<Vec<Choice>, ErrorsResolver> {
+            self.dynamic_res
+                .get(&plugin)
+                .cloned()
+                .ok_or_else(|| ErrorsResolver::NoChoiceWasAvailable(var.name()))
+        }
+
+        fn resolve_path(
+            &self,
+            var: &Var,
+            directory: String,
+            _ctx: &ResolverContext,
+        ) -> Result<Vec<Choice>, ErrorsResolver> {
+            self.dynamic_res
+                .get(&directory)
+                .cloned()
+                .ok_or_else(|| ErrorsResolver::NoChoiceWasAvailable(var.name()))
+        }
+
+        fn map_choice(
+            &self,
+            _var: &Var,
+            command: &str,
+            choice: Choice,
+            _ctx: &ResolverContext,
+        ) -> Result<Choice, ErrorsResolver> {
+            let value = match command {
+                "upper" => choice.value().to_uppercase(),
+                _ => choice.value().to_string(),
+            };
+            Ok(choice.with_value(value))
+        }
+
         fn resolve_static(
             &self,
             var: &Var,
             _cmd: impl Iterator<Item = Choice>,
             _ctx: &ResolverContext,
         ) -> Result<Vec<Choice>, ErrorsResolver> {
+            self.asked.borrow_mut().push(var.name());
             self.static_res
                 .get(&var.name())
                 .map(|c| c.to_owned())
-                .ok_or_else(|| ErrorsResolver::NoChoiceWasSelected(var.name()))
+                .ok_or_else(|| match self.non_interactive {
+                    true => ErrorsResolver::NonInteractive(var.name()),
+                    false => ErrorsResolver::NoChoiceWasSelected(var.name()),
+                })
+        }
+        fn resolve_secret(
+            &self,
+            var: &Var,
+            reference: String,
+            _ctx: &ResolverContext,
+        ) -> Result<Choice, ErrorsResolver> {
+            self.dynamic_res
+                .get(&reference)
+                .and_then(|e| e.first())
+                .cloned()
+                .ok_or_else(|| ErrorsResolver::NoChoiceWasAvailable(var.name()))
         }
+
+        fn resolve_history(
+            &self,
+            var: &Var,
+            alias: &Identifier,
+            _ctx: &ResolverContext,
+        ) -> Result<Vec<Choice>, ErrorsResolver> {
+            self.dynamic_res
+                .get(&alias.to_string())
+                .cloned()
+                .ok_or_else(|| ErrorsResolver::NoHistoryForAlias(var.name(), alias.clone()))
+        }
+
         fn select_identifier(
             &self,
             aliases: &[AliasAndDependencies],
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use crate::entities::aliases::{Alias, AliasAndDependencies};
-use crate::entities::choices::Choice;
+use crate::entities::choices::{Choice, ErrorChoiceFilter};
 use crate::entities::dependencies::ErrorsDependencies;
 use crate::entities::identifiers::Identifier;
 use crate::entities::vars::Var;
@@ -13,6 +13,8 @@ pub struct ResolverContext {
     pub full_name: String,
     pub choices: HashMap<Identifier, Vec<Choice>>,
     pub execution_sequence: Vec<Identifier>,
+    // the vars whose choices are masked in previews.
+    pub sensitive: Vec<Identifier>,
 }
 
 pub trait Resolver {
@@ -28,12 +30,76 @@ pub trait Resolver {
         cmd: String,
         ctx: &ResolverContext,
     ) -> Result<Vec<Choice>, ErrorsResolver>;
+    /// prefetch_dynamic is handed the commands of the `from_command` vars whose dependencies
+    /// are resolved before they are asked for, so that they can run at the same time in the
+    /// background. resolve_dynamic is called for each of them later on.
+    fn prefetch_dynamic(&self, _commands: &[(&Var, String)], _ctx: &ResolverContext) {}
+    /// stream_dynamic lets the user select the choices of a `from_command` var while its
+    /// command is still printing them. It returns None when it doesn't, resolve_dynamic and
+    /// resolve_static are then called as usual.
+    fn stream_dynamic(
+        &self,
+        _var: &Var,
+        _cmd: &str,
+        _ctx: &ResolverContext,
+    ) -> Option<Result<Vec<Choice>, ErrorsResolver>> {
+        None
+    }
+    /// resolve_url reads the choices of a `from_url` var from the response of the
+    /// endpoint, once the choices of its dependencies are substituted in the url.
+    fn resolve_url(
+        &self,
+        var: &Var,
+        url: String,
+        ctx: &ResolverContext,
+    ) -> Result<Vec<Choice>, ErrorsResolver>;
+    /// resolve_plugin asks an executable for the choices of a `from_plugin` var, once the
+    /// choices of its dependencies are substituted in its arguments.
+    fn resolve_plugin(
+        &self,
+        var: &Var,
+        plugin: String,
+        ctx: &ResolverContext,
+    ) -> Result<Vec<Choice>, ErrorsResolver>;
+    /// resolve_path lists the entries of the directory of a `from_path` var, once the
+    /// choices of its dependencies are substituted in it.
+    fn resolve_path(
+        &self,
+        var: &Var,
+        directory: String,
+        ctx: &ResolverContext,
+    ) -> Result<Vec<Choice>, ErrorsResolver>;
+    /// map_choice runs the `map_command` of a var on a selected choice and returns
+    /// the choice with the value it printed.
+    fn map_choice(
+        &self,
+        var: &Var,
+        command: &str,
+        choice: Choice,
+        ctx: &ResolverContext,
+    ) -> Result<Choice, ErrorsResolver>;
     fn resolve_static(
         &self,
         var: &Var,
         choices: impl Iterator<Item = Choice>,
         ctx: &ResolverContext,
     ) -> Result<Vec<Choice>, ErrorsResolver>;
+    /// resolve_secret reads the secret a `from_secret` var refers to, once the
+    /// choices of its dependencies are substituted in the reference.
+    fn resolve_secret(
+        &self,
+        var: &Var,
+        reference: String,
+        ctx: &ResolverContext,
+    ) -> Result<Choice, ErrorsResolver>;
+    /// resolve_history offers the lines captured from the past runs of an alias
+    /// as the choices of a `from_history` var.
+    fn resolve_history(
+        &self,
+        var: &Var,
+        alias: &Identifier,
+        ctx: &ResolverContext,
+    ) -> Result<Vec<Choice>, ErrorsResolver>;
     fn select_identifier(
         &self,
         identifiers: &[AliasAndDependencies],
@@ -41,6 +107,12 @@ pub trait Resolver {
     ) -> Result<AliasAndDependencies, ErrorsResolver>;
 }
 
+/// SecretsBackend reads secrets from where they are stored, the reference
+/// tells which backend to use and what to read, `pass:work/github` for example.
+pub trait SecretsBackend {
+    fn read(&self, reference: &str) -> Result<String, Box<dyn std::error::Error>>;
+}
+
 #[derive(Debug, Error)]
 pub enum ErrorsResolver {
     #[error("while performing choices substitution\n{0}")]
@@ -53,10 +125,32 @@ pub enum ErrorsResolver {
         "gathering choices for {0} failed because the command\n   {1} \n   returned empty content on stdout. stderr content was \n {2}", 
     )]
     DynamicResolveEmpty(Identifier, String, String),
+    #[error("gathering choices for {0} failed because the command\n   {1} \n   didn't finish within {2}s, it was killed")]
+    DynamicResolveTimeout(Identifier, String, u64),
+    #[error("the resolution of var {0} was cancelled")]
+    Cancelled(Identifier),
+    #[error("var {0} needs a choice and sam runs in non interactive mode")]
+    NonInteractive(Identifier),
+    #[error("an alias has to be given when sam runs in non interactive mode")]
+    NonInteractiveSelection,
     #[error("no choice was selected for var {0}")]
     NoChoiceWasSelected(Identifier),
     #[error("no input for for var {0} because {1}")]
     NoInputWasProvided(Identifier, String),
+    #[error("could not read the secret of var {0}\n-> {1}")]
+    SecretResolveFailure(Identifier, Box<dyn std::error::Error>),
+    #[error("no secrets backend is available to resolve var {0}")]
+    NoSecretsBackend(Identifier),
+    #[error("var {0} offers the outputs of alias {1}, which didn't capture any yet")]
+    NoHistoryForAlias(Identifier, Identifier),
+    #[error("the plugin of var {0} failed\n-> {1}")]
+    PluginFailure(Identifier, Box<dyn std::error::Error>),
+    #[error("could not list the entries of {1} for var {0}\n-> {2}")]
+    PathFailure(Identifier, String, Box<dyn std::error::Error>),
+    #[error("the filter of var {0} is invalid\n-> {1}")]
+    InvalidFilter(Identifier, ErrorChoiceFilter),
+    #[error("the map_command of var {0} failed on choice {1}\n-> {2}")]
+    MapFailure(Identifier, String, Box<dyn std::error::Error>),
     #[error("selection empty")]
     IdentifierSelectionEmpty(),
     #[error("selection invalid.")]
@@ -1,8 +1,12 @@
 use crate::entities::aliases::{Alias, ResolvedAlias};
 use std::fmt::Display;
+use std::time::Duration;
 use std::{cell::RefCell, collections::HashMap};
 
-use crate::engines::{ErrorSamEngine, SamExecutor, SamHistory, SamLogger};
+use crate::engines::{
+    CommandReport, ErrorSamEngine, ExecutionReport, SamConfirmation, SamExecutor, SamHistory,
+    SamLogger,
+};
 
 pub struct SilentLogger;
 impl SamLogger for SilentLogger {
@@ -11,6 +15,18 @@ impl SamLogger for SilentLogger {
     fn choice(&self, _: &dyn Display, _: &dyn Display) {}
     fn alias(&self, _: &Alias) {}
 }
+/// StaticConfirmation confirms all the aliases, or none of them.
+pub struct StaticConfirmation(pub bool);
+impl SamConfirmation for StaticConfirmation {
+    fn confirm(&self, alias: &ResolvedAlias) -> Result<(), ErrorSamEngine> {
+        if self.0 {
+            Ok(())
+        } else {
+            Err(ErrorSamEngine::ConfirmationDeclined(alias.name().clone()))
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct LogExecutor {
     pub commands: RefCell<Vec<(ResolvedAlias, HashMap<String, String>)>>,
@@ -21,10 +37,28 @@ impl SamExecutor for LogExecutor {
         &self,
         alias: &ResolvedAlias,
         env_variables: &HashMap<String, String>,
-    ) -> Result<i32, ErrorSamEngine> {
+    ) -> Result<ExecutionReport, ErrorSamEngine> {
         let mut cmd_mut = self.commands.borrow_mut();
         cmd_mut.push((alias.clone(), env_variables.to_owned()));
-        Ok(0)
+        Ok(ExecutionReport::default())
+    }
+}
+
+/// ExitCodeExecutor runs nothing, all the commands exit with its exit code.
+pub struct ExitCodeExecutor(pub i32);
+
+impl SamExecutor for ExitCodeExecutor {
+    fn execute_resolved_alias(
+        &self,
+        alias: &ResolvedAlias,
+        _: &HashMap<String, String>,
+    ) -> Result<ExecutionReport, ErrorSamEngine> {
+        let reports = alias
+            .commands()
+            .iter()
+            .map(|cmd| CommandReport::new(cmd, self.0, Duration::ZERO))
+            .collect();
+        Ok(ExecutionReport::new(reports))
     }
 }
 
@@ -1,9 +1,14 @@
+#[cfg(test)]
 pub mod mocks;
+mod reports;
 mod sam_engine;
+pub use reports::CommandReport;
+pub use reports::ExecutionReport;
 pub use sam_engine::AliasCollection;
 pub use sam_engine::ErrorSamEngine;
 pub use sam_engine::ErrorsAliasCollection;
 pub use sam_engine::SamCommand;
+pub use sam_engine::SamConfirmation;
 pub use sam_engine::SamEngine;
 pub use sam_engine::SamExecutor;
 pub use sam_engine::SamHistory;
@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+/// CommandReport tells how one of the commands of an alias ran. The choices of
+/// sensitive vars are masked in the command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandReport {
+    command: String,
+    exit_code: Option<i32>,
+    duration: Option<Duration>,
+}
+
+impl CommandReport {
+    pub fn new(command: impl Into<String>, exit_code: i32, duration: Duration) -> Self {
+        CommandReport {
+            command: command.into(),
+            exit_code: Some(exit_code),
+            duration: Some(duration),
+        }
+    }
+
+    /// detached reports a command sam started without waiting for it, in a tmux pane.
+    pub fn detached(command: impl Into<String>) -> Self {
+        CommandReport {
+            command: command.into(),
+            exit_code: None,
+            duration: None,
+        }
+    }
+
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// exit_code is unknown for the commands that were detached.
+    pub const fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    pub const fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+}
+
+/// ExecutionReport is what an executor returns once it ran an alias: how each of
+/// its commands ran, in the order of the alias, and the lines they printed when the
+/// alias captured or recorded its output.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ExecutionReport {
+    commands: Vec<CommandReport>,
+    outputs: Vec<String>,
+}
+
+impl ExecutionReport {
+    pub fn new(commands: Vec<CommandReport>) -> Self {
+        ExecutionReport {
+            commands,
+            outputs: vec![],
+        }
+    }
+
+    pub fn with_outputs(mut self, outputs: Vec<String>) -> Self {
+        self.outputs = outputs;
+        self
+    }
+
+    /// then appends the commands and the outputs of the report of what ran next.
+    pub fn then(mut self, next: ExecutionReport) -> Self {
+        self.commands.extend(next.commands);
+        self.outputs.extend(next.outputs);
+        self
+    }
+
+    pub fn commands(&self) -> &[CommandReport] {
+        &self.commands
+    }
+
+    pub fn outputs(&self) -> &[String] {
+        &self.outputs
+    }
+
+    /// exit_code is the exit code of the first command that didn't succeed, 0 when they all did.
+    pub fn exit_code(&self) -> i32 {
+        self.commands
+            .iter()
+            .filter_map(CommandReport::exit_code)
+            .find(|c| *c != 0)
+            .unwrap_or(0)
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.exit_code() == 0
+    }
+
+    /// duration is the time the commands took, added up.
+    pub fn duration(&self) -> Duration {
+        self.commands
+            .iter()
+            .filter_map(CommandReport::duration)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CommandReport, ExecutionReport};
+    use std::time::Duration;
+
+    #[test]
+    fn test_exit_code() {
+        let report = |codes: &[i32]| {
+            ExecutionReport::new(
+                codes
+                    .iter()
+                    .map(|c| CommandReport::new("true", *c, Duration::from_secs(1)))
+                    .collect(),
+            )
+        };
+        assert_eq!(report(&[]).exit_code(), 0);
+        assert!(report(&[0, 0, 0]).succeeded());
+        assert_eq!(report(&[0, 2, 1]).exit_code(), 2);
+        assert_eq!(report(&[0, 2, 1]).duration(), Duration::from_secs(3));
+
+        let report = ExecutionReport::new(vec![CommandReport::detached("htop")]);
+        assert!(report.succeeded());
+        assert_eq!(report.duration(), Duration::ZERO);
+    }
+}
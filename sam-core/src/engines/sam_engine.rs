@@ -1,16 +1,19 @@
+use super::reports::ExecutionReport;
 use crate::algorithms::resolver::{ErrorsResolver, Resolver};
 use crate::algorithms::{
-    choices_for_execution_sequence, execution_sequence_for_dependencies, ErrorDependencyResolution,
-    VarsCollection, VarsDefaultValues,
+    choices_for_execution_sequence, execution_sequence_for_dependencies, with_prompt_order,
+    ErrorDependencyResolution, VarsCollection, VarsDefaultValues,
 };
 use crate::entities::aliases::{Alias, AliasAndDependencies, ResolvedAlias};
 use crate::entities::choices::Choice;
-use crate::entities::identifiers::Identifier;
+use crate::entities::identifiers::{Identifier, Identifiers};
+use crate::entities::vars::Var;
 use std::cell::RefCell;
 // TODO get rid of this import
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::rc::Rc;
+use std::time::Instant;
 use thiserror::Error;
 
 const PROMPT: &str = "Choose an alias to run > ";
@@ -52,7 +55,7 @@ pub enum ErrorsAliasCollection {
     AliasSelectionFailure(#[from] ErrorsResolver),
     #[error("Invalid alias selected {0}")]
     AliasInvalidSelection(Identifier),
-    #[error("Can't figure out dependencies for alias")]
+    #[error("Can't figure out dependencies for alias\n-> {0}")]
     AliasDependencyResolution(#[from] ErrorDependencyResolution),
 }
 
@@ -68,7 +71,14 @@ pub enum ErrorsAliasCollection {
 #[derive(Clone, Debug, PartialEq)]
 pub enum SamCommand {
     ChooseAndExecuteAlias,
-    ExecuteAlias { alias: Identifier },
+    ExecuteAlias {
+        alias: Identifier,
+    },
+    /// ResolveAlias resolves the vars of an alias and hands it to the executor without
+    /// recording it in the history, so that the executor can print its commands.
+    ResolveAlias {
+        alias: Identifier,
+    },
 }
 
 // TODO Rename to UseCaseAliasExec
@@ -85,6 +95,7 @@ pub struct SamEngine<
     pub logger: Rc<dyn SamLogger>,
     pub history: RefCell<Box<dyn SamHistory>>,
     pub env_variables: HashMap<String, String>,
+    pub confirmation: Rc<dyn SamConfirmation>,
     pub executor: Rc<dyn SamExecutor>,
 }
 
@@ -95,22 +106,45 @@ impl<
         DV: VarsDefaultValues + VarsDefaultValuesSetter,
     > SamEngine<R, AR, VR, DV>
 {
-    pub fn run(&mut self, command: SamCommand) -> Result<i32> {
+    pub fn run(&mut self, command: SamCommand) -> Result<ExecutionReport> {
         use SamCommand::*;
         match command {
             ChooseAndExecuteAlias => self.choose_and_execute_alias(),
             ExecuteAlias { alias } => self.execute_alias(&alias),
+            ResolveAlias { alias } => self.resolve_alias(&alias),
         }
     }
 
-    fn choose_and_execute_alias(&self) -> Result<i32> {
+    /// resolve_var asks for the choices of a var, after the ones of the vars it depends on,
+    /// the way they are asked for when running an alias using it. Nothing is run nor recorded.
+    pub fn resolve_var(&self, var: &Identifier) -> Result<Vec<Choice>> {
+        let alias = Alias::new(var.name(), "", format!("{{{{ {} }}}}", var).as_str());
+        let exec_seq = execution_sequence_for_dependencies(&self.vars, &alias)?;
+        let choices = choices_for_execution_sequence(
+            &alias,
+            &self.vars,
+            &self.defaults,
+            &self.resolver,
+            exec_seq,
+        )?;
+        choices
+            .into_iter()
+            .find(|(id, _)| id == var)
+            .map(|(_, choices)| choices)
+            .ok_or_else(|| {
+                ErrorDependencyResolution::MissingDependencies(Identifiers(vec![var.clone()]))
+                    .into()
+            })
+    }
+
+    fn choose_and_execute_alias(&self) -> Result<ExecutionReport> {
         let id = self
             .aliases
             .select_alias(&self.resolver, &self.vars, PROMPT)?;
         self.run_alias(id)
     }
 
-    fn execute_alias(&self, alias_id: &Identifier) -> Result<i32> {
+    fn execute_alias(&self, alias_id: &Identifier) -> Result<ExecutionReport> {
         let alias = self
             .aliases
             .get(alias_id)
@@ -118,10 +152,79 @@ impl<
         self.run_alias(alias)
     }
 
-    fn run_alias(&self, alias: &Alias) -> Result<i32> {
+    fn resolve_alias(&self, alias_id: &Identifier) -> Result<ExecutionReport> {
+        let alias = self
+            .aliases
+            .get(alias_id)
+            .ok_or_else(|| ErrorsAliasCollection::AliasInvalidSelection(alias_id.clone()))?;
+        let final_alias = self.with_choices(alias)?;
+        self.executor
+            .execute_resolved_alias(&final_alias, &self.env_variables)
+    }
+
+    fn run_alias(&self, alias: &Alias) -> Result<ExecutionReport> {
+        let final_alias = self.with_choices(alias)?;
+        // aliases that aren't confirmed are neither run nor recorded.
+        self.confirmation.confirm(&final_alias)?;
+        let report = self.execute(alias, &final_alias);
+        // the maintainer is pointed to whether the commands couldn't run or failed.
+        let maintainer = match alias.maintainer() {
+            Some(maintainer) => maintainer,
+            None => return report,
+        };
+        let source = match report {
+            Ok(report) if report.succeeded() => return Ok(report),
+            Ok(report) => ErrorSamEngine::AliasFailure(alias.identifier(), report.exit_code()),
+            Err(err) => err,
+        };
+        Err(ErrorSamEngine::MaintainedAliasFailure {
+            maintainer,
+            source: Box::new(source),
+        })
+    }
+
+    // execute records and runs a confirmed alias, the steps of a workflow are run instead.
+    fn execute(&self, alias: &Alias, resolved: &ResolvedAlias) -> Result<ExecutionReport> {
+        if !alias.steps().is_empty() {
+            return self.run_steps(alias, resolved);
+        }
+        // the choices of sensitive vars are never written to the history.
+        self.history.borrow_mut().put(resolved.clone().redact())?;
+        self.executor
+            .execute_resolved_alias(resolved, &self.env_variables)
+    }
+
+    // run_steps runs each step of a workflow as its own alias, with the choices resolved
+    // for the whole workflow. It stops at the first step that fails unless errors are ignored.
+    fn run_steps(&self, alias: &Alias, workflow: &ResolvedAlias) -> Result<ExecutionReport> {
+        let mut report = ExecutionReport::default();
+        for id in alias.steps() {
+            let step = self
+                .aliases
+                .get(&id)
+                .ok_or_else(|| ErrorsAliasCollection::AliasInvalidSelection(id.clone()))?;
+            let resolved = step
+                .with_choices(workflow.choices())
+                .unwrap()
+                .with_sensitive(workflow.sensitive().to_vec());
+            self.confirmation.confirm(&resolved)?;
+            let step_report = self.execute(step, &resolved)?;
+            let failed = !step_report.succeeded();
+            report = report.then(step_report);
+            if failed && !alias.ignore_errors() {
+                break;
+            }
+        }
+        Ok(report)
+    }
+
+    // with_choices asks for the choices of the vars of the alias and substitutes them.
+    fn with_choices(&self, alias: &Alias) -> Result<ResolvedAlias> {
+        let started = Instant::now();
         self.logger.alias(alias);
         let exec_seq = execution_sequence_for_dependencies(&self.vars, alias)?;
-        let choices: HashMap<Identifier, Vec<Choice>> = choices_for_execution_sequence(
+        let exec_seq = with_prompt_order(&self.vars, exec_seq, &alias.prompt_order());
+        let mut choices: HashMap<Identifier, Vec<Choice>> = choices_for_execution_sequence(
             alias,
             &self.vars,
             &self.defaults,
@@ -130,10 +233,44 @@ impl<
         )?
         .into_iter()
         .collect();
-        let final_alias = alias.with_choices(&choices).unwrap();
-        self.history.borrow_mut().put(final_alias.clone())?;
-        self.executor
-            .execute_resolved_alias(&final_alias, &self.env_variables)
+        for parameter in alias.parameters() {
+            let arguments = self.arguments(&parameter).ok_or_else(|| {
+                ErrorSamEngine::MissingArgument(
+                    alias.identifier(),
+                    parameter.argument_position().unwrap_or_default(),
+                )
+            })?;
+            choices.insert(parameter, arguments);
+        }
+        let sensitive: Vec<Identifier> = choices
+            .keys()
+            .filter(|id| self.vars.get(id).is_some_and(Var::is_sensitive))
+            .cloned()
+            .collect();
+        let resolved = alias
+            .with_choices(&choices)
+            .unwrap()
+            .with_sensitive(sensitive)
+            .with_resolution_time(started.elapsed());
+        let mut ids: Vec<&Identifier> = choices.keys().collect();
+        ids.sort_by_key(|id| id.to_string());
+        for id in ids {
+            let values: Vec<&str> = choices[id].iter().map(Choice::value).collect();
+            self.logger.choice(id, &resolved.mask(&values.join(" ")));
+        }
+        Ok(resolved)
+    }
+
+    // arguments returns the command line argument for a parameter of an alias. They're
+    // given without a namespace, the ones of a replayed entry of the history have one.
+    fn arguments(&self, parameter: &Identifier) -> Option<Vec<Choice>> {
+        self.defaults
+            .default_values(parameter)
+            .or_else(|| {
+                self.defaults
+                    .default_values(&Identifier::new(parameter.name()))
+            })
+            .map(<[Choice]>::to_vec)
     }
 }
 
@@ -153,12 +290,18 @@ pub trait SamLogger {
     fn alias(&self, alias: &Alias);
 }
 
+/// SamConfirmation is asked whether a resolved alias can run, before it's
+/// recorded in the history and handed over to the executor.
+pub trait SamConfirmation {
+    fn confirm(&self, alias: &ResolvedAlias) -> Result<()>;
+}
+
 pub trait SamExecutor {
     fn execute_resolved_alias(
         &self,
         alias: &ResolvedAlias,
         env_variables: &HashMap<String, String>,
-    ) -> Result<i32>;
+    ) -> Result<ExecutionReport>;
 }
 
 pub type Result<T> = std::result::Result<T, ErrorSamEngine>;
@@ -171,6 +314,18 @@ pub enum ErrorSamEngine {
     ExecutorFailure(Box<dyn std::error::Error>),
     #[error("the requested alias was not found")]
     InvalidAliasSelection,
+    #[error("the confirmation did not match, alias {0} was not executed")]
+    ConfirmationMismatch(Identifier),
+    #[error("alias {0} was not executed")]
+    ConfirmationDeclined(Identifier),
+    #[error(
+        "alias {0} takes {1} argument(s) from the command line: `sam alias {0} -- <arg1> ...`"
+    )]
+    MissingArgument(Identifier, usize),
+    #[error("alias {0} reads its input from stdin, pipe it to sam: `... | sam alias {0}`")]
+    StdinRequired(Identifier),
+    #[error("alias {0} doesn't read its input from stdin, don't pipe anything to sam")]
+    StdinForbidden(Identifier),
     #[error("could not resolve the dependency because\n-> {0}")]
     Resolver(#[from] ErrorsResolver),
     #[error("could not figure out dependencies\n-> {0}")]
@@ -181,6 +336,24 @@ pub enum ErrorSamEngine {
     SubCommand(#[from] std::io::Error),
     #[error("history is unavailable\n-> {0}")]
     HistoryNotAvailable(#[from] Box<dyn std::error::Error>),
+    #[error("alias {0} failed with exit code {1}")]
+    AliasFailure(Identifier, i32),
+    #[error("{source}\nthis recipe is maintained by {maintainer}")]
+    MaintainedAliasFailure {
+        maintainer: String,
+        source: Box<ErrorSamEngine>,
+    },
+}
+
+impl ErrorSamEngine {
+    /// exit_code is the exit code of the alias that failed, 1 for the other errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorSamEngine::AliasFailure(_, code) => *code,
+            ErrorSamEngine::MaintainedAliasFailure { source, .. } => source.exit_code(),
+            _ => 1,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -193,12 +366,14 @@ mod tests {
     use crate::entities::{choices::Choice, identifiers::Identifier};
     use maplit::hashmap;
 
-    use crate::engines::mocks::{InMemoryHistory, LogExecutor, SilentLogger};
+    use crate::engines::mocks::{
+        ExitCodeExecutor, InMemoryHistory, LogExecutor, SilentLogger, StaticConfirmation,
+    };
 
     use crate::engines::{SamCommand, SamEngine};
 
     use super::mocks::StaticAliasRepository;
-    use super::{fixtures, SamExecutor};
+    use super::{fixtures, ErrorSamEngine, SamExecutor, VarsDefaultValuesSetter};
 
     #[test]
     fn choose_and_execute_alias() {
@@ -292,6 +467,180 @@ mod tests {
         );
     }
 
+    #[test]
+    fn declined_alias_is_neither_run_nor_recorded() {
+        let static_res = hashmap! {
+            Identifier::new("variable_1") => vec![Choice::new("value_1", None)],
+        };
+        let dynamic_res = hashmap! {
+            String::from("echo '$SOME_ENV_VAR\\ntoto'") => vec![Choice::new("toto", None)]
+        };
+
+        let executor = Rc::new(LogExecutor::default());
+        let mut engine = make_engine(None, dynamic_res, static_res, executor.clone());
+        engine.confirmation = Rc::new(StaticConfirmation(false));
+        let result = engine.run(SamCommand::ExecuteAlias {
+            alias: Identifier::new("alias_2"),
+        });
+        assert!(matches!(
+            result,
+            Err(ErrorSamEngine::ConfirmationDeclined(_))
+        ));
+        assert!(executor.commands.borrow().is_empty());
+        assert!(engine.history.borrow().get_last().unwrap().is_none());
+    }
+
+    #[test]
+    fn maintained_alias_failure() {
+        let static_res = hashmap! {
+            Identifier::new("variable_1") => vec![Choice::new("value_1", None)],
+        };
+        let dynamic_res = hashmap! {
+            String::from("echo '$SOME_ENV_VAR\\ntoto'") => vec![Choice::new("toto", None)]
+        };
+        let mut engine = make_engine(None, dynamic_res, static_res, Rc::new(ExitCodeExecutor(2)));
+        let err = engine
+            .run(SamCommand::ExecuteAlias {
+                alias: Identifier::new("alias_4"),
+            })
+            .expect_err("a failing alias with an owner should point to it");
+        assert_eq!(err.exit_code(), 2);
+        assert!(err
+            .to_string()
+            .ends_with("this recipe is maintained by platform team"));
+
+        // the exit code of aliases without a maintainer is in their report.
+        let report = engine
+            .run(SamCommand::ExecuteAlias {
+                alias: Identifier::new("alias_2"),
+            })
+            .expect("a failing alias without an owner should return its report");
+        assert_eq!(report.exit_code(), 2);
+    }
+
+    #[test]
+    fn resolve_alias() {
+        let static_res = hashmap! {
+            Identifier::new("variable_1") => vec![Choice::new("value_1", None)],
+        };
+        let dynamic_res = hashmap! {
+            String::from("echo '$SOME_ENV_VAR\\ntoto'") => vec![Choice::new("toto", None)]
+        };
+
+        let executor = Rc::new(LogExecutor::default());
+        let mut engine = make_engine(None, dynamic_res, static_res, executor.clone());
+        engine
+            .run(SamCommand::ResolveAlias {
+                alias: Identifier::new("alias_2"),
+            })
+            .expect("Should not return an error");
+        let resolved_aliases = executor.commands.borrow();
+        assert_eq!(resolved_aliases.len(), 1);
+        assert_eq!(resolved_aliases[0].0.choices().len(), 2);
+        // resolving an alias doesn't record it.
+        assert!(engine.history.borrow().get_last().unwrap().is_none());
+    }
+
+    #[test]
+    fn execute_alias_with_arguments() {
+        let static_res = hashmap! {
+            Identifier::new("variable_1") => vec![Choice::new("value_1", None)],
+        };
+        let executor = Rc::new(LogExecutor::default());
+        let mut engine = make_engine(None, HashMap::new(), static_res, executor.clone());
+        let alias = Identifier::new("alias_3");
+        engine.defaults.set_defaults(&hashmap! {
+            Identifier::argument(1) => vec![Choice::from_value("api")],
+        });
+        assert_eq!(
+            engine
+                .run(SamCommand::ExecuteAlias {
+                    alias: alias.clone()
+                })
+                .unwrap_err()
+                .to_string(),
+            "alias alias_3 takes 2 argument(s) from the command line: `sam alias alias_3 -- <arg1> ...`"
+        );
+        engine.defaults.set_defaults(&hashmap! {
+            Identifier::argument(2) => vec![Choice::from_value("v1.2")],
+        });
+        engine
+            .run(SamCommand::ExecuteAlias { alias })
+            .expect("Should not return an error");
+        let resolved_aliases = executor.commands.borrow();
+        assert_eq!(
+            resolved_aliases[0].0.commands(),
+            &[String::from("deploy api value_1 --version v1.2")]
+        );
+        assert_eq!(
+            resolved_aliases[0].0.choice(&Identifier::argument(2)),
+            Some(vec![Choice::from_value("v1.2")])
+        );
+    }
+
+    #[test]
+    fn resolve_var() {
+        let executor = Rc::new(LogExecutor::default());
+        let dynamic_res = hashmap! {
+            String::from("echo '$SOME_ENV_VAR\\ntoto'") => vec![Choice::new("toto", None)]
+        };
+        let engine = make_engine(None, dynamic_res, HashMap::new(), executor.clone());
+        assert_eq!(
+            engine.resolve_var(&Identifier::new("variable_2")).unwrap(),
+            vec![Choice::new("toto", None)]
+        );
+        assert!(engine.resolve_var(&Identifier::new("missing")).is_err());
+        assert!(executor.commands.borrow().is_empty());
+        assert!(engine.history.borrow().get_last().unwrap().is_none());
+    }
+
+    #[test]
+    fn execute_workflow() {
+        let static_res = hashmap! {
+            Identifier::new("variable_1") => vec![Choice::new("value_1", None)],
+        };
+        let dynamic_res = hashmap! {
+            String::from("echo '$SOME_ENV_VAR\\ntoto'") => vec![Choice::new("toto", None)]
+        };
+        let executor = Rc::new(LogExecutor::default());
+        let mut engine = make_engine(
+            None,
+            dynamic_res.clone(),
+            static_res.clone(),
+            executor.clone(),
+        );
+        engine
+            .run(SamCommand::ExecuteAlias {
+                alias: Identifier::new("workflow"),
+            })
+            .expect("Should not return an error");
+        // each step runs as its own alias with the choices of the workflow.
+        let steps = executor.commands.borrow();
+        let names: Vec<&str> = steps.iter().map(|(alias, _)| alias.name().name()).collect();
+        assert_eq!(names, vec!["alias_4", "alias_1"]);
+        assert_eq!(
+            steps[1].0.choice(&Identifier::new("variable_1")),
+            Some(vec![Choice::new("value_1", None)])
+        );
+        assert_eq!(engine.history.borrow().get_last_n(5).unwrap().len(), 2);
+
+        // a failing step stops the workflow unless errors are ignored.
+        let mut engine = make_engine(None, dynamic_res, static_res, Rc::new(ExitCodeExecutor(2)));
+        let report = engine
+            .run(SamCommand::ExecuteAlias {
+                alias: Identifier::new("workflow"),
+            })
+            .unwrap();
+        assert_eq!(report.commands().len(), 1);
+        assert_eq!(report.exit_code(), 2);
+        let report = engine
+            .run(SamCommand::ExecuteAlias {
+                alias: Identifier::new("lenient_workflow"),
+            })
+            .unwrap();
+        assert_eq!(report.commands().len(), 2);
+    }
+
     fn make_engine(
         identifier_to_select: Option<Identifier>,
         dynamic_res: HashMap<String, Vec<Choice>>,
@@ -311,6 +660,7 @@ mod tests {
             logger,
             history,
             env_variables: sam_data.env_variables,
+            confirmation: Rc::new(StaticConfirmation(true)),
             executor,
         }
     }
@@ -321,14 +671,11 @@ mod mocks {
     use crate::algorithms::mocks::VarsDefaultValuesMock;
     use crate::engines::AliasCollection;
     use crate::entities::aliases::Alias;
-    use crate::entities::aliases::ResolvedAlias;
     use crate::entities::choices::Choice;
     use crate::entities::identifiers::Identifier;
-    use std::cell::RefCell;
     use std::collections::HashMap;
-    use std::collections::VecDeque;
 
-    use super::{SamHistory, VarsDefaultValuesSetter};
+    use super::VarsDefaultValuesSetter;
 
     impl VarsDefaultValuesSetter for VarsDefaultValuesMock {
         fn set_defaults(&mut self, defaults: &HashMap<Identifier, Vec<Choice>>) {
@@ -338,29 +685,6 @@ mod mocks {
         }
     }
 
-    #[derive(Default)]
-    pub struct InMemoryHistory {
-        pub aliases: RefCell<VecDeque<ResolvedAlias>>,
-    }
-
-    impl SamHistory for InMemoryHistory {
-        fn put(&mut self, alias: ResolvedAlias) -> super::Result<()> {
-            let mut queue = self.aliases.borrow_mut();
-            queue.push_front(alias);
-            Ok(())
-        }
-
-        fn get_last_n(&self, n: usize) -> super::Result<Vec<ResolvedAlias>> {
-            Ok(self
-                .aliases
-                .borrow()
-                .iter()
-                .take(n)
-                .map(ToOwned::to_owned)
-                .collect())
-        }
-    }
-
     pub struct StaticAliasRepository {
         aliases: HashMap<Identifier, Alias>,
     }
@@ -440,7 +764,23 @@ mod fixtures {
               alias: 'some_cmd --type=$SOME_ENV_VAR_2 {{variable_1}}|grep {{variable_2}}'
             - name: 'alias_2'
               desc: 'description of alias_1 in ns2'
-              alias: 'some_cmd --type=$SOME_ENV_VAR_2 {{variable_1}}|grep {{variable_2}} | echo {{variable_1}} '";
+              alias: 'some_cmd --type=$SOME_ENV_VAR_2 {{variable_1}}|grep {{variable_2}} | echo {{variable_1}} '
+            - name: 'alias_3'
+              desc: 'description of alias_3 taking arguments'
+              alias: 'deploy {{ arg:1 }} {{variable_1}} --version {{ arg:2 }}'
+            - name: 'alias_4'
+              desc: 'description of alias_4 maintained by a team'
+              alias: 'rollback'
+              owner: 'platform team'
+            - name: 'workflow'
+              desc: 'rolls back then runs alias_1, with the command the repository gives it'
+              alias: '( rollback ) && ( some_cmd --type=$SOME_ENV_VAR_2 {{variable_1}}|grep {{variable_2}} )'
+              steps: [alias_4, alias_1]
+            - name: 'lenient_workflow'
+              desc: 'rolls back then runs alias_1 even if the rollback failed'
+              alias: '( rollback ); ( some_cmd --type=$SOME_ENV_VAR_2 {{variable_1}}|grep {{variable_2}} )'
+              steps: [alias_4, alias_1]
+              ignore_errors: true";
 
         let env_variables = hashmap! {
             "SOME_ENV_VAR".to_string() => "env_var_value".to_string(),
@@ -1,6 +1,8 @@
 use crate::entities::choices::Choice;
 use crate::entities::commands::Command;
+use crate::entities::conditions::matches_os;
 use crate::entities::dependencies::Dependencies;
+use crate::entities::filters::FILTERS_RE;
 use crate::entities::identifiers::Identifier;
 use crate::entities::namespaces::Namespace;
 use crate::entities::namespaces::NamespaceUpdater;
@@ -11,23 +13,168 @@ use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::time::Duration;
 
-use super::dependencies::ErrorsDependencies;
+use super::dependencies::{substitute_choices_partial, ErrorsDependencies};
 
 lazy_static! {
     // matches the following patters :
     // - {{ some_name_1 }}
     // - {{some_name_1 }}
     // - {{ some_name_1}}
-    pub static ref VARS_NO_NS_RE: Regex = Regex::new("\\{\\{ ?(?P<vars>[a-zA-Z0-9_]+) ?\\}\\}").unwrap();
+    // - {{ some_name_1 | upper }}
+    pub static ref VARS_NO_NS_RE: Regex = Regex::new(&format!("\\{{\\{{ ?(?P<vars>[a-zA-Z0-9_]+)(?P<filters>{}) ?\\}}\\}}", FILTERS_RE)).unwrap();
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
 pub struct Alias {
     #[serde(flatten)]
     name: Identifier,
     desc: String,
+    // aliases made of steps get their command from the steps once they are loaded.
+    #[serde(default)]
     alias: String,
+    // the identifiers of the aliases to run one after the other, the next
+    // step only runs if the previous one succeeded unless errors are ignored.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    steps: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    ignore_errors: bool,
+    // environment variables set when running the alias, values can reference vars.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    env: HashMap<String, String>,
+    // who maintains the alias and how to reach them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    owner: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    contact: Option<String>,
+    // critical aliases must be confirmed by typing their name, or the
+    // confirmation phrase when one is provided, before they run.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    critical: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    confirmation_phrase: Option<String>,
+    // a yes/no question asked before running the alias.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    confirm: Option<Confirm>,
+    // hidden aliases are left out of the alias picker, they can still be
+    // run with their identifier or composed in other aliases.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    hidden: bool,
+    // deprecated aliases still run, the hint telling what to use instead is printed first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deprecated: Option<String>,
+    // messages displayed once the alias ran, they can reference vars.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    success_message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    failure_message: Option<String>,
+    // commands run once the alias ran, depending on its outcome, they can reference vars.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    on_success: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    on_failure: Option<String>,
+    // whether choices are exported as SAM_CHOICE_ environment variables, they are by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    export_choices: Option<bool>,
+    // the order in which vars are prompted for when it doesn't break their dependencies.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    prompt_order: Vec<String>,
+    // the operating systems the alias is loaded on, separated by `|`, all of them by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    when_os: Option<String>,
+    // disabled aliases are left out, recipes can keep deprecated aliases around.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    enabled: Option<bool>,
+    // how much running the alias can affect the systems it targets and how long it takes,
+    // high impact aliases are confirmed before they run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    impact: Option<Impact>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    estimated_duration: Option<String>,
+    // the lines the alias prints are recorded in the history, `from_history` vars offer them as choices.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    capture: bool,
+    // whether the commands read what's piped to sam, sam asks before passing it on when it's unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stdin: Option<StdinMode>,
+    // the shell the commands run with, `zsh -c`, the one of the configuration otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shell: Option<String>,
+    // whether the commands run in a login shell, which reads the profile of the user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    login_shell: Option<bool>,
+    // interactive aliases, ssh or psql sessions, run in the foreground with the terminal,
+    // batch ones, `interactive: false`, read nothing and have their output captured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    interactive: Option<bool>,
+    // the lines printed by the commands, stdout and stderr, are kept in the history entry.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    record_output: bool,
+    // categories of the alias, the alias picker and listings can be limited to some of them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+}
+
+/// StdinMode is the `stdin` field of an alias. `require` aliases only run when something
+/// is piped to sam, `forbid` aliases never run when it's the case and `passthrough`
+/// aliases are handed what's piped to sam without a confirmation.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum StdinMode {
+    Require,
+    Forbid,
+    Passthrough,
+}
+
+/// Impact is the `impact` field of an alias, `low`, `medium` or `high`.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Impact {
+    Low,
+    Medium,
+    High,
+}
+
+impl Display for Impact {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Impact::Low => write!(f, "low"),
+            Impact::Medium => write!(f, "medium"),
+            Impact::High => write!(f, "high"),
+        }
+    }
+}
+
+// captures_output tells whether the output of an alias is captured, interactive
+// programs need the terminal while batch ones can be captured without a capture field.
+const fn captures_output(capture: bool, interactive: Option<bool>) -> bool {
+    match interactive {
+        Some(interactive) => !interactive,
+        None => capture,
+    }
+}
+
+// annotation describes the impact and the estimated duration of an alias in a few words.
+fn annotation(impact: Option<Impact>, estimated_duration: Option<&str>) -> Option<String> {
+    match (impact, estimated_duration) {
+        (Some(impact), Some(duration)) => Some(format!(
+            "{} impact, estimated duration: {}",
+            impact, duration
+        )),
+        (Some(impact), None) => Some(format!("{} impact", impact)),
+        (None, Some(duration)) => Some(format!("estimated duration: {}", duration)),
+        (None, None) => None,
+    }
+}
+
+/// Confirm is the `confirm` field of an alias, either `true` to ask
+/// a default question or the question to ask.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Confirm {
+    Enabled(bool),
+    Question(String),
 }
 
 impl Alias {
@@ -39,7 +186,319 @@ impl Alias {
             name: Identifier::new(name),
             desc: description.into(),
             alias: alias.into(),
+            steps: vec![],
+            ignore_errors: false,
+            env: HashMap::new(),
+            owner: None,
+            contact: None,
+            critical: false,
+            confirmation_phrase: None,
+            confirm: None,
+            hidden: false,
+            deprecated: None,
+            success_message: None,
+            failure_message: None,
+            on_success: None,
+            on_failure: None,
+            export_choices: None,
+            prompt_order: vec![],
+            when_os: None,
+            enabled: None,
+            impact: None,
+            estimated_duration: None,
+            capture: false,
+            stdin: None,
+            shell: None,
+            login_shell: None,
+            interactive: None,
+            record_output: false,
+            tags: vec![],
+        }
+    }
+
+    pub fn with_impact(
+        mut self,
+        impact: Option<Impact>,
+        estimated_duration: Option<String>,
+    ) -> Alias {
+        self.impact = impact;
+        self.estimated_duration = estimated_duration;
+        self
+    }
+
+    pub const fn impact(&self) -> Option<Impact> {
+        self.impact
+    }
+
+    pub fn estimated_duration(&self) -> Option<&str> {
+        self.estimated_duration.as_deref()
+    }
+
+    /// annotation returns the impact and the estimated duration of the alias, if it has some.
+    pub fn annotation(&self) -> Option<String> {
+        annotation(self.impact, self.estimated_duration())
+    }
+
+    pub fn with_when_os(mut self, when_os: Option<String>) -> Alias {
+        self.when_os = when_os;
+        self
+    }
+
+    /// runs_on tells whether the alias is meant for the given operating system.
+    pub fn runs_on(&self, os: &str) -> bool {
+        self.when_os
+            .as_deref()
+            .is_none_or(|when_os| matches_os(when_os, os))
+    }
+
+    pub fn with_enabled(mut self, enabled: Option<bool>) -> Alias {
+        self.enabled = enabled;
+        self
+    }
+
+    /// is_enabled tells whether the alias is loaded, it is unless `enabled: false` is set.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled != Some(false)
+    }
+
+    pub fn with_prompt_order(mut self, prompt_order: Vec<String>) -> Alias {
+        self.prompt_order = prompt_order;
+        self
+    }
+
+    /// prompt_order returns the vars the alias would like to be prompted for first, in order.
+    /// Vars without a namespace belong to the namespace of the alias.
+    pub fn prompt_order(&self) -> Vec<Identifier> {
+        self.qualified(&self.prompt_order)
+    }
+
+    pub fn with_steps(mut self, steps: Vec<String>, ignore_errors: bool) -> Alias {
+        self.steps = steps;
+        self.ignore_errors = ignore_errors;
+        self
+    }
+
+    /// steps returns the aliases this alias runs one after the other, aliases
+    /// without a namespace belong to the namespace of the alias.
+    pub fn steps(&self) -> Vec<Identifier> {
+        self.qualified(&self.steps)
+    }
+
+    pub const fn ignore_errors(&self) -> bool {
+        self.ignore_errors
+    }
+
+    /// parameters returns the `{{ arg:N }}` placeholders of the alias, by position. They're
+    /// filled with the arguments given after `--` on the command line instead of a var.
+    pub fn parameters(&self) -> Vec<Identifier> {
+        let mut parameters: Vec<Identifier> = self
+            .placeholders()
+            .into_iter()
+            .filter(|id| id.argument_position().is_some())
+            .collect();
+        parameters.sort_by_key(Identifier::argument_position);
+        parameters
+    }
+
+    // placeholders returns the vars and the parameters used by the alias and its environment.
+    fn placeholders(&self) -> Vec<Identifier> {
+        let mut env_values: Vec<&String> = self.env.values().collect();
+        env_values.sort();
+        let mut placeholders: Vec<Identifier> = vec![];
+        let command_deps = Identifier::parse(self.command(), self.namespace());
+        let env_deps = env_values
+            .into_iter()
+            .flat_map(|value| Identifier::parse(value, self.namespace()));
+        for dep in env_deps.chain(command_deps) {
+            if !placeholders.contains(&dep) {
+                placeholders.push(dep);
+            }
         }
+        placeholders
+    }
+
+    fn qualified(&self, ids: &[String]) -> Vec<Identifier> {
+        ids.iter()
+            .map(|id| {
+                let (name, namespace) = Identifier::maybe_namespace(id.as_str());
+                Identifier::with_namespace(name, namespace.or_else(|| self.name.namespace.clone()))
+            })
+            .collect()
+    }
+
+    pub fn with_export_choices(mut self, export_choices: Option<bool>) -> Alias {
+        self.export_choices = export_choices;
+        self
+    }
+
+    pub fn with_messages(mut self, success: Option<String>, failure: Option<String>) -> Alias {
+        self.success_message = success;
+        self.failure_message = failure;
+        self
+    }
+
+    pub fn with_hooks(mut self, on_success: Option<String>, on_failure: Option<String>) -> Alias {
+        self.on_success = on_success;
+        self.on_failure = on_failure;
+        self
+    }
+
+    pub fn with_hidden(mut self, hidden: bool) -> Alias {
+        self.hidden = hidden;
+        self
+    }
+
+    pub const fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    pub fn with_deprecated(mut self, hint: Option<String>) -> Alias {
+        self.deprecated = hint;
+        self
+    }
+
+    /// deprecation returns the hint of a deprecated alias, what to use instead.
+    pub fn deprecation(&self) -> Option<&str> {
+        self.deprecated.as_deref()
+    }
+
+    pub fn with_capture(mut self, capture: bool) -> Alias {
+        self.capture = capture;
+        self
+    }
+
+    /// captures_output tells whether the lines the alias prints are recorded in the history.
+    /// Batch aliases always are, interactive ones never are.
+    pub const fn captures_output(&self) -> bool {
+        captures_output(self.capture, self.interactive)
+    }
+
+    pub fn with_interactive(mut self, interactive: Option<bool>) -> Alias {
+        self.interactive = interactive;
+        self
+    }
+
+    pub const fn interactive(&self) -> Option<bool> {
+        self.interactive
+    }
+
+    pub fn with_record_output(mut self, record_output: bool) -> Alias {
+        self.record_output = record_output;
+        self
+    }
+
+    pub const fn records_output(&self) -> bool {
+        self.record_output
+    }
+
+    pub fn with_stdin(mut self, stdin: Option<StdinMode>) -> Alias {
+        self.stdin = stdin;
+        self
+    }
+
+    pub const fn stdin(&self) -> Option<StdinMode> {
+        self.stdin
+    }
+
+    pub fn with_shell(mut self, shell: Option<String>) -> Alias {
+        self.shell = shell;
+        self
+    }
+
+    pub fn shell(&self) -> Option<&str> {
+        self.shell.as_deref()
+    }
+
+    pub fn with_login_shell(mut self, login_shell: Option<bool>) -> Alias {
+        self.login_shell = login_shell;
+        self
+    }
+
+    pub const fn login_shell(&self) -> Option<bool> {
+        self.login_shell
+    }
+
+    pub fn with_tags(mut self, tags: Vec<String>) -> Alias {
+        self.tags = tags;
+        self
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// has_tags tells whether the alias carries all of the tags.
+    pub fn has_tags(&self, tags: &[String]) -> bool {
+        tags.iter().all(|tag| self.tags.contains(tag))
+    }
+
+    /// with_wildcards_expanded resolves the `{{ *::name }}` references of the alias,
+    /// its environment, question, messages and hooks among the known vars.
+    pub fn with_wildcards_expanded(mut self, known: &[Identifier]) -> Alias {
+        let namespace = self.name.namespace.clone();
+        let expand = |s: &str| Identifier::expand_wildcards(s, namespace.as_deref(), known);
+        self.alias = expand(&self.alias);
+        self.env = self
+            .env
+            .iter()
+            .map(|(key, value)| (key.clone(), expand(value)))
+            .collect();
+        if let Some(Confirm::Question(question)) = &self.confirm {
+            self.confirm = Some(Confirm::Question(expand(question)));
+        }
+        self.success_message = self.success_message.as_deref().map(expand);
+        self.failure_message = self.failure_message.as_deref().map(expand);
+        self.on_success = self.on_success.as_deref().map(expand);
+        self.on_failure = self.on_failure.as_deref().map(expand);
+        self
+    }
+
+    pub fn with_confirm(mut self, confirm: Option<Confirm>) -> Alias {
+        self.confirm = confirm;
+        self
+    }
+
+    /// question returns the yes/no question to ask before running the alias,
+    /// it can reference the vars of the alias. High impact aliases are confirmed
+    /// unless `confirm` is explicitly disabled, deprecated ones only with `confirm: true`.
+    pub fn question(&self) -> Option<String> {
+        match &self.confirm {
+            Some(Confirm::Enabled(true)) if self.deprecated.is_some() => Some(format!(
+                "{} is deprecated, run it anyway?",
+                self.full_name()
+            )),
+            Some(Confirm::Enabled(true)) => Some(format!("run {}?", self.full_name())),
+            Some(Confirm::Question(question)) => Some(question.clone()),
+            None if self.impact == Some(Impact::High) => Some(format!("run {}?", self.full_name())),
+            Some(Confirm::Enabled(false)) | None => None,
+        }
+    }
+
+    pub fn with_critical(mut self, critical: bool, confirmation_phrase: Option<String>) -> Alias {
+        self.critical = critical;
+        self.confirmation_phrase = confirmation_phrase;
+        self
+    }
+
+    /// confirmation returns the phrase that must be typed before running a critical alias.
+    pub fn confirmation(&self) -> Option<String> {
+        if self.critical {
+            let phrase = self.confirmation_phrase.clone();
+            Some(phrase.unwrap_or_else(|| self.full_name().to_string()))
+        } else {
+            None
+        }
+    }
+
+    pub fn with_owner(mut self, owner: Option<String>, contact: Option<String>) -> Alias {
+        self.owner = owner;
+        self.contact = contact;
+        self
+    }
+
+    pub fn with_env(mut self, env: HashMap<String, String>) -> Alias {
+        self.env = env;
+        self
     }
 
     pub fn update(&mut self, alias: String) {
@@ -57,18 +516,77 @@ impl Alias {
     pub fn alias(&self) -> &'_ str {
         self.alias.as_str()
     }
+    pub const fn env(&self) -> &HashMap<String, String> {
+        &self.env
+    }
+    pub fn owner(&self) -> Option<&str> {
+        self.owner.as_deref()
+    }
+    pub fn contact(&self) -> Option<&str> {
+        self.contact.as_deref()
+    }
+
+    /// maintainer returns a human readable description of who maintains the alias.
+    pub fn maintainer(&self) -> Option<String> {
+        match (self.owner(), self.contact()) {
+            (Some(owner), Some(contact)) => Some(format!("{} ({})", owner, contact)),
+            (Some(owner), None) => Some(owner.to_string()),
+            (None, Some(contact)) => Some(contact.to_string()),
+            (None, None) => None,
+        }
+    }
 
     pub fn with_choices(
         &self,
         choices: &HashMap<Identifier, Vec<Choice>>,
     ) -> Result<ResolvedAlias, ErrorsDependencies> {
         let res = self.substitute_for_choices(choices)?;
+        let first_choices: HashMap<Identifier, Choice> = choices
+            .iter()
+            .filter_map(|(id, c)| c.first().map(|c| (id.clone(), c.clone())))
+            .collect();
         Ok(ResolvedAlias {
             name: self.name.clone(),
             desc: self.desc.clone(),
             original_alias: self.alias.clone(),
             resolved_aliases: res,
             choices: choices.clone(),
+            env: self.env_with_partial_choices(&first_choices),
+            confirmation: self.confirmation(),
+            deprecated: self.deprecated.clone(),
+            question: self
+                .question()
+                .map(|q| substitute_choices_partial(&q, self.namespace(), &first_choices)),
+            success_message: self
+                .success_message
+                .as_ref()
+                .map(|m| substitute_choices_partial(m, self.namespace(), &first_choices)),
+            failure_message: self
+                .failure_message
+                .as_ref()
+                .map(|m| substitute_choices_partial(m, self.namespace(), &first_choices)),
+            on_success: self
+                .on_success
+                .as_ref()
+                .map(|c| substitute_choices_partial(c, self.namespace(), &first_choices)),
+            on_failure: self
+                .on_failure
+                .as_ref()
+                .map(|c| substitute_choices_partial(c, self.namespace(), &first_choices)),
+            export_choices: self.export_choices,
+            impact: self.impact,
+            estimated_duration: self.estimated_duration.clone(),
+            capture: self.capture,
+            stdin: self.stdin,
+            shell: self.shell.clone(),
+            login_shell: self.login_shell,
+            interactive: self.interactive,
+            record_output: self.record_output,
+            outputs: vec![],
+            transcript: vec![],
+            redacted: vec![],
+            resolution_ms: None,
+            sensitive: vec![],
         })
     }
 
@@ -79,12 +597,63 @@ impl Alias {
             name: self.name.clone(),
             desc: self.desc.clone(),
             alias: res,
+            steps: self.steps.clone(),
+            ignore_errors: self.ignore_errors,
+            env: self.env_with_partial_choices(choices),
+            owner: self.owner.clone(),
+            contact: self.contact.clone(),
+            critical: self.critical,
+            confirmation_phrase: self.confirmation_phrase.clone(),
+            confirm: self.confirm.clone(),
+            hidden: self.hidden,
+            deprecated: self.deprecated.clone(),
+            success_message: self.success_message.clone(),
+            failure_message: self.failure_message.clone(),
+            on_success: self.on_success.clone(),
+            on_failure: self.on_failure.clone(),
+            export_choices: self.export_choices,
+            prompt_order: self.prompt_order.clone(),
+            when_os: self.when_os.clone(),
+            enabled: self.enabled,
+            impact: self.impact,
+            estimated_duration: self.estimated_duration.clone(),
+            capture: self.capture,
+            stdin: self.stdin,
+            shell: self.shell.clone(),
+            login_shell: self.login_shell,
+            interactive: self.interactive,
+            record_output: self.record_output,
+            tags: self.tags.clone(),
         }
     }
 
+    // the environment can't be expanded for each choice the way commands are,
+    // when a var has multiple choices, the first one is used.
+    fn env_with_partial_choices(
+        &self,
+        choices: &HashMap<Identifier, Choice>,
+    ) -> HashMap<String, String> {
+        self.env
+            .iter()
+            .map(|(key, value)| {
+                let value = substitute_choices_partial(value, self.namespace(), choices);
+                (key.clone(), value)
+            })
+            .collect()
+    }
+
     pub fn sanitized_alias(&self) -> String {
         Self::sanitize(self.alias(), self.namespace().unwrap_or(""))
     }
+    /// sanitized_env returns the environment of the alias, the vars its values
+    /// refer to get the namespace of the alias.
+    pub fn sanitized_env(&self) -> HashMap<String, String> {
+        let namespace = self.namespace().unwrap_or("");
+        self.env
+            .iter()
+            .map(|(key, value)| (key.clone(), Self::sanitize(value, namespace)))
+            .collect()
+    }
     pub fn identifier(&self) -> Identifier {
         self.name.clone()
     }
@@ -100,7 +669,7 @@ impl Alias {
     }
 
     fn sanitize(alias_def: &str, namespace: &str) -> String {
-        let replace_pattern = format!("{{{{ {}::$vars }}}}", namespace);
+        let replace_pattern = format!("{{{{ {}::${{vars}}${{filters}} }}}}", namespace);
         VARS_NO_NS_RE
             .replace_all(alias_def, replace_pattern.as_str())
             .to_string()
@@ -129,12 +698,24 @@ impl Command for &Alias {
     fn command(&self) -> &str {
         self.alias.as_str()
     }
+    fn dependencies(&self) -> Vec<Identifier> {
+        (*self).dependencies()
+    }
 }
 
 impl Command for Alias {
     fn command(&self) -> &str {
         self.alias.as_str()
     }
+    // the vars used in the environment of an alias are dependencies as well.
+    // they come first so that they get resolved before the from_command vars
+    // that run with this environment. The parameters of the alias aren't vars.
+    fn dependencies(&self) -> Vec<Identifier> {
+        self.placeholders()
+            .into_iter()
+            .filter(|dep| dep.argument_position().is_none())
+            .collect()
+    }
 }
 
 impl Dependencies for &Alias {}
@@ -154,10 +735,81 @@ pub struct ResolvedAlias {
     original_alias: String,
     resolved_aliases: Vec<String>,
     choices: HashMap<Identifier, Vec<Choice>>,
+    #[serde(default)]
+    env: HashMap<String, String>,
+    #[serde(default)]
+    confirmation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deprecated: Option<String>,
+    #[serde(default)]
+    question: Option<String>,
+    #[serde(default)]
+    success_message: Option<String>,
+    #[serde(default)]
+    failure_message: Option<String>,
+    #[serde(default)]
+    on_success: Option<String>,
+    #[serde(default)]
+    on_failure: Option<String>,
+    #[serde(default)]
+    export_choices: Option<bool>,
+    #[serde(default)]
+    impact: Option<Impact>,
+    #[serde(default)]
+    estimated_duration: Option<String>,
+    #[serde(default)]
+    capture: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stdin: Option<StdinMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shell: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    login_shell: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    interactive: Option<bool>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    record_output: bool,
+    // the last lines printed by the commands, when the alias captures its output.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    outputs: Vec<String>,
+    // what the commands printed, stdout and stderr, when the output was recorded.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    transcript: Vec<String>,
+    // vars whose values were replaced by REDACTED before the alias was recorded.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    redacted: Vec<Identifier>,
+    // how long it took to resolve the vars of the alias, the user's picks included.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    resolution_ms: Option<u64>,
+    #[serde(skip)]
+    sensitive: Vec<Identifier>,
+}
+
+/// REDACTED replaces the choices of sensitive vars in previews, logs and the history.
+pub const REDACTED: &str = "********";
+
+/// mask replaces the values of the choices by REDACTED in a text, the longest values
+/// first so that a value containing another one is fully hidden.
+///```rust
+/// use sam_core::entities::aliases::mask;
+/// use sam_core::entities::choices::Choice;
+/// let choices = vec![Choice::from_value("s3cr3t"), Choice::from_value("")];
+/// assert_eq!(mask("token=s3cr3t", &choices), "token=********");
+///```
+pub fn mask<'a>(text: &str, choices: impl IntoIterator<Item = &'a Choice>) -> String {
+    let mut values: Vec<&str> = choices
+        .into_iter()
+        .map(Choice::value)
+        .filter(|v| !v.is_empty())
+        .collect();
+    values.sort_by_key(|v| std::cmp::Reverse(v.len()));
+    values.into_iter().fold(text.to_string(), |text, value| {
+        text.replace(value, REDACTED)
+    })
 }
 
 impl ResolvedAlias {
-    pub const fn new(
+    pub fn new(
         name: Identifier,
         desc: String,
         original_alias: String,
@@ -170,15 +822,168 @@ impl ResolvedAlias {
             original_alias,
             resolved_aliases,
             choices,
+            env: HashMap::new(),
+            confirmation: None,
+            deprecated: None,
+            question: None,
+            success_message: None,
+            failure_message: None,
+            on_success: None,
+            on_failure: None,
+            export_choices: None,
+            impact: None,
+            estimated_duration: None,
+            capture: false,
+            stdin: None,
+            shell: None,
+            login_shell: None,
+            interactive: None,
+            record_output: false,
+            outputs: vec![],
+            transcript: vec![],
+            redacted: vec![],
+            resolution_ms: None,
+            sensitive: vec![],
         }
     }
 
+    pub fn with_impact(
+        mut self,
+        impact: Option<Impact>,
+        estimated_duration: Option<String>,
+    ) -> Self {
+        self.impact = impact;
+        self.estimated_duration = estimated_duration;
+        self
+    }
+
+    /// annotation returns the impact and the estimated duration of the alias, if it has some.
+    pub fn annotation(&self) -> Option<String> {
+        annotation(self.impact, self.estimated_duration.as_deref())
+    }
+
+    pub fn with_hooks(mut self, on_success: Option<String>, on_failure: Option<String>) -> Self {
+        self.on_success = on_success;
+        self.on_failure = on_failure;
+        self
+    }
+
+    pub fn with_export_choices(mut self, export_choices: Option<bool>) -> Self {
+        self.export_choices = export_choices;
+        self
+    }
+
+    pub fn with_messages(mut self, success: Option<String>, failure: Option<String>) -> Self {
+        self.success_message = success;
+        self.failure_message = failure;
+        self
+    }
+
+    pub fn with_question(mut self, question: Option<String>) -> Self {
+        self.question = question;
+        self
+    }
+
+    pub fn with_confirmation(mut self, confirmation: Option<String>) -> Self {
+        self.confirmation = confirmation;
+        self
+    }
+
+    pub fn with_env(mut self, env: HashMap<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+
+    pub fn with_redacted(mut self, redacted: Vec<Identifier>) -> Self {
+        self.redacted = redacted;
+        self
+    }
+
+    pub fn with_capture(mut self, capture: bool) -> Self {
+        self.capture = capture;
+        self
+    }
+
+    pub const fn captures_output(&self) -> bool {
+        captures_output(self.capture, self.interactive)
+    }
+
+    pub fn with_interactive(mut self, interactive: Option<bool>) -> Self {
+        self.interactive = interactive;
+        self
+    }
+
+    /// interactive tells whether the commands get the terminal, or run as a batch, when the
+    /// alias decides it.
+    pub const fn interactive(&self) -> Option<bool> {
+        self.interactive
+    }
+
+    pub fn with_record_output(mut self, record_output: bool) -> Self {
+        self.record_output = record_output;
+        self
+    }
+
+    /// records_output tells whether what the commands print is kept in the history.
+    pub const fn records_output(&self) -> bool {
+        self.record_output
+    }
+
+    pub fn with_transcript(mut self, transcript: Vec<String>) -> Self {
+        self.transcript = transcript;
+        self
+    }
+
+    /// transcript returns the lines printed by the alias when it ran, if they were recorded.
+    pub fn transcript(&self) -> &[String] {
+        &self.transcript
+    }
+
+    pub fn with_stdin(mut self, stdin: Option<StdinMode>) -> Self {
+        self.stdin = stdin;
+        self
+    }
+
+    pub const fn stdin(&self) -> Option<StdinMode> {
+        self.stdin
+    }
+
+    pub fn with_shell(mut self, shell: Option<String>) -> Self {
+        self.shell = shell;
+        self
+    }
+
+    /// shell returns the shell the commands run with when the alias overrides it.
+    pub fn shell(&self) -> Option<&str> {
+        self.shell.as_deref()
+    }
+
+    pub fn with_login_shell(mut self, login_shell: Option<bool>) -> Self {
+        self.login_shell = login_shell;
+        self
+    }
+
+    /// login_shell tells whether the commands run in a login shell when the alias decides it.
+    pub const fn login_shell(&self) -> Option<bool> {
+        self.login_shell
+    }
+
+    pub fn with_outputs(mut self, outputs: Vec<String>) -> Self {
+        self.outputs = outputs;
+        self
+    }
+
+    /// outputs returns the lines printed by the alias when it ran, if it captures them.
+    pub fn outputs(&self) -> &[String] {
+        &self.outputs
+    }
+
     pub fn commands(&self) -> &[String] {
         self.resolved_aliases.as_slice()
     }
 
     pub fn choice(&self, identifier: &Identifier) -> Option<Vec<Choice>> {
-        self.choices.get(identifier).map(Clone::clone)
+        self.choices.get(identifier).cloned()
     }
 
     pub const fn name(&self) -> &Identifier {
@@ -198,6 +1003,132 @@ impl ResolvedAlias {
     pub fn resolved_alias(&self) -> &[String] {
         &self.resolved_aliases
     }
+    pub const fn env(&self) -> &HashMap<String, String> {
+        &self.env
+    }
+    /// confirmation returns the phrase to type before running the alias, if it's critical.
+    pub fn confirmation(&self) -> Option<&str> {
+        self.confirmation.as_deref()
+    }
+    /// question returns the yes/no question to ask before running the alias.
+    pub fn with_deprecated(mut self, hint: Option<String>) -> Self {
+        self.deprecated = hint;
+        self
+    }
+
+    /// deprecation returns the hint of a deprecated alias, what to use instead.
+    pub fn deprecation(&self) -> Option<&str> {
+        self.deprecated.as_deref()
+    }
+    pub fn question(&self) -> Option<&str> {
+        self.question.as_deref()
+    }
+    pub fn success_message(&self) -> Option<&str> {
+        self.success_message.as_deref()
+    }
+    pub fn failure_message(&self) -> Option<&str> {
+        self.failure_message.as_deref()
+    }
+    pub fn on_success(&self) -> Option<&str> {
+        self.on_success.as_deref()
+    }
+    pub fn on_failure(&self) -> Option<&str> {
+        self.on_failure.as_deref()
+    }
+    pub const fn export_choices(&self) -> Option<bool> {
+        self.export_choices
+    }
+    /// redacted returns the vars whose values were left out of the alias.
+    pub fn redacted(&self) -> &[Identifier] {
+        self.redacted.as_slice()
+    }
+
+    /// with_sensitive marks the vars whose choices are masked in logs and left out of the history.
+    pub fn with_sensitive(mut self, sensitive: Vec<Identifier>) -> Self {
+        self.sensitive = sensitive;
+        self
+    }
+
+    pub fn sensitive(&self) -> &[Identifier] {
+        self.sensitive.as_slice()
+    }
+
+    pub fn with_resolution_time(mut self, resolution_time: Duration) -> Self {
+        self.resolution_ms = Some(resolution_time.as_millis() as u64);
+        self
+    }
+
+    /// resolution_time returns how long the vars of the alias took to be resolved, entries
+    /// of the history recorded by older versions of sam don't have it.
+    pub fn resolution_time(&self) -> Option<Duration> {
+        self.resolution_ms.map(Duration::from_millis)
+    }
+
+    /// mask replaces the choices of the sensitive vars by REDACTED in a text.
+    pub fn mask(&self, text: &str) -> String {
+        let choices = self
+            .sensitive
+            .iter()
+            .filter_map(|id| self.choices.get(id))
+            .flatten();
+        mask(text, choices)
+    }
+
+    /// redact masks the choices of the sensitive vars in the commands, the environment
+    /// and the messages of the alias and drops them from its choices, before it's recorded.
+    pub fn redact(mut self) -> Self {
+        let mut masked_choices: Vec<Choice> = vec![];
+        for id in std::mem::take(&mut self.sensitive) {
+            if let Some(choices) = self.choices.remove(&id) {
+                masked_choices.extend(choices);
+                self.redacted.push(id);
+            }
+        }
+        let masked = |text: &mut String| *text = mask(text, &masked_choices);
+        self.resolved_aliases.iter_mut().for_each(masked);
+        self.env.values_mut().for_each(masked);
+        [
+            &mut self.confirmation,
+            &mut self.question,
+            &mut self.success_message,
+            &mut self.failure_message,
+            &mut self.on_success,
+            &mut self.on_failure,
+        ]
+        .into_iter()
+        .flatten()
+        .for_each(masked);
+        self
+    }
+
+    /// choices_env returns the choices as `SAM_CHOICE_<NS>_<NAME>` environment variables,
+    /// the values of vars with multiple choices are separated by new lines.
+    pub fn choices_env(&self) -> HashMap<String, String> {
+        if self.export_choices == Some(false) {
+            return HashMap::new();
+        }
+        self.choices
+            .iter()
+            .map(|(id, choices)| {
+                let name = match id.namespace() {
+                    Some(ns) => format!("SAM_CHOICE_{}_{}", ns, id.name()),
+                    None => format!("SAM_CHOICE_{}", id.name()),
+                };
+                let name: String = name
+                    .chars()
+                    .map(|c| {
+                        if c.is_ascii_alphanumeric() {
+                            c.to_ascii_uppercase()
+                        } else {
+                            '_'
+                        }
+                    })
+                    .collect();
+                let values: Vec<&str> = choices.iter().map(|c| c.value()).collect();
+                (name, values.join("\n"))
+            })
+            .collect()
+    }
 }
 
 impl From<ResolvedAlias> for Alias {
@@ -206,6 +1137,17 @@ impl From<ResolvedAlias> for Alias {
             name: r_alias.name,
             desc: r_alias.desc,
             alias: r_alias.original_alias,
+            env: r_alias.env,
+            critical: r_alias.confirmation.is_some(),
+            confirmation_phrase: r_alias.confirmation,
+            confirm: r_alias.question.map(Confirm::Question),
+            deprecated: r_alias.deprecated,
+            success_message: r_alias.success_message,
+            failure_message: r_alias.failure_message,
+            on_success: r_alias.on_success,
+            on_failure: r_alias.on_failure,
+            export_choices: r_alias.export_choices,
+            ..Default::default()
         }
     }
 }
@@ -234,6 +1176,14 @@ impl Display for ResolvedAlias {
             }
             writeln!(f)?;
         }
+        if !self.env.is_empty() {
+            writeln!(f, "\nEnvironment:",)?;
+            let mut env: Vec<_> = self.env.iter().collect();
+            env.sort();
+            for (key, value) in env {
+                writeln!(f, " - {}={}", key, value)?;
+            }
+        }
         writeln!(f, "\nExecuted commands:",)?;
         for cmd in &self.resolved_aliases {
             writeln!(f, " - {}", cmd)?;
@@ -243,7 +1193,7 @@ impl Display for ResolvedAlias {
 }
 
 #[allow(clippy::from_over_into)]
-impl<'a> Into<String> for &'a Alias {
+impl Into<String> for &Alias {
     fn into(self) -> String {
         format!("{} {}", &self.name, &self.desc)
     }
@@ -265,25 +1215,31 @@ pub mod fixtures {
             name: ALIAS_LS_DIR_NAME.clone(),
             desc: String::from("some desc"),
             alias: String::from("ls {{ directory }}"),
+            ..Default::default()
         };
         pub static ref ALIAS_GREP_DIR: Alias = Alias {
             name: ALIAS_GREP_DIR_NAME.clone(),
             desc: String::from("some desc"),
             alias: String::from("[[ dirs::list ]]|grep {{ pattern }}"),
+            ..Default::default()
         };
         pub static ref ALIAS_GREP_DIR_NO_NS: Alias = Alias {
             name: ALIAS_GREP_DIR_NAME.clone(),
             desc: String::from("some desc"),
             alias: String::from("[[ list ]]| grep {{ pattern }}"),
+            ..Default::default()
         };
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Alias;
+    use super::{Alias, Confirm, Impact, StdinMode};
+    use crate::entities::choices::Choice;
     use crate::entities::commands::Command;
+    use crate::entities::dependencies::ErrorsDependencies;
     use crate::entities::identifiers::Identifier;
+    use crate::entities::namespaces::NamespaceUpdater;
     #[test]
     fn vars() {
         let alias = Alias::new(
@@ -301,9 +1257,386 @@ mod tests {
         assert_eq!(expected_vars, vars);
     }
 
+    #[test]
+    fn env_dependencies_and_resolution() {
+        let alias =
+            Alias::new("deploy", "deploys", "deploy {{ app }}").with_env(maplit::hashmap! {
+                String::from("KUBECONFIG") => String::from("~/.kube/{{ cluster }}"),
+                String::from("APP") => String::from("{{ app }}"),
+            });
+        let vars: Vec<Identifier> = alias.dependencies();
+        assert_eq!(
+            vars,
+            vec![Identifier::new("app"), Identifier::new("cluster")]
+        );
+
+        let choices = maplit::hashmap! {
+            Identifier::new("app") => vec![Choice::new("front", None), Choice::new("back", None)],
+            Identifier::new("cluster") => vec![Choice::new("prod", None)],
+        };
+        let resolved = alias.with_choices(&choices).unwrap();
+        assert_eq!(resolved.commands(), &["deploy front", "deploy back"]);
+        assert_eq!(
+            resolved.env(),
+            &maplit::hashmap! {
+                String::from("KUBECONFIG") => String::from("~/.kube/prod"),
+                String::from("APP") => String::from("front"),
+            }
+        );
+    }
+
+    #[test]
+    fn maintainer() {
+        let alias = Alias::new("deploy", "deploys", "deploy");
+        assert_eq!(alias.maintainer(), None);
+        let alias = alias.with_owner(Some(String::from("platform team")), None);
+        assert_eq!(alias.maintainer(), Some(String::from("platform team")));
+        let alias = alias.with_owner(
+            Some(String::from("platform team")),
+            Some(String::from("#platform")),
+        );
+        assert_eq!(
+            alias.maintainer(),
+            Some(String::from("platform team (#platform)"))
+        );
+    }
+
+    #[test]
+    fn confirmation() {
+        let mut alias = Alias::new("drop", "drops the database", "dropdb {{ db }}");
+        NamespaceUpdater::update(&mut alias, "db");
+        assert_eq!(alias.confirmation(), None);
+        let alias = alias.with_critical(true, None);
+        assert_eq!(alias.confirmation(), Some(String::from("db::drop")));
+        let alias = alias.with_critical(true, Some(String::from("drop production")));
+        assert_eq!(alias.confirmation(), Some(String::from("drop production")));
+    }
+
+    #[test]
+    fn question() {
+        let mut alias = Alias::new("terminate", "terminates an instance", "terminate {{ id }}");
+        NamespaceUpdater::update(&mut alias, "aws");
+        assert_eq!(alias.question(), None);
+        let alias = alias.with_confirm(Some(Confirm::Enabled(true)));
+        assert_eq!(alias.question(), Some(String::from("run aws::terminate?")));
+        let alias = alias.with_confirm(Some(Confirm::Question(String::from("really?"))));
+        assert_eq!(alias.question(), Some(String::from("really?")));
+
+        let aliases: Vec<Alias> = serde_yaml::from_str(
+            "- name: a\n  desc: a\n  alias: a\n  confirm: true\n- name: b\n  desc: b\n  alias: b\n  confirm: are you sure?\n",
+        )
+        .unwrap();
+        assert_eq!(aliases[0].question(), Some(String::from("run a?")));
+        assert_eq!(aliases[1].question(), Some(String::from("are you sure?")));
+    }
+
+    #[test]
+    fn impact() {
+        let mut alias = Alias::new("failover", "fails the database over", "failover {{ db }}");
+        NamespaceUpdater::update(&mut alias, "db");
+        assert_eq!(alias.annotation(), None);
+        let alias = alias.with_impact(Some(Impact::Low), None);
+        assert_eq!(alias.annotation(), Some(String::from("low impact")));
+        assert_eq!(alias.question(), None);
+        let alias = alias.with_impact(Some(Impact::High), Some(String::from("10m")));
+        assert_eq!(
+            alias.annotation(),
+            Some(String::from("high impact, estimated duration: 10m"))
+        );
+        assert_eq!(alias.question(), Some(String::from("run db::failover?")));
+        let alias = alias.with_confirm(Some(Confirm::Enabled(false)));
+        assert_eq!(alias.question(), None);
+
+        let aliases: Vec<Alias> = serde_yaml::from_str(
+            "- name: a\n  desc: a\n  alias: a\n  impact: medium\n  estimated_duration: 2 hours\n",
+        )
+        .unwrap();
+        assert_eq!(aliases[0].impact(), Some(Impact::Medium));
+        assert_eq!(aliases[0].estimated_duration(), Some("2 hours"));
+    }
+
+    #[test]
+    fn messages() {
+        let mut alias = Alias::new("deploy", "deploys", "deploy {{ version }} {{ env }}")
+            .with_messages(
+                Some(String::from("Deployed {{ version }} to {{ env }}")),
+                Some(String::from("{{ version }} is not on {{ env }}")),
+            )
+            .with_hooks(Some(String::from("notify-send {{ version }}")), None);
+        NamespaceUpdater::update(&mut alias, "app");
+        let choices = maplit::hashmap! {
+            Identifier::with_namespace("version", Some("app")) => vec![Choice::new("1.2", None)],
+            Identifier::with_namespace("env", Some("app")) => vec![Choice::new("prod", None), Choice::new("staging", None)],
+        };
+        let resolved = alias.with_choices(&choices).unwrap();
+        assert_eq!(resolved.success_message(), Some("Deployed 1.2 to prod"));
+        assert_eq!(resolved.failure_message(), Some("1.2 is not on prod"));
+        assert_eq!(resolved.on_success(), Some("notify-send 1.2"));
+        assert_eq!(resolved.on_failure(), None);
+    }
+
+    #[test]
+    fn choices_env() {
+        let alias = Alias::new(
+            "deploy",
+            "deploys",
+            "deploy {{ version }} {{ app::target_env }}",
+        );
+        let choices = maplit::hashmap! {
+            Identifier::new("version") => vec![Choice::new("1.2", None)],
+            Identifier::with_namespace("target_env", Some("app")) => vec![Choice::new("prod", None), Choice::new("staging", None)],
+        };
+        let resolved = alias.with_choices(&choices).unwrap();
+        assert_eq!(
+            resolved.choices_env(),
+            maplit::hashmap! {
+                String::from("SAM_CHOICE_VERSION") => String::from("1.2"),
+                String::from("SAM_CHOICE_APP_TARGET_ENV") => String::from("prod\nstaging"),
+            }
+        );
+        let resolved = alias
+            .with_export_choices(Some(false))
+            .with_choices(&choices)
+            .unwrap();
+        assert!(resolved.choices_env().is_empty());
+    }
+
+    #[test]
+    fn wildcards() {
+        let mut alias = Alias::new(
+            "instances",
+            "lists instances",
+            "aws ec2 --region {{ *::region }}",
+        )
+        .with_env(maplit::hashmap! {
+            String::from("AWS_PROFILE") => String::from("{{ *::profile }}"),
+        });
+        NamespaceUpdater::update(&mut alias, "ec2");
+        let known = vec![
+            Identifier::with_namespace("region", Some("common")),
+            Identifier::with_namespace("profile", Some("ec2")),
+        ];
+        let alias = alias.with_wildcards_expanded(&known);
+        assert_eq!(alias.alias(), "aws ec2 --region {{ common::region }}");
+        assert_eq!(
+            alias.env().get("AWS_PROFILE"),
+            Some(&String::from("{{ ec2::profile }}"))
+        );
+        assert_eq!(
+            alias.dependencies(),
+            vec![
+                Identifier::with_namespace("profile", Some("ec2")),
+                Identifier::with_namespace("region", Some("common")),
+            ]
+        );
+    }
+
+    #[test]
+    fn hidden() {
+        let alias = Alias::new(
+            "kubeconfig",
+            "exports the kubeconfig",
+            "export KUBECONFIG=~/.kube",
+        );
+        assert!(!alias.is_hidden());
+        assert!(alias.with_hidden(true).is_hidden());
+
+        let aliases: Vec<Alias> =
+            serde_yaml::from_str("- name: a\n  desc: a\n  alias: a\n  hidden: true\n").unwrap();
+        assert!(aliases[0].is_hidden());
+    }
+
+    #[test]
+    fn deprecated() {
+        let aliases: Vec<Alias> = serde_yaml::from_str(
+            "- name: a\n  desc: a\n  alias: a\n  deprecated: use ns::b instead\n- name: c\n  desc: c\n  alias: c\n  deprecated: use ns::b instead\n  confirm: true\n",
+        )
+        .unwrap();
+        assert_eq!(aliases[0].deprecation(), Some("use ns::b instead"));
+        assert_eq!(aliases[0].question(), None);
+        assert_eq!(
+            aliases[1].question(),
+            Some(String::from("c is deprecated, run it anyway?"))
+        );
+        let resolved = aliases[0].with_choices(&maplit::hashmap! {}).unwrap();
+        assert_eq!(resolved.deprecation(), Some("use ns::b instead"));
+    }
+
+    #[test]
+    fn interactive() {
+        let aliases: Vec<Alias> = serde_yaml::from_str(
+            "- name: a\n  desc: a\n  alias: ssh {{ host }}\n  interactive: true\n  capture: true\n- name: b\n  desc: b\n  alias: b\n  interactive: false\n- name: c\n  desc: c\n  alias: c\n",
+        )
+        .unwrap();
+        assert_eq!(aliases[0].interactive(), Some(true));
+        assert!(!aliases[0].captures_output());
+        assert_eq!(aliases[1].interactive(), Some(false));
+        assert!(aliases[1].captures_output());
+        assert_eq!(aliases[2].interactive(), None);
+        assert!(!aliases[2].captures_output());
+        let resolved = aliases[1]
+            .with_choices(&std::collections::HashMap::new())
+            .unwrap();
+        assert_eq!(resolved.interactive(), Some(false));
+        assert!(resolved.captures_output());
+    }
+
+    #[test]
+    fn stdin() {
+        let aliases: Vec<Alias> = serde_yaml::from_str(
+            "- name: a\n  desc: a\n  alias: jq .\n  stdin: require\n- name: b\n  desc: b\n  alias: b\n",
+        )
+        .unwrap();
+        assert_eq!(aliases[0].stdin(), Some(StdinMode::Require));
+        assert_eq!(aliases[1].stdin(), None);
+        let resolved = aliases[0]
+            .with_choices(&std::collections::HashMap::new())
+            .unwrap();
+        assert_eq!(resolved.stdin(), Some(StdinMode::Require));
+        assert!(serde_yaml::from_str::<Vec<Alias>>(
+            "- name: a\n  desc: a\n  alias: a\n  stdin: always\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn shell() {
+        let aliases: Vec<Alias> = serde_yaml::from_str(
+            "- name: a\n  desc: a\n  alias: 'for f in *; echo $f; end'\n  shell: fish -c\n  login_shell: true\n- name: b\n  desc: b\n  alias: b\n",
+        )
+        .unwrap();
+        assert_eq!(aliases[0].shell(), Some("fish -c"));
+        assert_eq!(aliases[1].shell(), None);
+        assert_eq!(aliases[0].login_shell(), Some(true));
+        assert_eq!(aliases[1].login_shell(), None);
+        let resolved = aliases[0]
+            .with_choices(&std::collections::HashMap::new())
+            .unwrap();
+        assert_eq!(resolved.shell(), Some("fish -c"));
+        assert_eq!(resolved.login_shell(), Some(true));
+    }
+
+    #[test]
+    fn tags() {
+        let aliases: Vec<Alias> = serde_yaml::from_str(
+            "- name: a\n  desc: a\n  alias: a\n  tags: [k8s, prod]\n- name: b\n  desc: b\n  alias: b\n",
+        )
+        .unwrap();
+        assert_eq!(aliases[0].tags(), ["k8s", "prod"]);
+        assert!(aliases[0].has_tags(&[String::from("prod")]));
+        assert!(!aliases[0].has_tags(&[String::from("prod"), String::from("aws")]));
+        assert!(aliases[1].tags().is_empty());
+        assert!(aliases[1].has_tags(&[]));
+    }
+
+    #[test]
+    fn steps() {
+        let aliases: Vec<Alias> = serde_yaml::from_str(
+            "- name: release\n  desc: releases\n  steps: [build, ci::deploy]\n  ignore_errors: true\n",
+        )
+        .unwrap();
+        assert_eq!(aliases[0].alias(), "");
+        assert!(aliases[0].ignore_errors());
+        assert_eq!(
+            aliases[0].steps(),
+            vec![
+                Identifier::new("build"),
+                Identifier::with_namespace("deploy", Some("ci")),
+            ]
+        );
+    }
+
+    #[test]
+    fn prompt_order() {
+        let mut alias = Alias::new("pods", "lists pods", "kubectl get pods")
+            .with_prompt_order(vec![String::from("env"), String::from("common::cluster")]);
+        NamespaceUpdater::update(&mut alias, "k8s");
+        assert_eq!(
+            alias.prompt_order(),
+            vec![
+                Identifier::with_namespace("env", Some("k8s")),
+                Identifier::with_namespace("cluster", Some("common")),
+            ]
+        );
+    }
+
     #[test]
     fn sanitize() {
         let output = Alias::sanitize("{{ super }} no {{ ns::toto }}", "sup");
         assert_eq!("{{ sup::super }} no {{ ns::toto }}", output.as_str());
+        let output = Alias::sanitize("{{ super | upper }} {{super|trim|lower}}", "sup");
+        assert_eq!(
+            "{{ sup::super | upper }} {{ sup::super|trim|lower }}",
+            output.as_str()
+        );
+    }
+
+    #[test]
+    fn filters() {
+        let mut alias = Alias::new(
+            "upload",
+            "uploads a file",
+            "curl -T {{ file }} https://{{ *::env | lower }}.example.com/{{ file | basename | urlencode }}",
+        );
+        NamespaceUpdater::update(&mut alias, "s3");
+        let known = vec![Identifier::with_namespace("env", Some("common"))];
+        let alias = alias.with_wildcards_expanded(&known);
+        assert_eq!(
+            alias.dependencies(),
+            vec![
+                Identifier::with_namespace("file", Some("s3")),
+                Identifier::with_namespace("env", Some("common")),
+            ]
+        );
+        let choices = maplit::hashmap! {
+            Identifier::with_namespace("file", Some("s3")) => vec![Choice::new("/tmp/my report.pdf", None)],
+            Identifier::with_namespace("env", Some("common")) => vec![Choice::new("Staging", None)],
+        };
+        let resolved = alias
+            .with_choices(&choices)
+            .expect("can't resolve the alias");
+        assert_eq!(
+            resolved.commands(),
+            &[String::from(
+                "curl -T /tmp/my report.pdf https://staging.example.com/my%20report.pdf"
+            )]
+        );
+
+        let alias = Alias::new("upload", "uploads a file", "cp {{ file | capitalize }} .");
+        let choices = maplit::hashmap! {
+            Identifier::new("file") => vec![Choice::new("a", None)],
+        };
+        assert!(matches!(
+            alias.with_choices(&choices),
+            Err(ErrorsDependencies::Filter(_, _))
+        ));
+    }
+
+    #[test]
+    fn redact() {
+        let alias = Alias::new(
+            "login",
+            "logs in",
+            "curl -u {{ user }}:{{ token }} https://example.com",
+        )
+        .with_messages(Some(String::from("logged in with {{ token }}")), None);
+        let choices = maplit::hashmap! {
+            Identifier::new("user") => vec![Choice::new("bob", None)],
+            Identifier::new("token") => vec![Choice::new("s3cr3t", None)],
+        };
+        let resolved = alias
+            .with_choices(&choices)
+            .expect("can't resolve the alias")
+            .with_sensitive(vec![Identifier::new("token")]);
+        assert_eq!(resolved.mask("bob:s3cr3t"), String::from("bob:********"));
+        let resolved = resolved.redact();
+        assert_eq!(
+            resolved.commands(),
+            &[String::from("curl -u bob:******** https://example.com")]
+        );
+        assert_eq!(resolved.success_message(), Some("logged in with ********"));
+        assert_eq!(resolved.choice(&Identifier::new("token")), None);
+        assert!(resolved.choice(&Identifier::new("user")).is_some());
+        assert_eq!(resolved.redacted(), &[Identifier::new("token")]);
     }
 }
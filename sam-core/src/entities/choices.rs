@@ -1,9 +1,22 @@
+use lazy_static::lazy_static;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fmt::Display;
+use thiserror::Error;
+
+lazy_static! {
+    // matches the columns referenced in a choice_format template : {1}, {2}...
+    static ref COLUMN_RE: Regex = Regex::new("\\{(?P<column>[0-9]+)\\}").unwrap();
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, PartialOrd, Ord, Eq)]
 pub struct Choice {
     value: String,
     desc: Option<String>,
+    // what is displayed instead of the value when picking the choice.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    display: Option<String>,
 }
 
 impl Choice {
@@ -14,6 +27,7 @@ impl Choice {
         Choice {
             value: value.into(),
             desc: desc.map(String::from),
+            display: None,
         }
     }
     pub fn from_value<IntoStr>(value: IntoStr) -> Choice
@@ -23,14 +37,29 @@ impl Choice {
         Choice {
             value: value.into(),
             desc: None,
+            display: None,
         }
     }
+    pub fn with_display(mut self, display: Option<String>) -> Choice {
+        self.display = display;
+        self
+    }
+    /// with_value replaces the value of the choice, which keeps being displayed as before.
+    pub fn with_value(mut self, value: impl Into<String>) -> Choice {
+        self.display = Some(self.display().to_string());
+        self.value = value.into();
+        self
+    }
     pub fn value(&'_ self) -> &'_ str {
         self.value.as_str()
     }
     pub fn desc(&'_ self) -> Option<&'_ str> {
         self.desc.as_deref()
     }
+    /// display returns what is shown when picking the choice, its value by default.
+    pub fn display(&'_ self) -> &'_ str {
+        self.display.as_deref().unwrap_or(self.value())
+    }
 }
 
 impl Display for Choice {
@@ -38,3 +67,212 @@ impl Display for Choice {
         write!(f, "{}", self.value)
     }
 }
+
+/// ChoiceFormat tells how the lines printed by the `from_command` of a var are split
+/// into choices. Columns are numbered from 1, `value` is the column substituted in
+/// the alias while `display` and `desc` are templates referring to columns as `{n}`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChoiceFormat {
+    // a single space splits the line on runs of whitespaces.
+    #[serde(default = "ChoiceFormat::default_separator")]
+    separator: String,
+    #[serde(default = "ChoiceFormat::default_value")]
+    value: usize,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    display: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    desc: Option<String>,
+}
+
+impl ChoiceFormat {
+    pub fn new(
+        separator: impl Into<String>,
+        value: usize,
+        display: Option<String>,
+        desc: Option<String>,
+    ) -> Self {
+        ChoiceFormat {
+            separator: separator.into(),
+            value,
+            display,
+            desc,
+        }
+    }
+
+    fn default_separator() -> String {
+        String::from("\t")
+    }
+
+    const fn default_value() -> usize {
+        1
+    }
+
+    /// parse turns a line of output into a choice, lines without the value column are skipped.
+    ///```rust
+    /// use sam_core::entities::choices::{Choice, ChoiceFormat};
+    /// let format = ChoiceFormat::new(" ", 1, Some(String::from("{2}")), Some(String::from("{3}")));
+    /// let choice = format.parse("i-0a1b  web-1  running").unwrap();
+    /// assert_eq!(choice.value(), "i-0a1b");
+    /// assert_eq!(choice.display(), "web-1");
+    /// assert_eq!(choice.desc(), Some("running"));
+    /// assert_eq!(format.parse(""), None);
+    ///```
+    pub fn parse(&self, line: &str) -> Option<Choice> {
+        let columns: Vec<&str> = if self.separator == " " {
+            line.split_whitespace().collect()
+        } else {
+            line.split(self.separator.as_str()).collect()
+        };
+        let value = columns.get(self.value.checked_sub(1)?)?;
+        if value.is_empty() {
+            return None;
+        }
+        let render = |template: &String| {
+            COLUMN_RE
+                .replace_all(template, |caps: &regex::Captures| {
+                    caps["column"]
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|column| columns.get(column.checked_sub(1)?))
+                        .map(|column| column.to_string())
+                        .unwrap_or_default()
+                })
+                .to_string()
+        };
+        let desc = self.desc.as_ref().map(render).filter(|d| !d.is_empty());
+        let display = self.display.as_ref().map(render).filter(|d| !d.is_empty());
+        Some(Choice::new(value.to_string(), desc).with_display(display))
+    }
+}
+
+/// ChoiceFilter post-processes the choices of a var before they are offered. The choices
+/// whose value matches the regular expression `match` are kept, then the duplicated values
+/// are dropped with `unique`, they are sorted by value with `sort` and the first `limit`
+/// ones are kept.
+///```rust
+/// use sam_core::entities::choices::{Choice, ChoiceFilter};
+/// let filter = ChoiceFilter {
+///     pattern: Some(String::from("^prod-")),
+///     unique: Some(true),
+///     sort: Some(true),
+///     limit: Some(2),
+/// };
+/// let choices = ["prod-web", "dev-api", "prod-api", "prod-web", "prod-db"]
+///     .into_iter()
+///     .map(Choice::from_value)
+///     .collect();
+/// let values: Vec<String> = filter
+///     .apply(choices)
+///     .unwrap()
+///     .iter()
+///     .map(|c| c.value().to_string())
+///     .collect();
+/// assert_eq!(values, vec!["prod-api", "prod-db"]);
+///```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChoiceFilter {
+    #[serde(rename = "match", skip_serializing_if = "Option::is_none", default)]
+    pub pattern: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub unique: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sort: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub limit: Option<usize>,
+}
+
+impl ChoiceFilter {
+    /// check tells whether the pattern is a valid regular expression.
+    pub fn check(&self) -> Result<(), ErrorChoiceFilter> {
+        self.regex().map(|_| ())
+    }
+
+    pub fn apply(&self, choices: Vec<Choice>) -> Result<Vec<Choice>, ErrorChoiceFilter> {
+        let mut choices = match self.regex()? {
+            Some(re) => choices
+                .into_iter()
+                .filter(|c| re.is_match(c.value()))
+                .collect(),
+            None => choices,
+        };
+        if self.unique == Some(true) {
+            let mut seen = HashSet::new();
+            choices.retain(|c| seen.insert(c.value().to_string()));
+        }
+        if self.sort == Some(true) {
+            choices.sort_by(|a, b| a.value().cmp(b.value()));
+        }
+        if let Some(limit) = self.limit {
+            choices.truncate(limit);
+        }
+        Ok(choices)
+    }
+
+    fn regex(&self) -> Result<Option<Regex>, ErrorChoiceFilter> {
+        self.pattern
+            .as_ref()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|_| ErrorChoiceFilter::InvalidPattern(pattern.clone()))
+            })
+            .transpose()
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ErrorChoiceFilter {
+    #[error("the pattern {0} of the filter isn't a valid regular expression")]
+    InvalidPattern(String),
+}
+
+/// StructuredFormat is the format of the output of a `from_command` var that
+/// is parsed into choices instead of being read line by line.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StructuredFormat {
+    Json,
+    Yaml,
+}
+
+/// StructuredChoices tells how to extract choices from a structured output. `jsonpath`
+/// selects the items, `Reservations[*].Instances[*]` for example, and `value_field` and
+/// `desc_field` select the value and the description among the fields of each item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructuredChoices {
+    pub format: StructuredFormat,
+    pub jsonpath: Option<String>,
+    pub value_field: Option<String>,
+    pub desc_field: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Choice, ChoiceFormat};
+
+    #[test]
+    fn test_choice_format() {
+        let format = ChoiceFormat::new("\t", 1, None, Some(String::from("{2}")));
+        assert_eq!(
+            format.parse("prod\tthe production"),
+            Some(Choice::new("prod", Some("the production")))
+        );
+        assert_eq!(format.parse("prod"), Some(Choice::new("prod", None)));
+        assert_eq!(format.parse(""), None);
+
+        let format = ChoiceFormat::new(
+            ",",
+            3,
+            Some(String::from("{1} ({2})")),
+            Some(String::from("{4}")),
+        );
+        let choice = format
+            .parse("web-1,eu-west-1,i-0a1b")
+            .expect("can't parse the line");
+        assert_eq!(choice.value(), "i-0a1b");
+        assert_eq!(choice.display(), "web-1 (eu-west-1)");
+        assert_eq!(choice.desc(), None);
+        assert_eq!(format.parse("web-1,eu-west-1"), None);
+
+        let format = ChoiceFormat::new("\t", 0, None, None);
+        assert_eq!(format.parse("prod"), None);
+    }
+}
@@ -64,6 +64,8 @@ fn extract_programs_from_command(cmd: &str) -> Vec<String> {
     cmd.split("&&")
         .flat_map(|s| s.split("||"))
         .flat_map(|s| s.split('|'))
+        // the steps of a workflow are grouped in subshells
+        .map(|s| s.trim().trim_start_matches('(').trim_end_matches(')'))
         .chain(
             SUBCMD_RE
                 .captures_iter(cmd.as_str())
@@ -100,7 +102,7 @@ mod tests {
 
     #[test]
     fn test_unset_env_vars() {
-        let commands = vec![StringCommand::from_str("$SOME_CRAZY_ENV_VAR")];
+        let commands = [StringCommand::from_str("$SOME_CRAZY_ENV_VAR")];
         let unsets = unset_env_vars(commands.iter());
         assert_eq!(unsets.len(), 1);
         assert!(unsets.contains("SOME_CRAZY_ENV_VAR"));
@@ -114,7 +116,11 @@ mod tests {
         assert_eq!(
             vec! {"some_program", "grep", "yq", "some_text", "grep", "sub_cmd",},
             rslt
-        )
+        );
+
+        let rslt =
+            super::extract_programs_from_command("( kubectl get pods ) && ( ls -l | wc -l )");
+        assert_eq!(vec! {"kubectl", "ls", "wc"}, rslt)
     }
 
     struct StringCommand {
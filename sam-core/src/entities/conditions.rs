@@ -0,0 +1,79 @@
+use thiserror::Error;
+
+/// evaluate evaluates the condition of a var once the choices of its dependencies
+/// are substituted. A condition is made of comparisons, `a == b` or `a != b`, that
+/// can be combined with `&&` and `||`, `&&` binds tighter. Operands can be quoted.
+///```rust
+/// use sam_core::entities::conditions::evaluate;
+/// assert_eq!(evaluate("prod == 'prod'"), Ok(true));
+/// assert_eq!(evaluate("prod != prod || eu == eu"), Ok(true));
+/// assert_eq!(evaluate("prod == prod && eu == us"), Ok(false));
+///```
+pub fn evaluate(condition: &str) -> Result<bool, ErrorCondition> {
+    let mut any = false;
+    for alternative in condition.split("||") {
+        let mut all = true;
+        for comparison in alternative.split("&&") {
+            all &= compare(comparison)?;
+        }
+        any |= all;
+    }
+    Ok(any)
+}
+
+/// matches_os tells whether `os`, as named by `std::env::consts::OS`, is one of the
+/// operating systems of a `when_os` field, separated by `|`.
+///```rust
+/// use sam_core::entities::conditions::matches_os;
+/// assert!(matches_os("linux | macos", "macos"));
+/// assert!(!matches_os("linux", "windows"));
+///```
+pub fn matches_os(when_os: &str, os: &str) -> bool {
+    when_os.split('|').any(|candidate| candidate.trim() == os)
+}
+
+fn compare(comparison: &str) -> Result<bool, ErrorCondition> {
+    if let Some((lhs, rhs)) = comparison.split_once("!=") {
+        Ok(operand(lhs) != operand(rhs))
+    } else if let Some((lhs, rhs)) = comparison.split_once("==") {
+        Ok(operand(lhs) == operand(rhs))
+    } else {
+        Err(ErrorCondition::NotAComparison(
+            comparison.trim().to_string(),
+        ))
+    }
+}
+
+fn operand(s: &str) -> &str {
+    let s = s.trim();
+    for quote in ['\'', '"'] {
+        if s.len() >= 2 && s.starts_with(quote) && s.ends_with(quote) {
+            return &s[1..s.len() - 1];
+        }
+    }
+    s
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ErrorCondition {
+    #[error("'{0}' is not a comparison, expected 'a == b' or 'a != b'")]
+    NotAComparison(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate, ErrorCondition};
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(evaluate("prod == \"prod\""), Ok(true));
+        assert_eq!(evaluate(" 'staging' == prod "), Ok(false));
+        assert_eq!(evaluate("'' != ''"), Ok(false));
+        assert_eq!(evaluate("a == b || a == a && b == b"), Ok(true));
+        assert_eq!(evaluate("a == a && b == c || c == d"), Ok(false));
+        assert_eq!(
+            evaluate("prod"),
+            Err(ErrorCondition::NotAComparison(String::from("prod")))
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use crate::entities::choices::Choice;
 use crate::entities::commands::Command;
+use crate::entities::filters::{self, ErrorFilter, FILTERS_RE};
 use crate::entities::identifiers::Identifier;
 use regex::Regex;
 use std::collections::HashMap;
@@ -10,46 +11,91 @@ pub trait Dependencies: Command {
         &self,
         choices: &HashMap<Identifier, Vec<Choice>>,
     ) -> Result<Vec<String>, ErrorsDependencies> {
-        let mut command = vec![self.command().to_string()];
-        for dep in self.dependencies() {
-            let mut new_commands = Vec::with_capacity(command.len());
-            if let Some(choices_for_dep) = choices.get(&dep) {
-                for choice in choices_for_dep {
-                    let out = command
-                        .iter()
-                        .map(|cmd| substitute_choice(cmd, &dep, choice.value()));
-                    new_commands.extend(out);
+        substitute_choices(self.command(), self.namespace(), choices)
+    }
+
+    fn substitute_for_choices_partial(&self, choices: &HashMap<Identifier, Choice>) -> String {
+        substitute_choices_partial(self.command(), self.namespace(), choices)
+    }
+}
+
+/// substitute_choices returns the template once for each combination of the choices
+/// of the vars it uses.
+pub fn substitute_choices(
+    template: &str,
+    namespace: Option<&str>,
+    choices: &HashMap<Identifier, Vec<Choice>>,
+) -> Result<Vec<String>, ErrorsDependencies> {
+    let mut command = vec![template.to_string()];
+    // only the vars used in the command multiply it, dependencies can come
+    // from elsewhere, like the environment of an alias.
+    for dep in Identifier::parse(template, namespace) {
+        let mut new_commands = Vec::with_capacity(command.len());
+        if let Some(choices_for_dep) = choices.get(&dep) {
+            for choice in choices_for_dep {
+                for cmd in command.iter() {
+                    let out = substitute_choice(cmd, &dep, choice.value())
+                        .map_err(|err| ErrorsDependencies::Filter(dep.clone(), err))?;
+                    new_commands.push(out);
                 }
-            } else {
-                return Err(ErrorsDependencies::MissingChoicesForVar(dep));
             }
-            command = new_commands;
+        } else {
+            return Err(ErrorsDependencies::MissingChoicesForVar(dep));
         }
-        Ok(command)
+        command = new_commands;
     }
+    Ok(command)
+}
 
-    fn substitute_for_choices_partial(&self, choices: &HashMap<Identifier, Choice>) -> String {
-        let mut command = self.command().to_string();
-        for dep in self.dependencies() {
-            if let Some(chce) = choices.get(&dep) {
-                command = substitute_choice(&command, &dep, chce.value());
-            }
+pub fn substitute_choices_partial(
+    template: &str,
+    namespace: Option<&str>,
+    choices: &HashMap<Identifier, Choice>,
+) -> String {
+    let mut command = template.to_string();
+    for dep in Identifier::parse(template, namespace) {
+        // placeholders with unknown filters are left as is, they are reported
+        // once the choices are substituted for good.
+        if let Some(Ok(cmd)) = choices
+            .get(&dep)
+            .map(|chce| substitute_choice(&command, &dep, chce.value()))
+        {
+            command = cmd;
         }
-        command
     }
+    command
 }
 
-fn substitute_choice(origin: &str, dependency: &Identifier, choice: &str) -> String {
-    let re_fmt = format!(r#"(?P<var>\{{\{{ ?{} ?\}}\}})"#, dependency.name());
+fn substitute_choice(
+    origin: &str,
+    dependency: &Identifier,
+    choice: &str,
+) -> Result<String, ErrorFilter> {
+    let re_fmt = format!(
+        r#"(?P<var>\{{\{{ ?{}(?P<filters>{}) ?\}}\}})"#,
+        dependency.name(),
+        FILTERS_RE
+    );
     let re2_fmt = format!(
-        r#"(?P<var>\{{\{{ ?{}::{} ?\}}\}})"#,
+        r#"(?P<var>\{{\{{ ?{}::{}(?P<filters>{}) ?\}}\}})"#,
         dependency.namespace.clone().unwrap_or_default(),
-        dependency.name()
+        dependency.name(),
+        FILTERS_RE
     );
     let re: Regex = Regex::new(re_fmt.as_str()).unwrap();
     let re2: Regex = Regex::new(re2_fmt.as_str()).unwrap();
-    let tmp = re.replace(origin, choice).to_string();
-    re2.replace(&tmp, choice).to_string()
+    let tmp = replace_filtered(&re, origin, choice)?;
+    replace_filtered(&re2, &tmp, choice)
+}
+
+fn replace_filtered(re: &Regex, origin: &str, choice: &str) -> Result<String, ErrorFilter> {
+    match re.captures(origin) {
+        Some(caps) => {
+            let value = filters::apply(choice, &caps["filters"])?;
+            Ok(re.replace(origin, value.as_str()).to_string())
+        }
+        None => Ok(origin.to_string()),
+    }
 }
 
 #[derive(Debug)]
@@ -86,4 +132,6 @@ impl AsRef<[Identifier]> for ExecutionSequence {
 pub enum ErrorsDependencies {
     #[error("no choice is available for var {0}")]
     MissingChoicesForVar(Identifier),
+    #[error("can't substitute the choice of var {0} because\n-> {1}")]
+    Filter(Identifier, ErrorFilter),
 }
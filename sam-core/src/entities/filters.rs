@@ -0,0 +1,89 @@
+use std::path::Path;
+use thiserror::Error;
+
+/// FILTERS_RE is the pattern of the filters that can follow the name of a var
+/// in a template, `{{ file | basename | upper }}`.
+pub const FILTERS_RE: &str = "(?: ?\\| ?[a-zA-Z0-9_]+)*";
+
+/// apply runs the filters that followed the name of a var, from left to right,
+/// on the choice substituted for it.
+///```rust
+/// use sam_core::entities::filters::apply;
+/// assert_eq!(apply("/tmp/report.csv", " | basename | upper"), Ok(String::from("REPORT.CSV")));
+/// assert_eq!(apply(" a b ", "|trim|urlencode"), Ok(String::from("a%20b")));
+/// assert_eq!(apply("x", ""), Ok(String::from("x")));
+/// assert_eq!(apply("it's", "| quote"), Ok(String::from("'it'\\''s'")));
+///```
+pub fn apply(value: &str, filters: &str) -> Result<String, ErrorFilter> {
+    let mut value = value.to_string();
+    for filter in filters.split('|').map(str::trim).filter(|f| !f.is_empty()) {
+        value = match filter {
+            "upper" => value.to_uppercase(),
+            "lower" => value.to_lowercase(),
+            "trim" => value.trim().to_string(),
+            "basename" => basename(&value),
+            "urlencode" => urlencode(&value),
+            "quote" => quote(&value),
+            _ => return Err(ErrorFilter::UnknownFilter(filter.to_string())),
+        };
+    }
+    Ok(value)
+}
+
+fn basename(value: &str) -> String {
+    Path::new(value)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| value.to_string())
+}
+
+// quote makes a single shell word of the value, whatever it holds.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn urlencode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ErrorFilter {
+    #[error(
+        "unknown filter '{0}', expected one of upper, lower, trim, basename, urlencode or quote"
+    )]
+    UnknownFilter(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply, ErrorFilter};
+
+    #[test]
+    fn test_apply() {
+        assert_eq!(apply("Prod", " | lower"), Ok(String::from("prod")));
+        assert_eq!(apply("prod", "| upper"), Ok(String::from("PROD")));
+        assert_eq!(apply("  prod\n", " | trim"), Ok(String::from("prod")));
+        assert_eq!(apply("/var/log/", " | basename"), Ok(String::from("log")));
+        assert_eq!(apply("/", " | basename"), Ok(String::from("/")));
+        assert_eq!(
+            apply("a&b=c/é", " | urlencode"),
+            Ok(String::from("a%26b%3Dc%2F%C3%A9"))
+        );
+        assert_eq!(
+            apply("a 'b'\nc", " | quote"),
+            Ok(String::from("'a '\\''b'\\''\nc'"))
+        );
+        assert_eq!(
+            apply("prod", " | upper | capitalize"),
+            Err(ErrorFilter::UnknownFilter(String::from("capitalize")))
+        );
+    }
+}
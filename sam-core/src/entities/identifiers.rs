@@ -1,3 +1,4 @@
+use crate::entities::filters::FILTERS_RE;
 use crate::entities::namespaces::{Namespace, NamespaceUpdater};
 use lazy_static::lazy_static;
 use regex::Regex;
@@ -5,12 +6,18 @@ use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 use std::hash::Hash;
 
+const ARGUMENT_PREFIX: &str = "arg:";
+
 lazy_static! {
     // matches the following patters :
     // - {{ some_name_1 }}
     // - {{some_name_1 }}
     // - {{ some_name_1}}
-    static ref VARSRE: Regex = Regex::new("(?P<vars>\\{\\{ ?[a-zA-Z0-9_:]+ ?\\}\\})").unwrap();
+    // - {{ some_name_1 | upper }}
+    static ref VARSRE: Regex = Regex::new(&format!("\\{{\\{{ ?(?P<vars>[a-zA-Z0-9_:]+){} ?\\}}\\}}", FILTERS_RE)).unwrap();
+    // matches references to a var from any namespace :
+    // - {{ *::some_name_1 }}
+    static ref WILDCARD_RE: Regex = Regex::new(&format!("\\{{\\{{ ?\\*::(?P<name>[a-zA-Z0-9_]+)(?P<filters>{}) ?\\}}\\}}", FILTERS_RE)).unwrap();
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Ord, PartialOrd)]
@@ -119,6 +126,77 @@ impl Identifier {
         }
         (s, None)
     }
+    /// resolve_wildcard finds the var that `{{ *::name }}` refers to among the known vars.
+    /// The var from the given namespace is preferred, then the var without a namespace,
+    /// then the var from the first namespace in alphabetical order.
+    ///```rust
+    /// use sam_core::entities::identifiers::Identifier;
+    /// let known = vec![
+    ///     Identifier::with_namespace("region", Some("project")),
+    ///     Identifier::with_namespace("region", Some("common")),
+    /// ];
+    /// let region = Identifier::resolve_wildcard("region", Some("project"), &known);
+    /// assert_eq!(region, Some(Identifier::with_namespace("region", Some("project"))));
+    /// let region = Identifier::resolve_wildcard("region", Some("docker"), &known);
+    /// assert_eq!(region, Some(Identifier::with_namespace("region", Some("common"))));
+    ///```
+    pub fn resolve_wildcard(
+        name: &str,
+        namespace: Option<&str>,
+        known: &[Identifier],
+    ) -> Option<Identifier> {
+        let mut candidates: Vec<&Identifier> =
+            known.iter().filter(|id| id.name() == name).collect();
+        candidates.sort_by_key(|id| {
+            (
+                id.namespace() != namespace,
+                id.namespace().is_some(),
+                id.namespace(),
+            )
+        });
+        candidates.first().map(|id| (*id).clone())
+    }
+
+    /// expand_wildcards replaces the `{{ *::name }}` references of a template with the var
+    /// they resolve to, see `resolve_wildcard`. References that don't resolve to any known
+    /// var are left to the given namespace.
+    pub fn expand_wildcards(
+        template: &str,
+        namespace: Option<&str>,
+        known: &[Identifier],
+    ) -> String {
+        WILDCARD_RE
+            .replace_all(template, |caps: &regex::Captures| {
+                let name = &caps["name"];
+                let id = Self::resolve_wildcard(name, namespace, known)
+                    .unwrap_or_else(|| Identifier::with_namespace(name, namespace));
+                format!("{{{{ {}{} }}}}", id, &caps["filters"])
+            })
+            .to_string()
+    }
+
+    /// argument is the parameter `{{ arg:position }}` of an alias, it takes the argument at this
+    /// position, starting at 1, among the ones given after `--` on the command line.
+    pub fn argument(position: usize) -> Identifier {
+        Identifier::new(format!("{}{}", ARGUMENT_PREFIX, position))
+    }
+
+    /// argument_position returns the position of the command line argument the identifier
+    /// refers to, if it's the parameter of an alias rather than a var.
+    ///```rust
+    /// use sam_core::entities::identifiers::Identifier;
+    /// assert_eq!(Identifier::from_str("k8s::arg:2").argument_position(), Some(2));
+    /// assert_eq!(Identifier::from_str("arg:0").argument_position(), None);
+    /// assert_eq!(Identifier::from_str("k8s::env").argument_position(), None);
+    ///```
+    pub fn argument_position(&self) -> Option<usize> {
+        self.inner
+            .strip_prefix(ARGUMENT_PREFIX)?
+            .parse()
+            .ok()
+            .filter(|position| *position > 0)
+    }
+
     fn sanitize_identifier(s: String) -> String {
         s.replace("{ ", "{")
             .replace(" }", "}")
@@ -207,6 +285,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_expand_wildcards() {
+        let known = vec![
+            Identifier::with_namespace("region", Some("common")),
+            Identifier::with_namespace("region", Some("aws")),
+            Identifier::new("profile"),
+            Identifier::with_namespace("profile", Some("aws")),
+        ];
+        assert_eq!(
+            Identifier::expand_wildcards(
+                "aws --region {{ *::region }} --profile {{*::profile}} {{ zone }}",
+                Some("ec2"),
+                &known
+            ),
+            "aws --region {{ aws::region }} --profile {{ profile }} {{ zone }}"
+        );
+        assert_eq!(
+            Identifier::expand_wildcards("{{ *::region }} {{ *::zone }}", Some("common"), &known),
+            "{{ common::region }} {{ common::zone }}"
+        );
+    }
+
     #[test]
     fn test_identifier_from_str() {
         assert_eq!(
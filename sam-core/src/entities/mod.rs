@@ -1,7 +1,9 @@
 pub mod aliases;
 pub mod choices;
 pub mod commands;
+pub mod conditions;
 pub mod dependencies;
+pub mod filters;
 pub mod identifiers;
 pub mod namespaces;
 pub mod vars;
@@ -1,3 +1,5 @@
+use crate::entities::identifiers::Identifier;
+use std::collections::HashMap;
 use std::path::Path;
 pub trait Namespace {
     fn namespace(&self) -> Option<&str>;
@@ -14,3 +16,39 @@ pub trait NamespaceUpdater {
         namespace.map(|ns| self.update(ns))
     }
 }
+
+/// NamespaceAliases are other names of the namespaces, `k8s = "kubernetes"` lets the aliases
+/// and vars of the kubernetes namespace be addressed as `k8s::name`. Keeping the old name of a
+/// namespace that was renamed as an alias keeps the references to it working.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NamespaceAliases(HashMap<String, String>);
+
+impl NamespaceAliases {
+    pub fn new(aliases: HashMap<String, String>) -> Self {
+        NamespaceAliases(aliases)
+    }
+
+    /// resolve returns the identifier in the namespace its namespace is an alias of,
+    /// or the identifier itself.
+    ///```rust
+    /// use sam_core::entities::identifiers::Identifier;
+    /// use sam_core::entities::namespaces::NamespaceAliases;
+    /// let aliases = NamespaceAliases::new(maplit::hashmap! {
+    ///     String::from("k8s") => String::from("kubernetes"),
+    /// });
+    /// let id = aliases.resolve(&Identifier::with_namespace("pods", Some("k8s")));
+    /// assert_eq!(id, Identifier::with_namespace("pods", Some("kubernetes")));
+    /// let id = aliases.resolve(&Identifier::with_namespace("pods", Some("kubernetes")));
+    /// assert_eq!(id, Identifier::with_namespace("pods", Some("kubernetes")));
+    ///```
+    pub fn resolve(&self, id: &Identifier) -> Identifier {
+        match id.namespace.as_ref().and_then(|ns| self.0.get(ns)) {
+            Some(namespace) => Identifier::with_namespace(id.name(), Some(namespace.as_str())),
+            None => id.clone(),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.0.iter()
+    }
+}
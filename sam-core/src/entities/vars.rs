@@ -1,11 +1,18 @@
-use crate::entities::choices::Choice;
+use crate::entities::choices::{
+    Choice, ChoiceFilter, ChoiceFormat, StructuredChoices, StructuredFormat,
+};
 use crate::entities::commands::Command;
+use crate::entities::conditions::matches_os;
 use crate::entities::dependencies::Dependencies;
 use crate::entities::identifiers::Identifier;
 use crate::entities::namespaces::{Namespace, NamespaceUpdater};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::borrow::Borrow;
+use std::collections::HashMap;
 use std::hash::Hash;
+use std::time::Duration;
+use thiserror::Error;
 
 // Var represent a variable with a command that can be used in an crate::core:Alias.
 // Var can be static when choices is not empty or dyamic whenthe from_command is not empty
@@ -20,6 +27,105 @@ pub struct Var {
     from_command: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     from_input: Option<String>,
+    // the secret the var resolves to, `<backend>:<reference>`, secrets are
+    // never cached nor recorded in the history.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    from_secret: Option<String>,
+    // the alias whose captured outputs are offered as choices, `ns::alias`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    from_history: Option<String>,
+    // the http endpoint whose response is split into choices like the output of from_command,
+    // with the headers, whose values can refer to environment variables, `Bearer $TOKEN`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    from_url: Option<String>,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    headers: HashMap<String, String>,
+    // the executable, with its arguments, that is asked for the choices of the var,
+    // `my-resolver --team {{ team }}`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    from_plugin: Option<String>,
+    // the template the choices of other vars are substituted in to compute the value of the
+    // var, without running a command nor asking for anything, `{{ cluster }}-{{ env }}`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    from_template: Option<String>,
+    // the directory whose entries matching `glob` are offered as choices, `**` in the glob
+    // walks the subdirectories. `dirs_only` keeps the directories, `hidden` the dotfiles.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    from_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    glob: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    dirs_only: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    hidden: Option<bool>,
+    // the command the selected choices go through before they are substituted, their
+    // value is written to its stdin and replaced by what it prints, `cut -d' ' -f1`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    map_command: Option<String>,
+    // the command whose output is shown in the preview of the highlighted choice, which is
+    // substituted for `{{ value }}`, `kubectl describe pod {{ value }}`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    preview_command: Option<String>,
+    // the choice used with `--use-defaults` instead of asking for one, it's highlighted
+    // first in the picker otherwise.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    default: Option<String>,
+    // the rules the input of a from_input var has to follow, it's asked for again otherwise.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pattern: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    max: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    allow_empty: Option<bool>,
+    // the input of a from_input var is written in $EDITOR rather than on a single line.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    editor: Option<bool>,
+    // the choices of a sensitive var are masked in previews, logs and the history.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    sensitive: Option<bool>,
+    // the time in seconds for which the output of from_command is cached,
+    // overrides the ttl from the configuration.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    cache_ttl: Option<u64>,
+    // set to false to never cache the output of from_command.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    cache: Option<bool>,
+    // the time in seconds after which from_command is killed, and how many times it runs
+    // again, waiting longer each time, when it fails or times out.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    timeout_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    retries: Option<u32>,
+    // the var is only resolved when this condition on the choices of other vars holds,
+    // otherwise its value is `otherwise`, or empty.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    when: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    otherwise: Option<String>,
+    // how the lines printed by from_command are split into choices.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    choice_format: Option<ChoiceFormat>,
+    // how the choices are filtered, deduplicated, sorted and limited before they are offered.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    filter: Option<ChoiceFilter>,
+    // the operating systems the var is loaded on, separated by `|`, all of them by default.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    when_os: Option<String>,
+    // disabled vars are left out, recipes can keep deprecated vars around.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    enabled: Option<bool>,
+    // the output of from_command is parsed as json or yaml and choices are
+    // extracted with jsonpath, value_field and desc_field.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    parse: Option<StructuredFormat>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    jsonpath: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    value_field: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    desc_field: Option<String>,
 }
 
 impl Var {
@@ -34,6 +140,39 @@ impl Var {
             choices,
             from_command: None,
             from_input: None,
+            from_secret: None,
+            from_history: None,
+            from_url: None,
+            headers: HashMap::new(),
+            from_plugin: None,
+            from_template: None,
+            from_path: None,
+            glob: None,
+            dirs_only: None,
+            hidden: None,
+            map_command: None,
+            preview_command: None,
+            default: None,
+            pattern: None,
+            min: None,
+            max: None,
+            allow_empty: None,
+            editor: None,
+            sensitive: None,
+            cache_ttl: None,
+            cache: None,
+            timeout_secs: None,
+            retries: None,
+            when: None,
+            otherwise: None,
+            choice_format: None,
+            filter: None,
+            when_os: None,
+            enabled: None,
+            parse: None,
+            jsonpath: None,
+            value_field: None,
+            desc_field: None,
         }
     }
 
@@ -49,6 +188,39 @@ impl Var {
             choices: vec![],
             from_command: Some(from_command.into()),
             from_input: None,
+            from_secret: None,
+            from_history: None,
+            from_url: None,
+            headers: HashMap::new(),
+            from_plugin: None,
+            from_template: None,
+            from_path: None,
+            glob: None,
+            dirs_only: None,
+            hidden: None,
+            map_command: None,
+            preview_command: None,
+            default: None,
+            pattern: None,
+            min: None,
+            max: None,
+            allow_empty: None,
+            editor: None,
+            sensitive: None,
+            cache_ttl: None,
+            cache: None,
+            timeout_secs: None,
+            retries: None,
+            when: None,
+            otherwise: None,
+            choice_format: None,
+            filter: None,
+            when_os: None,
+            enabled: None,
+            parse: None,
+            jsonpath: None,
+            value_field: None,
+            desc_field: None,
         }
     }
 
@@ -62,21 +234,384 @@ impl Var {
             choices: vec![],
             from_command: None,
             from_input: Some(from_input.into()),
+            from_secret: None,
+            from_history: None,
+            from_url: None,
+            headers: HashMap::new(),
+            from_plugin: None,
+            from_template: None,
+            from_path: None,
+            glob: None,
+            dirs_only: None,
+            hidden: None,
+            map_command: None,
+            preview_command: None,
+            default: None,
+            pattern: None,
+            min: None,
+            max: None,
+            allow_empty: None,
+            editor: None,
+            sensitive: None,
+            cache_ttl: None,
+            cache: None,
+            timeout_secs: None,
+            retries: None,
+            when: None,
+            otherwise: None,
+            choice_format: None,
+            filter: None,
+            when_os: None,
+            enabled: None,
+            parse: None,
+            jsonpath: None,
+            value_field: None,
+            desc_field: None,
+        }
+    }
+
+    /// from_secret creates a new var that resolves to the secret the reference points to,
+    /// `env:GITHUB_TOKEN` or `pass:work/github` for example.
+    pub fn from_secret<IntoStr>(name: IntoStr, desc: IntoStr, reference: IntoStr) -> Var
+    where
+        IntoStr: Into<String>,
+    {
+        Var {
+            name: Identifier::new(name),
+            desc: desc.into(),
+            from_secret: Some(reference.into()),
+            ..Var::default()
+        }
+    }
+
+    /// from_history creates a new var whose choices are the lines printed by the past
+    /// runs of an alias that captures its output.
+    pub fn from_history<IntoStr>(name: IntoStr, desc: IntoStr, alias: IntoStr) -> Var
+    where
+        IntoStr: Into<String>,
+    {
+        Var {
+            name: Identifier::new(name),
+            desc: desc.into(),
+            from_history: Some(alias.into()),
+            ..Var::default()
+        }
+    }
+
+    /// from_url creates a new var whose choices are read from the response of an http endpoint.
+    pub fn from_url<IntoStr>(name: IntoStr, desc: IntoStr, url: IntoStr) -> Var
+    where
+        IntoStr: Into<String>,
+    {
+        Var {
+            name: Identifier::new(name),
+            desc: desc.into(),
+            from_url: Some(url.into()),
+            ..Var::default()
+        }
+    }
+
+    /// from_plugin creates a new var whose choices are given by an executable.
+    pub fn from_plugin<IntoStr>(name: IntoStr, desc: IntoStr, plugin: IntoStr) -> Var
+    where
+        IntoStr: Into<String>,
+    {
+        Var {
+            name: Identifier::new(name),
+            desc: desc.into(),
+            from_plugin: Some(plugin.into()),
+            ..Var::default()
         }
     }
 
+    /// from_template creates a new var whose value is computed from the choices of other vars.
+    pub fn from_template<IntoStr>(name: IntoStr, desc: IntoStr, template: IntoStr) -> Var
+    where
+        IntoStr: Into<String>,
+    {
+        Var {
+            name: Identifier::new(name),
+            desc: desc.into(),
+            from_template: Some(template.into()),
+            ..Var::default()
+        }
+    }
+
+    /// from_path creates a new var whose choices are the entries of a directory.
+    pub fn from_path<IntoStr>(name: IntoStr, desc: IntoStr, directory: IntoStr) -> Var
+    where
+        IntoStr: Into<String>,
+    {
+        Var {
+            name: Identifier::new(name),
+            desc: desc.into(),
+            from_path: Some(directory.into()),
+            ..Var::default()
+        }
+    }
+
+    pub fn with_map_command(mut self, map_command: Option<String>) -> Var {
+        self.map_command = map_command;
+        self
+    }
+
+    /// map_command returns the command that turns the selected choices into the values
+    /// that are substituted.
+    pub fn map_command(&self) -> Option<&str> {
+        self.map_command.as_deref()
+    }
+
+    pub fn with_preview_command(mut self, preview_command: Option<String>) -> Var {
+        self.preview_command = preview_command;
+        self
+    }
+
+    /// preview_command returns the command showing more about the highlighted choice.
+    pub fn preview_command(&self) -> Option<&str> {
+        self.preview_command.as_deref()
+    }
+
+    pub fn with_default(mut self, default: Option<String>) -> Var {
+        self.default = default;
+        self
+    }
+
+    /// default_choice returns the choice recipes suggest for the var.
+    pub fn default_choice(&self) -> Option<Choice> {
+        self.default.as_deref().map(Choice::from_value)
+    }
+
+    pub fn with_headers(mut self, headers: HashMap<String, String>) -> Var {
+        self.headers = headers;
+        self
+    }
+
+    pub fn with_cache(mut self, cache: Option<bool>, cache_ttl: Option<u64>) -> Var {
+        self.cache = cache;
+        self.cache_ttl = cache_ttl;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout_secs: Option<u64>, retries: Option<u32>) -> Var {
+        self.timeout_secs = timeout_secs;
+        self.retries = retries;
+        self
+    }
+
+    pub fn with_condition(mut self, when: Option<String>, otherwise: Option<String>) -> Var {
+        self.when = when;
+        self.otherwise = otherwise;
+        self
+    }
+
+    pub fn with_choice_format(mut self, choice_format: Option<ChoiceFormat>) -> Var {
+        self.choice_format = choice_format;
+        self
+    }
+
+    /// choice_format returns how the output of from_command is split into choices
+    /// when it differs from the default, a value and a description separated by a tab.
+    pub fn choice_format(&self) -> Option<&ChoiceFormat> {
+        self.choice_format.as_ref()
+    }
+
+    pub fn with_choice_filter(mut self, filter: Option<ChoiceFilter>) -> Var {
+        self.filter = filter;
+        self
+    }
+
+    /// choice_filter returns how the choices are post-processed before they are offered.
+    pub fn choice_filter(&self) -> Option<&ChoiceFilter> {
+        self.filter.as_ref()
+    }
+
+    pub fn with_structured_choices(mut self, structured: StructuredChoices) -> Var {
+        self.parse = Some(structured.format);
+        self.jsonpath = structured.jsonpath;
+        self.value_field = structured.value_field;
+        self.desc_field = structured.desc_field;
+        self
+    }
+
+    /// structured_choices returns how to extract choices from the output of
+    /// from_command when it's parsed as json or yaml.
+    pub fn structured_choices(&self) -> Option<StructuredChoices> {
+        self.parse.map(|format| StructuredChoices {
+            format,
+            jsonpath: self.jsonpath.clone(),
+            value_field: self.value_field.clone(),
+            desc_field: self.desc_field.clone(),
+        })
+    }
+
+    pub fn with_when_os(mut self, when_os: Option<String>) -> Var {
+        self.when_os = when_os;
+        self
+    }
+
+    /// runs_on tells whether the var is meant for the given operating system.
+    pub fn runs_on(&self, os: &str) -> bool {
+        self.when_os
+            .as_deref()
+            .is_none_or(|when_os| matches_os(when_os, os))
+    }
+
+    pub fn with_enabled(mut self, enabled: Option<bool>) -> Var {
+        self.enabled = enabled;
+        self
+    }
+
+    /// is_enabled tells whether the var is loaded, it is unless `enabled: false` is set.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled != Some(false)
+    }
+
+    /// condition returns the condition under which the var is resolved.
+    pub fn condition(&self) -> Option<&str> {
+        self.when.as_deref()
+    }
+
+    /// otherwise returns the choice of the var when its condition doesn't hold.
+    pub fn otherwise(&self) -> Choice {
+        Choice::new(self.otherwise.clone().unwrap_or_default(), None)
+    }
+
+    /// with_wildcards_expanded resolves the `{{ *::name }}` references of from_command
+    /// among the known vars.
+    pub fn with_wildcards_expanded(mut self, known: &[Identifier]) -> Var {
+        let namespace = self.name.namespace.clone();
+        self.from_command = self
+            .from_command
+            .map(|cmd| Identifier::expand_wildcards(&cmd, namespace.as_deref(), known));
+        self.from_url = self
+            .from_url
+            .map(|url| Identifier::expand_wildcards(&url, namespace.as_deref(), known));
+        self.from_plugin = self
+            .from_plugin
+            .map(|plugin| Identifier::expand_wildcards(&plugin, namespace.as_deref(), known));
+        self.when = self
+            .when
+            .map(|when| Identifier::expand_wildcards(&when, namespace.as_deref(), known));
+        self
+    }
+
+    /// is_cached returns false when the output of from_command should never be cached.
+    pub fn is_cached(&self) -> bool {
+        self.cache.unwrap_or(true)
+    }
+
+    /// cache_ttl returns how long the output of from_command is cached for
+    /// when it differs from the ttl of the configuration.
+    pub fn cache_ttl(&self) -> Option<Duration> {
+        self.cache_ttl.map(Duration::from_secs)
+    }
+
+    /// timeout returns how long from_command may run before it's killed.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout_secs.map(Duration::from_secs)
+    }
+
+    /// retries returns how many times from_command runs again when it fails or times out.
+    pub fn retries(&self) -> u32 {
+        self.retries.unwrap_or(0)
+    }
+
     pub const fn is_command(&self) -> bool {
         self.from_command.is_some()
     }
 
+    pub const fn is_url(&self) -> bool {
+        self.from_url.is_some()
+    }
+
+    /// url returns the endpoint the choices of a `from_url` var are read from.
+    pub fn url(&self) -> Option<&str> {
+        self.from_url.as_deref()
+    }
+
+    /// headers returns the headers sent along with the request of a `from_url` var.
+    pub fn headers(&self) -> &HashMap<String, String> {
+        &self.headers
+    }
+
+    /// plugin returns the executable giving the choices of a `from_plugin` var, with its arguments.
+    pub fn plugin(&self) -> Option<&str> {
+        self.from_plugin.as_deref()
+    }
+
+    /// template returns the template the value of a `from_template` var is computed with.
+    pub fn template(&self) -> Option<&str> {
+        self.from_template.as_deref()
+    }
+
+    /// path returns the directory the choices of a `from_path` var are listed from.
+    pub fn path(&self) -> Option<&str> {
+        self.from_path.as_deref()
+    }
+
+    pub fn with_path_rules(mut self, rules: PathRules) -> Var {
+        self.glob = rules.glob;
+        self.dirs_only = rules.dirs_only;
+        self.hidden = rules.hidden;
+        self
+    }
+
+    /// path_rules returns the rules the entries listed for a `from_path` var are kept with.
+    pub fn path_rules(&self) -> PathRules {
+        PathRules {
+            glob: self.glob.clone(),
+            dirs_only: self.dirs_only,
+            hidden: self.hidden,
+        }
+    }
+
     pub const fn is_input(&self) -> bool {
         self.from_input.is_some()
     }
 
+    pub const fn is_secret(&self) -> bool {
+        self.from_secret.is_some()
+    }
+
+    pub fn with_sensitive(mut self, sensitive: Option<bool>) -> Var {
+        self.sensitive = sensitive;
+        self
+    }
+
+    /// is_sensitive tells whether the choices of the var must be masked, secrets always are.
+    pub fn is_sensitive(&self) -> bool {
+        self.sensitive == Some(true) || self.is_secret()
+    }
+
+    /// history_source returns the alias whose outputs the var offers, aliases
+    /// without a namespace belong to the namespace of the var.
+    pub fn history_source(&self) -> Option<Identifier> {
+        let alias = Identifier::from_str(self.from_history.as_deref()?);
+        if alias.namespace().is_some() {
+            Some(alias)
+        } else {
+            Some(Identifier::with_namespace(alias.name(), self.namespace()))
+        }
+    }
+
+    /// secret returns the reference to the secret of the var.
+    pub fn secret(&self) -> Option<&str> {
+        self.from_secret.as_deref()
+    }
+
+    pub fn with_secret(mut self, reference: Option<String>) -> Var {
+        self.from_secret = reference;
+        self
+    }
+
     pub fn name(&self) -> Identifier {
         self.name.clone()
     }
 
+    pub fn desc(&self) -> &str {
+        self.desc.as_str()
+    }
+
     pub fn choices(&self) -> Vec<Choice> {
         self.choices.clone()
     }
@@ -84,6 +619,176 @@ impl Var {
     pub fn prompt(&self) -> Option<&str> {
         self.from_input.as_deref()
     }
+
+    pub fn with_editor(mut self, editor: Option<bool>) -> Var {
+        self.editor = editor;
+        self
+    }
+
+    /// uses_editor tells whether the input of the var is written in $EDITOR, for commit
+    /// messages, json payloads or sql queries spanning several lines.
+    pub fn uses_editor(&self) -> bool {
+        self.editor == Some(true)
+    }
+
+    pub fn with_input_rules(mut self, rules: InputRules) -> Var {
+        self.pattern = rules.pattern;
+        self.min = rules.min;
+        self.max = rules.max;
+        self.allow_empty = rules.allow_empty;
+        self
+    }
+
+    /// input_rules returns the rules the input of the var is validated with.
+    pub fn input_rules(&self) -> InputRules {
+        InputRules {
+            pattern: self.pattern.clone(),
+            min: self.min,
+            max: self.max,
+            allow_empty: self.allow_empty,
+        }
+    }
+}
+
+/// InputRules are the rules the input of a from_input var has to follow. The whole input has
+/// to match `pattern`, a number between `min` and `max` is expected when one of them is set,
+/// and an empty input is rejected when `allow_empty` is false.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InputRules {
+    pub pattern: Option<String>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub allow_empty: Option<bool>,
+}
+
+impl InputRules {
+    /// check tells whether the pattern is a valid regular expression.
+    pub fn check(&self) -> Result<(), ErrorsInput> {
+        self.regex().map(|_| ())
+    }
+
+    pub fn validate(&self, input: &str) -> Result<(), ErrorsInput> {
+        if input.is_empty() {
+            return match self.allow_empty {
+                Some(false) => Err(ErrorsInput::Required),
+                _ => Ok(()),
+            };
+        }
+        if let (Some(re), Some(pattern)) = (self.regex()?, &self.pattern) {
+            if !re.is_match(input) {
+                return Err(ErrorsInput::Mismatch(input.to_string(), pattern.clone()));
+            }
+        }
+        if self.min.is_none() && self.max.is_none() {
+            return Ok(());
+        }
+        let number: f64 = input
+            .parse()
+            .map_err(|_| ErrorsInput::NotANumber(input.to_string()))?;
+        match (self.min, self.max) {
+            (Some(min), _) if number < min => Err(ErrorsInput::TooLow(input.to_string(), min)),
+            (_, Some(max)) if number > max => Err(ErrorsInput::TooHigh(input.to_string(), max)),
+            _ => Ok(()),
+        }
+    }
+
+    // regex matches the whole input with the pattern.
+    fn regex(&self) -> Result<Option<Regex>, ErrorsInput> {
+        self.pattern
+            .as_ref()
+            .map(|pattern| {
+                Regex::new(&format!("^(?:{})$", pattern))
+                    .map_err(|_| ErrorsInput::InvalidPattern(pattern.clone()))
+            })
+            .transpose()
+    }
+}
+
+/// PathRules are the rules the entries of the directory of a from_path var are kept with.
+/// Their path, relative to the directory, has to match `glob`, `*` by default, where `*`
+/// and `?` don't match `/`, `**` matches any number of directories and `{a,b}` either
+/// alternative.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PathRules {
+    pub glob: Option<String>,
+    pub dirs_only: Option<bool>,
+    pub hidden: Option<bool>,
+}
+
+impl PathRules {
+    /// matches tells whether an entry, given by its path relative to the directory, is kept.
+    pub fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dirs_only == Some(true) && !is_dir {
+            return false;
+        }
+        if !self.shows_hidden() && path.split('/').any(|part| part.starts_with('.')) {
+            return false;
+        }
+        Regex::new(&format!("^{}$", glob_to_regex(self.glob())))
+            .expect("globs translate to valid regular expressions")
+            .is_match(path)
+    }
+
+    /// max_depth is how deep the directory is walked, None when the glob holds `**`.
+    pub fn max_depth(&self) -> Option<usize> {
+        let glob = self.glob();
+        (!glob.contains("**")).then(|| glob.matches('/').count() + 1)
+    }
+
+    pub fn shows_hidden(&self) -> bool {
+        self.hidden == Some(true)
+    }
+
+    fn glob(&self) -> &str {
+        self.glob.as_deref().unwrap_or("*")
+    }
+}
+
+// glob_to_regex translates a glob to a regular expression, braces that aren't closed
+// are matched as they are.
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::new();
+    let mut rest = glob;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        match c {
+            '*' if rest.starts_with("*/") => {
+                regex.push_str("(?:.*/)?");
+                rest = &rest[2..];
+            }
+            '*' if rest.starts_with('*') => {
+                regex.push_str(".*");
+                rest = &rest[1..];
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '{' if rest.contains('}') => {
+                let (alternatives, after) = rest.split_once('}').unwrap_or_default();
+                let alternatives: Vec<String> =
+                    alternatives.split(',').map(glob_to_regex).collect();
+                regex.push_str(&format!("(?:{})", alternatives.join("|")));
+                rest = after;
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ErrorsInput {
+    #[error("a value is required")]
+    Required,
+    #[error("'{0}' doesn't match the pattern {1}")]
+    Mismatch(String, String),
+    #[error("the pattern {0} of the var isn't a valid regular expression")]
+    InvalidPattern(String),
+    #[error("'{0}' isn't a number")]
+    NotANumber(String),
+    #[error("'{0}' is lower than the minimum, {1}")]
+    TooLow(String, f64),
+    #[error("'{0}' is greater than the maximum, {1}")]
+    TooHigh(String, f64),
 }
 
 impl NamespaceUpdater for Var {
@@ -108,12 +813,43 @@ impl Command for Var {
     fn command(&self) -> &str {
         self.from_command.as_deref().unwrap_or("")
     }
+    // the vars used in the condition, in the secret reference, in the url, in the arguments
+    // of the plugin, in the directory of from_path and in the template are dependencies as well.
+    fn dependencies(&self) -> Vec<Identifier> {
+        let mut deps = self
+            .when
+            .as_deref()
+            .map(|when| Identifier::parse(when, self.namespace()))
+            .unwrap_or_default();
+        let secret_deps = Identifier::parse(self.secret().unwrap_or(""), self.namespace());
+        let url_deps = Identifier::parse(self.url().unwrap_or(""), self.namespace());
+        let plugin_deps = Identifier::parse(self.plugin().unwrap_or(""), self.namespace());
+        let path_deps = Identifier::parse(self.path().unwrap_or(""), self.namespace());
+        let template_deps = Identifier::parse(self.template().unwrap_or(""), self.namespace());
+        let command_deps = Identifier::parse(self.command(), self.namespace());
+        for dep in secret_deps
+            .into_iter()
+            .chain(url_deps)
+            .chain(plugin_deps)
+            .chain(path_deps)
+            .chain(template_deps)
+            .chain(command_deps)
+        {
+            if !deps.contains(&dep) {
+                deps.push(dep);
+            }
+        }
+        deps
+    }
 }
 
 impl Command for &Var {
     fn command(&self) -> &str {
         self.from_command.as_deref().unwrap_or("")
     }
+    fn dependencies(&self) -> Vec<Identifier> {
+        (*self).dependencies()
+    }
 }
 /// Dependencies returns the dependencies of this variable if it gets it's
 /// choices from a command.
@@ -166,6 +902,189 @@ mod tests {
         assert_eq!(hasher.finish(), hasher_2.finish());
     }
 
+    #[test]
+    fn test_cache_settings() {
+        assert!(VAR_LISTING.is_cached());
+        assert_eq!(VAR_LISTING.cache_ttl(), None);
+        let vars: Vec<Var> = serde_yaml::from_str(
+            "- name: instances\n  desc: ec2 instances\n  from_command: aws ec2 describe-instances\n  cache_ttl: 300\n- name: token\n  desc: a token\n  from_command: vault read token\n  cache: false\n",
+        )
+        .unwrap();
+        assert_eq!(vars[0].cache_ttl(), Some(Duration::from_secs(300)));
+        assert!(vars[0].is_cached());
+        assert!(!vars[1].is_cached());
+    }
+
+    #[test]
+    fn test_condition_dependencies() {
+        let var = Var::from_command("pod", "a pod", "kubectl get pods -n {{ namespace }}")
+            .with_condition(Some(String::from("{{ env }} == 'prod'")), None);
+        assert_eq!(
+            var.dependencies(),
+            vec![Identifier::new("env"), Identifier::new("namespace")]
+        );
+        assert_eq!(var.otherwise(), Choice::new("", None));
+    }
+
+    #[test]
+    fn test_history_source() {
+        let vars: Vec<Var> = serde_yaml::from_str(
+            "- name: instance\n  desc: created instances\n  from_history: create\n- name: bucket\n  desc: created buckets\n  from_history: s3::create\n",
+        )
+        .unwrap();
+        let mut instance = vars[0].clone();
+        NamespaceUpdater::update(&mut instance, "ec2");
+        assert_eq!(
+            instance.history_source(),
+            Some(Identifier::with_namespace("create", Some("ec2")))
+        );
+        assert_eq!(
+            vars[1].history_source(),
+            Some(Identifier::with_namespace("create", Some("s3")))
+        );
+        assert!(instance.dependencies().is_empty());
+        assert_eq!(VAR_LISTING.history_source(), None);
+    }
+
+    #[test]
+    fn test_url() {
+        let vars: Vec<Var> = serde_yaml::from_str(
+            "- name: project\n  desc: a project\n  from_url: https://gitlab/api/v4/groups/{{ group }}/projects\n  headers:\n    PRIVATE-TOKEN: $GITLAB_TOKEN\n",
+        )
+        .unwrap();
+        assert!(vars[0].is_url());
+        assert!(!vars[0].is_command());
+        assert_eq!(
+            vars[0].url(),
+            Some("https://gitlab/api/v4/groups/{{ group }}/projects")
+        );
+        assert_eq!(
+            vars[0].headers().get("PRIVATE-TOKEN").unwrap(),
+            "$GITLAB_TOKEN"
+        );
+        assert_eq!(vars[0].dependencies(), vec![Identifier::new("group")]);
+    }
+
+    #[test]
+    fn test_default() {
+        let vars: Vec<Var> = serde_yaml::from_str(
+            "- name: env\n  desc: an environment\n  default: staging\n  choices:\n    - value: staging\n    - value: prod\n",
+        )
+        .unwrap();
+        assert_eq!(
+            vars[0].default_choice(),
+            Some(Choice::from_value("staging"))
+        );
+        assert_eq!(VAR_LISTING.default_choice(), None);
+    }
+
+    #[test]
+    fn test_editor() {
+        let vars: Vec<Var> = serde_yaml::from_str(
+            "- name: message\n  desc: a commit message\n  from_input: message\n  editor: true\n",
+        )
+        .unwrap();
+        assert!(vars[0].uses_editor());
+        assert!(!VAR_LISTING.uses_editor());
+        assert!(!VAR_LISTING.clone().with_editor(Some(false)).uses_editor());
+    }
+
+    #[test]
+    fn test_input_rules() {
+        let vars: Vec<Var> = serde_yaml::from_str(
+            "- name: port\n  desc: a port\n  from_input: port\n  min: 1024\n  max: 65535\n  allow_empty: false\n- name: version\n  desc: a version\n  from_input: version\n  pattern: v[0-9]+\\.[0-9]+\n",
+        )
+        .unwrap();
+        let port = vars[0].input_rules();
+        assert_eq!(port.validate("8080"), Ok(()));
+        assert_eq!(port.validate(""), Err(ErrorsInput::Required));
+        assert_eq!(
+            port.validate("80"),
+            Err(ErrorsInput::TooLow(String::from("80"), 1024.0))
+        );
+        assert_eq!(
+            port.validate("70000").unwrap_err().to_string(),
+            "'70000' is greater than the maximum, 65535"
+        );
+        assert_eq!(
+            port.validate("http"),
+            Err(ErrorsInput::NotANumber(String::from("http")))
+        );
+        let version = vars[1].input_rules();
+        assert_eq!(version.validate("v1.2"), Ok(()));
+        assert_eq!(version.validate(""), Ok(()));
+        assert_eq!(
+            version.validate("v1.2-rc1").unwrap_err().to_string(),
+            "'v1.2-rc1' doesn't match the pattern v[0-9]+\\.[0-9]+"
+        );
+        let invalid = InputRules {
+            pattern: Some(String::from("v[0-9")),
+            ..InputRules::default()
+        };
+        assert!(matches!(
+            invalid.validate("v1"),
+            Err(ErrorsInput::InvalidPattern(_))
+        ));
+        assert!(invalid.check().is_err());
+        assert!(version.check().is_ok());
+    }
+
+    #[test]
+    fn test_choice_filter() {
+        let vars: Vec<Var> = serde_yaml::from_str(
+            "- name: pod\n  desc: a pod\n  from_command: kubectl get pods\n  filter:\n    match: ^prod-\n    sort: true\n    limit: 50\n",
+        )
+        .unwrap();
+        assert_eq!(
+            vars[0].choice_filter(),
+            Some(&ChoiceFilter {
+                pattern: Some(String::from("^prod-")),
+                unique: None,
+                sort: Some(true),
+                limit: Some(50),
+            })
+        );
+        assert_eq!(VAR_LISTING.choice_filter(), None);
+    }
+
+    #[test]
+    fn test_path_rules() {
+        let vars: Vec<Var> = serde_yaml::from_str(
+            "- name: manifest\n  desc: a manifest\n  from_path: '{{ dir }}/k8s'\n  glob: '**/*.{yaml,yml}'\n",
+        )
+        .unwrap();
+        assert_eq!(vars[0].path(), Some("{{ dir }}/k8s"));
+        assert_eq!(vars[0].dependencies(), vec![Identifier::new("dir")]);
+        let manifests = vars[0].path_rules();
+        assert_eq!(manifests.max_depth(), None);
+        assert!(manifests.matches("api.yaml", false));
+        assert!(manifests.matches("prod/api.yml", false));
+        assert!(!manifests.matches("prod/api.json", false));
+        assert!(!manifests.matches(".github/ci.yaml", false));
+
+        let dirs = PathRules {
+            glob: Some(String::from("*/v?")),
+            dirs_only: Some(true),
+            hidden: Some(true),
+        };
+        assert_eq!(dirs.max_depth(), Some(2));
+        assert!(dirs.matches(".charts/v1", true));
+        assert!(!dirs.matches(".charts/v1", false));
+        assert!(!dirs.matches("charts/v10", true));
+        assert!(PathRules::default().matches("{a", false));
+    }
+
+    #[test]
+    fn test_plugin() {
+        let var = Var::from_plugin("pod", "a pod", "pods-resolver --namespace {{ namespace }}");
+        assert_eq!(
+            var.plugin(),
+            Some("pods-resolver --namespace {{ namespace }}")
+        );
+        assert_eq!(var.dependencies(), vec![Identifier::new("namespace")]);
+        assert_eq!(VAR_LISTING.plugin(), None);
+    }
+
     #[test]
     fn test_parse_vars() {
         assert_eq!(
@@ -206,6 +1125,7 @@ mod tests {
             ErrorsDependencies::MissingChoicesForVar(name) => {
                 assert_eq!(name, VAR_DIRECTORY_NAME.clone())
             }
+            err => panic!("unexpected error {}", err),
         }
     }
 }
@@ -229,6 +1149,39 @@ pub mod fixtures {
             desc: VAR_USE_LISTING_DESC.clone(),
             choices: VAR_USE_LISTING_CHOICES.clone(),
             from_input: None,
+            from_secret: None,
+            from_history: None,
+            from_url: None,
+            headers: HashMap::new(),
+            from_plugin: None,
+            from_template: None,
+            from_path: None,
+            glob: None,
+            dirs_only: None,
+            hidden: None,
+            map_command: None,
+            preview_command: None,
+            default: None,
+            pattern: None,
+            min: None,
+            max: None,
+            allow_empty: None,
+            editor: None,
+            sensitive: None,
+            cache_ttl: None,
+            cache: None,
+            timeout_secs: None,
+            retries: None,
+            when: None,
+            otherwise: None,
+            choice_format: None,
+            filter: None,
+            when_os: None,
+            enabled: None,
+            parse: None,
+            jsonpath: None,
+            value_field: None,
+            desc_field: None,
         };
         pub static ref VAR_LISTING_COMMAND: String =
             String::from("ls -l {{directory}} |grep -v {{ ns::pattern }}");
@@ -246,6 +1199,39 @@ pub mod fixtures {
             desc: VAR_LISTING_DESC.clone(),
             choices: VAR_LISTING_CHOICES.clone(),
             from_input: None,
+            from_secret: None,
+            from_history: None,
+            from_url: None,
+            headers: HashMap::new(),
+            from_plugin: None,
+            from_template: None,
+            from_path: None,
+            glob: None,
+            dirs_only: None,
+            hidden: None,
+            map_command: None,
+            preview_command: None,
+            default: None,
+            pattern: None,
+            min: None,
+            max: None,
+            allow_empty: None,
+            editor: None,
+            sensitive: None,
+            cache_ttl: None,
+            cache: None,
+            timeout_secs: None,
+            retries: None,
+            when: None,
+            otherwise: None,
+            choice_format: None,
+            filter: None,
+            when_os: None,
+            enabled: None,
+            parse: None,
+            jsonpath: None,
+            value_field: None,
+            desc_field: None,
         };
         pub static ref VAR_DIRECTORY_DESC: String =
             String::from("A list of safe directory paths where to perform commands.");
@@ -263,6 +1249,39 @@ pub mod fixtures {
             desc: VAR_DIRECTORY_DESC.clone(),
             choices: VAR_DIRECTORY_CHOICES.clone(),
             from_input: None,
+            from_secret: None,
+            from_history: None,
+            from_url: None,
+            headers: HashMap::new(),
+            from_plugin: None,
+            from_template: None,
+            from_path: None,
+            glob: None,
+            dirs_only: None,
+            hidden: None,
+            map_command: None,
+            preview_command: None,
+            default: None,
+            pattern: None,
+            min: None,
+            max: None,
+            allow_empty: None,
+            editor: None,
+            sensitive: None,
+            cache_ttl: None,
+            cache: None,
+            timeout_secs: None,
+            retries: None,
+            when: None,
+            otherwise: None,
+            choice_format: None,
+            filter: None,
+            when_os: None,
+            enabled: None,
+            parse: None,
+            jsonpath: None,
+            value_field: None,
+            desc_field: None,
         };
         pub static ref VAR_PATTERN_DESC: String = String::from("A black list of patterns");
         pub static ref VAR_PATTERN_CHOICE_1: Choice =
@@ -277,6 +1296,39 @@ pub mod fixtures {
             desc: VAR_PATTERN_DESC.clone(),
             choices: VAR_PATTERN_CHOICES.clone(),
             from_input: None,
+            from_secret: None,
+            from_history: None,
+            from_url: None,
+            headers: HashMap::new(),
+            from_plugin: None,
+            from_template: None,
+            from_path: None,
+            glob: None,
+            dirs_only: None,
+            hidden: None,
+            map_command: None,
+            preview_command: None,
+            default: None,
+            pattern: None,
+            min: None,
+            max: None,
+            allow_empty: None,
+            editor: None,
+            sensitive: None,
+            cache_ttl: None,
+            cache: None,
+            timeout_secs: None,
+            retries: None,
+            when: None,
+            otherwise: None,
+            choice_format: None,
+            filter: None,
+            when_os: None,
+            enabled: None,
+            parse: None,
+            jsonpath: None,
+            value_field: None,
+            desc_field: None,
         };
         pub static ref VAR_MISSING_COMMAND: String =
             String::from("ls -l {{directory}} |grep -v {{pattern2}}");
@@ -292,6 +1344,39 @@ pub mod fixtures {
             desc: VAR_MISSING_DESC.clone(),
             choices: VAR_MISSING_CHOICES.clone(),
             from_input: None,
+            from_secret: None,
+            from_history: None,
+            from_url: None,
+            headers: HashMap::new(),
+            from_plugin: None,
+            from_template: None,
+            from_path: None,
+            glob: None,
+            dirs_only: None,
+            hidden: None,
+            map_command: None,
+            preview_command: None,
+            default: None,
+            pattern: None,
+            min: None,
+            max: None,
+            allow_empty: None,
+            editor: None,
+            sensitive: None,
+            cache_ttl: None,
+            cache: None,
+            timeout_secs: None,
+            retries: None,
+            when: None,
+            otherwise: None,
+            choice_format: None,
+            filter: None,
+            when_os: None,
+            enabled: None,
+            parse: None,
+            jsonpath: None,
+            value_field: None,
+            desc_field: None,
         };
     }
 }
@@ -42,16 +42,20 @@ impl<T> Value for T where T: Serialize + DeserializeOwned + Send + Clone + std::
 struct StateEntry<V> {
     entry: V,
     when: u64,
+    // overrides the ttl of the state for this entry.
+    #[serde(default)]
+    ttl: Option<u64>,
 }
 
 impl<V> StateEntry<V> {
-    pub fn new(value: V) -> Self {
+    pub fn new(value: V, ttl: Option<Duration>) -> Self {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .expect("can't get system time");
         StateEntry {
             entry: value,
             when: now.as_secs(),
+            ttl: ttl.map(|ttl| ttl.as_secs()),
         }
     }
 }
@@ -66,7 +70,7 @@ where
         let db = AssociativeStateWithTTL {
             path: p.as_ref().to_owned(),
             ttl: Some(*ttl),
-            _marker: PhantomData::default(),
+            _marker: PhantomData,
         };
         db.open_db()?;
         Ok(db)
@@ -77,15 +81,27 @@ where
         let db = AssociativeStateWithTTL {
             path: p.as_ref().to_owned(),
             ttl: None,
-            _marker: PhantomData::default(),
+            _marker: PhantomData,
         };
         db.open_db()?;
         Ok(db)
     }
 
+    #[allow(dead_code)]
     pub fn put(&self, key: impl AsRef<str>, value: V) -> Result<(), ErrorAssociativeState> {
+        self.put_with_ttl(key, value, None)
+    }
+
+    /// put_with_ttl inserts a value that expires after the provided ttl
+    /// instead of the ttl of the state.
+    pub fn put_with_ttl(
+        &self,
+        key: impl AsRef<str>,
+        value: V,
+        ttl: Option<Duration>,
+    ) -> Result<(), ErrorAssociativeState> {
         let db = self.open_db()?;
-        let entry = StateEntry::new(value);
+        let entry = StateEntry::new(value, ttl);
         db.write(|db| {
             db.insert(key.as_ref().to_string(), entry);
 
@@ -108,7 +124,7 @@ where
         let db = self.open_db()?;
         let cache_key = command.as_ref();
         let entry = db
-            .read(|db| db.get(cache_key).map(Clone::clone))
+            .read(|db| db.get(cache_key).cloned())
             .map_err(ErrorAssociativeState::ReadFailure)?;
         Ok(entry.filter(|v| self.is_value_valid(v)).map(|e| e.entry))
     }
@@ -138,10 +154,9 @@ where
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .expect("Can't get system time");
-        if let Some(ttl) = self.ttl.as_ref() {
-            c.when + ttl.as_secs() > now.as_secs()
-        } else {
-            true
+        match c.ttl.or_else(|| self.ttl.map(|ttl| ttl.as_secs())) {
+            Some(ttl) => c.when + ttl > now.as_secs(),
+            None => true,
         }
     }
 }
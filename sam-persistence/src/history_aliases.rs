@@ -1,14 +1,21 @@
+use ron::value::{Number, Value};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
 use sam_core::{
     engines::{ErrorSamEngine, SamHistory},
-    entities::aliases::ResolvedAlias,
+    entities::{aliases::ResolvedAlias, choices::Choice, identifiers::Identifier},
 };
 
 use crate::sequential_state::{ErrorSequentialState, SequentialState};
 
+/// HISTORY_VERSION is the version of the format of history entries. Entries
+/// recorded before the format was versioned have version 0.
+pub const HISTORY_VERSION: u32 = 1;
+
 #[derive(Clone)]
 pub struct AliasHistory {
     state: SequentialState<HistoryEntry>,
@@ -19,6 +26,12 @@ pub struct AliasHistory {
 pub enum ErrorAliasHistory {
     #[error("failed to interact with alias history\n->{0}")]
     ErrSequentialState(#[from] ErrorSequentialState),
+    #[error("the history file {0} can't be read, it might have been written by an older version of sam, run `sam history migrate` to upgrade it\n-> {1}")]
+    NeedsMigration(PathBuf, ErrorSequentialState),
+    #[error("can't read the history file {0}\n-> {1}")]
+    Unreadable(PathBuf, std::io::Error),
+    #[error("can't migrate the history file {0}\n-> {1}")]
+    MigrationFailure(PathBuf, ron::Error),
 }
 
 impl AliasHistory {
@@ -26,21 +39,161 @@ impl AliasHistory {
         path: impl Into<PathBuf>,
         max_size: Option<usize>,
     ) -> Result<Self, ErrorAliasHistory> {
-        let state = SequentialState::new(path.into(), max_size)?;
+        let path = path.into();
+        let state = SequentialState::new(&path, max_size).map_err(|err| match err {
+            ErrorSequentialState::OpenFailure(_) => ErrorAliasHistory::NeedsMigration(path, err),
+            _ => ErrorAliasHistory::ErrSequentialState(err),
+        })?;
         let pwd = std::env::current_dir().expect("can't figure out local directory");
         Ok(AliasHistory { state, pwd })
     }
 
+    /// in_memory creates a history that is never written to disk, starting with the
+    /// entries of the history file when it can be read.
+    pub fn in_memory(path: impl AsRef<Path>, max_size: Option<usize>) -> Self {
+        let entries = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| ron::from_str(&content).ok())
+            .unwrap_or_default();
+        let state = SequentialState::in_memory(entries, max_size);
+        let pwd = std::env::current_dir().expect("can't figure out local directory");
+        AliasHistory { state, pwd }
+    }
+
+    /// migrate upgrades the entries of a history file to the current version of the format,
+    /// it returns the number of entries that were upgraded.
+    pub fn migrate(path: impl AsRef<Path>) -> Result<usize, ErrorAliasHistory> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| ErrorAliasHistory::Unreadable(path.to_path_buf(), err))?;
+        if content.trim().is_empty() {
+            return Ok(0);
+        }
+        let failure = |err| ErrorAliasHistory::MigrationFailure(path.to_path_buf(), err);
+        let entries = match ron::from_str(&content).map_err(failure)? {
+            Value::Seq(entries) => entries,
+            other => vec![other],
+        };
+        let mut migrated = 0;
+        let mut history = Vec::with_capacity(entries.len());
+        for mut entry in entries {
+            if migrate_entry(&mut entry) {
+                migrated += 1;
+            }
+            history.push(entry.into_rust::<HistoryEntry>().map_err(failure)?);
+        }
+        if migrated > 0 {
+            SequentialState::overwrite(path, history)?;
+        }
+        Ok(migrated)
+    }
+
     pub fn entries(&self) -> Result<impl Iterator<Item = HistoryEntry>, ErrorAliasHistory> {
         Ok(self.state.entries()?)
     }
+
+    /// frecency scores the aliases of the history by how often and how recently they ran.
+    pub fn frecency(&self) -> Result<HashMap<Identifier, f64>, ErrorAliasHistory> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+        Ok(frecency(self.state.entries()?, now))
+    }
+
+    /// last_choices returns the choices made the last time each var was resolved.
+    pub fn last_choices(&self) -> Result<HashMap<Identifier, Vec<Choice>>, ErrorAliasHistory> {
+        let mut choices = HashMap::new();
+        for entry in self.state.entries()? {
+            choices.extend(entry.r.choices().clone());
+        }
+        Ok(choices)
+    }
+
+    /// outputs returns, for each alias that captures its output, the distinct lines it
+    /// printed as choices, starting with the lines of its most recent run.
+    pub fn outputs(&self) -> Result<HashMap<Identifier, Vec<Choice>>, ErrorAliasHistory> {
+        let entries: Vec<HistoryEntry> = self.state.entries()?.collect();
+        let mut outputs: HashMap<Identifier, Vec<Choice>> = HashMap::new();
+        for entry in entries.iter().rev() {
+            let choices = outputs.entry(entry.r.name().clone()).or_default();
+            for line in entry.r.outputs() {
+                let line = line.trim();
+                if !line.is_empty() && choices.iter().all(|c| c.value() != line) {
+                    choices.push(Choice::from_value(line));
+                }
+            }
+        }
+        outputs.retain(|_, choices| !choices.is_empty());
+        Ok(outputs)
+    }
+
+    /// record_outputs stores the lines printed by the last run of an alias in its entry.
+    pub fn record_outputs(
+        &self,
+        alias: &Identifier,
+        outputs: Vec<String>,
+    ) -> Result<(), ErrorAliasHistory> {
+        self.update_last(alias, |r| r.with_outputs(outputs))
+    }
+
+    /// record_transcript stores what the last run of an alias printed, on stdout and
+    /// on stderr, in its entry.
+    pub fn record_transcript(
+        &self,
+        alias: &Identifier,
+        transcript: Vec<String>,
+    ) -> Result<(), ErrorAliasHistory> {
+        self.update_last(alias, |r| r.with_transcript(transcript))
+    }
+
+    fn update_last(
+        &self,
+        alias: &Identifier,
+        update: impl FnOnce(ResolvedAlias) -> ResolvedAlias,
+    ) -> Result<(), ErrorAliasHistory> {
+        let mut entries: Vec<HistoryEntry> = self.state.entries()?.collect();
+        if let Some(entry) = entries.iter_mut().rev().find(|e| e.r.name() == alias) {
+            entry.r = update(entry.r.clone());
+            self.state.replace(entries)?;
+        }
+        Ok(())
+    }
+
+    /// import merges entries into the history, ordered by timestamp. Entries that are
+    /// already in the history are skipped, it returns the number of imported entries.
+    pub fn import(
+        &self,
+        entries: impl Iterator<Item = HistoryEntry>,
+    ) -> Result<usize, ErrorAliasHistory> {
+        let mut history: Vec<HistoryEntry> = self.state.entries()?.collect();
+        let before = history.len();
+        for entry in entries {
+            if !history.contains(&entry) {
+                history.push(entry);
+            }
+        }
+        let imported = history.len() - before;
+        if imported > 0 {
+            // entries recorded by older versions of sam, without a timestamp, come first.
+            history.sort_by_key(|e| e.timestamp);
+            self.state.replace(history)?;
+        }
+        Ok(imported)
+    }
 }
 
 impl SamHistory for AliasHistory {
     fn put(&mut self, alias: ResolvedAlias) -> Result<(), ErrorSamEngine> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|d| d.as_secs() as i64);
         let entry = HistoryEntry {
             r: alias,
             pwd: self.pwd.to_string_lossy().to_string(),
+            timestamp,
+            version: HISTORY_VERSION,
         };
         self.state
             .push(entry)
@@ -62,10 +215,67 @@ impl SamHistory for AliasHistory {
     }
 }
 
+const HOUR: i64 = 60 * 60;
+const DAY: i64 = 24 * HOUR;
+
+// each execution adds to the score of an alias, recent executions weigh more.
+// executions without a timestamp weigh the same as the oldest ones.
+fn frecency(entries: impl Iterator<Item = HistoryEntry>, now: i64) -> HashMap<Identifier, f64> {
+    let mut scores = HashMap::new();
+    for entry in entries {
+        let weight = match entry.timestamp.map(|t| now - t) {
+            Some(age) if age < 4 * HOUR => 8.0,
+            Some(age) if age < DAY => 4.0,
+            Some(age) if age < 7 * DAY => 2.0,
+            Some(age) if age < 30 * DAY => 1.0,
+            _ => 0.5,
+        };
+        *scores.entry(entry.r.name().clone()).or_insert(0.0) += weight;
+    }
+    scores
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct HistoryEntry {
     pub r: ResolvedAlias,
     pub pwd: String,
+    /// the time of the execution in seconds since the unix epoch.
+    /// entries recorded by older versions of sam don't have one.
+    #[serde(default)]
+    pub timestamp: Option<i64>,
+    #[serde(default)]
+    pub version: u32,
+}
+
+// migrate_entry upgrades an entry read as a generic value, one version at a
+// time. It returns whether the entry was upgraded.
+fn migrate_entry(entry: &mut Value) -> bool {
+    let fields = match entry {
+        Value::Map(fields) => fields,
+        _ => return false,
+    };
+    let key = |name: &str| Value::String(name.to_string());
+    let version = match fields.remove(&key("version")) {
+        Some(Value::Number(n)) => n.as_i64().unwrap_or_default(),
+        _ => 0,
+    };
+    if version >= HISTORY_VERSION as i64 {
+        fields.insert(key("version"), Value::Number(Number::new(version)));
+        return false;
+    }
+    // version 1: aliases ran a single command, `resolved_alias`, they now run one
+    // command per combination of choices, `resolved_aliases`.
+    if let Some(Value::Map(mut r)) = fields.remove(&key("r")) {
+        if let Some(command) = r.remove(&key("resolved_alias")) {
+            r.insert(key("resolved_aliases"), Value::Seq(vec![command]));
+        }
+        fields.insert(key("r"), Value::Map(r));
+    }
+    fields.insert(
+        key("version"),
+        Value::Number(Number::new(HISTORY_VERSION as i64)),
+    );
+    true
 }
 
 #[cfg(test)]
@@ -76,7 +286,7 @@ mod tests {
     };
     use sam_utils::fsutils;
 
-    use super::AliasHistory;
+    use super::{frecency, AliasHistory, ErrorAliasHistory, HistoryEntry, DAY, HISTORY_VERSION};
 
     #[test]
     fn test_history_put() {
@@ -92,10 +302,172 @@ mod tests {
             },
         );
         hist.put(test.clone()).expect("The put should succeed");
+        let last_choices = hist.last_choices().expect("should be able to read");
+        assert_eq!(
+            last_choices.get(&Identifier::new("var")),
+            Some(&vec![Choice::new("choice", None)])
+        );
         let last = hist
             .get_last()
             .expect("should be able to read")
             .expect("Expecting a value to be returned");
         assert_eq!(test, last);
+        let entry = hist
+            .entries()
+            .expect("should be able to read")
+            .last()
+            .expect("Expecting an entry to be returned");
+        assert!(entry.timestamp.is_some());
+    }
+
+    #[test]
+    fn test_history_outputs() {
+        let f = fsutils::TempFile::new().expect("can't create temp file for test");
+        let mut hist = AliasHistory::new(f.path, None).expect("can't create history file");
+        let create = Identifier::with_namespace("create", Some("ec2"));
+        let resolved = ResolvedAlias::new(
+            create.clone(),
+            String::from("creates an instance"),
+            String::from("create-instance"),
+            vec![String::from("create-instance")],
+            maplit::hashmap! {},
+        )
+        .with_capture(true);
+        hist.put(resolved.clone()).expect("The put should succeed");
+        hist.record_outputs(&create, vec![String::from("i-1"), String::from(" ")])
+            .expect("should be able to record outputs");
+        hist.put(resolved).expect("The put should succeed");
+        hist.record_outputs(&create, vec![String::from("i-2"), String::from("i-1")])
+            .expect("should be able to record outputs");
+        let outputs = hist.outputs().expect("should be able to read");
+        assert_eq!(
+            outputs.get(&create),
+            Some(&vec![Choice::from_value("i-2"), Choice::from_value("i-1")])
+        );
+        assert_eq!(outputs.len(), 1);
+
+        hist.record_transcript(&create, vec![String::from("creating i-2")])
+            .expect("should be able to record the transcript");
+        let last = hist
+            .get_last()
+            .expect("should be able to read")
+            .expect("Expecting a value to be returned");
+        assert_eq!(last.transcript(), ["creating i-2"]);
+        assert_eq!(last.outputs(), ["i-2", "i-1"]);
+    }
+
+    #[test]
+    fn test_frecency() {
+        let now = 100 * DAY;
+        let entry = |name: &str, timestamp: Option<i64>| HistoryEntry {
+            r: ResolvedAlias::new(
+                Identifier::with_namespace(name, Some("ns")),
+                String::from("desc"),
+                String::from("echo"),
+                vec![String::from("echo")],
+                maplit::hashmap! {},
+            ),
+            pwd: String::from("/tmp"),
+            timestamp,
+            version: HISTORY_VERSION,
+        };
+        let entries = vec![
+            entry("old", Some(now - 60 * DAY)),
+            entry("old", Some(now - 50 * DAY)),
+            entry("old", None),
+            entry("recent", Some(now - 60)),
+        ];
+        let scores = frecency(entries.into_iter(), now);
+        assert_eq!(
+            scores.get(&Identifier::with_namespace("old", Some("ns"))),
+            Some(&1.5)
+        );
+        assert_eq!(
+            scores.get(&Identifier::with_namespace("recent", Some("ns"))),
+            Some(&8.0)
+        );
+    }
+
+    #[test]
+    fn test_history_import() {
+        let f = fsutils::TempFile::new().expect("can't create temp file for test");
+        let mut hist = AliasHistory::new(f.path, None).expect("can't create history file");
+        let entry = |name: &str, timestamp: i64| HistoryEntry {
+            r: ResolvedAlias::new(
+                Identifier::with_namespace(name, Some("ns")),
+                String::from("desc"),
+                String::from("echo"),
+                vec![String::from("echo")],
+                maplit::hashmap! {},
+            ),
+            pwd: String::from("/tmp"),
+            timestamp: Some(timestamp),
+            version: HISTORY_VERSION,
+        };
+        hist.put(entry("local", 0).r)
+            .expect("The put should succeed");
+        let local = hist
+            .entries()
+            .expect("should be able to read")
+            .next()
+            .expect("Expecting an entry to be returned");
+
+        let imported = vec![entry("old", 1), local.clone(), entry("older", 0)];
+        let count = hist
+            .import(imported.clone().into_iter())
+            .expect("The import should succeed");
+        assert_eq!(count, 2);
+        let count = hist
+            .import(imported.into_iter())
+            .expect("The import should succeed");
+        assert_eq!(count, 0);
+
+        let names: Vec<String> = hist
+            .entries()
+            .expect("should be able to read")
+            .map(|e| e.r.name().name().to_string())
+            .collect();
+        assert_eq!(names, vec!["older", "old", "local"]);
+    }
+
+    #[test]
+    fn test_history_migrate() {
+        let f = fsutils::TempFile::new().expect("can't create temp file for test");
+        let legacy = r#"[
+    (
+        r: (
+            name: (name: "pods", namespace: Some("k8s")),
+            desc: "list pods",
+            original_alias: "kubectl get pods -n {{ namespace }}",
+            resolved_alias: "kubectl get pods -n default",
+            choices: {
+                (name: "namespace", namespace: Some("k8s")): [(value: "default", desc: None)],
+            },
+        ),
+        pwd: "/tmp",
+    ),
+]"#;
+        std::fs::write(&f.path, legacy).expect("can't write the legacy history");
+        let err = AliasHistory::new(&f.path, None).err();
+        assert!(matches!(err, Some(ErrorAliasHistory::NeedsMigration(_, _))));
+        // the history is left untouched until it's migrated.
+        assert_eq!(std::fs::read_to_string(&f.path).unwrap(), legacy);
+
+        assert_eq!(AliasHistory::migrate(&f.path).expect("can't migrate"), 1);
+        let hist = AliasHistory::new(&f.path, None).expect("can't open the migrated history");
+        let entries: Vec<HistoryEntry> = hist.entries().expect("can't read").collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].version, HISTORY_VERSION);
+        assert_eq!(
+            entries[0].r.commands(),
+            &[String::from("kubectl get pods -n default")]
+        );
+        assert_eq!(
+            entries[0]
+                .r
+                .choice(&Identifier::with_namespace("namespace", Some("k8s"))),
+            Some(vec![Choice::new("default", None)])
+        );
+        assert_eq!(AliasHistory::migrate(&f.path).expect("can't migrate"), 0);
     }
 }
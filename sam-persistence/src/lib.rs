@@ -6,8 +6,11 @@ mod vars_cache;
 pub use history_aliases::AliasHistory;
 pub use history_aliases::ErrorAliasHistory;
 pub use history_aliases::HistoryEntry;
+pub use history_aliases::HISTORY_VERSION;
 pub use vars_cache::CacheEntry;
 pub use vars_cache::CacheError;
+pub use vars_cache::CacheKey;
+pub use vars_cache::CacheLock;
 pub use vars_cache::NoopVarsCache;
 pub use vars_cache::RustBreakCache;
 pub use vars_cache::VarsCache;
@@ -1,9 +1,10 @@
 use lazy_static::lazy_static;
 use regex::Regex;
 use sam_core::engines::AliasCollection;
-use sam_core::entities::aliases::Alias;
+use sam_core::entities::aliases::{Alias, Impact};
 use sam_core::entities::dependencies::ErrorsDependencies;
 use sam_core::entities::identifiers::Identifier;
+use sam_core::entities::namespaces::NamespaceAliases;
 use std::collections::HashMap;
 use std::ops::Range;
 use thiserror::Error;
@@ -19,30 +20,109 @@ lazy_static! {
 #[derive(Debug, Clone)]
 pub struct AliasesRepository {
     aliases: HashMap<Identifier, Alias>,
+    show_hidden: bool,
+    impacts: Vec<Impact>,
+    tags: Vec<String>,
+    usage: HashMap<Identifier, f64>,
+    namespaces: NamespaceAliases,
 }
 
 impl AliasesRepository {
     pub fn new(aliases: impl Iterator<Item = Alias>) -> Result<Self, ErrorsAliasesRepository> {
+        Self::with_namespace_aliases(aliases, NamespaceAliases::default())
+    }
+
+    /// with_namespace_aliases creates a repository in which the aliases can be addressed
+    /// with the aliases of their namespaces as well, in `[[ ns::alias ]]` and steps too.
+    pub fn with_namespace_aliases(
+        aliases: impl Iterator<Item = Alias>,
+        namespaces: NamespaceAliases,
+    ) -> Result<Self, ErrorsAliasesRepository> {
         let mut mp = HashMap::new();
         for alias in aliases {
             let id = alias.identifier();
+            if !alias.steps().is_empty() && !alias.alias().is_empty() {
+                return Err(ErrorsAliasesRepository::StepsWithAlias(id));
+            }
             mp.insert(id, alias);
         }
         let mut mpf = HashMap::new();
         for (key, alias) in mp.iter() {
-            let t_alias = Self::substitute_alias_defs(alias, &mp)?;
+            let t_alias = Self::substitute_alias_defs(alias, &mp, &namespaces)?;
             mpf.insert(key.clone(), t_alias);
         }
-        Ok(AliasesRepository { aliases: mpf })
+        let with_steps: Vec<Identifier> = mpf
+            .values()
+            .filter(|a| !a.steps().is_empty())
+            .map(Alias::identifier)
+            .collect();
+        for id in with_steps {
+            let (command, mut env) =
+                Self::steps_command(&mpf[&id], &mpf, &namespaces, &mut vec![])?;
+            if let Some(alias) = mpf.remove(&id) {
+                env.extend(alias.sanitized_env());
+                let mut alias = alias.with_env(env);
+                alias.update(command);
+                mpf.insert(id, alias);
+            }
+        }
+        Ok(AliasesRepository {
+            aliases: mpf,
+            show_hidden: false,
+            impacts: vec![],
+            tags: vec![],
+            usage: HashMap::new(),
+            namespaces,
+        })
+    }
+
+    /// with_hidden makes hidden aliases visible in the alias picker and listings.
+    pub fn with_hidden(mut self, show_hidden: bool) -> Self {
+        self.show_hidden = show_hidden;
+        self
+    }
+
+    /// with_impacts limits the alias picker and listings to the aliases with one of
+    /// the impacts, all of them are offered when it's empty.
+    pub fn with_impacts(mut self, impacts: Vec<Impact>) -> Self {
+        self.impacts = impacts;
+        self
+    }
+
+    /// with_tags limits the alias picker and listings to the aliases carrying all of the tags.
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// with_usage orders the aliases offered for selection by their usage score,
+    /// the most used first.
+    pub fn with_usage(mut self, usage: HashMap<Identifier, f64>) -> Self {
+        self.usage = usage;
+        self
     }
 
     pub fn aliases(&self) -> Vec<Alias> {
         self.aliases.values().map(Alias::clone).collect()
     }
 
+    /// visible_aliases returns the aliases that aren't hidden, or all of them
+    /// when hidden aliases are shown, among the ones with the selected impacts and tags.
+    pub fn visible_aliases(&self) -> Vec<&Alias> {
+        self.aliases
+            .values()
+            .filter(|a| self.show_hidden || !a.is_hidden())
+            .filter(|a| {
+                self.impacts.is_empty() || a.impact().is_some_and(|i| self.impacts.contains(&i))
+            })
+            .filter(|a| a.has_tags(&self.tags))
+            .collect()
+    }
+
     fn substitute_alias_defs(
         alias: &Alias,
         aliases: &HashMap<Identifier, Alias>,
+        namespaces: &NamespaceAliases,
     ) -> Result<Alias, ErrorsAliasesRepository> {
         let mut t_alias = alias.clone();
         let deps = Self::parse(alias);
@@ -50,7 +130,7 @@ impl AliasesRepository {
             let alias_str = alias.alias();
             let mut alias_parts = vec![];
             for (range, id) in deps.iter() {
-                if let Some(repl_alias) = aliases.get(id) {
+                if let Some(repl_alias) = aliases.get(&namespaces.resolve(id)) {
                     let prefix = &alias_str[0..range.start];
                     let suffix = &alias_str[range.end..];
                     alias_parts.push(prefix.to_string());
@@ -68,6 +148,39 @@ impl AliasesRepository {
         Ok(t_alias)
     }
 
+    /// steps_command chains the commands of the steps of an alias, each in a subshell,
+    /// the next step runs only if the previous one succeeded unless errors are ignored,
+    /// and gathers their environments. It's what the alias shows and resolves its vars
+    /// from, the engine runs the steps one by one.
+    fn steps_command(
+        alias: &Alias,
+        aliases: &HashMap<Identifier, Alias>,
+        namespaces: &NamespaceAliases,
+        visiting: &mut Vec<Identifier>,
+    ) -> Result<(String, HashMap<String, String>), ErrorsAliasesRepository> {
+        if visiting.contains(&alias.identifier()) {
+            return Err(ErrorsAliasesRepository::CyclicSteps(alias.identifier()));
+        }
+        visiting.push(alias.identifier());
+        let mut commands = vec![];
+        let mut env = HashMap::new();
+        for id in alias.steps() {
+            let step = aliases.get(&namespaces.resolve(&id)).ok_or_else(|| {
+                ErrorsAliasesRepository::MissingDependencies(alias.identifier(), id.clone())
+            })?;
+            let (command, step_env) = if step.steps().is_empty() {
+                (step.sanitized_alias(), step.sanitized_env())
+            } else {
+                Self::steps_command(step, aliases, namespaces, visiting)?
+            };
+            commands.push(format!("( {} )", command));
+            env.extend(step_env);
+        }
+        visiting.pop();
+        let separator = if alias.ignore_errors() { "; " } else { " && " };
+        Ok((commands.join(separator), env))
+    }
+
     fn parse(alias: &Alias) -> Vec<(Range<usize>, Identifier)> {
         let default_namespace = alias.identifier().namespace;
         ALIASESRE
@@ -86,11 +199,18 @@ impl AliasesRepository {
 
 impl AliasCollection for AliasesRepository {
     fn get(&self, id: &Identifier) -> Option<&Alias> {
-        self.aliases.get(id)
+        self.aliases.get(&self.namespaces.resolve(id))
     }
 
     fn aliases(&self) -> Vec<&Alias> {
-        self.aliases.values().collect()
+        let mut aliases = self.visible_aliases();
+        let score = |a: &Alias| self.usage.get(&a.identifier()).copied().unwrap_or(0.0);
+        aliases.sort_by(|a, b| {
+            score(b)
+                .total_cmp(&score(a))
+                .then_with(|| a.full_name().cmp(&b.full_name()))
+        });
+        aliases
     }
 }
 
@@ -98,6 +218,10 @@ impl AliasCollection for AliasesRepository {
 pub enum ErrorsAliasesRepository {
     #[error("Alias '{0}' has a missing dependency: '{1}'")]
     MissingDependencies(Identifier, Identifier),
+    #[error("Alias '{0}' is one of its own steps")]
+    CyclicSteps(Identifier),
+    #[error("Alias '{0}' has both an alias and steps, it can only have one of them")]
+    StepsWithAlias(Identifier),
     #[error("Alias selection failed because \n-> {0}")]
     AliasSelectionFailure(#[from] ErrorsDependencies),
     #[error("Invalid alias selected {0}")]
@@ -106,11 +230,15 @@ pub enum ErrorsAliasesRepository {
 
 #[cfg(test)]
 mod tests {
-    use super::AliasesRepository;
+    use super::{AliasesRepository, ErrorsAliasesRepository};
     use maplit::hashmap;
+    use sam_core::engines::AliasCollection;
     use sam_core::entities::aliases::fixtures::*;
-    use sam_core::entities::aliases::Alias;
+    use sam_core::entities::aliases::{Alias, Impact};
+    use sam_core::entities::commands::Command;
     use sam_core::entities::identifiers::fixtures::*;
+    use sam_core::entities::identifiers::Identifier;
+    use sam_core::entities::namespaces::{NamespaceAliases, NamespaceUpdater};
     use std::ops::Range;
     #[test]
     fn parse_test() {
@@ -119,7 +247,7 @@ mod tests {
         assert!(parsed.len() == 2);
         assert!(parsed[0].0 == Range { start: 9, end: 19 });
         assert!(parsed[0].1.name() == "toto");
-        assert!(parsed[0].1.namespace == None);
+        assert!(parsed[0].1.namespace.is_none());
         assert!(parsed[1].0 == Range { start: 22, end: 35 });
         assert!(parsed[1].1.name() == "toto");
         assert!(parsed[1].1.namespace == Some("ns".to_string()));
@@ -132,14 +260,16 @@ mod tests {
            ALIAS_GREP_DIR_NAME.clone() => ALIAS_GREP_DIR.clone(),
         };
         let a = ALIAS_GREP_DIR.clone();
-        let up_alias = AliasesRepository::substitute_alias_defs(&a, &aliases);
+        let namespaces = NamespaceAliases::default();
+        let up_alias = AliasesRepository::substitute_alias_defs(&a, &aliases, &namespaces);
         assert!(up_alias.is_ok());
         assert_eq!(
             "ls {{ dirs::directory }}|grep {{ pattern }}",
             up_alias.unwrap().alias()
         );
         let a_no_ns = ALIAS_GREP_DIR_NO_NS.clone();
-        let up_alias_no_ns = AliasesRepository::substitute_alias_defs(&a_no_ns, &aliases);
+        let up_alias_no_ns =
+            AliasesRepository::substitute_alias_defs(&a_no_ns, &aliases, &namespaces);
         assert!(up_alias_no_ns.is_ok());
         assert_eq!(
             "ls {{ dirs::directory }}| grep {{ pattern }}",
@@ -163,4 +293,167 @@ mod tests {
             alias.unwrap().alias()
         );
     }
+
+    #[test]
+    fn aliases_ordered_by_usage() {
+        let aliases = vec![ALIAS_LS_DIR.clone(), ALIAS_GREP_DIR.clone()];
+        let ar = AliasesRepository::new(aliases.into_iter()).unwrap();
+        let names = |ar: &AliasesRepository| -> Vec<String> {
+            AliasCollection::aliases(ar)
+                .iter()
+                .map(|a| a.full_name().to_string())
+                .collect()
+        };
+        let alphabetical = names(&ar);
+        let mut sorted = alphabetical.clone();
+        sorted.sort();
+        assert_eq!(alphabetical, sorted);
+
+        let ar = ar.with_usage(hashmap! {ALIAS_LS_DIR_NAME.clone() => 3.0});
+        assert_eq!(names(&ar)[0], ALIAS_LS_DIR.full_name().to_string());
+    }
+
+    #[test]
+    fn steps() {
+        let in_ns = |mut alias: Alias, ns: &str| {
+            NamespaceUpdater::update(&mut alias, ns);
+            alias
+        };
+        let build = in_ns(
+            Alias::new("build", "builds", "cargo build --profile {{ profile }}"),
+            "rust",
+        );
+        let deploy = in_ns(
+            Alias::new("deploy", "deploys", "./deploy.sh {{ env }}").with_env(
+                hashmap! {String::from("KUBECONFIG") => String::from("~/.kube/{{ cluster }}")},
+            ),
+            "ci",
+        );
+        let release = in_ns(
+            Alias::new("release", "releases", "").with_steps(
+                vec![String::from("rust::build"), String::from("deploy")],
+                false,
+            ),
+            "ci",
+        );
+        let cleanup = in_ns(
+            Alias::new("cleanup", "cleans up", "").with_steps(
+                vec![String::from("release"), String::from("rust::build")],
+                true,
+            ),
+            "ci",
+        );
+        let ar = AliasesRepository::new(
+            vec![build, deploy, release.clone(), cleanup.clone()].into_iter(),
+        )
+        .unwrap();
+        assert_eq!(
+            ar.get(&release.identifier()).unwrap().alias(),
+            "( cargo build --profile {{ rust::profile }} ) && ( ./deploy.sh {{ ci::env }} )"
+        );
+        // the vars of the environments of the steps are resolved with the workflow.
+        assert!(ar
+            .get(&release.identifier())
+            .unwrap()
+            .dependencies()
+            .contains(&Identifier::with_namespace("cluster", Some("ci"))));
+        assert_eq!(
+            ar.get(&cleanup.identifier()).unwrap().alias(),
+            "( ( cargo build --profile {{ rust::profile }} ) && ( ./deploy.sh {{ ci::env }} ) ); ( cargo build --profile {{ rust::profile }} )"
+        );
+
+        let cyclic = Alias::new("loop", "loops", "").with_steps(vec![String::from("loop")], false);
+        assert!(AliasesRepository::new(vec![cyclic].into_iter()).is_err());
+        let missing =
+            Alias::new("missing", "misses", "").with_steps(vec![String::from("nope")], false);
+        assert!(AliasesRepository::new(vec![missing].into_iter()).is_err());
+        let both =
+            Alias::new("both", "has both", "ls").with_steps(vec![String::from("list")], false);
+        let list = Alias::new("list", "lists", "ls");
+        assert!(matches!(
+            AliasesRepository::new(vec![both, list].into_iter()),
+            Err(ErrorsAliasesRepository::StepsWithAlias(_))
+        ));
+    }
+
+    #[test]
+    fn namespace_aliases() {
+        let mut pods = Alias::new("pods", "lists the pods", "kubectl get pods");
+        NamespaceUpdater::update(&mut pods, "kubernetes");
+        let mut count = Alias::new("count", "counts the pods", "[[ k8s::pods ]] | wc -l");
+        NamespaceUpdater::update(&mut count, "ops");
+        let mut check = Alias::new("check", "checks", "").with_steps(
+            vec![String::from("k8s::pods"), String::from("count")],
+            false,
+        );
+        NamespaceUpdater::update(&mut check, "ops");
+        let aliases = vec![pods, count, check];
+        assert!(AliasesRepository::new(aliases.clone().into_iter()).is_err());
+
+        let namespaces = NamespaceAliases::new(hashmap! {
+            String::from("k8s") => String::from("kubernetes"),
+        });
+        let ar =
+            AliasesRepository::with_namespace_aliases(aliases.into_iter(), namespaces).unwrap();
+        let k8s_pods = Identifier::with_namespace("pods", Some("k8s"));
+        assert_eq!(ar.get(&k8s_pods).unwrap().full_name(), "kubernetes::pods");
+        let count = Identifier::with_namespace("count", Some("ops"));
+        assert_eq!(ar.get(&count).unwrap().alias(), "kubectl get pods | wc -l");
+        let check = Identifier::with_namespace("check", Some("ops"));
+        assert_eq!(
+            ar.get(&check).unwrap().alias(),
+            "( kubectl get pods ) && ( kubectl get pods | wc -l )"
+        );
+    }
+
+    #[test]
+    fn hidden_aliases() {
+        let aliases = vec![
+            ALIAS_LS_DIR.clone().with_hidden(true),
+            ALIAS_GREP_DIR.clone(),
+        ];
+
+        let ar = AliasesRepository::new(aliases.into_iter()).unwrap();
+        let visible = AliasCollection::aliases(&ar);
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].identifier(), ALIAS_GREP_DIR_NAME.clone());
+        assert!(ar.get(&ALIAS_LS_DIR_NAME.clone()).is_some());
+
+        let ar = ar.with_hidden(true);
+        assert_eq!(AliasCollection::aliases(&ar).len(), 2);
+    }
+
+    #[test]
+    fn impacts() {
+        let aliases = vec![
+            ALIAS_LS_DIR.clone().with_impact(Some(Impact::High), None),
+            ALIAS_GREP_DIR.clone(),
+        ];
+
+        let ar = AliasesRepository::new(aliases.into_iter()).unwrap();
+        assert_eq!(AliasCollection::aliases(&ar).len(), 2);
+        let ar = ar.with_impacts(vec![Impact::High, Impact::Medium]);
+        let visible = AliasCollection::aliases(&ar);
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].identifier(), ALIAS_LS_DIR_NAME.clone());
+        assert!(ar.get(&ALIAS_GREP_DIR_NAME.clone()).is_some());
+    }
+
+    #[test]
+    fn tags() {
+        let aliases = vec![
+            ALIAS_LS_DIR
+                .clone()
+                .with_tags(vec![String::from("k8s"), String::from("prod")]),
+            ALIAS_GREP_DIR.clone().with_tags(vec![String::from("k8s")]),
+        ];
+
+        let ar = AliasesRepository::new(aliases.into_iter()).unwrap();
+        let ar = ar.with_tags(vec![String::from("k8s")]);
+        assert_eq!(AliasCollection::aliases(&ar).len(), 2);
+        let ar = ar.with_tags(vec![String::from("k8s"), String::from("prod")]);
+        let visible = AliasCollection::aliases(&ar);
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].identifier(), ALIAS_LS_DIR_NAME.clone());
+    }
 }
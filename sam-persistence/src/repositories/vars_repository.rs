@@ -4,6 +4,7 @@ use sam_core::entities::choices::Choice;
 use sam_core::entities::commands::Command;
 use sam_core::entities::dependencies::ErrorsDependencies;
 use sam_core::entities::identifiers::{Identifier, Identifiers};
+use sam_core::entities::namespaces::NamespaceAliases;
 use sam_core::entities::vars::Var;
 use std::collections::{HashMap, HashSet};
 use thiserror::Error;
@@ -12,6 +13,7 @@ use thiserror::Error;
 pub struct VarsRepository {
     vars: HashSet<Var>,
     defaults: HashMap<Identifier, Vec<Choice>>,
+    namespaces: NamespaceAliases,
 }
 
 impl VarsRepository {
@@ -22,6 +24,7 @@ impl VarsRepository {
         VarsRepository {
             vars,
             defaults: HashMap::default(),
+            namespaces: NamespaceAliases::default(),
         }
     }
 
@@ -30,7 +33,18 @@ impl VarsRepository {
         defaults: HashMap<Identifier, Vec<Choice>>,
     ) -> Self {
         let vars: HashSet<Var> = value.collect();
-        VarsRepository { vars, defaults }
+        VarsRepository {
+            vars,
+            defaults,
+            namespaces: NamespaceAliases::default(),
+        }
+    }
+
+    /// with_namespace_aliases lets the defaults, the choices given with `-c` among them,
+    /// address the vars with the aliases of their namespaces.
+    pub fn with_namespace_aliases(mut self, namespaces: NamespaceAliases) -> Self {
+        self.namespaces = namespaces;
+        self
     }
 
     pub fn merge(&mut self, other: VarsRepository) {
@@ -53,6 +67,61 @@ impl VarsRepository {
         }
     }
 
+    /// cycles returns the vars that depend on each other, each cycle starts and ends with the same var.
+    pub fn cycles(&self) -> Vec<Vec<Identifier>> {
+        let mut names = self.identifiers();
+        names.sort_by_key(|id| id.to_string());
+        let mut done = HashSet::new();
+        let mut cycles = vec![];
+        for name in names {
+            self.find_cycles(name, &mut vec![], &mut done, &mut cycles);
+        }
+        cycles
+    }
+
+    // find_cycles walks the dependencies of a var depth first, a dependency that is
+    // already on the path closes a cycle.
+    fn find_cycles(
+        &self,
+        name: Identifier,
+        path: &mut Vec<Identifier>,
+        done: &mut HashSet<Identifier>,
+        cycles: &mut Vec<Vec<Identifier>>,
+    ) {
+        if let Some(start) = path.iter().position(|id| *id == name) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(name);
+            cycles.push(cycle);
+            return;
+        }
+        if done.contains(&name) {
+            return;
+        }
+        let dependencies = self.vars.get(&name).map(Var::dependencies);
+        path.push(name);
+        for dependency in dependencies.into_iter().flatten() {
+            self.find_cycles(dependency, path, done, cycles);
+        }
+        if let Some(name) = path.pop() {
+            done.insert(name);
+        }
+    }
+
+    pub fn identifiers(&self) -> Vec<Identifier> {
+        self.vars.iter().map(Var::name).collect()
+    }
+
+    /// expand_wildcards resolves the `{{ *::name }}` references of the vars
+    /// among the vars of the repository.
+    pub fn expand_wildcards(&mut self) {
+        let known = self.identifiers();
+        self.vars = self
+            .vars
+            .drain()
+            .map(|var| var.with_wildcards_expanded(&known))
+            .collect();
+    }
+
     pub fn vars_iter(&self) -> impl Iterator<Item = &Var> {
         self.vars.iter()
     }
@@ -66,13 +135,19 @@ impl VarsDefaultValuesSetter for VarsRepository {
                 identifiers.push(key.clone());
             }
         }
-        self.defaults = defaults.to_owned();
+        self.defaults = defaults
+            .iter()
+            .map(|(id, choices)| (self.namespaces.resolve(id), choices.clone()))
+            .collect();
     }
 }
 
 impl VarsDefaultValues for VarsRepository {
-    fn default_value(&self, id: &Identifier) -> Option<&Choice> {
-        self.defaults.get(id).and_then(|v| v.first())
+    fn default_values(&self, id: &Identifier) -> Option<&[Choice]> {
+        self.defaults
+            .get(id)
+            .map(Vec::as_slice)
+            .filter(|c| !c.is_empty())
     }
 }
 
@@ -91,7 +166,7 @@ pub enum ErrorsVarsRepository {
     #[error("no choices available for var {var_name}\n-> {error}")]
     NoChoiceForVar {
         var_name: Identifier,
-        error: ErrorsDependencies,
+        error: Box<ErrorsDependencies>,
     },
 }
 
@@ -99,6 +174,7 @@ pub enum ErrorsVarsRepository {
 mod tests {
     use super::*;
     use sam_core::entities::identifiers::fixtures::*;
+    use sam_core::entities::namespaces::NamespaceUpdater;
     use sam_core::entities::vars::fixtures::*;
 
     #[test]
@@ -120,4 +196,52 @@ mod tests {
             _ => unreachable!(),
         }
     }
+
+    #[test]
+    fn test_var_repository_namespace_aliases() {
+        let mut env = Var::new("env", "the environment", vec![]);
+        NamespaceUpdater::update(&mut env, "kubernetes");
+        let mut repo = VarsRepository::new(vec![env.clone()].into_iter()).with_namespace_aliases(
+            NamespaceAliases::new(maplit::hashmap! {
+                String::from("k8s") => String::from("kubernetes"),
+            }),
+        );
+        repo.set_defaults(&maplit::hashmap! {
+            Identifier::with_namespace("env", Some("k8s")) => vec![Choice::from_value("prod")],
+        });
+        assert_eq!(
+            repo.default_values(&env.name()),
+            Some(vec![Choice::from_value("prod")].as_slice())
+        );
+    }
+
+    #[test]
+    fn test_var_repository_cycles() {
+        let repo = VarsRepository::new(
+            vec![
+                Var::from_command("a", "desc", "echo {{ b }}"),
+                Var::from_command("b", "desc", "echo {{ c }}"),
+                Var::from_command("c", "desc", "echo {{ a }}"),
+                Var::from_command("d", "desc", "echo {{ a }} {{ d }}"),
+                Var::from_command("e", "desc", "echo {{ a }}"),
+            ]
+            .into_iter(),
+        );
+        let ids = |names: &[&str]| -> Vec<Identifier> {
+            names.iter().map(|name| Identifier::new(*name)).collect()
+        };
+        assert_eq!(
+            repo.cycles(),
+            vec![ids(&["a", "b", "c", "a"]), ids(&["d", "d"])]
+        );
+        let full = VarsRepository::new(
+            vec![
+                VAR_DIRECTORY.clone(),
+                VAR_LISTING.clone(),
+                VAR_PATTERN.clone(),
+            ]
+            .into_iter(),
+        );
+        assert!(full.cycles().is_empty());
+    }
 }
@@ -4,6 +4,7 @@ use rustbreak::RustbreakError;
 use std::marker::PhantomData;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
 
 use serde::de::DeserializeOwned;
 use serde::Serialize;
@@ -13,6 +14,8 @@ use thiserror::Error;
 pub struct SequentialState<V> {
     path: PathBuf,
     max_size: Option<usize>,
+    // an in memory state shares its entries between its clones and is never saved.
+    memory: Option<Arc<RwLock<Vec<V>>>>,
     _marker: PhantomData<V>,
 }
 
@@ -47,15 +50,31 @@ where
         let db = SequentialState {
             path: p.as_ref().to_owned(),
             max_size,
-            _marker: PhantomData::default(),
+            memory: None,
+            _marker: PhantomData,
         };
         db.open_db()?;
         Ok(db)
     }
 
+    /// in_memory creates a state holding entries that is never written to disk,
+    /// it's meant for when the file of the state can't be written.
+    pub fn in_memory(mut entries: Vec<V>, max_size: Option<usize>) -> Self {
+        if let Some(max_size) = max_size {
+            if entries.len() > max_size {
+                entries.drain(..entries.len() - max_size);
+            }
+        }
+        SequentialState {
+            path: PathBuf::new(),
+            max_size,
+            memory: Some(Arc::new(RwLock::new(entries))),
+            _marker: PhantomData,
+        }
+    }
+
     pub fn push(&self, entry: V) -> ModResult<()> {
-        let db = self.open_db()?;
-        db.write(|db| {
+        self.write(|db| {
             db.push(entry);
             if let Some(max_size) = self.max_size {
                 if db.len() > max_size {
@@ -63,44 +82,83 @@ where
                 }
             }
         })
-        .map_err(ErrorSequentialState::WriteFailures)?;
-        db.save().map_err(ErrorSequentialState::SaveFailures)
     }
 
     #[allow(dead_code)]
     pub fn last(&self) -> ModResult<Option<V>> {
-        let db = self.open_db()?;
-        db.read(|db| db.last().map(Clone::clone))
-            .map_err(ErrorSequentialState::ReadFailure)
+        self.read(|db| db.last().cloned())
     }
 
     #[allow(dead_code)]
     pub fn first(&self) -> ModResult<Option<V>> {
-        let db = self.open_db()?;
-        db.read(|db| db.first().map(Clone::clone))
-            .map_err(ErrorSequentialState::ReadFailure)
+        self.read(|db| db.first().cloned())
     }
 
     pub fn entries(&self) -> ModResult<impl Iterator<Item = V>> {
-        let db = self.open_db()?;
-        db.read(|db| db.clone().into_iter())
-            .map_err(ErrorSequentialState::ReadFailure)
+        self.read(|db| db.clone().into_iter())
+    }
+
+    /// replace overwrites the state with entries, keeping the last ones when
+    /// they don't all fit.
+    pub fn replace(&self, mut entries: Vec<V>) -> ModResult<()> {
+        if let Some(max_size) = self.max_size {
+            if entries.len() > max_size {
+                entries.drain(..entries.len() - max_size);
+            }
+        }
+        self.write(|db| *db = entries)
     }
 
     #[allow(dead_code)]
     pub fn delete(&self, position: usize) -> ModResult<()> {
-        let db = self.open_db()?;
-        db.write(|db| {
+        self.write(|db| {
             db.remove(position);
         })
-        .map_err(ErrorSequentialState::WriteFailures)?;
+    }
+
+    /// overwrite writes entries to a state without reading it first, it's
+    /// meant to rewrite a state that can't be read anymore.
+    pub fn overwrite(p: impl AsRef<Path>, entries: Vec<V>) -> ModResult<()> {
+        let db = Fdb::<V>::create_at_path(p.as_ref(), entries)
+            .map_err(ErrorSequentialState::CreationFailure)?;
         db.save().map_err(ErrorSequentialState::SaveFailures)
     }
 
+    // a poisoned lock only means a thread panicked while holding it, the entries are still usable.
+    fn read<T>(&self, f: impl FnOnce(&Vec<V>) -> T) -> ModResult<T> {
+        match &self.memory {
+            Some(memory) => Ok(f(&memory.read().unwrap_or_else(|e| e.into_inner()))),
+            None => {
+                let db = self.open_db()?;
+                db.read(f).map_err(ErrorSequentialState::ReadFailure)
+            }
+        }
+    }
+
+    fn write(&self, f: impl FnOnce(&mut Vec<V>)) -> ModResult<()> {
+        match &self.memory {
+            Some(memory) => {
+                f(&mut memory.write().unwrap_or_else(|e| e.into_inner()));
+                Ok(())
+            }
+            None => {
+                let db = self.open_db()?;
+                db.write(f).map_err(ErrorSequentialState::WriteFailures)?;
+                db.save().map_err(ErrorSequentialState::SaveFailures)
+            }
+        }
+    }
+
+    // a state that exists but can't be read is not replaced by an empty one,
+    // its entries would be lost.
     fn open_db(&self) -> ModResult<Fdb<V>> {
-        Fdb::<V>::load_from_path(&self.path)
-            .or_else(|_| Fdb::<V>::create_at_path(&self.path, vec![]))
-            .map_err(ErrorSequentialState::OpenFailure)
+        let is_empty = std::fs::metadata(&self.path).map_or(true, |m| m.len() == 0);
+        if is_empty {
+            Fdb::<V>::create_at_path(&self.path, vec![])
+                .map_err(ErrorSequentialState::CreationFailure)
+        } else {
+            Fdb::<V>::load_from_path(&self.path).map_err(ErrorSequentialState::OpenFailure)
+        }
     }
 }
 
@@ -139,6 +197,19 @@ mod tests {
         assert_eq!(returned_values, values);
     }
 
+    #[test]
+    fn test_sequential_state_replace() {
+        let f = TempFile::new().expect("failed to created a temporary file");
+        let state = SequentialState::new(f.path, Some(3)).expect("failed to create a new db");
+        insert_values(&state, &[1, 2]).expect("could not into state");
+        state
+            .replace(vec![3, 4, 5, 6])
+            .expect("could not replace the state");
+        let returned_values: Vec<i32> =
+            state.entries().expect("call to into_iter failed").collect();
+        assert_eq!(returned_values, vec![4, 5, 6]);
+    }
+
     #[test]
     fn test_sequential_state_first_last() {
         let values = vec![1, 2, 3, 4, 7];
@@ -148,4 +219,22 @@ mod tests {
         assert_eq!(state.first().expect("could not get first element"), Some(1));
         assert_eq!(state.last().expect("could not get last element"), Some(7));
     }
+
+    #[test]
+    fn test_sequential_state_in_memory() {
+        let state = SequentialState::in_memory(vec![1, 2, 3], Some(3));
+        let shared = state.clone();
+        insert_values(&shared, &[4, 5]).expect("could not into state");
+        let returned_values: Vec<i32> =
+            state.entries().expect("call to into_iter failed").collect();
+        assert_eq!(returned_values, vec![3, 4, 5]);
+        state.delete(0).expect("could not delete from state");
+        assert_eq!(state.first().expect("could not get first element"), Some(4));
+
+        let truncated = SequentialState::in_memory(vec![1, 2, 3], Some(2));
+        assert_eq!(
+            truncated.first().expect("could not get first element"),
+            Some(2)
+        );
+    }
 }
@@ -1,8 +1,14 @@
 use serde::Deserialize;
 use serde::Serialize;
-use std::path::Path;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
 use std::time::Duration;
-use std::time::SystemTimeError;
+use std::time::{SystemTime, SystemTimeError};
 use thiserror::Error;
 
 use crate::associative_state::AssociativeStateWithTTL;
@@ -12,36 +18,208 @@ pub trait VarsCache {
     fn put(
         &self,
         name: &dyn AsRef<str>,
-        command: &dyn AsRef<str>,
+        key: &CacheKey,
         output: &dyn AsRef<str>,
+        ttl: Option<Duration>,
     ) -> Result<(), CacheError>;
-    fn get(&self, command: &dyn AsRef<str>) -> Result<Option<String>, CacheError>;
+    fn get(&self, key: &CacheKey) -> Result<Option<String>, CacheError>;
+    /// lock prevents other sam processes from running the command of a key while
+    /// the lock is held, it is released when dropped.
+    fn lock(&self, _key: &CacheKey) -> Result<CacheLock, CacheError> {
+        Ok(CacheLock::default())
+    }
+}
+
+/// LOCK_TIMEOUT is how long a process waits for another one to fill the cache
+/// before running the command itself, locks older than that are considered stale.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// tells apart the locks taken by the same process.
+static LOCK_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// CacheLock is a lock file holding the token of its owner, removed when the lock is
+/// dropped unless another process took it over in the meantime.
+#[derive(Debug, Default)]
+pub struct CacheLock {
+    path: Option<PathBuf>,
+    token: String,
+}
+
+impl CacheLock {
+    /// acquire waits for the lock file to be released by another process, up to
+    /// the timeout. Past the timeout, the lock is considered stale and taken over,
+    /// the timeout starts over whenever the lock changes hands.
+    fn acquire(path: PathBuf, timeout: Duration) -> Result<CacheLock, CacheError> {
+        let token = Self::token();
+        let mut holder = None;
+        let mut waited = Duration::ZERO;
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut file) => {
+                    let lock = CacheLock {
+                        path: Some(path),
+                        token,
+                    };
+                    file.write_all(lock.token.as_bytes())
+                        .map_err(CacheError::LockFailure)?;
+                    return Ok(lock);
+                }
+                Err(err) if err.kind() != io::ErrorKind::AlreadyExists => {
+                    return Err(CacheError::LockFailure(err))
+                }
+                Err(_) => {
+                    let current = Self::holder(&path);
+                    if current != holder {
+                        holder = current;
+                        waited = Duration::ZERO;
+                    } else if waited >= timeout {
+                        if let Some(stale) = &holder {
+                            Self::release(&path, stale).map_err(CacheError::LockFailure)?;
+                        }
+                        waited = Duration::ZERO;
+                        continue;
+                    }
+                    thread::sleep(LOCK_POLL_INTERVAL);
+                    waited += LOCK_POLL_INTERVAL;
+                }
+            }
+        }
+    }
+
+    // token identifies the owner of a lock: the pid of the process and a nonce.
+    fn token() -> String {
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+        let count = LOCK_COUNTER.fetch_add(1, Ordering::Relaxed);
+        format!("{}-{:x}-{}", std::process::id(), nanos, count)
+    }
+
+    // holder returns the token of the owner of a lock file, None when it's gone.
+    fn holder(path: &Path) -> Option<String> {
+        fs::read_to_string(path).ok()
+    }
+
+    // release removes the lock file if it still belongs to the owner of the token.
+    fn release(path: &Path, token: &str) -> io::Result<()> {
+        if Self::holder(path).as_deref() != Some(token) {
+            return Ok(());
+        }
+        fs::remove_file(path).or_else(|err| match err.kind() {
+            io::ErrorKind::NotFound => Ok(()),
+            _ => Err(err),
+        })
+    }
+}
+
+impl Drop for CacheLock {
+    fn drop(&mut self) {
+        if let Some(path) = &self.path {
+            let _ = Self::release(path, &self.token);
+        }
+    }
+}
+
+/// fnv1a hashes the bytes with 64 bits FNV-1a, which unlike the hasher of the
+/// standard library gives the same hash from one build of sam to the next.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// CacheKey identifies the output of a `from_command` var: the command once the
+/// choices of its dependencies are substituted, along with the environment
+/// variables declared by the alias that the command runs with.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct CacheKey {
+    pub command: String,
+    pub env: Vec<(String, String)>,
+}
+
+impl CacheKey {
+    pub fn new(command: impl Into<String>, env: &HashMap<String, String>) -> Self {
+        let mut env: Vec<(String, String)> =
+            env.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        env.sort();
+        CacheKey {
+            command: command.into(),
+            env,
+        }
+    }
+}
+
+impl Display for CacheKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.command)?;
+        if !self.env.is_empty() {
+            let env: Vec<String> = self
+                .env
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect();
+            write!(f, " [{}]", env.join(" "))?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
 pub struct RustBreakCache {
     state: AssociativeStateWithTTL<CacheEntry>,
+    locks_dir: PathBuf,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct CacheEntry {
     pub name: String,
     pub command: String,
+    #[serde(default)]
+    pub env: Vec<(String, String)>,
     pub output: String,
 }
 
+impl CacheEntry {
+    pub fn key(&self) -> CacheKey {
+        CacheKey {
+            command: self.command.clone(),
+            env: self.env.clone(),
+        }
+    }
+}
+
 impl RustBreakCache {
     pub fn with_ttl(p: impl AsRef<Path>, ttl: &Duration) -> Result<Self, CacheError> {
+        let mut locks_dir = p.as_ref().as_os_str().to_owned();
+        locks_dir.push(".locks");
         Ok(RustBreakCache {
             state: AssociativeStateWithTTL::<CacheEntry>::with_ttl(p, ttl)?,
+            locks_dir: PathBuf::from(locks_dir),
         })
     }
 
+    fn lock_with_timeout(
+        &self,
+        key: &CacheKey,
+        timeout: Duration,
+    ) -> Result<CacheLock, CacheError> {
+        fs::create_dir_all(&self.locks_dir).map_err(CacheError::LockFailure)?;
+        let hash = fnv1a(key.to_string().as_bytes());
+        let path = self.locks_dir.join(format!("{:016x}", hash));
+        CacheLock::acquire(path, timeout)
+    }
+
     pub fn entries(&self) -> Result<impl Iterator<Item = CacheEntry>, CacheError> {
         Ok(self.state.entries()?.map(|(_, v)| v))
     }
 
-    pub fn delete(&self, key: &str) -> Result<Option<CacheEntry>, CacheError> {
+    pub fn delete(&self, key: impl AsRef<str>) -> Result<Option<CacheEntry>, CacheError> {
         Ok(self.state.delete(key)?)
     }
 
@@ -57,21 +235,25 @@ impl VarsCache for RustBreakCache {
     fn put(
         &self,
         name: &dyn AsRef<str>,
-        command: &dyn AsRef<str>,
+        key: &CacheKey,
         output: &dyn AsRef<str>,
+        ttl: Option<Duration>,
     ) -> Result<(), CacheError> {
-        let key = command.as_ref().to_string();
         let entry = CacheEntry {
             name: name.as_ref().to_string(),
-            command: key.clone(),
+            command: key.command.clone(),
+            env: key.env.clone(),
             output: output.as_ref().to_string(),
         };
-        Ok(self.state.put(key, entry)?)
+        Ok(self.state.put_with_ttl(key.to_string(), entry, ttl)?)
+    }
+
+    fn get(&self, key: &CacheKey) -> Result<Option<String>, CacheError> {
+        Ok(self.state.get(key.to_string())?.map(|v| v.output))
     }
 
-    fn get(&self, command: &dyn AsRef<str>) -> Result<Option<String>, CacheError> {
-        let cache_key = command.as_ref();
-        Ok(self.state.get(cache_key)?.map(|v| v.output))
+    fn lock(&self, key: &CacheKey) -> Result<CacheLock, CacheError> {
+        self.lock_with_timeout(key, LOCK_TIMEOUT)
     }
 }
 
@@ -81,12 +263,13 @@ impl VarsCache for NoopVarsCache {
     fn put(
         &self,
         _name: &dyn AsRef<str>,
-        _command: &dyn AsRef<str>,
+        _key: &CacheKey,
         _output: &dyn AsRef<str>,
+        _ttl: Option<Duration>,
     ) -> Result<(), CacheError> {
         Ok(())
     }
-    fn get(&self, _command: &dyn AsRef<str>) -> Result<Option<String>, CacheError> {
+    fn get(&self, _key: &CacheKey) -> Result<Option<String>, CacheError> {
         Ok(None)
     }
 }
@@ -99,13 +282,16 @@ pub enum CacheError {
     CantGetTimeStamp(#[from] SystemTimeError),
     #[error("could not interract with cache because\n-> {0}")]
     ErrAssociativeState(#[from] ErrorAssociativeState),
+    #[error("could not lock the cache because\n-> {0}")]
+    LockFailure(io::Error),
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::vars_cache::{RustBreakCache, VarsCache};
+    use crate::vars_cache::{fnv1a, CacheKey, RustBreakCache, VarsCache};
     use sam_utils::fsutils::TempFile;
-    use std::time::Duration;
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
 
     #[test]
     pub fn test_rustbreak_cache() {
@@ -115,14 +301,15 @@ mod tests {
         cache
             .put(
                 &String::from("name"),
-                &String::from("command"),
+                &CacheKey::new("command", &HashMap::new()),
                 &String::from("output"),
+                None,
             )
             .expect("can't write in rustbreak cache");
 
         let cache2 = RustBreakCache::with_ttl(&tmp_dir.path, &ttl).expect("Can't open cache");
         let value = cache2
-            .get(&String::from("command"))
+            .get(&CacheKey::new("command", &HashMap::new()))
             .expect("can't read from rustbreak cache")
             .expect("can't retrieve the value from rustbreak cache");
         assert_eq!(value, "output");
@@ -131,15 +318,105 @@ mod tests {
         cache
             .put(
                 &String::from("name"),
-                &String::from("command2"),
+                &CacheKey::new("command2", &HashMap::new()),
                 &String::from("output"),
+                None,
             )
             .expect("can't write in rustbreak cache");
 
         let value = cache2
-            .get(&String::from("command2"))
+            .get(&CacheKey::new("command2", &HashMap::new()))
             .expect("can't read from rustbreak cache")
             .expect("can't retrieve the value from rustbreak cache");
         assert_eq!(value, "output");
     }
+
+    #[test]
+    pub fn test_cache_key_with_env() {
+        let tmp_dir = TempFile::new().expect("can't create a temporary file");
+        let ttl = Duration::from_secs(90);
+        let cache = RustBreakCache::with_ttl(&tmp_dir.path, &ttl).expect("Can't open cache");
+        let prod = CacheKey::new(
+            "kubectl get ns",
+            &maplit::hashmap! {String::from("KUBECONFIG") => String::from("prod")},
+        );
+        assert_eq!(prod.to_string(), "kubectl get ns [KUBECONFIG=prod]");
+        cache
+            .put(
+                &String::from("namespace"),
+                &prod,
+                &String::from("default"),
+                None,
+            )
+            .expect("can't write in rustbreak cache");
+
+        let staging = CacheKey::new(
+            "kubectl get ns",
+            &maplit::hashmap! {String::from("KUBECONFIG") => String::from("staging")},
+        );
+        let value = cache
+            .get(&staging)
+            .expect("can't read from rustbreak cache");
+        assert_eq!(value, None);
+        let entry = cache
+            .entries()
+            .expect("can't read from rustbreak cache")
+            .next()
+            .expect("can't retrieve the entry from rustbreak cache");
+        assert_eq!(entry.key(), prod);
+    }
+
+    #[test]
+    pub fn test_lock() {
+        let tmp_dir = TempFile::new().expect("can't create a temporary file");
+        let ttl = Duration::from_secs(90);
+        let cache = RustBreakCache::with_ttl(&tmp_dir.path, &ttl).expect("Can't open cache");
+        let key = CacheKey::new("aws ec2 describe-instances", &HashMap::new());
+        let lock = cache.lock(&key).expect("can't lock the cache");
+        let lock_path = lock.path.clone().expect("the lock has no file");
+        assert!(lock_path.exists());
+
+        // a held lock is taken over once the timeout elapsed, the stale holder
+        // leaves the lock of the new one alone when it's dropped.
+        let start = Instant::now();
+        let other = cache
+            .lock_with_timeout(&key, Duration::from_millis(200))
+            .expect("can't lock the cache");
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert_ne!(lock.token, other.token);
+        drop(lock);
+        assert!(lock_path.exists());
+        drop(other);
+        assert!(!lock_path.exists());
+
+        let other_key = CacheKey::new("aws ec2 describe-regions", &HashMap::new());
+        let lock = cache.lock(&key).expect("can't lock the cache");
+        let other = cache.lock(&other_key).expect("can't lock the cache");
+        assert_ne!(lock.path, other.path);
+    }
+
+    #[test]
+    pub fn test_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    pub fn test_per_entry_ttl() {
+        let tmp_dir = TempFile::new().expect("can't create a temporary file");
+        let ttl = Duration::from_secs(90);
+        let cache = RustBreakCache::with_ttl(&tmp_dir.path, &ttl).expect("Can't open cache");
+        let key = CacheKey::new("aws ec2 describe-instances", &HashMap::new());
+        cache
+            .put(
+                &String::from("instances"),
+                &key,
+                &String::from("i-1234"),
+                Some(Duration::from_secs(0)),
+            )
+            .expect("can't write in rustbreak cache");
+        let value = cache.get(&key).expect("can't read from rustbreak cache");
+        assert_eq!(value, None);
+    }
 }
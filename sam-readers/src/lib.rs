@@ -1,6 +1,23 @@
+mod locations;
+mod namespaces;
+mod paths;
 mod readers;
+mod structured;
+mod warnings;
+pub use locations::Location;
+pub use locations::Sources;
+pub use namespaces::NamespaceStrategy;
+pub use namespaces::Namespaces;
+pub use paths::read_paths;
 pub use readers::read_aliases_from_path;
+pub use readers::read_aliases_with_namespaces;
 pub use readers::read_choices;
+pub use readers::read_choices_page;
 pub use readers::read_vars_repository;
+pub use readers::read_vars_with_namespaces;
+pub use readers::ChoicesPage;
 pub use readers::ErrorsAliasRead;
 pub use readers::ErrorsVarRead;
+pub use structured::read_structured_choices;
+pub use warnings::Warning;
+pub use warnings::Warnings;
@@ -0,0 +1,137 @@
+use sam_core::entities::identifiers::Identifier;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Location is where an alias or a var is defined, lines and columns start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: PathBuf,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl Location {
+    pub fn new(file: impl Into<PathBuf>, line: Option<usize>, column: Option<usize>) -> Self {
+        Location {
+            file: file.into(),
+            line,
+            column,
+        }
+    }
+
+    /// of_definition finds the `name:` field of the last definition of an alias or
+    /// a var in a file, the line and column are left out when it can't be found.
+    pub fn of_definition(file: &Path, name: &str) -> Self {
+        let content = fs::read_to_string(file).unwrap_or_default();
+        let position = find_definition(&content, name);
+        Location::new(
+            file,
+            position.map(|(line, _)| line),
+            position.map(|(_, column)| column),
+        )
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        if let Some(column) = self.column {
+            write!(f, ":{}", column)?;
+        }
+        Ok(())
+    }
+}
+
+/// Sources records the file the aliases and the vars in use were read from.
+#[derive(Debug, Clone, Default)]
+pub struct Sources {
+    aliases: HashMap<Identifier, PathBuf>,
+    vars: HashMap<Identifier, PathBuf>,
+}
+
+impl Sources {
+    /// add_alias records the file of an alias, the last definition of an alias takes precedence.
+    pub fn add_alias(&mut self, identifier: Identifier, file: impl Into<PathBuf>) {
+        self.aliases.insert(identifier, file.into());
+    }
+
+    /// add_var records the file of a var, the first definition of a var takes precedence.
+    pub fn add_var(&mut self, identifier: Identifier, file: impl Into<PathBuf>) {
+        self.vars.entry(identifier).or_insert_with(|| file.into());
+    }
+
+    pub fn aliases(&self) -> impl Iterator<Item = &Identifier> {
+        self.aliases.keys()
+    }
+
+    pub fn vars(&self) -> impl Iterator<Item = &Identifier> {
+        self.vars.keys()
+    }
+
+    pub fn alias(&self, identifier: &Identifier) -> Option<Location> {
+        self.aliases
+            .get(identifier)
+            .map(|file| Location::of_definition(file, identifier.name()))
+    }
+
+    pub fn var(&self, identifier: &Identifier) -> Option<Location> {
+        self.vars
+            .get(identifier)
+            .map(|file| Location::of_definition(file, identifier.name()))
+    }
+}
+
+fn find_definition(content: &str, name: &str) -> Option<(usize, usize)> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(idx, line)| {
+            let column = line.find("name:")?;
+            let value = line[column + "name:".len()..]
+                .trim()
+                .trim_matches(|c| c == '\'' || c == '"');
+            (value == name).then_some((idx + 1, column + 1))
+        })
+        .last()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_definition, Location, Sources};
+    use sam_core::entities::identifiers::Identifier;
+
+    #[test]
+    fn test_find_definition() {
+        let content = "- name: list\n  desc: lists\n  alias: ls\n\n-   name: 'show'\n  alias: cat\n- desc: shows\n  name: \"show\"\n";
+        assert_eq!(find_definition(content, "list"), Some((1, 3)));
+        assert_eq!(find_definition(content, "show"), Some((8, 3)));
+        assert_eq!(find_definition(content, "missing"), None);
+    }
+
+    #[test]
+    fn test_sources() {
+        let list = Identifier::with_namespace("list", Some("dirs"));
+        let mut sources = Sources::default();
+        sources.add_alias(list.clone(), "/tmp/first/aliases.yaml");
+        sources.add_alias(list.clone(), "/tmp/second/aliases.yaml");
+        sources.add_var(list.clone(), "/tmp/first/vars.yaml");
+        sources.add_var(list.clone(), "/tmp/second/vars.yaml");
+        let alias = sources.alias(&list).expect("the alias has a source");
+        assert_eq!(alias, Location::new("/tmp/second/aliases.yaml", None, None));
+        assert_eq!(alias.to_string(), "/tmp/second/aliases.yaml");
+        let var = sources.var(&list).expect("the var has a source");
+        assert_eq!(var.file.to_str(), Some("/tmp/first/vars.yaml"));
+        assert_eq!(sources.var(&Identifier::new("missing")), None);
+        assert_eq!(sources.aliases().collect::<Vec<_>>(), vec![&list]);
+        assert_eq!(sources.vars().count(), 1);
+        assert_eq!(
+            Location::new("/tmp/vars.yaml", Some(3), Some(5)).to_string(),
+            "/tmp/vars.yaml:3:5"
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const HEADER_KEY: &str = "namespace:";
+const PATH_SEPARATOR: &str = "_";
+
+/// NamespaceStrategy is how the namespace of the aliases and vars of a file is found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NamespaceStrategy {
+    /// the name of the directory of the file, `k8s` for `team/k8s/aliases.yaml`.
+    #[default]
+    Directory,
+    /// the path of the directory of the file from its root directory, `team_k8s`.
+    Path,
+    /// the `# namespace: k8s` comment at the top of the file, the directory otherwise.
+    Header,
+    /// the aliases and vars have no namespace.
+    None,
+}
+
+/// Namespaces gives the namespace of the files found in the root directories.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Namespaces {
+    strategy: NamespaceStrategy,
+    roots: Vec<PathBuf>,
+}
+
+impl Namespaces {
+    pub fn new(strategy: NamespaceStrategy, roots: impl IntoIterator<Item = PathBuf>) -> Self {
+        Namespaces {
+            strategy,
+            roots: roots.into_iter().collect(),
+        }
+    }
+
+    /// namespace returns the namespace of the aliases or vars of a file given its content.
+    pub fn namespace(&self, path: &Path, content: &str) -> Option<String> {
+        match self.strategy {
+            NamespaceStrategy::Directory => directory(path),
+            NamespaceStrategy::Path => self.relative_path(path).or_else(|| directory(path)),
+            NamespaceStrategy::Header => header(content).or_else(|| directory(path)),
+            NamespaceStrategy::None => None,
+        }
+    }
+
+    // relative_path joins the directories between the deepest root directory holding
+    // the file and the file. Files right under a root directory take its name.
+    fn relative_path(&self, path: &Path) -> Option<String> {
+        let dir = path.parent()?;
+        let relative = self
+            .roots
+            .iter()
+            .filter_map(|root| dir.strip_prefix(root).ok())
+            .min_by_key(|relative| relative.components().count())?;
+        let parts: Vec<&str> = relative.iter().filter_map(|p| p.to_str()).collect();
+        if parts.is_empty() {
+            return None;
+        }
+        Some(parts.join(PATH_SEPARATOR))
+    }
+}
+
+fn directory(path: &Path) -> Option<String> {
+    path.parent()
+        .and_then(|e| e.file_name())
+        .and_then(|e| e.to_str())
+        .map(String::from)
+}
+
+// header looks for `# namespace: name` in the comments at the top of the file.
+fn header(content: &str) -> Option<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .take_while(|l| l.is_empty() || l.starts_with('#'))
+        .filter_map(|l| l.trim_start_matches('#').trim().strip_prefix(HEADER_KEY))
+        .map(str::trim)
+        .find(|ns| !ns.is_empty())
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NamespaceStrategy, Namespaces};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_namespace() {
+        let roots = vec![PathBuf::from("/recipes"), PathBuf::from("/recipes/packs")];
+        let file = Path::new("/recipes/packs/team/k8s/aliases.yaml");
+        let header = "# shared by the team\n# namespace: kube\n- name: pods\n# namespace: nope\n";
+        let namespace = |strategy, path, content| {
+            Namespaces::new(strategy, roots.clone()).namespace(path, content)
+        };
+
+        assert_eq!(
+            namespace(NamespaceStrategy::Directory, file, header),
+            Some(String::from("k8s"))
+        );
+        assert_eq!(
+            namespace(NamespaceStrategy::Path, file, header),
+            Some(String::from("team_k8s"))
+        );
+        assert_eq!(
+            namespace(
+                NamespaceStrategy::Path,
+                Path::new("/recipes/aliases.yaml"),
+                ""
+            ),
+            Some(String::from("recipes"))
+        );
+        assert_eq!(
+            namespace(NamespaceStrategy::Header, file, header),
+            Some(String::from("kube"))
+        );
+        assert_eq!(
+            namespace(
+                NamespaceStrategy::Header,
+                file,
+                "- name: pods\n# namespace: nope\n"
+            ),
+            Some(String::from("k8s"))
+        );
+        assert_eq!(namespace(NamespaceStrategy::None, file, header), None);
+    }
+}
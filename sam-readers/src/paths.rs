@@ -0,0 +1,125 @@
+use sam_core::entities::choices::Choice;
+use sam_core::entities::vars::PathRules;
+use sam_utils::fsutils::replace_home_variable;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// read_paths lists the entries of the directory of a `from_path` var that the rules keep,
+/// sorted. The choices are the paths of the entries, under the directory as it's written,
+/// `~` and `$HOME` being expanded. The hidden directories are only walked with `hidden`.
+pub fn read_paths(directory: &str, rules: &PathRules) -> io::Result<Vec<Choice>> {
+    let directory = match directory.trim() {
+        "" => String::from("."),
+        "~" => replace_home_variable(String::from("$HOME")),
+        dir => match dir.strip_prefix("~/") {
+            Some(rest) => replace_home_variable(format!("$HOME/{}", rest)),
+            None => replace_home_variable(dir.to_string()),
+        },
+    };
+    let root = PathBuf::from(&directory);
+    let mut entries = vec![];
+    walk(&root, "", 1, rules, &mut entries)?;
+    entries.sort();
+    Ok(entries
+        .into_iter()
+        .map(|(relative, is_dir)| {
+            let value = if directory == "." {
+                relative
+            } else {
+                root.join(relative).display().to_string()
+            };
+            Choice::new(value, is_dir.then(|| String::from("directory")))
+        })
+        .collect())
+}
+
+// walk pushes the entries of dir matching the rules, with their path relative to the
+// directory of the var, and goes down the subdirectories up to the depth of the glob.
+fn walk(
+    dir: &Path,
+    prefix: &str,
+    depth: usize,
+    rules: &PathRules,
+    entries: &mut Vec<(String, bool)>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') && !rules.shows_hidden() {
+            continue;
+        }
+        let relative = format!("{}{}", prefix, name);
+        // symlinks to directories are offered but not followed, to avoid loops.
+        let is_dir = entry.path().is_dir();
+        if rules.matches(&relative, is_dir) {
+            entries.push((relative.clone(), is_dir));
+        }
+        let walked = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+        if walked && rules.max_depth().is_none_or(|max| depth < max) {
+            // the subdirectories that can't be read are left out.
+            let _ = walk(
+                &entry.path(),
+                &format!("{}/", relative),
+                depth + 1,
+                rules,
+                entries,
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_paths;
+    use sam_core::entities::choices::Choice;
+    use sam_core::entities::vars::PathRules;
+    use sam_utils::fsutils::TempDirectory;
+    use std::fs;
+
+    #[test]
+    fn test_read_paths() {
+        let dir = TempDirectory::new().unwrap();
+        fs::create_dir_all(dir.path.join("prod/api")).unwrap();
+        fs::create_dir_all(dir.path.join(".git")).unwrap();
+        fs::write(dir.path.join("prod/api/deploy.yaml"), "").unwrap();
+        fs::write(dir.path.join("prod/notes.txt"), "").unwrap();
+        fs::write(dir.path.join("base.yaml"), "").unwrap();
+        fs::write(dir.path.join(".git/config.yaml"), "").unwrap();
+        let root = dir.path.display().to_string();
+
+        let top = read_paths(&root, &PathRules::default()).unwrap();
+        assert_eq!(
+            top,
+            vec![
+                Choice::from_value(format!("{}/base.yaml", root)),
+                Choice::new(format!("{}/prod", root), Some(String::from("directory"))),
+            ]
+        );
+
+        let yaml = PathRules {
+            glob: Some(String::from("**/*.yaml")),
+            ..PathRules::default()
+        };
+        let values: Vec<String> = read_paths(&root, &yaml)
+            .unwrap()
+            .iter()
+            .map(|c| c.value().to_string())
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                format!("{}/base.yaml", root),
+                format!("{}/prod/api/deploy.yaml", root),
+            ]
+        );
+
+        let dirs = PathRules {
+            glob: Some(String::from("**")),
+            dirs_only: Some(true),
+            hidden: Some(true),
+        };
+        assert_eq!(read_paths(&root, &dirs).unwrap().len(), 3);
+        assert!(read_paths(&format!("{}/missing", root), &dirs).is_err());
+    }
+}
@@ -1,28 +1,49 @@
+use crate::locations::Location;
+use crate::namespaces::Namespaces;
 use sam_core::entities::aliases::Alias;
-use sam_core::entities::choices::Choice;
+use sam_core::entities::choices::{Choice, ChoiceFormat};
 use sam_core::entities::namespaces::NamespaceUpdater;
 use sam_core::entities::vars::Var;
 use sam_persistence::repositories::{ErrorsVarsRepository, VarsRepository};
+use sam_utils::interpolation::interpolate;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::env::consts::OS;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufRead, Read};
 use std::path::Path;
 use std::path::PathBuf;
 use thiserror::Error;
 
+const SOPS_SCHEME: &str = "sops:";
+
 pub fn read_aliases_from_path(path: &'_ Path) -> Result<Vec<Alias>, ErrorsAliasRead> {
+    read_aliases_with_namespaces(path, &Namespaces::default())
+}
+
+/// read_aliases_with_namespaces reads the aliases of a file in the namespace
+/// that the strategy of `namespaces` gives it.
+pub fn read_aliases_with_namespaces(
+    path: &'_ Path,
+    namespaces: &Namespaces,
+) -> Result<Vec<Alias>, ErrorsAliasRead> {
     let f = File::open(path)?;
     let l = File::metadata(&f)?.len();
     if l == 0 {
         return Ok(vec![]);
     }
-    let buf = BufReader::new(f);
-    let mut aliases = read_aliases(buf).map_err(|error| ErrorsAliasRead::AliasSerde {
-        error,
-        source_file: path.to_path_buf(),
-    })?;
+    let content = interpolate(&std::fs::read_to_string(path)?);
+    let mut aliases =
+        read_aliases(content.as_bytes()).map_err(|error| ErrorsAliasRead::AliasSerde {
+            error,
+            source_file: path.to_path_buf(),
+        })?;
 
+    let namespace = namespaces.namespace(path, &content);
     for a in aliases.as_mut_slice() {
-        NamespaceUpdater::update_from_path(a, path);
+        if let Some(namespace) = &namespace {
+            NamespaceUpdater::update(a, namespace.clone());
+        }
         if a.identifier().inner.contains(' ') {
             return Err(ErrorsAliasRead::AliasInvalidName(
                 a.identifier().to_string(),
@@ -33,11 +54,32 @@ pub fn read_aliases_from_path(path: &'_ Path) -> Result<Vec<Alias>, ErrorsAliasR
     Ok(aliases)
 }
 
+// read_documents reads the entries of all the documents of a yaml stream, separated
+// by `---`. Documents holding only comments have no entries.
+fn read_documents<E, T>(r: T) -> Result<Vec<E>, serde_yaml::Error>
+where
+    E: DeserializeOwned,
+    T: Read,
+{
+    let mut entries = vec![];
+    for document in serde_yaml::Deserializer::from_reader(r) {
+        entries.extend(Option::<Vec<E>>::deserialize(document)?.unwrap_or_default());
+    }
+    Ok(entries)
+}
+
+// aliases and vars meant for other operating systems are left out, so that
+// the variants of a recipe for each of them can share the same name. So are
+// the disabled ones.
 fn read_aliases<T>(r: T) -> Result<Vec<Alias>, serde_yaml::Error>
 where
     T: Read,
 {
-    serde_yaml::from_reader(r)
+    let aliases: Vec<Alias> = read_documents(r)?;
+    Ok(aliases
+        .into_iter()
+        .filter(|a| a.runs_on(OS) && a.is_enabled())
+        .collect())
 }
 
 pub fn read_choices<T>(r: T) -> Result<Vec<Choice>, ErrorsChoiceRead>
@@ -47,43 +89,121 @@ where
     let mut out = vec![];
     for line_r in r.lines() {
         let line = line_r?;
-        if line.is_empty() {
-            continue;
-        }
-        let splits: Vec<&str> = line.split('\t').collect();
-        let value_o = splits.get(0).map(|e| e.to_string());
-        let desc = splits.get(1).map(|e| e.to_string());
-        if let Some(value) = value_o {
-            out.push(Choice::new(value, desc));
+        if let Some(choice) = parse_choice(&line) {
+            out.push(choice);
         }
     }
     Ok(out)
 }
 
+/// ChoicesPage is a slice of the choices of a `from_command` var.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChoicesPage {
+    pub choices: Vec<Choice>,
+    pub has_more: bool,
+}
+
+/// read_choices_page reads up to `limit` choices, after skipping the first `offset` ones.
+/// Only the choices of the page are kept in memory, which matters for commands with huge outputs.
+/// Lines are split with the `choice_format` of the var when it has one.
+pub fn read_choices_page<T>(
+    r: T,
+    offset: usize,
+    limit: usize,
+    format: Option<&ChoiceFormat>,
+) -> Result<ChoicesPage, ErrorsChoiceRead>
+where
+    T: BufRead,
+{
+    let mut lines = r
+        .lines()
+        .filter(|l| !matches!(l, Ok(line) if line.is_empty()));
+    let mut choices = Vec::with_capacity(limit);
+    for line_r in lines.by_ref().skip(offset).take(limit) {
+        let line = line_r?;
+        let choice = match format {
+            Some(format) => format.parse(&line),
+            None => parse_choice(&line),
+        };
+        if let Some(choice) = choice {
+            choices.push(choice);
+        }
+    }
+    Ok(ChoicesPage {
+        choices,
+        has_more: lines.next().is_some(),
+    })
+}
+
+fn parse_choice(line: &str) -> Option<Choice> {
+    if line.is_empty() {
+        return None;
+    }
+    let mut splits = line.split('\t');
+    let value = splits.next()?;
+    Some(Choice::new(
+        value.to_string(),
+        splits.next().map(String::from),
+    ))
+}
+
 pub fn read_vars_repository(path: &'_ Path) -> Result<VarsRepository, ErrorsVarRead> {
+    read_vars_with_namespaces(path, &Namespaces::default())
+}
+
+/// read_vars_with_namespaces reads the vars of a file in the namespace
+/// that the strategy of `namespaces` gives it.
+pub fn read_vars_with_namespaces(
+    path: &'_ Path,
+    namespaces: &Namespaces,
+) -> Result<VarsRepository, ErrorsVarRead> {
     let f = File::open(path)?;
     let l = File::metadata(&f)?.len();
     if l == 0 {
         return Ok(VarsRepository::default());
     }
-    let buf = BufReader::new(f);
-    let mut vars = read_vars(buf).map_err(|e| ErrorsVarRead::VarsSerde {
+    let content = interpolate(&std::fs::read_to_string(path)?);
+    let mut vars = read_vars(content.as_bytes()).map_err(|e| ErrorsVarRead::VarsSerde {
         error: e,
         source_file: path.to_path_buf(),
     })?;
 
-    for a in vars.as_mut_slice() {
-        NamespaceUpdater::update_from_path(a, path);
+    if let Some(namespace) = namespaces.namespace(path, &content) {
+        for var in vars.as_mut_slice() {
+            NamespaceUpdater::update(var, namespace.clone());
+        }
     }
 
-    Ok(VarsRepository::new(vars.into_iter()))
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    Ok(VarsRepository::new(
+        vars.into_iter().map(|var| sops_relative_to(var, dir)),
+    ))
+}
+
+// sops_relative_to makes the relative files of `sops:` secrets relative to the directory
+// of the vars file, so that recipes can ship their encrypted files next to them. Files
+// starting with a var are left as they are.
+fn sops_relative_to(var: Var, dir: &Path) -> Var {
+    let file = match var
+        .secret()
+        .and_then(|s| s.trim().strip_prefix(SOPS_SCHEME))
+    {
+        Some(file) if Path::new(file).is_relative() && !file.starts_with("{{") => file,
+        _ => return var,
+    };
+    let reference = format!("{}{}", SOPS_SCHEME, dir.join(file).display());
+    var.with_secret(Some(reference))
 }
 
 fn read_vars<T>(r: T) -> Result<Vec<Var>, serde_yaml::Error>
 where
     T: Read,
 {
-    serde_yaml::from_reader(r)
+    let vars: Vec<Var> = read_documents(r)?;
+    Ok(vars
+        .into_iter()
+        .filter(|v| v.runs_on(OS) && v.is_enabled())
+        .collect())
 }
 
 #[derive(Debug, Error)]
@@ -112,19 +232,64 @@ pub enum ErrorsVarRead {
     VarsRepositoryInit(#[from] ErrorsVarsRepository),
 }
 
+impl ErrorsAliasRead {
+    /// location returns where the error is in the aliases file when it's known.
+    pub fn location(&self) -> Option<Location> {
+        match self {
+            ErrorsAliasRead::AliasSerde { error, source_file } => {
+                Some(serde_location(error, source_file))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl ErrorsVarRead {
+    /// location returns where the error is in the vars file when it's known.
+    pub fn location(&self) -> Option<Location> {
+        match self {
+            ErrorsVarRead::VarsSerde { error, source_file } => {
+                Some(serde_location(error, source_file))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn serde_location(error: &serde_yaml::Error, source_file: &Path) -> Location {
+    let position = error.location();
+    Location::new(
+        source_file,
+        position.as_ref().map(|p| p.line()),
+        position.as_ref().map(|p| p.column()),
+    )
+}
+
 #[derive(Debug, Error)]
 pub enum ErrorsChoiceRead {
     #[error("got an IO error while reading choices\n-> {0}")]
     ChoiceIO(#[from] std::io::Error),
+    #[error("can't parse the output as json\n-> {0}")]
+    ChoiceJson(#[from] serde_json::Error),
+    #[error("can't parse the output as yaml\n-> {0}")]
+    ChoiceYaml(#[from] serde_yaml::Error),
+    #[error("invalid jsonpath {0}")]
+    InvalidPath(String),
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{read_aliases, read_vars};
+    use super::{
+        read_aliases, read_aliases_from_path, read_choices, read_choices_page, read_vars,
+        read_vars_repository, sops_relative_to,
+    };
     use sam_core::entities::aliases::Alias;
-    use sam_core::entities::choices::Choice;
+    use sam_core::entities::choices::{Choice, ChoiceFormat};
+    use sam_core::entities::identifiers::Identifier;
     use sam_core::entities::vars::Var;
+    use std::env::consts::OS;
     use std::io::BufReader;
+    use std::path::Path;
 
     #[test]
     fn test_read_vars() {
@@ -197,4 +362,183 @@ mod tests {
         let aliases_r = read_aliases(r);
         assert!(aliases_r.is_err());
     }
+
+    #[test]
+    fn test_read_when_os() {
+        let aliases_str = format!(
+            "
+            - name: 'open'
+              desc: 'opens a file'
+              alias: 'xdg-open {{{{ file }}}}'
+              when_os: '{}'
+            - name: 'open'
+              desc: 'opens a file'
+              alias: 'start {{{{ file }}}}'
+              when_os: 'plan9 | haiku'
+            - name: 'ls'
+              desc: 'lists files'
+              alias: 'ls'",
+            OS
+        );
+        let aliases = read_aliases(BufReader::new(aliases_str.as_bytes())).unwrap();
+        assert_eq!(
+            aliases,
+            vec![
+                Alias::new("open", "opens a file", "xdg-open {{ file }}")
+                    .with_when_os(Some(OS.to_string())),
+                Alias::new("ls", "lists files", "ls"),
+            ]
+        );
+
+        let vars_str = "
+            - name: 'file'
+              desc: 'a file'
+              from_command: 'ls'
+              when_os: 'plan9'"
+            .as_bytes();
+        let vars = read_vars(BufReader::new(vars_str)).unwrap();
+        assert!(vars.is_empty());
+    }
+
+    #[test]
+    fn test_read_disabled() {
+        let aliases_str = "
+            - name: 'pods'
+              desc: 'lists pods'
+              alias: 'kubectl get pods'
+              enabled: false
+            - name: 'nodes'
+              desc: 'lists nodes'
+              alias: 'kubectl get nodes'
+              enabled: true"
+            .as_bytes();
+        let aliases = read_aliases(BufReader::new(aliases_str)).unwrap();
+        assert_eq!(
+            aliases,
+            vec![Alias::new("nodes", "lists nodes", "kubectl get nodes").with_enabled(Some(true))]
+        );
+
+        let vars_str = "
+            - name: 'file'
+              desc: 'a file'
+              from_command: 'ls'
+              enabled: false"
+            .as_bytes();
+        let vars = read_vars(BufReader::new(vars_str)).unwrap();
+        assert!(vars.is_empty());
+    }
+
+    #[test]
+    fn test_read_aliases_from_path_interpolates() {
+        let dir = std::env::temp_dir().join(format!("sam-interpolation-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("k8s")).unwrap();
+        let path = dir.join("k8s/aliases.yaml");
+        std::fs::write(
+            &path,
+            "- name: pods\n  desc: lists pods\n  alias: kubectl --kubeconfig ${SAM_READERS_TEST_KUBECONFIG} -n ${SAM_READERS_TEST_NS:-default} get pods ${SELECTOR}\n",
+        )
+        .unwrap();
+        std::env::set_var("SAM_READERS_TEST_KUBECONFIG", "/etc/kube/config");
+        let aliases = read_aliases_from_path(&path).unwrap();
+        assert_eq!(
+            aliases[0].alias(),
+            "kubectl --kubeconfig /etc/kube/config -n default get pods ${SELECTOR}"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn fixture(path: &str) -> std::path::PathBuf {
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures")
+            .join(path)
+    }
+
+    #[test]
+    fn test_read_fixtures() {
+        for dir in ["empty", "comments"] {
+            assert!(read_aliases_from_path(&fixture(dir).join("aliases.yaml"))
+                .unwrap()
+                .is_empty());
+            assert!(read_vars_repository(&fixture(dir).join("vars.yaml"))
+                .unwrap()
+                .identifiers()
+                .is_empty());
+        }
+
+        let aliases = read_aliases_from_path(&fixture("multi/aliases.yaml")).unwrap();
+        assert_eq!(
+            aliases.iter().map(Alias::identifier).collect::<Vec<_>>(),
+            vec![
+                Identifier::with_namespace("pods", Some("multi")),
+                Identifier::with_namespace("logs", Some("multi")),
+            ]
+        );
+        let mut vars = read_vars_repository(&fixture("multi/vars.yaml"))
+            .unwrap()
+            .identifiers();
+        vars.sort();
+        assert_eq!(
+            vars,
+            vec![
+                Identifier::with_namespace("namespace", Some("multi")),
+                Identifier::with_namespace("pod", Some("multi")),
+            ]
+        );
+
+        let error = read_aliases_from_path(&fixture("invalid/aliases.yaml")).unwrap_err();
+        let location = error.location().unwrap();
+        assert_eq!(location.file, fixture("invalid/aliases.yaml"));
+        assert_eq!(location.line, Some(5));
+    }
+
+    #[test]
+    fn test_sops_relative_to() {
+        let dir = Path::new("/packs/k8s");
+        let secret = |reference: &str| {
+            sops_relative_to(Var::from_secret("token", "a token", reference), dir)
+                .secret()
+                .map(String::from)
+        };
+        assert_eq!(
+            secret("sops:secrets.enc.yaml#db.password").as_deref(),
+            Some("sops:/packs/k8s/secrets.enc.yaml#db.password")
+        );
+        assert_eq!(
+            secret("sops:/etc/secrets.enc.yaml#token").as_deref(),
+            Some("sops:/etc/secrets.enc.yaml#token")
+        );
+        assert_eq!(
+            secret("sops:{{ dir }}/secrets.enc.yaml").as_deref(),
+            Some("sops:{{ dir }}/secrets.enc.yaml")
+        );
+        assert_eq!(secret("pass:k8s/token").as_deref(), Some("pass:k8s/token"));
+    }
+
+    #[test]
+    fn test_read_choices_page() {
+        let output = "a\tfirst\nb\n\nc\td\te\nf\n";
+        let all = read_choices(output.as_bytes()).unwrap();
+        assert_eq!(
+            all,
+            vec![
+                Choice::new("a", Some("first")),
+                Choice::new("b", None),
+                Choice::new("c", Some("d")),
+                Choice::new("f", None),
+            ]
+        );
+        let page = read_choices_page(output.as_bytes(), 0, 2, None).unwrap();
+        assert_eq!(page.choices, all[0..2].to_vec());
+        assert!(page.has_more);
+        let page = read_choices_page(output.as_bytes(), 2, 2, None).unwrap();
+        assert_eq!(page.choices, all[2..4].to_vec());
+        assert!(!page.has_more);
+
+        let format = ChoiceFormat::new("\t", 3, Some(String::from("{1}")), None);
+        let page = read_choices_page(output.as_bytes(), 0, 10, Some(&format)).unwrap();
+        assert_eq!(
+            page.choices,
+            vec![Choice::new("e", None).with_display(Some(String::from("c")))]
+        );
+    }
 }
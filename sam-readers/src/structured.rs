@@ -0,0 +1,145 @@
+use crate::readers::ErrorsChoiceRead;
+use sam_core::entities::choices::{Choice, StructuredChoices, StructuredFormat};
+use serde_json::Value;
+
+/// read_structured_choices parses the output of a `from_command` var as json or yaml
+/// and extracts a choice from each of the items selected by the jsonpath of the var.
+/// Paths are made of fields separated by dots, arrays can be indexed with `[0]` or
+/// expanded with `[*]`, like `$.Reservations[*].Instances[*]`. When the path selects
+/// an array, each of its elements is an item.
+pub fn read_structured_choices(
+    output: &[u8],
+    structured: &StructuredChoices,
+) -> Result<Vec<Choice>, ErrorsChoiceRead> {
+    let root: Value = match structured.format {
+        StructuredFormat::Json => serde_json::from_slice(output)?,
+        StructuredFormat::Yaml => serde_yaml::from_slice(output)?,
+    };
+    let items = select(&root, structured.jsonpath.as_deref().unwrap_or("$"))?
+        .into_iter()
+        .flat_map(|v| match v {
+            Value::Array(elements) => elements.iter().collect(),
+            _ => vec![v],
+        });
+    let mut choices = vec![];
+    for item in items {
+        let value = match &structured.value_field {
+            Some(field) => select(item, field)?.into_iter().next(),
+            None => Some(item),
+        };
+        let desc = match &structured.desc_field {
+            Some(field) => select(item, field)?.into_iter().next().and_then(to_string),
+            None => None,
+        };
+        if let Some(value) = value.and_then(to_string) {
+            choices.push(Choice::new(value, desc));
+        }
+    }
+    Ok(choices)
+}
+
+fn to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        _ => Some(value.to_string()),
+    }
+}
+
+fn select<'a>(root: &'a Value, path: &str) -> Result<Vec<&'a Value>, ErrorsChoiceRead> {
+    let path = path.trim();
+    let path = path.strip_prefix('$').unwrap_or(path);
+    let mut selected = vec![root];
+    for segment in path.split('.').filter(|s| !s.is_empty()) {
+        let (field, indexes) = segment.split_once('[').unwrap_or((segment, ""));
+        if !field.is_empty() {
+            selected = selected.into_iter().filter_map(|v| v.get(field)).collect();
+        }
+        if indexes.is_empty() {
+            continue;
+        }
+        for index in format!("[{}", indexes).split_terminator(']') {
+            let index = index
+                .strip_prefix('[')
+                .ok_or_else(|| ErrorsChoiceRead::InvalidPath(path.to_string()))?;
+            selected = match index {
+                "*" => selected
+                    .into_iter()
+                    .filter_map(Value::as_array)
+                    .flatten()
+                    .collect(),
+                _ => {
+                    let index: usize = index
+                        .parse()
+                        .map_err(|_| ErrorsChoiceRead::InvalidPath(path.to_string()))?;
+                    selected.into_iter().filter_map(|v| v.get(index)).collect()
+                }
+            };
+        }
+    }
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_structured_choices;
+    use sam_core::entities::choices::{Choice, StructuredChoices, StructuredFormat};
+
+    #[test]
+    fn test_read_structured_choices() {
+        let output = r#"{"Reservations": [
+            {"Instances": [
+                {"InstanceId": "i-01", "State": {"Name": "running"}, "Tags": [{"Value": "web"}]},
+                {"InstanceId": "i-02", "State": {"Name": "stopped"}, "Tags": []}
+            ]},
+            {"Instances": [{"InstanceId": "i-03"}]}
+        ]}"#;
+        let structured = StructuredChoices {
+            format: StructuredFormat::Json,
+            jsonpath: Some(String::from("$.Reservations[*].Instances[*]")),
+            value_field: Some(String::from("InstanceId")),
+            desc_field: Some(String::from("State.Name")),
+        };
+        let choices = read_structured_choices(output.as_bytes(), &structured).unwrap();
+        assert_eq!(
+            choices,
+            vec![
+                Choice::new("i-01", Some("running")),
+                Choice::new("i-02", Some("stopped")),
+                Choice::new("i-03", None),
+            ]
+        );
+
+        let structured = StructuredChoices {
+            desc_field: Some(String::from("Tags[0].Value")),
+            ..structured
+        };
+        let choices = read_structured_choices(output.as_bytes(), &structured).unwrap();
+        assert_eq!(choices[0], Choice::new("i-01", Some("web")));
+        assert_eq!(choices[1], Choice::new("i-02", None));
+
+        let output = "items:\n  - name: default\n  - name: kube-system\n  - 42\n";
+        let structured = StructuredChoices {
+            format: StructuredFormat::Yaml,
+            jsonpath: Some(String::from("items")),
+            value_field: None,
+            desc_field: None,
+        };
+        let choices = read_structured_choices(output.as_bytes(), &structured).unwrap();
+        assert_eq!(
+            choices,
+            vec![
+                Choice::new(r#"{"name":"default"}"#, None),
+                Choice::new(r#"{"name":"kube-system"}"#, None),
+                Choice::new("42", None),
+            ]
+        );
+
+        let structured = StructuredChoices {
+            jsonpath: Some(String::from("items[first]")),
+            ..structured
+        };
+        assert!(read_structured_choices(output.as_bytes(), &structured).is_err());
+        assert!(read_structured_choices(b"{", &structured).is_err());
+    }
+}
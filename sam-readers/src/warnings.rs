@@ -0,0 +1,215 @@
+use crate::locations::Location;
+use sam_core::entities::identifiers::Identifier;
+use std::fmt::Display;
+use std::path::PathBuf;
+
+/// Warning represents a non fatal issue that was detected while loading
+/// aliases and vars. Warnings are collected during the load and reported
+/// once the program is done running or through `sam check-config`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    DuplicateAlias {
+        identifier: Identifier,
+        source_file: PathBuf,
+    },
+    DuplicateVar {
+        identifier: Identifier,
+        source_file: PathBuf,
+    },
+    UnknownDefault(Identifier),
+    /// the history or the cache files can't be written, they are kept in memory instead.
+    ReadOnlyState(Vec<PathBuf>),
+    /// a root directory holds no aliases nor vars file, or doesn't exist.
+    NoRecipes(PathBuf),
+    /// a configuration file uses a setting that was renamed.
+    DeprecatedField {
+        field: String,
+        replacement: String,
+        source_file: PathBuf,
+    },
+    /// a configuration file gives another default choice to a var than a previous one.
+    ShadowedDefault {
+        identifier: Identifier,
+        source_file: PathBuf,
+    },
+}
+
+impl Warning {
+    /// code identifies the kind of warning for tools reading `sam check-config --output json`.
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Warning::DuplicateAlias { .. } => "duplicate-alias",
+            Warning::DuplicateVar { .. } => "duplicate-var",
+            Warning::UnknownDefault(_) => "unknown-default",
+            Warning::ReadOnlyState(_) => "read-only-state",
+            Warning::NoRecipes(_) => "no-recipes",
+            Warning::DeprecatedField { .. } => "deprecated-field",
+            Warning::ShadowedDefault { .. } => "shadowed-default",
+        }
+    }
+
+    /// location returns where the definition the warning is about is, defaults
+    /// come from the command line and have none. The ones about a configuration
+    /// file point to the file.
+    pub fn location(&self) -> Option<Location> {
+        match self {
+            Warning::DuplicateAlias {
+                identifier,
+                source_file,
+            }
+            | Warning::DuplicateVar {
+                identifier,
+                source_file,
+            } => Some(Location::of_definition(source_file, identifier.name())),
+            Warning::DeprecatedField { source_file, .. }
+            | Warning::ShadowedDefault { source_file, .. } => {
+                Some(Location::new(source_file, None, None))
+            }
+            Warning::UnknownDefault(_) | Warning::ReadOnlyState(_) | Warning::NoRecipes(_) => None,
+        }
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Warning::DuplicateAlias {
+                identifier,
+                source_file,
+            } => write!(
+                f,
+                "alias {} is defined more than once, the definition in {} takes precedence",
+                identifier,
+                source_file.display()
+            ),
+            Warning::DuplicateVar {
+                identifier,
+                source_file,
+            } => write!(
+                f,
+                "var {} is defined more than once, the definition in {} is ignored",
+                identifier,
+                source_file.display()
+            ),
+            Warning::UnknownDefault(identifier) => write!(
+                f,
+                "a default choice was provided for the unknown var {}",
+                identifier
+            ),
+            Warning::ReadOnlyState(paths) => {
+                let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+                write!(
+                    f,
+                    "{} can't be written, they are kept in memory until sam exits",
+                    paths.join(", ")
+                )
+            }
+            Warning::NoRecipes(dir) => write!(
+                f,
+                "the root directory {} holds no aliases.yaml nor vars.yaml",
+                dir.display()
+            ),
+            Warning::DeprecatedField {
+                field,
+                replacement,
+                source_file,
+            } => write!(
+                f,
+                "{} in {} is deprecated, use {} instead",
+                field,
+                source_file.display(),
+                replacement
+            ),
+            Warning::ShadowedDefault {
+                identifier,
+                source_file,
+            } => write!(
+                f,
+                "the default choice of var {} in {} overrides the one of a previous configuration file",
+                identifier,
+                source_file.display()
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Warnings(Vec<Warning>);
+
+impl Warnings {
+    pub fn push(&mut self, warning: Warning) {
+        self.0.push(warning)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Warning> {
+        self.0.iter()
+    }
+}
+
+impl Extend<Warning> for Warnings {
+    fn extend<T: IntoIterator<Item = Warning>>(&mut self, iter: T) {
+        self.0.extend(iter)
+    }
+}
+
+impl Display for Warnings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for warning in &self.0 {
+            writeln!(f, "- {}", warning)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Warning, Warnings};
+    use sam_core::entities::identifiers::Identifier;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_warnings_summary() {
+        let mut warnings = Warnings::default();
+        assert!(warnings.is_empty());
+        warnings.push(Warning::DuplicateAlias {
+            identifier: Identifier::with_namespace("list", Some("dirs")),
+            source_file: PathBuf::from("/tmp/dirs/aliases.yaml"),
+        });
+        warnings.push(Warning::UnknownDefault(Identifier::with_namespace(
+            "missing",
+            Some("ns"),
+        )));
+        warnings.push(Warning::ReadOnlyState(vec![
+            PathBuf::from("/home/sam/.local/share/sam/history"),
+            PathBuf::from("/home/sam/.cache/sam"),
+        ]));
+        warnings.push(Warning::NoRecipes(PathBuf::from("/tmp/recipes")));
+        warnings.push(Warning::DeprecatedField {
+            field: String::from("ttl"),
+            replacement: String::from("cache_ttl"),
+            source_file: PathBuf::from("/home/sam/.sam_rc.toml"),
+        });
+        warnings.push(Warning::ShadowedDefault {
+            identifier: Identifier::with_namespace("env", Some("k8s")),
+            source_file: PathBuf::from("/project/.sam_rc.toml"),
+        });
+        assert_eq!(warnings.len(), 6);
+        assert_eq!(
+            format!("{}", warnings),
+            "- alias dirs::list is defined more than once, the definition in /tmp/dirs/aliases.yaml takes precedence\n\
+             - a default choice was provided for the unknown var ns::missing\n\
+             - /home/sam/.local/share/sam/history, /home/sam/.cache/sam can't be written, they are kept in memory until sam exits\n\
+             - the root directory /tmp/recipes holds no aliases.yaml nor vars.yaml\n\
+             - ttl in /home/sam/.sam_rc.toml is deprecated, use cache_ttl instead\n\
+             - the default choice of var k8s::env in /project/.sam_rc.toml overrides the one of a previous configuration file\n"
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use sam_core::algorithms::resolver::SecretsBackend;
+use std::process::Command;
+use thiserror::Error;
+
+/// Reference tells where a secret is stored, it's parsed from the `from_secret`
+/// field of a var:
+/// - `env:NAME` reads the environment variable NAME.
+/// - `pass:path/to/secret` reads the first line of a secret of the `pass` password store.
+/// - `op://vault/item/field` reads a field of a 1Password item with the `op` CLI.
+/// - `vault:path/to/secret#field` reads a field of a HashiCorp Vault secret.
+/// - `sops:path/to/file.yaml#db.password` decrypts a file encrypted with sops, or a
+///   value of it, with the `sops` CLI. It needs the `sops` feature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reference {
+    Env(String),
+    Pass(String),
+    OnePassword(String),
+    Vault {
+        path: String,
+        field: String,
+    },
+    #[cfg(feature = "sops")]
+    Sops {
+        file: String,
+        key: Option<String>,
+    },
+}
+
+impl Reference {
+    ///```rust
+    /// use sam_secrets::Reference;
+    /// let reference = Reference::parse("env:GITHUB_TOKEN").unwrap();
+    /// assert_eq!(reference, Reference::Env(String::from("GITHUB_TOKEN")));
+    /// assert!(Reference::parse("keychain:github").is_err());
+    ///```
+    pub fn parse(reference: &str) -> Result<Reference, ErrorsSecrets> {
+        let reference = reference.trim();
+        let invalid = || ErrorsSecrets::InvalidReference(reference.to_string());
+        if reference.starts_with("op://") {
+            return Ok(Reference::OnePassword(reference.to_string()));
+        }
+        let (scheme, target) = reference.split_once(':').ok_or_else(invalid)?;
+        if target.is_empty() {
+            return Err(invalid());
+        }
+        match scheme {
+            "env" => Ok(Reference::Env(target.to_string())),
+            "pass" => Ok(Reference::Pass(target.to_string())),
+            "vault" => {
+                let (path, field) = target.split_once('#').ok_or_else(invalid)?;
+                if path.is_empty() || field.is_empty() {
+                    return Err(invalid());
+                }
+                Ok(Reference::Vault {
+                    path: path.to_string(),
+                    field: field.to_string(),
+                })
+            }
+            #[cfg(feature = "sops")]
+            "sops" => {
+                let (file, key) = match target.split_once('#') {
+                    Some((file, key)) if !key.is_empty() => (file, Some(key.to_string())),
+                    Some(_) => return Err(invalid()),
+                    None => (target, None),
+                };
+                if file.is_empty() {
+                    return Err(invalid());
+                }
+                Ok(Reference::Sops {
+                    file: file.to_string(),
+                    key,
+                })
+            }
+            #[cfg(not(feature = "sops"))]
+            "sops" => Err(ErrorsSecrets::Disabled(String::from("sops"))),
+            _ => Err(invalid()),
+        }
+    }
+
+    // command returns the command printing the secret, env vars are read directly.
+    fn command(&self) -> Option<Command> {
+        let (program, args): (&str, Vec<String>) = match self {
+            Reference::Env(_) => return None,
+            Reference::Pass(path) => ("pass", vec![String::from("show"), path.clone()]),
+            Reference::OnePassword(reference) => {
+                ("op", vec![String::from("read"), reference.clone()])
+            }
+            Reference::Vault { path, field } => (
+                "vault",
+                vec![
+                    String::from("kv"),
+                    String::from("get"),
+                    format!("-field={}", field),
+                    path.clone(),
+                ],
+            ),
+            #[cfg(feature = "sops")]
+            Reference::Sops { file, key } => {
+                let mut args = vec![String::from("--decrypt")];
+                if let Some(key) = key {
+                    args.extend([String::from("--extract"), sops_extract(key)]);
+                }
+                args.push(file.clone());
+                ("sops", args)
+            }
+        };
+        let mut command = Command::new(program);
+        command.args(args);
+        Some(command)
+    }
+
+    /// read returns the secret, the trailing new line printed by the backends is dropped.
+    pub fn read(&self) -> Result<String, ErrorsSecrets> {
+        if let Reference::Env(name) = self {
+            return std::env::var(name).map_err(|_| ErrorsSecrets::UnsetEnvVar(name.clone()));
+        }
+        let mut command = match self.command() {
+            Some(command) => command,
+            None => unreachable!("only env references are read without a command"),
+        };
+        let program = command.get_program().to_string_lossy().to_string();
+        let output = command
+            .output()
+            .map_err(|err| ErrorsSecrets::Backend(program.clone(), err))?;
+        if !output.status.success() {
+            return Err(ErrorsSecrets::Failure(
+                program,
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let secret = match self {
+            // pass stores metadata after the first line of a secret.
+            Reference::Pass(_) => stdout.lines().next().unwrap_or_default(),
+            _ => stdout.trim_end_matches(['\n', '\r']),
+        };
+        Ok(secret.to_string())
+    }
+}
+
+// sops_extract turns a dotted key, `db.password` or `replicas.0.host`, into the
+// path sops extracts, `["db"]["password"]`, indexes stay numbers.
+#[cfg(feature = "sops")]
+fn sops_extract(key: &str) -> String {
+    key.split('.')
+        .map(|part| match part.parse::<usize>() {
+            Ok(index) => format!("[{}]", index),
+            Err(_) => format!("[\"{}\"]", part),
+        })
+        .collect()
+}
+
+/// Secrets is the secrets backend used by sam, it dispatches each reference to
+/// the backend its scheme refers to.
+#[derive(Debug, Default)]
+pub struct Secrets {}
+
+impl SecretsBackend for Secrets {
+    fn read(&self, reference: &str) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(Reference::parse(reference)?.read()?)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ErrorsSecrets {
+    #[error("invalid secret reference '{0}', expected env:NAME, pass:PATH, op://VAULT/ITEM/FIELD, vault:PATH#FIELD or sops:FILE#KEY")]
+    InvalidReference(String),
+    #[error("{0} secrets are not supported, sam was built without the {0} feature")]
+    Disabled(String),
+    #[error("the environment variable {0} is not set")]
+    UnsetEnvVar(String),
+    #[error("could not run {0}\n-> {1}")]
+    Backend(String, std::io::Error),
+    #[error("{0} could not read the secret\n-> {1}")]
+    Failure(String, String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorsSecrets, Reference, Secrets};
+    use sam_core::algorithms::resolver::SecretsBackend;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Reference::parse("pass:work/github").unwrap(),
+            Reference::Pass(String::from("work/github"))
+        );
+        assert_eq!(
+            Reference::parse("op://dev/github/token").unwrap(),
+            Reference::OnePassword(String::from("op://dev/github/token"))
+        );
+        assert_eq!(
+            Reference::parse("vault:secret/github#token").unwrap(),
+            Reference::Vault {
+                path: String::from("secret/github"),
+                field: String::from("token")
+            }
+        );
+        assert!(Reference::parse("vault:secret/github").is_err());
+        assert!(Reference::parse("env:").is_err());
+        assert!(Reference::parse("github").is_err());
+    }
+
+    #[cfg(feature = "sops")]
+    #[test]
+    fn test_sops() {
+        assert_eq!(
+            Reference::parse("sops:secrets.enc.yaml#db.password").unwrap(),
+            Reference::Sops {
+                file: String::from("secrets.enc.yaml"),
+                key: Some(String::from("db.password"))
+            }
+        );
+        assert!(Reference::parse("sops:secrets.enc.yaml#").is_err());
+        assert!(Reference::parse("sops:#db").is_err());
+
+        let command = Reference::parse("sops:/packs/secrets.enc.yaml#replicas.0.host")
+            .unwrap()
+            .command()
+            .expect("sops secrets are read with a command");
+        assert_eq!(command.get_program(), "sops");
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            args,
+            vec![
+                "--decrypt",
+                "--extract",
+                "[\"replicas\"][0][\"host\"]",
+                "/packs/secrets.enc.yaml"
+            ]
+        );
+        let command = Reference::parse("sops:token.enc")
+            .unwrap()
+            .command()
+            .unwrap();
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, vec!["--decrypt", "token.enc"]);
+    }
+
+    #[cfg(not(feature = "sops"))]
+    #[test]
+    fn test_sops_disabled() {
+        assert!(matches!(
+            Reference::parse("sops:secrets.enc.yaml#db.password"),
+            Err(ErrorsSecrets::Disabled(_))
+        ));
+    }
+
+    #[test]
+    fn test_command() {
+        let command = Reference::Vault {
+            path: String::from("secret/github"),
+            field: String::from("token"),
+        }
+        .command()
+        .expect("vault secrets are read with a command");
+        assert_eq!(command.get_program(), "vault");
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, vec!["kv", "get", "-field=token", "secret/github"]);
+        assert!(Reference::Env(String::from("HOME")).command().is_none());
+    }
+
+    #[test]
+    fn test_read_env() {
+        std::env::set_var("SAM_SECRETS_TEST_TOKEN", "s3cr3t");
+        assert_eq!(
+            Secrets::default()
+                .read("env:SAM_SECRETS_TEST_TOKEN")
+                .expect("can't read the secret"),
+            "s3cr3t"
+        );
+        assert!(matches!(
+            Reference::Env(String::from("SAM_SECRETS_TEST_UNSET")).read(),
+            Err(ErrorsSecrets::UnsetEnvVar(_))
+        ));
+    }
+}
@@ -0,0 +1,131 @@
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::env;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use thiserror::Error;
+
+// the time after which a request is given up, endpoints that hang shouldn't hang sam.
+const MAX_TIME_SECONDS: &str = "30";
+
+lazy_static! {
+    static ref ENV_VAR_RE: Regex =
+        Regex::new(r"\$(?:\{(?P<braced>[a-zA-Z0-9_]+)\}|(?P<var>[a-zA-Z0-9_]+))").unwrap();
+}
+
+/// get returns the body of the response to a GET request. It's sent with curl, which
+/// follows redirections and uses the proxies and the certificates the user configured
+/// for it. The headers are written to its stdin so that they don't show up in `ps`.
+pub fn get(url: &str, headers: &[(String, String)]) -> Result<Vec<u8>, ErrorsHttp> {
+    let mut command = Command::new("curl");
+    command
+        .args(["--silent", "--show-error", "--fail", "--location"])
+        .args(["--max-time", MAX_TIME_SECONDS])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if !headers.is_empty() {
+        command.args(["--header", "@-"]);
+    }
+    command.arg("--").arg(url);
+    let mut child = command.spawn().map_err(ErrorsHttp::Curl)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        for (name, value) in headers {
+            writeln!(stdin, "{}: {}", name, value).map_err(ErrorsHttp::Curl)?;
+        }
+    }
+    let output = child.wait_with_output().map_err(ErrorsHttp::Curl)?;
+    if !output.status.success() {
+        return Err(ErrorsHttp::Failure(
+            url.to_string(),
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// expand_headers replaces the `$NAME` and `${NAME}` references to environment variables
+/// in the values of the headers, with the key value pairs of the configuration first.
+/// The headers are sorted by name.
+pub fn expand_headers(
+    headers: &HashMap<String, String>,
+    variables: &HashMap<String, String>,
+) -> Result<Vec<(String, String)>, ErrorsHttp> {
+    let mut expanded = Vec::with_capacity(headers.len());
+    for (name, value) in headers {
+        let mut unset = None;
+        let value = ENV_VAR_RE.replace_all(value, |caps: &Captures| {
+            let var = caps
+                .name("braced")
+                .or_else(|| caps.name("var"))
+                .map_or("", |m| m.as_str());
+            match variables.get(var).cloned().or_else(|| env::var(var).ok()) {
+                Some(value) => value,
+                None => {
+                    unset.get_or_insert_with(|| var.to_string());
+                    String::new()
+                }
+            }
+        });
+        if let Some(var) = unset {
+            return Err(ErrorsHttp::UnsetEnvVar(name.clone(), var));
+        }
+        expanded.push((name.clone(), value.to_string()));
+    }
+    expanded.sort();
+    Ok(expanded)
+}
+
+#[derive(Debug, Error)]
+pub enum ErrorsHttp {
+    #[error("could not run curl, which sends the requests of from_url vars\n-> {0}")]
+    Curl(io::Error),
+    #[error("the request to {0} failed\n-> {1}")]
+    Failure(String, String),
+    #[error("the header {0} refers to the environment variable {1}, which is not set")]
+    UnsetEnvVar(String, String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand_headers, get, ErrorsHttp};
+    use maplit::hashmap;
+    use std::fs;
+
+    #[test]
+    fn test_expand_headers() {
+        let headers = hashmap! {
+            String::from("PRIVATE-TOKEN") => String::from("${SAM_HTTP_TEST_TOKEN}"),
+            String::from("Authorization") => String::from("Bearer $SAM_HTTP_TEST_BEARER"),
+            String::from("Accept") => String::from("application/json"),
+        };
+        let variables = hashmap! { String::from("SAM_HTTP_TEST_BEARER") => String::from("b3ar3r") };
+        std::env::set_var("SAM_HTTP_TEST_TOKEN", "t0k3n");
+        assert_eq!(
+            expand_headers(&headers, &variables).unwrap(),
+            vec![
+                (String::from("Accept"), String::from("application/json")),
+                (String::from("Authorization"), String::from("Bearer b3ar3r")),
+                (String::from("PRIVATE-TOKEN"), String::from("t0k3n")),
+            ]
+        );
+
+        let headers = hashmap! { String::from("Authorization") => String::from("Bearer $SAM_HTTP_TEST_UNSET") };
+        assert!(matches!(
+            expand_headers(&headers, &variables),
+            Err(ErrorsHttp::UnsetEnvVar(header, var)) if header == "Authorization" && var == "SAM_HTTP_TEST_UNSET"
+        ));
+    }
+
+    #[test]
+    fn test_get() {
+        let path = std::env::temp_dir().join(format!("sam-http-{}.txt", std::process::id()));
+        fs::write(&path, "prod\tproduction\nstaging\n").unwrap();
+        let url = format!("file://{}", path.display());
+        let headers = vec![(String::from("Accept"), String::from("text/plain"))];
+        assert_eq!(get(&url, &headers).unwrap(), b"prod\tproduction\nstaging\n");
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(get(&url, &[]), Err(ErrorsHttp::Failure(_, _))));
+    }
+}
@@ -0,0 +1,51 @@
+use std::fs::File;
+use std::io::{self, IsTerminal, Read};
+
+#[cfg(unix)]
+const TTY: &str = "/dev/tty";
+#[cfg(windows)]
+const TTY: &str = "CONIN$";
+
+/// stdin_is_piped tells whether sam's stdin is a pipe, a socket or a file, its content is
+/// then meant for the commands sam runs. A terminal or /dev/null, which cron and
+/// services usually give, aren't.
+#[cfg(unix)]
+pub fn stdin_is_piped() -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    std::fs::metadata("/dev/stdin")
+        .map(|m| {
+            let file_type = m.file_type();
+            file_type.is_fifo() || file_type.is_socket() || file_type.is_file()
+        })
+        .unwrap_or(false)
+}
+
+/// stdin_is_piped tells whether sam's stdin is a pipe or a file, its content is then
+/// meant for the commands sam runs. The console and NUL aren't.
+#[cfg(windows)]
+pub fn stdin_is_piped() -> bool {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{GetFileType, FILE_TYPE_DISK, FILE_TYPE_PIPE};
+    // SAFETY: the handle of stdin stays valid while sam runs.
+    let file_type = unsafe { GetFileType(io::stdin().as_raw_handle() as _) };
+    file_type == FILE_TYPE_PIPE || file_type == FILE_TYPE_DISK
+}
+
+/// user_input is where sam reads what the user types: stdin, or the terminal when stdin is
+/// piped so that its content is left untouched for the commands. Stdin is used when there
+/// is no terminal at all.
+pub fn user_input() -> Box<dyn Read> {
+    if io::stdin().is_terminal() {
+        return Box::new(io::stdin());
+    }
+    match terminal() {
+        Some(tty) => Box::new(tty),
+        None => Box::new(io::stdin()),
+    }
+}
+
+/// terminal opens the controlling terminal of sam, the console on windows, None when it
+/// has none, in CI for instance.
+pub fn terminal() -> Option<File> {
+    File::options().read(true).write(true).open(TTY).ok()
+}
@@ -0,0 +1,74 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Interrupts makes Ctrl-C interrupt what sam is waiting for instead of killing it,
+/// until it's dropped. The commands sam runs in their own process group don't get
+/// the signal either.
+pub struct Interrupts {
+    #[cfg(unix)]
+    previous: libc::sighandler_t,
+}
+
+impl Interrupts {
+    pub fn catch() -> Self {
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        let interrupts = Interrupts {
+            #[cfg(unix)]
+            previous: unsafe {
+                let handler: extern "C" fn(libc::c_int) = on_interrupt;
+                libc::signal(libc::SIGINT, handler as libc::sighandler_t)
+            },
+        };
+        #[cfg(windows)]
+        unsafe {
+            windows_sys::Win32::System::Console::SetConsoleCtrlHandler(Some(on_interrupt), 1);
+        }
+        interrupts
+    }
+
+    /// interrupted tells whether Ctrl-C was pressed since the interrupts were caught.
+    pub fn interrupted(&self) -> bool {
+        INTERRUPTED.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for Interrupts {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        unsafe {
+            libc::signal(libc::SIGINT, self.previous);
+        }
+        #[cfg(windows)]
+        unsafe {
+            windows_sys::Win32::System::Console::SetConsoleCtrlHandler(Some(on_interrupt), 0);
+        }
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_interrupt(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+#[cfg(windows)]
+unsafe extern "system" fn on_interrupt(ctrl_type: u32) -> windows_sys::Win32::Foundation::BOOL {
+    if ctrl_type != windows_sys::Win32::System::Console::CTRL_C_EVENT {
+        return 0;
+    }
+    INTERRUPTED.store(true, Ordering::SeqCst);
+    1
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::Interrupts;
+
+    #[test]
+    fn test_interrupts() {
+        let interrupts = Interrupts::catch();
+        assert!(!interrupts.interrupted());
+        unsafe { libc::raise(libc::SIGINT) };
+        assert!(interrupts.interrupted());
+    }
+}
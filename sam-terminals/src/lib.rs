@@ -1,2 +1,7 @@
+pub mod http;
+pub mod input;
+pub mod interrupts;
+pub mod plugins;
 pub mod processes;
+pub mod streams;
 pub mod tmux;
@@ -0,0 +1,201 @@
+use sam_core::algorithms::resolver::ResolverContext;
+use sam_core::entities::choices::Choice;
+use sam_core::entities::vars::Var;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use thiserror::Error;
+
+/// PROTOCOL_VERSION is the version of the requests sent to plugins, it changes when
+/// a request or a response changes in a way that breaks the existing plugins.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// PluginRequest is what a `from_plugin` var's executable reads on its stdin, as json:
+/// the var to give choices for, the alias being run and the choices made so far.
+/// The choices of sensitive vars are left out.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PluginRequest {
+    pub version: u32,
+    pub var: PluginVar,
+    pub alias: PluginAlias,
+    pub choices: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PluginVar {
+    pub name: String,
+    pub desc: String,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PluginAlias {
+    pub name: String,
+    pub desc: String,
+    pub command: String,
+}
+
+/// PluginResponse is what the executable prints on its stdout, as json: the choices,
+/// or an error to report to the user.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum PluginResponse {
+    Choices { choices: Vec<Choice> },
+    Error { error: String },
+}
+
+impl PluginRequest {
+    pub fn new(var: &Var, ctx: &ResolverContext) -> PluginRequest {
+        let choices = ctx
+            .choices
+            .iter()
+            .filter(|(id, _)| !ctx.sensitive.contains(id))
+            .map(|(id, choices)| {
+                let values = choices.iter().map(|c| c.value().to_string()).collect();
+                (id.to_string(), values)
+            })
+            .collect();
+        PluginRequest {
+            version: PROTOCOL_VERSION,
+            var: PluginVar {
+                name: var.name().to_string(),
+                desc: var.desc().to_string(),
+            },
+            alias: PluginAlias {
+                name: ctx.alias.identifier().to_string(),
+                desc: ctx.alias.desc().to_string(),
+                command: ctx.alias.alias().to_string(),
+            },
+            choices,
+        }
+    }
+}
+
+/// run runs the executable of a plugin, with its arguments split the way a shell would,
+/// writes the request to its stdin and reads the choices from its stdout. Its stderr
+/// is left to the terminal so that plugins can log or ask for things.
+pub fn run(
+    plugin: &str,
+    request: &PluginRequest,
+    env_variables: &HashMap<String, String>,
+) -> Result<Vec<Choice>, ErrorsPlugin> {
+    let args = shellwords::split(plugin).map_err(|_| ErrorsPlugin::Invalid(plugin.to_string()))?;
+    let (program, args) = args
+        .split_first()
+        .ok_or_else(|| ErrorsPlugin::Invalid(plugin.to_string()))?;
+    let mut child = Command::new(program)
+        .args(args)
+        .envs(env_variables)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|err| ErrorsPlugin::Spawn(program.clone(), err))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let request = serde_json::to_vec(request).map_err(ErrorsPlugin::Request)?;
+        // a plugin that doesn't need the request may exit without reading it.
+        match stdin.write_all(&request) {
+            Err(err) if err.kind() != io::ErrorKind::BrokenPipe => {
+                return Err(ErrorsPlugin::Spawn(program.clone(), err))
+            }
+            _ => {}
+        }
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|err| ErrorsPlugin::Spawn(program.clone(), err))?;
+    if !output.status.success() {
+        return Err(ErrorsPlugin::Exit(
+            program.clone(),
+            output
+                .status
+                .code()
+                .map_or_else(|| String::from("none"), |code| code.to_string()),
+        ));
+    }
+    match serde_json::from_slice(&output.stdout).map_err(ErrorsPlugin::Response)? {
+        PluginResponse::Choices { choices } => Ok(choices),
+        PluginResponse::Error { error } => Err(ErrorsPlugin::Reported(program.clone(), error)),
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ErrorsPlugin {
+    #[error("invalid plugin '{0}', expected an executable followed by its arguments")]
+    Invalid(String),
+    #[error("could not run the plugin {0}\n-> {1}")]
+    Spawn(String, io::Error),
+    #[error("the plugin {0} exited with code {1}")]
+    Exit(String, String),
+    #[error("the plugin {0} reported an error\n-> {1}")]
+    Reported(String, String),
+    #[error("could not serialize the request to the plugin\n-> {0}")]
+    Request(serde_json::Error),
+    #[error("the plugin printed an invalid response, expected {{\"choices\": [...]}} or {{\"error\": \"...\"}}\n-> {0}")]
+    Response(serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run, ErrorsPlugin, PluginRequest};
+    use sam_core::algorithms::resolver::ResolverContext;
+    use sam_core::entities::aliases::Alias;
+    use sam_core::entities::choices::Choice;
+    use sam_core::entities::identifiers::Identifier;
+    use sam_core::entities::vars::Var;
+    use std::collections::HashMap;
+
+    fn context() -> ResolverContext {
+        ResolverContext {
+            alias: Alias::new("logs", "tails the logs", "kubectl logs {{ pod }}"),
+            full_name: String::from("logs"),
+            choices: maplit::hashmap! {
+                Identifier::new("namespace") => vec![Choice::from_value("default")],
+                Identifier::new("token") => vec![Choice::from_value("s3cr3t")],
+            },
+            execution_sequence: vec![],
+            sensitive: vec![Identifier::new("token")],
+        }
+    }
+
+    #[test]
+    fn test_request() {
+        let var = Var::from_plugin("pod", "a pod", "pods-resolver");
+        let request = PluginRequest::new(&var, &context());
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"version":1,"var":{"name":"pod","desc":"a pod"},"alias":{"name":"logs","desc":"tails the logs","command":"kubectl logs {{ pod }}"},"choices":{"namespace":["default"]}}"#
+        );
+    }
+
+    #[test]
+    fn test_run() {
+        let var = Var::from_plugin("pod", "a pod", "pods-resolver");
+        let request = PluginRequest::new(&var, &context());
+        let env = HashMap::new();
+
+        // the plugin echoes the namespace it was asked for.
+        let plugin = r#"sh -c 'sed -e "s/.*\"namespace\":\[\"\([a-z]*\)\"\].*/{\"choices\":[{\"value\":\"\1\",\"desc\":\"from sed\"}]}/"'"#;
+        assert_eq!(
+            run(plugin, &request, &env).unwrap(),
+            vec![Choice::new("default", Some("from sed"))]
+        );
+
+        let plugin = r#"sh -c 'echo "{\"error\": \"no cluster\"}"'"#;
+        assert_eq!(
+            run(plugin, &request, &env).unwrap_err().to_string(),
+            "the plugin sh reported an error\n-> no cluster"
+        );
+        assert!(matches!(
+            run("sh -c 'exit 3'", &request, &env),
+            Err(ErrorsPlugin::Exit(_, code)) if code == "3"
+        ));
+        assert!(matches!(
+            run("sh -c 'echo nope'", &request, &env),
+            Err(ErrorsPlugin::Response(_))
+        ));
+        assert!(matches!(
+            run("sam-plugin-that-does-not-exist", &request, &env),
+            Err(ErrorsPlugin::Spawn(_, _))
+        ));
+    }
+}
@@ -1,23 +1,338 @@
 use std::collections::HashMap;
 use std::env;
 use std::ffi::OsStr;
-use std::process::Command;
+use std::fmt::{Display, Formatter};
+use std::io::{self, BufRead, BufReader, Read};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::OnceLock;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+// how often a command running with a timeout is checked for completion.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
 
 #[derive(Debug, Clone)]
 pub struct ShellCommand<T: Clone> {
     command: T,
+    shell: Option<Shell>,
+}
+
+static DEFAULT_SHELL: OnceLock<Shell> = OnceLock::new();
+
+/// Shell is the program command lines are run with and the arguments that precede
+/// the command line, `zsh -c`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shell {
+    program: String,
+    args: Vec<String>,
+    login: bool,
+}
+
+impl Shell {
+    /// parse reads a shell from the configuration or an alias, the program followed by
+    /// its arguments. The flag taking the command line is added when only the program
+    /// is given, `fish` runs `fish -c`.
+    pub fn parse(shell: &str) -> Result<Shell, ErrorsShell> {
+        let mut words =
+            shellwords::split(shell).map_err(|_| ErrorsShell::Invalid(shell.to_string()))?;
+        if words.is_empty() {
+            return Err(ErrorsShell::Invalid(shell.to_string()));
+        }
+        let program = words.remove(0);
+        if words.is_empty() {
+            words.push(shell_flag(&program).to_string());
+        }
+        Ok(Shell {
+            program,
+            args: words,
+            login: false,
+        })
+    }
+
+    /// user is the shell of the user: `$SHELL -c`, `/bin/sh -c` otherwise, on unix and
+    /// `%COMSPEC% /C`, `cmd /C` otherwise, on windows. PowerShell takes `-Command` instead.
+    pub fn user() -> Shell {
+        let program = current_shell();
+        let args = vec![shell_flag(&program).to_string()];
+        Shell {
+            program,
+            args,
+            login: false,
+        }
+    }
+
+    /// with_login runs the commands in a login shell, `bash -l -c`, which reads the profile
+    /// of the user first. cmd has no login shell, the flag is ignored.
+    pub fn with_login(mut self, login: bool) -> Shell {
+        self.login = login;
+        self
+    }
+
+    pub const fn is_login(&self) -> bool {
+        self.login
+    }
+
+    /// set_default makes the shell the one commands run with unless they pick another one,
+    /// the shell of the user is used until then. Only the first call has an effect.
+    pub fn set_default(shell: Shell) {
+        let _ = DEFAULT_SHELL.set(shell);
+    }
+
+    pub fn default_shell() -> Shell {
+        DEFAULT_SHELL.get().cloned().unwrap_or_else(Shell::user)
+    }
+
+    pub fn command(&self, line: impl AsRef<OsStr>) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(self.args()).arg(line);
+        command
+    }
+
+    fn args(&self) -> impl Iterator<Item = &str> {
+        let login = login_flag(&self.program).filter(|_| self.login);
+        login
+            .into_iter()
+            .chain(self.args.iter().map(String::as_str))
+    }
 }
 
-fn current_shell_or_sh() -> String {
+impl Display for Shell {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in self.args() {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+/// shell_command returns a command running a command line with the default shell.
+pub fn shell_command(line: impl AsRef<OsStr>) -> Command {
+    Shell::default_shell().command(line)
+}
+
+/// editor returns the editor of the user, $VISUAL or $EDITOR, vi otherwise.
+pub fn editor() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| String::from("vi"))
+}
+
+/// editor_command runs the editor through the shell since it can hold arguments,
+/// `code --wait`. On unix the arguments are passed as positional parameters to sh,
+/// which doesn't need them quoted, whatever the shell of the user is.
+#[cfg(unix)]
+pub fn editor_command(editor: &str, args: &[String]) -> Command {
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(format!("{} \"$@\"", editor))
+        .arg("sh")
+        .args(args);
+    command
+}
+
+#[cfg(windows)]
+pub fn editor_command(editor: &str, args: &[String]) -> Command {
+    let args: Vec<String> = args.iter().map(|arg| format!("\"{}\"", arg)).collect();
+    shell_command(format!("{} {}", editor, args.join(" ")))
+}
+
+/// output_with_timeout runs a command like Command::output does. Past the timeout, or once
+/// `cancelled` is set, the command and the processes it started are killed and None is returned.
+pub fn output_with_timeout(
+    command: &mut Command,
+    timeout: Option<Duration>,
+    cancelled: &AtomicBool,
+) -> io::Result<Option<Output>> {
+    command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    // the command gets its own process group, killed at once with its children.
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(command, 0);
+    let mut child = command.spawn()?;
+    let stdout = read_all(child.stdout.take());
+    let stderr = read_all(child.stderr.take());
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(Output {
+                status,
+                stdout: join(stdout)?,
+                stderr: join(stderr)?,
+            }));
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline)
+            || cancelled.load(Ordering::SeqCst)
+        {
+            kill(&mut child)?;
+            child.wait()?;
+            // the readers are left behind, a process that left the group may still hold the pipes.
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// LineStream runs a command and hands out the lines it prints on stdout as they come,
+/// instead of once it's done.
+pub struct LineStream {
+    child: Child,
+    lines: Receiver<String>,
+    stderr: JoinHandle<io::Result<Vec<u8>>>,
+    done: bool,
+}
+
+impl LineStream {
+    pub fn spawn(command: &mut Command) -> io::Result<Self> {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(command, 0);
+        let mut child = command.spawn()?;
+        let stdout = child.stdout.take();
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            let Some(stdout) = stdout else { return };
+            let mut stdout = BufReader::new(stdout);
+            let mut line = vec![];
+            while matches!(stdout.read_until(b'\n', &mut line), Ok(n) if n > 0) {
+                let text = String::from_utf8_lossy(&line);
+                if sender
+                    .send(text.trim_end_matches(['\n', '\r']).to_string())
+                    .is_err()
+                {
+                    return;
+                }
+                line.clear();
+            }
+        });
+        let stderr = read_all(child.stderr.take());
+        Ok(LineStream {
+            child,
+            lines,
+            stderr,
+            done: false,
+        })
+    }
+
+    /// next_lines returns the lines printed since it was last called, it waits up to `wait`
+    /// for the first one. None once the command closed its stdout and every line was returned.
+    pub fn next_lines(&mut self, wait: Duration) -> Option<Vec<String>> {
+        if self.done {
+            return None;
+        }
+        let mut lines = match self.lines.recv_timeout(wait) {
+            Ok(line) => vec![line],
+            Err(RecvTimeoutError::Timeout) => return Some(vec![]),
+            Err(RecvTimeoutError::Disconnected) => {
+                self.done = true;
+                return None;
+            }
+        };
+        lines.extend(self.lines.try_iter());
+        Some(lines)
+    }
+
+    pub const fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// wait waits for the command to exit and returns its status along with what it printed on stderr.
+    pub fn wait(mut self) -> io::Result<(ExitStatus, Vec<u8>)> {
+        let status = self.child.wait()?;
+        Ok((status, join(self.stderr)?))
+    }
+
+    /// kill stops the command and the processes it started, when its lines are no longer needed.
+    pub fn kill(mut self) -> io::Result<()> {
+        kill(&mut self.child)?;
+        self.child.wait().map(|_| ())
+    }
+}
+
+fn read_all(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut content = vec![];
+        if let Some(mut pipe) = pipe {
+            pipe.read_to_end(&mut content)?;
+        }
+        Ok(content)
+    })
+}
+
+fn join(reader: JoinHandle<io::Result<Vec<u8>>>) -> io::Result<Vec<u8>> {
+    reader
+        .join()
+        .map_err(|_| io::Error::other("could not read the output of the command"))?
+}
+
+#[cfg(unix)]
+fn kill(child: &mut Child) -> io::Result<()> {
+    let group = -(child.id() as libc::pid_t);
+    if unsafe { libc::kill(group, libc::SIGKILL) } == -1 {
+        return child.kill();
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn kill(child: &mut Child) -> io::Result<()> {
+    child.kill()
+}
+
+#[cfg(unix)]
+fn current_shell() -> String {
     env::var("SHELL").unwrap_or_else(|_| String::from("/bin/sh"))
 }
 
+#[cfg(windows)]
+fn current_shell() -> String {
+    env::var("COMSPEC").unwrap_or_else(|_| String::from("cmd"))
+}
+
+// shell_name is the name of the program of a shell, `bash` for `/bin/bash`.
+fn shell_name(shell: &str) -> String {
+    let name = shell.rsplit(['/', '\\']).next().unwrap_or(shell);
+    name.to_lowercase().trim_end_matches(".exe").to_string()
+}
+
+// shell_flag is the flag a shell reads its command line from.
+fn shell_flag(shell: &str) -> &'static str {
+    match shell_name(shell).as_str() {
+        "cmd" => "/C",
+        "powershell" | "pwsh" => "-Command",
+        _ => "-c",
+    }
+}
+
+// login_flag is the flag starting a shell as a login shell, it has to come first.
+fn login_flag(shell: &str) -> Option<&'static str> {
+    match shell_name(shell).as_str() {
+        "cmd" | "powershell" => None,
+        "pwsh" => Some("-Login"),
+        _ => Some("-l"),
+    }
+}
+
 impl<T> ShellCommand<T>
 where
     T: Clone,
 {
     pub fn new(command: T) -> Self {
-        Self { command }
+        Self {
+            command,
+            shell: None,
+        }
+    }
+
+    /// with_shell runs the command with the shell rather than the default one.
+    pub fn with_shell(mut self, shell: Option<Shell>) -> Self {
+        self.shell = shell;
+        self
     }
 
     pub fn make_command<U>(u: U) -> Command
@@ -53,13 +368,12 @@ impl ShellCommand<String> {
             .to_string();
         let command_escaped = shellwords::escape(&sanitized);
         let s = format!("echo \"{}\"|envsubst", command_escaped);
-        let shell_cmd = ShellCommand::<String>::new(s);
+        // the configured shell may not understand the pipe, nu for instance.
+        let shell_cmd = ShellCommand::<String>::new(s).with_shell(Some(Shell::user()));
         let mut cmd: Command = shell_cmd.into();
         cmd.envs(variables);
         let out = cmd.output()?;
-        let new_cmd = String::from_utf8_lossy(out.stdout.as_slice())
-            .replace('\n', "")
-            .replace('\\', "");
+        let new_cmd = String::from_utf8_lossy(out.stdout.as_slice()).replace(['\n', '\\'], "");
         Ok(ShellCommand::<String>::new(new_cmd))
     }
 }
@@ -94,8 +408,8 @@ where
     T: AsRef<OsStr> + Clone,
 {
     fn into(self) -> Command {
-        let mut command = Command::new(current_shell_or_sh());
-        command.arg("-c").arg(self.command);
+        let shell = self.shell.unwrap_or_else(Shell::default_shell);
+        let mut command = shell.command(self.command);
         command.envs(env::vars());
         let curr_dir = std::env::current_dir();
         if let Ok(dir) = curr_dir {
@@ -105,9 +419,114 @@ where
     }
 }
 
+#[derive(Debug, Error)]
+pub enum ErrorsShell {
+    #[error("invalid shell '{0}', expected a program followed by its arguments, `zsh -c`")]
+    Invalid(String),
+}
+
 #[cfg(test)]
 mod tests {
-    use super::ShellCommand;
+    use super::{output_with_timeout, shell_command, shell_flag, LineStream, Shell, ShellCommand};
+    use std::process::Command;
+    use std::sync::atomic::AtomicBool;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_output_with_timeout() {
+        let running = AtomicBool::new(false);
+        let output = output_with_timeout(&mut shell_command("echo hi"), None, &running).unwrap();
+        assert_eq!(output.unwrap().stdout, b"hi\n");
+        let timeout = Some(Duration::from_secs(5));
+        let output = output_with_timeout(&mut shell_command("echo hi; exit 3"), timeout, &running)
+            .unwrap()
+            .unwrap();
+        assert_eq!(output.stdout, b"hi\n");
+        assert_eq!(output.status.code(), Some(3));
+
+        let started = Instant::now();
+        let timeout = Some(Duration::from_millis(100));
+        let output =
+            output_with_timeout(&mut shell_command("sleep 5; echo late"), timeout, &running);
+        assert!(output.unwrap().is_none());
+        assert!(started.elapsed() < Duration::from_secs(4));
+
+        let started = Instant::now();
+        let cancelled = AtomicBool::new(true);
+        let output = output_with_timeout(&mut shell_command("sleep 5"), None, &cancelled);
+        assert!(output.unwrap().is_none());
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+
+    #[test]
+    fn test_line_stream() {
+        let started = Instant::now();
+        let mut stream =
+            LineStream::spawn(&mut shell_command("echo one; sleep 5; echo two")).unwrap();
+        let mut lines = vec![];
+        while lines.is_empty() {
+            lines = stream.next_lines(Duration::from_secs(1)).unwrap();
+        }
+        assert_eq!(lines, vec!["one"]);
+        assert_eq!(stream.next_lines(Duration::ZERO), Some(vec![]));
+        stream.kill().unwrap();
+        assert!(started.elapsed() < Duration::from_secs(4));
+
+        let mut stream =
+            LineStream::spawn(&mut shell_command("printf 'a\\nb\\n'; echo oops >&2")).unwrap();
+        let mut lines = vec![];
+        while let Some(batch) = stream.next_lines(Duration::from_secs(1)) {
+            lines.extend(batch);
+        }
+        assert!(stream.is_done());
+        assert_eq!(lines, vec!["a", "b"]);
+        let (status, stderr) = stream.wait().unwrap();
+        assert!(status.success());
+        assert_eq!(stderr, b"oops\n");
+    }
+
+    #[test]
+    fn test_shell() {
+        let shell = Shell::parse("zsh -c").unwrap();
+        assert_eq!(shell.to_string(), "zsh -c");
+        assert_eq!(Shell::parse("fish").unwrap().to_string(), "fish -c");
+        assert_eq!(
+            Shell::parse("bash --noprofile -c").unwrap().to_string(),
+            "bash --noprofile -c"
+        );
+        assert_eq!(
+            Shell::parse("/bin/bash")
+                .unwrap()
+                .with_login(true)
+                .to_string(),
+            "/bin/bash -l -c"
+        );
+        assert_eq!(
+            Shell::parse("pwsh").unwrap().with_login(true).to_string(),
+            "pwsh -Login -Command"
+        );
+        assert_eq!(
+            Shell::parse("cmd").unwrap().with_login(true).to_string(),
+            "cmd /C"
+        );
+        assert!(Shell::parse(" ").is_err());
+        assert!(Shell::parse("zsh '-c").is_err());
+
+        let mut command: Command = ShellCommand::new("echo \"$0\"")
+            .with_shell(Some(Shell::parse("sh -c").unwrap()))
+            .into();
+        let output = command.output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "sh\n");
+    }
+
+    #[test]
+    fn test_shell_flag() {
+        assert_eq!(shell_flag("/bin/zsh"), "-c");
+        assert_eq!(shell_flag("sh"), "-c");
+        assert_eq!(shell_flag("C:\\Windows\\system32\\cmd.exe"), "/C");
+        assert_eq!(shell_flag("pwsh"), "-Command");
+        assert_eq!(shell_flag("powershell.exe"), "-Command");
+    }
 
     #[test]
     fn test_replace_env_vars_in_command() {
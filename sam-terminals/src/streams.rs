@@ -0,0 +1,87 @@
+use std::fs::File;
+use std::io::{self, IsTerminal};
+
+#[cfg(unix)]
+const NULL: &str = "/dev/null";
+#[cfg(windows)]
+const NULL: &str = "NUL";
+
+#[cfg(unix)]
+const TERMINAL_OUTPUT: &str = "/dev/tty";
+#[cfg(windows)]
+const TERMINAL_OUTPUT: &str = "CONOUT$";
+
+/// detach_stdin replaces the stdin of sam, which the commands inherit, by /dev/null.
+pub fn detach_stdin() -> io::Result<()> {
+    replace_stdin(&File::open(NULL)?)
+}
+
+/// stdout_to_terminal draws what sam writes to stdout on the terminal when stdout is
+/// captured, by a shell widget for instance. It returns where stdout pointed before.
+pub fn stdout_to_terminal() -> io::Result<File> {
+    let stdout = duplicate_stdout()?;
+    if stdout.is_terminal() {
+        return Ok(stdout);
+    }
+    if let Ok(terminal) = File::options().write(true).open(TERMINAL_OUTPUT) {
+        replace_stdout(&terminal)?;
+    }
+    Ok(stdout)
+}
+
+#[cfg(unix)]
+fn duplicate_stdout() -> io::Result<File> {
+    use std::os::unix::io::AsFd;
+    Ok(File::from(io::stdout().as_fd().try_clone_to_owned()?))
+}
+
+#[cfg(unix)]
+fn replace_stdin(file: &File) -> io::Result<()> {
+    dup2(file, libc::STDIN_FILENO)
+}
+
+#[cfg(unix)]
+fn replace_stdout(file: &File) -> io::Result<()> {
+    dup2(file, libc::STDOUT_FILENO)
+}
+
+#[cfg(unix)]
+fn dup2(file: &File, fd: libc::c_int) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    // SAFETY: the file descriptor is valid as long as the file is open, dup2 keeps its own.
+    if unsafe { libc::dup2(file.as_raw_fd(), fd) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn duplicate_stdout() -> io::Result<File> {
+    use std::os::windows::io::AsHandle;
+    Ok(File::from(io::stdout().as_handle().try_clone_to_owned()?))
+}
+
+#[cfg(windows)]
+fn replace_stdin(file: &File) -> io::Result<()> {
+    set_std_handle(windows_sys::Win32::System::Console::STD_INPUT_HANDLE, file)
+}
+
+#[cfg(windows)]
+fn replace_stdout(file: &File) -> io::Result<()> {
+    set_std_handle(windows_sys::Win32::System::Console::STD_OUTPUT_HANDLE, file)
+}
+
+#[cfg(windows)]
+fn set_std_handle(
+    std_handle: windows_sys::Win32::System::Console::STD_HANDLE,
+    file: &File,
+) -> io::Result<()> {
+    use std::os::windows::io::IntoRawHandle;
+    // the handle is never closed, it's the standard stream of sam from now on.
+    let handle = file.try_clone()?.into_raw_handle();
+    // SAFETY: the handle is valid and owned by no one else.
+    if unsafe { windows_sys::Win32::System::Console::SetStdHandle(std_handle, handle as _) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -10,11 +10,13 @@ fn main() {
             text: String::from("option"),
             key: 'o',
             active: false,
+            tag: None,
         },
         OptionToggle {
             text: String::from("not option"),
             key: 'n',
             active: true,
+            tag: None,
         },
     ];
     let controller = ModalView::new(initial_list, initial_options, true);
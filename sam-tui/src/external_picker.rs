@@ -0,0 +1,162 @@
+use crate::modal_view::Value;
+use crate::ErrorsUIV2;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Picker is the program used to select aliases and choices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Picker {
+    #[default]
+    Builtin,
+    Fzf,
+    Skim,
+}
+
+impl Picker {
+    /// program returns the binary to run for external pickers.
+    pub const fn program(&self) -> Option<&'static str> {
+        match self {
+            Picker::Builtin => None,
+            Picker::Fzf => Some("fzf"),
+            Picker::Skim => Some("sk"),
+        }
+    }
+}
+
+/// choose runs an fzf compatible picker. Each line given to the picker starts
+/// with the index of the choice followed by a tab, the index is hidden from
+/// the user and used to find the preview of the choice and the selected values.
+/// Descriptions follow the text, dimmed.
+pub fn choose<T: Value>(
+    program: &str,
+    choices: Vec<T>,
+    prompt: &str,
+    allow_multiple: bool,
+) -> Result<HashSet<T>, ErrorsUIV2> {
+    let previews_dir = std::env::temp_dir().join(format!("sam-previews-{}", std::process::id()));
+    fs::create_dir_all(&previews_dir)?;
+    let selection = run(program, &previews_dir, &choices, prompt, allow_multiple);
+    fs::remove_dir_all(&previews_dir)?;
+    let selected: HashSet<T> = selection?
+        .into_iter()
+        .filter_map(|idx| choices.get(idx).cloned())
+        .collect();
+    if selected.is_empty() {
+        Err(ErrorsUIV2::EmptySelection)
+    } else {
+        Ok(selected)
+    }
+}
+
+fn run<T: Value>(
+    program: &str,
+    previews_dir: &Path,
+    choices: &[T],
+    prompt: &str,
+    allow_multiple: bool,
+) -> Result<Vec<usize>, ErrorsUIV2> {
+    for (idx, choice) in choices.iter().enumerate() {
+        fs::write(previews_dir.join(idx.to_string()), choice.preview())?;
+    }
+    let mut cmd = Command::new(program);
+    cmd.args(["--ansi", "--delimiter", "\t", "--with-nth", "2.."])
+        .arg("--prompt")
+        .arg(format!("{} > ", prompt))
+        .arg("--preview")
+        .arg(format!("cat '{}'/{{1}}", previews_dir.display()));
+    if allow_multiple {
+        cmd.arg("--multi");
+    }
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(picker_input(choices).as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(ErrorsUIV2::EmptySelection);
+    }
+    Ok(parse_selection(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn picker_input<T: Value>(choices: &[T]) -> String {
+    choices
+        .iter()
+        .enumerate()
+        .map(|(idx, choice)| {
+            let mut line = if choice.deprecated() {
+                format!("{}\t\x1b[9m{}\x1b[0m", idx, single_line(choice.text()))
+            } else {
+                format!("{}\t{}", idx, single_line(choice.text()))
+            };
+            if let Some(desc) = choice.desc() {
+                line.push_str(&format!("\t\x1b[2m{}\x1b[0m", single_line(desc)));
+            }
+            line.push('\n');
+            line
+        })
+        .collect()
+}
+
+fn single_line(s: &str) -> String {
+    s.replace(['\t', '\n'], " ")
+}
+
+fn parse_selection(output: &str) -> Vec<usize> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter_map(|(idx, _)| idx.parse().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_selection, picker_input};
+    use crate::modal_view::Value;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    struct Item(&'static str, Option<&'static str>, bool);
+
+    impl Value for Item {
+        fn text(&self) -> &str {
+            self.0
+        }
+        fn desc(&self) -> Option<&str> {
+            self.1
+        }
+        fn deprecated(&self) -> bool {
+            self.2
+        }
+        fn preview(&self) -> String {
+            String::new()
+        }
+    }
+
+    #[test]
+    fn test_picker_input_and_selection() {
+        let choices = vec![
+            Item("ns::list\tlists", None, false),
+            Item("ns::show", Some("shows"), false),
+            Item("ns::old", None, true),
+        ];
+        let input = picker_input(&choices);
+        assert_eq!(
+            input,
+            "0\tns::list lists\n1\tns::show\t\x1b[2mshows\x1b[0m\n2\t\x1b[9mns::old\x1b[0m\n"
+        );
+        assert_eq!(
+            parse_selection("1\tns::show\n0\tns::list lists\n"),
+            vec![1, 0]
+        );
+        assert!(parse_selection("").is_empty());
+    }
+}
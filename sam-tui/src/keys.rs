@@ -0,0 +1,93 @@
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use std::io;
+use std::time::Duration;
+
+/// Key is a key pressed by the user, read from the terminal on any platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Ctrl(char),
+    Alt(char),
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    BackTab,
+    Insert,
+    Esc,
+    F(u8),
+}
+
+impl Key {
+    // from_event maps the keys of crossterm, enter and tab are characters like any other.
+    fn from_event(event: KeyEvent) -> Option<Key> {
+        let key = match event.code {
+            KeyCode::Char(c) if event.modifiers.contains(KeyModifiers::CONTROL) => Key::Ctrl(c),
+            KeyCode::Char(c) if event.modifiers.contains(KeyModifiers::ALT) => Key::Alt(c),
+            KeyCode::Char(c) => Key::Char(c),
+            KeyCode::Enter => Key::Char('\n'),
+            KeyCode::Tab => Key::Char('\t'),
+            KeyCode::Backspace => Key::Backspace,
+            KeyCode::Delete => Key::Delete,
+            KeyCode::Left => Key::Left,
+            KeyCode::Right => Key::Right,
+            KeyCode::Up => Key::Up,
+            KeyCode::Down => Key::Down,
+            KeyCode::Home => Key::Home,
+            KeyCode::End => Key::End,
+            KeyCode::PageUp => Key::PageUp,
+            KeyCode::PageDown => Key::PageDown,
+            KeyCode::BackTab => Key::BackTab,
+            KeyCode::Insert => Key::Insert,
+            KeyCode::Esc => Key::Esc,
+            KeyCode::F(n) => Key::F(n),
+            KeyCode::Null => return None,
+        };
+        Some(key)
+    }
+}
+
+/// read_key waits for the next event of the terminal, which is None when it isn't a key,
+/// a resize for instance.
+pub fn read_key() -> io::Result<Option<Key>> {
+    match event::read()? {
+        Event::Key(event) => Ok(Key::from_event(event)),
+        _ => Ok(None),
+    }
+}
+
+/// key_pressed waits up to the timeout for an event of the terminal, it tells whether
+/// there is one to read.
+pub fn key_pressed(timeout: Duration) -> io::Result<bool> {
+    event::poll(timeout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Key;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    #[test]
+    fn test_from_event() {
+        let key = |code, modifiers| Key::from_event(KeyEvent::new(code, modifiers));
+        assert_eq!(
+            key(KeyCode::Char('c'), KeyModifiers::CONTROL),
+            Some(Key::Ctrl('c'))
+        );
+        assert_eq!(
+            key(KeyCode::Char('G'), KeyModifiers::SHIFT),
+            Some(Key::Char('G'))
+        );
+        assert_eq!(
+            key(KeyCode::Enter, KeyModifiers::NONE),
+            Some(Key::Char('\n'))
+        );
+        assert_eq!(key(KeyCode::Null, KeyModifiers::NONE), None);
+    }
+}
@@ -1,4 +1,10 @@
+mod external_picker;
+mod keys;
 pub mod modal_view;
+mod pager;
+mod spinner;
 mod ui_v2;
+pub use external_picker::Picker;
+pub use pager::page;
 pub use ui_v2::ErrorsUIV2;
 pub use ui_v2::UserInterfaceV2;
@@ -10,4 +10,5 @@ pub use state::OptionToggle;
 pub use state::Value;
 pub use state::ViewResponse;
 pub use state::ViewState;
+pub use theme::{ColorSupport, InvalidColor, ThemeColor, ThemeName, ThemeSettings, UITheme};
 pub use view::ModalView;
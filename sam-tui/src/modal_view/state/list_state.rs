@@ -1,71 +1,150 @@
-use fzy_rs::has_match;
+use fzy_rs::{has_match, score};
 
 use crate::modal_view::state::Value;
 use std::collections::HashSet;
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct ListState<V: Value> {
     pub filter_query: ListFilter,
+    // only the values carrying all of these tags are displayed.
+    tags: Vec<String>,
     values: Vec<V>,
+    // what the filter is matched against for each value, its text and its desc.
+    haystacks: Vec<String>,
+    // the values matching each prefix of the filter, the last ones match all of it. A char
+    // added to the filter only narrows the last ones down, a char removed costs nothing.
+    matches: Vec<Vec<usize>>,
+    // the values matching the filter, the best matches first.
+    displayed: Vec<usize>,
     marked_values: HashSet<V>,
-    pub current_displayed_values: Vec<V>,
     pub highlighted_line: Option<usize>,
+    // the first displayed value that's visible, and how many of them fit in the view,
+    // all of them until the view tells.
+    offset: usize,
+    page_size: usize,
+}
+
+impl<V: Value> Default for ListState<V> {
+    fn default() -> Self {
+        ListState::new(vec![])
+    }
 }
 
 impl<V: Value> ListState<V> {
     pub fn new(list: Vec<V>) -> Self {
-        let cursor = list.first().map(|_| 0);
-        ListState::<V> {
-            values: list.clone(),
-            current_displayed_values: list,
+        let mut state = ListState::<V> {
+            values: vec![],
+            haystacks: vec![],
+            matches: vec![vec![]],
+            displayed: vec![],
             marked_values: HashSet::default(),
-            highlighted_line: cursor,
+            highlighted_line: None,
             filter_query: ListFilter::default(),
-        }
+            tags: vec![],
+            offset: 0,
+            page_size: usize::MAX,
+        };
+        state.extend(list);
+        state
     }
+
     pub fn displayed_values(&self) -> Vec<(bool, &V)> {
-        self.current_displayed_values
+        self.displayed
+            .iter()
+            .map(|idx| &self.values[*idx])
+            .map(|v| (self.marked_values.contains(v), v))
+            .collect()
+    }
+
+    /// visible_values returns the displayed values that fit in the view, the highlighted
+    /// line is then at `highlighted_line - offset`.
+    pub fn visible_values(&self) -> Vec<(bool, &V)> {
+        self.displayed
             .iter()
+            .skip(self.offset)
+            .take(self.page_size)
+            .map(|idx| &self.values[*idx])
             .map(|v| (self.marked_values.contains(v), v))
             .collect()
     }
 
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// set_page_size tells how many values fit in the view.
+    pub fn set_page_size(&mut self, page_size: usize) {
+        self.page_size = page_size.max(1);
+        self.scroll();
+    }
+
+    pub fn page_up(&mut self) {
+        let page_size = self.page_size;
+        self.offset = self.offset.saturating_sub(page_size);
+        self.move_to(|cursor, _| cursor.saturating_sub(page_size));
+    }
+
+    pub fn page_down(&mut self) {
+        let page_size = self.page_size;
+        self.offset = self.offset.saturating_add(page_size);
+        self.move_to(|cursor, last| cursor.saturating_add(page_size).min(last));
+    }
+
+    pub fn home(&mut self) {
+        self.move_to(|_, _| 0);
+    }
+
+    pub fn end(&mut self) {
+        self.move_to(|_, last| last);
+    }
+
+    // move_to moves the highlighted line to the one `to` picks given the current and the last
+    // ones, and scrolls the view so that it's visible.
+    fn move_to(&mut self, to: impl FnOnce(usize, usize) -> usize) {
+        let last = self.displayed.len().saturating_sub(1);
+        self.highlighted_line = self.highlighted_line.map(|cursor| to(cursor, last));
+        self.scroll();
+    }
+
+    // scroll moves the view the least it takes for the highlighted line to be visible.
+    fn scroll(&mut self) {
+        let cursor = self.highlighted_line.unwrap_or_default();
+        if cursor < self.offset {
+            self.offset = cursor;
+        } else if cursor - self.offset >= self.page_size {
+            self.offset = cursor + 1 - self.page_size;
+        }
+        let last_page = self.displayed.len().saturating_sub(self.page_size);
+        self.offset = self.offset.min(last_page);
+    }
+
+    pub fn highlighted_value(&self) -> Option<&V> {
+        self.highlighted_line
+            .and_then(|cursor| self.displayed.get(cursor))
+            .map(|idx| &self.values[*idx])
+    }
+
     pub fn up(&mut self) {
-        self.highlighted_line =
-            self.highlighted_line
-                .map(|cursor| if cursor > 0 { cursor - 1 } else { cursor });
+        self.move_to(|cursor, _| cursor.saturating_sub(1));
     }
 
     pub fn down(&mut self) {
-        self.highlighted_line = self.highlighted_line.map(|cursor| {
-            if cursor < self.current_displayed_values.len() - 1 {
-                cursor + 1
-            } else {
-                cursor
-            }
-        });
+        self.move_to(|cursor, last| if cursor < last { cursor + 1 } else { cursor });
     }
 
     pub fn mark(&mut self) -> Option<bool> {
-        let value = self
-            .highlighted_line
-            .and_then(|cursor| self.current_displayed_values.get(cursor));
-
-        if let Some(v) = value {
-            if self.marked_values.contains(v) {
-                self.marked_values.remove(v);
-                Some(false)
-            } else {
-                self.marked_values.insert(v.clone());
-                Some(true)
-            }
+        let value = self.highlighted_value().cloned()?;
+        if self.marked_values.remove(&value) {
+            Some(false)
         } else {
-            None
+            self.marked_values.insert(value);
+            Some(true)
         }
     }
 
     pub fn mark_all(&mut self) {
-        for value in &self.current_displayed_values {
+        for idx in &self.displayed {
+            let value = &self.values[*idx];
             if !self.marked_values.contains(value) {
                 self.marked_values.insert(value.clone());
             }
@@ -73,29 +152,45 @@ impl<V: Value> ListState<V> {
     }
 
     pub fn entr(&mut self) -> Option<bool> {
-        let value = self
-            .highlighted_line
-            .and_then(|cursor| self.current_displayed_values.get(cursor));
-
-        if let Some(v) = value {
-            if self.marked_values.contains(v) {
-                Some(false)
-            } else {
-                self.marked_values.insert(v.clone());
-                Some(true)
-            }
+        let value = self.highlighted_value().cloned()?;
+        if self.marked_values.contains(&value) {
+            Some(false)
         } else {
-            None
+            self.marked_values.insert(value);
+            Some(true)
         }
     }
 
     pub fn update_filter(&mut self, c: char) {
         self.filter_query.push_back(c);
+        let pat = self.filter_query.as_ref().as_bytes();
+        let narrowed = self
+            .matches
+            .last()
+            .map(|matches| {
+                matches
+                    .iter()
+                    .copied()
+                    .filter(|idx| has_match(pat, self.haystacks[*idx].as_bytes()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.matches.push(narrowed);
         self.update_display_and_highlight()
     }
 
     pub fn remove_last_char_from_filter(&mut self) {
-        self.filter_query.pop();
+        if self.filter_query.pop().is_some() && self.matches.len() > 1 {
+            self.matches.pop();
+        }
+        self.update_display_and_highlight()
+    }
+
+    pub fn filter_tags(&mut self, tags: Vec<String>) {
+        self.tags = tags;
+        let prefixes = self.prefixes();
+        self.matches = vec![vec![]; prefixes.len()];
+        self.add_matches(0, &prefixes);
         self.update_display_and_highlight()
     }
 
@@ -103,16 +198,60 @@ impl<V: Value> ListState<V> {
         self.filter_query.as_ref()
     }
 
-    fn filtered_view(&self) -> Vec<V> {
-        let mut filters = Vec::with_capacity(self.values.len());
-        let pat = self.filter_query.as_ref().as_bytes();
-        for v in &self.values {
-            let text = v.text().as_bytes();
-            if has_match(pat, text) {
-                filters.push(v.clone());
+    /// extend appends values to the list, the ones that match the filter show up
+    /// without moving the highlighted line.
+    pub fn extend(&mut self, values: Vec<V>) {
+        let first = self.values.len();
+        self.haystacks.extend(values.iter().map(|v| match v.desc() {
+            Some(desc) => format!("{} {}", v.text(), desc),
+            None => v.text().to_string(),
+        }));
+        self.values.extend(values);
+        let prefixes = self.prefixes();
+        self.add_matches(first, &prefixes);
+        self.update_display_and_highlight();
+    }
+
+    // prefixes returns each prefix of the filter, from the empty one to the whole filter.
+    fn prefixes(&self) -> Vec<String> {
+        let query = self.filter_query.as_ref();
+        std::iter::once(0)
+            .chain(query.char_indices().map(|(i, c)| i + c.len_utf8()))
+            .map(|end| query[..end].to_string())
+            .collect()
+    }
+
+    // add_matches adds the values from `first` on to the matches of each prefix of the filter.
+    fn add_matches(&mut self, first: usize, prefixes: &[String]) {
+        for idx in first..self.values.len() {
+            let value = &self.values[idx];
+            if !self.tags.iter().all(|tag| value.tags().contains(tag)) {
+                continue;
+            }
+            let haystack = self.haystacks[idx].as_bytes();
+            for (matches, prefix) in self.matches.iter_mut().zip(prefixes) {
+                if !has_match(prefix.as_bytes(), haystack) {
+                    break;
+                }
+                matches.push(idx);
             }
         }
-        filters
+    }
+
+    // ranked sorts the values matching the filter by score, the ones that score the
+    // same keep the order of the list.
+    fn ranked(&self) -> Vec<usize> {
+        let matches = self.matches.last().cloned().unwrap_or_default();
+        let pat = self.filter_query.as_ref().as_bytes();
+        if pat.is_empty() {
+            return matches;
+        }
+        let mut scored: Vec<(f64, usize)> = matches
+            .into_iter()
+            .map(|idx| (score(pat, self.haystacks[idx].as_bytes()), idx))
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.into_iter().map(|(_, idx)| idx).collect()
     }
 
     pub fn marked_values(&self) -> HashSet<V> {
@@ -120,10 +259,10 @@ impl<V: Value> ListState<V> {
     }
 
     fn update_display_and_highlight(&mut self) {
-        self.current_displayed_values = self.filtered_view();
+        self.displayed = self.ranked();
         self.highlighted_line = if let Some(cursor) = self.highlighted_line {
-            if cursor >= self.current_displayed_values.len() {
-                if !self.current_displayed_values.is_empty() {
+            if cursor >= self.displayed.len() {
+                if !self.displayed.is_empty() {
                     Some(0)
                 } else {
                     None
@@ -131,11 +270,12 @@ impl<V: Value> ListState<V> {
             } else {
                 Some(cursor)
             }
-        } else if !self.current_displayed_values.is_empty() {
+        } else if !self.displayed.is_empty() {
             Some(0)
         } else {
             None
-        }
+        };
+        self.scroll();
     }
 }
 
@@ -152,14 +292,15 @@ impl ListFilter {
     pub fn push_back(&mut self, c: char) {
         self.0.push(c)
     }
-    pub fn pop(&mut self) {
-        self.0.pop();
+    pub fn pop(&mut self) -> Option<char> {
+        self.0.pop()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::modal_view::state::mocks::MockValue;
+    use crate::modal_view::state::Value;
 
     use super::ListState;
 
@@ -183,4 +324,98 @@ mod tests {
         assert!(list.marked_values.contains(&MockValue::new(2, "two")));
         assert!(list.marked_values.contains(&MockValue::new(4, "four")));
     }
+
+    #[test]
+    fn test_extend() {
+        let mut list = ListState::<MockValue>::new(vec![]);
+        list.update_filter('t');
+        list.extend(vec![MockValue::new(1, "one"), MockValue::new(2, "two")]);
+        assert_eq!(
+            list.displayed_values(),
+            vec![(false, &MockValue::new(2, "two"))]
+        );
+        assert_eq!(list.highlighted_line, Some(0));
+
+        list.down();
+        list.extend(vec![MockValue::new(3, "three"), MockValue::new(4, "four")]);
+        list.down();
+        list.mark();
+        assert!(list.marked_values.contains(&MockValue::new(3, "three")));
+        list.remove_last_char_from_filter();
+        assert_eq!(list.displayed_values().len(), 4);
+    }
+
+    #[test]
+    fn test_paging() {
+        let mut list = ListState::<MockValue>::new(
+            (0..10_000)
+                .map(|i| MockValue::new(i, &format!("pod-{}", i)))
+                .collect(),
+        );
+        list.set_page_size(20);
+        let visible = |list: &ListState<MockValue>| -> (usize, Option<usize>, usize) {
+            (
+                list.offset(),
+                list.highlighted_line,
+                list.visible_values().len(),
+            )
+        };
+        assert_eq!(visible(&list), (0, Some(0), 20));
+        list.page_down();
+        assert_eq!(visible(&list), (20, Some(20), 20));
+        list.page_up();
+        list.up();
+        assert_eq!(visible(&list), (0, Some(0), 20));
+        list.end();
+        assert_eq!(visible(&list), (9_980, Some(9_999), 20));
+        list.page_down();
+        assert_eq!(visible(&list), (9_980, Some(9_999), 20));
+        list.home();
+        assert_eq!(visible(&list), (0, Some(0), 20));
+
+        // the view follows the highlighted line when the filter leaves fewer values.
+        list.end();
+        list.update_filter('9');
+        list.update_filter('9');
+        list.update_filter('9');
+        assert_eq!(list.visible_values().len(), 20);
+        assert!(list.highlighted_line.unwrap() >= list.offset());
+    }
+
+    #[test]
+    fn test_ranking() {
+        let mut list = ListState::<MockValue>::new(vec![
+            MockValue::new(1, "app/models/zrder").with_tags(&["k8s"]),
+            MockValue::new(2, "app/models/order"),
+            MockValue::new(3, "app/models/order").with_tags(&["k8s"]),
+            MockValue::new(4, "nothing"),
+        ]);
+        let ids = |list: &ListState<MockValue>| -> Vec<String> {
+            list.displayed_values()
+                .iter()
+                .map(|(_, v)| v.text().to_string())
+                .collect()
+        };
+        for c in "amor".chars() {
+            list.update_filter(c);
+        }
+        assert_eq!(
+            ids(&list),
+            vec!["app/models/order", "app/models/order", "app/models/zrder"]
+        );
+        assert_eq!(list.matches.len(), 5);
+
+        list.filter_tags(vec![String::from("k8s")]);
+        assert_eq!(ids(&list), vec!["app/models/order", "app/models/zrder"]);
+
+        // the values matching the shorter filters are kept, removing chars goes back to them.
+        list.filter_tags(vec![]);
+        for _ in 0..4 {
+            list.remove_last_char_from_filter();
+        }
+        list.remove_last_char_from_filter();
+        assert_eq!(list.matches.len(), 1);
+        assert_eq!(ids(&list).len(), 4);
+        assert_eq!(ids(&list)[0], "app/models/zrder");
+    }
 }
@@ -14,6 +14,23 @@ pub use view_state::ViewResponse;
 pub trait Value: Eq + std::hash::Hash + Clone + std::fmt::Debug {
     fn text(&self) -> &str;
     fn preview(&self) -> String;
+    /// desc is displayed dimmed next to the text and can be searched as well.
+    fn desc(&self) -> Option<&str> {
+        None
+    }
+    /// tags are what the options of the view can limit the list to.
+    fn tags(&self) -> &[String] {
+        &[]
+    }
+    /// deprecated values are crossed out, they can still be selected.
+    fn deprecated(&self) -> bool {
+        false
+    }
+    /// preview_pending tells whether the preview is still on its way, the view is drawn
+    /// again until it's there.
+    fn preview_pending(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -25,6 +42,10 @@ pub enum Event {
     Entr,
     Up,
     Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
     Mark,
     MarkAll,
 }
@@ -33,11 +54,16 @@ pub mod mocks {
     use super::Value;
 
     #[derive(Eq, Hash, Clone, Debug, PartialEq, Default)]
-    pub struct MockValue(usize, String);
+    pub struct MockValue(usize, String, Vec<String>);
 
     impl MockValue {
         pub fn new(id: usize, msg: &str) -> Self {
-            MockValue(id, msg.to_string())
+            MockValue(id, msg.to_string(), vec![])
+        }
+
+        pub fn with_tags(mut self, tags: &[&str]) -> Self {
+            self.2 = tags.iter().map(|t| t.to_string()).collect();
+            self
         }
     }
     impl Value for MockValue {
@@ -48,6 +74,10 @@ pub mod mocks {
         fn preview(&self) -> String {
             self.1.clone()
         }
+
+        fn tags(&self) -> &[String] {
+            &self.2
+        }
     }
 }
 
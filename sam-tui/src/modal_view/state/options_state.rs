@@ -1,8 +1,39 @@
+// the keys given to the tag options when the first letter of a tag is already taken.
+const TAG_KEYS: &str = "abcdefghijklmnopqrstuvwxyz0123456789";
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct OptionToggle {
     pub key: char,
     pub text: String,
     pub active: bool,
+    // the tag the list is limited to while the option is active.
+    pub tag: Option<String>,
+}
+
+impl OptionToggle {
+    /// for_tags creates an option per tag, toggled with the first letter of the tag
+    /// when it's free, with another letter or a digit otherwise.
+    pub fn for_tags<'a>(tags: impl IntoIterator<Item = &'a str>) -> Vec<OptionToggle> {
+        let mut options: Vec<OptionToggle> = vec![];
+        for tag in tags {
+            let taken = |key: &char| options.iter().any(|o| o.key == *key);
+            let key = tag
+                .chars()
+                .next()
+                .map(|c| c.to_ascii_lowercase())
+                .filter(|c| !taken(c))
+                .or_else(|| TAG_KEYS.chars().find(|c| !taken(c)));
+            if let Some(key) = key {
+                options.push(OptionToggle {
+                    key,
+                    text: format!("only the aliases tagged {}", tag),
+                    active: false,
+                    tag: Some(tag.to_string()),
+                });
+            }
+        }
+        options
+    }
 }
 
 #[derive(Debug, Default)]
@@ -16,7 +47,7 @@ impl OptionsState {
     }
 
     pub fn toggle_option(&mut self, key: char) {
-        for mut a in &mut self.options {
+        for a in &mut self.options {
             if a.key == key {
                 a.active = !a.active;
             }
@@ -26,4 +57,22 @@ impl OptionsState {
     pub fn active(&self) -> impl Iterator<Item = OptionToggle> {
         self.options.clone().into_iter().filter(|e| e.active)
     }
+
+    pub fn active_tags(&self) -> Vec<String> {
+        self.active().filter_map(|o| o.tag).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OptionToggle;
+
+    #[test]
+    fn test_for_tags() {
+        let keys: Vec<char> = OptionToggle::for_tags(["k8s", "prod", "kafka", "Aws"])
+            .iter()
+            .map(|o| o.key)
+            .collect();
+        assert_eq!(keys, vec!['k', 'p', 'a', 'b']);
+    }
 }
@@ -5,9 +5,10 @@ use crate::modal_view::state::Event;
 use crate::modal_view::state::Value;
 use std::collections::HashSet;
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Default)]
 pub enum ViewMode {
     OptionsMode,
+    #[default]
     InsertMode,
 }
 
@@ -21,17 +22,13 @@ impl ViewMode {
     }
 }
 
-impl Default for ViewMode {
-    fn default() -> Self {
-        Self::InsertMode
-    }
-}
-
 #[derive(Debug, Default)]
 pub struct ViewState<V: Value> {
     pub current_mod: ViewMode,
     pub list: ListState<V>,
     pub options: OptionsState,
+    // whether more values are on their way to the list.
+    pub loading: bool,
 }
 
 #[derive(PartialEq, Debug)]
@@ -43,10 +40,7 @@ pub enum ExecutionState {
 
 impl<V: Value> ViewState<V> {
     pub fn preview(&self) -> Option<String> {
-        self.list
-            .highlighted_line
-            .and_then(|idx| self.list.current_displayed_values.get(idx))
-            .map(|v| v.preview())
+        self.list.highlighted_value().map(|v| v.preview())
     }
 }
 
@@ -56,6 +50,7 @@ impl<V: Value> ViewState<V> {
             current_mod: ViewMode::default(),
             list: ListState::new(list),
             options: OptionsState::new(options),
+            loading: false,
         }
     }
 
@@ -76,6 +71,7 @@ impl<V: Value> ViewState<V> {
             }
             Event::InputChar(c) if self.current_mod == ViewMode::OptionsMode => {
                 self.options.toggle_option(c);
+                self.list.filter_tags(self.options.active_tags());
                 ExecutionState::Keep
             }
 
@@ -92,6 +88,22 @@ impl<V: Value> ViewState<V> {
                 self.list.down();
                 ExecutionState::Keep
             }
+            Event::PageUp if self.current_mod == ViewMode::InsertMode => {
+                self.list.page_up();
+                ExecutionState::Keep
+            }
+            Event::PageDown if self.current_mod == ViewMode::InsertMode => {
+                self.list.page_down();
+                ExecutionState::Keep
+            }
+            Event::Home if self.current_mod == ViewMode::InsertMode => {
+                self.list.home();
+                ExecutionState::Keep
+            }
+            Event::End if self.current_mod == ViewMode::InsertMode => {
+                self.list.end();
+                ExecutionState::Keep
+            }
             Event::Mark if self.current_mod == ViewMode::InsertMode => {
                 self.list.mark();
                 ExecutionState::Keep
@@ -253,11 +265,13 @@ mod tests {
                     text: String::from("option"),
                     key: 'o',
                     active: false,
+                    tag: None,
                 },
                 OptionToggle {
                     text: String::from("not option"),
                     key: 'n',
                     active: false,
+                    tag: None,
                 },
             ],
             expected_response: ViewResponse {
@@ -268,9 +282,43 @@ mod tests {
                     text: String::from("option"),
                     key: 'o',
                     active: true,
+                    tag: None,
                 }],
             },
         };
         run_case(case_toggle_option_filter_select)
     }
+
+    #[test]
+    fn toggle_tag_options_filter_elements() {
+        let case_toggle_tags = TestCase {
+            input_sequence: &[
+                Event::ToggleViewMode,
+                Event::InputChar('k'),
+                Event::InputChar('p'),
+                Event::InputChar('p'),
+                Event::ToggleViewMode,
+                Event::Down,
+                Event::Entr,
+            ],
+            initial_list: vec![
+                MockValue::new(1, "elem 1"),
+                MockValue::new(2, "elem 2").with_tags(&["k8s", "prod"]),
+                MockValue::new(3, "elem 3").with_tags(&["k8s"]),
+            ],
+            initial_options: OptionToggle::for_tags(["k8s", "prod"]),
+            expected_response: ViewResponse {
+                marked_values: vec![MockValue::new(3, "elem 3").with_tags(&["k8s"])]
+                    .into_iter()
+                    .collect(),
+                selected_options: vec![OptionToggle {
+                    text: String::from("only the aliases tagged k8s"),
+                    key: 'k',
+                    active: true,
+                    tag: Some(String::from("k8s")),
+                }],
+            },
+        };
+        run_case(case_toggle_tags)
+    }
 }
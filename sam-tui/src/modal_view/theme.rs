@@ -1,13 +1,52 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use thiserror::Error;
 use tui::style::{Color, Modifier, Style};
 
+static DEFAULT_THEME: OnceLock<UITheme> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct UITheme {
     pub background: Color,
     pub foreground: Color,
     pub highlight: Color,
     pub borders: Color,
+    pub marked: Color,
+    pub preview_header: Color,
+    pub prompt: Color,
 }
 
 impl UITheme {
+    /// set_default makes the theme the one the views are drawn with, the dark theme is
+    /// used until then. Only the first call has an effect.
+    pub fn set_default(theme: UITheme) {
+        let _ = DEFAULT_THEME.set(theme);
+    }
+
+    pub fn default_theme() -> UITheme {
+        DEFAULT_THEME.get().copied().unwrap_or_default()
+    }
+
+    /// for_colors makes the theme use the colors the terminal supports, the 24 bit
+    /// colors are replaced by the closest of the 256 colors otherwise.
+    pub fn for_colors(self, support: ColorSupport) -> Self {
+        let convert = |color| match support {
+            ColorSupport::TrueColor => color,
+            ColorSupport::Ansi256 => to_ansi256(color),
+        };
+        UITheme {
+            background: convert(self.background),
+            foreground: convert(self.foreground),
+            highlight: convert(self.highlight),
+            borders: convert(self.borders),
+            marked: convert(self.marked),
+            preview_header: convert(self.preview_header),
+            prompt: convert(self.prompt),
+        }
+    }
+
     pub(super) fn style(&self) -> Style {
         Style::default().fg(self.foreground).bg(self.background)
     }
@@ -17,15 +56,297 @@ impl UITheme {
             .bg(self.foreground)
             .fg(self.highlight)
     }
+    pub(super) fn marked_style(&self) -> Style {
+        Style::default()
+            .fg(self.marked)
+            .add_modifier(Modifier::BOLD)
+    }
+    pub(super) fn preview_header_style(&self) -> Style {
+        Style::default()
+            .fg(self.preview_header)
+            .add_modifier(Modifier::BOLD)
+    }
+    pub(super) fn prompt_style(&self) -> Style {
+        Style::default().fg(self.prompt).bg(self.background)
+    }
 }
 
 impl Default for UITheme {
     fn default() -> Self {
-        Self {
-            foreground: Color::Rgb(209, 208, 208),
-            background: Color::Rgb(38, 38, 38),
-            highlight: Color::Rgb(87, 85, 127),
-            borders: Color::Rgb(104, 134, 209),
+        ThemeName::Dark.theme()
+    }
+}
+
+/// ThemeName is one of the themes sam comes with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeName {
+    #[default]
+    Dark,
+    Light,
+    Solarized,
+}
+
+impl ThemeName {
+    pub const fn theme(&self) -> UITheme {
+        match self {
+            ThemeName::Dark => UITheme {
+                foreground: Color::Rgb(209, 208, 208),
+                background: Color::Rgb(38, 38, 38),
+                highlight: Color::Rgb(87, 85, 127),
+                borders: Color::Rgb(104, 134, 209),
+                marked: Color::Rgb(229, 192, 123),
+                preview_header: Color::Rgb(104, 134, 209),
+                prompt: Color::Rgb(209, 208, 208),
+            },
+            ThemeName::Light => UITheme {
+                foreground: Color::Rgb(56, 58, 66),
+                background: Color::Rgb(250, 250, 250),
+                highlight: Color::Rgb(250, 250, 250),
+                borders: Color::Rgb(64, 120, 242),
+                marked: Color::Rgb(193, 132, 1),
+                preview_header: Color::Rgb(64, 120, 242),
+                prompt: Color::Rgb(80, 161, 79),
+            },
+            ThemeName::Solarized => UITheme {
+                foreground: Color::Rgb(131, 148, 150),
+                background: Color::Rgb(0, 43, 54),
+                highlight: Color::Rgb(7, 54, 66),
+                borders: Color::Rgb(38, 139, 210),
+                marked: Color::Rgb(181, 137, 0),
+                preview_header: Color::Rgb(42, 161, 152),
+                prompt: Color::Rgb(133, 153, 0),
+            },
+        }
+    }
+}
+
+/// ColorSupport is how many colors the terminal can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSupport {
+    TrueColor,
+    Ansi256,
+}
+
+impl ColorSupport {
+    /// detect reads `COLORTERM`, which terminals supporting 24 bit colors set to
+    /// `truecolor` or `24bit`.
+    pub fn detect() -> Self {
+        Self::from_colorterm(std::env::var("COLORTERM").ok().as_deref())
+    }
+
+    fn from_colorterm(colorterm: Option<&str>) -> Self {
+        match colorterm {
+            Some("truecolor" | "24bit") => ColorSupport::TrueColor,
+            _ => ColorSupport::Ansi256,
+        }
+    }
+}
+
+/// ThemeSettings is the `[theme]` section of the configuration, a theme sam comes with
+/// and the colors overriding its own.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ThemeSettings {
+    #[serde(default)]
+    pub name: ThemeName,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<ThemeColor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub marked: Option<ThemeColor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview_header: Option<ThemeColor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<ThemeColor>,
+}
+
+impl ThemeSettings {
+    pub fn theme(&self, support: ColorSupport) -> UITheme {
+        let mut theme = self.name.theme();
+        let overrides = [
+            (&mut theme.highlight, self.highlight),
+            (&mut theme.marked, self.marked),
+            (&mut theme.preview_header, self.preview_header),
+            (&mut theme.prompt, self.prompt),
+        ];
+        for (color, setting) in overrides {
+            if let Some(ThemeColor(c)) = setting {
+                *color = c;
+            }
+        }
+        theme.for_colors(support)
+    }
+}
+
+/// ThemeColor is a color of the configuration, a name like `yellow`, one of the 256
+/// colors like `178` or a 24 bit color like `#e5c07b`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ThemeColor(pub Color);
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("invalid color '{0}', expected a name like yellow, a number from 0 to 255 or #rrggbb")]
+pub struct InvalidColor(String);
+
+const COLOR_NAMES: [(&str, Color); 16] = [
+    ("black", Color::Black),
+    ("red", Color::Red),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("gray", Color::Gray),
+    ("darkgray", Color::DarkGray),
+    ("lightred", Color::LightRed),
+    ("lightgreen", Color::LightGreen),
+    ("lightyellow", Color::LightYellow),
+    ("lightblue", Color::LightBlue),
+    ("lightmagenta", Color::LightMagenta),
+    ("lightcyan", Color::LightCyan),
+    ("white", Color::White),
+];
+
+impl FromStr for ThemeColor {
+    type Err = InvalidColor;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidColor(s.to_string());
+        let value = s.trim();
+        if let Some(hex) = value.strip_prefix('#') {
+            if hex.len() != 6 || !hex.is_ascii() {
+                return Err(invalid());
+            }
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+            return Ok(ThemeColor(Color::Rgb(
+                channel(0)?,
+                channel(2)?,
+                channel(4)?,
+            )));
+        }
+        if let Ok(index) = value.parse::<u8>() {
+            return Ok(ThemeColor(Color::Indexed(index)));
+        }
+        COLOR_NAMES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(value))
+            .map(|(_, color)| ThemeColor(*color))
+            .ok_or_else(invalid)
+    }
+}
+
+impl TryFrom<String> for ThemeColor {
+    type Error = InvalidColor;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ThemeColor> for String {
+    fn from(color: ThemeColor) -> Self {
+        color.to_string()
+    }
+}
+
+impl Display for ThemeColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Color::Rgb(r, g, b) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+            Color::Indexed(index) => write!(f, "{}", index),
+            color => {
+                let name = COLOR_NAMES
+                    .iter()
+                    .find(|(_, c)| *c == color)
+                    .map_or("reset", |(name, _)| name);
+                write!(f, "{}", name)
+            }
+        }
+    }
+}
+
+// to_ansi256 picks the closest color of the 6x6x6 cube or of the grayscale ramp
+// of the 256 colors.
+fn to_ansi256(color: Color) -> Color {
+    let Color::Rgb(r, g, b) = color else {
+        return color;
+    };
+    let level = |c: u8| -> u8 {
+        if c < 48 {
+            0
+        } else if c < 115 {
+            1
+        } else {
+            (c - 35) / 40
         }
+    };
+    let (lr, lg, lb) = (level(r), level(g), level(b));
+    let cube_value = |l: u8| if l == 0 { 0 } else { 55 + 40 * l };
+    let cube = (cube_value(lr), cube_value(lg), cube_value(lb));
+
+    let average = (u16::from(r) + u16::from(g) + u16::from(b)) / 3;
+    let gray_level = if average > 238 {
+        23
+    } else {
+        (average.saturating_sub(3) / 10) as u8
+    };
+    let gray = 8 + 10 * gray_level;
+
+    let distance = |(cr, cg, cb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2);
+        d(cr, r) + d(cg, g) + d(cb, b)
+    };
+    if distance((gray, gray, gray)) < distance(cube) {
+        Color::Indexed(232 + gray_level)
+    } else {
+        Color::Indexed(16 + 36 * lr + 6 * lg + lb)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_ansi256, ColorSupport, ThemeColor, ThemeName, ThemeSettings};
+    use tui::style::Color;
+
+    #[test]
+    fn test_parse_color() {
+        let parse = |s: &str| s.parse::<ThemeColor>().map(|c| c.0);
+        assert_eq!(parse("#e5c07b"), Ok(Color::Rgb(229, 192, 123)));
+        assert_eq!(parse("178"), Ok(Color::Indexed(178)));
+        assert_eq!(parse("LightBlue"), Ok(Color::LightBlue));
+        assert!(parse("#e5c07").is_err());
+        assert!(parse("256").is_err());
+        assert!(parse("purple").is_err());
+        for color in ["#e5c07b", "178", "lightblue"] {
+            assert_eq!(color.parse::<ThemeColor>().unwrap().to_string(), color);
+        }
+    }
+
+    #[test]
+    fn test_to_ansi256() {
+        assert_eq!(to_ansi256(Color::Rgb(0, 0, 0)), Color::Indexed(16));
+        assert_eq!(to_ansi256(Color::Rgb(255, 255, 255)), Color::Indexed(231));
+        assert_eq!(to_ansi256(Color::Rgb(255, 0, 0)), Color::Indexed(196));
+        assert_eq!(to_ansi256(Color::Rgb(38, 38, 38)), Color::Indexed(235));
+        assert_eq!(to_ansi256(Color::Yellow), Color::Yellow);
+    }
+
+    #[test]
+    fn test_theme_settings() {
+        let settings = ThemeSettings {
+            name: ThemeName::Light,
+            marked: Some(ThemeColor(Color::Yellow)),
+            ..ThemeSettings::default()
+        };
+        let theme = settings.theme(ColorSupport::TrueColor);
+        assert_eq!(theme.marked, Color::Yellow);
+        assert_eq!(theme.background, ThemeName::Light.theme().background);
+
+        let theme = settings.theme(ColorSupport::Ansi256);
+        assert_eq!(theme.background, Color::Indexed(231));
+        assert_eq!(
+            ColorSupport::from_colorterm(Some("truecolor")),
+            ColorSupport::TrueColor
+        );
+        assert_eq!(ColorSupport::from_colorterm(None), ColorSupport::Ansi256);
     }
 }
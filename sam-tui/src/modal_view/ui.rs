@@ -1,10 +1,11 @@
+use crossterm::execute;
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
 use std::cell::{Cell, RefCell};
-use std::io::Stdout;
+use std::io::{self, Stdout};
 use std::marker::PhantomData;
 use std::time::SystemTime;
-use termion::raw::{IntoRawMode, RawTerminal};
-use termion::screen::AlternateScreen;
-use tui::backend::TermionBackend;
+use tui::backend::CrosstermBackend;
+use tui::layout::Rect;
 
 use tui::Terminal;
 
@@ -17,7 +18,9 @@ use super::ui_options_mode::UIOptionsMode;
 const MIN_TIME_TO_REFRESH_IN_MS: u128 = 75;
 
 pub struct UIModal<V: Value> {
-    raw_terminal: RefCell<RawTerminal<Stdout>>,
+    terminal: RefCell<Terminal<CrosstermBackend<Stdout>>>,
+    // whether the terminal is in raw mode and shows the alternate screen.
+    raw_mode: bool,
     last_update: Cell<Option<SystemTime>>,
 
     theme: UITheme,
@@ -25,21 +28,28 @@ pub struct UIModal<V: Value> {
 }
 
 impl<V: Value> UIModal<V> {
-    pub fn new() -> std::io::Result<Self> {
-        let raw_stdout = std::io::stdout().into_raw_mode()?;
+    pub fn new() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen)?;
         Ok(UIModal {
-            raw_terminal: RefCell::new(raw_stdout),
+            terminal: RefCell::new(Terminal::new(CrosstermBackend::new(stdout))?),
+            raw_mode: true,
             last_update: Cell::new(None),
-            theme: UITheme::default(),
-            _marker: PhantomData::default(),
+            theme: UITheme::default_theme(),
+            _marker: PhantomData,
         })
     }
 
     pub fn suspend_raw_mode(&mut self) {
-        let raw_terminal = &mut *self.raw_terminal.borrow_mut();
-        raw_terminal
-            .suspend_raw_mode()
-            .expect("Can't suspect raw mode");
+        if !self.raw_mode {
+            return;
+        }
+        self.raw_mode = false;
+        let terminal = self.terminal.get_mut();
+        execute!(terminal.backend_mut(), LeaveAlternateScreen).expect("Can't leave the screen");
+        terminal.show_cursor().expect("Can't show the cursor");
+        terminal::disable_raw_mode().expect("Can't suspect raw mode");
         // This is a workaround because on my machine I can't get
         // stdin and stdout to work after I suspend raw mode
         eprintln!();
@@ -48,13 +58,12 @@ impl<V: Value> UIModal<V> {
 }
 
 impl<V: Value> UIModal<V> {
-    pub(super) fn draw(&self, state: &ViewState<V>) {
-        let raw_terminal = &mut *self.raw_terminal.borrow_mut();
-        let stdout = AlternateScreen::from(raw_terminal);
-        let backend = TermionBackend::new(stdout);
-        let mut terminal = Terminal::new(backend).expect("can't setup terminal");
+    /// draw tells whether the view was drawn, it isn't when the last time was too recent.
+    pub(super) fn draw(&self, state: &ViewState<V>) -> bool {
+        let terminal = &mut *self.terminal.borrow_mut();
 
-        if self.enough_time_since_last_refresh() {
+        let refresh = self.enough_time_since_last_refresh();
+        if refresh {
             terminal
                 .draw(|f| {
                     match state.current_mod {
@@ -66,7 +75,7 @@ impl<V: Value> UIModal<V> {
                             let insert_mode_view = UIInsertMode::new(f.size(), &self.theme);
                             insert_mode_view.draw(
                                 f,
-                                ListItems::from(state),
+                                ListItems::new(state, &self.theme),
                                 state.search_filter(),
                                 state.preview().unwrap_or_default().as_str(),
                             )
@@ -75,6 +84,14 @@ impl<V: Value> UIModal<V> {
                 })
                 .expect("Can't draw");
         }
+        refresh
+    }
+
+    /// list_height is how many values the list of the insert mode can show.
+    pub(super) fn list_height(&self) -> usize {
+        let (width, height) = crossterm::terminal::size().unwrap_or((80, 24));
+        let area = Rect::new(0, 0, width, height);
+        UIInsertMode::new(area, &self.theme).list_height()
     }
 
     fn enough_time_since_last_refresh(&self) -> bool {
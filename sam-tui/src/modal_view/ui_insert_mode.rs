@@ -1,6 +1,7 @@
 use tui::backend::Backend;
 use tui::layout::Direction;
-use tui::style::Style;
+use tui::style::{Modifier, Style};
+use tui::text::{Span, Spans};
 use tui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
 
 use tui::layout::{Alignment, Constraint};
@@ -39,31 +40,41 @@ impl<'a> UIInsertMode<'a> {
         }
     }
 
-    fn list_widget(&self, items: Vec<ListItem<'a>>) -> List {
+    /// list_height is how many lines of the list fit within its borders.
+    pub(super) fn list_height(&self) -> usize {
+        usize::from(self.list_chunk.height.saturating_sub(2))
+    }
+
+    fn list_widget(&self, items: Vec<ListItem<'a>>, loading: bool) -> List<'_> {
+        let title = if loading {
+            "Choices (loading…)"
+        } else {
+            "Choices"
+        };
         List::new(items)
-            .block(self.block("Choices"))
+            .block(self.block(Span::raw(title)))
             .style(self.theme.style())
             .highlight_style(self.theme.highlight_style())
             .highlight_symbol("➺ ")
     }
 
-    fn filter_widget(&self, filter_query: &'a str) -> Paragraph {
+    fn filter_widget(&self, filter_query: &'a str) -> Paragraph<'_> {
         Paragraph::new(filter_query)
-            .block(self.block("Filter"))
-            .style(self.theme.style())
+            .block(self.block(Span::raw("Filter")))
+            .style(self.theme.prompt_style())
             .alignment(Alignment::Left)
             .wrap(Wrap { trim: true })
     }
 
-    fn preview_widget(&self, preview: &'a str) -> Paragraph {
+    fn preview_widget(&self, preview: &'a str) -> Paragraph<'_> {
         Paragraph::new(preview)
-            .block(self.block("Preview"))
+            .block(self.block(Span::styled("Preview", self.theme.preview_header_style())))
             .style(self.theme.style())
             .alignment(Alignment::Left)
             .wrap(Wrap { trim: true })
     }
 
-    fn block(&self, title: &'static str) -> Block {
+    fn block(&self, title: Span<'static>) -> Block<'_> {
         Block::default()
             .title(title)
             .borders(Borders::ALL)
@@ -80,7 +91,7 @@ impl<'a> UIInsertMode<'a> {
     ) where
         B: Backend,
     {
-        let list_widget = self.list_widget(list_items.items);
+        let list_widget = self.list_widget(list_items.items, list_items.loading);
         let filter_widget = self.filter_widget(filter);
         let preview_widget = self.preview_widget(preview);
         f.render_stateful_widget(list_widget, self.list_chunk, &mut list_items.state);
@@ -92,29 +103,52 @@ impl<'a> UIInsertMode<'a> {
 pub(super) struct ListItems<'a> {
     items: Vec<ListItem<'a>>,
     state: ListState,
+    loading: bool,
 }
 
-impl<'a, V: Value> From<&'a ViewState<V>> for ListItems<'a> {
-    fn from(state: &'a ViewState<V>) -> Self {
+impl<'a> ListItems<'a> {
+    pub(super) fn new<V: Value>(state: &'a ViewState<V>, theme: &UITheme) -> Self {
         let items = state
             .list
-            .displayed_values()
+            .visible_values()
             .iter()
             .map(|e| {
-                if e.0 {
-                    ListItem::new(format!("❄ {}", e.1.text()))
+                let marker = if e.0 {
+                    Span::styled("❄ ", theme.marked_style())
+                } else {
+                    Span::raw("  ")
+                };
+                let text = if e.1.deprecated() {
+                    Span::styled(
+                        e.1.text(),
+                        Style::default().add_modifier(Modifier::CROSSED_OUT),
+                    )
                 } else {
-                    ListItem::new(format!("  {}", e.1.text()))
+                    Span::raw(e.1.text())
+                };
+                let mut spans = vec![marker, text];
+                if let Some(desc) = e.1.desc() {
+                    spans.push(Span::styled(
+                        format!("    {}", desc.replace('\n', " ")),
+                        Style::default().add_modifier(Modifier::DIM),
+                    ));
                 }
+                ListItem::new(Spans::from(spans))
             })
             .collect();
 
         let mut list_state = ListState::default();
-        list_state.select(state.list.highlighted_line);
+        list_state.select(
+            state
+                .list
+                .highlighted_line
+                .map(|line| line.saturating_sub(state.list.offset())),
+        );
 
         ListItems {
             items,
             state: list_state,
+            loading: state.loading,
         }
     }
 }
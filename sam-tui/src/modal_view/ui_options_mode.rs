@@ -27,7 +27,7 @@ impl<'a> UIOptionsMode<'a> {
         f.render_widget(options_widget, f.size())
     }
 
-    fn block(&self, title: &'static str) -> Block {
+    fn block(&self, title: &'static str) -> Block<'_> {
         Block::default()
             .title(title)
             .borders(Borders::ALL)
@@ -35,7 +35,7 @@ impl<'a> UIOptionsMode<'a> {
             .border_type(tui::widgets::BorderType::Rounded)
     }
 
-    fn options_widget(&self, options: &OptionsState) -> Paragraph {
+    fn options_widget(&self, options: &OptionsState) -> Paragraph<'_> {
         let mut text = String::new();
         for opt in &options.options {
             let toggle = if opt.active { "⌘" } else { " " };
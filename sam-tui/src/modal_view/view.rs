@@ -1,73 +1,131 @@
-use std::io::Stdin;
+use std::time::Duration;
 
+use crate::keys::{key_pressed, read_key, Key};
 use crate::modal_view::state::Event;
-use termion::input::{Keys, TermRead};
 
 use super::{
     state::{OptionToggle, Value, ViewResponse, ViewState},
     ui::UIModal,
 };
-use termion::event::Key;
 
-pub struct ModalView<V: Value> {
+// how often the source of a view is checked for new values, and the preview of the
+// highlighted value for its content, while the user doesn't type.
+const REFRESH_TICK: Duration = Duration::from_millis(50);
+
+type Source<'s, V> = Box<dyn FnMut() -> Option<Vec<V>> + 's>;
+
+pub struct ModalView<'s, V: Value> {
     state: ViewState<V>,
     ui: UIModal<V>,
-    init: bool,
-    events: Keys<Stdin>,
     has_options: bool,
     allow_multi_select: bool,
+    // hands out the values that come after the view is shown, None once there are no more.
+    source: Option<Source<'s, V>>,
+    // whether the last changes of the state are yet to be drawn.
+    stale: bool,
 }
 
-impl<V: Value> Drop for ModalView<V> {
+impl<V: Value> Drop for ModalView<'_, V> {
     fn drop(&mut self) {
         self.ui.suspend_raw_mode();
     }
 }
 
-impl<V: Value> ModalView<V> {
+impl<'s, V: Value> ModalView<'s, V> {
     pub fn new(list: Vec<V>, options: Vec<OptionToggle>, allow_multi_select: bool) -> Self {
         let has_options = !options.is_empty();
         let state = ViewState::<V>::new(list, options);
         let ui = UIModal::<V>::new().expect("Can't initialize the ui");
-        let events = std::io::stdin().keys();
         ModalView {
             state,
-            events,
             ui,
             has_options,
             allow_multi_select,
-            init: false,
+            source: None,
+            stale: true,
         }
     }
 
+    /// with_source appends the values the source hands out to the list while the user
+    /// filters it, until the source returns None.
+    pub fn with_source(mut self, source: impl FnMut() -> Option<Vec<V>> + 's) -> Self {
+        self.source = Some(Box::new(source));
+        self.state.loading = true;
+        self
+    }
+
     pub fn run(mut self) -> Option<ViewResponse<V>> {
-        if !self.init {
-            self.ui.draw(&self.state);
-            self.init = true;
-        }
-        if let Some(event) = self.next_event() {
+        loop {
+            self.pull_source();
+            if self.preview_pending() {
+                self.stale = true;
+            }
+            if self.stale {
+                self.stale = !self.draw();
+            }
+            if !self.key_pressed() {
+                continue;
+            }
+            let Some(event) = self.next_event() else {
+                continue;
+            };
             if event == Event::AppClosed {
                 self.ui.suspend_raw_mode();
                 return None;
             }
             let status = self.state.update(&event);
-            self.ui.draw(&self.state);
+            self.stale = !self.draw();
             match status {
-                super::state::ExecutionState::Keep => self.run(),
-                super::state::ExecutionState::ExitSuccess => Some(self.state.response()),
-                super::state::ExecutionState::Cancelled => None,
+                super::state::ExecutionState::Keep => {}
+                super::state::ExecutionState::ExitSuccess => return Some(self.state.response()),
+                super::state::ExecutionState::Cancelled => return None,
             }
-        } else {
-            self.run()
         }
     }
 
+    fn draw(&mut self) -> bool {
+        self.state.list.set_page_size(self.ui.list_height());
+        self.ui.draw(&self.state)
+    }
+
+    fn pull_source(&mut self) {
+        let Some(source) = self.source.as_mut() else {
+            return;
+        };
+        match source() {
+            Some(values) if values.is_empty() => {}
+            Some(values) => {
+                self.state.list.extend(values);
+                self.stale = true;
+            }
+            None => {
+                self.source = None;
+                self.state.loading = false;
+                self.stale = true;
+            }
+        }
+    }
+
+    fn preview_pending(&self) -> bool {
+        self.state
+            .list
+            .highlighted_value()
+            .is_some_and(V::preview_pending)
+    }
+
+    // key_pressed waits a tick for a key while the view has values to pull, a preview to
+    // wait for or changes to draw, it's otherwise left to next_event to block until one is pressed.
+    fn key_pressed(&self) -> bool {
+        if self.source.is_some() || self.stale || self.preview_pending() {
+            return key_pressed(REFRESH_TICK).unwrap_or(true);
+        }
+        true
+    }
+
     pub fn next_event(&mut self) -> Option<Event> {
-        self.events
-            .next()
-            .transpose()
-            .map(|ev| ev.and_then(|evt| self.key_transformer(evt)))
+        read_key()
             .expect("Can't read")
+            .and_then(|key| self.key_transformer(key))
     }
 
     fn key_transformer(&self, key: Key) -> Option<Event> {
@@ -81,6 +139,11 @@ impl<V: Value> ModalView<V> {
             Key::Ctrl('p') => Some(Event::Up),
             Key::Ctrl('n') => Some(Event::Down),
 
+            Key::PageUp => Some(Event::PageUp),
+            Key::PageDown => Some(Event::PageDown),
+            Key::Home => Some(Event::Home),
+            Key::End => Some(Event::End),
+
             Key::Ctrl('c') => Some(Event::AppClosed),
 
             Key::Ctrl('s') if self.allow_multi_select => Some(Event::Mark),
@@ -91,17 +154,11 @@ impl<V: Value> ModalView<V> {
             Key::Ctrl(_)
             | Key::Left
             | Key::Right
-            | Key::Home
-            | Key::End
             | Key::Esc
-            | Key::PageUp
-            | Key::PageDown
             | Key::BackTab
             | Key::Insert
             | Key::F(_)
-            | Key::Alt(_)
-            | Key::Null
-            | Key::__IsNotComplete => None,
+            | Key::Alt(_) => None,
         }
     }
 }
@@ -0,0 +1,316 @@
+use crate::keys::{read_key, Key};
+use crossterm::style::Attribute;
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, execute, queue};
+use std::io::{self, Write};
+
+const TAB: &str = "    ";
+
+/// page shows a text, which may contain colors, in the alternate screen of the terminal.
+/// It scrolls with the arrows, j/k, space/b and g/G, searches with `/` then n/N and
+/// quits with q or escape.
+pub fn page(text: &str) -> io::Result<()> {
+    let mut screen = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(screen, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let paged = browse(&mut PagerState::new(text), &mut screen);
+    // the terminal is restored even when the pager failed.
+    execute!(screen, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    paged
+}
+
+fn browse(pager: &mut PagerState, screen: &mut impl Write) -> io::Result<()> {
+    pager.draw(screen)?;
+    loop {
+        match read_key()? {
+            Some(key) if !pager.on_key(key) => return Ok(()),
+            // anything else, a resize included, redraws the page.
+            _ => pager.draw(screen)?,
+        }
+    }
+}
+
+/// PagerState is what the pager shows, the lines of the text from the top one,
+/// the search being typed and the line of the last match.
+#[derive(Debug)]
+struct PagerState {
+    lines: Vec<String>,
+    // the lines without their colors, in lowercase, where the searches happen.
+    plain: Vec<String>,
+    top: usize,
+    height: usize,
+    prompt: Option<String>,
+    pattern: Option<String>,
+    matched: Option<usize>,
+    status: Option<String>,
+}
+
+impl PagerState {
+    fn new(text: &str) -> PagerState {
+        let lines: Vec<String> = text.lines().map(|l| l.replace('\t', TAB)).collect();
+        let plain = lines.iter().map(|l| strip_ansi(l).to_lowercase()).collect();
+        PagerState {
+            lines,
+            plain,
+            top: 0,
+            height: 1,
+            prompt: None,
+            pattern: None,
+            matched: None,
+            status: None,
+        }
+    }
+
+    fn max_top(&self) -> usize {
+        self.lines.len().saturating_sub(self.height)
+    }
+
+    fn scroll_to(&mut self, line: usize) {
+        self.top = line.min(self.max_top());
+    }
+
+    fn scroll_by(&mut self, lines: isize) {
+        self.scroll_to(self.top.saturating_add_signed(lines));
+    }
+
+    /// on_key applies a key and returns false when it quits the pager.
+    fn on_key(&mut self, key: Key) -> bool {
+        self.status = None;
+        if let Some(prompt) = self.prompt.as_mut() {
+            match key {
+                Key::Char('\n') => {
+                    let pattern = self.prompt.take().unwrap_or_default();
+                    if !pattern.is_empty() {
+                        self.pattern = Some(pattern.to_lowercase());
+                        self.matched = None;
+                        self.search(true);
+                    }
+                }
+                Key::Esc | Key::Ctrl('c') => self.prompt = None,
+                Key::Backspace => {
+                    prompt.pop();
+                }
+                Key::Char(c) => prompt.push(c),
+                _ => {}
+            }
+            return true;
+        }
+        let page = self.height as isize;
+        match key {
+            Key::Char('q') | Key::Esc | Key::Ctrl('c') => return false,
+            Key::Char('j') | Key::Char('\n') | Key::Down => self.scroll_by(1),
+            Key::Char('k') | Key::Up => self.scroll_by(-1),
+            Key::Char(' ') | Key::Char('f') | Key::PageDown => self.scroll_by(page),
+            Key::Char('b') | Key::PageUp => self.scroll_by(-page),
+            Key::Char('g') | Key::Home => self.scroll_to(0),
+            Key::Char('G') | Key::End => self.scroll_to(usize::MAX),
+            Key::Char('/') => self.prompt = Some(String::new()),
+            Key::Char('n') => self.search(true),
+            Key::Char('N') => self.search(false),
+            _ => {}
+        }
+        true
+    }
+
+    // search moves to the next match of the pattern after the last one, or before it
+    // when it goes backward. The first search starts from the top line.
+    fn search(&mut self, forward: bool) {
+        let pattern = match &self.pattern {
+            Some(pattern) => pattern,
+            None => return,
+        };
+        let found = if forward {
+            let from = self.matched.map_or(self.top, |m| m + 1);
+            (from..self.plain.len()).find(|&i| self.plain[i].contains(pattern.as_str()))
+        } else {
+            let to = self.matched.unwrap_or(self.top);
+            (0..to)
+                .rev()
+                .find(|&i| self.plain[i].contains(pattern.as_str()))
+        };
+        match found {
+            Some(line) => {
+                self.matched = Some(line);
+                self.scroll_to(line);
+            }
+            None if self.matched.is_some() => self.status = Some(String::from("no more matches")),
+            None => self.status = Some(format!("pattern not found: {}", pattern)),
+        }
+    }
+
+    fn status_line(&self) -> String {
+        if let Some(prompt) = &self.prompt {
+            return format!("/{}", prompt);
+        }
+        if let Some(status) = &self.status {
+            return status.clone();
+        }
+        let bottom = (self.top + self.height).min(self.lines.len());
+        format!(
+            "lines {}-{}/{} (q to quit, / to search)",
+            (self.top + 1).min(bottom),
+            bottom,
+            self.lines.len()
+        )
+    }
+
+    fn draw(&mut self, w: &mut impl Write) -> io::Result<()> {
+        let (width, height) = terminal::size()?;
+        self.height = height.saturating_sub(1).max(1) as usize;
+        self.scroll_to(self.top);
+        for row in 0..self.height {
+            queue!(
+                w,
+                cursor::MoveTo(0, row as u16),
+                terminal::Clear(ClearType::CurrentLine)
+            )?;
+            let idx = self.top + row;
+            if let Some(line) = self.lines.get(idx) {
+                if self.matched == Some(idx) {
+                    write!(w, "{}", Attribute::Reverse)?;
+                }
+                write!(w, "{}{}", truncate(line, width as usize), Attribute::Reset)?;
+            }
+        }
+        queue!(
+            w,
+            cursor::MoveTo(0, height.saturating_sub(1)),
+            terminal::Clear(ClearType::CurrentLine)
+        )?;
+        write!(
+            w,
+            "{}{}{}",
+            Attribute::Reverse,
+            truncate(&self.status_line(), width as usize),
+            Attribute::Reset
+        )?;
+        w.flush()
+    }
+}
+
+// escape_end returns the length of the escape sequence at the start of a text,
+// `\x1b[` followed by parameters and a final character between `@` and `~`.
+fn escape_end(text: &str) -> Option<usize> {
+    let rest = text.strip_prefix("\x1b[")?;
+    rest.find(|c: char| ('@'..='~').contains(&c))
+        .map(|end| end + 3)
+}
+
+fn strip_ansi(line: &str) -> String {
+    let mut plain = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        let len = match escape_end(rest) {
+            Some(len) => len,
+            None => {
+                plain.push(c);
+                c.len_utf8()
+            }
+        };
+        rest = &rest[len..];
+    }
+    plain
+}
+
+// truncate keeps the characters of a line that fit in the width of the terminal
+// along with all of its escape sequences, which take no room.
+fn truncate(line: &str, width: usize) -> String {
+    let mut kept = String::with_capacity(line.len());
+    let mut visible = 0;
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        let len = match escape_end(rest) {
+            Some(len) => len,
+            None if visible < width => {
+                visible += 1;
+                c.len_utf8()
+            }
+            None => {
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+        };
+        kept.push_str(&rest[..len]);
+        rest = &rest[len..];
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{strip_ansi, truncate, PagerState};
+    use crate::keys::Key;
+
+    fn pager(lines: usize, height: usize) -> PagerState {
+        let text: Vec<String> = (0..lines).map(|i| format!("line {}", i)).collect();
+        let mut pager = PagerState::new(&text.join("\n"));
+        pager.height = height;
+        pager
+    }
+
+    #[test]
+    fn test_scroll() {
+        let mut pager = pager(25, 10);
+        assert!(pager.on_key(Key::Char(' ')));
+        assert_eq!(pager.top, 10);
+        pager.on_key(Key::Char(' '));
+        assert_eq!(pager.top, 15);
+        pager.on_key(Key::Up);
+        assert_eq!(pager.top, 14);
+        pager.on_key(Key::Char('g'));
+        pager.on_key(Key::Char('b'));
+        assert_eq!(pager.top, 0);
+        pager.on_key(Key::End);
+        assert_eq!(
+            pager.status_line(),
+            "lines 16-25/25 (q to quit, / to search)"
+        );
+        assert!(!pager.on_key(Key::Char('q')));
+    }
+
+    #[test]
+    fn test_search() {
+        let mut pager = pager(40, 10);
+        for key in "/E 1\n".chars() {
+            pager.on_key(Key::Char(key));
+        }
+        assert_eq!((pager.matched, pager.top), (Some(1), 1));
+        pager.on_key(Key::Char('n'));
+        assert_eq!((pager.matched, pager.top), (Some(10), 10));
+        pager.on_key(Key::Char('n'));
+        pager.on_key(Key::Char('n'));
+        assert_eq!((pager.matched, pager.top), (Some(12), 12));
+        pager.on_key(Key::Char('N'));
+        assert_eq!(pager.matched, Some(11));
+
+        // a new search starts from the top line, the last matches are shown on the last page.
+        pager.on_key(Key::Char('G'));
+        for key in "/e 3\n".chars() {
+            pager.on_key(Key::Char(key));
+        }
+        assert_eq!((pager.matched, pager.top), (Some(30), 30));
+        for _ in 0..9 {
+            pager.on_key(Key::Char('n'));
+        }
+        assert_eq!((pager.matched, pager.top), (Some(39), 30));
+        pager.on_key(Key::Char('n'));
+        assert_eq!(pager.status_line(), "no more matches");
+        pager.on_key(Key::Char('N'));
+        assert_eq!(pager.matched, Some(38));
+
+        for key in "/nope\n".chars() {
+            pager.on_key(Key::Char(key));
+        }
+        assert_eq!(pager.status_line(), "pattern not found: nope");
+        assert_eq!(pager.top, 30);
+    }
+
+    #[test]
+    fn test_ansi() {
+        let line = "\x1b[1m\x1b[38;5;2mns::list\x1b[m listing";
+        assert_eq!(strip_ansi(line), "ns::list listing");
+        assert_eq!(truncate(line, 6), "\x1b[1m\x1b[38;5;2mns::li\x1b[m");
+        assert_eq!(truncate("déjà vu", 4), "déjà");
+    }
+}
@@ -0,0 +1,70 @@
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+// what's done sooner than this isn't worth a spinner, it would only flicker.
+const SHOW_AFTER: Duration = Duration::from_millis(200);
+pub(crate) const TICK: Duration = Duration::from_millis(80);
+
+/// Spinner tells on stderr what sam is waiting for, on a line that's cleared once it's dropped.
+pub(crate) struct Spinner {
+    message: String,
+    started: Instant,
+    frame: usize,
+    shown: bool,
+    enabled: bool,
+}
+
+impl Spinner {
+    pub(crate) fn new(message: impl Into<String>) -> Self {
+        Spinner {
+            message: message.into(),
+            started: Instant::now(),
+            frame: 0,
+            shown: false,
+            enabled: io::stderr().is_terminal(),
+        }
+    }
+
+    /// tick draws the next frame of the spinner.
+    pub(crate) fn tick(&mut self) {
+        if !self.enabled || self.started.elapsed() < SHOW_AFTER {
+            return;
+        }
+        let mut stderr = io::stderr();
+        let _ = write!(stderr, "\r\x1b[2K{}", self.next_line());
+        let _ = stderr.flush();
+        self.shown = true;
+    }
+
+    fn next_line(&mut self) -> String {
+        let frame = FRAMES[self.frame % FRAMES.len()];
+        self.frame += 1;
+        format!("{} {}", frame, self.message)
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        if self.shown {
+            let mut stderr = io::stderr();
+            let _ = write!(stderr, "\r\x1b[2K");
+            let _ = stderr.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Spinner, FRAMES};
+
+    #[test]
+    fn test_next_line() {
+        let mut spinner = Spinner::new("running the command of var pod");
+        assert_eq!(spinner.next_line(), "⠋ running the command of var pod");
+        for _ in 1..FRAMES.len() {
+            spinner.next_line();
+        }
+        assert_eq!(spinner.next_line(), "⠋ running the command of var pod");
+    }
+}
@@ -1,48 +1,686 @@
 use sam_core::algorithms::resolver::ErrorsResolver;
 use sam_core::algorithms::resolver::Resolver;
 use sam_core::algorithms::resolver::ResolverContext;
+use sam_core::algorithms::resolver::SecretsBackend;
 
-use sam_core::entities::aliases::AliasAndDependencies;
+use sam_core::entities::aliases::{mask, AliasAndDependencies};
 use sam_core::entities::choices::Choice;
 use sam_core::entities::commands::Command;
-use sam_core::entities::vars::Var;
-use sam_readers::read_choices;
-use sam_terminals::processes::ShellCommand;
-use sam_utils::fsutils::ErrorsFS;
-use std::collections::{HashMap, HashSet};
+use sam_core::entities::dependencies::substitute_choices_partial;
+use sam_core::entities::filters;
+use sam_core::entities::identifiers::Identifier;
+use sam_core::entities::vars::{ErrorsInput, Var};
+use sam_readers::{read_choices_page, read_paths, read_structured_choices};
+use sam_terminals::http;
+use sam_terminals::input::{terminal, user_input};
+use sam_terminals::interrupts::Interrupts;
+use sam_terminals::plugins::{self, PluginRequest};
+use sam_terminals::processes::{
+    editor, editor_command, output_with_timeout, LineStream, ShellCommand,
+};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use log::debug;
 
 use thiserror::Error;
 
-use sam_persistence::VarsCache;
+use sam_persistence::{CacheKey, VarsCache};
+
+use crate::external_picker::{self, Picker};
+use crate::modal_view::{ModalView, OptionToggle, Value};
+use crate::spinner::{self, Spinner};
+
+// the number of choices of a from_command var that are read at once, the
+// following ones are read when the user asks for more.
+const CHOICES_PAGE_SIZE: usize = 1000;
+const LOAD_MORE: &str = "load more choices";
+// the time waited before the command of a var runs again, doubled at each retry.
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+// the time the command of a var is given to finish before its choices are shown as they come.
+const STREAM_AFTER: Duration = Duration::from_millis(300);
 
-use crate::modal_view::{ModalView, Value};
+// the time the preview_command of a var is given to print the preview of a choice.
+const PREVIEW_TIMEOUT: Duration = Duration::from_secs(10);
+
+// the output of the command of a var and whether it succeeded, None when it timed out.
+type CommandOutput = io::Result<Option<(Vec<u8>, bool)>>;
 
 pub struct UserInterfaceV2 {
     env_variables: HashMap<String, String>,
     cache: Box<dyn VarsCache>,
+    picker: Picker,
+    last_choices: HashMap<Identifier, Vec<Choice>>,
+    // the lines captured from the past runs of each alias, for `from_history` vars.
+    history_outputs: HashMap<Identifier, Vec<Choice>>,
+    // outputs of the commands that already ran during this invocation.
+    outputs: RefCell<HashMap<CacheKey, Vec<u8>>>,
+    // the commands running in the background, whose vars are asked for later.
+    prefetched: RefCell<HashMap<CacheKey, Running>>,
+    // for each var, the outputs that have choices left to read, along with the
+    // offset of the next page.
+    more_choices: RefCell<HashMap<Identifier, Vec<(CacheKey, usize)>>>,
+    // reads the secrets of the `from_secret` vars, they are never cached.
+    secrets: Option<Box<dyn SecretsBackend>>,
+    previews: Previews,
+    // fail instead of asking the user for anything.
+    non_interactive: bool,
 }
 
-impl<'a> UserInterfaceV2 {
+impl UserInterfaceV2 {
     pub fn new(variables: HashMap<String, String>, cache: Box<dyn VarsCache>) -> UserInterfaceV2 {
         UserInterfaceV2 {
+            previews: Previews::new(variables.clone()),
             env_variables: variables,
             cache,
+            picker: Picker::default(),
+            last_choices: HashMap::new(),
+            history_outputs: HashMap::new(),
+            outputs: RefCell::new(HashMap::new()),
+            prefetched: RefCell::new(HashMap::new()),
+            more_choices: RefCell::new(HashMap::new()),
+            secrets: None,
+            non_interactive: false,
+        }
+    }
+
+    pub fn with_secrets(mut self, secrets: Box<dyn SecretsBackend>) -> UserInterfaceV2 {
+        self.secrets = Some(secrets);
+        self
+    }
+
+    /// with_last_choices lists the choices used last time for each var first, so they can be selected
+    /// with just Enter.
+    pub fn with_last_choices(
+        mut self,
+        last_choices: HashMap<Identifier, Vec<Choice>>,
+    ) -> UserInterfaceV2 {
+        self.last_choices = last_choices;
+        self
+    }
+
+    /// with_history_outputs provides the lines captured from the past runs of the aliases.
+    pub fn with_history_outputs(
+        mut self,
+        history_outputs: HashMap<Identifier, Vec<Choice>>,
+    ) -> UserInterfaceV2 {
+        self.history_outputs = history_outputs;
+        self
+    }
+
+    /// next_choices reads the next page of the outputs that have choices left.
+    fn next_choices(
+        &self,
+        var: &Var,
+        more_choices: &mut Vec<(CacheKey, usize)>,
+    ) -> Result<Vec<Choice>, ErrorsResolver> {
+        let outputs = self.outputs.borrow();
+        let mut choices = vec![];
+        for (key, offset) in std::mem::take(more_choices) {
+            let output = outputs.get(&key).map(Vec::as_slice).unwrap_or_default();
+            let page = read_choices_page(output, offset, CHOICES_PAGE_SIZE, var.choice_format())
+                .map_err(|e| ErrorsResolver::DynamicResolveFailure(var.name(), e.into()))?;
+            choices.extend(page.choices);
+            if page.has_more {
+                more_choices.push((key, offset + CHOICES_PAGE_SIZE));
+            }
+        }
+        Ok(choices)
+    }
+
+    /// output returns the output of a `from_command` or a `from_url` var, from this invocation
+    /// or from the cache when it's there. Otherwise `run` produces it and tells whether it
+    /// succeeded, it's then stored in the cache if it's cacheable.
+    fn output(
+        &self,
+        var: &Var,
+        cache_key: &CacheKey,
+        ctx: &ResolverContext,
+        run: impl FnOnce() -> Result<(Vec<u8>, bool), ErrorsResolver>,
+    ) -> Result<Vec<u8>, ErrorsResolver> {
+        let shared = self.outputs.borrow().get(cache_key).cloned();
+        let from_cache = |out: Vec<u8>| {
+            debug!(
+                "[SAM][ var = '{}' ] command='{}' from_cache=true",
+                var.name(),
+                mask(&cache_key.command, sensitive_choices(ctx)),
+            );
+            out
+        };
+        let run_and_cache = || -> Result<Vec<u8>, ErrorsResolver> {
+            let (output, succeeded) = run()?;
+            if succeeded {
+                self.cache_output(var, cache_key, ctx, &output)?;
+            }
+            Ok(output)
+        };
+        if let Some(out) = shared {
+            Ok(from_cache(out))
+        } else if !is_cacheable(var, cache_key, ctx) {
+            run_and_cache()
+        } else if let Ok(Some(out)) = self.cache.get(cache_key) {
+            Ok(from_cache(out.into_bytes()))
+        } else {
+            // another sam process might be running the same command, once it is
+            // done its output is read from the cache instead of running it again.
+            let _lock = self
+                .cache
+                .lock(cache_key)
+                .map_err(|e| ErrorsResolver::DynamicResolveFailure(var.name(), Box::new(e)))?;
+            if let Ok(Some(out)) = self.cache.get(cache_key) {
+                Ok(from_cache(out.into_bytes()))
+            } else {
+                run_and_cache()
+            }
+        }
+    }
+
+    /// has_output tells whether the output of a command is already there, or on its way.
+    fn has_output(&self, var: &Var, cache_key: &CacheKey, ctx: &ResolverContext) -> bool {
+        self.outputs.borrow().contains_key(cache_key)
+            || self.prefetched.borrow().contains_key(cache_key)
+            || (is_cacheable(var, cache_key, ctx)
+                && matches!(self.cache.get(cache_key), Ok(Some(_))))
+    }
+
+    fn cache_output(
+        &self,
+        var: &Var,
+        cache_key: &CacheKey,
+        ctx: &ResolverContext,
+        output: &[u8],
+    ) -> Result<(), ErrorsResolver> {
+        if !is_cacheable(var, cache_key, ctx) {
+            return Ok(());
+        }
+        self.cache
+            .put(
+                &var.name().to_string(),
+                cache_key,
+                &String::from_utf8_lossy(output),
+                var.cache_ttl(),
+            )
+            .map_err(|e| ErrorsResolver::DynamicResolveFailure(var.name(), Box::new(e)))
+    }
+
+    /// choices splits the output of a `from_command` or a `from_url` var into choices,
+    /// the first page of them when they aren't parsed as json or yaml.
+    fn choices(
+        &self,
+        var: &Var,
+        cache_key: CacheKey,
+        output: Vec<u8>,
+    ) -> Result<Vec<Choice>, ErrorsResolver> {
+        if let Some(structured) = var.structured_choices() {
+            let choices = read_structured_choices(output.as_slice(), &structured)
+                .map_err(|e| ErrorsResolver::DynamicResolveFailure(var.name(), e.into()))?;
+            self.outputs.borrow_mut().insert(cache_key, output);
+            return Ok(choices);
         }
+        let page = read_choices_page(output.as_slice(), 0, CHOICES_PAGE_SIZE, var.choice_format())
+            .map_err(|e| ErrorsResolver::DynamicResolveFailure(var.name(), e.into()))?;
+        self.outputs.borrow_mut().insert(cache_key.clone(), output);
+        let mut choices = page.choices;
+        let mut more_choices = vec![];
+        if page.has_more {
+            more_choices.push((cache_key, CHOICES_PAGE_SIZE));
+        }
+        // the filter of the var applies to all the choices, the next pages are read at once.
+        while var.choice_filter().is_some() && !more_choices.is_empty() {
+            choices.extend(self.next_choices(var, &mut more_choices)?);
+        }
+        if !more_choices.is_empty() {
+            self.more_choices
+                .borrow_mut()
+                .entry(var.name())
+                .or_default()
+                .extend(more_choices);
+        }
+        Ok(choices)
+    }
+
+    /// run_dynamic runs the command of a `from_command` var, or waits for it when it was
+    /// prefetched, and tells whether it succeeded. Ctrl-C cancels it.
+    fn run_dynamic(
+        &self,
+        var: &Var,
+        to_run: std::process::Command,
+        cache_key: &CacheKey,
+        ctx: &ResolverContext,
+    ) -> Result<(Vec<u8>, bool), ErrorsResolver> {
+        let command = mask(&cache_key.command, sensitive_choices(ctx));
+        let prefetched = self.prefetched.borrow_mut().remove(cache_key);
+        let running = prefetched.unwrap_or_else(|| Running::spawn(var, to_run, command.clone()));
+        let output = running
+            .wait(&var.name())?
+            .map_err(|e| ErrorsResolver::DynamicResolveFailure(var.name(), e.into()))?;
+        output.ok_or_else(|| {
+            let timeout = var.timeout().unwrap_or_default().as_secs();
+            ErrorsResolver::DynamicResolveTimeout(var.name(), command, timeout)
+        })
+    }
+
+    /// dynamic_command makes the process running the command of a `from_command` var with the
+    /// environment variables and the env of the alias, its output is shared and cached under the key.
+    fn dynamic_command(
+        &self,
+        var: &Var,
+        cmd: String,
+        ctx: &ResolverContext,
+    ) -> Result<(std::process::Command, CacheKey), ErrorsResolver> {
+        let alias_env = alias_env(ctx);
+        let mut env_variables = self.env_variables.clone();
+        env_variables.extend(alias_env.clone());
+        let sh_cmd: ShellCommand<String> = cmd.into();
+        let cmd_key = sh_cmd
+            .replace_env_vars_in_command(&env_variables)
+            .map_err(|e| ErrorsResolver::DynamicResolveFailure(var.name(), Box::new(e)))?;
+        let cache_key = CacheKey::new(cmd_key.value(), &alias_env);
+        let mut to_run = ShellCommand::make_command(sh_cmd);
+        // what's piped to sam is meant for the alias, not for the commands of its vars.
+        to_run.envs(&env_variables).stdin(Stdio::null());
+        Ok((to_run, cache_key))
+    }
+
+    /// stream_choices runs the command of a `from_command` var and shows the choices it prints
+    /// as they come. A command that's done within STREAM_AFTER isn't streamed, its output is
+    /// stored for resolve_dynamic to read it and None is returned.
+    fn stream_choices(
+        &self,
+        var: &Var,
+        mut to_run: std::process::Command,
+        cache_key: CacheKey,
+        ctx: &ResolverContext,
+    ) -> Result<Option<Vec<Choice>>, ErrorsResolver> {
+        let command = mask(&cache_key.command, sensitive_choices(ctx));
+        let failure = |e: io::Error| ErrorsResolver::DynamicResolveFailure(var.name(), e.into());
+        let started = Instant::now();
+        let interrupts = Interrupts::catch();
+        let mut stream = LineStream::spawn(&mut to_run).map_err(failure)?;
+        let mut lines = vec![];
+        let deadline = started + STREAM_AFTER;
+        while let Some(batch) =
+            stream.next_lines(deadline.saturating_duration_since(Instant::now()))
+        {
+            lines.extend(batch);
+            if Instant::now() >= deadline {
+                break;
+            }
+        }
+        if interrupts.interrupted() {
+            stream.kill().map_err(failure)?;
+            return Err(ErrorsResolver::Cancelled(var.name()));
+        }
+        drop(interrupts);
+        if stream.is_done() {
+            self.finish_stream(var, stream, &lines, cache_key, ctx, started)?;
+            return Ok(None);
+        }
+
+        let last_used = self.last_choices.get(&var.name());
+        let elements = |lines: &[String]| -> Vec<ChoiceElement<'_>> {
+            let output = lines.join("\n");
+            read_choices_page(output.as_bytes(), 0, lines.len(), var.choice_format())
+                .map(|page| page.choices)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|choice| {
+                    let used = last_used.is_some_and(|used| used.contains(&choice));
+                    ChoiceElement::from(var.name(), choice, ctx)
+                        .with_last_used(used)
+                        .with_preview(var, &self.previews)
+                })
+                .collect()
+        };
+        let received = RefCell::new(lines);
+        let initial = elements(&received.borrow());
+        let response = ModalView::new(initial, vec![], true)
+            .with_source(|| {
+                let batch = stream.next_lines(Duration::ZERO)?;
+                let values = elements(&batch);
+                received.borrow_mut().extend(batch);
+                Some(values)
+            })
+            .run();
+        let mut lines = received.into_inner();
+        while let Some(batch) = stream.next_lines(Duration::ZERO) {
+            if batch.is_empty() {
+                break;
+            }
+            lines.extend(batch);
+        }
+
+        let done = stream.is_done();
+        let stderr = if done {
+            self.finish_stream(var, stream, &lines, cache_key, ctx, started)?
+        } else {
+            // the choices listed so far aren't all of them, they aren't kept.
+            debug!(
+                "[SAM][ var = '{}' ] command='{}' stopped after {} lines",
+                var.name(),
+                command,
+                lines.len()
+            );
+            stream.kill().map_err(failure)?;
+            String::new()
+        };
+        match response {
+            Some(response) if !response.marked_values.is_empty() => {
+                Ok(Some(response.values().map(|e| e.choice).collect()))
+            }
+            _ if done && lines.iter().all(|l| l.is_empty()) => Err(
+                ErrorsResolver::DynamicResolveEmpty(var.name(), command, stderr),
+            ),
+            _ => Err(ErrorsResolver::NoChoiceWasSelected(var.name())),
+        }
+    }
+
+    // finish_stream waits for the command of a streamed var, stores its output like run_dynamic
+    // would have and returns what it printed on stderr.
+    fn finish_stream(
+        &self,
+        var: &Var,
+        stream: LineStream,
+        lines: &[String],
+        cache_key: CacheKey,
+        ctx: &ResolverContext,
+        started: Instant,
+    ) -> Result<String, ErrorsResolver> {
+        let (status, stderr) = stream
+            .wait()
+            .map_err(|e| ErrorsResolver::DynamicResolveFailure(var.name(), e.into()))?;
+        debug!(
+            "[SAM][ var = '{}' ] command='{}' duration={}ms exit_code={} from_cache=false",
+            var.name(),
+            mask(&cache_key.command, sensitive_choices(ctx)),
+            started.elapsed().as_millis(),
+            status
+                .code()
+                .map_or_else(|| String::from("none"), |code| code.to_string()),
+        );
+        let mut output = lines.join("\n").into_bytes();
+        if !output.is_empty() {
+            output.push(b'\n');
+        }
+        if status.success() && stderr.is_empty() {
+            self.cache_output(var, &cache_key, ctx, &output)?;
+        }
+        self.outputs.borrow_mut().insert(cache_key, output);
+        Ok(String::from_utf8_lossy(&stderr).to_string())
+    }
+
+    pub fn with_picker(mut self, picker: Picker) -> UserInterfaceV2 {
+        self.picker = picker;
+        self
+    }
+
+    /// with_non_interactive makes the vars the user would have to pick a choice for,
+    /// or type an input for, fail with ErrorsResolver::NonInteractive instead.
+    pub fn with_non_interactive(mut self, non_interactive: bool) -> UserInterfaceV2 {
+        self.non_interactive = non_interactive;
+        self
+    }
+
+    // resolve_in_editor opens $EDITOR on a temporary file prefilled with the default of
+    // the var, its content once saved is the choice, quoted so that it stays a single
+    // word of the command whatever lines, spaces or quotes it holds.
+    fn resolve_in_editor(&self, var: &Var) -> Result<Choice, ErrorsResolver> {
+        let no_input = |err: String| ErrorsResolver::NoInputWasProvided(var.name(), err);
+        let file = EditorFile::create(var).map_err(|err| no_input(err.to_string()))?;
+        let rules = var.input_rules();
+        let mut content = var
+            .default_choice()
+            .map(|c| c.value().to_string())
+            .unwrap_or_default();
+        loop {
+            file.write(&content)
+                .map_err(|err| no_input(err.to_string()))?;
+            let editor = editor();
+            let mut command = editor_command(&editor, &[file.path.display().to_string()]);
+            // the editor needs the terminal even when the input of sam is piped.
+            if let Some(tty) = terminal() {
+                command.stdin(tty);
+            }
+            let status = command.status();
+            let written = fs::read_to_string(&file.path);
+            match status {
+                Ok(status) if status.success() => {}
+                Ok(status) => return Err(no_input(format!("{} exited with {}", editor, status))),
+                Err(err) => return Err(no_input(format!("{} can't be run: {}", editor, err))),
+            }
+            content = written
+                .map_err(|err| no_input(err.to_string()))?
+                .trim_end_matches(['\n', '\r'])
+                .to_string();
+            match rules.validate(&content) {
+                Ok(()) => break,
+                Err(err @ ErrorsInput::InvalidPattern(_)) => return Err(no_input(err.to_string())),
+                Err(err) => {
+                    println!(
+                        "{}{}, press enter to edit it again.{}",
+                        crossterm::style::SetForegroundColor(crossterm::style::Color::DarkRed),
+                        err,
+                        crossterm::style::Attribute::Reset
+                    );
+                    let mut buffer = String::new();
+                    if let Ok(0) | Err(_) = BufReader::new(user_input()).read_line(&mut buffer) {
+                        return Err(no_input(String::from("the input was closed")));
+                    }
+                }
+            }
+        }
+        let quoted = filters::apply(&content, "quote").expect("quote is a known filter");
+        Ok(Choice::new(quoted, None))
     }
 
     pub fn choose<T: Value>(
         &self,
         choices: Vec<T>,
-        _prompt: &str,
+        prompt: &str,
+        allow_multiple: bool,
+    ) -> Result<HashSet<T>, ErrorsUIV2> {
+        self.choose_with_options(choices, prompt, allow_multiple, vec![])
+    }
+
+    /// choose_with_options lets the user toggle options in the options mode of sam's chooser,
+    /// external pickers don't support them.
+    fn choose_with_options<T: Value>(
+        &self,
+        choices: Vec<T>,
+        prompt: &str,
         allow_multiple: bool,
+        options: Vec<OptionToggle>,
     ) -> Result<HashSet<T>, ErrorsUIV2> {
-        let controller = ModalView::new(choices, vec![], allow_multiple);
+        if let Some(program) = self.picker.program() {
+            return external_picker::choose(program, choices, prompt, allow_multiple);
+        }
+        let controller = ModalView::new(choices, options, allow_multiple);
         let output = controller.run();
         output
             .map(|e| e.marked_values)
             .ok_or(ErrorsUIV2::EmptySelection)
     }
 }
+
+// EditorFile is the temporary file the input of a var is written in with $EDITOR. It's
+// created anew, only readable by the user on unix, and removed once dropped.
+struct EditorFile {
+    path: PathBuf,
+}
+
+impl EditorFile {
+    fn create(var: &Var) -> io::Result<Self> {
+        loop {
+            let nanos = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.subsec_nanos())
+                .unwrap_or_default();
+            let path = std::env::temp_dir().join(format!(
+                "sam-input-{}-{}-{:x}.txt",
+                var.name().name(),
+                std::process::id(),
+                nanos
+            ));
+            let mut options = fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            match options.open(&path) {
+                Ok(_) => return Ok(EditorFile { path }),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn write(&self, content: &str) -> io::Result<()> {
+        fs::OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?
+            .write_all(content.as_bytes())
+    }
+}
+
+impl Drop for EditorFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Running is the command of a `from_command` var running in the background.
+struct Running {
+    handle: JoinHandle<CommandOutput>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Running {
+    fn spawn(var: &Var, to_run: std::process::Command, command: String) -> Self {
+        let var = var.clone();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let cancel = Arc::clone(&cancelled);
+        let handle = thread::spawn(move || run_command(&var, to_run, &command, &cancel));
+        Running { handle, cancelled }
+    }
+
+    // wait waits for the command to be done while a spinner tells which var it's for,
+    // Ctrl-C kills it.
+    fn wait(self, var_name: &Identifier) -> Result<CommandOutput, ErrorsResolver> {
+        let interrupts = Interrupts::catch();
+        let mut spinner = Spinner::new(format!(
+            "running the command of var {}, ctrl-c to cancel",
+            var_name
+        ));
+        while !self.handle.is_finished() {
+            if interrupts.interrupted() {
+                self.cancelled.store(true, Ordering::SeqCst);
+                // the command is killed before the user is asked for anything else.
+                let _ = self.handle.join();
+                return Err(ErrorsResolver::Cancelled(var_name.clone()));
+            }
+            spinner.tick();
+            thread::sleep(spinner::TICK);
+        }
+        Ok(self
+            .handle
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("the command panicked"))))
+    }
+}
+
+// run_command runs the command of a `from_command` var, again when it fails or times out
+// as many times as the var allows. It returns the output along with whether the command
+// succeeded, None when it timed out or was cancelled.
+fn run_command(
+    var: &Var,
+    mut to_run: std::process::Command,
+    command: &str,
+    cancelled: &AtomicBool,
+) -> CommandOutput {
+    let mut backoff = RETRY_BACKOFF;
+    for attempt in 0..=var.retries() {
+        if cancelled.load(Ordering::SeqCst) {
+            break;
+        }
+        if attempt > 0 {
+            debug!(
+                "[SAM][ var = '{}' ] retrying command='{}' in {}ms",
+                var.name(),
+                command,
+                backoff.as_millis()
+            );
+            thread::sleep(backoff);
+            backoff *= 2;
+        }
+        let started = Instant::now();
+        let output = output_with_timeout(&mut to_run, var.timeout(), cancelled)?;
+        let code = output.as_ref().and_then(|o| o.status.code());
+        debug!(
+            "[SAM][ var = '{}' ] command='{}' duration={}ms exit_code={} from_cache=false",
+            var.name(),
+            command,
+            started.elapsed().as_millis(),
+            code.map_or_else(|| String::from("none"), |code| code.to_string()),
+        );
+        match output {
+            Some(output) if code == Some(0) || attempt == var.retries() => {
+                let succeeded = code == Some(0) && output.stderr.is_empty();
+                return Ok(Some((output.stdout, succeeded)));
+            }
+            _ => {}
+        }
+    }
+    Ok(None)
+}
+
+// is_cacheable tells whether the output of a `from_command` var can be cached, it never
+// is when the cache key holds a sensitive choice, in the command or in the alias env.
+fn is_cacheable(var: &Var, cache_key: &CacheKey, ctx: &ResolverContext) -> bool {
+    let sensitive = sensitive_choices(ctx);
+    let holds_sensitive = |text: &str| mask(text, sensitive.iter().copied()) != text;
+    var.is_cached()
+        && !holds_sensitive(&cache_key.command)
+        && !cache_key
+            .env
+            .iter()
+            .any(|(_, value)| holds_sensitive(value))
+}
+
+// sensitive_choices returns the choices made so far for sensitive vars.
+fn sensitive_choices(ctx: &ResolverContext) -> Vec<&Choice> {
+    ctx.sensitive
+        .iter()
+        .filter_map(|id| ctx.choices.get(id))
+        .flatten()
+        .collect()
+}
+
+// alias_env returns the environment variables declared by the alias being resolved
+// once the choices made so far are substituted. from_command vars run with them,
+// the ones that still depend on unresolved vars are left out.
+fn alias_env(ctx: &ResolverContext) -> HashMap<String, String> {
+    let first_choices: HashMap<Identifier, Choice> = ctx
+        .choices
+        .iter()
+        .filter_map(|(id, c)| c.first().map(|c| (id.clone(), c.clone())))
+        .collect();
+    ctx.alias
+        .with_partial_choices(&first_choices)
+        .env()
+        .iter()
+        .filter(|(_, value)| !value.contains("{{"))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
 #[derive(Debug, Error)]
 pub enum ErrorsUIV2 {
     #[error("no selection was provided")]
@@ -51,31 +689,74 @@ pub enum ErrorsUIV2 {
     EmptySelection,
     #[error("initialisation of UI failed")]
     InitError(Box<dyn std::error::Error>),
-    #[error("an unexpected error happend while filling the preview window {0}")]
-    IOError(#[from] std::io::Error),
-    #[error("an unexpected error happend while initialising the preview window {0}")]
-    FSError(#[from] ErrorsFS),
+    #[error("could not run the external picker\n-> {0}")]
+    ExternalPicker(#[from] std::io::Error),
 }
 
-impl<'a> Resolver for UserInterfaceV2 {
+impl Resolver for UserInterfaceV2 {
     fn resolve_input(
         &self,
         var: &Var,
         prompt: &str,
         _ctx: &ResolverContext,
     ) -> Result<Choice, ErrorsResolver> {
-        let mut buffer = String::new();
-        println!(
-            "Please provide an input for variable {}.\n{} :",
-            &var.name(),
-            prompt
-        );
-        match std::io::stdin().read_line(&mut buffer) {
-            Ok(_) => Ok(Choice::new(buffer.replace('\n', ""), None)),
-            Err(err) => Err(ErrorsResolver::NoInputWasProvided(
-                var.name(),
-                err.to_string(),
-            )),
+        if self.non_interactive {
+            return Err(ErrorsResolver::NonInteractive(var.name()));
+        }
+        if var.uses_editor() {
+            return self.resolve_in_editor(var);
+        }
+        let default = var.default_choice();
+        let rules = var.input_rules();
+        // the input is asked for again until it follows the rules of the var.
+        loop {
+            match &default {
+                Some(default) => println!(
+                    "Please provide an input for variable {}, {} when empty.\n{} :",
+                    &var.name(),
+                    default.value(),
+                    prompt
+                ),
+                None => println!(
+                    "Please provide an input for variable {}.\n{} :",
+                    &var.name(),
+                    prompt
+                ),
+            }
+            let mut buffer = String::new();
+            let choice = match BufReader::new(user_input()).read_line(&mut buffer) {
+                Ok(0) => {
+                    return Err(ErrorsResolver::NoInputWasProvided(
+                        var.name(),
+                        String::from("the input was closed"),
+                    ))
+                }
+                Ok(_) => match (buffer.replace('\n', ""), &default) {
+                    (input, Some(default)) if input.is_empty() => default.clone(),
+                    (input, _) => Choice::new(input, None),
+                },
+                Err(err) => {
+                    return Err(ErrorsResolver::NoInputWasProvided(
+                        var.name(),
+                        err.to_string(),
+                    ))
+                }
+            };
+            match rules.validate(choice.value()) {
+                Ok(()) => return Ok(choice),
+                Err(err @ ErrorsInput::InvalidPattern(_)) => {
+                    return Err(ErrorsResolver::NoInputWasProvided(
+                        var.name(),
+                        err.to_string(),
+                    ))
+                }
+                Err(err) => println!(
+                    "{}{}, try again.{}",
+                    crossterm::style::SetForegroundColor(crossterm::style::Color::DarkRed),
+                    err,
+                    crossterm::style::Attribute::Reset
+                ),
+            }
         }
     }
 
@@ -83,79 +764,261 @@ impl<'a> Resolver for UserInterfaceV2 {
         &self,
         var: &Var,
         cmd: String,
-        _ctx: &ResolverContext,
+        ctx: &ResolverContext,
     ) -> Result<Vec<Choice>, ErrorsResolver> {
-        let sh_cmd: ShellCommand<String> = cmd.into();
-        let cmd_key = sh_cmd
-            .replace_env_vars_in_command(&self.env_variables)
-            .map_err(|e| ErrorsResolver::DynamicResolveFailure(var.name(), Box::new(e)))?;
-        let cache_entry = self.cache.get(cmd_key.value());
-        let (stdout_output, _) = if let Ok(Some(out)) = cache_entry {
-            (out.as_bytes().to_owned(), vec![])
-        } else {
-            let mut to_run = ShellCommand::make_command(sh_cmd);
-            to_run.envs(&self.env_variables);
-            let output = to_run
-                .output()
-                .map_err(|e| ErrorsResolver::DynamicResolveFailure(var.name(), e.into()))?;
-            if output.status.code() == Some(0) && output.stderr.is_empty() {
-                self.cache
-                    .put(
-                        &var.name().to_string(),
-                        cmd_key.value(),
-                        &String::from_utf8_lossy(output.stdout.as_slice()).to_owned(),
-                    )
-                    .map_err(|e| ErrorsResolver::DynamicResolveFailure(var.name(), Box::new(e)))?;
+        let (to_run, cache_key) = self.dynamic_command(var, cmd, ctx)?;
+        let output = self.output(var, &cache_key, ctx, || {
+            self.run_dynamic(var, to_run, &cache_key, ctx)
+        })?;
+        self.choices(var, cache_key, output)
+    }
+
+    fn prefetch_dynamic(&self, commands: &[(&Var, String)], ctx: &ResolverContext) {
+        for (var, cmd) in commands {
+            let Ok((to_run, cache_key)) = self.dynamic_command(var, cmd.clone(), ctx) else {
+                continue;
+            };
+            if self.has_output(var, &cache_key, ctx) {
+                continue;
             }
-            (output.stdout, output.stderr)
-        };
+            let command = mask(&cache_key.command, sensitive_choices(ctx));
+            debug!(
+                "[SAM][ var = '{}' ] prefetching command='{}'",
+                var.name(),
+                command
+            );
+            let prefetched = Running::spawn(var, to_run, command);
+            self.prefetched.borrow_mut().insert(cache_key, prefetched);
+        }
+    }
 
-        read_choices(stdout_output.as_slice())
-            .map_err(|e| ErrorsResolver::DynamicResolveFailure(var.name(), e.into()))
+    fn stream_dynamic(
+        &self,
+        var: &Var,
+        cmd: &str,
+        ctx: &ResolverContext,
+    ) -> Option<Result<Vec<Choice>, ErrorsResolver>> {
+        // the choices parsed from json or yaml need the whole output, external pickers
+        // read all the choices at once, the commands with a timeout may run again and
+        // nothing is picked in non interactive mode.
+        if self.non_interactive
+            || self.picker.program().is_some()
+            || var.structured_choices().is_some()
+            || var.timeout().is_some()
+            || var.retries() > 0
+        {
+            return None;
+        }
+        let (to_run, cache_key) = self.dynamic_command(var, cmd.to_string(), ctx).ok()?;
+        if self.has_output(var, &cache_key, ctx) {
+            return None;
+        }
+        self.stream_choices(var, to_run, cache_key, ctx).transpose()
+    }
+
+    fn resolve_url(
+        &self,
+        var: &Var,
+        url: String,
+        ctx: &ResolverContext,
+    ) -> Result<Vec<Choice>, ErrorsResolver> {
+        let headers = http::expand_headers(var.headers(), &self.env_variables)
+            .map_err(|e| ErrorsResolver::DynamicResolveFailure(var.name(), Box::new(e)))?;
+        // the headers hold credentials, they are left out of the cache key.
+        let cache_key = CacheKey::new(url.as_str(), &HashMap::new());
+        let output = self.output(var, &cache_key, ctx, || {
+            let started = Instant::now();
+            let body = http::get(&url, &headers)
+                .map_err(|e| ErrorsResolver::DynamicResolveFailure(var.name(), Box::new(e)))?;
+            debug!(
+                "[SAM][ var = '{}' ] url='{}' duration={}ms from_cache=false",
+                var.name(),
+                mask(&url, sensitive_choices(ctx)),
+                started.elapsed().as_millis(),
+            );
+            Ok((body, true))
+        })?;
+        self.choices(var, cache_key, output)
     }
 
-    fn resolve_static<'b>(
-        &'b self,
+    fn resolve_plugin(
+        &self,
+        var: &Var,
+        plugin: String,
+        ctx: &ResolverContext,
+    ) -> Result<Vec<Choice>, ErrorsResolver> {
+        let request = PluginRequest::new(var, ctx);
+        let started = Instant::now();
+        let choices = plugins::run(&plugin, &request, &self.env_variables)
+            .map_err(|e| ErrorsResolver::PluginFailure(var.name(), Box::new(e)))?;
+        debug!(
+            "[SAM][ var = '{}' ] plugin='{}' duration={}ms choices={}",
+            var.name(),
+            mask(&plugin, sensitive_choices(ctx)),
+            started.elapsed().as_millis(),
+            choices.len(),
+        );
+        Ok(choices)
+    }
+
+    fn resolve_path(
+        &self,
+        var: &Var,
+        directory: String,
+        _ctx: &ResolverContext,
+    ) -> Result<Vec<Choice>, ErrorsResolver> {
+        let choices = read_paths(&directory, &var.path_rules())
+            .map_err(|e| ErrorsResolver::PathFailure(var.name(), directory.clone(), e.into()))?;
+        if choices.is_empty() {
+            return Err(ErrorsResolver::NoChoiceWasAvailable(var.name()));
+        }
+        Ok(choices)
+    }
+
+    fn map_choice(
+        &self,
+        var: &Var,
+        command: &str,
+        choice: Choice,
+        _ctx: &ResolverContext,
+    ) -> Result<Choice, ErrorsResolver> {
+        let failure = |e: Box<dyn std::error::Error>| {
+            ErrorsResolver::MapFailure(var.name(), choice.value().to_string(), e)
+        };
+        let mut to_run = ShellCommand::make_command(command);
+        to_run
+            .envs(&self.env_variables)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut child = to_run.spawn().map_err(|e| failure(e.into()))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // a command that doesn't need the value may exit without reading it.
+            match writeln!(stdin, "{}", choice.value()) {
+                Err(err) if err.kind() != io::ErrorKind::BrokenPipe => {
+                    return Err(failure(err.into()))
+                }
+                _ => {}
+            }
+        }
+        let output = child.wait_with_output().map_err(|e| failure(e.into()))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            return Err(failure(stderr.into()));
+        }
+        let value = String::from_utf8_lossy(&output.stdout)
+            .trim_end_matches(['\n', '\r'])
+            .to_string();
+        Ok(choice.with_value(value))
+    }
+
+    // ChoiceElement is hashed by its choice only, the previews it shares aren't part of the key.
+    #[allow(clippy::mutable_key_type)]
+    fn resolve_static(
+        &self,
         var: &Var,
         cmd: impl Iterator<Item = Choice>,
         _ctx: &ResolverContext,
     ) -> Result<Vec<Choice>, ErrorsResolver> {
-        let choices: Vec<Choice> = cmd.collect();
+        let mut choices: Vec<Choice> = cmd.collect();
+        let mut more_choices = self
+            .more_choices
+            .borrow_mut()
+            .remove(&var.name())
+            .unwrap_or_default();
 
         if choices.is_empty() {
             return Err(ErrorsResolver::NoChoiceWasAvailable(var.name()));
         }
 
-        if choices.len() == 1 {
+        if choices.len() == 1 && more_choices.is_empty() {
             return Ok(choices);
         }
+        if self.non_interactive {
+            return Err(ErrorsResolver::NonInteractive(var.name()));
+        }
 
-        let choice = {
-            let items: Vec<ChoiceElement<'_>> = choices
+        let last_used = self.last_choices.get(&var.name());
+        let prompt = format!("please make a choices for variable: {}", var.name());
+        loop {
+            let default = var.default_choice();
+            let ordered = default_first(choices.clone(), default.as_ref());
+            let mut items: Vec<ChoiceElement<'_>> = last_used_first(ordered, last_used)
                 .into_iter()
-                .map(|choice| ChoiceElement::from(choice, _ctx))
+                .map(|(choice, last_used)| {
+                    let is_default = !last_used
+                        && default
+                            .as_ref()
+                            .is_some_and(|d| d.value() == choice.value());
+                    ChoiceElement::from(var.name(), choice, _ctx)
+                        .with_last_used(last_used)
+                        .with_default(is_default)
+                        .with_preview(var, &self.previews)
+                })
                 .collect();
-            let prompt = format!("please make a choices for variable: {}", var.name());
-            let choice: Vec<Choice> = self
+            if !more_choices.is_empty() {
+                items.push(ChoiceElement::load_more(var.name(), choices.len(), _ctx));
+            }
+            let chosen = self
                 .choose(items, &prompt, true)
-                .map_err(|_e| ErrorsResolver::NoChoiceWasSelected(var.name()))
-                .map(|chosen| chosen.into_iter().map(|e| e.choice).collect())?;
-            choice
-        };
-        Ok(choice)
+                .map_err(|_e| ErrorsResolver::NoChoiceWasSelected(var.name()))?;
+            if chosen.iter().any(|e| e.load_more) {
+                choices.extend(self.next_choices(var, &mut more_choices)?);
+                continue;
+            }
+            return Ok(chosen.into_iter().map(|e| e.choice).collect());
+        }
     }
 
-    fn select_identifier<'b>(
-        &'b self,
+    fn resolve_secret(
+        &self,
+        var: &Var,
+        reference: String,
+        _ctx: &ResolverContext,
+    ) -> Result<Choice, ErrorsResolver> {
+        let secrets = self
+            .secrets
+            .as_ref()
+            .ok_or_else(|| ErrorsResolver::NoSecretsBackend(var.name()))?;
+        let secret = secrets
+            .read(&reference)
+            .map_err(|e| ErrorsResolver::SecretResolveFailure(var.name(), e))?;
+        Ok(Choice::from_value(secret))
+    }
+
+    fn resolve_history(
+        &self,
+        var: &Var,
+        alias: &Identifier,
+        ctx: &ResolverContext,
+    ) -> Result<Vec<Choice>, ErrorsResolver> {
+        let choices = self
+            .history_outputs
+            .get(alias)
+            .filter(|choices| !choices.is_empty())
+            .ok_or_else(|| ErrorsResolver::NoHistoryForAlias(var.name(), alias.clone()))?;
+        self.resolve_static(var, choices.clone().into_iter(), ctx)
+    }
+
+    fn select_identifier(
+        &self,
         identifiers: &[AliasAndDependencies],
         prompt: &str,
     ) -> Result<AliasAndDependencies, ErrorsResolver> {
+        if self.non_interactive {
+            return Err(ErrorsResolver::NonInteractiveSelection);
+        }
         let items: Vec<AliasElement> = identifiers
             .iter()
             .map(|identifier| AliasElement(identifier.clone()))
             .collect();
+        let tags: BTreeSet<&str> = identifiers
+            .iter()
+            .flat_map(|identifier| identifier.alias.tags())
+            .map(String::as_str)
+            .collect();
         let alias = self
-            .choose(items, prompt, false)
+            .choose_with_options(items, prompt, false, OptionToggle::for_tags(tags))
             .map_err(|e| ErrorsResolver::IdentifierSelectionInvalid(Box::new(e)))?
             .iter()
             .next()
@@ -184,9 +1047,29 @@ impl Value for AliasElement {
         &self.0.full_name
     }
 
+    fn desc(&self) -> Option<&str> {
+        Some(self.0.alias.desc())
+    }
+
+    fn tags(&self) -> &[String] {
+        self.0.alias.tags()
+    }
+
+    fn deprecated(&self) -> bool {
+        self.0.alias.deprecation().is_some()
+    }
+
     fn preview(&self) -> String {
         let mut output = String::new();
 
+        if let Some(hint) = self.0.alias.deprecation() {
+            output.push_str(&format!("DEPRECATED: {}\n\n", hint));
+        }
+
+        if let Some(annotation) = self.0.alias.annotation() {
+            output.push_str(&format!("{}\n\n", annotation.to_uppercase()));
+        }
+
         output.push_str(&format!(
             "Name: {}\n\nDescription:\n{}\n\nAlias:\n\n{}\n",
             self.0.alias.name(),
@@ -194,6 +1077,14 @@ impl Value for AliasElement {
             self.0.alias.command(),
         ));
 
+        if let Some(maintainer) = self.0.alias.maintainer() {
+            output.push_str(&format!("\nMaintained by:\n{}\n", maintainer));
+        }
+
+        if !self.0.alias.tags().is_empty() {
+            output.push_str(&format!("\nTags: {}\n", self.0.alias.tags().join(", ")));
+        }
+
         if !self.0.dependencies.is_empty() {
             output.push_str("\nDependencies:\n");
             for id in &self.0.dependencies {
@@ -205,43 +1096,240 @@ impl Value for AliasElement {
     }
 }
 
+// default_first moves the default choice of the var to the top, the choices used last
+// time still come before it.
+fn default_first(mut choices: Vec<Choice>, default: Option<&Choice>) -> Vec<Choice> {
+    let position = default.and_then(|d| choices.iter().position(|c| c.value() == d.value()));
+    if let Some(position) = position {
+        let choice = choices.remove(position);
+        choices.insert(0, choice);
+    }
+    choices
+}
+
+// last_used_first moves the choices used last time to the top, the order of
+// the other choices is kept. Each choice is returned along with whether it was last used.
+fn last_used_first(choices: Vec<Choice>, last_used: Option<&Vec<Choice>>) -> Vec<(Choice, bool)> {
+    let (first, rest): (Vec<Choice>, Vec<Choice>) = choices
+        .into_iter()
+        .partition(|c| last_used.is_some_and(|l| l.iter().any(|u| u.value() == c.value())));
+    first
+        .into_iter()
+        .map(|c| (c, true))
+        .chain(rest.into_iter().map(|c| (c, false)))
+        .collect()
+}
+
+/// Previews runs the `preview_command` of the vars in the background, for the choices
+/// that get highlighted, and keeps what they print for the rest of the invocation.
+#[derive(Debug)]
+struct Previews {
+    env_variables: HashMap<String, String>,
+    // what each command printed, None while it runs.
+    outputs: Arc<Mutex<HashMap<String, Option<String>>>>,
+}
+
+impl Previews {
+    fn new(env_variables: HashMap<String, String>) -> Self {
+        Previews {
+            env_variables,
+            outputs: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    // output returns what the command printed, None while it runs. It starts running
+    // the first time it's asked for, with the env of the alias.
+    fn output(&self, command: &str, ctx: &ResolverContext) -> Option<String> {
+        let mut outputs = self.outputs.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(output) = outputs.get(command) {
+            return output.clone();
+        }
+        outputs.insert(command.to_string(), None);
+        let mut to_run = ShellCommand::make_command(command.to_string());
+        to_run
+            .envs(&self.env_variables)
+            .envs(alias_env(ctx))
+            .stdin(Stdio::null());
+        let command = command.to_string();
+        let shared = Arc::clone(&self.outputs);
+        thread::spawn(move || {
+            let text = match output_with_timeout(
+                &mut to_run,
+                Some(PREVIEW_TIMEOUT),
+                &AtomicBool::new(false),
+            ) {
+                Ok(Some(output)) => {
+                    let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+                    text.push_str(&String::from_utf8_lossy(&output.stderr));
+                    text
+                }
+                Ok(None) => format!(
+                    "the preview didn't finish within {}s",
+                    PREVIEW_TIMEOUT.as_secs()
+                ),
+                Err(err) => format!("the preview failed: {}", err),
+            };
+            let mut outputs = shared.lock().unwrap_or_else(PoisonError::into_inner);
+            outputs.insert(command, Some(text));
+        });
+        None
+    }
+
+    fn is_running(&self, command: &str) -> bool {
+        let outputs = self.outputs.lock().unwrap_or_else(PoisonError::into_inner);
+        matches!(outputs.get(command), Some(None))
+    }
+}
+
 #[derive(Clone, Debug)]
 struct ChoiceElement<'a> {
     resolver_context: &'a ResolverContext,
+    var: Identifier,
     choice: Choice,
-    text: String,
+    desc: Option<String>,
+    // the entry to select to read more choices.
+    load_more: bool,
+    // the preview_command of the var and what runs it.
+    preview: Option<(&'a str, &'a Previews)>,
 }
 
 impl<'a> ChoiceElement<'a> {
-    pub fn from(choice: Choice, ctx: &'a ResolverContext) -> Self {
-        let text = format!(
-            "{}    {}",
-            choice.value(),
-            choice.desc().unwrap_or_default()
-        );
+    pub fn from(var: Identifier, choice: Choice, ctx: &'a ResolverContext) -> Self {
+        let desc = choice.desc().map(String::from);
         ChoiceElement {
             resolver_context: ctx,
+            var,
             choice,
-            text,
+            desc,
+            load_more: false,
+            preview: None,
+        }
+    }
+
+    fn load_more(var: Identifier, loaded: usize, ctx: &'a ResolverContext) -> Self {
+        ChoiceElement {
+            resolver_context: ctx,
+            var,
+            choice: Choice::from_value(LOAD_MORE),
+            desc: Some(format!("{} choices loaded so far", loaded)),
+            load_more: true,
+            preview: None,
+        }
+    }
+
+    fn with_preview(mut self, var: &'a Var, previews: &'a Previews) -> Self {
+        self.preview = var.preview_command().map(|command| (command, previews));
+        self
+    }
+
+    // preview_command is the preview_command of the var once the choices made so far and
+    // this choice, as `{{ value }}` too, are substituted.
+    fn preview_command(&self) -> Option<String> {
+        let (command, _) = self.preview?;
+        let mut choices: HashMap<Identifier, Choice> = self
+            .resolver_context
+            .choices
+            .iter()
+            .filter_map(|(id, c)| c.first().map(|c| (id.clone(), c.clone())))
+            .collect();
+        choices.insert(self.var.clone(), self.choice.clone());
+        let namespace = self.var.namespace.as_deref();
+        choices.insert(
+            Identifier::with_namespace("value", namespace),
+            self.choice.clone(),
+        );
+        Some(substitute_choices_partial(command, namespace, &choices))
+    }
+
+    // with_last_used marks the choice as the one used last time in its description.
+    fn with_last_used(mut self, last_used: bool) -> Self {
+        if last_used {
+            self.desc = Some(match self.choice.desc() {
+                Some(desc) => format!("(last used) {}", desc),
+                None => String::from("(last used)"),
+            });
+        }
+        self
+    }
+
+    // with_default marks the choice as the default of the var in its description.
+    fn with_default(mut self, default: bool) -> Self {
+        if default {
+            self.desc = Some(match self.choice.desc() {
+                Some(desc) => format!("(default) {}", desc),
+                None => String::from("(default)"),
+            });
+        }
+        self
+    }
+
+    // the alias once the choices made so far and this choice are substituted.
+    fn command_with_choice(&self) -> String {
+        let mut choices: HashMap<Identifier, Choice> = self
+            .resolver_context
+            .choices
+            .iter()
+            .filter_map(|(id, c)| c.first().map(|c| (id.clone(), c.clone())))
+            .collect();
+        choices.insert(self.var.clone(), self.choice.clone());
+        self.resolver_context
+            .alias
+            .with_partial_choices(&choices)
+            .alias()
+            .to_string()
+    }
+
+    // masked hides the choices of the sensitive vars in a text, this choice included.
+    fn masked(&self, text: &str) -> String {
+        let ctx = self.resolver_context;
+        let mut sensitive = sensitive_choices(ctx);
+        if ctx.sensitive.contains(&self.var) {
+            sensitive.push(&self.choice);
         }
+        mask(text, sensitive)
     }
 }
 
 impl<'a> Eq for ChoiceElement<'a> {}
 impl<'a> PartialEq for ChoiceElement<'a> {
     fn eq(&self, other: &Self) -> bool {
-        self.choice == other.choice
+        self.choice == other.choice && self.load_more == other.load_more
     }
 }
 
 impl<'a> Value for ChoiceElement<'a> {
     fn text(&self) -> &str {
-        &self.text
+        self.choice.display()
+    }
+
+    fn desc(&self) -> Option<&str> {
+        self.desc.as_deref()
     }
 
     fn preview(&self) -> String {
+        if self.load_more {
+            return format!(
+                "Select this entry to read the next {} choices for {}.",
+                CHOICES_PAGE_SIZE, self.var
+            );
+        }
         let mut output = String::new();
 
+        if let (Some(command), Some((_, previews))) = (self.preview_command(), self.preview) {
+            let preview = previews
+                .output(&command, self.resolver_context)
+                .unwrap_or_else(|| String::from("…"));
+            output.push_str(&format!(
+                "$ {}\n\n{}\n\n",
+                self.masked(&command),
+                self.masked(preview.trim_end())
+            ));
+        }
+
+        if let Some(annotation) = self.resolver_context.alias.annotation() {
+            output.push_str(&format!("{}\n\n", annotation.to_uppercase()));
+        }
+
         output.push_str(&format!(
             "Name: {}\n\nDescription:\n{}\n\nAlias:\n\n{}\n",
             self.resolver_context.alias.name(),
@@ -249,6 +1337,17 @@ impl<'a> Value for ChoiceElement<'a> {
             self.resolver_context.alias.command(),
         ));
 
+        output.push_str(&format!(
+            "\nWith {} = {}:\n\n{}\n",
+            self.var,
+            self.masked(self.choice.value()),
+            self.masked(&self.command_with_choice()),
+        ));
+
+        if let Some(maintainer) = self.resolver_context.alias.maintainer() {
+            output.push_str(&format!("\nMaintained by:\n{}\n", maintainer));
+        }
+
         if !self.resolver_context.execution_sequence.is_empty() {
             output.push_str("\nDependencies:\n");
             for id in &self.resolver_context.execution_sequence {
@@ -258,12 +1357,20 @@ impl<'a> Value for ChoiceElement<'a> {
 
         if !self.resolver_context.choices.is_empty() {
             output.push_str("\nCurrent Choices:\n");
-            for (id, choice) in self.resolver_context.choices.iter() {
-                output.push_str(&format!("- {} = {:?}", id, choice));
+            for (id, choices) in self.resolver_context.choices.iter() {
+                let values: Vec<&str> = choices.iter().map(|c| c.value()).collect();
+                output.push_str(&format!("- {} = {}\n", id, self.masked(&values.join(", "))));
             }
         }
         output
     }
+
+    fn preview_pending(&self) -> bool {
+        match (self.preview_command(), self.preview) {
+            (Some(command), Some((_, previews))) => previews.is_running(&command),
+            _ => false,
+        }
+    }
 }
 
 impl std::hash::Hash for ChoiceElement<'_> {
@@ -271,3 +1378,307 @@ impl std::hash::Hash for ChoiceElement<'_> {
         self.choice.value().hash(state);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        default_first, is_cacheable, last_used_first, ChoiceElement, EditorFile, Previews,
+        UserInterfaceV2, CHOICES_PAGE_SIZE,
+    };
+    use crate::modal_view::Value;
+    use sam_core::algorithms::resolver::{ErrorsResolver, Resolver, ResolverContext};
+    use sam_core::entities::aliases::Alias;
+    use sam_core::entities::choices::Choice;
+    use sam_core::entities::commands::Command;
+    use sam_core::entities::identifiers::Identifier;
+    use sam_core::entities::vars::Var;
+    use sam_persistence::{CacheKey, NoopVarsCache};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_last_used_first() {
+        let choices = vec![
+            Choice::new("dev", None),
+            Choice::new("staging", None),
+            Choice::new("prod", Some("production")),
+        ];
+        let last_used = vec![Choice::new("prod", None)];
+        let ordered = last_used_first(choices.clone(), Some(&last_used));
+        let values: Vec<(&str, bool)> = ordered.iter().map(|(c, l)| (c.value(), *l)).collect();
+        assert_eq!(
+            values,
+            vec![("prod", true), ("dev", false), ("staging", false)]
+        );
+        let ordered = last_used_first(choices, None);
+        assert!(ordered.iter().all(|(_, l)| !l));
+    }
+
+    #[test]
+    fn test_default_first() {
+        let choices = vec![
+            Choice::new("dev", None),
+            Choice::new("staging", None),
+            Choice::new("prod", None),
+        ];
+        let default = Choice::from_value("staging");
+        let values = |choices: Vec<Choice>| -> Vec<String> {
+            choices.iter().map(|c| c.value().to_string()).collect()
+        };
+        assert_eq!(
+            values(default_first(choices.clone(), Some(&default))),
+            vec!["staging", "dev", "prod"]
+        );
+        let last_used = vec![Choice::new("prod", None)];
+        let ordered = last_used_first(
+            default_first(choices.clone(), Some(&default)),
+            Some(&last_used),
+        );
+        let values: Vec<&str> = ordered.iter().map(|(c, _)| c.value()).collect();
+        assert_eq!(values, vec!["prod", "staging", "dev"]);
+        let missing = Choice::from_value("qa");
+        assert_eq!(
+            default_first(choices.clone(), Some(&missing)),
+            default_first(choices, None)
+        );
+    }
+
+    #[test]
+    fn test_next_choices() {
+        let ui = UserInterfaceV2::new(HashMap::new(), Box::new(NoopVarsCache {}));
+        let var = Var::from_command("pod", "a pod", "kubectl get pods");
+        let key = CacheKey::new("kubectl get pods", &HashMap::new());
+        let output: String = (0..CHOICES_PAGE_SIZE * 2 + 1)
+            .map(|i| format!("pod-{}\n", i))
+            .collect();
+        ui.outputs
+            .borrow_mut()
+            .insert(key.clone(), output.into_bytes());
+
+        let mut more_choices = vec![(key.clone(), CHOICES_PAGE_SIZE)];
+        let page = ui.next_choices(&var, &mut more_choices).unwrap();
+        assert_eq!(page.len(), CHOICES_PAGE_SIZE);
+        assert_eq!(page[0].value(), format!("pod-{}", CHOICES_PAGE_SIZE));
+        assert_eq!(more_choices, vec![(key, CHOICES_PAGE_SIZE * 2)]);
+
+        let page = ui.next_choices(&var, &mut more_choices).unwrap();
+        assert_eq!(
+            page,
+            vec![Choice::from_value(format!("pod-{}", CHOICES_PAGE_SIZE * 2))]
+        );
+        assert!(more_choices.is_empty());
+    }
+
+    #[test]
+    fn test_run_dynamic_timeout_and_retries() {
+        let ui = UserInterfaceV2::new(HashMap::new(), Box::new(NoopVarsCache {}));
+        let alias = Alias::new("describe", "describes a pod", "kubectl describe {{ pod }}");
+        let ctx = ResolverContext {
+            full_name: alias.full_name().to_string(),
+            alias,
+            choices: HashMap::new(),
+            execution_sequence: vec![],
+            sensitive: vec![],
+        };
+        let run = |var: &Var| {
+            let (to_run, key) = ui
+                .dynamic_command(var, var.command().to_string(), &ctx)
+                .unwrap();
+            ui.run_dynamic(var, to_run, &key, &ctx)
+        };
+        // the command fails the first time it runs.
+        let marker = std::env::temp_dir().join(format!("sam-retry-{}", std::process::id()));
+        let cmd = format!(
+            "test -e {0} && echo api || {{ touch {0}; exit 1; }}",
+            marker.display()
+        );
+        let var = Var::from_command("pod", "a pod", cmd.as_str()).with_timeout(None, Some(1));
+        assert_eq!(run(&var).unwrap(), (b"api\n".to_vec(), true));
+        std::fs::remove_file(&marker).unwrap();
+
+        let var = Var::from_command("pod", "a pod", "sleep 5").with_timeout(Some(1), None);
+        assert!(matches!(
+            run(&var),
+            Err(ErrorsResolver::DynamicResolveTimeout(_, _, 1))
+        ));
+    }
+
+    #[test]
+    fn test_prefetch_dynamic() {
+        let ui = UserInterfaceV2::new(HashMap::new(), Box::new(NoopVarsCache {}));
+        let alias = Alias::new("describe", "describes a pod", "kubectl describe {{ pod }}");
+        let ctx = ResolverContext {
+            full_name: alias.full_name().to_string(),
+            alias,
+            choices: HashMap::new(),
+            execution_sequence: vec![],
+            sensitive: vec![],
+        };
+        let vars: Vec<Var> = ["pod", "node", "namespace"]
+            .iter()
+            .map(|name| Var::from_command(*name, "", format!("sleep 1; echo {}", name).as_str()))
+            .collect();
+        let commands: Vec<(&Var, String)> =
+            vars.iter().map(|v| (v, v.command().to_string())).collect();
+        let started = std::time::Instant::now();
+        ui.prefetch_dynamic(&commands, &ctx);
+        // prefetching the same commands again doesn't run them twice.
+        ui.prefetch_dynamic(&commands, &ctx);
+        assert_eq!(ui.prefetched.borrow().len(), 3);
+        for var in &vars {
+            let (to_run, key) = ui
+                .dynamic_command(var, var.command().to_string(), &ctx)
+                .unwrap();
+            let (output, _) = ui.run_dynamic(var, to_run, &key, &ctx).unwrap();
+            assert_eq!(output, format!("{}\n", var.name()).into_bytes());
+        }
+        // the commands ran at the same time.
+        assert!(started.elapsed() < std::time::Duration::from_secs(3));
+        assert!(ui.prefetched.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_map_choice() {
+        let ui = UserInterfaceV2::new(HashMap::new(), Box::new(NoopVarsCache {}));
+        let alias = Alias::new("describe", "describes a pod", "kubectl describe {{ pod }}");
+        let ctx = ResolverContext {
+            full_name: alias.full_name().to_string(),
+            alias,
+            choices: HashMap::new(),
+            execution_sequence: vec![],
+            sensitive: vec![],
+        };
+        let var = Var::new("pod", "a pod", vec![]);
+        let choice = Choice::new("api-7d9 Running 3d", Some("the api"));
+        let mapped = ui
+            .map_choice(&var, "cut -d' ' -f1", choice.clone(), &ctx)
+            .unwrap();
+        assert_eq!(mapped.value(), "api-7d9");
+        assert_eq!(mapped.display(), "api-7d9 Running 3d");
+        assert_eq!(mapped.desc(), Some("the api"));
+        assert_eq!(
+            ui.map_choice(&var, "echo broken >&2; exit 1", choice, &ctx)
+                .unwrap_err()
+                .to_string(),
+            "the map_command of var pod failed on choice api-7d9 Running 3d\n-> broken"
+        );
+    }
+
+    #[test]
+    fn test_preview_masks_sensitive_choices() {
+        let alias = Alias::new(
+            "login",
+            "logs in",
+            "login {{ user }} {{ password }} {{ otp }}",
+        );
+        let ctx = ResolverContext {
+            full_name: alias.full_name().to_string(),
+            alias,
+            choices: HashMap::from([
+                (Identifier::new("user"), vec![Choice::from_value("bob")]),
+                (
+                    Identifier::new("password"),
+                    vec![Choice::from_value("hunter2")],
+                ),
+            ]),
+            execution_sequence: vec![],
+            sensitive: vec![Identifier::new("password"), Identifier::new("otp")],
+        };
+        let preview =
+            ChoiceElement::from(Identifier::new("otp"), Choice::from_value("424242"), &ctx)
+                .preview();
+        assert!(preview.contains("With otp = ********"));
+        assert!(preview.contains("login bob ******** ********"));
+        assert!(preview.contains("- password = ********"));
+        assert!(!preview.contains("hunter2") && !preview.contains("424242"));
+    }
+
+    #[test]
+    fn test_preview_command() {
+        let alias = Alias::new("logs", "shows logs", "kubectl logs -n {{ ns }} {{ pod }}");
+        let ctx = ResolverContext {
+            full_name: alias.full_name().to_string(),
+            alias,
+            choices: HashMap::from([(Identifier::new("ns"), vec![Choice::from_value("kube")])]),
+            execution_sequence: vec![],
+            sensitive: vec![],
+        };
+        let previews = Previews::new(HashMap::new());
+        let var = Var::from_command("pod", "a pod", "kubectl get pods").with_preview_command(Some(
+            String::from("echo {{ value }} in {{ ns }} as {{ pod }}"),
+        ));
+        let element = ChoiceElement::from(Identifier::new("pod"), Choice::from_value("api"), &ctx)
+            .with_preview(&var, &previews);
+        let command = element.preview_command().unwrap();
+        assert_eq!(command, "echo api in kube as api");
+
+        // the command runs in the background and is run only once.
+        assert_eq!(previews.output(&command, &ctx), None);
+        let start = std::time::Instant::now();
+        while previews.is_running(&command) {
+            assert!(start.elapsed() < std::time::Duration::from_secs(5));
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(
+            previews.output(&command, &ctx),
+            Some(String::from("api in kube as api\n"))
+        );
+        assert!(!element.preview_pending());
+        assert!(element.preview().contains("api in kube as api"));
+    }
+
+    #[test]
+    fn test_is_cacheable() {
+        let alias = Alias::new("repos", "lists repos", "echo {{ repo }}");
+        let ctx = ResolverContext {
+            full_name: alias.full_name().to_string(),
+            alias,
+            choices: HashMap::from([(
+                Identifier::new("token"),
+                vec![Choice::from_value("s3cr3t")],
+            )]),
+            execution_sequence: vec![],
+            sensitive: vec![Identifier::new("token")],
+        };
+        let var = Var::from_command("repo", "a repo", "gh repo list --token {{ token }}");
+        let key = |command: &str, env: &[(&str, &str)]| {
+            let env = env
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            CacheKey::new(command, &env)
+        };
+        assert!(is_cacheable(&var, &key("gh repo list", &[]), &ctx));
+        assert!(!is_cacheable(
+            &var,
+            &key("gh repo list --token s3cr3t", &[]),
+            &ctx
+        ));
+        assert!(!is_cacheable(
+            &var,
+            &key("gh repo list", &[("GH_TOKEN", "s3cr3t")]),
+            &ctx
+        ));
+        let var = var.with_cache(Some(false), None);
+        assert!(!is_cacheable(&var, &key("gh repo list", &[]), &ctx));
+    }
+
+    #[test]
+    fn test_editor_file() {
+        let var = Var::from_input("message", "a commit message", "the message");
+        let file = EditorFile::create(&var).unwrap();
+        let other = EditorFile::create(&var).unwrap();
+        assert_ne!(file.path, other.path);
+        file.write("first draft").unwrap();
+        file.write("fix").unwrap();
+        assert_eq!(std::fs::read_to_string(&file.path).unwrap(), "fix");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&file.path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let path = file.path.clone();
+        drop(file);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,100 @@
+use std::env;
+
+/// interpolate replaces `${VAR}` by the value of the environment variable VAR and
+/// `${VAR:-default}` by its value, or by the default when it's unset or empty.
+/// `${VAR}` is left as is when VAR is unset, so that shell commands relying on
+/// variables set at runtime keep working, and `$${VAR}` is written as `${VAR}`.
+///```rust
+/// use sam_utils::interpolation::interpolate;
+/// std::env::set_var("SAM_INTERPOLATION_DOC", "/opt/recipes");
+/// assert_eq!(interpolate("${SAM_INTERPOLATION_DOC}/k8s"), "/opt/recipes/k8s");
+/// assert_eq!(interpolate("${SAM_INTERPOLATION_UNSET:-prod}"), "prod");
+/// assert_eq!(interpolate("$${SAM_INTERPOLATION_DOC}"), "${SAM_INTERPOLATION_DOC}");
+///```
+pub fn interpolate(text: &str) -> String {
+    interpolate_with(text, |name| env::var(name).ok())
+}
+
+fn interpolate_with(text: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            output.push_str(&rest[..start - 1]);
+            output.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let expression = rest[2..].find('}').map(|end| &rest[2..end + 2]);
+        match expression.and_then(|e| expand(e, &lookup)) {
+            Some(value) => {
+                output.push_str(&value);
+                rest = &rest[expression.map_or(0, str::len) + 3..];
+            }
+            None => {
+                output.push_str("${");
+                rest = &rest[2..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+// expand returns the value of `VAR` or `VAR:-default`, None when the expression
+// isn't one of them or when the variable is unset and there is no default.
+fn expand(expression: &str, lookup: &impl Fn(&str) -> Option<String>) -> Option<String> {
+    let (name, default) = match expression.split_once(":-") {
+        Some((name, default)) => (name, Some(default)),
+        None => (expression, None),
+    };
+    if !is_variable_name(name) {
+        return None;
+    }
+    match (lookup(name), default) {
+        (Some(value), Some(default)) if value.is_empty() => Some(default.to_string()),
+        (Some(value), _) => Some(value),
+        (None, default) => default.map(String::from),
+    }
+}
+
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::interpolate_with;
+
+    #[test]
+    fn test_interpolate() {
+        let lookup = |name: &str| match name {
+            "RECIPES" => Some(String::from("/opt/recipes")),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        };
+        let cases = [
+            (
+                "root_dir = [\"${RECIPES}/k8s\"]",
+                "root_dir = [\"/opt/recipes/k8s\"]",
+            ),
+            ("${UNSET:-prod} ${RECIPES:-/tmp}", "prod /opt/recipes"),
+            ("${EMPTY:-fallback}|${EMPTY}|", "fallback||"),
+            (
+                "echo ${UNSET} ${1} ${not a var}",
+                "echo ${UNSET} ${1} ${not a var}",
+            ),
+            ("$${RECIPES} ${RECIPES", "${RECIPES} ${RECIPES"),
+            ("${UNSET:-}x", "x"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(interpolate_with(text, lookup), expected, "{}", text);
+        }
+    }
+}
@@ -1 +1,2 @@
 pub mod fsutils;
+pub mod interpolation;